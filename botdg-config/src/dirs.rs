@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+use directories::ProjectDirs;
+
+// Where both bots keep what they make when its path isn't configured (the TDLib session, the
+// reaction history, the manager's state, logs) and where the config file is looked for after the
+// working directory: the platform's directories for "botdg", so a bot finds the same files
+// whichever directory it's started from.
+//
+//   data    ~/.local/share/botdg, ~/Library/Application Support/botdg, %APPDATA%\botdg\data
+//   logs    ~/.local/state/botdg on Linux, the data directory elsewhere
+//   config  ~/.config/botdg, ~/Library/Application Support/botdg, %APPDATA%\botdg\config
+//
+// BOTDG_DATA_DIR puts data and logs in one directory instead (BOTDG_DATA_DIR=. for the working
+// directory, as before), BOTDG_CONFIG_DIR the config file. Each path setting still overrides its own
+fn project() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "botdg")
+}
+
+fn from_env(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+pub fn data_dir() -> PathBuf {
+    from_env("BOTDG_DATA_DIR")
+        .or_else(|| project().map(|dirs| dirs.data_dir().to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+pub fn state_dir() -> PathBuf {
+    from_env("BOTDG_DATA_DIR")
+        .or_else(|| project().map(|dirs| dirs.state_dir().unwrap_or(dirs.data_local_dir()).to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+pub fn config_dir() -> PathBuf {
+    from_env("BOTDG_CONFIG_DIR")
+        .or_else(|| project().map(|dirs| dirs.config_dir().to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+// A default path in `dir`. One that's already in the working directory is kept there, as setups
+// from before the platform directories have their session and state next to them
+pub fn place(path: &str, dir: &Path) -> String {
+    if Path::new(path).is_absolute() || Path::new(path).exists() {
+        return path.to_string();
+    }
+    // What goes in it makes its own subdirectories and files, but not the directory itself
+    let _ = std::fs::create_dir_all(dir);
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+// A data path the bots make up at runtime, such as a new instance's session directory
+pub fn data_path(path: &str) -> String {
+    place(path, &data_dir())
+}
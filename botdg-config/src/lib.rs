@@ -0,0 +1,499 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+pub mod dirs;
+pub mod schedule;
+pub mod timezone;
+pub mod validate;
+
+// Looked for in the working directory, then the config directory (see dirs.rs), when neither
+// --config nor BOTDG_CONFIG names a file
+const DEFAULT_CONFIG_FILE: &str = "botdg.toml";
+// Tables of the config file, one per binary
+const SECTIONS: [&str; 2] = ["reaction-bot", "manager"];
+// Settings that hold a secret. Each can come from the file named by <NAME>_FILE instead, such as a
+// Docker or Kubernetes secret or a systemd credential (BOT_TOKEN_FILE=%d/bot_token), so the value
+// itself never has to be in the environment, the config file or a unit file
+pub const SECRETS: &[&str] = &[
+    "TELEGRAM_API_ID",
+    "TELEGRAM_API_HASH",
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_2FA_PASSWORD",
+    "BOT_API_TOKEN",
+    "BOT_TOKEN",
+    "SMTP_PASSWORD",
+    "DISCORD_WEBHOOK_URL",
+    "SLACK_WEBHOOK_URL",
+    "WEBHOOK_URL",
+    "WEBHOOK_SECRET",
+    "REDIS_URL",
+    "HISTORY_POSTGRES_URL",
+];
+
+// Configuration shared by both bots. Every setting is still an environment variable name
+// (MIN_AMOUNT, HISTORY_DIR, ...), read where it's used; this fills the environment from the layers
+// below it, so each setting resolves the same way in both binaries:
+//
+//   --set KEY=VALUE  >  --instance file  >  environment (and .env)  >  <NAME>_FILE  >  config file  >  defaults
+//
+// The config file is TOML. Top-level keys apply to both bots; a table named after a binary
+// ([reaction-bot], [manager]) applies to that one only and wins over the top level. Keys are
+// case-insensitive, arrays become comma separated lists:
+//
+//   allowed_chat_ids = [-1001234567890, -1009876543210]
+//   [reaction-bot]
+//   min_amount = 40000
+//
+// A profile (--profile test or BOTDG_PROFILE=test) runs a second setup next to the first without
+// editing files: .env.test is read before .env, botdg.test.toml instead of botdg.toml, and
+// per-profile defaults such as the TDLib data directory get the profile in their name.
+//
+// An instance file is a config file in the same format written for one run of a binary by another,
+// as the manager does for each reaction bot it starts with that bot's filters (see write). What it
+// sets wins over the environment the bot inherits, and the rest still comes from the usual layers
+#[derive(clap::Args, Default)]
+pub struct ConfigArgs {
+    /// Config file (BOTDG_CONFIG, or botdg.toml / botdg.<profile>.toml in the working directory or the config directory)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Profile, e.g. prod or test (BOTDG_PROFILE): its own .env, config file and data paths
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Settings written for this run by the manager, over the environment and the config file
+    #[arg(long, global = true, value_name = "PATH")]
+    pub instance: Option<PathBuf>,
+    /// Override a setting, e.g. --set MIN_AMOUNT=50000 (repeatable)
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+// Which layer a setting came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Cli,
+    Instance,
+    Env,
+    SecretFile,
+    File,
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cli => "--set",
+            Self::Instance => "instance file",
+            Self::Env => "environment",
+            Self::SecretFile => "secret file",
+            Self::File => "config file",
+            Self::Default => "default",
+        })
+    }
+}
+
+// A binary's default for a setting. Paths go in the data directory, or the logs' (see dirs.rs).
+// Per-profile ones are paths that two setups must not share; under a profile they get its name
+// ("tdlib_data" -> "tdlib_data_test", "reactions.db" -> "reactions_test.db")
+pub struct DefaultValue {
+    key: &'static str,
+    value: &'static str,
+    per_profile: bool,
+    dir: Option<Dir>,
+}
+
+#[derive(Clone, Copy)]
+enum Dir {
+    Data,
+    State,
+}
+
+impl DefaultValue {
+    pub const fn new(key: &'static str, value: &'static str) -> Self {
+        Self { key, value, per_profile: false, dir: None }
+    }
+
+    // A file or directory shared by every profile
+    pub const fn path(key: &'static str, value: &'static str) -> Self {
+        Self { key, value, per_profile: false, dir: Some(Dir::Data) }
+    }
+
+    pub const fn per_profile(key: &'static str, value: &'static str) -> Self {
+        Self { key, value, per_profile: true, dir: Some(Dir::Data) }
+    }
+
+    // Logs and the like, in the state directory instead
+    pub const fn in_state_dir(self) -> Self {
+        Self { dir: Some(Dir::State), ..self }
+    }
+
+    fn resolve(&self, profile: Option<&str>) -> String {
+        let value = match profile {
+            Some(profile) if self.per_profile => with_profile(self.value, profile),
+            _ => self.value.to_string(),
+        };
+        match self.dir {
+            Some(Dir::Data) => dirs::place(&value, &dirs::data_dir()),
+            Some(Dir::State) => dirs::place(&value, &dirs::state_dir()),
+            None => value,
+        }
+    }
+}
+
+// What `load` did: the profile, the file it read, and where each setting it knows about came from
+pub struct Loaded {
+    pub profile: Option<String>,
+    pub file: Option<PathBuf>,
+    origins: BTreeMap<String, Origin>,
+}
+
+impl Loaded {
+    // Settings named in the file, the defaults or --set, by name
+    pub fn origins(&self) -> impl Iterator<Item = (&str, &Origin)> {
+        self.origins.iter().map(|(key, origin)| (key.as_str(), origin))
+    }
+}
+
+// Resolve the layers into the environment. `section` is the binary's table in the config file;
+// `defaults` are its settings that have a default worth showing (others keep theirs in code).
+// Has to run before any thread reads or writes the environment
+pub fn load(section: &str, args: &ConfigArgs, defaults: &[DefaultValue]) -> Result<Loaded, String> {
+    // BOTDG_PROFILE may itself be in .env, but the profile's .env has to be read first to win
+    // over it: dotenv leaves variables that are already set alone
+    let profile = args
+        .profile
+        .clone()
+        .or_else(|| std::env::var("BOTDG_PROFILE").ok())
+        .or_else(|| {
+            std::fs::read_to_string(".env").ok()?.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("BOTDG_PROFILE=")?;
+                Some(value.trim().trim_matches(['"', '\'']).to_string())
+            })
+        })
+        .filter(|profile| !profile.is_empty());
+    if let Some(profile) = &profile {
+        if !profile.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid profile '{}': only letters, digits, '-' and '_'", profile));
+        }
+        dotenv::from_filename(format!(".env.{}", profile)).ok();
+        // Passed on to anything started from here, such as the manager's reaction bots
+        std::env::set_var("BOTDG_PROFILE", profile);
+    }
+    dotenv::dotenv().ok();
+
+    let mut cli = Vec::new();
+    for setting in &args.overrides {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("--set {} is not KEY=VALUE", setting))?;
+        cli.push((key.trim().to_uppercase(), value.to_string()));
+    }
+    let instance = match &args.instance {
+        Some(path) => read(path, section)?,
+        None => BTreeMap::new(),
+    };
+    let file = match args.config.clone().or_else(|| std::env::var_os("BOTDG_CONFIG").map(PathBuf::from)) {
+        Some(path) => Some(path),
+        None => Some(default_file(profile.as_deref())).filter(|path| path.exists()),
+    };
+    let from_file = match &file {
+        Some(path) => read(path, section)?,
+        None => BTreeMap::new(),
+    };
+    let layers = Layers {
+        cli,
+        instance,
+        file: from_file,
+        defaults: defaults.iter().map(|default| (default.key.to_string(), default.resolve(profile.as_deref()))).collect(),
+    };
+
+    let resolved = resolve(&layers, |key| std::env::var_os(key).map(|value| value.to_string_lossy().into_owned()))?;
+    let mut origins = BTreeMap::new();
+    for (key, (value, origin)) in resolved {
+        if origin != Origin::Env {
+            std::env::set_var(&key, value);
+        }
+        origins.insert(key, origin);
+    }
+
+    Ok(Loaded { profile, file, origins })
+}
+
+// What each layer but the environment says, as read by `load`
+struct Layers {
+    cli: Vec<(String, String)>,
+    instance: BTreeMap<String, String>,
+    file: BTreeMap<String, String>,
+    defaults: Vec<(String, String)>,
+}
+
+// Every setting the layers know about, with its value and where it came from, in the order the
+// ConfigArgs comment gives. `env` looks up the environment, which wins over the layers below it:
+// any value counts there, except that an empty secret still lets its <NAME>_FILE through.
+// <NAME>_FILE itself may come from any layer, the config file included; secrets are read only when
+// nothing above the secret file sets them. Only a trailing newline is stripped from them, as
+// editors and `echo` add one
+fn resolve(layers: &Layers, env: impl Fn(&str) -> Option<String>) -> Result<BTreeMap<String, (String, Origin)>, String> {
+    let mut resolved = BTreeMap::new();
+    for (key, value) in &layers.defaults {
+        resolved.insert(key.clone(), (value.clone(), Origin::Default));
+    }
+    for (key, value) in &layers.file {
+        resolved.insert(key.clone(), (value.clone(), Origin::File));
+    }
+
+    let from_cli = |key: &str| layers.cli.iter().rev().find(|(name, _)| name == key).map(|(_, value)| value.clone());
+    let above_secrets = |key: &str| {
+        from_cli(key)
+            .or_else(|| layers.instance.get(key).cloned())
+            .or_else(|| env(key).filter(|value| !value.is_empty()))
+    };
+    for name in SECRETS {
+        if above_secrets(name).is_some() {
+            continue;
+        }
+        let file_key = format!("{}_FILE", name);
+        let Some(path) = above_secrets(&file_key).or_else(|| layers.file.get(&file_key).cloned()).filter(|path| !path.is_empty()) else {
+            continue;
+        };
+        let value = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {} {}: {}", file_key, Path::new(&path).display(), e))?;
+        let value = value.strip_suffix('\n').map_or(value.as_str(), |value| value.strip_suffix('\r').unwrap_or(value));
+        resolved.insert(name.to_string(), (value.to_string(), Origin::SecretFile));
+    }
+
+    for (key, (value, origin)) in resolved.iter_mut() {
+        if let Some(from_env) = env(key).filter(|from_env| !(from_env.is_empty() && *origin == Origin::SecretFile)) {
+            *value = from_env;
+            *origin = Origin::Env;
+        }
+    }
+    for (key, value) in &layers.instance {
+        resolved.insert(key.clone(), (value.clone(), Origin::Instance));
+    }
+    for (key, value) in &layers.cli {
+        resolved.insert(key.clone(), (value.clone(), Origin::Cli));
+    }
+    Ok(resolved)
+}
+
+// The config file used without --config: botdg.toml (or botdg.<profile>.toml) in the working
+// directory if there is one, and in the config directory otherwise
+pub fn default_file(profile: Option<&str>) -> PathBuf {
+    let name = PathBuf::from(match profile {
+        Some(profile) => with_profile(DEFAULT_CONFIG_FILE, profile),
+        None => DEFAULT_CONFIG_FILE.to_string(),
+    });
+    if name.exists() {
+        name
+    } else {
+        dirs::config_dir().join(name)
+    }
+}
+
+// "botdg.toml" -> "botdg.test.toml" style for the config file, "name_test" / "name_test.ext" for data paths
+fn with_profile(value: &str, profile: &str) -> String {
+    if value == DEFAULT_CONFIG_FILE {
+        return format!("botdg.{}.toml", profile);
+    }
+    match value.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !extension.contains('/') => format!("{}_{}.{}", stem, profile, extension),
+        _ => format!("{}_{}", value.trim_end_matches('/'), profile),
+    }
+}
+
+// The file's settings for `section`: its own table over the top level
+fn read(path: &Path, section: &str) -> Result<BTreeMap<String, String>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let table: toml::Table = contents.parse().map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+
+    let mut settings = BTreeMap::new();
+    let mut own = None;
+    for (key, value) in &table {
+        match value {
+            toml::Value::Table(table) if key == section => own = Some(table),
+            // Another binary's table
+            toml::Value::Table(_) if SECTIONS.contains(&key.as_str()) => {}
+            toml::Value::Table(_) => return Err(format!("{}: unknown table [{}] ({})", path.display(), key, SECTIONS.join(", "))),
+            value => {
+                settings.insert(key.to_uppercase(), to_env(key, value, path)?);
+            }
+        }
+    }
+    for (key, value) in own.into_iter().flatten() {
+        settings.insert(key.to_uppercase(), to_env(key, value, path)?);
+    }
+    Ok(settings)
+}
+
+// Write an instance file: `settings` (environment variable names and values) in the `section`
+// table, under a comment saying who wrote it. Numbers and booleans are written as such, the rest
+// as strings, so the file reads like one written by hand; "0123" stays a string, as it reads back
+// as 123 otherwise
+pub fn write(path: &Path, comment: &str, section: &str, settings: &[(&str, String)]) -> Result<(), String> {
+    let mut table = toml::Table::new();
+    for (key, value) in settings {
+        let value = if let Some(number) = value.parse::<i64>().ok().filter(|number| number.to_string() == *value) {
+            toml::Value::Integer(number)
+        } else if let Ok(flag) = value.parse::<bool>() {
+            toml::Value::Boolean(flag)
+        } else {
+            toml::Value::String(value.clone())
+        };
+        table.insert(key.to_lowercase(), value);
+    }
+    let mut document = toml::Table::new();
+    document.insert(section.to_string(), toml::Value::Table(table));
+    let contents = toml::to_string(&document).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let comment: String = comment.lines().map(|line| format!("# {}\n", line)).collect();
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, format!("{}\n{}", comment, contents)).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn to_env(key: &str, value: &toml::Value, path: &Path) -> Result<String, String> {
+    Ok(match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Datetime(d) => d.to_string(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                toml::Value::Array(_) | toml::Value::Table(_) => {
+                    Err(format!("{}: '{}' can only list plain values", path.display(), key))
+                }
+                item => to_env(key, item, path),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(","),
+        toml::Value::Table(_) => return Err(format!("{}: '{}' is a table inside a table", path.display(), key)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers() -> Layers {
+        Layers { cli: Vec::new(), instance: BTreeMap::new(), file: BTreeMap::new(), defaults: Vec::new() }
+    }
+
+    fn set(map: &mut BTreeMap<String, String>, key: &str, value: &str) {
+        map.insert(key.to_string(), value.to_string());
+    }
+
+    // The value and origin `key` resolves to, with `env` standing in for the environment
+    fn resolved(layers: &Layers, env: &[(&str, &str)], key: &str) -> (String, Origin) {
+        let env = |name: &str| env.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string());
+        resolve(layers, env).unwrap().remove(key).unwrap()
+    }
+
+    // A secret file unique to the test, so tests running in parallel don't share one
+    fn secret_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("botdg-config-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn cli_wins_over_instance() {
+        let mut layers = layers();
+        set(&mut layers.instance, "MIN_LIKES", "2");
+        layers.cli.push(("MIN_LIKES".to_string(), "3".to_string()));
+        assert_eq!(resolved(&layers, &[], "MIN_LIKES"), ("3".to_string(), Origin::Cli));
+    }
+
+    #[test]
+    fn last_cli_override_wins() {
+        let mut layers = layers();
+        layers.cli.push(("MIN_LIKES".to_string(), "3".to_string()));
+        layers.cli.push(("MIN_LIKES".to_string(), "4".to_string()));
+        assert_eq!(resolved(&layers, &[], "MIN_LIKES"), ("4".to_string(), Origin::Cli));
+    }
+
+    #[test]
+    fn instance_wins_over_env() {
+        let mut layers = layers();
+        set(&mut layers.instance, "MIN_LIKES", "2");
+        assert_eq!(resolved(&layers, &[("MIN_LIKES", "5")], "MIN_LIKES"), ("2".to_string(), Origin::Instance));
+    }
+
+    #[test]
+    fn env_wins_over_secret_file() {
+        let mut layers = layers();
+        let path = secret_file("env-wins", "from file\n");
+        let env = [("TELEGRAM_BOT_TOKEN", "from env"), ("TELEGRAM_BOT_TOKEN_FILE", path.as_str())];
+        layers.defaults.push(("TELEGRAM_BOT_TOKEN".to_string(), String::new()));
+        assert_eq!(resolved(&layers, &env, "TELEGRAM_BOT_TOKEN"), ("from env".to_string(), Origin::Env));
+    }
+
+    #[test]
+    fn empty_env_secret_leaves_it_to_the_secret_file() {
+        let layers = layers();
+        let path = secret_file("empty-env", "from file\r\n");
+        let env = [("TELEGRAM_BOT_TOKEN", ""), ("TELEGRAM_BOT_TOKEN_FILE", path.as_str())];
+        assert_eq!(resolved(&layers, &env, "TELEGRAM_BOT_TOKEN"), ("from file".to_string(), Origin::SecretFile));
+    }
+
+    #[test]
+    fn secret_file_wins_over_config_file() {
+        let mut layers = layers();
+        let path = secret_file("over-config", "from file\n");
+        set(&mut layers.file, "TELEGRAM_BOT_TOKEN", "from config");
+        assert_eq!(resolved(&layers, &[("TELEGRAM_BOT_TOKEN_FILE", &path)], "TELEGRAM_BOT_TOKEN"), ("from file".to_string(), Origin::SecretFile));
+    }
+
+    #[test]
+    fn secret_file_named_in_config_file() {
+        let mut layers = layers();
+        let path = secret_file("named-in-config", "from file\n");
+        set(&mut layers.file, "TELEGRAM_BOT_TOKEN_FILE", &path);
+        assert_eq!(resolved(&layers, &[], "TELEGRAM_BOT_TOKEN"), ("from file".to_string(), Origin::SecretFile));
+    }
+
+    #[test]
+    fn secret_file_named_in_env_wins_over_config_file() {
+        let mut layers = layers();
+        let ignored = secret_file("ignored", "from config's file\n");
+        let path = secret_file("named-in-env", "from env's file\n");
+        set(&mut layers.file, "TELEGRAM_BOT_TOKEN_FILE", &ignored);
+        assert_eq!(resolved(&layers, &[("TELEGRAM_BOT_TOKEN_FILE", &path)], "TELEGRAM_BOT_TOKEN"), ("from env's file".to_string(), Origin::SecretFile));
+    }
+
+    #[test]
+    fn instance_secret_skips_the_secret_file() {
+        let mut layers = layers();
+        set(&mut layers.instance, "TELEGRAM_BOT_TOKEN", "from instance");
+        set(&mut layers.file, "TELEGRAM_BOT_TOKEN_FILE", "/nonexistent/bot_token");
+        assert_eq!(resolved(&layers, &[], "TELEGRAM_BOT_TOKEN"), ("from instance".to_string(), Origin::Instance));
+    }
+
+    #[test]
+    fn missing_secret_file_is_an_error() {
+        let mut layers = layers();
+        set(&mut layers.file, "TELEGRAM_BOT_TOKEN_FILE", "/nonexistent/bot_token");
+        assert!(resolve(&layers, |_| None).unwrap_err().contains("TELEGRAM_BOT_TOKEN_FILE"));
+    }
+
+    #[test]
+    fn env_wins_over_config_file() {
+        let mut layers = layers();
+        set(&mut layers.file, "MIN_LIKES", "2");
+        assert_eq!(resolved(&layers, &[("MIN_LIKES", "5")], "MIN_LIKES"), ("5".to_string(), Origin::Env));
+    }
+
+    #[test]
+    fn config_file_wins_over_defaults() {
+        let mut layers = layers();
+        set(&mut layers.file, "MIN_LIKES", "2");
+        layers.defaults.push(("MIN_LIKES".to_string(), "1".to_string()));
+        assert_eq!(resolved(&layers, &[], "MIN_LIKES"), ("2".to_string(), Origin::File));
+        layers.file.clear();
+        assert_eq!(resolved(&layers, &[], "MIN_LIKES"), ("1".to_string(), Origin::Default));
+    }
+}
@@ -0,0 +1,186 @@
+// Hours of the week: the reaction bot's WORKING_HOURS and the manager's QUIET_HOURS use the same
+// syntax. Comma separated windows, each an optional day or day range and a time range:
+//
+//   09:00-18:00                      every day
+//   mon-fri 09:00-18:00, sat 10:00-14:00
+//   пт 22:00-02:00                   past midnight: until 02:00 on Saturday
+//   00:00-00:00                      around the clock (equal times span a whole day)
+//
+// Days are English (mon..sun) or Russian (пн..вс) abbreviations; a range may wrap (fri-mon)
+
+const DAYS: [(&str, &str); 7] = [("mon", "пн"), ("tue", "вт"), ("wed", "ср"), ("thu", "чт"), ("fri", "пт"), ("sat", "сб"), ("sun", "вс")];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Window {
+    days: [bool; 7],   // Monday first
+    from: u32,         // Minutes since midnight
+    to: u32,           // Not after `from` means the window runs past midnight
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule(Vec<Window>);
+
+impl Schedule {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let windows = value
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(parse_window)
+            .collect::<Result<Vec<_>, _>>()?;
+        if windows.is_empty() {
+            return Err("no working hours given".to_string());
+        }
+        Ok(Self(windows))
+    }
+
+    // Whether `minute` (since midnight) of `weekday` (0 is Monday) is within the working hours
+    pub fn is_open(&self, weekday: usize, minute: u32) -> bool {
+        let yesterday = (weekday + 6) % 7;
+        self.0.iter().any(|window| {
+            if window.from < window.to {
+                window.days[weekday] && (window.from..window.to).contains(&minute)
+            } else {
+                (window.days[weekday] && minute >= window.from) || (window.days[yesterday] && minute < window.to)
+            }
+        })
+    }
+}
+
+fn parse_window(entry: &str) -> Result<Window, String> {
+    let entry = entry.trim();
+    let (days, times) = entry.rsplit_once(char::is_whitespace).unwrap_or(("", entry));
+    let days = match days.trim() {
+        "" => [true; 7],
+        days => parse_days(days).ok_or_else(|| format!("'{}' is not a day or day range like mon-fri", days))?,
+    };
+    let (from, to) = times
+        .split_once('-')
+        .and_then(|(from, to)| Some((parse_time(from)?, parse_time(to)?)))
+        .ok_or_else(|| format!("'{}' is not a time range like 09:00-18:00", times))?;
+    Ok(Window { days, from, to })
+}
+
+fn parse_days(days: &str) -> Option<[bool; 7]> {
+    let day = |name: &str| {
+        let name = name.trim().to_lowercase();
+        DAYS.iter().position(|(en, ru)| name.starts_with(en) || name.starts_with(ru))
+    };
+    let (first, last) = match days.split_once('-') {
+        Some((first, last)) => (day(first)?, day(last)?),
+        None => (day(days)?, day(days)?),
+    };
+    let mut selected = [false; 7];
+    let mut current = first;
+    loop {
+        selected[current] = true;
+        if current == last {
+            return Some(selected);
+        }
+        current = (current + 1) % 7;
+    }
+}
+
+// "9:00" or "09:00"; "24:00" is the end of the day
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+    (minutes < 60 && (hours < 24 || hours == 24 && minutes == 0)).then_some(hours * 60 + minutes)
+}
+
+// WORKING_HOURS_TZ or TIMEZONE as seconds east of UTC: "+03:00", "-5", "UTC+3". None for "local" (the
+// machine's time zone)
+pub fn parse_offset(value: &str) -> Result<Option<i32>, String> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("local") {
+        return Ok(None);
+    }
+    let invalid = || format!("'{}' is not a UTC offset like +03:00 or local", value);
+    let offset = value.strip_prefix("UTC").or_else(|| value.strip_prefix("utc")).unwrap_or(value);
+    if offset.is_empty() {
+        return Ok(Some(0));
+    }
+    let (sign, offset) = match offset.chars().next() {
+        Some('+') => (1, &offset[1..]),
+        Some('-') => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+    let (hours, minutes) = (hours.parse::<i32>().map_err(|_| invalid())?, minutes.parse::<i32>().map_err(|_| invalid())?);
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(Some(sign * (hours * 3600 + minutes * 60)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MON: usize = 0;
+    const FRI: usize = 4;
+    const SAT: usize = 5;
+    const SUN: usize = 6;
+
+    fn at(hours: u32, minutes: u32) -> u32 {
+        hours * 60 + minutes
+    }
+
+    #[test]
+    fn parses_days_and_times() {
+        let schedule = Schedule::parse("mon-fri 9:00-18:00, сб 10:00-14:00").unwrap();
+        assert!(schedule.is_open(MON, at(9, 0)));
+        assert!(schedule.is_open(FRI, at(17, 59)));
+        assert!(!schedule.is_open(FRI, at(18, 0)));
+        assert!(!schedule.is_open(MON, at(8, 59)));
+        assert!(schedule.is_open(SAT, at(10, 0)));
+        assert!(!schedule.is_open(SAT, at(9, 0)));
+        assert!(!schedule.is_open(SUN, at(12, 0)));
+    }
+
+    #[test]
+    fn day_ranges_wrap_around_the_week() {
+        let schedule = Schedule::parse("fri-mon 12:00-13:00").unwrap();
+        for day in [FRI, SAT, SUN, MON] {
+            assert!(schedule.is_open(day, at(12, 30)), "{}", day);
+        }
+        assert!(!schedule.is_open(1, at(12, 30)));
+    }
+
+    #[test]
+    fn windows_run_past_midnight_into_the_next_day() {
+        let schedule = Schedule::parse("fri 22:00-02:00").unwrap();
+        assert!(schedule.is_open(FRI, at(23, 0)));
+        assert!(schedule.is_open(SAT, at(1, 59)));
+        assert!(!schedule.is_open(SAT, at(2, 0)));
+        assert!(!schedule.is_open(FRI, at(1, 0)));
+        assert!(!schedule.is_open(SAT, at(23, 0)));
+    }
+
+    #[test]
+    fn equal_times_span_a_whole_day() {
+        let schedule = Schedule::parse("00:00-00:00").unwrap();
+        for day in 0..7 {
+            assert!(schedule.is_open(day, at(0, 0)) && schedule.is_open(day, at(23, 59)));
+        }
+        // From 09:00 on Monday until 09:00 on Tuesday
+        let schedule = Schedule::parse("mon 09:00-09:00").unwrap();
+        assert!(schedule.is_open(MON, at(9, 0)));
+        assert!(schedule.is_open(1, at(8, 59)));
+        assert!(!schedule.is_open(1, at(9, 0)));
+        assert!(!schedule.is_open(MON, at(8, 59)));
+    }
+
+    #[test]
+    fn end_of_day_is_24_00() {
+        let schedule = Schedule::parse("18:00-24:00").unwrap();
+        assert!(schedule.is_open(MON, at(23, 59)));
+        assert!(!schedule.is_open(1, at(0, 0)));
+    }
+
+    #[test]
+    fn rejects_bad_windows() {
+        for value in ["", " , ", "9-18", "09:00-25:00", "09:60-10:00", "someday 09:00-18:00", "mon-xyz 09:00-18:00"] {
+            assert!(Schedule::parse(value).is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::OnceLock;
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Utc};
+
+use crate::schedule;
+
+// TIMEZONE=+03:00: the time zone both bots go by for schedules (working hours, the balance refill,
+// the daily report), where "today" starts and the times shown in notifications. A VPS usually
+// runs on UTC, which puts 09:00 and midnight hours off from the user's. A UTC offset as in
+// WORKING_HOURS_TZ (see schedule::parse_offset); the machine's own time zone when unset or "local"
+pub fn offset() -> Option<FixedOffset> {
+    static OFFSET: OnceLock<Option<FixedOffset>> = OnceLock::new();
+    *OFFSET.get_or_init(|| {
+        let value = std::env::var("TIMEZONE").unwrap_or_default();
+        schedule::parse_offset(&value).ok().flatten().and_then(FixedOffset::east_opt)
+    })
+}
+
+// The current time in TIMEZONE
+pub fn now() -> DateTime<FixedOffset> {
+    at(Utc::now())
+}
+
+// A Unix timestamp in TIMEZONE
+pub fn from_timestamp(timestamp: i64) -> Option<DateTime<FixedOffset>> {
+    DateTime::from_timestamp(timestamp, 0).map(at)
+}
+
+// Unix timestamp of the last midnight in TIMEZONE, where "today" starts
+pub fn midnight() -> i64 {
+    let now = now();
+    now.date_naive()
+        .and_time(NaiveTime::MIN)
+        .and_local_timezone(*now.offset())
+        .earliest()
+        .map_or(0, |midnight| midnight.timestamp())
+}
+
+fn at(time: DateTime<Utc>) -> DateTime<FixedOffset> {
+    match offset() {
+        Some(offset) => time.with_timezone(&offset),
+        None => time.with_timezone(&Local).fixed_offset(),
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+// Everything wrong with a binary's settings, collected in one pass so a broken setup fails on
+// start with the whole list instead of the first expect() or a silently ignored value. Unset
+// optional settings are fine; checks only look at values that are there
+#[derive(Default)]
+pub struct Problems(Vec<String>);
+
+impl Problems {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, problem: impl Into<String>) {
+        self.0.push(problem.into());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    // Ok when there is nothing to report
+    pub fn check(self) -> Result<(), Self> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    // A setting that has to be there
+    pub fn required(&mut self, name: &str, value: Option<String>) -> Option<String> {
+        let value = value.filter(|value| !value.trim().is_empty());
+        if value.is_none() {
+            self.push(format!("{} is not set", name));
+        }
+        value
+    }
+
+    // A number, if set, within `range` (e.g. 1.. for counts that can't be 0)
+    pub fn number<T>(&mut self, name: &str, range: impl std::ops::RangeBounds<T>) -> Option<T>
+    where
+        T: FromStr + PartialOrd + fmt::Display,
+    {
+        self.number_value(name, var(name), range)
+    }
+
+    // The same for a value read some other way
+    pub fn number_value<T>(&mut self, name: &str, value: Option<String>, range: impl std::ops::RangeBounds<T>) -> Option<T>
+    where
+        T: FromStr + PartialOrd + fmt::Display,
+    {
+        let value = value?;
+        match value.trim().parse::<T>() {
+            Ok(number) if range.contains(&number) => Some(number),
+            Ok(number) => {
+                self.push(format!("{} is {}, out of range ({})", name, number, describe(&range)));
+                None
+            }
+            Err(_) => {
+                self.push(format!("{} '{}' is not a number", name, value));
+                None
+            }
+        }
+    }
+
+    // One of a fixed set of words, case-insensitive, if set
+    pub fn one_of(&mut self, name: &str, allowed: &[&str]) {
+        if let Some(value) = var(name) {
+            if !allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(value.trim())) {
+                self.push(format!("{} '{}' is not one of {}", name, value, allowed.join(", ")));
+            }
+        }
+    }
+
+    // A comma separated list of Telegram IDs. Entries that don't parse are reported rather than
+    // dropped, so a typo doesn't quietly stop one chat (or all of them) from being watched
+    pub fn ids(&mut self, name: &str) -> Vec<i64> {
+        let mut ids = Vec::new();
+        let mut invalid = Vec::new();
+        for entry in var(name).unwrap_or_default().split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.parse() {
+                Ok(id) => ids.push(id),
+                Err(_) => invalid.push(entry.to_string()),
+            }
+        }
+        if !invalid.is_empty() {
+            self.push(format!("{} has entries that aren't IDs: {}", name, invalid.join(", ")));
+        }
+        ids
+    }
+
+    // A directory the binary creates and writes to, if set (or `default`)
+    pub fn writable_dir(&mut self, name: &str, default: Option<&str>) {
+        if let Some(dir) = var(name).or(default.map(str::to_string)) {
+            if let Err(e) = probe(Path::new(&dir)) {
+                self.push(format!("{} {} is not writable: {}", name, dir, e));
+            }
+        }
+    }
+
+    // A file the binary creates or appends to, if set (or `default`): its directory has to be writable
+    pub fn writable_file(&mut self, name: &str, default: Option<&str>) {
+        if let Some(file) = var(name).or(default.map(str::to_string)) {
+            let path = Path::new(&file);
+            if path.is_dir() {
+                self.push(format!("{} {} is a directory", name, file));
+                return;
+            }
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            match std::fs::metadata(path) {
+                Ok(metadata) if metadata.permissions().readonly() => self.push(format!("{} {} is read-only", name, file)),
+                _ => {
+                    if let Err(e) = probe(dir) {
+                        self.push(format!("{} {}: directory {} is not writable: {}", name, file, dir.display(), e));
+                    }
+                }
+            }
+        }
+    }
+
+    // A file or directory that has to exist, if set
+    pub fn existing_path(&mut self, name: &str) {
+        if let Some(path) = var(name) {
+            if !Path::new(&path).exists() {
+                self.push(format!("{} {} does not exist", name, path));
+            }
+        }
+    }
+}
+
+impl fmt::Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} configuration problem(s):", self.0.len())?;
+        for problem in &self.0 {
+            write!(f, "\n  ✗ {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+// Debug is what `main` returning an error prints; keep it the readable list
+impl fmt::Debug for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Problems {}
+
+// A setting's value, unset when empty
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn describe<T: fmt::Display>(range: &impl std::ops::RangeBounds<T>) -> String {
+    use std::ops::Bound::*;
+    match (range.start_bound(), range.end_bound()) {
+        (Included(min), Unbounded) => format!("at least {}", min),
+        (Included(min), Included(max)) => format!("{} to {}", min, max),
+        (Included(min), Excluded(max)) => format!("{} to under {}", min, max),
+        (Unbounded, Included(max)) => format!("at most {}", max),
+        _ => "invalid".to_string(),
+    }
+}
+
+// Create the directory if needed and write a file into it: permission bits alone don't tell
+// (ACLs, read-only mounts, another owner)
+fn probe(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe: PathBuf = dir.join(format!(".botdg-write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
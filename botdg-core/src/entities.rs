@@ -0,0 +1,82 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{normalize, order::Amount, patterns};
+
+// Formatting of a message's text as Telegram sends it (TDLib's textEntity, the Bot API's
+// MessageEntity). Offsets and lengths count UTF-16 code units, as Telegram does
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Entity {
+    pub offset: usize,
+    pub length: usize,
+    pub kind: EntityKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntityKind {
+    Bold,
+    Code,   // Inline code, which order bots use for what's meant to be copied
+    Pre,
+    Other,
+}
+
+impl Entity {
+    pub fn new(offset: usize, length: usize, kind: EntityKind) -> Self {
+        Self { offset, length, kind }
+    }
+
+    // The formatted part of `text`, None when the entity doesn't fit it
+    pub fn slice<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let (mut start, mut end) = (None, None);
+        let mut units = 0;
+        for (at, c) in text.char_indices().chain([(text.len(), '\0')]) {
+            if units == self.offset {
+                start = Some(at);
+            }
+            if units == self.offset + self.length {
+                end = Some(at);
+                break;
+            }
+            units += c.len_utf16();
+        }
+        text.get(start?..end?)
+    }
+}
+
+// A run that is nothing but an amount: "50 000 ₽", "500 USDT", "1 250,50", "75000"
+static BARE_AMOUNT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?P<value>\d[\d ,.]*\d|\d)\s*(?:(?P<currency>₽|руб|rub|р\.|\$|€|¥|usdt|usd|eur|cny)\.?)?\s*$").unwrap()
+});
+
+// The first bold run that is an amount; order bots often bold it and nothing else. In rubles when
+// the run names no currency, unless it's as long as a phone number or card
+pub fn bold_amount(text: &str, entities: &[Entity]) -> Option<Amount> {
+    entities.iter().filter(|entity| entity.kind == EntityKind::Bold).find_map(|entity| {
+        let run = normalize::message(entity.slice(text)?);
+        let captures = BARE_AMOUNT.captures(&run)?;
+        let value = captures.name("value")?.as_str();
+        let currency = match captures.name("currency") {
+            Some(currency) => patterns::currency_code(currency.as_str()),
+            None if value.chars().filter(char::is_ascii_digit).count() < 10 => "RUB".to_string(),
+            None => return None,
+        };
+        Some(Amount { value: patterns::parse_number(value)?, currency })
+    })
+}
+
+// The first code or pre entity that looks like a requisite: a phone number, card or account of at
+// least 10 digits, and only digits and separators. Exactly what is meant to be copied, without the
+// card holder's name or a note after it on the same line
+pub fn code_requisite(text: &str, entities: &[Entity]) -> Option<String> {
+    entities
+        .iter()
+        .filter(|entity| matches!(entity.kind, EntityKind::Code | EntityKind::Pre))
+        .filter_map(|entity| entity.slice(text))
+        .map(normalize::message)
+        .find(|run| {
+            run.chars().filter(char::is_ascii_digit).count() >= 10
+                && run.chars().all(|c| c.is_ascii_digit() || " +-()".contains(c))
+        })
+}
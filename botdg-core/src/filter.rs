@@ -0,0 +1,342 @@
+use crate::{normalize, order::Order, profit::Commissions};
+
+// Why a message was not reacted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    NoPrice,            // No "а: ... ₽" amount in the message
+    BelowMinAmount,     // Amount under MIN_AMOUNT (or the bank's own minimum)
+    BankMismatch,       // Bank missing or not matching BANK_FILTER
+    RequisiteMismatch,  // Requisite missing or not matching REQUISITE_FILTER
+    ChatNotAllowed,     // Chat isn't in ALLOWED_CHAT_IDS
+    Duplicate,          // Already reacted to this message
+    TopicNotAllowed,    // Forum topic isn't one of the chat's ALLOWED_TOPICS
+    TooOld,             // Posted more than MAX_MESSAGE_AGE_SECS before it arrived
+    BelowMinProfit,     // Expected profit under MIN_PROFIT (see profit.rs)
+    BelowMinScore,      // Score under MIN_SCORE (see score.rs)
+    OverBudget,         // REACTION_BUDGET for the hour already spent
+    OffHours,           // Outside WORKING_HOURS
+    OverBalance,        // Amount over what's left of WORKING_BALANCE
+    Blacklisted,        // Requisite is on REQUISITE_BLACKLIST
+    Plugin,             // Turned down by a plugin (PLUGINS or WASM_FILTERS in the reaction bot)
+    Hook,               // Turned down by PRE_REACTION_HOOK (in the reaction bot)
+    Deadline,           // Not decided within PROCESSING_DEADLINE_MS (in the reaction bot)
+    ContentType,        // A sticker, voice note, service message... in IGNORE_CONTENT_TYPES (in the reaction bot)
+    OriginNotAllowed,   // Not forwarded from one of the chat's FORWARD_ORIGINS (in the reaction bot)
+    SenderNotAllowed,   // Not sent by one of the chat's ORDER_SENDERS (in the reaction bot)
+    Suspicious,         // Tripped one of the SCAM_CHECKS (in the reaction bot)
+    Language,           // Not written in one of ORDER_LANGUAGES (in the reaction bot)
+}
+
+pub const REASONS: [(SkipReason, &str); 22] = [
+    (SkipReason::NoPrice, "no_price"),
+    (SkipReason::BelowMinAmount, "below_min_amount"),
+    (SkipReason::BankMismatch, "bank_mismatch"),
+    (SkipReason::RequisiteMismatch, "requisite_mismatch"),
+    (SkipReason::ChatNotAllowed, "chat_not_allowed"),
+    (SkipReason::Duplicate, "duplicate"),
+    (SkipReason::TopicNotAllowed, "topic_not_allowed"),
+    (SkipReason::TooOld, "too_old"),
+    (SkipReason::BelowMinProfit, "below_min_profit"),
+    (SkipReason::BelowMinScore, "below_min_score"),
+    (SkipReason::OverBudget, "over_budget"),
+    (SkipReason::OffHours, "off_hours"),
+    (SkipReason::OverBalance, "over_balance"),
+    (SkipReason::Blacklisted, "blacklisted"),
+    (SkipReason::Plugin, "plugin"),
+    (SkipReason::Hook, "hook"),
+    (SkipReason::Deadline, "deadline"),
+    (SkipReason::ContentType, "content_type"),
+    (SkipReason::OriginNotAllowed, "origin_not_allowed"),
+    (SkipReason::SenderNotAllowed, "sender_not_allowed"),
+    (SkipReason::Suspicious, "suspicious"),
+    (SkipReason::Language, "language"),
+];
+
+impl SkipReason {
+    pub fn name(self) -> &'static str {
+        REASONS[self as usize].1
+    }
+}
+
+// Ok to react, otherwise the first filter the order failed
+pub type Decision = Result<(), SkipReason>;
+
+// One rule of `Filter::explain`: what the order had, what the filter wanted and whether it passed
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Check {
+    pub rule: &'static str,     // "blacklist", "amount", "profit", "bank" or "requisite"
+    pub input: Option<String>,  // From the order; None when it doesn't say
+    pub wanted: String,
+    pub passed: bool,
+}
+
+// What an order has to be to react to it. Built with `new` and the `with_` methods; the fields
+// are there to read
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Filter {
+    pub bank: Option<String>,               // Bank name part (e.g. "Т" for T-banks)
+    pub requisite: Option<String>,          // Requisite part (e.g. "+" for SBP)
+    pub min_amount: i32,                    // In rubles; 0 turns the amount check off
+    pub bank_min_amounts: Vec<(String, i32)>, // Per-bank minimums (normalize::bank_key, amount)
+    pub min_profit: f64,                    // In rubles, by `commissions`; 0 turns the check off
+    pub commissions: Commissions,
+    pub blacklist: Vec<String>,             // Requisites never reacted to (normalize::requisite_key)
+}
+
+impl Filter {
+    // Orders of at least `min_amount` rubles from any bank
+    pub fn new(min_amount: i32) -> Self {
+        Self { min_amount, ..Self::default() }
+    }
+
+    // Only banks whose name contains `bank`; "Т" (Latin or Cyrillic) stands for every T-bank spelling
+    pub fn with_bank(mut self, bank: impl Into<String>) -> Self {
+        self.bank = Some(bank.into());
+        self
+    }
+
+    // Only requisites containing `requisite`; "+" (SBP) also lets T-bank orders without one through
+    pub fn with_requisite(mut self, requisite: impl Into<String>) -> Self {
+        self.requisite = Some(requisite.into());
+        self
+    }
+
+    // Never react to orders paying to `requisite` (a card number or phone, in any spelling)
+    pub fn with_blacklisted(mut self, requisite: &str) -> Self {
+        self.blacklist.push(normalize::requisite_key(requisite));
+        self
+    }
+
+    // A different minimum for banks matching `bank`, e.g. ("tbank", 50000)
+    pub fn with_bank_min_amount(mut self, bank: &str, min_amount: i32) -> Self {
+        self.bank_min_amounts.push((normalize::bank_key(bank), min_amount));
+        self
+    }
+
+    // Only orders expected to earn at least `min_profit` rubles at `commissions`
+    pub fn with_min_profit(mut self, min_profit: f64, commissions: Commissions) -> Self {
+        self.min_profit = min_profit;
+        self.commissions = commissions;
+        self
+    }
+
+    // Minimum amount for the order's bank, falling back to the global minimum
+    pub fn min_amount_for(&self, order: &Order) -> i32 {
+        let Some(bank) = &order.bank else {
+            return self.min_amount;
+        };
+        let bank = normalize::bank_key(bank);
+        self.bank_min_amounts
+            .iter()
+            .find(|(key, _)| bank.contains(key.as_str()))
+            .map(|(_, amount)| *amount)
+            .unwrap_or(self.min_amount)
+    }
+
+    // Every rule the filter applies to the order, in the order `evaluate` checks them, for telling
+    // later why an order was or wasn't reacted to. Rules that are off aren't listed
+    pub fn explain(&self, order: &Order) -> Vec<Check> {
+        let mut checks = Vec::new();
+        if !self.blacklist.is_empty() {
+            checks.push(Check {
+                rule: "blacklist",
+                input: order.requisite.clone(),
+                wanted: "not blacklisted".to_string(),
+                passed: !self.blacklisted(order),
+            });
+        }
+        let min_amount = self.min_amount_for(order);
+        if self.min_amount > 0 && min_amount > 0 {
+            checks.push(Check {
+                rule: "amount",
+                input: order.rubles.map(|rubles| rubles.to_string()),
+                wanted: format!(">= {}", min_amount),
+                passed: order.rubles.is_some_and(|rubles| rubles >= min_amount),
+            });
+        }
+        if self.min_profit > 0.0 {
+            let profit = self.commissions.expected_profit(order);
+            checks.push(Check {
+                rule: "profit",
+                input: profit.map(|profit| format!("{:.2} at {}%", profit, self.commissions.percent_for(order))),
+                wanted: format!(">= {}", self.min_profit),
+                passed: profit.is_some_and(|profit| profit >= self.min_profit),
+            });
+        }
+        if let Some(filter) = &self.bank {
+            checks.push(Check {
+                rule: "bank",
+                input: order.bank.clone(),
+                wanted: filter.clone(),
+                passed: order.bank.as_deref().is_some_and(|bank| bank_matches(filter, bank)),
+            });
+        }
+        if let Some(filter) = &self.requisite {
+            checks.push(Check {
+                rule: "requisite",
+                input: order.requisite.clone(),
+                wanted: filter.clone(),
+                passed: self.requisite_passed(order),
+            });
+        }
+        checks
+    }
+
+    pub fn evaluate(&self, order: &Order) -> Decision {
+        if self.blacklisted(order) {
+            return Err(SkipReason::Blacklisted);
+        }
+        // Nothing to go by
+        if order.rubles.is_none() && self.bank.is_none() && self.requisite.is_none() {
+            return Err(SkipReason::NoPrice);
+        }
+        if self.bank.is_some() && order.bank.is_none() {
+            return Err(SkipReason::BankMismatch);
+        }
+
+        // A per-bank minimum only applies while the global one is on
+        let min_amount = self.min_amount_for(order);
+        let amount_passed = self.min_amount <= 0 || min_amount <= 0 || order.rubles.is_some_and(|rubles| rubles >= min_amount);
+        let profit_passed = self.min_profit <= 0.0 || self.commissions.expected_profit(order).is_some_and(|profit| profit >= self.min_profit);
+        let bank_passed = match (&self.bank, &order.bank) {
+            (Some(filter), Some(bank)) => bank_matches(filter, bank),
+            _ => true,
+        };
+        let requisite_passed = self.requisite_passed(order);
+
+        if !amount_passed {
+            Err(if order.rubles.is_some() { SkipReason::BelowMinAmount } else { SkipReason::NoPrice })
+        } else if !profit_passed {
+            Err(if order.rubles.is_some() { SkipReason::BelowMinProfit } else { SkipReason::NoPrice })
+        } else if !bank_passed {
+            Err(SkipReason::BankMismatch)
+        } else if !requisite_passed {
+            Err(SkipReason::RequisiteMismatch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn blacklisted(&self, order: &Order) -> bool {
+        order.requisite.as_deref().is_some_and(|requisite| self.blacklist.contains(&normalize::requisite_key(requisite)))
+    }
+
+    fn requisite_passed(&self, order: &Order) -> bool {
+        match &self.requisite {
+            // SBP ("+") orders from T-banks go through without a requisite line
+            Some(filter) if filter == "+" && order.bank.as_deref().is_some_and(normalize::is_t_bank) => true,
+            Some(filter) => order.requisite.as_deref().is_some_and(|requisite| match filter.as_str() {
+                "+" => requisite.contains('+'),
+                filter => requisite.contains(filter),
+            }),
+            None => true,
+        }
+    }
+}
+
+// "Т" (Latin or Cyrillic) stands for every T-bank spelling; other filters are a part of the
+// name, compared without case, hyphens and spaces
+fn bank_matches(filter: &str, bank: &str) -> bool {
+    let filter = filter.to_lowercase();
+    if filter == "t" || filter == "т" {
+        return normalize::is_t_bank(bank);
+    }
+    normalize::bank_name(bank).contains(&filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::parse_order;
+
+    fn order(text: &str) -> Order {
+        parse_order(text).unwrap()
+    }
+
+    #[test]
+    fn min_amount_is_inclusive() {
+        let filter = Filter::new(40_000);
+        assert_eq!(filter.evaluate(&order("Сумма: 40 000 ₽")), Ok(()));
+        assert_eq!(filter.evaluate(&order("Сумма: 39 999 ₽")), Err(SkipReason::BelowMinAmount));
+        assert_eq!(Filter::new(0).evaluate(&order("Сумма: 1 ₽")), Ok(()));
+    }
+
+    #[test]
+    fn bank_min_amount_overrides_the_global_one() {
+        let filter = Filter::new(40_000).with_bank_min_amount("Т-Банк", 60_000);
+        assert_eq!(filter.evaluate(&order("Сумма: 59 999 ₽\nБанк: T Bank")), Err(SkipReason::BelowMinAmount));
+        assert_eq!(filter.evaluate(&order("Сумма: 60 000 ₽\nБанк: тбанк")), Ok(()));
+        assert_eq!(filter.evaluate(&order("Сумма: 40 000 ₽\nБанк: Сбер")), Ok(()));
+        // Only while the global minimum is on
+        let filter = Filter::new(0).with_bank_min_amount("Т-Банк", 60_000);
+        assert_eq!(filter.evaluate(&order("Сумма: 1 000 ₽\nБанк: Т-Банк")), Ok(()));
+    }
+
+    #[test]
+    fn min_profit_is_inclusive() {
+        let filter = Filter::new(0).with_min_profit(500.0, Commissions::new(1.0).with_bank("Сбер", 2.0));
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽")), Ok(()));
+        assert_eq!(filter.evaluate(&order("Сумма: 49 999 ₽")), Err(SkipReason::BelowMinProfit));
+        assert_eq!(filter.evaluate(&order("Сумма: 25 000 ₽\nБанк: Сбер")), Ok(()));
+        // The order's own commission comes first
+        assert_eq!(filter.evaluate(&order("Сумма: 25 000 ₽\nБанк: Сбер\nКомиссия: 1,9%")), Err(SkipReason::BelowMinProfit));
+    }
+
+    #[test]
+    fn skips_orders_with_nothing_to_go_by() {
+        assert_eq!(Filter::new(0).evaluate(&order("Банк: Сбер")), Err(SkipReason::NoPrice));
+        assert_eq!(Filter::new(40_000).evaluate(&order("Сумма: 500 USDT")), Err(SkipReason::NoPrice));
+        assert_eq!(Filter::new(0).with_min_profit(1.0, Commissions::new(1.0)).evaluate(&order("Сумма: 500 USDT\nБанк: Сбер")), Err(SkipReason::NoPrice));
+    }
+
+    #[test]
+    fn skips_blacklisted_requisites_in_any_spelling() {
+        let filter = Filter::new(0).with_blacklisted("8 (900) 123-45-67");
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nРеквизит: +7 900 1234567")), Err(SkipReason::Blacklisted));
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nРеквизит: +7 900 7654321")), Ok(()));
+    }
+
+    #[test]
+    fn matches_t_banks_in_any_spelling() {
+        let filter = Filter::new(0).with_bank("Т");
+        for bank in ["Т-Банк", "T Bank", "тбанк", "Tinkoff"] {
+            assert_eq!(filter.evaluate(&order(&format!("Сумма: 50 000 ₽\nБанк: {}", bank))), Ok(()), "{}", bank);
+        }
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nБанк: Сбер")), Err(SkipReason::BankMismatch));
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽")), Err(SkipReason::BankMismatch));
+        assert_eq!(Filter::new(0).with_bank("сбер").evaluate(&order("Сумма: 50 000 ₽\nБанк: Сбер Банк")), Ok(()));
+    }
+
+    #[test]
+    fn sbp_lets_t_banks_through_without_a_requisite() {
+        // The usual setup: T-banks over SBP
+        let filter = Filter::new(40_000).with_bank("т").with_requisite("+");
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nБанк: Т-Банк")), Ok(()));
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nБанк: Т-Банк\nРеквизит: +7 900 000-00-00")), Ok(()));
+
+        let filter = Filter::new(0).with_requisite("+");
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nБанк: Сбер")), Err(SkipReason::RequisiteMismatch));
+        assert_eq!(filter.evaluate(&order("Сумма: 50 000 ₽\nБанк: Сбер\nРеквизит: 2200 1234 5678 9012")), Err(SkipReason::RequisiteMismatch));
+    }
+
+    #[test]
+    fn explains_the_rules_it_applies() {
+        let filter = Filter::new(40_000).with_bank("т");
+        let checks = filter.explain(&order("Сумма: 39 999 ₽\nБанк: Т-Банк"));
+        let rules: Vec<_> = checks.iter().map(|check| (check.rule, check.passed)).collect();
+        assert_eq!(rules, [("amount", false), ("bank", true)]);
+        assert_eq!(checks[0].wanted, ">= 40000");
+        assert!(Filter::new(0).explain(&order("Сумма: 1 ₽")).is_empty());
+    }
+
+    #[test]
+    fn reason_names_match_the_enum() {
+        for (at, (reason, name)) in REASONS.iter().enumerate() {
+            assert_eq!(*reason as usize, at);
+            assert_eq!(reason.name(), *name);
+        }
+    }
+}
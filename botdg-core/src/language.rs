@@ -0,0 +1,92 @@
+use crate::normalize;
+
+// A language the built-in price patterns are written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Language {
+    Russian,
+    English,
+}
+
+const NAMES: [(Language, &str, &str); 2] = [(Language::Russian, "ru", "russian"), (Language::English, "en", "english")];
+
+// A set of languages: those a message is written in, or those orders are expected in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Languages(u8);
+
+impl Languages {
+    pub const ALL: Self = Self(0b11);
+
+    pub fn of(languages: &[Language]) -> Self {
+        Self(languages.iter().fold(0, |bits, &language| bits | Self::bit(language)))
+    }
+
+    // Comma separated "ru" or "russian", "en" or "english"
+    pub fn parse(names: &str) -> Result<Self, String> {
+        let mut languages = Self::default();
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let (language, ..) = NAMES
+                .iter()
+                .find(|(_, code, long)| name.eq_ignore_ascii_case(code) || name.eq_ignore_ascii_case(long))
+                .ok_or_else(|| format!("unknown language '{}' (ru or en)", name))?;
+            languages.0 |= Self::bit(*language);
+        }
+        Ok(languages)
+    }
+
+    pub fn contains(self, language: Language) -> bool {
+        self.0 & Self::bit(language) != 0
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    // Codes of the languages in the set, "ru" first
+    pub fn names(self) -> Vec<&'static str> {
+        NAMES.iter().filter(|(language, ..)| self.contains(*language)).map(|(_, code, _)| *code).collect()
+    }
+
+    fn bit(language: Language) -> u8 {
+        1 << language as u8
+    }
+}
+
+impl std::ops::BitAnd for Languages {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+// The languages a message is written in, by its letters: Russian for Cyrillic and English for Latin,
+// each when it has at least a quarter of them, so "Сумма: 500 USDT" is both. Nothing for a message
+// without letters or with more emoji than letters, which is chatter whatever numbers it has
+pub fn detect(text: &str) -> Languages {
+    let (mut cyrillic, mut latin, mut emoji) = (0, 0, 0);
+    for c in text.chars() {
+        match c {
+            'а'..='я' | 'А'..='Я' | 'ё' | 'Ё' => cyrillic += 1,
+            c if c.is_ascii_alphabetic() => latin += 1,
+            c if normalize::is_emoji(c) => emoji += 1,
+            _ => {}
+        }
+    }
+    let letters = cyrillic + latin;
+    let mut languages = Languages::default();
+    if letters == 0 || emoji > letters {
+        return languages;
+    }
+    if cyrillic * 4 >= letters {
+        languages.0 |= Languages::bit(Language::Russian);
+    }
+    if latin * 4 >= letters {
+        languages.0 |= Languages::bit(Language::English);
+    }
+    languages
+}
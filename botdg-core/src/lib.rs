@@ -0,0 +1,35 @@
+//! Order messages from P2P exchange chats on Telegram, read and filtered: amount (rubles or
+//! another currency), bank, requisite and commission, then a decision against bank, requisite,
+//! amount and expected profit filters, and a score to rank orders by. Pure functions only: no I/O,
+//! logging or environment.
+//!
+//! ```
+//! use botdg_core::{parse_order, Filter, SkipReason};
+//!
+//! let order = parse_order("Сумма: 50 000 ₽\nБанк: Т-Банк\nРеквизит: +7 900 000-00-00").unwrap();
+//! assert_eq!(order.rubles, Some(50_000));
+//!
+//! let filter = Filter::new(40_000).with_bank("т").with_bank_min_amount("Сбер", 60_000);
+//! assert_eq!(filter.evaluate(&order), Ok(()));
+//! assert_eq!(Filter::new(60_000).evaluate(&order), Err(SkipReason::BelowMinAmount));
+//! ```
+//!
+//! Amounts in other currencies have no ruble value until [`Order::convert`] is given the rates.
+//! The API follows semver: structs and enums are `#[non_exhaustive]`, so fields and skip reasons
+//! can be added in minor versions.
+pub mod entities;
+pub mod filter;
+pub mod language;
+pub mod normalize;
+pub mod order;
+pub mod patterns;
+pub mod profit;
+pub mod score;
+
+pub use filter::{Check, Decision, Filter, SkipReason};
+pub use entities::{Entity, EntityKind};
+pub use language::{Language, Languages};
+pub use order::{parse_order, parse_order_formatted, parse_order_with, Amount, Order};
+pub use patterns::PricePatterns;
+pub use profit::Commissions;
+pub use score::{Context, Scorer, Weights};
@@ -0,0 +1,143 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+// Bank names are typed by hand in mixed Cyrillic and Latin ("Т-Банк", "T Bank", "тбанк",
+// "Tinkoff"); these make the spellings compare equal. Message text changes with every cosmetic
+// tweak of an exchange bot's template; `message` takes the cosmetics out before parsing
+
+// Key for per-bank settings: lower case, Cyrillic т and "банк" in Latin, no hyphens or spaces,
+// so "Т-Банк", "T Bank" and "tbank" are all "tbank"
+pub fn bank_key(bank: &str) -> String {
+    bank.trim()
+        .to_lowercase()
+        .replace('т', "t")
+        .replace("банк", "bank")
+        .replace(['-', ' '], "")
+}
+
+// Bank name for matching a filter against: lower case, Cyrillic т in Latin, no hyphens or spaces
+// ("банк" stays, unlike in `bank_key`)
+pub fn bank_name(bank: &str) -> String {
+    bank.to_lowercase().replace('т', "t").replace(['-', ' '], "")
+}
+
+// Whether a bank is one of the T-banks, in any spelling
+pub fn is_t_bank(bank: &str) -> bool {
+    let bank = bank.to_lowercase();
+    bank.starts_with('t')
+        || bank.starts_with('т')
+        || ["t-bank", "т-bank", "t bank", "т bank", "tbank", "тbank", "t-банк", "т-банк", "t банк", "т банк", "tбанк", "тбанк"]
+            .iter()
+            .any(|spelling| bank.contains(spelling))
+}
+
+// Key for comparing requisites: the digits only, with phone numbers in the +7 form ("8 (900) 123-45-67"
+// and "+7 900 1234567" are both "79001234567"); requisites without digits in lower case
+pub fn requisite_key(requisite: &str) -> String {
+    let digits: String = requisite.chars().filter(char::is_ascii_digit).collect();
+    match digits.len() {
+        0 => requisite.trim().to_lowercase(),
+        11 if digits.starts_with('8') => format!("7{}", &digits[1..]),
+        10 if digits.starts_with('9') => format!("7{}", digits),
+        _ => digits,
+    }
+}
+
+// The HTML Telegram formatting comes as when a template is pasted or relayed: tags, <br> and entities
+static HTML_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)<br\s*/?>|</?(?:b|strong|i|em|u|ins|s|strike|del|code|pre|tg-spoiler|tg-emoji|span|a|blockquote)(?:\s[^>]*)?>").unwrap()
+});
+static HTML_ENTITY: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(?:(amp|lt|gt|quot|apos|nbsp)|#(\d{1,7})|#x([0-9a-fA-F]{1,6}));").unwrap());
+// Markdown: [label](link), and the markers of bold, italic, code, strikethrough and spoilers
+static MARKDOWN_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]\n]*)\]\([^)\s]*\)").unwrap());
+static MARKDOWN_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*+|`+|~~|__|\|\|").unwrap());
+// What lines start with in a list or a quote
+static LINE_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[>•·▪▫◦‣○●■□–—-]\s+|>)+").unwrap());
+
+// Message text as the order parser and substring filters should see it: markdown and HTML markup
+// taken out (its text kept), invisible formatting characters removed, emoji decorations ("🟢 Новая
+// заявка", "💰 Сумма: ...") dropped, list and quote markers cut from the line starts, and every run
+// of spaces, non-breaking ones included, made a single space. Lines stay lines, so "Банк: " still
+// starts one
+pub fn message(text: &str) -> String {
+    let mut text = std::borrow::Cow::Borrowed(text);
+    if text.contains('<') {
+        text = HTML_TAG.replace_all(&text, |tag: &Captures| if tag[0].to_lowercase().starts_with("<br") { "\n" } else { "" }).into_owned().into();
+    }
+    if text.contains('&') {
+        text = HTML_ENTITY.replace_all(&text, html_entity).into_owned().into();
+    }
+    if text.contains("](") {
+        text = MARKDOWN_LINK.replace_all(&text, "$1").into_owned().into();
+    }
+    let text = MARKDOWN_MARKER.replace_all(&text, "");
+
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        // A keycap ("1️⃣") is a decoration as a whole, digit included
+        if c.is_ascii_digit() || c == '#' {
+            let mut rest = chars.clone();
+            rest.next_if_eq(&'\u{FE0F}');
+            if rest.next() == Some('\u{20E3}') {
+                chars = rest;
+                continue;
+            }
+        }
+        if is_space(c) {
+            plain.push(' ');
+        } else if !is_invisible(c) && !is_emoji(c) {
+            plain.push(c);
+        }
+    }
+
+    plain
+        .lines()
+        .map(|line| {
+            let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            LINE_MARKER.replace(&line, "").into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn html_entity(entity: &Captures) -> String {
+    let code = match (entity.get(1), entity.get(2), entity.get(3)) {
+        (Some(name), _, _) => {
+            return match name.as_str() {
+                "amp" => "&",
+                "lt" => "<",
+                "gt" => ">",
+                "quot" => "\"",
+                "apos" => "'",
+                _ => " ",
+            }
+            .to_string()
+        }
+        (_, Some(decimal), _) => decimal.as_str().parse().ok(),
+        (_, _, Some(hex)) => u32::from_str_radix(hex.as_str(), 16).ok(),
+        _ => None,
+    };
+    code.and_then(char::from_u32).map_or_else(|| entity[0].to_string(), String::from)
+}
+
+// Spaces of every width, the non-breaking ones included
+fn is_space(c: char) -> bool {
+    c != '\n' && c.is_whitespace()
+}
+
+// Characters that change nothing visible: zero-width spaces and joiners, the soft hyphen,
+// direction marks and isolates, the byte order mark, variation selectors and tag characters
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{00AD}' | '\u{034F}' | '\u{061C}' | '\u{180E}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{FE00}'..='\u{FE0F}' | '\u{FEFF}'
+        | '\u{20E3}' | '\u{E0000}'..='\u{E007F}')
+}
+
+// Emoji and pictographs, flags and skin tones included; not currency signs or letters
+pub(crate) fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{2190}'..='\u{21FF}' | '\u{2300}'..='\u{23FF}' | '\u{25A0}'..='\u{27BF}' | '\u{2900}'..='\u{297F}'
+        | '\u{2B00}'..='\u{2BFF}' | '\u{3030}' | '\u{303D}' | '\u{3297}' | '\u{3299}' | '\u{1F000}'..='\u{1FAFF}')
+}
@@ -0,0 +1,207 @@
+use crate::{
+    entities::{bold_amount, code_requisite, Entity},
+    normalize,
+    patterns::PricePatterns,
+};
+
+// An amount as the message states it
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Amount {
+    pub value: f64,
+    pub currency: String,   // Upper-case code: RUB, USD, USDT, EUR, CNY
+}
+
+// What an order message says, line by line ("Сумма: ", "Банк: ", "Реквизит: ", "Комиссия: ")
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Order {
+    pub amount: Option<Amount>,
+    pub rubles: Option<i32>,        // The amount in rubles, rounded down: set for ₽, by `convert` for the rest
+    pub bank: Option<String>,
+    pub requisite: Option<String>,
+    pub commission: Option<f64>,    // Percent
+}
+
+impl Order {
+    // Fill in `rubles` for an amount in another currency; `rate` gives rubles per unit of a
+    // currency, None when it isn't known (and the order then has no ruble amount)
+    pub fn convert(&mut self, rate: impl Fn(&str) -> Option<f64>) {
+        if let Some(amount) = self.amount.as_ref().filter(|_| self.rubles.is_none()) {
+            self.rubles = rate(&amount.currency).map(|rate| (amount.value * rate) as i32);
+        }
+    }
+}
+
+// The order in a message, or None when the message has no amount, bank or requisite at all
+pub fn parse_order(text: &str) -> Option<Order> {
+    parse_order_with(text, &PricePatterns::default())
+}
+
+// `parse_order` with the amount read by other patterns than the default ones
+pub fn parse_order_with(text: &str, patterns: &PricePatterns) -> Option<Order> {
+    parse_order_formatted(text, &[], patterns)
+}
+
+// `parse_order_with` helped by the message's formatting: a bold run that is an amount gives the
+// amount when no pattern finds one, and a code entity that looks like a requisite is taken over the
+// rest of the "Реквизит: " line (see entities.rs). Entities point into `text` as given; the lines
+// are read from it normalized (see `normalize::message`)
+pub fn parse_order_formatted(text: &str, entities: &[Entity], patterns: &PricePatterns) -> Option<Order> {
+    let formatted = text;
+    let text = &normalize::message(text);
+    let amount = patterns.find_amount(text).or_else(|| bold_amount(formatted, entities));
+    let rubles = amount.as_ref().filter(|amount| amount.currency == "RUB").map(|amount| amount.value as i32);
+    let order = Order {
+        amount,
+        rubles,
+        bank: find_bank(text).map(str::to_string),
+        requisite: code_requisite(formatted, entities).or_else(|| find_requisite(text).map(str::to_string)),
+        commission: extract_commission(text),
+    };
+    (order.amount.is_some() || order.bank.is_some() || order.requisite.is_some()).then_some(order)
+}
+
+// The stated amount: rubles when there is a "...а: N ₽", otherwise the first amount in a
+// currency we have a code for
+pub fn find_amount(text: &str) -> Option<Amount> {
+    PricePatterns::default().find_amount(text)
+}
+
+// Bank name from the "Банк: " line of an order message
+pub fn find_bank(text: &str) -> Option<&str> {
+    text.lines()
+        .find(|line| line.starts_with("Банк: "))
+        .map(|line| line.trim_start_matches("Банк: "))
+}
+
+// Requisite from the "Реквизит: " line: a phone number for SBP, otherwise a card or account
+pub fn find_requisite(text: &str) -> Option<&str> {
+    text.lines()
+        .find(|line| line.starts_with("Реквизит: "))
+        .map(|line| line.trim_start_matches("Реквизит: "))
+}
+
+// Commission stated in an order, e.g. "Комиссия: 1,5%", in percent
+pub fn extract_commission(text: &str) -> Option<f64> {
+    let line = text.lines().find_map(|line| {
+        ["Комиссия: ", "Ставка: ", "Вознаграждение: "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+    })?;
+    let (value, _) = line.split_once('%')?;
+    value.trim().replace(',', ".").parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+
+    fn amount(text: &str) -> Option<(f64, String)> {
+        parse_order(text)?.amount.map(|amount| (amount.value, amount.currency))
+    }
+
+    #[test]
+    fn reads_a_ruble_order() {
+        let order = parse_order("Сумма: 50 000 ₽\nБанк: Т-Банк\nРеквизит: +7 900 000-00-00\nКомиссия: 1,5%").unwrap();
+        assert_eq!(order.rubles, Some(50_000));
+        assert_eq!(order.bank.as_deref(), Some("Т-Банк"));
+        assert_eq!(order.requisite.as_deref(), Some("+7 900 000-00-00"));
+        assert_eq!(order.commission, Some(1.5));
+    }
+
+    #[test]
+    fn reads_amount_formats() {
+        assert_eq!(amount("Сумма: 500 USDT"), Some((500.0, "USDT".to_string())));
+        assert_eq!(amount("Сумма: 1 250,50 $"), Some((1250.5, "USD".to_string())));
+        assert_eq!(amount("Сумма: 75 €"), Some((75.0, "EUR".to_string())));
+        // Non-breaking spaces and decorations are taken out before the patterns see the text
+        assert_eq!(amount("💰 Сумма:\u{a0}50\u{a0}000\u{a0}₽"), Some((50_000.0, "RUB".to_string())));
+        assert_eq!(amount("<b>Сумма:</b> 50 000 ₽"), Some((50_000.0, "RUB".to_string())));
+    }
+
+    #[test]
+    fn reads_amounts_by_selected_patterns() {
+        let amount = |names, text| {
+            let patterns = PricePatterns::parse(names).unwrap();
+            parse_order_with(text, &patterns).and_then(|order| order.amount).map(|amount| (amount.value, amount.currency))
+        };
+        assert_eq!(amount("rub_anywhere", "Покупка 50 000 руб. через СБП"), Some((50_000.0, "RUB".to_string())));
+        assert_eq!(amount("amount_en", "Amount: 50,000.00 RUB"), Some((50_000.0, "RUB".to_string())));
+        assert_eq!(amount("currency_first", "Amount: $1,250.50"), Some((1250.5, "USD".to_string())));
+        assert_eq!(amount("thousands", "Сумма: 50к"), Some((50_000.0, "RUB".to_string())));
+        assert_eq!(amount("thousands", "Сумма 120 тыс"), Some((120_000.0, "RUB".to_string())));
+        // Not among the defaults
+        assert_eq!(amount("default", "Amount: 50,000.00 RUB"), None);
+        assert!(PricePatterns::parse("rub, nope").is_err());
+        assert!(PricePatterns::parse(" , ").is_err());
+    }
+
+    #[test]
+    fn converts_other_currencies_to_rubles() {
+        let mut order = parse_order("Сумма: 500 USDT").unwrap();
+        assert_eq!(order.rubles, None);
+        order.convert(|currency| (currency == "USDT").then_some(95.5));
+        assert_eq!(order.rubles, Some(47_750));
+
+        let mut order = parse_order("Сумма: 500 CNY").unwrap();
+        order.convert(|_| None);
+        assert_eq!(order.rubles, None);
+    }
+
+    #[test]
+    fn reads_a_bold_amount_when_no_pattern_does() {
+        let text = "Новая заявка 50 000 ₽\nБанк: Сбер";
+        let entities = [Entity::new(13, 8, EntityKind::Bold)];
+        let order = parse_order_formatted(text, &entities, &PricePatterns::default()).unwrap();
+        assert_eq!(order.rubles, Some(50_000));
+
+        // As long as a phone number, a bare bold run isn't taken for rubles
+        let text = "Новая заявка 79001234567\nБанк: Сбер";
+        let entities = [Entity::new(13, 11, EntityKind::Bold)];
+        let order = parse_order_formatted(text, &entities, &PricePatterns::default()).unwrap();
+        assert_eq!(order.amount, None);
+    }
+
+    #[test]
+    fn takes_the_requisite_from_a_code_entity() {
+        let text = "Сумма: 50 000 ₽\nРеквизит: 2200 1234 5678 9012 Иван И.";
+        let entities = [Entity::new(26, 19, EntityKind::Code)];
+        let order = parse_order_formatted(text, &entities, &PricePatterns::default()).unwrap();
+        assert_eq!(order.requisite.as_deref(), Some("2200 1234 5678 9012"));
+
+        // Too few digits to be a requisite: the line is used
+        let entities = [Entity::new(26, 4, EntityKind::Code)];
+        let order = parse_order_formatted(text, &entities, &PricePatterns::default()).unwrap();
+        assert_eq!(order.requisite.as_deref(), Some("2200 1234 5678 9012 Иван И."));
+    }
+
+    #[test]
+    fn entity_offsets_count_utf16_units() {
+        // The emoji takes two UTF-16 units
+        let text = "🟢 50 000 ₽";
+        assert_eq!(Entity::new(3, 8, EntityKind::Bold).slice(text), Some("50 000 ₽"));
+        assert_eq!(Entity::new(1, 2, EntityKind::Bold).slice(text), None);
+        assert_eq!(Entity::new(3, 100, EntityKind::Bold).slice(text), None);
+    }
+
+    #[test]
+    fn ignores_malformed_input() {
+        assert_eq!(parse_order(""), None);
+        assert_eq!(parse_order("Привет! Как дела?"), None);
+        assert_eq!(amount("Сумма: ₽"), None);
+        assert_eq!(amount("Сумма: 50 000 тугриков\nБанк: Сбер"), None);
+        assert_eq!(amount("Сумма: 1.2.3 $\nБанк: Сбер"), None);
+
+        let order = parse_order("Банк: Сбер\nКомиссия: много%").unwrap();
+        assert_eq!(order.commission, None);
+        let order = parse_order("Банк: Сбер\nКомиссия: 1,5").unwrap();
+        assert_eq!(order.commission, None);
+
+        // An entity past the end of the text is ignored rather than a panic
+        let entities = [Entity::new(500, 10, EntityKind::Bold), Entity::new(2, 500, EntityKind::Code)];
+        let order = parse_order_formatted("Банк: Сбер", &entities, &PricePatterns::default()).unwrap();
+        assert_eq!((order.amount, order.requisite), (None, None));
+    }
+}
@@ -0,0 +1,180 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    language::{Language, Languages},
+    order::Amount,
+};
+
+// An amount format of one kind of exchange bot: a regex with a `value` group and, unless the
+// currency is fixed, a `currency` group
+struct Pattern {
+    name: &'static str,
+    example: &'static str,
+    regex: Regex,
+    currency: Option<&'static str>,   // When the format doesn't say
+    scale: f64,                       // "50к" is 50 thousand
+    languages: Languages,             // The messages it's meant for (see language.rs)
+}
+
+impl Pattern {
+    fn new(name: &'static str, example: &'static str, regex: &str) -> Self {
+        Self { name, example, regex: Regex::new(regex).unwrap(), currency: None, scale: 1.0, languages: Languages::ALL }
+    }
+
+    fn only(mut self, language: Language) -> Self {
+        self.languages = Languages::of(&[language]);
+        self
+    }
+
+    fn rubles(mut self, scale: f64) -> Self {
+        self.currency = Some("RUB");
+        self.scale = scale;
+        self
+    }
+
+    fn find(&self, text: &str) -> Option<Amount> {
+        let captures = self.regex.captures(text)?;
+        let value = parse_number(captures.name("value")?.as_str())? * self.scale;
+        let currency = match (captures.name("currency"), self.currency) {
+            (Some(currency), _) => currency_code(currency.as_str()),
+            (None, currency) => currency?.to_string(),
+        };
+        Some(Amount { value, currency })
+    }
+}
+
+// The built-in formats, picked by name in `PricePatterns::parse`
+static LIBRARY: Lazy<Vec<Pattern>> = Lazy::new(|| {
+    vec![
+        // The usual order messages: rubles after "Сумма:" (or any "...а:")
+        Pattern::new("rub", "Сумма: 50 000 ₽", r"а:\s*(?P<value>[\d\s]+)\s*(?P<currency>₽)").only(Language::Russian),
+        Pattern::new(
+            "foreign",
+            "Сумма: 500 USDT, Сумма: 1 250,50 $",
+            r"(?i)а:\s*(?P<value>\d[\d\s]*(?:[.,]\d+)?)\s*(?P<currency>\$|€|¥|usdt|usd|eur|cny)",
+        )
+        .only(Language::Russian),
+        // Rubles anywhere in the text, for bots that don't label the amount
+        Pattern::new(
+            "rub_anywhere",
+            "Покупка 50 000 руб. через СБП",
+            r"(?i)(?P<value>\d[\d\s]*(?:[.,]\d+)?)\s*(?P<currency>₽|руб|rub\b|р\.)",
+        )
+        .only(Language::Russian),
+        // English-speaking P2P bots
+        Pattern::new(
+            "amount_en",
+            "Amount: 50,000.00 RUB, Fiat amount: 500 USDT",
+            r"(?i)(?:amount|total|sum)\s*:\s*(?P<value>\d[\d ,.]*\d|\d)\s*(?P<currency>rub|₽|usdt|usd|\$|eur|€|cny|¥)",
+        )
+        .only(Language::English),
+        // The currency before the amount
+        Pattern::new(
+            "currency_first",
+            "Amount: $1,250.50, Сумма: ₽ 50 000",
+            r"(?i)(?:сумма|amount|total)\s*:\s*(?P<currency>₽|\$|€|¥|usdt|usd|eur|rub)\s*(?P<value>\d[\d ,.]*\d|\d)",
+        ),
+        // Thousands of rubles
+        Pattern::new("thousands", "Сумма: 50к, Сумма 120 тыс", r"(?i)(?:сумма|amount)\s*:?\s*(?P<value>\d+(?:[.,]\d+)?)\s*(?:тыс|к|k)\b")
+            .rubles(1000.0),
+    ]
+});
+
+// What PRICE_PATTERNS falls back to, and what "default" stands for
+const DEFAULT_NAMES: [&str; 2] = ["rub", "foreign"];
+
+// The amount formats to look for (PRICE_PATTERNS in the reaction bot): the first that matches
+// gives the amount
+#[derive(Clone)]
+pub struct PricePatterns(Vec<usize>);   // Indices into LIBRARY, ascending
+
+impl PricePatterns {
+    // Comma separated names from `library`, or "default" for the usual "Сумма: 50 000 ₽" and
+    // "Сумма: 500 USDT". Patterns are tried in the library's order whatever the order of the names
+    pub fn parse(names: &str) -> Result<Self, String> {
+        let mut selected = Vec::new();
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let names = if name.eq_ignore_ascii_case("default") { &DEFAULT_NAMES[..] } else { &[name][..] };
+            for name in names {
+                let index = LIBRARY
+                    .iter()
+                    .position(|pattern| pattern.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("unknown price pattern '{}'", name))?;
+                selected.push(index);
+            }
+        }
+        if selected.is_empty() {
+            return Err("no price patterns given".to_string());
+        }
+        selected.sort_unstable();
+        selected.dedup();
+        Ok(Self(selected))
+    }
+
+    // The stated amount, by the first selected pattern that finds one
+    pub fn find_amount(&self, text: &str) -> Option<Amount> {
+        self.0.iter().find_map(|&index| LIBRARY[index].find(text))
+    }
+
+    // Those of the patterns meant for one of the languages, for a message written in them (see
+    // `language::detect`); patterns for any language stay unless there are none
+    pub fn only(&self, languages: Languages) -> Self {
+        Self(self.0.iter().copied().filter(|&index| LIBRARY[index].languages.intersects(languages)).collect())
+    }
+
+    // Names of the selected patterns
+    pub fn names(&self) -> Vec<&'static str> {
+        self.0.iter().map(|&index| LIBRARY[index].name).collect()
+    }
+}
+
+impl Default for PricePatterns {
+    fn default() -> Self {
+        Self::parse("default").unwrap()
+    }
+}
+
+impl std::fmt::Debug for PricePatterns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+// Every built-in pattern's name with an example of what it reads
+pub fn library() -> impl Iterator<Item = (&'static str, &'static str)> {
+    LIBRARY.iter().map(|pattern| (pattern.name, pattern.example))
+}
+
+// "50 000", "1 250,50", "50,000.00" or "50.000". With both separators the last one is the decimal
+// point; a single kind is one unless it repeats or has exactly three digits after it
+pub(crate) fn parse_number(value: &str) -> Option<f64> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let decimal = match (value.rfind(','), value.rfind('.')) {
+        (Some(comma), Some(point)) => Some(comma.max(point)),
+        (Some(at), None) | (None, Some(at)) => {
+            let separator = value.as_bytes()[at] as char;
+            (value.matches(separator).count() == 1 && value.len() - at - 1 != 3).then_some(at)
+        }
+        (None, None) => None,
+    };
+    let number: String = value
+        .char_indices()
+        .filter_map(|(at, c)| match c {
+            ',' | '.' if Some(at) == decimal => Some('.'),
+            ',' | '.' => None,
+            c => Some(c),
+        })
+        .collect();
+    number.parse().ok()
+}
+
+pub(crate) fn currency_code(currency: &str) -> String {
+    match currency.to_lowercase().as_str() {
+        "₽" | "руб" | "rub" | "р." => "RUB".to_string(),
+        "$" => "USD".to_string(),
+        "€" => "EUR".to_string(),
+        "¥" => "CNY".to_string(),
+        code => code.to_uppercase(),
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{normalize, order::Order};
+
+// What an order earns: its ruble amount times the commission, which is the order's own when it
+// states one ("Комиссия: 1,5%"), otherwise its bank's, otherwise the default. Built with `new`
+// and `with_bank`
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Commissions {
+    pub default_pct: f64,
+    pub bank_pcts: Vec<(String, f64)>,   // Per-bank percents (normalize::bank_key, percent)
+}
+
+impl Commissions {
+    pub fn new(default_pct: f64) -> Self {
+        Self { default_pct, ..Self::default() }
+    }
+
+    // A different commission for banks matching `bank`, e.g. ("tbank", 1.5)
+    pub fn with_bank(mut self, bank: &str, pct: f64) -> Self {
+        self.bank_pcts.push((normalize::bank_key(bank), pct));
+        self
+    }
+
+    // Commission in percent the order is expected to pay
+    pub fn percent_for(&self, order: &Order) -> f64 {
+        if let Some(pct) = order.commission {
+            return pct;
+        }
+        let Some(bank) = &order.bank else {
+            return self.default_pct;
+        };
+        let bank = normalize::bank_key(bank);
+        self.bank_pcts
+            .iter()
+            .find(|(key, _)| bank.contains(key.as_str()))
+            .map(|(_, pct)| *pct)
+            .unwrap_or(self.default_pct)
+    }
+
+    // Expected profit in rubles, or None for an order without a ruble amount
+    pub fn expected_profit(&self, order: &Order) -> Option<f64> {
+        Some(order.rubles? as f64 * self.percent_for(order) / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{filter::{Filter, SkipReason}, order::parse_order};
+
+    fn order(text: &str) -> Order {
+        parse_order(text).unwrap()
+    }
+
+    #[test]
+    fn commission_is_the_orders_then_the_banks_then_the_default() {
+        let commissions = Commissions::new(1.0).with_bank("Т-Банк", 1.5);
+        assert_eq!(commissions.percent_for(&order("Сумма: 10 000 ₽")), 1.0);
+        assert_eq!(commissions.percent_for(&order("Сумма: 10 000 ₽\nБанк: T Bank")), 1.5);
+        assert_eq!(commissions.percent_for(&order("Сумма: 10 000 ₽\nБанк: Сбер")), 1.0);
+        assert_eq!(commissions.percent_for(&order("Сумма: 10 000 ₽\nБанк: Т-Банк\nКомиссия: 2,5%")), 2.5);
+    }
+
+    #[test]
+    fn profit_is_the_ruble_amount_times_the_commission() {
+        let commissions = Commissions::new(1.0).with_bank("Сбер", 2.0);
+        assert_eq!(commissions.expected_profit(&order("Сумма: 50 000 ₽")), Some(500.0));
+        assert_eq!(commissions.expected_profit(&order("Сумма: 50 000 ₽\nБанк: Сбер")), Some(1000.0));
+        assert_eq!(Commissions::new(0.0).expected_profit(&order("Сумма: 50 000 ₽")), Some(0.0));
+    }
+
+    #[test]
+    fn other_currencies_earn_at_their_ruble_rate() {
+        let commissions = Commissions::new(1.0);
+        let mut usdt = order("Сумма: 500 USDT");
+        usdt.convert(|currency| (currency == "USDT").then_some(95.5));
+        assert_eq!(usdt.rubles, Some(47_750));
+        assert_eq!(commissions.expected_profit(&usdt), Some(477.5));
+    }
+
+    #[test]
+    fn nothing_is_expected_without_a_price() {
+        let commissions = Commissions::new(1.0);
+        assert_eq!(commissions.expected_profit(&order("Банк: Сбер")), None);
+        // No rate for the currency
+        let mut euros = order("Сумма: 500 EUR");
+        euros.convert(|currency| (currency == "USDT").then_some(95.5));
+        assert_eq!(commissions.expected_profit(&euros), None);
+    }
+
+    #[test]
+    fn threshold_is_reached_at_exactly_min_profit() {
+        let filter = Filter::new(0).with_min_profit(477.5, Commissions::new(1.0));
+        let mut usdt = order("Сумма: 500 USDT");
+        usdt.convert(|_| Some(95.5));
+        assert_eq!(filter.evaluate(&usdt), Ok(()));
+        usdt.rubles = Some(47_749);
+        assert_eq!(filter.evaluate(&usdt), Err(SkipReason::BelowMinProfit));
+    }
+}
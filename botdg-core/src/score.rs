@@ -0,0 +1,140 @@
+use crate::{normalize, order::Order};
+
+// Where and when an order arrived, for scoring
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Context {
+    pub chat_id: i64,
+    pub hour: u32,   // Local hour of day, 0-23
+}
+
+impl Context {
+    pub fn new(chat_id: i64, hour: u32) -> Self {
+        Self { chat_id, hour }
+    }
+}
+
+// How much an order is worth reacting to; higher is better. Implement it to rank orders your own
+// way, or use `Weights`
+pub trait Scorer {
+    fn score(&self, order: &Order, context: &Context) -> f64;
+}
+
+// A score summed from weights: `per_thousand` for every thousand rubles of the amount, plus the
+// weight of the first matching bank, of the chat and of every hour range the order falls in.
+// Built with `new` and the `with_` methods
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Weights {
+    pub per_thousand: f64,
+    pub banks: Vec<(String, f64)>,      // (normalize::bank_key, weight)
+    pub chats: Vec<(i64, f64)>,
+    pub hours: Vec<(u32, u32, f64)>,    // (from, to, weight), inclusive; from > to wraps past midnight
+}
+
+impl Weights {
+    pub fn new(per_thousand: f64) -> Self {
+        Self { per_thousand, ..Self::default() }
+    }
+
+    // A weight for banks matching `bank`, e.g. ("tbank", 10.0); negative to prefer others
+    pub fn with_bank(mut self, bank: &str, weight: f64) -> Self {
+        self.banks.push((normalize::bank_key(bank), weight));
+        self
+    }
+
+    pub fn with_chat(mut self, chat_id: i64, weight: f64) -> Self {
+        self.chats.push((chat_id, weight));
+        self
+    }
+
+    // A weight for orders arriving from hour `from` to hour `to`, e.g. (22, 6, -20.0) for nights
+    pub fn with_hours(mut self, from: u32, to: u32, weight: f64) -> Self {
+        self.hours.push((from, to, weight));
+        self
+    }
+}
+
+// Whether a score clears the minimum (MIN_SCORE); reaching it exactly is enough
+pub fn meets_min(score: f64, min_score: f64) -> bool {
+    score >= min_score
+}
+
+impl Scorer for Weights {
+    fn score(&self, order: &Order, context: &Context) -> f64 {
+        let amount = order.rubles.map_or(0.0, |rubles| rubles as f64 / 1000.0 * self.per_thousand);
+        let bank = order.bank.as_deref().map(normalize::bank_key).and_then(|bank| {
+            self.banks.iter().find(|(key, _)| bank.contains(key.as_str())).map(|(_, weight)| *weight)
+        });
+        let chat = self.chats.iter().find(|(chat_id, _)| *chat_id == context.chat_id).map(|(_, weight)| *weight);
+        let hours: f64 = self
+            .hours
+            .iter()
+            .filter(|(from, to, _)| match from <= to {
+                true => (*from..=*to).contains(&context.hour),
+                false => context.hour >= *from || context.hour <= *to,
+            })
+            .map(|(_, _, weight)| weight)
+            .sum();
+        amount + bank.unwrap_or_default() + chat.unwrap_or_default() + hours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::parse_order;
+
+    const CHAT: i64 = -1001;
+
+    fn score(weights: &Weights, text: &str, hour: u32) -> f64 {
+        weights.score(&parse_order(text).unwrap(), &Context::new(CHAT, hour))
+    }
+
+    #[test]
+    fn amount_counts_per_thousand_rubles() {
+        let weights = Weights::new(2.0);
+        assert_eq!(score(&weights, "Сумма: 50 000 ₽", 12), 100.0);
+        assert_eq!(score(&weights, "Сумма: 500 ₽", 12), 1.0);
+        // Nothing for an amount with no ruble value
+        assert_eq!(score(&weights, "Сумма: 500 USDT", 12), 0.0);
+    }
+
+    #[test]
+    fn only_the_first_matching_bank_counts() {
+        let weights = Weights::new(0.0).with_bank("Т-Банк", 10.0).with_bank("банк", 3.0).with_bank("Сбер", -5.0);
+        assert_eq!(score(&weights, "Сумма: 1 000 ₽\nБанк: T Bank", 12), 10.0);
+        assert_eq!(score(&weights, "Сумма: 1 000 ₽\nБанк: Сбер", 12), -5.0);
+        assert_eq!(score(&weights, "Сумма: 1 000 ₽\nБанк: Альфа-Банк", 12), 3.0);
+        assert_eq!(score(&weights, "Сумма: 1 000 ₽", 12), 0.0);
+    }
+
+    #[test]
+    fn chat_and_hour_weights_add_up() {
+        let weights = Weights::new(1.0).with_chat(CHAT, 5.0).with_chat(-1002, 100.0).with_hours(9, 17, 2.0).with_hours(12, 12, 1.0);
+        assert_eq!(score(&weights, "Сумма: 10 000 ₽", 12), 10.0 + 5.0 + 2.0 + 1.0);
+        assert_eq!(score(&weights, "Сумма: 10 000 ₽", 17), 10.0 + 5.0 + 2.0);
+        assert_eq!(score(&weights, "Сумма: 10 000 ₽", 18), 10.0 + 5.0);
+    }
+
+    #[test]
+    fn hour_ranges_wrap_past_midnight() {
+        let weights = Weights::new(0.0).with_hours(22, 6, -20.0);
+        for hour in [22, 23, 0, 6] {
+            assert_eq!(score(&weights, "Сумма: 1 ₽", hour), -20.0, "{}", hour);
+        }
+        for hour in [7, 12, 21] {
+            assert_eq!(score(&weights, "Сумма: 1 ₽", hour), 0.0, "{}", hour);
+        }
+    }
+
+    #[test]
+    fn min_score_is_inclusive() {
+        let weights = Weights::new(1.0).with_bank("Сбер", 0.5);
+        let at_minimum = score(&weights, "Сумма: 49 500 ₽\nБанк: Сбер", 12);
+        assert_eq!(at_minimum, 50.0);
+        assert!(meets_min(at_minimum, 50.0));
+        assert!(!meets_min(score(&weights, "Сумма: 49 499 ₽\nБанк: Сбер", 12), 50.0));
+        assert!(meets_min(score(&weights, "Сумма: 1 ₽", 12), f64::MIN));
+    }
+}
@@ -0,0 +1,18 @@
+use std::process::Command;
+
+// Embed the git commit the binary was built from (GIT_COMMIT overrides it, e.g. in Docker builds without .git)
+fn main() {
+    let commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .filter(|c| !c.is_empty())
+        .or_else(|| {
+            let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=BUILD_COMMIT={}", commit);
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
@@ -0,0 +1,44 @@
+use std::{env, path::Path};
+
+pub const DEFAULT_ACCOUNTS_DIR: &str = "accounts";
+
+// Named Telegram accounts: each is a TDLib session directory under ACCOUNTS_DIR, so an instance
+// can be switched to a backup account with /account use
+pub fn accounts_dir() -> String {
+    env::var("ACCOUNTS_DIR").unwrap_or_else(|_| DEFAULT_ACCOUNTS_DIR.to_string())
+}
+
+pub fn account_dir(name: &str) -> String {
+    format!("{}/{}", accounts_dir(), name)
+}
+
+// Names end up in paths, so only letters, digits, '-' and '_' are allowed
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+// The account whose session directory this is, if it is one
+pub fn name_of(session_dir: &str) -> Option<String> {
+    let name = Path::new(session_dir).strip_prefix(accounts_dir()).ok()?.to_str()?;
+    valid_name(name).then(|| name.to_string())
+}
+
+// TDLib keeps the login in td.binlog, so a directory without one still needs to log in
+pub fn is_authorized(name: &str) -> bool {
+    Path::new(&account_dir(name)).join("td.binlog").exists()
+}
+
+// Account names, sorted. The reaction bot keeps downloaded files next to the session in
+// "<dir>_files", which isn't an account of its own
+pub fn list() -> Vec<String> {
+    let mut names = std::fs::read_dir(accounts_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| valid_name(name) && !name.ends_with("_files"))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
@@ -0,0 +1,35 @@
+use std::{collections::BTreeMap, env};
+
+// Short names for frequent commands, e.g. /a50 for "/amount 50000" or /tb for "/bank t", so
+// filters can be switched quickly from a phone. COMMAND_ALIASES sets them in the configuration
+// ("a50=/amount 50000;tb=/bank t"), /alias at runtime; runtime ones win
+pub fn from_env() -> BTreeMap<String, String> {
+    env::var("COMMAND_ALIASES")
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(parse_entry)
+        .collect()
+}
+
+// "name=/command args"
+pub fn parse_entry(entry: &str) -> Option<(String, String)> {
+    let (name, command) = entry.split_once('=')?;
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    let command = command.trim();
+    (is_valid_name(&name) && command.starts_with('/')).then(|| (name, command.to_string()))
+}
+
+// Telegram command names: letters, digits and underscores
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 32 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// "/alias rest" as the aliased command with `rest` appended, or None if the message doesn't start
+// with an alias. "/alias@bot" works like in any other command
+pub fn expand(text: &str, aliases: &BTreeMap<String, String>) -> Option<String> {
+    let text = text.trim();
+    let (head, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let name = head.strip_prefix('/')?.split('@').next()?.to_lowercase();
+    let command = aliases.get(&name)?;
+    Some(format!("{} {}", command, rest.trim()).trim_end().to_string())
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use teloxide::{types::UserId, utils::command::BotCommands};
+
+use crate::{accounts, aliases, state::{BotState, FilterSettings, Instance}, TelegramCommand};
+
+// Bumped when the document changes incompatibly; older versions are still read
+pub const VERSION: u32 = 1;
+
+// Settings of one instance that make sense on another deployment. Binary and session paths
+// don't, so they stay behind
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct InstanceSettings {
+    pub filters: FilterSettings,
+    pub allowed_chats: Option<Vec<i64>>,
+    pub balance: Option<i64>,
+    pub balance_reset: Option<String>,
+}
+
+// What /export_settings sends and /import_settings reads: the filters, chats and balance of every
+// instance the user can see, the presets, the requisite blacklist, the /alias aliases and where the
+// event feed and daily report go
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsBackup {
+    pub version: u32,
+    pub exported_at: String,
+    pub instances: BTreeMap<String, InstanceSettings>,
+    pub presets: BTreeMap<String, FilterSettings>,
+    pub blacklist: BTreeSet<String>,
+    pub aliases: BTreeMap<String, String>,
+    pub event_feed_chat_id: Option<i64>,
+    pub report_chat_id: Option<i64>,
+}
+
+// What an import changed
+#[derive(Default)]
+pub struct Imported {
+    pub updated: Vec<String>,
+    pub added: Vec<String>,
+    pub skipped: Vec<String>,   // Other users' instances
+    pub invalid: Vec<String>,   // Instance names /instance add wouldn't take
+    pub presets: usize,
+}
+
+pub fn export(state: &BotState, user: Option<UserId>) -> SettingsBackup {
+    SettingsBackup {
+        version: VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        instances: state
+            .accessible(user)
+            .map(|(name, instance)| {
+                let settings = InstanceSettings {
+                    filters: instance.filters.clone(),
+                    allowed_chats: instance.allowed_chats.clone(),
+                    balance: instance.balance,
+                    balance_reset: instance.balance_reset.clone(),
+                };
+                (name.clone(), settings)
+            })
+            .collect(),
+        presets: state.presets.clone(),
+        blacklist: state.blacklist.clone(),
+        aliases: state.aliases.clone(),
+        event_feed_chat_id: state.event_feed_chat_id,
+        report_chat_id: state.report_chat_id,
+    }
+}
+
+pub fn parse(contents: &[u8]) -> Result<SettingsBackup, String> {
+    let backup: SettingsBackup = serde_json::from_slice(contents).map_err(|e| e.to_string())?;
+    if backup.version > VERSION {
+        return Err(format!("version {} is newer than this manager understands ({})", backup.version, VERSION));
+    }
+    Ok(backup)
+}
+
+// Apply a backup: instances the user can see get its filters, chats and balance, missing ones are
+// added as the user's own (like /instance add), presets and aliases with the same name are replaced
+// and blacklisted requisites are added. The event feed and report chats are only taken from an
+// admin's import, as only admins set them
+pub fn import(state: &mut BotState, backup: SettingsBackup, user: Option<UserId>) -> Imported {
+    let mut imported = Imported::default();
+    for (name, settings) in backup.instances {
+        if !accounts::valid_name(&name) {
+            imported.invalid.push(name);
+            continue;
+        }
+        match state.instances.get_mut(&name) {
+            Some(instance) if instance.accessible_by(user) => {
+                instance.filters = settings.filters;
+                instance.allowed_chats = settings.allowed_chats;
+                if instance.balance != settings.balance {
+                    instance.balance_left = settings.balance;
+                }
+                instance.balance = settings.balance;
+                instance.balance_reset = settings.balance_reset;
+                imported.updated.push(name);
+            }
+            Some(_) => imported.skipped.push(name),
+            None => {
+                let instance = Instance {
+                    filters: settings.filters,
+                    allowed_chats: settings.allowed_chats,
+                    balance: settings.balance,
+                    balance_left: settings.balance,
+                    balance_reset: settings.balance_reset,
+                    session_dir: Some(crate::state::session_dir(&name)),
+                    owner: user.map(|user| user.0),
+                    ..Default::default()
+                };
+                state.instances.insert(name.clone(), instance);
+                imported.added.push(name);
+            }
+        }
+    }
+    imported.presets = backup.presets.len();
+    state.presets.extend(backup.presets);
+    state.blacklist.extend(backup.blacklist);
+    // The same checks as /alias
+    let commands = TelegramCommand::bot_commands();
+    state.aliases.extend(backup.aliases.into_iter().filter(|(name, command)| {
+        aliases::is_valid_name(name)
+            && command.starts_with('/')
+            && !commands.iter().any(|known| known.command.trim_start_matches('/') == name)
+    }));
+    if state.is_admin(user) {
+        if backup.event_feed_chat_id.is_some() {
+            state.event_feed_chat_id = backup.event_feed_chat_id;
+        }
+        if backup.report_chat_id.is_some() {
+            state.report_chat_id = backup.report_chat_id;
+        }
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN: UserId = UserId(1);
+
+    fn without_date(backup: &SettingsBackup) -> serde_json::Value {
+        let mut value = serde_json::to_value(backup).unwrap();
+        value.as_object_mut().unwrap().remove("exported_at");
+        value
+    }
+
+    #[test]
+    fn export_then_import_restores_everything() {
+        std::env::set_var("ALLOWED_USERS", ADMIN.0.to_string());
+
+        let mut state = BotState::default();
+        let mut filters = FilterSettings { min_amount: 7000, bank_filter: Some("sber".to_string()), ..Default::default() };
+        filters.emoji_tiers.insert(50000, "🔥".to_string());
+        let instance = Instance {
+            filters: filters.clone(),
+            allowed_chats: Some(vec![-100123]),
+            balance: Some(300000),
+            balance_reset: Some("09:00".to_string()),
+            ..Default::default()
+        };
+        state.instances.insert("main".to_string(), instance);
+        state.presets.insert("night".to_string(), filters);
+        state.blacklist.insert("+79001234567".to_string());
+        state.aliases.insert("a50".to_string(), "/amount 50000".to_string());
+        state.event_feed_chat_id = Some(-100456);
+        state.report_chat_id = Some(-100789);
+
+        let exported = export(&state, Some(ADMIN));
+        let backup = parse(&serde_json::to_vec(&exported).unwrap()).unwrap();
+        let mut restored = BotState::default();
+        let imported = import(&mut restored, backup, Some(ADMIN));
+        assert_eq!(imported.added, ["main"]);
+
+        assert_eq!(without_date(&export(&restored, Some(ADMIN))), without_date(&exported));
+        assert_eq!(restored.instances["main"].balance_left, Some(300000));
+
+        // Someone else's import adds their instances but leaves the feed and report chats alone
+        let mut other = BotState::default();
+        let mut backup = parse(&serde_json::to_vec(&exported).unwrap()).unwrap();
+        backup.instances.insert("../main".to_string(), InstanceSettings::default());
+        backup.aliases.insert("status".to_string(), "/stop".to_string());
+        let imported = import(&mut other, backup, Some(UserId(2)));
+        assert_eq!(imported.added, ["main"]);
+        assert_eq!(imported.invalid, ["../main"]);
+        assert_eq!(other.instances["main"].owner, Some(2));
+        assert!(!other.aliases.contains_key("status"));
+        assert_eq!(other.event_feed_chat_id, None);
+        assert_eq!(other.report_chat_id, None);
+    }
+}
@@ -0,0 +1,48 @@
+use std::{collections::BTreeSet, env};
+use log::info;
+
+use crate::storage;
+
+pub const DEFAULT_BLACKLIST_FILE: &str = "requisite_blacklist.json";
+
+// Get the blacklist file path from environment or use default
+fn blacklist_path() -> String {
+    env::var("REQUISITE_BLACKLIST_FILE").unwrap_or_else(|_| DEFAULT_BLACKLIST_FILE.to_string())
+}
+
+// Requisites (cards, phones) no instance reacts to, added with /blacklist; empty if the file is
+// missing or invalid
+pub fn load() -> BTreeSet<String> {
+    storage::load_json(&blacklist_path()).unwrap_or_default()
+}
+
+// Write the blacklist back to disk
+pub fn save(blacklist: &BTreeSet<String>) -> anyhow::Result<()> {
+    let path = blacklist_path();
+    storage::save_json(&path, blacklist)?;
+    info!("Saved {} blacklisted requisites to {}", blacklist.len(), path);
+    Ok(())
+}
+
+// The blacklisted entry for a requisite written in any spelling: the same digits ("8 900 123-45-67"
+// and "+79001234567" are one phone), or the same text when it has none
+pub fn find<'a>(blacklist: &'a BTreeSet<String>, requisite: &str) -> Option<&'a String> {
+    let wanted = key(requisite);
+    blacklist.iter().find(|entry| key(entry) == wanted)
+}
+
+// In the REQUISITE_BLACKLIST format understood by the reaction bot, which compares them the same way
+pub fn env(blacklist: &BTreeSet<String>) -> String {
+    blacklist.iter().map(String::as_str).collect::<Vec<_>>().join(",")
+}
+
+// Same as botdg-core's normalize::requisite_key
+fn key(requisite: &str) -> String {
+    let digits: String = requisite.chars().filter(char::is_ascii_digit).collect();
+    match digits.len() {
+        0 => requisite.trim().to_lowercase(),
+        11 if digits.starts_with('8') => format!("7{}", &digits[1..]),
+        10 if digits.starts_with('9') => format!("7{}", digits),
+        _ => digits,
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{history, i18n::{tr, Lang}};
+
+// /competitors looks back a week unless told otherwise
+pub const DEFAULT_PERIOD_SECS: i64 = 7 * 24 * 60 * 60;
+const TOP_COMPETITORS: usize = 5;
+const QUIET_HOURS: usize = 3;
+
+// Main competitors and the least contested hours of one instance's history since a Unix timestamp;
+// None if no competing reactions were recorded
+pub fn section(path: &str, since: i64, lang: Lang) -> rusqlite::Result<Option<String>> {
+    let competitors = history::competitors(path, since, TOP_COMPETITORS)?;
+    if competitors.is_empty() {
+        return Ok(None);
+    }
+
+    let mut text = lang.pick("Main competitors:", "Основные конкуренты:").to_string();
+    for competitor in &competitors {
+        text.push_str(&tr!(
+            lang,
+            "\n• {}: {} orders, reacts after {:.1} s on average, ahead of us {} times",
+            "\n• {}: {} заявок, реагирует в среднем через {:.1} с, опередил нас {} раз",
+            history::label(competitor.name.as_deref(), competitor.sender_id),
+            competitor.orders,
+            competitor.avg_delay_secs,
+            competitor.ahead_of_us
+        ));
+    }
+
+    let mut hours = history::competition_by_hour(path, since)?;
+    hours.sort_by(|a, b| a.avg_competitors.total_cmp(&b.avg_competitors).then(b.orders.cmp(&a.orders)));
+    if !hours.is_empty() {
+        text.push_str(lang.pick("\nLeast competition:", "\nМеньше всего конкурентов:"));
+        for hour in hours.iter().take(QUIET_HOURS) {
+            text.push_str(&tr!(
+                lang,
+                "\n• {:02}:00-{:02}:59: {:.1} competitors per order ({} orders)",
+                "\n• {:02}:00-{:02}:59: {:.1} конкурента на заявку ({} заявок)",
+                hour.hour,
+                hour.hour,
+                hour.avg_competitors,
+                hour.orders
+            ));
+        }
+    }
+    Ok(Some(text))
+}
@@ -0,0 +1,36 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_CONFIRM_TIMEOUT_SECS: u64 = 30;
+
+// How long a destructive command waits for /confirm: CONFIRM_TIMEOUT_SECS, 30 by default
+pub fn timeout() -> Duration {
+    let secs = std::env::var("CONFIRM_TIMEOUT_SECS").ok().and_then(|s| s.parse::<u64>().ok()).filter(|secs| *secs > 0);
+    Duration::from_secs(secs.unwrap_or(DEFAULT_CONFIRM_TIMEOUT_SECS))
+}
+
+// Destructive commands (/clear, /stop, /logout, /restore) waiting for their user's /confirm. One per user;
+// a newer one replaces it
+pub struct Confirmations<T> {
+    pending: HashMap<u64, (T, Instant)>,
+}
+
+impl<T> Default for Confirmations<T> {
+    fn default() -> Self {
+        Self { pending: HashMap::new() }
+    }
+}
+
+impl<T> Confirmations<T> {
+    pub fn ask(&mut self, user: u64, command: T) {
+        self.pending.insert(user, (command, Instant::now()));
+    }
+
+    // The user's command if it was asked for less than CONFIRM_TIMEOUT_SECS ago
+    pub fn take(&mut self, user: u64) -> Option<T> {
+        let (command, asked_at) = self.pending.remove(&user)?;
+        (asked_at.elapsed() < timeout()).then_some(command)
+    }
+}
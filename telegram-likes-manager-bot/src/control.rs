@@ -0,0 +1,642 @@
+use std::{
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use log::{info, warn};
+use serde_json::{json, Value};
+use teloxide::prelude::*;
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, process::Child, sync::{mpsc, oneshot, Mutex}};
+
+use crate::{history, i18n::{tr, Lang}, notify::Notifier, process, rpc, state::{self, BotState, Instance}, status_channel::StatusChannel, templates::Templates, webhook::Webhook};
+
+// A line written by a reaction bot to its stdout
+pub struct ChildEvent {
+    pub instance: String,
+    pub line: String,
+}
+
+// Admin notices about these events are alerts, also posted to Discord, Slack and NOTIFY_CHAT_IDS
+const ALERT_EVENTS: &[&str] = &["auth_error", "session_closed", "failover", "error_rate", "panic", "stalled", "disk"];
+
+// Where a relayed event should go
+enum Notice {
+    Admin(String),
+    Feed(String),
+}
+
+const DEFAULT_FEED_MAX_PER_MINUTE: u32 = 20;
+// How long `ask` waits for a reaction bot's answer
+const REPLY_TIMEOUT: Duration = Duration::from_secs(3);
+const FEED_WINDOW: Duration = Duration::from_secs(60);
+
+// Caps the live event feed so a burst of orders doesn't hit Telegram's flood limits
+struct FeedLimiter {
+    max_per_window: u32,
+    window_start: Instant,
+    sent: u32,
+    dropped: u32,
+}
+
+impl FeedLimiter {
+    fn from_env() -> Self {
+        Self {
+            max_per_window: env::var("EVENT_FEED_MAX_PER_MINUTE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_FEED_MAX_PER_MINUTE),
+            window_start: Instant::now(),
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    // Returns the text to send (noting any events dropped earlier), or None if over the limit
+    fn admit(&mut self, text: String, lang: Lang) -> Option<String> {
+        if self.window_start.elapsed() >= FEED_WINDOW {
+            self.window_start = Instant::now();
+            self.sent = 0;
+        }
+        if self.sent >= self.max_per_window {
+            self.dropped += 1;
+            return None;
+        }
+
+        self.sent += 1;
+        if self.dropped == 0 {
+            return Some(text);
+        }
+        let text = tr!(
+            lang,
+            "({} events skipped by the rate limit)\n{}",
+            "({} событий пропущено из-за ограничения частоты)\n{}",
+            self.dropped,
+            text
+        );
+        self.dropped = 0;
+        Some(text)
+    }
+}
+
+pub type EventSender = mpsc::UnboundedSender<ChildEvent>;
+pub type EventReceiver = mpsc::UnboundedReceiver<ChildEvent>;
+
+// Lines for a running bot's stdin, written by a task of its own so a bot that's slow to read
+// never holds up a handler. Dropping it closes the bot's stdin
+pub type ControlSender = mpsc::UnboundedSender<String>;
+
+pub fn channel() -> (EventSender, EventReceiver) {
+    mpsc::unbounded_channel()
+}
+
+// Hook up a freshly spawned child's stdin/stdout as its control channel
+// (after output::capture, so stdout lines also land in the run log)
+pub fn attach(name: &str, instance: &mut Instance, child: &mut Child, events: &EventSender) {
+    instance.control = child.stdin.take().map(|mut stdin| {
+        let (control, mut lines) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(line) = lines.recv().await {
+                if stdin.write_all(line.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+        control
+    });
+    instance.pending_auth = None;
+    instance.paused = false;
+
+    let Some(stdout) = child.stdout.take() else { return };
+    let name = name.to_string();
+    let events = events.clone();
+    let run_log = instance.run_log.clone();
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(log) = &run_log {
+                log.write_line("stdout", &line);
+            }
+            let event = ChildEvent { instance: name.clone(), line };
+            if events.send(event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+// Write a JSON command to an instance's control channel
+pub fn send_command(instance: &mut Instance, command: &Value) -> std::io::Result<()> {
+    let Some(control) = instance.control.as_ref() else {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotConnected, "no control channel"));
+    };
+    control
+        .send(format!("{}\n", command))
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the bot's stdin is closed"))
+}
+
+// Send a command and wait for the event that answers it, e.g. "recent" and "recent_reactions".
+// Asked over the instance's control socket when it has one (see rpc.rs), as a method with the
+// command's other fields for params, and over stdio otherwise. None when the instance isn't
+// running or doesn't answer within REPLY_TIMEOUT (a bot from before the command ignores it)
+pub async fn ask(bot_state: &Mutex<BotState>, name: &str, command: Value, reply: &str) -> Option<Value> {
+    if !bot_state.lock().await.instances.get(name).is_some_and(|instance| instance.is_running) {
+        return None;
+    }
+    if let Some(socket) = process::control_socket(name).filter(|socket| socket.exists()) {
+        let mut params = command.clone();
+        let method = params.as_object_mut().and_then(|params| params.remove("command"));
+        match rpc::call(&socket, method.as_ref().and_then(Value::as_str).unwrap_or_default(), params).await {
+            Ok(answer) => return Some(answer),
+            Err(e) => warn!("Failed to ask '{}' for {} over {}, trying stdio: {}", name, reply, socket.display(), e),
+        }
+    }
+
+    let (sender, receiver) = oneshot::channel();
+    {
+        let mut state = bot_state.lock().await;
+        let instance = state.instances.get_mut(name).filter(|instance| instance.is_running)?;
+        instance.waiting.insert(reply.to_string(), sender);
+        if let Err(e) = send_command(instance, &command) {
+            warn!("Failed to ask '{}' for {}: {}", name, reply, e);
+            instance.waiting.remove(reply);
+            return None;
+        }
+    }
+    tokio::time::timeout(REPLY_TIMEOUT, receiver).await.ok()?.ok()
+}
+
+// Engage or release an instance's kill switch: recorded here for the next start, and passed on
+// right away when it's running
+pub fn set_panic(name: &str, instance: &mut Instance, engaged: bool) -> std::io::Result<()> {
+    instance.panicked = engaged;
+    if !instance.is_running {
+        return Ok(());
+    }
+    info!("{} the kill switch of '{}'", if engaged { "Engaging" } else { "Releasing" }, name);
+    send_command(instance, &json!({ "command": if engaged { "panic" } else { "unpanic" } }))
+}
+
+// Relay an authorization value typed in Telegram back to the reaction bot
+pub fn send_auth_reply(instance: &mut Instance, kind: &str, value: &str) -> std::io::Result<()> {
+    // Login codes are sent with separators because Telegram invalidates codes
+    // that are shared verbatim in a chat
+    let value = if kind == "code" {
+        value.chars().filter(|c| c.is_ascii_digit()).collect()
+    } else {
+        value.trim().to_string()
+    };
+
+    send_command(instance, &json!({ "command": "auth_reply", "value": value }))?;
+    instance.pending_auth = None;
+    Ok(())
+}
+
+// Handle events from all reaction bots, relaying them to the instance's owner (or the admin chat) or the event feed
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>, mut events: EventReceiver, notifier: Notifier) {
+    let mut feed_limiter = FeedLimiter::from_env();
+    let webhook = Webhook::from_env();
+    let mut status_channel = StatusChannel::from_env();
+    let mut templates = Templates::from_env();
+
+    while let Some(ChildEvent { instance: name, line }) = events.recv().await {
+        // Anything that isn't a JSON event is plain program output
+        let Ok(event) = serde_json::from_str::<Value>(&line) else {
+            println!("[{}] {}", name, line);
+            continue;
+        };
+        webhook.forward(&name, &event);
+        // MATCH_TEMPLATE and the like word orders' notices (see templates.rs)
+        let templated = templates.render(&name, &event);
+        notifier.quiet().note_event(&name, &event);
+        if event["event"] == "match" {
+            notifier.matched(&name, &event, templated.clone());
+        }
+
+        let status_notice = match status_channel.chat() {
+            Some(chat) if event["event"] == "connection" => {
+                let lang = bot_state.lock().await.chat_language(Some(chat));
+                status_channel.connection_notice(&name, &event, lang)
+            }
+            _ => None,
+        };
+        if let Some((chat, text)) = status_notice {
+            if let Err(e) = bot.send_message(chat, text).await {
+                warn!("Failed to post status of '{}': {}", name, e);
+            }
+        }
+
+        let (chats, text) = {
+            let mut state = bot_state.lock().await;
+            let admin_chat = state.notify_chat(&name);
+            let admin_lang = state.chat_language(admin_chat);
+            let feed_lang = state.chat_language(state.event_feed_chat_id.map(ChatId));
+            let notice = match state.instances.get_mut(&name) {
+                Some(instance) => handle_event(&name, instance, &event, admin_lang, feed_lang),
+                None => None,
+            };
+            let notice = match (notice, templated) {
+                (Some(Notice::Feed(_)), Some(text)) => Some(Notice::Feed(text)),
+                (notice, _) => notice,
+            };
+            // The panic phrase sent in a chat stops the owner's other instances too
+            if event["event"] == "panic" && event["engaged"] == true && event["source"] == "chat" {
+                let owner = state.instances.get(&name).and_then(|instance| instance.owner);
+                for (other, instance) in state.instances.iter_mut().filter(|(other, instance)| **other != name && instance.owner == owner) {
+                    if let Err(e) = set_panic(other, instance, true) {
+                        warn!("Failed to engage the kill switch of '{}': {}", other, e);
+                    }
+                }
+            }
+            if event["event"] == "panic" || event["event"] == "watch" {
+                state.save();
+            }
+            match notice {
+                Some(Notice::Admin(text)) => {
+                    // Without a standby a closed session stops the reactions until someone logs in
+                    let alert = ALERT_EVENTS.iter().any(|alert| event["event"] == *alert);
+                    let critical = event["event"] == "session_closed" && event["standby"] != true;
+                    if critical {
+                        notifier.critical(&name, &text);
+                    } else if alert && notifier.quiet().hold_alert(&name, &text) {
+                        // Kept for the QUIET_HOURS digest
+                        continue;
+                    } else if alert {
+                        notifier.alert(&name, &text);
+                    }
+                    // Login prompts and the like are for whoever runs the instance
+                    let chats = if alert { state::with_broadcast(admin_chat) } else { Vec::from_iter(admin_chat) };
+                    (chats, Some(text))
+                }
+                // Quiet hours hold the whole feed; matches and outcomes are counted for the digest
+                Some(Notice::Feed(_)) if notifier.quiet().is_quiet() => (Vec::new(), None),
+                // Every instance's feed goes to the one chat admins pick with /events
+                Some(Notice::Feed(text)) => match state.event_feed_chat_id {
+                    Some(chat) => (vec![ChatId(chat)], feed_limiter.admit(text, feed_lang)),
+                    None => (Vec::new(), None),
+                },
+                None => (Vec::new(), None),
+            }
+        };
+
+        let Some(text) = text else { continue };
+        for chat in chats {
+            if let Err(e) = bot.send_message(chat, text.clone()).await {
+                warn!("Failed to relay event from '{}': {}", name, e);
+            }
+        }
+    }
+}
+
+// Where an order was reacted to: "Title (chat:message)" once the reaction bot knows the chat's title
+// (see its names.rs), the "chat:message" target until then
+pub fn place(event: &Value) -> String {
+    let target = event["target"].as_str().unwrap_or("?");
+    match event["chat_title"].as_str() {
+        Some(title) => format!("{} ({})", title, target),
+        None => target.to_string(),
+    }
+}
+
+// Update instance state for an event; returns a message for the admin chat or the event feed,
+// in the language of its destination
+
+fn handle_event(name: &str, instance: &mut Instance, event: &Value, admin: Lang, feed: Lang) -> Option<Notice> {
+    // Matches carry what's left of the working balance, kept for the bot's next start
+    if let Some(left) = event["balance_left"].as_i64() {
+        instance.balance_left = Some(left);
+    }
+    // An answer to `ask`
+    if let Some(waiting) = event["event"].as_str().and_then(|name| instance.waiting.remove(name)) {
+        let _ = waiting.send(event.clone());
+        return None;
+    }
+    match event["event"].as_str()? {
+        "auth_prompt" => {
+            let kind = event["kind"].as_str().unwrap_or_default();
+            info!("Reaction bot '{}' is waiting for {}", name, kind);
+            instance.pending_auth = Some(kind.to_string());
+            instance.last_status = tr!(admin, "Waiting for {}", "Ожидает {}", kind);
+
+            let text = match kind {
+                "phone_number" => tr!(
+                    admin,
+                    "🔐 Reaction bot '{}' needs the account phone number.\nReply with /auth +1234567890",
+                    "🔐 Боту реакций '{}' нужен номер телефона аккаунта.\nОтветьте командой /auth +79991234567",
+                    name
+                ),
+                "code" => tr!(
+                    admin,
+                    "🔐 Reaction bot '{}' needs the login code Telegram just sent.\nReply with the digits separated by spaces, e.g. /auth 1 2 3 4 5 (Telegram invalidates codes that are forwarded as-is).",
+                    "🔐 Боту реакций '{}' нужен код входа, который только что прислал Telegram.\nОтправьте цифры через пробел, например /auth 1 2 3 4 5 (Telegram аннулирует коды, пересланные как есть).",
+                    name
+                ),
+                "password" => tr!(
+                    admin,
+                    "🔐 Reaction bot '{}' needs the 2FA password.\nReply with /auth <password>; the message is deleted once relayed.",
+                    "🔐 Боту реакций '{}' нужен пароль 2FA.\nОтветьте командой /auth <пароль>; сообщение будет удалено после отправки.",
+                    name
+                ),
+                _ => tr!(
+                    admin,
+                    "🔐 Reaction bot '{}' asks: {}\nReply with /auth <value>",
+                    "🔐 Бот реакций '{}' запрашивает: {}\nОтветьте командой /auth <значение>",
+                    name,
+                    event["text"].as_str().unwrap_or(kind)
+                ),
+            };
+            Some(Notice::Admin(text))
+        }
+        "auth_error" => {
+            let message = event["message"].as_str().unwrap_or(admin.pick("unknown error", "неизвестная ошибка"));
+            Some(Notice::Admin(tr!(admin, "❌ Reaction bot '{}' authorization error: {}", "❌ Ошибка авторизации бота реакций '{}': {}", name, message)))
+        }
+        "authorized" => {
+            instance.pending_auth = None;
+            instance.last_status = admin.pick("Running", "Работает").to_string();
+            Some(Notice::Admin(tr!(admin, "✅ Reaction bot '{}' authorized and running.", "✅ Бот реакций '{}' авторизован и работает.", name)))
+        }
+        // With a standby session the bot doesn't log in again but keeps going on the standby
+        "session_closed" if event["standby"].as_bool() == Some(true) => {
+            warn!("Reaction bot '{}' lost its primary Telegram session, running on the standby", name);
+            instance.last_status = admin.pick("Running on the standby session", "Работает на резервной сессии").to_string();
+            Some(Notice::Admin(tr!(
+                admin,
+                "⚠️ Reaction bot '{}' was logged out of its primary Telegram session and keeps reacting through the standby session. Restart it with /restart to log the primary in again.",
+                "⚠️ Основная сессия Telegram бота реакций '{}' завершена, он продолжает работать через резервную. Перезапустите его командой /restart, чтобы снова войти в основную.",
+                name
+            )))
+        }
+        "failover" => {
+            let on_standby = event["active"] == "standby";
+            if on_standby {
+                warn!("Reaction bot '{}' switched to its standby TDLib client", name);
+            } else {
+                info!("Reaction bot '{}' is back on its primary TDLib client", name);
+            }
+            Some(Notice::Admin(if on_standby {
+                tr!(
+                    admin,
+                    "🔀 Reaction bot '{}': the primary TDLib client is down, reactions go through the standby session.",
+                    "🔀 Бот реакций '{}': основной клиент TDLib недоступен, реакции идут через резервную сессию.",
+                    name
+                )
+            } else {
+                tr!(admin, "✅ Reaction bot '{}' is back on the primary TDLib client.", "✅ Бот реакций '{}' снова работает через основной клиент TDLib.", name)
+            }))
+        }
+        "session_closed" => {
+            warn!("Reaction bot '{}' lost its Telegram session", name);
+            instance.last_status = admin.pick("Logging in again", "Повторный вход").to_string();
+            Some(Notice::Admin(tr!(
+                admin,
+                "⚠️ Reaction bot '{}' was logged out of Telegram (the session was terminated or logged out elsewhere). It is logging in again; answer its prompts with /auth.",
+                "⚠️ Бот реакций '{}' вышел из Telegram (сессия завершена или выполнен выход на другом устройстве). Он входит заново; отвечайте на его запросы командой /auth.",
+                name
+            )))
+        }
+        "paused" => {
+            instance.paused = true;
+            instance.last_status = admin.pick("Paused", "Приостановлен").to_string();
+            Some(Notice::Admin(tr!(admin, "⏸ Reaction bot '{}' paused.", "⏸ Бот реакций '{}' приостановлен.", name)))
+        }
+        "resumed" => {
+            instance.paused = false;
+            instance.last_status = admin.pick("Running", "Работает").to_string();
+            Some(Notice::Admin(tr!(admin, "▶️ Reaction bot '{}' resumed.", "▶️ Бот реакций '{}' возобновил работу.", name)))
+        }
+        "panic" => {
+            let engaged = event["engaged"].as_bool().unwrap_or(true);
+            instance.panicked = engaged;
+            if !engaged {
+                info!("Reaction bot '{}' kill switch released", name);
+                instance.last_status = admin.pick("Running", "Работает").to_string();
+                return Some(Notice::Admin(tr!(admin, "✅ Reaction bot '{}' is reacting again.", "✅ Бот реакций '{}' снова ставит реакции.", name)));
+            }
+            warn!("Reaction bot '{}' kill switch engaged", name);
+            instance.last_status = admin.pick("Kill switch engaged", "Аварийная остановка").to_string();
+            Some(Notice::Admin(match event["chat_id"].as_i64() {
+                Some(chat) => tr!(
+                    admin,
+                    "🛑 Reaction bot '{}' stopped all reactions: the panic phrase was sent in chat {}. Nothing is reacted to until /panic off.",
+                    "🛑 Бот реакций '{}' остановил все реакции: в чате {} отправлена аварийная фраза. Реакций не будет до /panic off.",
+                    name,
+                    chat
+                ),
+                None => tr!(
+                    admin,
+                    "🛑 Reaction bot '{}' stopped all reactions. Nothing is reacted to until /panic off.",
+                    "🛑 Бот реакций '{}' остановил все реакции. Реакций не будет до /panic off.",
+                    name
+                ),
+            }))
+        }
+        // The owner's /watch in a chat: from now on the instance has its own list of chats, so the
+        // change outlives a restart
+        "watch" => {
+            let chat = event["chat_id"].as_i64()?;
+            let watching = event["watching"].as_bool().unwrap_or(true);
+            let mut chats = instance.allowed_chats.clone().unwrap_or_else(|| {
+                let global = env::var("ALLOWED_CHAT_IDS").unwrap_or_default();
+                global.split(',').filter_map(|id| id.trim().parse().ok()).collect()
+            });
+            chats.retain(|&id| id != chat);
+            if watching {
+                chats.push(chat);
+            }
+            instance.allowed_chats = Some(chats);
+            let chat = history::label(event["chat_title"].as_str(), chat);
+            Some(Notice::Admin(if watching {
+                tr!(admin, "👀 Reaction bot '{}' now watches chat {} (/watch from the owner).", "👀 Бот реакций '{}' теперь отслеживает чат {} (/watch от владельца).", name, chat)
+            } else {
+                tr!(admin, "🙈 Reaction bot '{}' no longer watches chat {} (/watch from the owner).", "🙈 Бот реакций '{}' больше не отслеживает чат {} (/watch от владельца).", name, chat)
+            }))
+        }
+        "balance" => {
+            instance.balance_left = event["left"].as_i64();
+            let text = match (event["total"].as_i64(), event["reason"].as_str()) {
+                (Some(total), Some("reset")) => tr!(
+                    admin,
+                    "💰 Reaction bot '{}': working balance refilled to {} ₽.",
+                    "💰 Бот реакций '{}': рабочий баланс пополнен до {} ₽.",
+                    name,
+                    total
+                ),
+                (Some(total), _) => tr!(admin, "💰 Reaction bot '{}': working balance is {} ₽.", "💰 Бот реакций '{}': рабочий баланс {} ₽.", name, total),
+                (None, _) => tr!(admin, "💰 Reaction bot '{}': working balance is off.", "💰 Бот реакций '{}': рабочий баланс отключен.", name),
+            };
+            Some(Notice::Admin(text))
+        }
+        // The bot keeps running off hours, only its reactions stop
+        "working_hours" => {
+            let open = event["open"].as_bool().unwrap_or(true);
+            info!("Reaction bot '{}' {} working hours", name, if open { "entered" } else { "left" });
+            instance.last_status = if open { admin.pick("Running", "Работает") } else { admin.pick("Off hours", "Вне рабочего времени") }.to_string();
+            Some(Notice::Admin(if open {
+                tr!(admin, "🕘 Reaction bot '{}': working hours started, reacting again.", "🕘 Бот реакций '{}': рабочее время началось, реакции снова ставятся.", name)
+            } else {
+                tr!(admin, "🌙 Reaction bot '{}': working hours are over, no reactions until they start.", "🌙 Бот реакций '{}': рабочее время закончилось, реакции не ставятся до его начала.", name)
+            }))
+        }
+        "backfill" => {
+            let count = |key: &str| event[key].as_u64().unwrap_or_default();
+            let open = if event["reacted"].as_bool().unwrap_or(false) {
+                tr!(admin, "{} still open, reacted to", "{} еще открыты, реакции поставлены", count("open"))
+            } else {
+                tr!(admin, "{} still open (BACKFILL_REACT is off)", "{} еще открыты (BACKFILL_REACT выключен)", count("open"))
+            };
+            Some(Notice::Admin(tr!(
+                admin,
+                "🔎 Reaction bot '{}' read {} recent messages, {} matched the filters: {}; {} too old, {} already reacted to.",
+                "🔎 Бот реакций '{}' прочитал {} последних сообщений, {} подходят под фильтры: {}; {} слишком старые, на {} уже есть реакции.",
+                name,
+                count("fetched"),
+                count("matched"),
+                open,
+                count("stale"),
+                count("taken")
+            )))
+        }
+        "stalled" => {
+            let silent = event["silent_secs"].as_u64().unwrap_or_default();
+            warn!("Reaction bot '{}' got no updates for {} s, restarting its TDLib client", name, silent);
+            Some(Notice::Admin(tr!(
+                admin,
+                "🔄 Reaction bot '{}' got nothing from Telegram for {} s although connected, and restarted its TDLib client.",
+                "🔄 Бот реакций '{}' {} с не получал ничего от Telegram при активном соединении и перезапустил клиент TDLib.",
+                name,
+                silent
+            )))
+        }
+        "disk" => {
+            let mb = |key: &str| event[key].as_u64().map(|bytes| bytes / (1024 * 1024));
+            let used = mb("used_bytes").unwrap_or_default();
+            let free = match mb("free_bytes") {
+                Some(free) => tr!(admin, "{} MB", "{} МБ", free),
+                None => admin.pick("unknown", "неизвестно").to_string(),
+            };
+            let text = if event["alerting"].as_bool().unwrap_or(false) {
+                warn!("Reaction bot '{}' is running out of disk space: TDLib takes {} MB, {} free", name, used, free);
+                let trimmed = match mb("limit_bytes").filter(|limit| used > *limit) {
+                    Some(limit) => tr!(admin, " That's over the {} MB limit, so old files are being removed.", " Это больше лимита {} МБ, поэтому старые файлы удаляются.", limit),
+                    None => String::new(),
+                };
+                tr!(
+                    admin,
+                    "💾 Reaction bot '{}': TDLib data takes {} MB, free disk space: {}.{}",
+                    "💾 Бот реакций '{}': данные TDLib занимают {} МБ, свободно на диске: {}.{}",
+                    name,
+                    used,
+                    free,
+                    trimmed
+                )
+            } else {
+                tr!(
+                    admin,
+                    "✅ Reaction bot '{}': disk usage is back to normal (TDLib {} MB, free: {}).",
+                    "✅ Бот реакций '{}': место на диске снова в норме (TDLib {} МБ, свободно: {}).",
+                    name,
+                    used,
+                    free
+                )
+            };
+            Some(Notice::Admin(text))
+        }
+        "error_rate" => {
+            let failed = event["failed"].as_u64().unwrap_or_default();
+            let total = event["total"].as_u64().unwrap_or_default();
+            let window = event["window_secs"].as_u64().unwrap_or_default();
+            let text = if event["alerting"].as_bool().unwrap_or(false) {
+                warn!("Reaction bot '{}' TDLib error rate is high: {} of {} requests", name, failed, total);
+                tr!(
+                    admin,
+                    "🚨 Reaction bot '{}': {} of {} TDLib requests failed in the last {} s. Sustained errors usually mean the reaction request format doesn't match the installed TDLib.",
+                    "🚨 Бот реакций '{}': {} из {} запросов к TDLib завершились ошибкой за последние {} с. Постоянные ошибки обычно означают, что формат запроса реакции не подходит к установленной версии TDLib.",
+                    name,
+                    failed,
+                    total,
+                    window
+                )
+            } else {
+                tr!(
+                    admin,
+                    "✅ Reaction bot '{}': TDLib error rate is back to normal ({} of {} requests failed).",
+                    "✅ Бот реакций '{}': доля ошибок TDLib снова в норме ({} из {} запросов с ошибкой).",
+                    name,
+                    failed,
+                    total
+                )
+            };
+            Some(Notice::Admin(text))
+        }
+        // SCAM_ACTION=notify: an order the bot didn't react to because it looks fake
+        "suspicious" => {
+            let checks = event["checks"]
+                .as_array()
+                .map(|checks| {
+                    checks
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(|check| match check {
+                            "new_sender" => admin.pick("first order from this sender", "первая заявка от этого отправителя").to_string(),
+                            "bank_conflict" => admin.pick("requisite seen with another bank", "реквизит раньше был с другим банком").to_string(),
+                            "commission" => tr!(admin, "commission {}% is too high", "комиссия {}% слишком высокая", event["commission"]),
+                            other => other.to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            Some(Notice::Admin(tr!(
+                admin,
+                "🕵️ Reaction bot '{}' didn't react to order {}:{} ({} ₽, bank {}): {}.",
+                "🕵️ Бот реакций '{}' не стал реагировать на заявку {}:{} ({} ₽, банк {}): {}.",
+                name,
+                event["chat_id"],
+                event["message_id"],
+                event["amount"].as_i64().map_or("?".to_string(), |amount| amount.to_string()),
+                event["bank"].as_str().unwrap_or("?"),
+                checks
+            )))
+        }
+        "match" => Some(Notice::Feed(tr!(
+            feed,
+            "🎯 [{}] Match {}: {} ₽, bank {}",
+            "🎯 [{}] Заявка {}: {} ₽, банк {}",
+            name,
+            place(event),
+            event["price"].as_i64().map_or("?".to_string(), |p| p.to_string()),
+            event["bank"].as_str().unwrap_or(feed.pick("unknown", "неизвестен"))
+        ))),
+        "reaction_sent" => Some(Notice::Feed(tr!(
+            feed,
+            "👍 [{}] Reaction sent to {}",
+            "👍 [{}] Реакция отправлена на {}",
+            name,
+            event["target"].as_str().unwrap_or("?")
+        ))),
+        "reaction_failed" => Some(Notice::Feed(tr!(
+            feed,
+            "❌ [{}] Reaction to {} failed: {}",
+            "❌ [{}] Не удалось поставить реакцию на {}: {}",
+            name,
+            event["target"].as_str().unwrap_or("?"),
+            event["message"].as_str().unwrap_or(feed.pick("unknown error", "неизвестная ошибка"))
+        ))),
+        "reaction_unconfirmed" => Some(Notice::Feed(tr!(
+            feed,
+            "⏳ [{}] No confirmation for the reaction to {}",
+            "⏳ [{}] Реакция на {} не подтверждена",
+            name,
+            event["target"].as_str().unwrap_or("?")
+        ))),
+        "connection" => Some(Notice::Feed(tr!(
+            feed,
+            "🔌 [{}] Connection state: {}",
+            "🔌 [{}] Состояние соединения: {}",
+            name,
+            event["state"].as_str().unwrap_or(feed.pick("unknown", "неизвестно"))
+        ))),
+        _ => None,
+    }
+}
@@ -0,0 +1,86 @@
+use std::{env, sync::Arc, time::Duration};
+use botdg_config::timezone;
+use log::{info, warn};
+use teloxide::{prelude::*, types::MessageId, ApiError, RequestError};
+use tokio::sync::Mutex;
+
+use crate::{history, i18n::{tr, Lang}, state::BotState};
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+// How often the pinned dashboard is edited, from DASHBOARD_INTERVAL_SECS
+fn interval() -> Duration {
+    let secs = env::var("DASHBOARD_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+// Status, filters and counters since midnight in TIMEZONE of every instance `user` can see
+pub fn build(state: &BotState, user: Option<UserId>, lang: Lang) -> String {
+    let midnight = timezone::midnight();
+    let mut text = lang.pick("📌 Dashboard", "📌 Панель").to_string();
+
+    for (name, instance) in state.visible_to(user) {
+        let path = instance.history_path(name);
+        let today = match history::readable(&path).then(|| history::summary(&path, midnight)) {
+            Some(Ok(summary)) => tr!(
+                lang,
+                "Today: {} matches (confirmed {}, failed {}), {} ₽",
+                "Сегодня: {} заявок (подтверждено {}, не удалось {}), {} ₽",
+                summary.matches,
+                summary.confirmed,
+                summary.failed,
+                summary.volume
+            ),
+            Some(Err(e)) => tr!(lang, "Today: failed to read the history: {}", "Сегодня: не удалось прочитать историю: {}", e),
+            None => lang.pick("Today: no matches", "Сегодня: заявок не было").to_string(),
+        };
+        text.push_str(&format!("\n\n{}: {}\n{}\n{}", name, instance.status_icon(lang), instance.filters.describe(lang), today));
+    }
+
+    text.push_str(&tr!(lang, "\n\nUpdated {}", "\n\nОбновлено {}", timezone::now().format("%H:%M:%S")));
+    text
+}
+
+// Post the dashboard to a chat and pin it, quietly
+pub async fn post(bot: &Bot, chat: ChatId, text: String) -> Result<MessageId, RequestError> {
+    let message = bot.send_message(chat, text).await?;
+    bot.pin_chat_message(chat, message.id).disable_notification(true).await?;
+    Ok(message.id)
+}
+
+// Edit the /dashboard message in place every DASHBOARD_INTERVAL_SECS; if someone deleted it, post
+// and pin a new one
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>) {
+    let interval = interval();
+    info!("Updating the dashboard every {} s", interval.as_secs());
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let (chat, message, text) = {
+            let state = bot_state.lock().await;
+            let Some((chat, message)) = state.dashboard else { continue };
+            (ChatId(chat), MessageId(message), build(&state, state.dashboard_user.map(UserId), state.chat_language(Some(ChatId(chat)))))
+        };
+
+        match bot.edit_message_text(chat, message, text.clone()).await {
+            Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {}
+            Err(RequestError::Api(ApiError::MessageToEditNotFound)) => match post(&bot, chat, text).await {
+                Ok(message) => {
+                    let mut state = bot_state.lock().await;
+                    // Unless /dashboard was turned off or moved in the meantime
+                    if state.dashboard.is_some_and(|(dashboard_chat, _)| dashboard_chat == chat.0) {
+                        state.dashboard = Some((chat.0, message.0));
+                        state.save();
+                    }
+                }
+                Err(e) => warn!("Failed to post the dashboard again: {}", e),
+            },
+            Err(e) => warn!("Failed to update the dashboard: {}", e),
+        }
+    }
+}
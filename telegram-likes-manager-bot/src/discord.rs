@@ -0,0 +1,77 @@
+use std::env;
+use serde_json::{json, Value};
+
+use crate::{control, i18n::{tr, Lang}, notify::{self, Notification, Sink}};
+
+// Embed colors
+const MATCH_COLOR: u32 = 0x2ECC71;
+const ALERT_COLOR: u32 = 0xE67E22;
+const CRITICAL_COLOR: u32 = 0xE74C3C;
+
+// Posts embeds to a Discord channel through DISCORD_WEBHOOK_URL, for teams whose ops chat lives
+// in Discord
+pub struct DiscordSink {
+    url: String,
+    client: reqwest::Client,
+    lang: Lang,
+}
+
+impl DiscordSink {
+    pub fn from_env(lang: Lang) -> Option<Self> {
+        let url = env::var("DISCORD_WEBHOOK_URL").ok().filter(|url| !url.is_empty())?;
+        Some(Self { url, client: notify::http_client(), lang })
+    }
+
+    fn embed(&self, notification: &Notification) -> Value {
+        let lang = self.lang;
+        match notification {
+            Notification::Match { instance, text: Some(text), .. } => json!({
+                "title": tr!(lang, "🎯 Match on '{}'", "🎯 Заявка на '{}'", instance),
+                "description": text,
+                "color": MATCH_COLOR,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            }),
+            Notification::Match { instance, event, text: None } => {
+                let amount = event["price"].as_i64().map_or("?".to_string(), |p| format!("{} ₽", p));
+                let bank = event["bank"].as_str().unwrap_or(lang.pick("unknown", "неизвестен"));
+                let latency = event["latency_us"]
+                    .as_u64()
+                    .map_or("?".to_string(), |us| format!("{:.2} ms", us as f64 / 1000.0));
+                json!({
+                    "title": tr!(lang, "🎯 Match on '{}'", "🎯 Заявка на '{}'", instance),
+                    "color": MATCH_COLOR,
+                    "fields": [
+                        { "name": lang.pick("Amount", "Сумма"), "value": amount, "inline": true },
+                        { "name": lang.pick("Bank", "Банк"), "value": bank, "inline": true },
+                        { "name": lang.pick("Latency", "Задержка"), "value": latency, "inline": true },
+                        { "name": lang.pick("Message", "Сообщение"), "value": control::place(event), "inline": false },
+                    ],
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                })
+            }
+            Notification::Digest { text } => json!({
+                "title": lang.pick("Quiet hours digest", "Сводка за тихие часы"),
+                "description": text,
+                "color": ALERT_COLOR,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            }),
+            Notification::Alert { instance, text, critical } => json!({
+                "title": tr!(lang, "Reaction bot '{}'", "Бот реакций '{}'", instance),
+                "description": text,
+                "color": if *critical { CRITICAL_COLOR } else { ALERT_COLOR },
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            }),
+        }
+    }
+}
+
+impl Sink for DiscordSink {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    async fn send(&mut self, notification: &Notification) {
+        let body = json!({ "embeds": [self.embed(notification)] });
+        notify::post_json(&self.client, &self.url, &body, self.name()).await;
+    }
+}
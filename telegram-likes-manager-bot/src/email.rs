@@ -0,0 +1,118 @@
+use std::{env, time::Duration};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use log::warn;
+
+use crate::{i18n::{tr, Lang}, notify::{Notification, Sink}};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+// Emails critical failures over SMTP: a channel that still works when Telegram itself is what's broken
+pub struct EmailSink {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    lang: Lang,
+}
+
+impl EmailSink {
+    // SMTP_HOST enables email. SMTP_TLS is "starttls" (default, port 587), "tls" (port 465) or
+    // "none" (port 25, local relays only); SMTP_PORT overrides the port. SMTP_USERNAME and
+    // SMTP_PASSWORD log in, SMTP_FROM and SMTP_TO (comma separated) address the message
+    pub fn from_env(lang: Lang) -> Option<Self> {
+        let host = env::var("SMTP_HOST").ok().filter(|host| !host.is_empty())?;
+        match Self::build(&host, lang) {
+            Ok(sink) => Some(sink),
+            Err(e) => {
+                warn!("Email alerts disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    fn build(host: &str, lang: Lang) -> Result<Self, String> {
+        let tls = env::var("SMTP_TLS").unwrap_or_default().to_lowercase();
+        let mut transport = match tls.as_str() {
+            "" | "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(|e| e.to_string())?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(|e| e.to_string())?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            other => return Err(format!("unknown SMTP_TLS '{}' (starttls, tls or none)", other)),
+        };
+        if let Some(port) = env::var("SMTP_PORT").ok().and_then(|port| port.parse().ok()) {
+            transport = transport.port(port);
+        }
+        if let Some(username) = env::var("SMTP_USERNAME").ok().filter(|username| !username.is_empty()) {
+            transport = transport.credentials(Credentials::new(username, env::var("SMTP_PASSWORD").unwrap_or_default()));
+        }
+
+        let from = env::var("SMTP_FROM")
+            .map_err(|_| "SMTP_FROM is not set".to_string())?
+            .parse::<Mailbox>()
+            .map_err(|e| format!("invalid SMTP_FROM: {}", e))?;
+        let to = env::var("SMTP_TO")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(|address| address.parse::<Mailbox>().map_err(|e| format!("invalid SMTP_TO address '{}': {}", address, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if to.is_empty() {
+            return Err("SMTP_TO is not set".to_string());
+        }
+
+        Ok(Self { transport: transport.build(), from, to, lang })
+    }
+
+    fn message(&self, subject: String, body: String) -> Result<Message, lettre::error::Error> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject).header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        builder.body(body)
+    }
+}
+
+impl Sink for EmailSink {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&mut self, notification: &Notification) {
+        let lang = self.lang;
+        let (subject, body) = match notification {
+            Notification::Alert { instance, text, .. } => (
+                tr!(lang, "[botdg] Reaction bot '{}' needs attention", "[botdg] Боту реакций '{}' нужно внимание", instance),
+                format!("{}\n\n{}", text, chrono::Utc::now().to_rfc3339()),
+            ),
+            Notification::Digest { text } => (
+                lang.pick("[botdg] Quiet hours digest", "[botdg] Сводка за тихие часы").to_string(),
+                text.clone(),
+            ),
+            Notification::Match { instance, event, text } => (
+                tr!(lang, "[botdg] Match on '{}'", "[botdg] Заявка на '{}'", instance),
+                text.clone().unwrap_or_else(|| event.to_string()),
+            ),
+        };
+        let message = match self.message(subject, body) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to build an alert email: {}", e);
+                return;
+            }
+        };
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            match self.transport.send(message.clone()).await {
+                Ok(_) => return,
+                Err(e) => warn!("Failed to send an alert email (attempt {}): {}", attempt, e),
+            }
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(Duration::from_secs(5 * attempt as u64)).await;
+            }
+        }
+        warn!("Giving up on an alert email after {} attempts", MAX_ATTEMPTS);
+    }
+}
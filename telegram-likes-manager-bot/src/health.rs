@@ -0,0 +1,268 @@
+use std::{env, sync::Arc, time::Duration};
+use log::{info, warn};
+use serde::Deserialize;
+use teloxide::prelude::*;
+use tokio::sync::Mutex;
+
+use crate::{i18n::{tr, Lang}, notify::Notifier, process, state::{BotState, Instance}, stats::FilterStats};
+
+pub const DEFAULT_HEARTBEAT_DIR: &str = "heartbeats";
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 10;
+const DEFAULT_HEALTH_TIMEOUT_SECS: i64 = 60;
+const CONNECTION_READY: &str = "connectionStateReady";
+// How long an instance may stay unhealthy before a critical alert (see notify.rs)
+const DEFAULT_CRITICAL_AFTER_MINUTES: i64 = 15;
+
+// Heartbeat file written by the reaction bot (older builds don't report their start time or versions)
+#[derive(Deserialize)]
+pub struct Heartbeat {
+    pub timestamp: i64,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    pub connection_state: String,
+    pub connection_changed_at: i64,
+    #[serde(default)]
+    pub started_at: Option<i64>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub tdlib_version: Option<String>,
+    #[serde(default)]
+    pub filter_stats: Option<FilterStats>,
+    #[serde(default)]
+    pub disk_usage: Option<DiskUsage>,
+    #[serde(default)]
+    pub backlog: Option<Backlog>,
+}
+
+// Size of the bot's TDLib directories, measured every few minutes
+#[derive(Deserialize)]
+pub struct DiskUsage {
+    pub data_bytes: u64,
+    pub files_bytes: u64,
+    pub free_bytes: Option<u64>,
+    pub limit_bytes: Option<u64>,
+}
+
+impl DiskUsage {
+    // One-line summary for /status
+    pub fn describe(&self, lang: Lang) -> String {
+        let mb = |bytes: u64| bytes / (1024 * 1024);
+        let mut line = tr!(
+            lang,
+            "Disk: TDLib {} MB (database {} MB, files {} MB)",
+            "Диск: TDLib {} МБ (база {} МБ, файлы {} МБ)",
+            mb(self.data_bytes + self.files_bytes),
+            mb(self.data_bytes),
+            mb(self.files_bytes)
+        );
+        if let Some(limit) = self.limit_bytes {
+            line += &tr!(lang, " of {} MB", " из {} МБ", mb(limit));
+        }
+        if let Some(free) = self.free_bytes {
+            line += &tr!(lang, ", {} MB free", ", свободно {} МБ", mb(free));
+        }
+        line
+    }
+}
+
+// How far behind the bot's receive loop runs, since its previous heartbeat
+#[derive(Deserialize)]
+pub struct Backlog {
+    pub per_receive: f64,
+    pub max_per_receive: u64,
+    pub lag_ms: u64,
+    pub max_lag_ms: u64,
+}
+
+impl Backlog {
+    // One-line summary for /status
+    pub fn describe(&self, lang: Lang) -> String {
+        tr!(
+            lang,
+            "Queue: {} updates per receive (up to {}), oldest waiting {} ms (up to {} ms)",
+            "Очередь: {} обновлений за приём (до {}), самое старое ждёт {} мс (до {} мс)",
+            self.per_receive,
+            self.max_per_receive,
+            self.lag_ms,
+            self.max_lag_ms
+        )
+    }
+}
+
+// Last known health of an instance (not persisted)
+#[derive(Default)]
+pub struct HealthState {
+    pub unhealthy_reason: Option<String>,
+    pub unhealthy_since: Option<i64>,
+    pub critical_sent: bool,
+    pub last_heartbeat: Option<i64>,
+    pub connection_state: Option<String>,
+}
+
+impl HealthState {
+    // One-line summary for /status
+    pub fn describe(&self, lang: Lang) -> String {
+        let heartbeat = match self.last_heartbeat {
+            Some(ts) => tr!(lang, "last heartbeat {} s ago", "последний heartbeat {} с назад", chrono::Utc::now().timestamp() - ts),
+            None => lang.pick("no heartbeat yet", "heartbeat еще не получен").to_string(),
+        };
+        let connection = self.connection_state.as_deref().unwrap_or(lang.pick("unknown", "неизвестно"));
+
+        match &self.unhealthy_reason {
+            Some(reason) => tr!(lang, "⚠️ Unhealthy: {} ({}, connection: {})", "⚠️ Проблема: {} ({}, соединение: {})", reason, heartbeat, connection),
+            None => tr!(lang, "✅ Healthy ({}, connection: {})", "✅ В порядке ({}, соединение: {})", heartbeat, connection),
+        }
+    }
+}
+
+// Get the directory for heartbeat files from environment or use default
+pub fn heartbeat_dir() -> String {
+    env::var("HEARTBEAT_DIR").unwrap_or_else(|_| DEFAULT_HEARTBEAT_DIR.to_string())
+}
+
+fn health_timeout() -> i64 {
+    env::var("HEALTH_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_HEALTH_TIMEOUT_SECS)
+}
+
+pub fn read_heartbeat(instance: &Instance, name: &str) -> Option<Heartbeat> {
+    let contents = std::fs::read_to_string(instance.heartbeat_path(name)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// PID of a reaction bot we lost the handle to (e.g. after a manager restart), taken from a recent
+// heartbeat, as long as the process is still that instance's bot (see process::is_instance)
+pub fn live_pid(instance: &Instance, name: &str) -> Option<u32> {
+    let heartbeat = read_heartbeat(instance, name)?;
+    (chrono::Utc::now().timestamp() - heartbeat.timestamp <= health_timeout())
+        .then_some(heartbeat.pid)
+        .flatten()
+        .filter(|&pid| process::is_instance(pid, name))
+}
+
+// Periodically read every running instance's heartbeat and alert on health changes
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>, notifier: Notifier) {
+    let check_interval = env::var("HEALTH_CHECK_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    let timeout = health_timeout();
+    let critical_after = env::var("CRITICAL_UNHEALTHY_MINUTES")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_CRITICAL_AFTER_MINUTES)
+        * 60;
+
+    info!("Health checks every {} s, heartbeat timeout {} s", check_interval, timeout);
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(check_interval)).await;
+
+        let notifications = {
+            let mut state = bot_state.lock().await;
+            let chats = state.notify_chats();
+            state.instances
+                .iter_mut()
+                .filter_map(|(name, instance)| {
+                    let (targets, lang) = &chats[name];
+                    let (text, critical) = check(name, instance, timeout, critical_after, *lang)?;
+                    if critical {
+                        notifier.critical(name, &text);
+                    } else if notifier.quiet().hold_alert(name, &text) {
+                        return None;
+                    } else {
+                        notifier.alert(name, &text);
+                    }
+                    Some((targets.clone(), text))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (chats, text) in notifications {
+            for chat in chats {
+                if let Err(e) = bot.send_message(chat, text.clone()).await {
+                    warn!("Failed to send health notification: {}", e);
+                }
+            }
+        }
+    }
+}
+
+// Re-evaluate an instance's health; returns a notification when it changes, or a critical one
+// once it has been unhealthy for `critical_after` seconds
+fn check(name: &str, instance: &mut Instance, timeout: i64, critical_after: i64, lang: Lang) -> Option<(String, bool)> {
+    if !instance.is_running || instance.reaction_bot_process.is_none() {
+        instance.health = HealthState::default();
+        return None;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let heartbeat = read_heartbeat(instance, name);
+
+    let reason = match &heartbeat {
+        None => {
+            let running_for = instance.supervisor.started_at.map_or(0, |t| t.elapsed().as_secs() as i64);
+            (running_for > timeout)
+                .then(|| tr!(
+                    lang,
+                    "no heartbeat received in {} s since start (stuck or waiting for login input?)",
+                    "нет heartbeat {} с после запуска (завис или ждет данных для входа?)",
+                    running_for
+                ))
+        }
+        Some(hb) if now - hb.timestamp > timeout => {
+            Some(tr!(lang, "no heartbeat for {} s", "нет heartbeat {} с", now - hb.timestamp))
+        }
+        Some(hb) if hb.connection_state != CONNECTION_READY
+            && hb.connection_state != "unknown"
+            && now - hb.connection_changed_at > timeout =>
+        {
+            Some(tr!(lang, "TDLib connection is {} for {} s", "соединение TDLib в состоянии {} уже {} с", hb.connection_state, now - hb.connection_changed_at))
+        }
+        Some(_) => None,
+    };
+
+    if let Some(hb) = heartbeat {
+        instance.health.last_heartbeat = Some(hb.timestamp);
+        instance.health.connection_state = Some(hb.connection_state);
+    }
+
+    let was_healthy = instance.health.unhealthy_reason.is_none();
+    instance.health.unhealthy_reason = reason.clone();
+
+    match (was_healthy, reason) {
+        (true, Some(reason)) => {
+            warn!("Reaction bot '{}' is unhealthy: {}", name, reason);
+            instance.health.unhealthy_since = Some(now);
+            Some((tr!(lang, "🩺 Reaction bot '{}' is unhealthy: {}", "🩺 Проблема с ботом реакций '{}': {}", name, reason), false))
+        }
+        (false, None) => {
+            info!("Reaction bot '{}' is healthy again", name);
+            instance.health.unhealthy_since = None;
+            instance.health.critical_sent = false;
+            Some((tr!(lang, "✅ Reaction bot '{}' is healthy again.", "✅ Бот реакций '{}' снова в порядке.", name), false))
+        }
+        (false, Some(reason)) => {
+            let since = *instance.health.unhealthy_since.get_or_insert(now);
+            if instance.health.critical_sent || now - since < critical_after {
+                return None;
+            }
+            warn!("Reaction bot '{}' has been unhealthy for {} s: {}", name, now - since, reason);
+            instance.health.critical_sent = true;
+            Some((tr!(
+                lang,
+                "🚨 Reaction bot '{}' has been unhealthy for {} min and is probably not receiving updates: {}",
+                "🚨 Бот реакций '{}' неисправен уже {} мин и, вероятно, не получает обновления: {}",
+                name,
+                (now - since) / 60,
+                reason
+            ), true))
+        }
+        (true, None) => None,
+    }
+}
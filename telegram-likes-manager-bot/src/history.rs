@@ -0,0 +1,492 @@
+use std::{collections::HashMap, env};
+use botdg_config::timezone;
+use rusqlite::{Connection, OpenFlags, Row};
+use serde::Serialize;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::i18n::{tr, Lang};
+
+pub const DEFAULT_HISTORY_DIR: &str = "history";
+pub const DEFAULT_LIST_LIMIT: usize = 20;
+pub const MAX_LIST_LIMIT: usize = 100;
+pub const DEFAULT_PAGE_SIZE: usize = 10;
+pub const MAX_PAGE_SIZE: usize = 50;
+// Callback data prefix of the /history page buttons
+pub const PAGE_CALLBACK: &str = "history:";
+
+// Directory for the reaction history databases, one per instance (written by the reaction bot)
+pub fn history_dir() -> String {
+    env::var("HISTORY_DIR").unwrap_or_else(|_| DEFAULT_HISTORY_DIR.to_string())
+}
+
+// The reaction bot's HISTORY_STORE when it isn't SQLite: "memory" and "postgres" histories are
+// out of the manager's reach, which only reads the SQLite files in HISTORY_DIR
+pub fn other_store() -> Option<String> {
+    env::var("HISTORY_STORE")
+        .ok()
+        .map(|store| store.trim().to_lowercase())
+        .filter(|store| !store.is_empty() && store != "sqlite")
+}
+
+// Whether the manager can read this history file: the store is SQLite and the bot has written it
+pub fn readable(path: &str) -> bool {
+    other_store().is_none() && std::path::Path::new(path).exists()
+}
+
+// What to reply instead of an instance's history when there's none to read
+pub fn unavailable(name: &str, path: &str, lang: Lang) -> Option<String> {
+    if let Some(store) = other_store() {
+        return Some(tr!(
+            lang,
+            "📋 The reaction history of '{}' is kept in {} (HISTORY_STORE), which the manager can't read. Send /list from the reaction bot's account instead.",
+            "📋 История реакций '{}' хранится в {} (HISTORY_STORE), ее контрольный бот прочитать не может. Отправьте /list с аккаунта бота реакций.",
+            name,
+            store
+        ));
+    }
+    (!std::path::Path::new(path).exists()).then(|| tr!(lang, "📋 No reaction history for '{}' yet.", "📋 Истории реакций '{}' пока нет.", name))
+}
+
+// A reaction recorded by the reaction bot
+#[derive(Serialize)]
+pub struct Reaction {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub amount: Option<i32>,
+    pub bank: Option<String>,
+    pub reacted_at: i64,
+    pub latency_us: i64,
+    pub outcome: Option<String>,   // "sent", "failed", or None while unconfirmed
+    pub chat_title: Option<String>,
+}
+
+const COLUMNS: &str = "chat_id, message_id, amount, bank, reacted_at, latency_us, outcome";
+
+impl Reaction {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            chat_id: row.get(0)?,
+            message_id: row.get(1)?,
+            amount: row.get(2)?,
+            bank: row.get(3)?,
+            reacted_at: row.get(4)?,
+            latency_us: row.get(5)?,
+            outcome: row.get(6)?,
+            chat_title: None,
+        })
+    }
+}
+
+// The database is opened read-only; the reaction bot keeps it in WAL mode so reading never blocks its writes
+fn open(path: &str) -> rusqlite::Result<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+// Chat titles and user names by ID, as the reaction bot learned them (see its names.rs); none in
+// histories from before it kept them
+fn names(connection: &Connection) -> HashMap<i64, String> {
+    let names = connection.prepare("SELECT id, name FROM names").and_then(|mut statement| {
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()
+    });
+    names.unwrap_or_default()
+}
+
+// "Title (id)", or just the ID when the name isn't known
+pub fn label(name: Option<&str>, id: i64) -> String {
+    match name {
+        Some(name) => format!("{} ({})", name, id),
+        None => id.to_string(),
+    }
+}
+
+fn with_titles(connection: &Connection, mut reactions: Vec<Reaction>) -> Vec<Reaction> {
+    let names = names(connection);
+    for reaction in &mut reactions {
+        reaction.chat_title = names.get(&reaction.chat_id).cloned();
+    }
+    reactions
+}
+
+// Most recent reactions, newest first
+pub fn recent(path: &str, limit: usize) -> rusqlite::Result<Vec<Reaction>> {
+    let connection = open(path)?;
+    let mut statement = connection.prepare(&format!("SELECT {} FROM reactions ORDER BY id DESC LIMIT ?1", COLUMNS))?;
+    let rows = statement.query_map([limit as i64], Reaction::from_row)?;
+    Ok(with_titles(&connection, rows.collect::<rusqlite::Result<_>>()?))
+}
+
+// One /history page, newest first
+pub struct Page {
+    pub orders: Vec<(Reaction, Option<bool>)>,   // With whether we won the reaction race, None if it wasn't tracked
+    pub total: usize,                            // Reactions in the whole history
+}
+
+pub fn page(path: &str, offset: usize, limit: usize) -> rusqlite::Result<Page> {
+    let connection = open(path)?;
+    let total: i64 = connection.query_row("SELECT COUNT(*) FROM reactions", [], |row| row.get(0))?;
+    let mut statement = connection.prepare(&format!("SELECT {}, won FROM reactions ORDER BY id DESC LIMIT ?1 OFFSET ?2", COLUMNS))?;
+    let rows = statement.query_map([limit as i64, offset as i64], |row| Ok((Reaction::from_row(row)?, row.get(7)?)))?;
+    let names = names(&connection);
+    let orders = rows
+        .map(|row| row.map(|(mut reaction, won): (Reaction, _)| {
+            reaction.chat_title = names.get(&reaction.chat_id).cloned();
+            (reaction, won)
+        }))
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(Page { orders, total: total as usize })
+}
+
+// All reactions since a Unix timestamp, oldest first
+pub fn since(path: &str, since: i64) -> rusqlite::Result<Vec<Reaction>> {
+    let connection = open(path)?;
+    let mut statement = connection.prepare(&format!("SELECT {} FROM reactions WHERE reacted_at >= ?1 ORDER BY id", COLUMNS))?;
+    let rows = statement.query_map([since], Reaction::from_row)?;
+    Ok(with_titles(&connection, rows.collect::<rusqlite::Result<_>>()?))
+}
+
+// Parse an export start: a date ("2026-10-01") or a period back from now ("7d", "12h")
+pub fn parse_since(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp());
+    }
+
+    let (count, unit) = value.split_at(value.len().checked_sub(1)?);
+    let seconds = match unit {
+        "d" => 86_400,
+        "h" => 3_600,
+        _ => return None,
+    };
+    Some(chrono::Utc::now().timestamp() - count.parse::<i64>().ok()? * seconds)
+}
+
+fn iso_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(reactions: &[Reaction]) -> String {
+    let mut csv = "reacted_at,chat_id,message_id,amount,bank,latency_us,outcome,chat_title\n".to_string();
+    for reaction in reactions {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            iso_time(reaction.reacted_at),
+            reaction.chat_id,
+            reaction.message_id,
+            reaction.amount.map(|a| a.to_string()).unwrap_or_default(),
+            csv_field(reaction.bank.as_deref().unwrap_or_default()),
+            reaction.latency_us,
+            reaction.outcome.as_deref().unwrap_or_default(),
+            csv_field(reaction.chat_title.as_deref().unwrap_or_default())
+        ));
+    }
+    csv
+}
+
+pub fn to_json(reactions: &[Reaction]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(reactions)
+}
+
+pub fn format(name: &str, reactions: &[Reaction], lang: Lang) -> String {
+    if reactions.is_empty() {
+        return tr!(lang, "📋 '{}' hasn't reacted to anything yet.", "📋 '{}' еще ни на что не реагировал.", name);
+    }
+
+    let mut text = tr!(lang, "📋 Last {} reactions of '{}':\n", "📋 Последние {} реакций '{}':\n", reactions.len(), name);
+    for reaction in reactions {
+        let time = timezone::from_timestamp(reaction.reacted_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        text.push_str(&tr!(
+            lang,
+            "\n{} | chat {} msg {} | {} ₽ | {} | {} µs",
+            "\n{} | чат {} сообщ. {} | {} ₽ | {} | {} мкс",
+            time,
+            label(reaction.chat_title.as_deref(), reaction.chat_id),
+            reaction.message_id,
+            reaction.amount.map_or("?".to_string(), |a| a.to_string()),
+            reaction.bank.as_deref().unwrap_or("?"),
+            reaction.latency_us
+        ));
+    }
+    text
+}
+
+// A /history page: the text, and prev/next buttons when there is more on either side
+pub fn format_page(name: &str, path: &str, offset: usize, limit: usize, lang: Lang) -> (String, Option<InlineKeyboardMarkup>) {
+    let Page { orders, total } = match page(path, offset, limit) {
+        Ok(page) => page,
+        Err(e) => return (tr!(lang, "❌ Failed to read the reaction history of '{}': {}", "❌ Не удалось прочитать историю реакций '{}': {}", name, e), None),
+    };
+    if orders.is_empty() {
+        return (tr!(lang, "📜 '{}' hasn't matched any orders yet.", "📜 '{}' еще не нашел ни одной заявки.", name), None);
+    }
+
+    let mut text = tr!(
+        lang,
+        "📜 Matched orders of '{}', {}–{} of {}:\n",
+        "📜 Заявки '{}', {}–{} из {}:\n",
+        name,
+        offset + 1,
+        offset + orders.len(),
+        total
+    );
+    for (reaction, won) in &orders {
+        let time = timezone::from_timestamp(reaction.reacted_at)
+            .map(|t| t.format("%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let result = match won {
+            Some(true) => lang.pick("🏆 won", "🏆 первые"),
+            Some(false) => lang.pick("lost", "не первые"),
+            None => "—",
+        };
+        text.push_str(&tr!(
+            lang,
+            "\n{} | {} ₽ | {} | chat {} | {} | {} µs",
+            "\n{} | {} ₽ | {} | чат {} | {} | {} мкс",
+            time,
+            reaction.amount.map_or("?".to_string(), |a| a.to_string()),
+            reaction.bank.as_deref().unwrap_or("?"),
+            label(reaction.chat_title.as_deref(), reaction.chat_id),
+            result,
+            reaction.latency_us
+        ));
+    }
+
+    let button = |label: &str, offset: usize| InlineKeyboardButton::callback(label, format!("{}{}:{}:{}", PAGE_CALLBACK, offset, limit, name));
+    let mut buttons = Vec::new();
+    if offset > 0 {
+        buttons.push(button(lang.pick("◀ Newer", "◀ Новее"), offset.saturating_sub(limit)));
+    }
+    if offset + orders.len() < total {
+        buttons.push(button(lang.pick("Older ▶", "Старше ▶"), offset + limit));
+    }
+    (text, (!buttons.is_empty()).then(|| InlineKeyboardMarkup::new([buttons])))
+}
+
+// Instance, offset and page size from a page button's callback data
+pub fn parse_page_callback(data: &str) -> Option<(String, usize, usize)> {
+    let mut parts = data.strip_prefix(PAGE_CALLBACK)?.splitn(3, ':');
+    let offset = parts.next()?.parse().ok()?;
+    let limit = parts.next()?.parse::<usize>().ok()?.clamp(1, MAX_PAGE_SIZE);
+    Some((parts.next()?.to_string(), offset, limit))
+}
+
+// TDLib message IDs are the number in the message's link shifted by this
+const SERVER_ID_SHIFT: u32 = 20;
+
+// Why the bot did or didn't react to a message: the decision traces the reaction bot stored for
+// it (one per chat the number was seen in), newest first. Histories from before traces existed
+// have none
+pub fn format_decisions(name: &str, path: &str, message: i64, lang: Lang) -> String {
+    // The number from the message's link, or TDLib's ID
+    let message_id = if message < 1 << SERVER_ID_SHIFT { message << SERVER_ID_SHIFT } else { message };
+    let traces = open(path).and_then(|connection| {
+        let mut statement = connection.prepare(
+            "SELECT chat_id, decided_at, trace FROM decisions WHERE message_id = ?1 ORDER BY decided_at DESC",
+        )?;
+        let rows = statement.query_map([message_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+    });
+    let traces = match traces {
+        Ok(traces) if !traces.is_empty() => traces,
+        _ => {
+            return tr!(
+                lang,
+                "🤷 '{}' has no decision about message {}. Only messages in watched chats are traced, and kept for DECISION_TRACE_DAYS.",
+                "🤷 У '{}' нет решения по сообщению {}. Решения сохраняются только для отслеживаемых чатов и хранятся DECISION_TRACE_DAYS дней.",
+                name,
+                message_id >> SERVER_ID_SHIFT
+            )
+        }
+    };
+
+    let names = open(path).map(|connection| names(&connection)).unwrap_or_default();
+    let mut text = String::new();
+    for (chat_id, decided_at, trace) in traces {
+        let time = timezone::from_timestamp(decided_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let trace: serde_json::Value = serde_json::from_str(&trace).unwrap_or_default();
+        let decision = match trace["skip_reason"].as_str() {
+            Some(reason) => tr!(lang, "❌ skipped: {}", "❌ пропущено: {}", reason),
+            None => lang.pick("✅ reacted", "✅ реакция поставлена").to_string(),
+        };
+        text.push_str(&tr!(
+            lang,
+            "💬 '{}', chat {}, message {} at {}\n{}\n",
+            "💬 '{}', чат {}, сообщение {}, {}\n{}\n",
+            name,
+            label(names.get(&chat_id).map(String::as_str), chat_id),
+            message_id >> SERVER_ID_SHIFT,
+            time,
+            decision
+        ));
+        for check in trace["checks"].as_array().into_iter().flatten() {
+            text.push_str(&tr!(
+                lang,
+                "• {}: {} (wanted {}) {}\n",
+                "• {}: {} (нужно {}) {}\n",
+                check["rule"].as_str().unwrap_or_default(),
+                check["input"].as_str().unwrap_or(lang.pick("none", "нет")),
+                check["wanted"].as_str().unwrap_or_default(),
+                if check["passed"].as_bool() == Some(true) { "✅" } else { "❌" }
+            ));
+        }
+        text.push('\n');
+    }
+    text.trim_end().to_string()
+}
+
+// Totals over a period, for the daily report
+pub struct Summary {
+    pub matches: i64,
+    pub confirmed: i64,
+    pub failed: i64,
+    pub volume: i64,
+    pub busiest_chat: Option<(String, i64)>,   // The chat (see `label`) and its number of matches
+    pub avg_latency_us: Option<f64>,
+}
+
+pub fn summary(path: &str, since: i64) -> rusqlite::Result<Summary> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let (matches, confirmed, failed, volume, avg_latency_us) = connection.query_row(
+        "SELECT COUNT(*),
+                COUNT(CASE WHEN outcome = 'sent' THEN 1 END),
+                COUNT(CASE WHEN outcome = 'failed' THEN 1 END),
+                COALESCE(SUM(amount), 0),
+                AVG(latency_us)
+         FROM reactions WHERE reacted_at >= ?1",
+        [since],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    )?;
+    let busiest_chat = connection
+        .query_row(
+            "SELECT chat_id, COUNT(*) AS matches FROM reactions WHERE reacted_at >= ?1
+             GROUP BY chat_id ORDER BY matches DESC LIMIT 1",
+            [since],
+            |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)),
+        )
+        .ok()
+        .map(|(chat_id, matches)| (label(names(&connection).get(&chat_id).map(String::as_str), chat_id), matches));
+
+    Ok(Summary { matches, confirmed, failed, volume, busiest_chat, avg_latency_us })
+}
+
+// Reaction race results over a period (see the reaction bot's race.rs)
+pub struct RaceSummary {
+    pub wins: i64,
+    pub losses: i64,
+    pub avg_margin_secs: Option<f64>,   // Over races someone else also reacted in
+    pub paid: i64,                      // Won orders the chat later marked paid or cancelled
+    pub cancelled: i64,
+}
+
+pub fn race_summary(path: &str, since: i64) -> rusqlite::Result<RaceSummary> {
+    let connection = open(path)?;
+    connection.query_row(
+        "SELECT COUNT(CASE WHEN won = 1 THEN 1 END),
+                COUNT(CASE WHEN won = 0 THEN 1 END),
+                AVG(margin_secs),
+                COUNT(CASE WHEN won = 1 AND final_state = 'paid' THEN 1 END),
+                COUNT(CASE WHEN won = 1 AND final_state = 'cancelled' THEN 1 END)
+         FROM reactions WHERE reacted_at >= ?1",
+        [since],
+        |row| {
+            Ok(RaceSummary {
+                wins: row.get(0)?,
+                losses: row.get(1)?,
+                avg_margin_secs: row.get(2)?,
+                paid: row.get(3)?,
+                cancelled: row.get(4)?,
+            })
+        },
+    )
+}
+
+// Orders we were first on over a period, and what they should have earned
+pub struct Earnings {
+    pub orders: i64,
+    pub volume: i64,
+    pub with_commission: i64,   // Orders that stated their own commission
+    pub estimated: f64,
+}
+
+// Orders without a commission of their own are counted at default_pct; cancelled ones earn nothing
+pub fn earnings(path: &str, since: i64, default_pct: f64) -> rusqlite::Result<Earnings> {
+    let connection = open(path)?;
+    connection.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(amount), 0), COUNT(commission_pct),
+                COALESCE(SUM(amount * COALESCE(commission_pct, ?2) / 100.0), 0)
+         FROM reactions WHERE reacted_at >= ?1 AND won = 1 AND amount IS NOT NULL
+             AND COALESCE(final_state, '') != 'cancelled'",
+        rusqlite::params![since, default_pct],
+        |row| Ok(Earnings { orders: row.get(0)?, volume: row.get(1)?, with_commission: row.get(2)?, estimated: row.get(3)? }),
+    )
+}
+
+// Another account competing for the same orders, over a period
+pub struct CompetitorSummary {
+    pub sender_id: i64,
+    pub name: Option<String>,
+    pub orders: i64,
+    pub avg_delay_secs: f64,   // From the order being posted to their reaction
+    pub ahead_of_us: i64,      // Orders they reacted to in an earlier second than we did
+}
+
+pub fn competitors(path: &str, since: i64, limit: usize) -> rusqlite::Result<Vec<CompetitorSummary>> {
+    let connection = open(path)?;
+    let mut statement = connection.prepare(
+        "SELECT c.sender_id, COUNT(*) AS orders, AVG(c.delay_secs),
+                COUNT(CASE WHEN c.reacted_at < r.reacted_at THEN 1 END)
+         FROM competitor_reactions c
+         JOIN reactions r ON r.chat_id = c.chat_id AND r.message_id = c.message_id
+         WHERE r.reacted_at >= ?1
+         GROUP BY c.sender_id ORDER BY orders DESC LIMIT ?2",
+    )?;
+    let rows = statement.query_map([since, limit as i64], |row| {
+        Ok(CompetitorSummary {
+            sender_id: row.get(0)?,
+            name: None,
+            orders: row.get(1)?,
+            avg_delay_secs: row.get(2)?,
+            ahead_of_us: row.get(3)?,
+        })
+    })?;
+    let names = names(&connection);
+    rows.map(|row| row.map(|competitor| CompetitorSummary { name: names.get(&competitor.sender_id).cloned(), ..competitor })).collect()
+}
+
+// How contested the orders of one local hour of the day were
+pub struct HourSummary {
+    pub hour: u32,
+    pub orders: i64,
+    pub avg_competitors: f64,
+}
+
+// Only orders whose race was tracked count, so channels (where reactors are hidden) don't look uncontested
+pub fn competition_by_hour(path: &str, since: i64) -> rusqlite::Result<Vec<HourSummary>> {
+    let connection = open(path)?;
+    let mut statement = connection.prepare(
+        "SELECT CAST(strftime('%H', r.reacted_at, 'unixepoch', 'localtime') AS INTEGER) AS hour, COUNT(*),
+                AVG((SELECT COUNT(*) FROM competitor_reactions c WHERE c.chat_id = r.chat_id AND c.message_id = r.message_id))
+         FROM reactions r
+         WHERE r.reacted_at >= ?1 AND r.won IS NOT NULL
+         GROUP BY hour ORDER BY hour",
+    )?;
+    let rows = statement.query_map([since], |row| {
+        Ok(HourSummary { hour: row.get(0)?, orders: row.get(1)?, avg_competitors: row.get(2)? })
+    })?;
+    rows.collect()
+}
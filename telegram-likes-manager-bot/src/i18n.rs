@@ -0,0 +1,119 @@
+use std::env;
+use serde::{Deserialize, Serialize};
+use teloxide::types::BotCommand;
+
+// Language of the manager bot's replies
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Ru,
+}
+
+impl Lang {
+    pub fn parse(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "en" | "english" => Some(Self::En),
+            "ru" | "russian" | "русский" => Some(Self::Ru),
+            _ => None,
+        }
+    }
+
+    // Default language for users who haven't picked one with /language
+    pub fn from_env() -> Self {
+        env::var("BOT_LANGUAGE")
+            .ok()
+            .and_then(|s| Self::parse(&s))
+            .unwrap_or_default()
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Ru => "ru",
+        }
+    }
+
+    pub fn pick<'a>(self, en: &'a str, ru: &'a str) -> &'a str {
+        match self {
+            Self::En => en,
+            Self::Ru => ru,
+        }
+    }
+}
+
+// Format the English or Russian variant of a message; both take the same arguments
+macro_rules! tr {
+    ($lang:expr, $en:literal, $ru:literal $(, $arg:expr)* $(,)?) => {
+        match $lang {
+            $crate::i18n::Lang::En => format!($en $(, $arg)*),
+            $crate::i18n::Lang::Ru => format!($ru $(, $arg)*),
+        }
+    };
+}
+pub(crate) use tr;
+
+// Russian command descriptions for the command menu and /help, in TelegramCommand order
+const RU_COMMANDS: &[(&str, &str)] = &[
+    ("start", "Запустить бот реакций (например, /start или /start backup)"),
+    ("stop", "Остановить бот реакций (например, /stop или /stop backup)"),
+    ("restart", "Перезапустить бот реакций с текущими настройками (например, /restart или /restart backup)"),
+    ("pause", "Приостановить реакции, не останавливая бот (например, /pause или /pause backup)"),
+    ("resume", "Возобновить реакции после /pause (например, /resume или /resume backup)"),
+    ("panic", "Аварийная остановка: никаких реакций ни на одном вашем экземпляре, даже после перезапуска, до /panic off"),
+    ("backfill", "Проверить последние сообщения чатов на заявки, пропущенные, пока бот не работал: /backfill, /backfill 100, /backfill backup 100"),
+    ("status", "Проверить, работает ли бот реакций (/status all - все экземпляры)"),
+    ("uptime", "Сколько работают контрольный бот и каждый бот реакций"),
+    ("version", "Версия и коммит сборки контрольного бота и ботов реакций, версия TDLib"),
+    ("stats", "Почему сообщения получили реакцию или были пропущены с момента запуска: /stats или /stats backup"),
+    ("competitors", "Основные конкуренты и часы с наименьшей конкуренцией: /competitors [с: 2026-10-01, 7d или 12h] [экземпляр], по умолчанию 7 дней"),
+    ("list", "Последние реакции: /list, /list 50, /list backup 50"),
+    ("history", "Найденные заявки постранично, с результатом гонки: /history, /history 20, /history backup 20"),
+    ("why", "Почему на сообщение была или не была поставлена реакция, по номеру из ссылки: /why 42, /why 42 backup"),
+    ("export", "Выгрузить историю реакций файлом: /export csv|json [с: 2026-10-01, 7d или 12h] [экземпляр]"),
+    ("instance", "Экземпляры: /instance add <имя> [бинарник] [каталог_сессии], /instance use <имя>, /instance remove <имя>, /instance list"),
+    ("account", "Аккаунты Telegram: /account list, /account add <имя>, /account use <имя> [экземпляр] (переключить экземпляр на сессию этого аккаунта)"),
+    ("logout", "Выйти из аккаунта остановленного экземпляра и удалить его сессию: /logout или /logout backup"),
+    ("snapshot", "Сохранить копию сессии TDLib выбранного экземпляра (бот останавливается на время копирования): /snapshot, /snapshot list"),
+    ("restore", "Вернуть сессию TDLib выбранного экземпляра из копии: /restore <имя>"),
+    ("chats", "Чаты выбранного экземпляра: /chats -100123,-100456, /chats off (общий ALLOWED_CHAT_IDS), /chats"),
+    ("bank", "Фильтр по банку (например, /bank t для Т-Банка)"),
+    ("requisite", "Фильтр по реквизитам (например, /requisite + для СБП)"),
+    ("blacklist", "Никогда не реагировать на эти реквизиты, во всех экземплярах: /blacklist add <карта или телефон>, /blacklist remove <карта или телефон>, /blacklist list"),
+    ("amount", "Минимальная сумма (например, /amount 50000) или для банка: /amount tbank 50000, /amount tbank off"),
+    ("profit", "Пропускать заявки с меньшей ожидаемой прибылью (по их комиссии, BANK_COMMISSIONS или COMMISSION_PERCENT): /profit 500, /profit off"),
+    ("balance", "Пропускать заявки больше остатка рабочего баланса: /balance set 150000, /balance reset, /balance daily 09:00, /balance off"),
+    ("emoji", "Эмодзи для реакции (например, /emoji 🔥) или по суммам: /emoji tiers 60000:🔥 100000:⚡, /emoji tiers off"),
+    ("preset", "Пресеты фильтров: /preset save <имя>, /preset load <имя>, /preset list"),
+    ("export_settings", "Выгрузить все фильтры, чаты и пресеты файлом - для переноса на другой сервер или резервной копии"),
+    ("import_settings", "Восстановить настройки из файла /export_settings: ответьте на файл командой /import_settings"),
+    ("alias", "Сокращения для частых команд: /alias a50 /amount 50000, /alias a50 off, /alias (список)"),
+    ("auth", "Ответить на запрос входа от бота реакций (например, /auth +79991234567)"),
+    ("events", "Лента событий: /events on (этот чат), /events <chat_id>, /events off"),
+    ("report", "Сводка за сутки: /report (сейчас), /report on (ежедневно в этот чат), /report <chat_id>, /report off"),
+    ("dashboard", "Закрепленное сообщение в этом чате со статусом, фильтрами и счетчиками за сегодня: /dashboard on, /dashboard off"),
+    ("adduser", "Разрешить пользователю работать с ботом (только администраторы из ALLOWED_USERS): /adduser <user_id>, /adduser - список"),
+    ("removeuser", "Запретить пользователю работать с ботом (только администраторы): /removeuser <user_id>"),
+    ("setup", "Пошаговая настройка банка, суммы, реквизитов и чатов"),
+    ("cancel", "Отменить /setup"),
+    ("language", "Язык ответов: /language ru или /language en"),
+    ("clear", "Сбросить все фильтры"),
+    ("confirm", "Подтвердить /clear, /stop или /logout, отправленную за последние 30 секунд"),
+    ("help", "Показать эту справку"),
+];
+
+pub fn ru_help() -> String {
+    let mut text = "Поддерживаются команды:\n\n".to_string();
+    for (command, description) in RU_COMMANDS {
+        text.push_str(&format!("/{} — {}\n", command, description));
+    }
+    text
+}
+
+pub fn ru_bot_commands() -> Vec<BotCommand> {
+    RU_COMMANDS
+        .iter()
+        .map(|(command, description)| BotCommand::new(*command, *description))
+        .collect()
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use log::info;
+use teloxide::{
+    prelude::*,
+    types::{InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText},
+};
+use tokio::sync::Mutex;
+
+use crate::{i18n::tr, state::BotState, status_channel};
+
+// "@manager_bot status" in any chat: a status card of the user's instances to post there, so the
+// bot can be checked without opening the manager chat. "@manager_bot status backup" shows one
+// instance. Needs inline mode turned on for the bot in @BotFather (/setinline)
+pub async fn answer(bot: Bot, query: InlineQuery, bot_state: Arc<Mutex<BotState>>) -> anyhow::Result<()> {
+    let user = query.from.id;
+    let (text, running, total, lang) = {
+        let state = bot_state.lock().await;
+        if !state.user_allowed(user.0) {
+            info!("Unauthorized inline query from user {}", user.0);
+            // An empty answer, so their client doesn't keep waiting
+            bot.answer_inline_query(query.id, Vec::new()).cache_time(0).is_personal(true).await?;
+            return Ok(());
+        }
+        let lang = state.language(Some(user));
+
+        // Every word but "status" may name an instance; without one, all of them
+        let named = query.query.split_whitespace().find(|word| state.instances.get(*word).is_some_and(|i| i.accessible_by(Some(user))));
+        let instances = || state.accessible(Some(user)).filter(|(name, _)| named.is_none_or(|named| *name == named));
+        let running = instances().filter(|(_, instance)| instance.is_running).count();
+        (status_channel::build(instances(), lang), running, instances().count(), lang)
+    };
+
+    let article = InlineQueryResultArticle::new(
+        "status",
+        lang.pick("Reaction bot status", "Статус ботов реакций"),
+        InputMessageContent::Text(InputMessageContentText::new(text)),
+    )
+    .description(tr!(lang, "{} of {} running", "Работает {} из {}", running, total));
+
+    // Never cached: the card is only useful while it's fresh
+    bot.answer_inline_query(query.id, vec![InlineQueryResult::Article(article)])
+        .cache_time(0)
+        .is_personal(true)
+        .await?;
+    Ok(())
+}
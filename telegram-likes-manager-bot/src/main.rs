@@ -1,46 +1,146 @@
-use std::{process::{Child, Command as ProcessCommand}, sync::Arc, env};
-use tokio::sync::Mutex;
-use log::info;
-use teloxide::prelude::*;
+use std::{sync::Arc, env, time::Duration};
+use tokio::{process::Command as ProcessCommand, sync::Mutex};
+use log::{info, warn};
+use teloxide::{dispatching::dialogue::InMemStorage, net::Download, prelude::*, types::{BotCommand, InputFile}};
 use teloxide::utils::command::BotCommands;
-use dotenv::dotenv;
 use anyhow::Result;
+use botdg_config::{timezone, DefaultValue};
+use clap::Parser;
 
-// Global state to track the reaction bot process
-struct BotState {
-    reaction_bot_process: Option<Child>,
-    is_running: bool,
-    last_status: String,
-    bank_filter: Option<String>,
-    requisite_filter: Option<String>,
-    min_amount: i32,
-}
+mod accounts;
+mod aliases;
+mod backup;
+mod blacklist;
+mod competitors;
+mod confirm;
+mod control;
+mod dashboard;
+mod discord;
+mod email;
+mod health;
+mod history;
+mod i18n;
+mod inline;
+mod notify;
+mod output;
+mod presets;
+mod process;
+mod quiet;
+mod report;
+mod rpc;
+mod setup;
+mod slack;
+mod snapshot;
+mod state;
+mod stats;
+mod status_channel;
+mod storage;
+mod supervisor;
+mod templates;
+mod validate;
+mod version;
+mod webhook;
 
-impl BotState {
-    fn new() -> Self {
-        Self {
-            reaction_bot_process: None,
-            is_running: false,
-            last_status: "Not started".to_string(),
-            bank_filter: None,
-            requisite_filter: None,
-            min_amount: 38000, // Default minimum amount
-        }
-    }
+use notify::Notifier;
+use i18n::{tr, Lang};
+use state::{BotState, FilterSettings, Instance, DEFAULT_MIN_AMOUNT, DEFAULT_REACTION_EMOJI};
+
+// How long /stop and /restart wait for the process to exit before killing it
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+// Defaults filled in by the config loader; the rest of the settings keep theirs where they're read.
+// Under --profile the per-profile ones get the profile's own state, sessions and history
+const CONFIG_DEFAULTS: &[DefaultValue] = &[
+    DefaultValue::new("REACTION_BOT_PATH", process::DEFAULT_REACTION_BOT_PATH),
+    DefaultValue::path("PRESETS_FILE", presets::DEFAULT_PRESETS_FILE),
+    DefaultValue::path("REQUISITE_BLACKLIST_FILE", blacklist::DEFAULT_BLACKLIST_FILE),
+    DefaultValue::per_profile("STATE_FILE", state::DEFAULT_STATE_FILE),
+    DefaultValue::per_profile("ACCOUNTS_DIR", accounts::DEFAULT_ACCOUNTS_DIR),
+    DefaultValue::per_profile("HISTORY_DIR", history::DEFAULT_HISTORY_DIR),
+    DefaultValue::per_profile("HEARTBEAT_DIR", health::DEFAULT_HEARTBEAT_DIR),
+    DefaultValue::per_profile("LOG_DIR", output::DEFAULT_LOG_DIR).in_state_dir(),
+    DefaultValue::per_profile("TDLIB_DATA_DIR", state::DEFAULT_TDLIB_DATA_DIR),
+    DefaultValue::per_profile("SNAPSHOT_DIR", snapshot::DEFAULT_SNAPSHOT_DIR),
+    DefaultValue::per_profile("INSTANCES_DIR", process::DEFAULT_INSTANCES_DIR),
+];
+
+// Settings come from the environment, .env and the same config file as the reaction bot's
+// (see botdg-config); the command line only points at the file and overrides single settings
+#[derive(Parser)]
+#[command(version = version::VERSION, about = "Telegram bot that manages the reaction bots")]
+struct Cli {
+    #[command(flatten)]
+    config: botdg_config::ConfigArgs,
 }
 
 // Define bot commands
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase", description = "These commands are supported:")]
 enum TelegramCommand {
-    #[command(description = "Start the reaction bot")]
-    Start,
+    #[command(description = "Start the reaction bot (e.g., /start or /start backup)")]
+    Start { name: String },
+    
+    #[command(description = "Stop the reaction bot (e.g., /stop or /stop backup)")]
+    Stop { name: String },
+    
+    #[command(description = "Restart the reaction bot with the current settings (e.g., /restart or /restart backup)")]
+    Restart { name: String },
+    
+    #[command(description = "Pause reactions without stopping the bot (e.g., /pause or /pause backup)")]
+    Pause { name: String },
+    
+    #[command(description = "Resume reactions after /pause (e.g., /resume or /resume backup)")]
+    Resume { name: String },
+    
+    #[command(description = "Emergency stop: no reactions on any of your instances, even after a restart, until /panic off")]
+    Panic { args: String },
+    
+    #[command(description = "Check the last messages of each chat for orders posted while the bot was down: /backfill, /backfill 100, /backfill backup 100")]
+    Backfill { args: String },
+    
+    #[command(description = "Check if the reaction bot is running (/status all for every instance)")]
+    Status { target: String },
+    
+    #[command(description = "Show how long the manager and each reaction bot have been running")]
+    Uptime,
+    
+    #[command(description = "Show the build version and commit of the manager and reaction bots, and the TDLib version")]
+    Version,
+    
+    #[command(description = "Show why messages were reacted to or skipped since the bot started: /stats or /stats backup")]
+    Stats { name: String },
+    
+    #[command(description = "Show the main competitors and the least contested hours: /competitors [since: 2026-10-01, 7d or 12h] [instance], 7 days by default")]
+    Competitors { args: String },
     
-    #[command(description = "Stop the reaction bot")]
-    Stop,
+    #[command(description = "Show recent reactions: /list, /list 50, /list backup 50")]
+    List { args: String },
     
-    #[command(description = "Check if the reaction bot is running")]
-    Status,
+    #[command(description = "Show the matched orders page by page, with the race result: /history, /history 20, /history backup 20")]
+    History { args: String },
+    
+    #[command(description = "Why a message was or wasn't reacted to, by the number in its link: /why 42, /why 42 backup")]
+    Why { args: String },
+    
+    #[command(description = "Export the reaction history as a file: /export csv|json [since: 2026-10-01, 7d or 12h] [instance]")]
+    Export { args: String },
+    
+    #[command(description = "Manage instances: /instance add <name> [binary] [session_dir], /instance use <name>, /instance remove <name>, /instance list")]
+    Instance { args: String },
+    
+    #[command(description = "Telegram accounts: /account list, /account add <name>, /account use <name> [instance] (switch the instance to that account's session)")]
+    Account { args: String },
+    
+    #[command(description = "Log a stopped instance's account out and delete its session: /logout or /logout backup")]
+    Logout { name: String },
+    
+    #[command(description = "Save a copy of the selected instance's TDLib session, stopping the bot meanwhile: /snapshot, /snapshot list")]
+    Snapshot { args: String },
+    
+    #[command(description = "Put the selected instance's TDLib session back from a snapshot: /restore <name>")]
+    Restore { name: String },
+    
+    #[command(description = "Set the chats the selected instance monitors: /chats -100123,-100456, /chats off (use ALLOWED_CHAT_IDS), /chats")]
+    Chats { ids: String },
     
     #[command(description = "Set the bank filter (e.g., /bank t for T-Bank)")]
     Bank { filter: String },
@@ -48,413 +148,2235 @@ enum TelegramCommand {
     #[command(description = "Set the requisite filter (e.g., /requisite + for SBP)")]
     Requisite { filter: String },
     
-    #[command(description = "Set the minimum amount (e.g., /amount 50000)")]
-    Amount { value: i32 },
+    #[command(description = "Never react to these requisites, in any instance: /blacklist add <card or phone>, /blacklist remove <card or phone>, /blacklist list")]
+    Blacklist { args: String },
+    
+    #[command(description = "Set the minimum amount (e.g., /amount 50000), or per bank: /amount tbank 50000, /amount tbank off")]
+    Amount { args: String },
+    
+    #[command(description = "Skip orders expected to earn less, at their commission, BANK_COMMISSIONS or COMMISSION_PERCENT: /profit 500, /profit off")]
+    Profit { value: String },
+    
+    #[command(description = "Skip orders larger than what's left of a working balance: /balance set 150000, /balance reset, /balance daily 09:00, /balance off")]
+    Balance { args: String },
+    
+    #[command(description = "Set the reaction emoji (e.g., /emoji 🔥), or by amount: /emoji tiers 60000:🔥 100000:⚡, /emoji tiers off")]
+    Emoji { emoji: String },
+    
+    #[command(description = "Manage filter presets: /preset save <name>, /preset load <name>, /preset list")]
+    Preset { args: String },
+    
+    #[command(rename = "export_settings", description = "Send all filters, chats and presets as a file, to move them to another deployment or keep as a backup")]
+    ExportSettings,
+    
+    #[command(rename = "import_settings", description = "Restore settings from a file made by /export_settings: reply to the file with /import_settings")]
+    ImportSettings,
+    
+    #[command(description = "Shortcuts for frequent commands: /alias a50 /amount 50000, /alias a50 off, /alias (list)")]
+    Alias { args: String },
+    
+    #[command(description = "Answer a login prompt from the reaction bot (e.g., /auth +1234567890)")]
+    Auth { value: String },
+    
+    #[command(description = "Live event feed: /events on (this chat), /events <chat_id>, /events off")]
+    Events { args: String },
+    
+    #[command(description = "Daily summary: /report (now), /report on (this chat, daily), /report <chat_id>, /report off")]
+    Report { args: String },
+    
+    #[command(description = "Keep a pinned message in this chat with live status, filters and today's counters: /dashboard on, /dashboard off")]
+    Dashboard { args: String },
+    
+    #[command(description = "Let a user use this bot (admins from ALLOWED_USERS only): /adduser <user_id>, or /adduser to list users")]
+    AddUser { id: String },
+    
+    #[command(description = "Stop a user from using this bot (admins only): /removeuser <user_id>")]
+    RemoveUser { id: String },
+    
+    #[command(description = "Step-by-step setup of bank, amount, requisites and chats")]
+    Setup,
+    
+    #[command(description = "Cancel /setup")]
+    Cancel,
+    
+    #[command(description = "Reply language: /language en or /language ru")]
+    Language { code: String },
     
     #[command(description = "Clear all filters")]
     Clear,
     
+    #[command(description = "Go ahead with the /clear, /stop or /logout sent in the last 30 seconds")]
+    Confirm,
+    
     #[command(description = "Display this help message")]
     Help,
 }
 
+type Confirmations = confirm::Confirmations<TelegramCommand>;
+
 async fn handle_command(
     bot: Bot,
     message: Message,
     command: TelegramCommand,
     bot_state: Arc<Mutex<BotState>>,
+    events: control::EventSender,
+    dialogue: setup::SetupDialogue,
+    confirmations: Arc<Mutex<Confirmations>>,
 ) -> Result<()> {
     let chat_id = message.chat.id;
+    let user = message.from().map(|user| user.id);
+    let lang = bot_state.lock().await.language(user);
+    
+    // A mistyped /clear, /stop or /logout mid-day costs orders, so they wait for /confirm
+    let command = match command {
+        TelegramCommand::Confirm => {
+            let confirmed = match user {
+                Some(user) => confirmations.lock().await.take(user.0),
+                None => None,
+            };
+            let Some(command) = confirmed else {
+                bot.send_message(chat_id, lang.pick("Nothing to confirm.", "Нечего подтверждать.")).await?;
+                return Ok(());
+            };
+            command
+        }
+        command => {
+            let prompt = confirmation_prompt(&command, &*bot_state.lock().await, user, lang);
+            if let (Some(prompt), Some(user)) = (prompt, user) {
+                confirmations.lock().await.ask(user.0, command);
+                bot.send_message(chat_id, prompt).await?;
+                return Ok(());
+            }
+            command
+        }
+    };
     
     match command {
-        TelegramCommand::Start => {
+        TelegramCommand::Setup => return setup::start(bot, message, dialogue, bot_state).await,
+        
+        TelegramCommand::Cancel => return setup::cancel(bot, message, dialogue, bot_state).await,
+        
+        TelegramCommand::Start { name } => {
             let mut state = bot_state.lock().await;
             
-            if state.is_running {
-                bot.send_message(chat_id, "The reaction bot is already running.").await?;
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
                 return Ok(());
-            }
+            };
+            // Remember where to send supervisor notifications
+            state.notify_chat_id = Some(chat_id.0);
             
-            // Get reaction bot path from environment
-            let reaction_bot_path = env::var("REACTION_BOT_PATH")
-                .unwrap_or_else(|_| "/Users/h/Rustown/telegram-reaction-bot".to_string());
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
             
-            // First, make sure no existing instances are running
-            if cfg!(target_os = "windows") {
-                let _ = ProcessCommand::new("taskkill")
-                    .args(["/F", "/IM", "tdlib-test.exe"])
-                    .output();
-            } else {
-                let _ = ProcessCommand::new("pkill")
-                    .args(["-f", "tdlib-test"])
-                    .output();
+            if instance.is_running {
+                bot.send_message(chat_id, tr!(lang, "The reaction bot '{}' is already running.", "Бот реакций '{}' уже запущен.", name)).await?;
+                return Ok(());
+            }
+            
+            // First, make sure a process left over from a previous manager run isn't still
+            // using this instance's session
+            if let Some(pid) = health::live_pid(instance, &name) {
+                info!("Stopping stale reaction bot '{}' (PID {})", name, pid);
+                if let Err(e) = process::terminate_pid(pid, STOP_TIMEOUT).await {
+                    warn!("Failed to stop stale reaction bot '{}' (PID {}): {}", name, pid, e);
+                }
             }
             
-            // For maximum speed, use the pre-built binary directly instead of cargo run
-            // This significantly reduces startup time and improves reaction speed
-            let binary_path = format!("{}/target/release/tdlib-test", reaction_bot_path);
+            let binary_path = instance.binary_path();
             
             // Check if the binary exists, if not, build it first
             if !std::path::Path::new(&binary_path).exists() {
                 // Build the reaction bot first
-                bot.send_message(chat_id, "🔨 Building reaction bot (one-time setup)...").await?;
+                bot.send_message(chat_id, lang.pick("🔨 Building reaction bot (one-time setup)...", "🔨 Сборка бота реакций (однократно)...")).await?;
                 
                 let build_result = ProcessCommand::new("cargo")
-                    .current_dir(&reaction_bot_path)
+                    .current_dir(process::reaction_bot_path())
                     .arg("build")
                     .arg("--release")
-                    .output();
+                    .output()
+                    .await;
                 
                 if let Err(e) = build_result {
-                    state.last_status = format!("Failed to build: {}", e);
+                    instance.last_status = tr!(lang, "Failed to build: {}", "Ошибка сборки: {}", e);
                     bot.send_message(
                         chat_id, 
-                        format!("❌ Failed to build reaction bot: {}", e)
+                        tr!(lang, "❌ Failed to build reaction bot: {}", "❌ Не удалось собрать бот реакций: {}", e)
                     ).await?;
                     return Ok(());
                 }
             }
             
-            // Set environment variables for the reaction bot based on filters
-            let mut command = ProcessCommand::new(&binary_path);
-            
-            // Set bank filter if specified
-            if let Some(bank) = &state.bank_filter {
-                command.env("BANK_FILTER", bank);
-            }
-            
-            // Set requisite filter if specified
-            if let Some(requisite) = &state.requisite_filter {
-                command.env("REQUISITE_FILTER", requisite);
-            }
-            
-            // Set minimum amount
-            command.env("MIN_AMOUNT", state.min_amount.to_string());
-            
-            // Special handling for T-Bank messages when requisite filter is set to "+"
-            // This ensures T-Bank messages are included even if they don't have a "+" in their requisite
-            if state.requisite_filter.as_deref() == Some("+") {
-                info!("Special handling for T-Bank messages with '+' filter is enabled");
-            }
-            
-            match command.spawn() {
-                Ok(child) => {
-                    state.reaction_bot_process = Some(child);
-                    state.is_running = true;
-                    state.last_status = "Running".to_string();
-                    
-                    let filter_info = format!(
-                        "Bank filter: {}\nRequisite filter: {}\nMinimum amount: {}",
-                        state.bank_filter.as_deref().unwrap_or("None"),
-                        state.requisite_filter.as_deref().unwrap_or("None"),
-                        state.min_amount
-                    );
+            match launch(&name, instance, &events, lang) {
+                Ok(()) => {
+                    let filter_info = instance.filters.describe(lang);
                     
                     bot.send_message(
                         chat_id, 
-                        format!("✅ Reaction bot started successfully with the following settings:\n\n{}", filter_info)
+                        tr!(
+                            lang,
+                            "✅ Reaction bot '{}' started successfully with the following settings:\n\n{}",
+                            "✅ Бот реакций '{}' запущен со следующими настройками:\n\n{}",
+                            name,
+                            filter_info
+                        )
                     ).await?;
                 },
                 Err(e) => {
-                    state.last_status = format!("Failed to start: {}", e);
+                    instance.last_status = tr!(lang, "Failed to start: {}", "Ошибка запуска: {}", e);
                     bot.send_message(
                         chat_id, 
-                        format!("❌ Failed to start reaction bot: {}", e)
+                        tr!(lang, "❌ Failed to start reaction bot '{}': {}", "❌ Не удалось запустить бот реакций '{}': {}", name, e)
                     ).await?;
                 }
             }
         },
         
-        TelegramCommand::Stop => {
+        TelegramCommand::Stop { name } => {
             let mut state = bot_state.lock().await;
             
-            if !state.is_running {
-                bot.send_message(chat_id, "The reaction bot is not running.").await?;
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+            
+            if !instance.is_running {
+                // A crashed bot waiting for its automatic restart counts as stopped
+                if instance.supervisor.next_restart.is_some() {
+                    instance.supervisor.reset();
+                    bot.send_message(chat_id, tr!(lang, "✅ Pending automatic restart of '{}' cancelled.", "✅ Автоматический перезапуск '{}' отменен.", name)).await?;
+                } else {
+                    bot.send_message(chat_id, not_running(lang, &name)).await?;
+                }
                 return Ok(());
             }
             
             // Print to terminal that we're stopping the bot
-            println!("\n==== STOPPING REACTION BOT ====\n");
-            
-            // More reliable process termination using system commands
-            if let Some(mut child) = state.reaction_bot_process.take() {
-                // First try graceful termination
-                let pid = child.id();
-                info!("Attempting to stop reaction bot process with PID {}", pid);
-                println!("Stopping reaction bot process with PID {}", pid);
-                
-                // Use kill command to terminate the process and its children
-                let kill_command = if cfg!(target_os = "windows") {
-                    format!("taskkill /F /T /PID {}", pid)
-                } else {
-                    format!("pkill -TERM -P {}", pid)
-                };
-                
-                println!("Executing: {}", kill_command);
-                
-                let kill_result = if cfg!(target_os = "windows") {
-                    ProcessCommand::new("taskkill")
-                        .args(["/F", "/T", "/PID", &pid.to_string()])
-                        .output()
-                } else {
-                    // On Unix systems, use pkill to kill the process group
-                    ProcessCommand::new("pkill")
-                        .args(["-TERM", "-P", &pid.to_string()])
-                        .output()
-                };
+            println!("\n==== STOPPING REACTION BOT '{}' ====\n", name);
+            
+            // Closing the control channel also unblocks a bot waiting for login input
+            instance.control = None;
+            instance.pending_auth = None;
+            
+            let stopped = lang.pick("Stopped", "Остановлен");
+            
+            if let Some(mut child) = instance.reaction_bot_process.take() {
+                info!("Stopping reaction bot '{}' (PID {})", name, child.id());
                 
-                match kill_result {
-                    Ok(output) => {
-                        // Print the command output to terminal
-                        if !output.stdout.is_empty() {
-                            println!("Command output: {}", String::from_utf8_lossy(&output.stdout));
-                        }
-                        if !output.stderr.is_empty() {
-                            println!("Command error: {}", String::from_utf8_lossy(&output.stderr));
-                        }
-                        
-                        // Also try to kill the process directly
-                        println!("Also killing process directly");
-                        let _ = child.kill();
-                        state.is_running = false;
-                        state.last_status = "Stopped".to_string();
-                        bot.send_message(chat_id, "✅ Reaction bot stopped successfully.").await?;
-                        println!("✅ Reaction bot stopped successfully.");
+                match process::terminate(&mut child, STOP_TIMEOUT).await {
+                    Ok(status) => {
+                        info!("Reaction bot '{}' exited: {}", name, status);
+                        instance.is_running = false;
+                        instance.last_status = stopped.to_string();
+                        bot.send_message(chat_id, stopped_successfully(lang, &name)).await?;
                     },
                     Err(e) => {
-                        println!("Error with kill command: {}", e);
-                        // Try direct kill as fallback
-                        println!("Trying direct kill as fallback");
-                        match child.kill() {
-                            Ok(_) => {
-                                state.is_running = false;
-                                state.last_status = "Stopped".to_string();
-                                bot.send_message(chat_id, "✅ Reaction bot stopped successfully (fallback method).").await?;
-                                println!("✅ Reaction bot stopped successfully (fallback method).");
-                            },
-                            Err(e2) => {
-                                println!("Failed to kill process: {}", e2);
-                                state.reaction_bot_process = Some(child);
-                                bot.send_message(
-                                    chat_id, 
-                                    format!("❌ Failed to stop reaction bot: {} (fallback error: {})", e, e2)
-                                ).await?;
-                            }
-                        }
+                        warn!("Failed to stop reaction bot '{}': {}", name, e);
+                        instance.reaction_bot_process = Some(child);
+                        bot.send_message(chat_id, tr!(lang, "❌ Failed to stop reaction bot '{}': {}", "❌ Не удалось остановить бот реакций '{}': {}", name, e)).await?;
                     }
                 }
-            } else {
-                // No child process found, but state says it's running
-                println!("No child process found, but state says it's running");
-                println!("Killing any potential orphaned processes");
-                
-                // Kill any potential orphaned processes
-                let kill_command = if cfg!(target_os = "windows") {
-                    "taskkill /F /IM tdlib-test.exe"
-                } else {
-                    "pkill -f tdlib-test"
-                };
-                
-                println!("Executing: {}", kill_command);
-                
-                let output = if cfg!(target_os = "windows") {
-                    ProcessCommand::new("taskkill")
-                        .args(["/F", "/IM", "tdlib-test.exe"])
-                        .output()
-                } else {
-                    ProcessCommand::new("pkill")
-                        .args(["-f", "tdlib-test"])
-                        .output()
-                };
+            } else if let Some(pid) = health::live_pid(instance, &name) {
+                // Started before a manager restart: the heartbeat still tells us its PID
+                info!("No process handle for '{}', stopping PID {} from its heartbeat", name, pid);
                 
-                if let Ok(output) = output {
-                    // Print the command output to terminal
-                    if !output.stdout.is_empty() {
-                        println!("Command output: {}", String::from_utf8_lossy(&output.stdout));
-                    }
-                    if !output.stderr.is_empty() {
-                        println!("Command error: {}", String::from_utf8_lossy(&output.stderr));
+                match process::terminate_pid(pid, STOP_TIMEOUT).await {
+                    Ok(()) => {
+                        instance.is_running = false;
+                        instance.last_status = stopped.to_string();
+                        bot.send_message(chat_id, stopped_successfully(lang, &name)).await?;
+                    },
+                    Err(e) => {
+                        bot.send_message(
+                            chat_id,
+                            tr!(lang, "❌ Failed to stop reaction bot '{}' (PID {}): {}", "❌ Не удалось остановить бот реакций '{}' (PID {}): {}", name, pid, e)
+                        ).await?;
                     }
                 }
-                
-                state.is_running = false;
-                state.last_status = "Stopped".to_string();
-                bot.send_message(chat_id, "✅ Reaction bot stopped successfully.").await?;
-                println!("✅ Reaction bot stopped successfully.");
+            } else {
+                // No handle and no recent heartbeat: the process is most likely gone already
+                instance.is_running = false;
+                instance.last_status = stopped.to_string();
+                bot.send_message(
+                    chat_id,
+                    tr!(
+                        lang,
+                        "⚠️ No running process found for '{}'. It was marked as stopped.",
+                        "⚠️ Работающий процесс '{}' не найден. Он отмечен как остановленный.",
+                        name
+                    )
+                ).await?;
             }
             
             println!("\n==== REACTION BOT STOPPED ====\n");
         },
         
-        TelegramCommand::Status => {
-            let state = bot_state.lock().await;
+        TelegramCommand::Restart { name } => {
+            let mut state = bot_state.lock().await;
             
-            let status = if state.is_running {
-                "✅ Running"
-            } else {
-                "❌ Not running"
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
             };
+            state.notify_chat_id = Some(chat_id.0);
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
             
-            let filter_info = format!(
-                "Bank filter: {}\nRequisite filter: {}\nMinimum amount: {}",
-                state.bank_filter.as_deref().unwrap_or("None"),
-                state.requisite_filter.as_deref().unwrap_or("None"),
-                state.min_amount
-            );
+            instance.control = None;
+            instance.pending_auth = None;
             
-            bot.send_message(
-                chat_id, 
-                format!("Reaction bot status: {}\n\nCurrent settings:\n{}", status, filter_info)
-            ).await?;
+            if let Some(mut child) = instance.reaction_bot_process.take() {
+                info!("Restarting reaction bot '{}' (PID {})", name, child.id());
+                match process::terminate(&mut child, STOP_TIMEOUT).await {
+                    Ok(status) => info!("Reaction bot '{}' exited: {}", name, status),
+                    Err(e) => {
+                        instance.reaction_bot_process = Some(child);
+                        bot.send_message(chat_id, tr!(lang, "❌ Failed to stop reaction bot '{}': {}", "❌ Не удалось остановить бот реакций '{}': {}", name, e)).await?;
+                        return Ok(());
+                    }
+                }
+            } else if instance.is_running {
+                bot.send_message(
+                    chat_id,
+                    tr!(
+                        lang,
+                        "⚠️ No process handle for '{}' (it was probably started before a manager restart). Stop it with /stop first.",
+                        "⚠️ Нет дескриптора процесса '{}' (вероятно, он запущен до перезапуска контрольного бота). Сначала остановите его командой /stop.",
+                        name
+                    )
+                ).await?;
+                return Ok(());
+            }
+            
+            instance.is_running = false;
+            
+            match launch(&name, instance, &events, lang) {
+                Ok(()) => {
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "✅ Reaction bot '{}' restarted with the following settings:\n\n{}",
+                            "✅ Бот реакций '{}' перезапущен со следующими настройками:\n\n{}",
+                            name,
+                            instance.filters.describe(lang)
+                        )
+                    ).await?;
+                },
+                Err(e) => {
+                    instance.last_status = tr!(lang, "Failed to start: {}", "Ошибка запуска: {}", e);
+                    bot.send_message(chat_id, tr!(lang, "❌ Failed to start reaction bot '{}': {}", "❌ Не удалось запустить бот реакций '{}': {}", name, e)).await?;
+                }
+            }
         },
         
-        TelegramCommand::Bank { filter } => {
+        TelegramCommand::Pause { ref name } | TelegramCommand::Resume { ref name } => {
+            let pause = matches!(command, TelegramCommand::Pause { .. });
             let mut state = bot_state.lock().await;
             
-            if filter.trim().to_lowercase() == "none" || filter.trim().is_empty() {
-                state.bank_filter = None;
-                bot.send_message(chat_id, "✅ Bank filter cleared.").await?;
-            } else {
-                state.bank_filter = Some(filter.clone());
-                bot.send_message(chat_id, format!("✅ Bank filter set to: {}", filter)).await?;
+            let Some(name) = state.resolve_instance(name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, name)).await?;
+                return Ok(());
+            };
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+            
+            if !instance.is_running {
+                bot.send_message(chat_id, not_running(lang, &name)).await?;
+                return Ok(());
             }
             
-            // If the bot is running, we need to restart it for the changes to take effect
-            if state.is_running {
-                bot.send_message(
-                    chat_id, 
-                    "⚠️ Please restart the bot with /stop and then /start for the changes to take effect."
-                ).await?;
+            // The bot confirms with a "paused"/"resumed" event, relayed to the admin chat
+            let command = if pause { "pause" } else { "resume" };
+            if let Err(e) = control::send_command(instance, &serde_json::json!({ "command": command })) {
+                bot.send_message(chat_id, tr!(lang, "❌ Failed to {} reaction bot '{}': {}", "❌ Не удалось выполнить {} для бота реакций '{}': {}", command, name, e)).await?;
             }
         },
         
-        TelegramCommand::Requisite { filter } => {
+        TelegramCommand::Panic { args } => {
+            let engage = match args.trim().to_lowercase().as_str() {
+                "" | "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(chat_id, tr!(lang, "Usage: /panic or /panic off", "Использование: /panic или /panic off")).await?;
+                    return Ok(());
+                }
+            };
             let mut state = bot_state.lock().await;
             
-            if filter.trim().to_lowercase() == "none" || filter.trim().is_empty() {
-                state.requisite_filter = None;
-                bot.send_message(chat_id, "✅ Requisite filter cleared.").await?;
-            } else {
-                state.requisite_filter = Some(filter.clone());
-                
-                // Special note for "+" filter about T-Bank handling
-                if filter == "+" {
-                    bot.send_message(
-                        chat_id, 
-                        format!("✅ Requisite filter set to: {}\n\n⚠️ Note: With '+' filter, the bot will also react to T-Bank messages regardless of their requisite.", filter)
-                    ).await?;
-                } else {
-                    bot.send_message(chat_id, format!("✅ Requisite filter set to: {}", filter)).await?;
+            // Admins stop everything; others their own and the shared instances
+            let admin = state.is_admin(user);
+            let mut failed = Vec::new();
+            for (name, instance) in state.instances.iter_mut().filter(|(_, instance)| admin || instance.accessible_by(user)) {
+                if let Err(e) = control::set_panic(name, instance, engage) {
+                    failed.push(format!("{}: {}", name, e));
                 }
             }
+            state.save();
             
-            // If the bot is running, we need to restart it for the changes to take effect
-            if state.is_running {
-                bot.send_message(
-                    chat_id, 
-                    "⚠️ Please restart the bot with /stop and then /start for the changes to take effect."
-                ).await?;
+            // Each running bot confirms with a "panic" event, relayed to the admin chat
+            let mut text = if engage {
+                tr!(
+                    lang,
+                    "🛑 Kill switch engaged: no reactions until /panic off, also after restarts.",
+                    "🛑 Аварийная остановка: реакций не будет до /panic off, в том числе после перезапуска."
+                )
+            } else {
+                tr!(lang, "✅ Kill switch released, reactions are back on.", "✅ Аварийная остановка снята, реакции снова ставятся.")
+            };
+            if !failed.is_empty() {
+                text.push_str(&tr!(lang, "\n\n❌ Not reached: {}", "\n\n❌ Не удалось передать: {}", failed.join("; ")));
             }
+            bot.send_message(chat_id, text).await?;
         },
         
-        TelegramCommand::Amount { value } => {
+        TelegramCommand::Backfill { args } => {
             let mut state = bot_state.lock().await;
             
-            state.min_amount = value;
-            bot.send_message(chat_id, format!("✅ Minimum amount set to: {}", value)).await?;
+            // Like /list: an optional instance name and an optional message count per chat
+            let mut name = String::new();
+            let mut limit = None;
+            for word in args.split_whitespace() {
+                match word.parse::<u64>() {
+                    Ok(count) => limit = Some(count.max(1)),
+                    Err(_) => name = word.to_string(),
+                }
+            }
             
-            // If the bot is running, we need to restart it for the changes to take effect
-            if state.is_running {
-                bot.send_message(
-                    chat_id, 
-                    "⚠️ Please restart the bot with /stop and then /start for the changes to take effect."
-                ).await?;
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+            
+            if !instance.is_running {
+                bot.send_message(chat_id, not_running(lang, &name)).await?;
+                return Ok(());
+            }
+            
+            // The bot reports what it found with a "backfill" event, relayed to the admin chat
+            if let Err(e) = control::send_command(instance, &serde_json::json!({ "command": "backfill", "limit": limit })) {
+                bot.send_message(chat_id, tr!(lang, "❌ Failed to backfill reaction bot '{}': {}", "❌ Не удалось проверить пропущенные заявки бота реакций '{}': {}", name, e)).await?;
+                return Ok(());
             }
+            bot.send_message(chat_id, tr!(lang, "🔎 Reaction bot '{}' is reading the chats' recent messages…", "🔎 Бот реакций '{}' читает последние сообщения чатов…", name)).await?;
         },
         
-        TelegramCommand::Clear => {
-            let mut state = bot_state.lock().await;
+        TelegramCommand::Status { target } => {
+            let state = bot_state.lock().await;
+            
+            if target.trim().eq_ignore_ascii_case("all") {
+                let lines = state.accessible(user)
+                    .map(|(name, instance)| format!("• {}: {} ({})", name, instance.status_icon(lang), instance.last_status))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                
+                bot.send_message(chat_id, tr!(lang, "Reaction bot instances:\n\n{}", "Экземпляры бота реакций:\n\n{}", lines)).await?;
+                return Ok(());
+            }
             
-            state.bank_filter = None;
-            state.requisite_filter = None;
-            state.min_amount = 38000; // Reset to default
+            let Some(name) = state.resolve_instance(&target, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &target)).await?;
+                return Ok(());
+            };
+            drop(state);
+            // The running bot's last reactions, asked for over the control channel
+            let recent = control::ask(&bot_state, &name, serde_json::json!({ "command": "recent" }), "recent_reactions")
+                .await
+                .and_then(|reply| reply["reactions"].as_array().map(|reactions| format!("\n\n{}", stats::format_recent(reactions, lang))))
+                .unwrap_or_default();
+            let state = bot_state.lock().await;
+            let Some(instance) = state.instances.get(&name) else { return Ok(()) };
             
-            bot.send_message(chat_id, "✅ All filters cleared and minimum amount reset to default (38000).").await?;
+            let filter_info = instance.filters.describe(lang);
+            // Measured by the running bot and published through its heartbeat
+            let heartbeat = health::read_heartbeat(instance, &name).filter(|_| instance.is_running);
+            let disk = heartbeat
+                .as_ref()
+                .and_then(|hb| hb.disk_usage.as_ref())
+                .map(|disk| format!("\n{}", disk.describe(lang)))
+                .unwrap_or_default();
+            let backlog = heartbeat
+                .as_ref()
+                .and_then(|hb| hb.backlog.as_ref())
+                .map(|backlog| format!("\n{}", backlog.describe(lang)))
+                .unwrap_or_default();
             
-            // If the bot is running, we need to restart it for the changes to take effect
-            if state.is_running {
-                bot.send_message(
-                    chat_id, 
-                    "⚠️ Please restart the bot with /stop and then /start for the changes to take effect."
-                ).await?;
+            bot.send_message(
+                chat_id, 
+                tr!(
+                    lang,
+                    "Reaction bot '{}' status: {}\nLast status: {}\nHealth: {}{}{}\n{}\n\nCurrent settings:\n{}{}",
+                    "Статус бота реакций '{}': {}\nПоследний статус: {}\nЗдоровье: {}{}{}\n{}\n\nТекущие настройки:\n{}{}",
+                    name,
+                    instance.status_icon(lang),
+                    instance.last_status,
+                    instance.health.describe(lang),
+                    disk,
+                    backlog,
+                    instance.describe_paths(lang),
+                    filter_info,
+                    recent
+                )
+            ).await?;
+        },
+        
+        TelegramCommand::Uptime => {
+            let state = bot_state.lock().await;
+            let now = chrono::Utc::now().timestamp();
+            
+            let mut lines = vec![tr!(lang, "Manager: up {}", "Контрольный бот: работает {}", version::format_duration(version::uptime_secs(), lang))];
+            for (name, instance) in state.accessible(user) {
+                // The heartbeat knows the real start time, even for a bot started before a manager restart
+                let uptime = instance.is_running
+                    .then(|| {
+                        health::read_heartbeat(instance, name)
+                            .and_then(|hb| hb.started_at)
+                            .map(|started| now - started)
+                            .or_else(|| instance.supervisor.started_at.map(|t| t.elapsed().as_secs() as i64))
+                    })
+                    .flatten();
+                lines.push(match uptime {
+                    Some(secs) => tr!(lang, "• {}: up {}", "• {}: работает {}", name, version::format_duration(secs, lang)),
+                    None => tr!(lang, "• {}: not running", "• {}: не запущен", name),
+                });
             }
+            
+            bot.send_message(chat_id, lines.join("\n")).await?;
         },
         
-        TelegramCommand::Help => {
-            bot.send_message(
-                chat_id,
-                TelegramCommand::descriptions().to_string(),
-            ).await?;
-        }
-    }
-    
-    Ok(())
-}
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize environment variables and logging
-    dotenv().ok();
-    pretty_env_logger::init();
-    
-    let bot_token = env::var("BOT_TOKEN").expect("BOT_TOKEN must be set in .env file");
-    let allowed_users = env::var("ALLOWED_USERS")
-        .unwrap_or_default()
-        .split(',')
-        .filter_map(|s| s.trim().parse::<i64>().ok())
-        .collect::<Vec<i64>>();
-    
-    info!("Starting Telegram controller bot");
-    info!("Allowed users: {:?}", allowed_users);
-    
-    // Create bot state
-    let bot_state = Arc::new(Mutex::new(BotState::new()));
-    
-    // Create bot instance
-    let bot = Bot::new(bot_token);
-    
-    // Set bot commands
-    bot.set_my_commands(TelegramCommand::bot_commands()).await?;
-    
-    // Clone allowed_users for the closure
-    let allowed_users_clone = allowed_users.clone();
-    
-    // Start command handler
-    let handler = Update::filter_message()
-        .filter_map(move |message: Message| {
-            let user_id = message.from().map(|user| user.id.0 as i64);
+        TelegramCommand::Version => {
+            let state = bot_state.lock().await;
+            
+            let mut lines = vec![tr!(lang, "Manager: {} {} ({})", "Контрольный бот: {} {} ({})", env!("CARGO_PKG_NAME"), version::VERSION, version::COMMIT)];
+            for (name, instance) in state.accessible(user) {
+                // Reported by the reaction bot itself, so this is the binary that actually ran
+                let line = match health::read_heartbeat(instance, name) {
+                    Some(hb) => {
+                        let unknown = lang.pick("unknown", "неизвестно");
+                        let last_run = if instance.is_running { "" } else { lang.pick(" (last run)", " (последний запуск)") };
+                        tr!(
+                            lang,
+                            "• {}: {} ({}), TDLib {}{}",
+                            "• {}: {} ({}), TDLib {}{}",
+                            name,
+                            hb.version.as_deref().unwrap_or(unknown),
+                            hb.commit.as_deref().unwrap_or(unknown),
+                            hb.tdlib_version.as_deref().unwrap_or(unknown),
+                            last_run
+                        )
+                    },
+                    None => tr!(lang, "• {}: unknown (no heartbeat yet, start it first)", "• {}: неизвестно (heartbeat еще не получен, сначала запустите)", name),
+                };
+                lines.push(line);
+            }
+            
+            bot.send_message(chat_id, lines.join("\n")).await?;
+        },
+        
+        TelegramCommand::Stats { name } => {
+            let state = bot_state.lock().await;
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let instance = &state.instances[&name];
+            
+            // Filter counters live in the running bot and are published through its heartbeat
+            let mut reply = match health::read_heartbeat(instance, &name).and_then(|hb| hb.filter_stats) {
+                Some(filter_stats) if instance.is_running => stats::format(&name, &filter_stats, lang),
+                _ => tr!(lang, "📊 No filter statistics for '{}': the reaction bot isn't running.", "📊 Нет статистики фильтров для '{}': бот реакций не запущен.", name),
+            };
+            // Race results are kept in the history, so they cover every run
+            let path = instance.history_path(&name);
+            // Out of reach in another store, and missing (nothing to say) in SQLite
+            if let Some(note) = history::other_store().and_then(|_| history::unavailable(&name, &path, lang)) {
+                reply.push_str(&format!("\n\n{}", note));
+            } else if std::path::Path::new(&path).exists() {
+                match history::race_summary(&path, 0) {
+                    Ok(race) => reply.push_str(&format!("\n\n{}", stats::format_race(&race, lang))),
+                    Err(e) => warn!("Failed to read race results of '{}': {}", name, e),
+                }
+                let commission = stats::default_commission();
+                match history::earnings(&path, 0, commission) {
+                    Ok(earnings) => reply.push_str(&format!("\n\n{}", stats::format_earnings(&earnings, commission, lang))),
+                    Err(e) => warn!("Failed to read earnings of '{}': {}", name, e),
+                }
+            }
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::Competitors { args } => {
+            let state = bot_state.lock().await;
+            
+            let mut since = chrono::Utc::now().timestamp() - competitors::DEFAULT_PERIOD_SECS;
+            let mut name = String::new();
+            for word in args.split_whitespace() {
+                match history::parse_since(word) {
+                    Some(timestamp) => since = timestamp,
+                    None => name = word.to_string(),
+                }
+            }
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let path = state.instances[&name].history_path(&name);
+            
+            let reply = if let Some(reply) = history::unavailable(&name, &path, lang) {
+                reply
+            } else {
+                match competitors::section(&path, since, lang) {
+                    Ok(Some(section)) => format!("🥊 {}\n{}", name, section),
+                    Ok(None) => tr!(lang, "🥊 No competing reactions recorded for '{}' in this period.", "🥊 Чужих реакций для '{}' за этот период не записано.", name),
+                    Err(e) => tr!(lang, "❌ Failed to read the reaction history of '{}': {}", "❌ Не удалось прочитать историю реакций '{}': {}", name, e),
+                }
+            };
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::List { args } => {
+            let state = bot_state.lock().await;
             
-            // Check if user is allowed
-            if let Some(user_id) = user_id {
-                if !allowed_users_clone.is_empty() && !allowed_users_clone.contains(&user_id) {
-                    info!("Unauthorized access attempt from user {}", user_id);
-                    return None;
+            // Arguments are an optional instance name and an optional count, in either order
+            let mut name = String::new();
+            let mut limit = history::DEFAULT_LIST_LIMIT;
+            for word in args.split_whitespace() {
+                match word.parse::<usize>() {
+                    Ok(count) => limit = count.clamp(1, history::MAX_LIST_LIMIT),
+                    Err(_) => name = word.to_string(),
                 }
+            }
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let path = state.instances[&name].history_path(&name);
+            
+            let reply = if let Some(reply) = history::unavailable(&name, &path, lang) {
+                reply
             } else {
+                match history::recent(&path, limit) {
+                    Ok(reactions) => history::format(&name, &reactions, lang),
+                    Err(e) => tr!(lang, "❌ Failed to read the reaction history of '{}': {}", "❌ Не удалось прочитать историю реакций '{}': {}", name, e),
+                }
+            };
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::History { args } => {
+            let state = bot_state.lock().await;
+            
+            // Like /list, but the count is the page size
+            let mut name = String::new();
+            let mut limit = history::DEFAULT_PAGE_SIZE;
+            for word in args.split_whitespace() {
+                match word.parse::<usize>() {
+                    Ok(count) => limit = count.clamp(1, history::MAX_PAGE_SIZE),
+                    Err(_) => name = word.to_string(),
+                }
+            }
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let path = state.instances[&name].history_path(&name);
+            
+            if let Some(reply) = history::unavailable(&name, &path, lang) {
+                bot.send_message(chat_id, reply).await?;
+                return Ok(());
+            }
+            let (text, buttons) = history::format_page(&name, &path, 0, limit, lang);
+            let request = bot.send_message(chat_id, text);
+            match buttons {
+                Some(buttons) => request.reply_markup(buttons).await?,
+                None => request.await?,
+            };
+        },
+        
+        TelegramCommand::Why { args } => {
+            let state = bot_state.lock().await;
+            
+            let mut message = None;
+            let mut name = String::new();
+            for word in args.split_whitespace() {
+                match word.parse::<i64>() {
+                    Ok(id) if id > 0 => message = Some(id),
+                    _ => name = word.to_string(),
+                }
+            }
+            let Some(message) = message else {
+                bot.send_message(chat_id, lang.pick("Usage: /why <message number from its link> [instance]", "Использование: /why <номер сообщения из ссылки> [экземпляр]")).await?;
+                return Ok(());
+            };
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let path = state.instances[&name].history_path(&name);
+            
+            if let Some(reply) = history::unavailable(&name, &path, lang) {
+                bot.send_message(chat_id, reply).await?;
+                return Ok(());
+            }
+            bot.send_message(chat_id, history::format_decisions(&name, &path, message, lang)).await?;
+        },
+        
+        TelegramCommand::Export { args } => {
+            let state = bot_state.lock().await;
+            
+            let mut words = args.split_whitespace();
+            let format = words.next().unwrap_or_default().to_lowercase();
+            let mut since = 0;
+            let mut name = String::new();
+            for word in words {
+                match history::parse_since(word) {
+                    Some(timestamp) => since = timestamp,
+                    None => name = word.to_string(),
+                }
+            }
+            
+            if format != "csv" && format != "json" {
+                bot.send_message(
+                    chat_id,
+                    lang.pick(
+                        "Usage: /export csv|json [since] [instance], e.g. /export csv 7d or /export json 2026-10-01 backup",
+                        "Использование: /export csv|json [с] [экземпляр], например /export csv 7d или /export json 2026-10-01 backup"
+                    )
+                ).await?;
+                return Ok(());
+            }
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let path = state.instances[&name].history_path(&name);
+            drop(state);
+            
+            if let Some(reply) = history::unavailable(&name, &path, lang) {
+                bot.send_message(chat_id, reply).await?;
+                return Ok(());
+            }
+            
+            let contents = history::since(&path, since).map_err(anyhow::Error::from).and_then(|reactions| {
+                Ok(if format == "csv" { history::to_csv(&reactions) } else { history::to_json(&reactions)? })
+            });
+            match contents {
+                Ok(contents) => {
+                    let file_name = format!("{}-reactions-{}.{}", name, timezone::now().format("%Y%m%d"), format);
+                    bot.send_document(chat_id, InputFile::memory(contents.into_bytes()).file_name(file_name)).await?;
+                },
+                Err(e) => {
+                    bot.send_message(chat_id, tr!(lang, "❌ Failed to export the reaction history of '{}': {}", "❌ Не удалось выгрузить историю реакций '{}': {}", name, e)).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Instance { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let mut parts = args.split_whitespace();
+            let action = parts.next().unwrap_or("list").to_lowercase();
+            let name = parts.next();
+            
+            match (action.as_str(), name) {
+                // The name becomes part of file names (instance file, control socket, heartbeat, history)
+                ("add", Some(name)) if !accounts::valid_name(name) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "❌ Instance names may only contain letters, digits, '-' and '_'.",
+                            "❌ Имя экземпляра может содержать только буквы, цифры, '-' и '_'."
+                        )
+                    ).await?;
+                },
+                ("add", Some(name)) => {
+                    let (binary_path, session_dir) = (parts.next(), parts.next());
+                    // The manager runs whatever binary the instance names, and a session directory
+                    // may be another instance's
+                    if binary_path.is_some() && !state.is_admin(user) {
+                        bot.send_message(
+                            chat_id,
+                            lang.pick(
+                                "❌ Only admins (ALLOWED_USERS) can choose an instance's binary and session directory.",
+                                "❌ Выбирать бинарник и каталог сессии экземпляра могут только администраторы (ALLOWED_USERS)."
+                            )
+                        ).await?;
+                        return Ok(());
+                    }
+                    if state.instances.contains_key(name) {
+                        bot.send_message(chat_id, tr!(lang, "❌ Instance '{}' already exists.", "❌ Экземпляр '{}' уже существует.", name)).await?;
+                        return Ok(());
+                    }
+                    
+                    // Every instance needs its own TDLib session directory; it belongs to whoever added it
+                    let instance = state::Instance {
+                        binary_path: binary_path.map(str::to_string),
+                        session_dir: Some(session_dir.map(str::to_string).unwrap_or_else(|| state::session_dir(name))),
+                        owner: user.map(|user| user.0),
+                        ..Default::default()
+                    };
+                    let paths = instance.describe_paths(lang);
+                    state.instances.insert(name.to_string(), instance);
+                    
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "✅ Instance '{}' added:\n{}\n\nUse /instance use {} to configure its filters.",
+                            "✅ Экземпляр '{}' добавлен:\n{}\n\nИспользуйте /instance use {}, чтобы настроить его фильтры.",
+                            name,
+                            paths,
+                            name
+                        )
+                    ).await?;
+                },
+                ("use", Some(name)) => {
+                    if state.resolve_instance(name, user).is_none() {
+                        bot.send_message(chat_id, unknown_instance(lang, name)).await?;
+                        return Ok(());
+                    }
+                    
+                    state.select(user, name);
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "✅ Selected instance '{}'. Filter commands now apply to it.",
+                            "✅ Выбран экземпляр '{}'. Команды фильтров теперь применяются к нему.",
+                            name
+                        )
+                    ).await?;
+                },
+                ("remove", Some(name)) => {
+                    let shared = state.instances.values().filter(|instance| instance.owner.is_none()).count();
+                    match state.instances.get(name).filter(|instance| instance.accessible_by(user)) {
+                        None => {
+                            bot.send_message(chat_id, unknown_instance(lang, name)).await?;
+                        },
+                        Some(instance) if instance.is_running => {
+                            bot.send_message(chat_id, tr!(lang, "❌ Stop instance '{}' before removing it.", "❌ Остановите экземпляр '{}' перед удалением.", name)).await?;
+                        },
+                        // Users without an instance of their own fall back to a shared one
+                        Some(instance) if instance.owner.is_none() && shared == 1 => {
+                            bot.send_message(chat_id, lang.pick("❌ Can't remove the last shared instance.", "❌ Нельзя удалить последний общий экземпляр.")).await?;
+                        },
+                        Some(_) => {
+                            state.instances.remove(name);
+                            state.selections.retain(|_, selected| selected != name);
+                            if state.selected_instance == name {
+                                state.selected_instance = state.first_shared();
+                            }
+                            bot.send_message(
+                                chat_id,
+                                tr!(
+                                    lang,
+                                    "✅ Instance '{}' removed. Selected instance: {}",
+                                    "✅ Экземпляр '{}' удален. Выбранный экземпляр: {}",
+                                    name,
+                                    state.selected_name(user)
+                                )
+                            ).await?;
+                        }
+                    }
+                },
+                ("list", _) => {
+                    let selected_name = state.selected_name(user);
+                    let list = state.accessible(user)
+                        .map(|(name, instance)| {
+                            let mut markers = String::new();
+                            if instance.owner.is_none() {
+                                markers.push_str(lang.pick(" (shared)", " (общий)"));
+                            }
+                            if *name == selected_name {
+                                markers.push_str(lang.pick(" (selected)", " (выбран)"));
+                            }
+                            format!("• {}{}: {}\n{}", name, markers, instance.status_icon(lang), instance.describe_paths(lang))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    bot.send_message(chat_id, tr!(lang, "Instances:\n\n{}", "Экземпляры:\n\n{}", list)).await?;
+                },
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "Usage:\n/instance add <name> [binary_path] [session_dir]\n/instance use <name>\n/instance remove <name>\n/instance list",
+                            "Использование:\n/instance add <имя> [бинарник] [каталог_сессии]\n/instance use <имя>\n/instance remove <имя>\n/instance list"
+                        )
+                    ).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Account { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let mut parts = args.split_whitespace();
+            let action = parts.next().unwrap_or("list").to_lowercase();
+            let account = parts.next();
+            
+            match (action.as_str(), account) {
+                (_, Some(account)) if !accounts::valid_name(account) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "❌ Account names may only contain letters, digits, '-' and '_'.",
+                            "❌ Имя аккаунта может содержать только буквы, цифры, '-' и '_'."
+                        )
+                    ).await?;
+                },
+                ("add", Some(account)) => {
+                    let reply = match std::fs::create_dir_all(accounts::account_dir(account)) {
+                        Ok(()) => tr!(
+                            lang,
+                            "✅ Account '{}' added. Switch an instance to it with /account use {}; the login prompts will come here.",
+                            "✅ Аккаунт '{}' добавлен. Переключите на него экземпляр командой /account use {}; запросы входа придут сюда.",
+                            account,
+                            account
+                        ),
+                        Err(e) => tr!(lang, "❌ Failed to create account '{}': {}", "❌ Не удалось создать аккаунт '{}': {}", account, e),
+                    };
+                    bot.send_message(chat_id, reply).await?;
+                },
+                ("use", Some(account)) => {
+                    let instance_name = parts.next().unwrap_or_default();
+                    let Some(name) = state.resolve_instance(instance_name, user) else {
+                        bot.send_message(chat_id, unknown_instance(lang, instance_name)).await?;
+                        return Ok(());
+                    };
+                    if !accounts::list().iter().any(|existing| existing == account) {
+                        bot.send_message(chat_id, tr!(lang, "❌ Unknown account '{}'. Add it with /account add {}.", "❌ Неизвестный аккаунт '{}'. Добавьте его командой /account add {}.", account, account)).await?;
+                        return Ok(());
+                    }
+                    // TDLib can't open one session from two processes
+                    let dir = accounts::account_dir(account);
+                    if let Some((other, _)) = state.instances.iter().find(|(other, instance)| **other != name && instance.session_dir.as_deref() == Some(dir.as_str())) {
+                        bot.send_message(chat_id, tr!(lang, "❌ Account '{}' is already used by instance '{}'.", "❌ Аккаунт '{}' уже используется экземпляром '{}'.", account, other)).await?;
+                        return Ok(());
+                    }
+                    
+                    let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+                    instance.session_dir = Some(dir);
+                    let mut reply = tr!(lang, "✅ Instance '{}' now uses account '{}'.", "✅ Экземпляр '{}' теперь использует аккаунт '{}'.", name, account);
+                    if !accounts::is_authorized(account) {
+                        reply.push_str(lang.pick(
+                            "\nThe account hasn't logged in yet: the reaction bot will ask for the phone number and code on start (answer with /auth).",
+                            "\nАккаунт еще не авторизован: при запуске бот реакций запросит номер телефона и код (ответьте командой /auth)."
+                        ));
+                    }
+                    if instance.is_running {
+                        reply.push('\n');
+                        reply.push_str(restart_hint(lang));
+                    }
+                    bot.send_message(chat_id, reply).await?;
+                },
+                ("list", _) => {
+                    let accounts = accounts::list();
+                    let reply = if accounts.is_empty() {
+                        lang.pick("No accounts yet. Add one with /account add <name>.", "Аккаунтов пока нет. Добавьте командой /account add <имя>.").to_string()
+                    } else {
+                        let lines = accounts
+                            .iter()
+                            .map(|account| {
+                                let dir = accounts::account_dir(account);
+                                let used_by = state.accessible(user)
+                                    .filter(|(_, instance)| instance.session_dir.as_deref() == Some(dir.as_str()))
+                                    .map(|(name, _)| name.as_str())
+                                    .collect::<Vec<_>>();
+                                let mut line = format!(
+                                    "• {}: {}",
+                                    account,
+                                    if accounts::is_authorized(account) { lang.pick("logged in", "авторизован") } else { lang.pick("not logged in", "не авторизован") }
+                                );
+                                if !used_by.is_empty() {
+                                    line.push_str(&tr!(lang, ", used by {}", ", используется: {}", used_by.join(", ")));
+                                }
+                                line
+                            })
+                            .collect::<Vec<_>>();
+                        tr!(lang, "Accounts:\n{}", "Аккаунты:\n{}", lines.join("\n"))
+                    };
+                    bot.send_message(chat_id, reply).await?;
+                },
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "Usage:\n/account list\n/account add <name>\n/account use <name> [instance]",
+                            "Использование:\n/account list\n/account add <имя>\n/account use <имя> [экземпляр]"
+                        )
+                    ).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Logout { name } => {
+            let mut state = bot_state.lock().await;
+            
+            let Some(name) = state.resolve_instance(&name, user) else {
+                bot.send_message(chat_id, unknown_instance(lang, &name)).await?;
+                return Ok(());
+            };
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+            
+            // TDLib can't open the session while the bot holds it
+            if instance.is_running {
+                bot.send_message(chat_id, tr!(lang, "❌ Stop instance '{}' before logging it out.", "❌ Остановите экземпляр '{}' перед выходом из аккаунта.", name)).await?;
+                return Ok(());
+            }
+            // Nothing restarts it while it's logged out
+            instance.supervisor.reset();
+            let logout = process::logout(instance.binary_path(), instance.session_dir.clone());
+            drop(state);
+            
+            bot.send_message(chat_id, tr!(lang, "Logging '{}' out…", "Выход из аккаунта '{}'…", name)).await?;
+            let reply = match logout.await {
+                Ok(output) if output.status.success() => tr!(
+                    lang,
+                    "✅ '{}' logged out and its session deleted. The next /start will ask for a login.",
+                    "✅ '{}': выход выполнен, сессия удалена. При следующем /start потребуется вход.",
+                    name
+                ),
+                Ok(output) => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    let reason = stderr.lines().last().unwrap_or_default().to_string();
+                    tr!(lang, "❌ Failed to log '{}' out: {}", "❌ Не удалось выйти из аккаунта '{}': {}", name, reason)
+                },
+                Err(e) => tr!(lang, "❌ Failed to log '{}' out: {}", "❌ Не удалось выйти из аккаунта '{}': {}", name, e),
+            };
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::Snapshot { ref args } | TelegramCommand::Restore { name: ref args } => {
+            let restore = matches!(command, TelegramCommand::Restore { .. });
+            let mut state = bot_state.lock().await;
+            let name = state.selected_name(user);
+            let instance = state.selected_mut(user);
+            let args = args.trim();
+            
+            if (!restore && args == "list") || (restore && args.is_empty()) {
+                let snapshots = snapshot::list(&name);
+                let text = if snapshots.is_empty() {
+                    tr!(lang, "No snapshots of '{}' yet. Take one with /snapshot.", "Копий сессии '{}' пока нет. Создать: /snapshot", name)
+                } else {
+                    let list = snapshots.iter().map(|snapshot| format!("• {}", snapshot)).collect::<Vec<_>>().join("\n");
+                    tr!(lang, "Snapshots of '{}', newest first:\n\n{}\n\nRestore with /restore <name>.", "Копии сессии '{}', новые сверху:\n\n{}\n\nВосстановить: /restore <имя>", name, list)
+                };
+                bot.send_message(chat_id, text).await?;
+                return Ok(());
+            }
+            if !restore && !args.is_empty() {
+                bot.send_message(chat_id, lang.pick("Usage: /snapshot, /snapshot list", "Использование: /snapshot, /snapshot list")).await?;
+                return Ok(());
+            }
+            // Another instance's snapshot would bring its session over
+            if restore && !snapshot::belongs_to(args, &name) {
+                bot.send_message(
+                    chat_id,
+                    tr!(lang, "❌ '{}' is not a snapshot of '{}'. See /restore for the list.", "❌ '{}' - не копия сессии '{}'. Список: /restore", args, name)
+                ).await?;
+                return Ok(());
+            }
+            
+            // TDLib's database can't be copied or replaced under a running bot
+            let was_running = instance.is_running;
+            if was_running {
+                let Some(mut child) = instance.reaction_bot_process.take() else {
+                    bot.send_message(
+                        chat_id,
+                        tr!(lang, "⚠️ No process handle for '{}'. Stop it with /stop first.", "⚠️ Нет дескриптора процесса '{}'. Сначала остановите его командой /stop.", name)
+                    ).await?;
+                    return Ok(());
+                };
+                instance.control = None;
+                instance.pending_auth = None;
+                if let Err(e) = process::terminate(&mut child, STOP_TIMEOUT).await {
+                    instance.reaction_bot_process = Some(child);
+                    bot.send_message(chat_id, tr!(lang, "❌ Failed to stop reaction bot '{}': {}", "❌ Не удалось остановить бот реакций '{}': {}", name, e)).await?;
+                    return Ok(());
+                }
+                instance.is_running = false;
+            }
+            instance.supervisor.reset();
+            instance.last_status = lang.pick("Stopped", "Остановлен").to_string();
+            let data_dir = instance.data_dir();
+            drop(state);
+            
+            let reply = if restore {
+                bot.send_message(chat_id, tr!(lang, "Restoring '{}' from {}…", "Восстановление '{}' из {}…", name, args)).await?;
+                match snapshot::restore(&name, args, data_dir.clone()).await {
+                    Ok(()) => tr!(
+                        lang,
+                        "✅ Session of '{}' restored from {}. The one it replaced is in {}.before-restore.",
+                        "✅ Сессия '{}' восстановлена из {}. Прежняя сохранена в {}.before-restore.",
+                        name,
+                        args,
+                        data_dir
+                    ),
+                    Err(e) => tr!(lang, "❌ Failed to restore '{}': {}", "❌ Не удалось восстановить '{}': {}", name, e),
+                }
+            } else {
+                bot.send_message(chat_id, tr!(lang, "Taking a snapshot of '{}'…", "Копирование сессии '{}'…", name)).await?;
+                match snapshot::create(&name, data_dir).await {
+                    Ok((snapshot, size)) => tr!(
+                        lang,
+                        "✅ Snapshot {} saved ({} KB). Roll back to it with /restore {}.",
+                        "✅ Копия {} сохранена ({} КБ). Вернуться к ней: /restore {}",
+                        snapshot,
+                        size / 1024,
+                        snapshot
+                    ),
+                    Err(e) => tr!(lang, "❌ Failed to take a snapshot of '{}': {}", "❌ Не удалось скопировать сессию '{}': {}", name, e),
+                }
+            };
+            bot.send_message(chat_id, reply).await?;
+            
+            if was_running {
+                let mut state = bot_state.lock().await;
+                let Some(instance) = state.instances.get_mut(&name) else {
+                    return Ok(());
+                };
+                if !instance.is_running {
+                    if let Err(e) = launch(&name, instance, &events, lang) {
+                        instance.last_status = tr!(lang, "Failed to start: {}", "Ошибка запуска: {}", e);
+                        bot.send_message(chat_id, tr!(lang, "❌ Failed to start reaction bot '{}': {}", "❌ Не удалось запустить бот реакций '{}': {}", name, e)).await?;
+                    } else {
+                        bot.send_message(chat_id, tr!(lang, "✅ Reaction bot '{}' started again.", "✅ Бот реакций '{}' снова запущен.", name)).await?;
+                    }
+                }
+            }
+        },
+        
+        TelegramCommand::Chats { ids } => {
+            let mut state = bot_state.lock().await;
+            let name = state.selected_name(user);
+            let instance = state.selected_mut(user);
+            
+            let ids = ids.trim();
+            if ids.is_empty() {
+                bot.send_message(chat_id, tr!(lang, "Chats monitored by '{}': {}", "Чаты, которые отслеживает '{}': {}", name, instance.describe_chats(lang))).await?;
+                return Ok(());
+            }
+            
+            if ids.eq_ignore_ascii_case("off") {
+                instance.allowed_chats = None;
+            } else {
+                let parsed = ids
+                    .split([',', ' '])
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse::<i64>())
+                    .collect::<Result<Vec<_>, _>>();
+                match parsed {
+                    Ok(chats) => instance.allowed_chats = Some(chats),
+                    Err(_) => {
+                        bot.send_message(
+                            chat_id,
+                            lang.pick(
+                                "❌ Chat IDs must be numbers separated by commas (e.g., /chats -1002685602852,-4649902952).",
+                                "❌ ID чатов должны быть числами через запятую (например, /chats -1002685602852,-4649902952)."
+                            )
+                        ).await?;
+                        return Ok(());
+                    }
+                }
+            }
+            
+            bot.send_message(chat_id, tr!(lang, "✅ Chats monitored by '{}': {}", "✅ Чаты, которые отслеживает '{}': {}", name, instance.describe_chats(lang))).await?;
+            
+            if instance.is_running {
+                bot.send_message(chat_id, restart_hint(lang)).await?;
+            }
+        },
+        
+        TelegramCommand::Bank { filter } => {
+            let mut state = bot_state.lock().await;
+            
+            if filter.trim().to_lowercase() == "none" || filter.trim().is_empty() {
+                state.selected_mut(user).filters.bank_filter = None;
+                bot.send_message(chat_id, lang.pick("✅ Bank filter cleared.", "✅ Фильтр по банку сброшен.")).await?;
+            } else {
+                state.selected_mut(user).filters.bank_filter = Some(filter.clone());
+                bot.send_message(chat_id, tr!(lang, "✅ Bank filter set to: {}", "✅ Фильтр по банку: {}", filter)).await?;
+            }
+            
+            // If the bot is running, we need to restart it for the changes to take effect
+            if state.selected(user).is_running {
+                bot.send_message(
+                    chat_id, 
+                    restart_hint(lang)
+                ).await?;
+            }
+        },
+        
+        TelegramCommand::Requisite { filter } => {
+            let mut state = bot_state.lock().await;
+            
+            if filter.trim().to_lowercase() == "none" || filter.trim().is_empty() {
+                state.selected_mut(user).filters.requisite_filter = None;
+                bot.send_message(chat_id, lang.pick("✅ Requisite filter cleared.", "✅ Фильтр по реквизитам сброшен.")).await?;
+            } else {
+                state.selected_mut(user).filters.requisite_filter = Some(filter.clone());
+                
+                // Special note for "+" filter about T-Bank handling
+                if filter == "+" {
+                    bot.send_message(
+                        chat_id, 
+                        tr!(
+                            lang,
+                            "✅ Requisite filter set to: {}\n\n⚠️ Note: With '+' filter, the bot will also react to T-Bank messages regardless of their requisite.",
+                            "✅ Фильтр по реквизитам: {}\n\n⚠️ Примечание: с фильтром '+' бот также реагирует на сообщения Т-Банка независимо от реквизитов.",
+                            filter
+                        )
+                    ).await?;
+                } else {
+                    bot.send_message(chat_id, tr!(lang, "✅ Requisite filter set to: {}", "✅ Фильтр по реквизитам: {}", filter)).await?;
+                }
+            }
+            
+            // If the bot is running, we need to restart it for the changes to take effect
+            if state.selected(user).is_running {
+                bot.send_message(
+                    chat_id, 
+                    restart_hint(lang)
+                ).await?;
+            }
+        },
+        
+        TelegramCommand::Blacklist { args } => {
+            let mut state = bot_state.lock().await;
+            let (action, requisite) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let requisite = requisite.trim();
+            
+            let text = match (action.to_lowercase().as_str(), requisite) {
+                // Entries are passed on comma separated
+                ("add", requisite) if !requisite.is_empty() && !requisite.contains(',') => {
+                    if let Some(entry) = blacklist::find(&state.blacklist, requisite) {
+                        tr!(lang, "Already blacklisted: {}", "Уже в черном списке: {}", entry)
+                    } else {
+                        state.blacklist.insert(requisite.to_string());
+                        tr!(lang, "✅ Blacklisted: {}", "✅ Добавлено в черный список: {}", requisite)
+                    }
+                }
+                ("remove", requisite) if !requisite.is_empty() => match blacklist::find(&state.blacklist, requisite).cloned() {
+                    Some(entry) => {
+                        state.blacklist.remove(&entry);
+                        tr!(lang, "✅ Removed from the blacklist: {}", "✅ Удалено из черного списка: {}", entry)
+                    }
+                    None => tr!(lang, "❌ Not blacklisted: {}", "❌ Нет в черном списке: {}", requisite),
+                },
+                ("list", "") | ("", "") => {
+                    let text = if state.blacklist.is_empty() {
+                        lang.pick("The blacklist is empty.", "Черный список пуст.").to_string()
+                    } else {
+                        let list = state.blacklist.iter().map(|entry| format!("• {}", entry)).collect::<Vec<_>>().join("\n");
+                        tr!(lang, "Blacklisted requisites:\n\n{}", "Реквизиты в черном списке:\n\n{}", list)
+                    };
+                    bot.send_message(chat_id, text).await?;
+                    return Ok(());
+                }
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "Usage:\n/blacklist add <card or phone>\n/blacklist remove <card or phone>\n/blacklist list",
+                            "Использование:\n/blacklist add <карта или телефон>\n/blacklist remove <карта или телефон>\n/blacklist list"
+                        )
+                    ).await?;
+                    return Ok(());
+                }
+            };
+            
+            if let Err(e) = blacklist::save(&state.blacklist) {
+                bot.send_message(chat_id, tr!(lang, "❌ Failed to save the blacklist: {}", "❌ Не удалось сохранить черный список: {}", e)).await?;
+                return Ok(());
+            }
+            
+            // Running bots take the new list right away
+            let command = serde_json::json!({ "command": "blacklist", "requisites": state.blacklist });
+            for (name, instance) in state.instances.iter_mut().filter(|(_, instance)| instance.is_running) {
+                if let Err(e) = control::send_command(instance, &command) {
+                    warn!("Failed to send the blacklist to reaction bot '{}': {}", name, e);
+                }
+            }
+            bot.send_message(chat_id, text).await?;
+        },
+        
+        TelegramCommand::Amount { args } => {
+            let mut state = bot_state.lock().await;
+            let filters = &mut state.selected_mut(user).filters;
+            
+            let words = args.split_whitespace().collect::<Vec<_>>();
+            let invalid_amount = |value: &str| tr!(lang, "❌ Invalid amount '{}'.", "❌ Неверная сумма '{}'.", value);
+            let result = match words.as_slice() {
+                [value] => match value.parse::<i32>() {
+                    Ok(value) => {
+                        filters.min_amount = value;
+                        Ok(tr!(lang, "✅ Minimum amount set to: {}", "✅ Минимальная сумма: {}", value))
+                    },
+                    Err(_) => Err(invalid_amount(value)),
+                },
+                [bank, "off"] => {
+                    let bank = bank.to_lowercase();
+                    match filters.bank_min_amounts.remove(&bank) {
+                        Some(_) => Ok(tr!(
+                            lang,
+                            "✅ Minimum amount for {} removed, the default ({}) applies.",
+                            "✅ Отдельная сумма для {} удалена, действует общая ({}).",
+                            bank,
+                            filters.min_amount
+                        )),
+                        None => Err(tr!(lang, "❌ No minimum amount set for {}.", "❌ Для {} не задана отдельная сумма.", bank)),
+                    }
+                },
+                [bank, value] => match value.parse::<i32>() {
+                    Ok(value) => {
+                        let bank = bank.to_lowercase();
+                        let reply = tr!(lang, "✅ Minimum amount for {} set to: {}", "✅ Минимальная сумма для {}: {}", bank, value);
+                        filters.bank_min_amounts.insert(bank, value);
+                        Ok(reply)
+                    },
+                    Err(_) => Err(invalid_amount(value)),
+                },
+                _ => Err(lang.pick(
+                    "Usage: /amount <value>, /amount <bank> <value> or /amount <bank> off",
+                    "Использование: /amount <сумма>, /amount <банк> <сумма> или /amount <банк> off"
+                ).to_string()),
+            };
+            
+            match result {
+                Ok(reply) => bot.send_message(chat_id, reply).await?,
+                Err(reply) => {
+                    bot.send_message(chat_id, reply).await?;
+                    return Ok(());
+                }
+            };
+            
+            // If the bot is running, we need to restart it for the changes to take effect
+            if state.selected(user).is_running {
+                bot.send_message(
+                    chat_id, 
+                    restart_hint(lang)
+                ).await?;
+            }
+        },
+        
+        TelegramCommand::Profit { value } => {
+            let mut state = bot_state.lock().await;
+            let filters = &mut state.selected_mut(user).filters;
+            
+            let reply = match value.trim() {
+                "off" | "0" => {
+                    filters.min_profit = 0.0;
+                    lang.pick("✅ Minimum expected profit removed.", "✅ Минимальная ожидаемая прибыль отключена.").to_string()
+                },
+                value => match value.replace(',', ".").parse::<f64>() {
+                    Ok(profit) if profit > 0.0 => {
+                        filters.min_profit = profit;
+                        tr!(lang, "✅ Minimum expected profit set to: {} ₽", "✅ Минимальная ожидаемая прибыль: {} ₽", profit)
+                    },
+                    _ => {
+                        bot.send_message(chat_id, lang.pick("Usage: /profit <rubles> or /profit off", "Использование: /profit <рубли> или /profit off")).await?;
+                        return Ok(());
+                    }
+                },
+            };
+            bot.send_message(chat_id, reply).await?;
+            
+            if state.selected(user).is_running {
+                bot.send_message(chat_id, restart_hint(lang)).await?;
+            }
+        },
+        
+        TelegramCommand::Balance { args } => {
+            let mut state = bot_state.lock().await;
+            let name = state.selected_name(user);
+            let instance = state.selected_mut(user);
+            let words: Vec<&str> = args.split_whitespace().collect();
+            
+            // Applied right away when the bot runs (it confirms with a "balance" event); the daily
+            // refill time only on the next start
+            let total = match words.as_slice() {
+                [] => {
+                    let text = match (instance.balance, &instance.balance_reset) {
+                        (Some(total), reset) => {
+                            let mut text = tr!(
+                                lang,
+                                "💰 Working balance of '{}': {} of {} ₽ left.",
+                                "💰 Рабочий баланс '{}': осталось {} из {} ₽.",
+                                name,
+                                instance.balance_left.unwrap_or(total),
+                                total
+                            );
+                            if let Some(reset) = reset {
+                                text.push_str(&tr!(lang, "\nRefilled every day at {}.", "\nПополняется каждый день в {}.", reset));
+                            }
+                            text
+                        }
+                        (None, _) => tr!(lang, "💰 No working balance set for '{}'.", "💰 Рабочий баланс для '{}' не задан.", name),
+                    };
+                    bot.send_message(chat_id, text).await?;
+                    return Ok(());
+                }
+                ["set", amount] if amount.parse::<i64>().is_ok_and(|amount| amount > 0) => amount.parse::<i64>().ok(),
+                ["reset"] if instance.balance.is_some() => instance.balance,
+                ["off"] => None,
+                ["daily", "off"] => {
+                    instance.balance_reset = None;
+                    bot.send_message(chat_id, lang.pick("✅ Daily balance refill removed.", "✅ Ежедневное пополнение баланса отключено.")).await?;
+                    if instance.is_running {
+                        bot.send_message(chat_id, restart_hint(lang)).await?;
+                    }
+                    return Ok(());
+                }
+                ["daily", time] if chrono::NaiveTime::parse_from_str(time, "%H:%M").is_ok() => {
+                    instance.balance_reset = Some(time.to_string());
+                    bot.send_message(chat_id, tr!(lang, "✅ The working balance is refilled every day at {}.", "✅ Рабочий баланс пополняется каждый день в {}.", time)).await?;
+                    if instance.is_running {
+                        bot.send_message(chat_id, restart_hint(lang)).await?;
+                    }
+                    return Ok(());
+                }
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "Usage: /balance, /balance set <rubles>, /balance reset, /balance daily <HH:MM|off>, /balance off",
+                            "Использование: /balance, /balance set <рубли>, /balance reset, /balance daily <ЧЧ:ММ|off>, /balance off",
+                        ),
+                    ).await?;
+                    return Ok(());
+                }
+            };
+            
+            instance.balance = total;
+            instance.balance_left = total;
+            let text = match total {
+                Some(total) => tr!(lang, "✅ Working balance of '{}' set to {} ₽.", "✅ Рабочий баланс '{}': {} ₽.", name, total),
+                None => tr!(lang, "✅ Working balance of '{}' removed.", "✅ Рабочий баланс '{}' отключен.", name),
+            };
+            if instance.is_running {
+                if let Err(e) = control::send_command(instance, &serde_json::json!({ "command": "balance", "total": total })) {
+                    bot.send_message(chat_id, tr!(lang, "❌ Failed to update reaction bot '{}': {}", "❌ Не удалось обновить бот реакций '{}': {}", name, e)).await?;
+                    return Ok(());
+                }
+            }
+            bot.send_message(chat_id, text).await?;
+        },
+        
+        TelegramCommand::Emoji { emoji } => {
+            let mut state = bot_state.lock().await;
+            
+            let emoji = emoji.trim();
+            if emoji.is_empty() {
+                bot.send_message(
+                    chat_id,
+                    lang.pick(
+                        "Usage: /emoji <emoji>, /emoji tiers <amount:emoji> ..., /emoji tiers off",
+                        "Использование: /emoji <эмодзи>, /emoji tiers <сумма:эмодзи> ..., /emoji tiers off"
+                    )
+                ).await?;
+                return Ok(());
+            }
+            
+            // Larger orders get their own emoji; below the lowest tier it's the reaction emoji
+            if let Some(tiers) = emoji.strip_prefix("tiers").map(str::trim) {
+                let filters = &mut state.selected_mut(user).filters;
+                if tiers == "off" {
+                    filters.emoji_tiers.clear();
+                    bot.send_message(chat_id, lang.pick("✅ Emoji tiers removed.", "✅ Эмодзи по суммам отключены.")).await?;
+                } else {
+                    let parsed = tiers
+                        .split([' ', ','])
+                        .filter(|tier| !tier.is_empty())
+                        .map(|tier| match tier.split_once(':') {
+                            Some((amount, emoji)) if !emoji.is_empty() => amount.parse::<i32>().ok().map(|amount| (amount, emoji.to_string())),
+                            _ => None,
+                        })
+                        .collect::<Option<std::collections::BTreeMap<_, _>>>()
+                        .filter(|tiers| !tiers.is_empty());
+                    let Some(parsed) = parsed else {
+                        bot.send_message(
+                            chat_id,
+                            lang.pick("Usage: /emoji tiers 60000:🔥 100000:⚡", "Использование: /emoji tiers 60000:🔥 100000:⚡")
+                        ).await?;
+                        return Ok(());
+                    };
+                    filters.emoji_tiers = parsed;
+                    let tiers = filters.emoji_tiers.iter().map(|(amount, emoji)| format!("{} ₽+: {}", amount, emoji)).collect::<Vec<_>>().join(", ");
+                    bot.send_message(chat_id, tr!(lang, "✅ Emoji by amount: {}", "✅ Эмодзи по суммам: {}", tiers)).await?;
+                }
+            } else {
+                state.selected_mut(user).filters.reaction_emoji = emoji.to_string();
+                bot.send_message(chat_id, tr!(lang, "✅ Reaction emoji set to: {}", "✅ Эмодзи реакции: {}", emoji)).await?;
+            }
+            
+            // If the bot is running, we need to restart it for the changes to take effect
+            if state.selected(user).is_running {
+                bot.send_message(
+                    chat_id, 
+                    restart_hint(lang)
+                ).await?;
+            }
+        },
+        
+        TelegramCommand::Preset { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let mut parts = args.split_whitespace();
+            let action = parts.next().unwrap_or("").to_lowercase();
+            let name = parts.next();
+            
+            match (action.as_str(), name) {
+                ("save", Some(name)) => {
+                    let filters = state.selected(user).filters.clone();
+                    state.presets.insert(name.to_string(), filters);
+                    
+                    if let Err(e) = presets::save(&state.presets) {
+                        bot.send_message(chat_id, tr!(lang, "❌ Failed to save preset: {}", "❌ Не удалось сохранить пресет: {}", e)).await?;
+                        return Ok(());
+                    }
+                    
+                    bot.send_message(
+                        chat_id,
+                        tr!(lang, "✅ Preset '{}' saved:\n\n{}", "✅ Пресет '{}' сохранен:\n\n{}", name, state.selected(user).filters.describe(lang))
+                    ).await?;
+                },
+                ("load", Some(name)) => {
+                    let Some(preset) = state.presets.get(name).cloned() else {
+                        bot.send_message(
+                            chat_id,
+                            tr!(
+                                lang,
+                                "❌ Preset '{}' not found. Use /preset list to see saved presets.",
+                                "❌ Пресет '{}' не найден. Список сохраненных пресетов: /preset list",
+                                name
+                            )
+                        ).await?;
+                        return Ok(());
+                    };
+                    
+                    state.selected_mut(user).filters = preset;
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "✅ Preset '{}' loaded into '{}':\n\n{}",
+                            "✅ Пресет '{}' загружен в '{}':\n\n{}",
+                            name,
+                            state.selected_name(user),
+                            state.selected(user).filters.describe(lang)
+                        )
+                    ).await?;
+                    
+                    // If the bot is running, we need to restart it for the changes to take effect
+                    if state.selected(user).is_running {
+                        bot.send_message(
+                            chat_id, 
+                            restart_hint(lang)
+                        ).await?;
+                    }
+                },
+                ("list", _) => {
+                    if state.presets.is_empty() {
+                        bot.send_message(
+                            chat_id,
+                            lang.pick(
+                                "No presets saved yet. Use /preset save <name> to create one.",
+                                "Сохраненных пресетов пока нет. Создайте пресет командой /preset save <имя>."
+                            )
+                        ).await?;
+                    } else {
+                        let list = state.presets
+                            .iter()
+                            .map(|(name, filters)| format!("• {}\n{}", name, filters.describe(lang)))
+                            .collect::<Vec<_>>()
+                            .join("\n\n");
+                        bot.send_message(chat_id, tr!(lang, "Saved presets:\n\n{}", "Сохраненные пресеты:\n\n{}", list)).await?;
+                    }
+                },
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.pick(
+                            "Usage:\n/preset save <name>\n/preset load <name>\n/preset list",
+                            "Использование:\n/preset save <имя>\n/preset load <имя>\n/preset list"
+                        )
+                    ).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Alias { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let args = args.trim();
+            let (name, command) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            let name = name.trim_start_matches('/').to_lowercase();
+            let command = command.trim();
+            
+            if name.is_empty() {
+                let aliases = state.all_aliases();
+                let reply = if aliases.is_empty() {
+                    lang.pick("No aliases yet. Usage: /alias a50 /amount 50000", "Сокращений пока нет. Использование: /alias a50 /amount 50000").to_string()
+                } else {
+                    let list = aliases.iter().map(|(name, command)| format!("/{} → {}", name, command)).collect::<Vec<_>>().join("\n");
+                    tr!(lang, "Aliases:\n{}", "Сокращения:\n{}", list)
+                };
+                bot.send_message(chat_id, reply).await?;
+            } else if command == "off" {
+                if state.aliases.remove(&name).is_none() && state.configured_aliases.contains_key(&name) {
+                    bot.send_message(chat_id, tr!(lang, "❌ /{} comes from COMMAND_ALIASES; change it there.", "❌ /{} задано в COMMAND_ALIASES, изменить его можно только там.", name)).await?;
+                    return Ok(());
+                }
+                bot.send_message(chat_id, tr!(lang, "✅ Alias /{} removed.", "✅ Сокращение /{} удалено.", name)).await?;
+            } else if !aliases::is_valid_name(&name) || !command.starts_with('/') {
+                bot.send_message(
+                    chat_id,
+                    lang.pick(
+                        "Usage: /alias <name> /<command> [args], e.g. /alias a50 /amount 50000. Names are letters, digits and _",
+                        "Использование: /alias <имя> /<команда> [аргументы], например /alias a50 /amount 50000. Имя - буквы, цифры и _"
+                    )
+                ).await?;
+            } else if TelegramCommand::bot_commands().iter().any(|known| known.command.trim_start_matches('/') == name) {
+                bot.send_message(chat_id, tr!(lang, "❌ /{} is already a command.", "❌ /{} - это уже команда.", name)).await?;
+            } else {
+                state.aliases.insert(name.clone(), command.to_string());
+                bot.send_message(chat_id, tr!(lang, "✅ /{} now runs {}", "✅ /{} теперь выполняет {}", name, command)).await?;
+            }
+        },
+        
+        TelegramCommand::ExportSettings => {
+            let state = bot_state.lock().await;
+            let contents = serde_json::to_string_pretty(&backup::export(&state, user))?;
+            drop(state);
+            
+            let file_name = format!("botdg-settings-{}.json", timezone::now().format("%Y%m%d"));
+            bot.send_document(chat_id, InputFile::memory(contents.into_bytes()).file_name(file_name))
+                .caption(lang.pick(
+                    "Reply to this file with /import_settings to restore these settings.",
+                    "Чтобы восстановить эти настройки, ответьте на этот файл командой /import_settings."
+                ))
+                .await?;
+        },
+        
+        TelegramCommand::ImportSettings => {
+            let Some(document) = message.reply_to_message().and_then(|reply| reply.document()) else {
+                bot.send_message(
+                    chat_id,
+                    lang.pick(
+                        "Send the file made by /export_settings, then reply to it with /import_settings.",
+                        "Отправьте файл, созданный /export_settings, и ответьте на него командой /import_settings."
+                    )
+                ).await?;
+                return Ok(());
+            };
+            
+            let file = bot.get_file(&document.file.id).await?;
+            let mut contents = Vec::new();
+            bot.download_file(&file.path, &mut contents).await?;
+            let settings = match backup::parse(&contents) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    bot.send_message(chat_id, tr!(lang, "❌ Not a settings file: {}", "❌ Это не файл настроек: {}", e)).await?;
+                    return Ok(());
+                }
+            };
+            
+            let mut state = bot_state.lock().await;
+            let imported = backup::import(&mut state, settings, user);
+            if let Err(e) = presets::save(&state.presets) {
+                warn!("Failed to save imported presets: {}", e);
+            }
+            if let Err(e) = blacklist::save(&state.blacklist) {
+                warn!("Failed to save the imported blacklist: {}", e);
+            }
+            let command = serde_json::json!({ "command": "blacklist", "requisites": state.blacklist });
+            for (name, instance) in state.instances.iter_mut().filter(|(_, instance)| instance.is_running) {
+                if let Err(e) = control::send_command(instance, &command) {
+                    warn!("Failed to send the blacklist to reaction bot '{}': {}", name, e);
+                }
+            }
+            
+            let none = lang.pick("none", "нет");
+            let list = |names: &[String]| if names.is_empty() { none.to_string() } else { names.join(", ") };
+            let mut reply = tr!(
+                lang,
+                "✅ Settings imported.\nUpdated instances: {}\nAdded instances: {}\nPresets: {}",
+                "✅ Настройки загружены.\nОбновлены экземпляры: {}\nДобавлены экземпляры: {}\nПресеты: {}",
+                list(&imported.updated),
+                list(&imported.added),
+                imported.presets
+            );
+            if !imported.skipped.is_empty() {
+                reply.push_str(&tr!(
+                    lang,
+                    "\nSkipped (other users' instances): {}",
+                    "\nПропущены (экземпляры других пользователей): {}",
+                    list(&imported.skipped)
+                ));
+            }
+            if !imported.invalid.is_empty() {
+                reply.push_str(&tr!(
+                    lang,
+                    "\nSkipped (invalid names): {}",
+                    "\nПропущены (недопустимые имена): {}",
+                    list(&imported.invalid)
+                ));
+            }
+            bot.send_message(chat_id, reply).await?;
+            
+            if imported.updated.iter().any(|name| state.instances[name].is_running) {
+                bot.send_message(chat_id, restart_hint(lang)).await?;
+            }
+        },
+        
+        TelegramCommand::Clear => {
+            let mut state = bot_state.lock().await;
+            
+            state.selected_mut(user).filters = FilterSettings::default();
+            
+            bot.send_message(
+                chat_id,
+                tr!(
+                    lang,
+                    "✅ All filters cleared, minimum amount reset to default ({}) and emoji reset to {}.",
+                    "✅ Все фильтры сброшены, минимальная сумма возвращена к значению по умолчанию ({}), эмодзи - к {}.",
+                    DEFAULT_MIN_AMOUNT,
+                    DEFAULT_REACTION_EMOJI
+                )
+            ).await?;
+            
+            // If the bot is running, we need to restart it for the changes to take effect
+            if state.selected(user).is_running {
+                bot.send_message(
+                    chat_id, 
+                    restart_hint(lang)
+                ).await?;
+            }
+        },
+        
+        TelegramCommand::Auth { value } => {
+            let mut state = bot_state.lock().await;
+            
+            // "/auth <instance> <value>" targets a specific instance; otherwise use the
+            // selected instance or the only one waiting for input
+            let mut words = value.splitn(2, char::is_whitespace);
+            let first = words.next().unwrap_or_default();
+            let (name, value) = match (state.instances.get(first).filter(|i| i.accessible_by(user)), words.next()) {
+                (Some(instance), Some(rest)) if instance.pending_auth.is_some() => (Some(first.to_string()), rest.to_string()),
+                _ => {
+                    let waiting = state.accessible(user)
+                        .filter(|(_, i)| i.pending_auth.is_some())
+                        .map(|(n, _)| n.clone())
+                        .collect::<Vec<_>>();
+                    let name = if state.selected(user).pending_auth.is_some() {
+                        Some(state.selected_name(user))
+                    } else if waiting.len() == 1 {
+                        waiting.into_iter().next()
+                    } else {
+                        None
+                    };
+                    (name, value)
+                }
+            };
+            
+            let Some(name) = name else {
+                bot.send_message(
+                    chat_id,
+                    lang.pick(
+                        "No reaction bot is waiting for login input (or several are: use /auth <instance> <value>).",
+                        "Ни один бот реакций не ждет данных для входа (или ждут несколько: используйте /auth <экземпляр> <значение>)."
+                    )
+                ).await?;
+                return Ok(());
+            };
+            
+            let instance = state.instances.get_mut(&name).expect("resolved instance exists");
+            let kind = instance.pending_auth.clone().unwrap_or_default();
+            
+            match control::send_auth_reply(instance, &kind, &value) {
+                Ok(()) => {
+                    // Don't leave secrets lying around in the chat history
+                    if kind == "password" || kind == "code" {
+                        let _ = bot.delete_message(chat_id, message.id).await;
+                    }
+                    bot.send_message(chat_id, tr!(lang, "✅ Sent {} to '{}'.", "✅ {} отправлен в '{}'.", kind.replace('_', " "), name)).await?;
+                },
+                Err(e) => {
+                    bot.send_message(chat_id, tr!(lang, "❌ Failed to send {} to '{}': {}", "❌ Не удалось отправить {} в '{}': {}", kind, name, e)).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Events { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let reply = match args.trim() {
+                "" => match state.event_feed_chat_id {
+                    Some(feed) => tr!(
+                        lang,
+                        "Event feed is sent to chat {}. Use /events off to disable it.",
+                        "Лента событий отправляется в чат {}. Отключить: /events off",
+                        feed
+                    ),
+                    None => lang.pick(
+                        "Event feed is off. Use /events on to receive it in this chat.",
+                        "Лента событий отключена. Включить в этом чате: /events on"
+                    ).to_string(),
+                },
+                // One feed for every instance
+                _ if !state.is_admin(user) => lang.pick(
+                    "❌ Only admins (ALLOWED_USERS) can change where the event feed goes.",
+                    "❌ Менять чат ленты событий могут только администраторы (ALLOWED_USERS)."
+                ).to_string(),
+                "off" => {
+                    state.event_feed_chat_id = None;
+                    lang.pick("✅ Event feed disabled.", "✅ Лента событий отключена.").to_string()
+                },
+                "on" => {
+                    state.event_feed_chat_id = Some(chat_id.0);
+                    lang.pick("✅ Event feed enabled for this chat.", "✅ Лента событий включена для этого чата.").to_string()
+                },
+                other => match other.parse::<i64>() {
+                    Ok(feed) => {
+                        state.event_feed_chat_id = Some(feed);
+                        tr!(lang, "✅ Event feed enabled for chat {}.", "✅ Лента событий включена для чата {}.", feed)
+                    },
+                    Err(_) => lang.pick(
+                        "Usage: /events on, /events <chat_id> or /events off",
+                        "Использование: /events on, /events <chat_id> или /events off"
+                    ).to_string(),
+                },
+            };
+            
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::Report { args } => {
+            let mut state = bot_state.lock().await;
+            
+            let reply = match args.trim() {
+                "" => report::build(state.visible_to(user), lang),
+                // One daily report for every instance
+                _ if !state.is_admin(user) => lang.pick(
+                    "❌ Only admins (ALLOWED_USERS) can change where the daily report goes.",
+                    "❌ Менять чат ежедневной сводки могут только администраторы (ALLOWED_USERS)."
+                ).to_string(),
+                "off" => {
+                    state.report_chat_id = None;
+                    lang.pick("✅ Daily report disabled.", "✅ Ежедневная сводка отключена.").to_string()
+                },
+                "on" => {
+                    state.report_chat_id = Some(chat_id.0);
+                    lang.pick("✅ The daily report will be posted to this chat.", "✅ Ежедневная сводка будет приходить в этот чат.").to_string()
+                },
+                other => match other.parse::<i64>() {
+                    Ok(report_chat) => {
+                        state.report_chat_id = Some(report_chat);
+                        tr!(lang, "✅ The daily report will be posted to chat {}.", "✅ Ежедневная сводка будет приходить в чат {}.", report_chat)
+                    },
+                    Err(_) => lang.pick(
+                        "Usage: /report, /report on, /report <chat_id> or /report off",
+                        "Использование: /report, /report on, /report <chat_id> или /report off"
+                    ).to_string(),
+                },
+            };
+            
+            bot.send_message(chat_id, reply).await?;
+        },
+        
+        TelegramCommand::Dashboard { args } => {
+            let old = bot_state.lock().await.dashboard;
+            let enable = match args.trim() {
+                "on" => true,
+                "off" => false,
+                _ => {
+                    bot.send_message(chat_id, lang.pick("Usage: /dashboard on or /dashboard off", "Использование: /dashboard on или /dashboard off")).await?;
+                    return Ok(());
+                }
+            };
+            
+            // One dashboard at a time: the previous one stays as it is, just unpinned
+            if let Some((old_chat, old_message)) = old {
+                if let Err(e) = bot.unpin_chat_message(ChatId(old_chat)).message_id(teloxide::types::MessageId(old_message)).await {
+                    warn!("Failed to unpin the old dashboard: {}", e);
+                }
+            }
+            if !enable {
+                bot_state.lock().await.dashboard = None;
+                bot.send_message(chat_id, lang.pick("✅ Dashboard disabled.", "✅ Панель отключена.")).await?;
+                return Ok(());
+            }
+            
+            let text = {
+                let state = bot_state.lock().await;
+                dashboard::build(&state, user, state.chat_language(Some(chat_id)))
+            };
+            match dashboard::post(&bot, chat_id, text).await {
+                Ok(message) => {
+                    let mut state = bot_state.lock().await;
+                    state.dashboard = Some((chat_id.0, message.0));
+                    state.dashboard_user = user.map(|user| user.0);
+                },
+                Err(e) => {
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "❌ Failed to post and pin the dashboard (in groups the bot needs the right to pin messages): {}",
+                            "❌ Не удалось отправить и закрепить панель (в группах боту нужно право закреплять сообщения): {}",
+                            e
+                        )
+                    ).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::AddUser { id } => {
+            let mut state = bot_state.lock().await;
+            
+            if !state.is_admin(user) {
+                bot.send_message(chat_id, admins_only(lang)).await?;
+                return Ok(());
+            }
+            if id.trim().is_empty() {
+                let admins = state::configured_users();
+                let list = state
+                    .users()
+                    .iter()
+                    .map(|user| if admins.contains(user) { tr!(lang, "• {} (admin)", "• {} (администратор)", user) } else { format!("• {}", user) })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                bot.send_message(chat_id, tr!(lang, "Users:\n{}\n\nUsage: /adduser <user_id>", "Пользователи:\n{}\n\nИспользование: /adduser <user_id>", list)).await?;
+                return Ok(());
+            }
+            let Ok(new_user) = id.trim().parse::<u64>() else {
+                bot.send_message(chat_id, tr!(lang, "❌ '{}' is not a user ID.", "❌ '{}' - не ID пользователя.", id.trim())).await?;
+                return Ok(());
+            };
+            
+            state.removed_users.remove(&new_user);
+            if !state::configured_users().contains(&new_user) {
+                state.added_users.insert(new_user);
+            }
+            info!("User {} added by {:?}", new_user, user);
+            bot.send_message(chat_id, tr!(lang, "✅ User {} can use the bot now.", "✅ Пользователь {} теперь может пользоваться ботом.", new_user)).await?;
+        },
+        
+        TelegramCommand::RemoveUser { id } => {
+            let mut state = bot_state.lock().await;
+            
+            if !state.is_admin(user) {
+                bot.send_message(chat_id, admins_only(lang)).await?;
+                return Ok(());
+            }
+            let Ok(old_user) = id.trim().parse::<u64>() else {
+                bot.send_message(chat_id, lang.pick("Usage: /removeuser <user_id>", "Использование: /removeuser <user_id>")).await?;
+                return Ok(());
+            };
+            if user.is_some_and(|user| user.0 == old_user) {
+                bot.send_message(chat_id, lang.pick("❌ You can't remove yourself.", "❌ Нельзя удалить самого себя.")).await?;
+                return Ok(());
+            }
+            
+            state.added_users.remove(&old_user);
+            if state::configured_users().contains(&old_user) {
+                state.removed_users.insert(old_user);
+            }
+            info!("User {} removed by {:?}", old_user, user);
+            bot.send_message(chat_id, tr!(lang, "✅ User {} can no longer use the bot.", "✅ Пользователь {} больше не может пользоваться ботом.", old_user)).await?;
+        },
+        
+        TelegramCommand::Language { code } => {
+            let mut state = bot_state.lock().await;
+            
+            match (Lang::parse(&code), user) {
+                (Some(new_lang), Some(user)) => {
+                    state.languages.insert(user.0, new_lang);
+                    bot.send_message(chat_id, new_lang.pick("✅ Replies will be in English.", "✅ Ответы будут на русском языке.")).await?;
+                },
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        tr!(
+                            lang,
+                            "Current language: {}. Usage: /language en or /language ru",
+                            "Текущий язык: {}. Использование: /language ru или /language en",
+                            lang.code()
+                        )
+                    ).await?;
+                }
+            }
+        },
+        
+        TelegramCommand::Confirm => {}
+        
+        TelegramCommand::Help => {
+            let help = match lang {
+                Lang::En => TelegramCommand::descriptions().to_string(),
+                Lang::Ru => i18n::ru_help(),
+            };
+            bot.send_message(chat_id, help).await?;
+        }
+    }
+    
+    // Persist any changes so a manager restart doesn't reset the configuration
+    bot_state.lock().await.save();
+    
+    Ok(())
+}
+
+fn unknown_instance(lang: Lang, name: &str) -> String {
+    tr!(
+        lang,
+        "❌ Unknown instance '{}'. Use /instance list to see instances.",
+        "❌ Неизвестный экземпляр '{}'. Список экземпляров: /instance list",
+        name.trim()
+    )
+}
+
+// What a destructive command is about to do, asking for /confirm; None for other commands, and for
+// unknown instances, which get their usual error instead
+fn confirmation_prompt(command: &TelegramCommand, state: &BotState, user: Option<UserId>, lang: Lang) -> Option<String> {
+    let seconds = confirm::timeout().as_secs();
+    match command {
+        TelegramCommand::Clear => Some(tr!(
+            lang,
+            "⚠️ This clears every filter of '{}'. Send /confirm within {} s to go ahead.",
+            "⚠️ Все фильтры '{}' будут сброшены. Отправьте /confirm в течение {} с, чтобы продолжить.",
+            state.selected_name(user),
+            seconds
+        )),
+        TelegramCommand::Stop { name } => {
+            let name = state.resolve_instance(name, user)?;
+            state.instances[&name].is_running.then(|| tr!(
+                lang,
+                "⚠️ This stops the reaction bot '{}'. Send /confirm within {} s to go ahead.",
+                "⚠️ Бот реакций '{}' будет остановлен. Отправьте /confirm в течение {} с, чтобы продолжить.",
+                name,
+                seconds
+            ))
+        }
+        TelegramCommand::Logout { name } => {
+            let name = state.resolve_instance(name, user)?;
+            Some(tr!(
+                lang,
+                "⚠️ This logs '{}' out and deletes its session; logging in again needs the phone code. Send /confirm within {} s to go ahead.",
+                "⚠️ Аккаунт '{}' выйдет из Telegram, а его сессия будет удалена; для нового входа понадобится код. Отправьте /confirm в течение {} с, чтобы продолжить.",
+                name,
+                seconds
+            ))
+        }
+        TelegramCommand::Restore { name } if !name.trim().is_empty() => Some(tr!(
+            lang,
+            "⚠️ This replaces the TDLib session of '{}' with snapshot {}, stopping the bot meanwhile. Send /confirm within {} s to go ahead.",
+            "⚠️ Сессия TDLib '{}' будет заменена копией {}, бот на это время остановится. Отправьте /confirm в течение {} с, чтобы продолжить.",
+            state.selected_name(user),
+            name.trim(),
+            seconds
+        )),
+        _ => None,
+    }
+}
+
+fn admins_only(lang: Lang) -> &'static str {
+    lang.pick("❌ Only admins (ALLOWED_USERS) can manage users.", "❌ Управлять пользователями могут только администраторы (ALLOWED_USERS).")
+}
+
+fn not_running(lang: Lang, name: &str) -> String {
+    tr!(lang, "The reaction bot '{}' is not running.", "Бот реакций '{}' не запущен.", name)
+}
+
+fn stopped_successfully(lang: Lang, name: &str) -> String {
+    tr!(lang, "✅ Reaction bot '{}' stopped successfully.", "✅ Бот реакций '{}' остановлен.", name)
+}
+
+fn restart_hint(lang: Lang) -> &'static str {
+    lang.pick("⚠️ Use /restart for the changes to take effect.", "⚠️ Используйте /restart, чтобы изменения вступили в силу.")
+}
+
+// The prev/next buttons under a /history page: show that page in the same message
+async fn history_page(bot: Bot, query: CallbackQuery, bot_state: Arc<Mutex<BotState>>) -> Result<()> {
+    let user = Some(query.from.id);
+    let page = query.data.as_deref().and_then(history::parse_page_callback);
+    let reply = {
+        let state = bot_state.lock().await;
+        let lang = state.language(user);
+        match page {
+            Some((name, offset, limit)) if state.user_allowed(query.from.id.0) => state.instances
+                .get(&name)
+                .filter(|instance| instance.accessible_by(user))
+                .map(|instance| history::format_page(&name, &instance.history_path(&name), offset, limit, lang)),
+            _ => None,
+        }
+    };
+    
+    if let (Some((text, buttons)), Some(message)) = (reply, &query.message) {
+        let request = bot.edit_message_text(message.chat.id, message.id, text);
+        let result = match buttons {
+            Some(buttons) => request.reply_markup(buttons).await,
+            None => request.await,
+        };
+        if let Err(e) = result {
+            warn!("Failed to show a history page: {}", e);
+        }
+    }
+    // Stops the button's loading spinner
+    bot.answer_callback_query(query.id).await?;
+    Ok(())
+}
+
+// A command, or an alias of one (see aliases.rs)
+async fn parse_command(message: Message, me: teloxide::types::Me, bot_state: Arc<Mutex<BotState>>) -> Option<TelegramCommand> {
+    let text = message.text()?;
+    if let Ok(command) = TelegramCommand::parse(text, me.username()) {
+        return Some(command);
+    }
+    let expanded = aliases::expand(text, &bot_state.lock().await.all_aliases())?;
+    TelegramCommand::parse(&expanded, me.username()).ok()
+}
+
+// Spawn an instance's reaction bot and start tracking it
+fn launch(name: &str, instance: &mut Instance, events: &control::EventSender, lang: Lang) -> std::io::Result<()> {
+    let mut child = process::spawn(name, instance)?;
+    output::capture(name, instance, &mut child);
+    control::attach(name, instance, &mut child, events);
+    instance.reaction_bot_process = Some(process::watch(name, child));
+    instance.is_running = true;
+    instance.last_status = lang.pick("Running", "Работает").to_string();
+    instance.supervisor.reset();
+    instance.supervisor.mark_started();
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    // The environment is filled in before the runtime starts any threads
+    let cli = Cli::parse();
+    let config = botdg_config::load("manager", &cli.config, CONFIG_DEFAULTS).map_err(anyhow::Error::msg)?;
+    pretty_env_logger::init();
+    // Every bad setting at once, before anything connects to Telegram
+    validate::all().check()?;
+    if let Some(profile) = &config.profile {
+        info!("Profile {}", profile);
+    }
+    if let Some(file) = &config.file {
+        info!("Configuration from {}", file.display());
+    }
+    process::set_config_file(config.file.as_deref());
+    // handle_command's future is large enough to overflow tokio's default 2 MiB in debug builds
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(8 * 1024 * 1024)
+        .build()?
+        .block_on(run())
+}
+
+async fn run() -> Result<()> {
+    
+    // Checked by validate.rs
+    let bot_token = env::var("BOT_TOKEN").unwrap_or_default();
+    let allowed_users = state::configured_users();
+    
+    version::mark_started();
+    info!("Starting Telegram controller bot {} ({})", version::VERSION, version::COMMIT);
+    info!("Allowed users: {:?}", allowed_users);
+    
+    // Restore bot state from the previous run
+    let bot_state = Arc::new(Mutex::new(BotState::load()));
+    
+    // Create bot instance; BOT_API_URL points it at a local Bot API server instead of Telegram's
+    let mut bot = Bot::new(bot_token);
+    if let Some(url) = env::var("BOT_API_URL").ok().filter(|url| !url.is_empty()) {
+        bot = bot.set_api_url(reqwest::Url::parse(&url)?);
+    }
+    
+    // Command menus: per Telegram app language, and BOT_LANGUAGE's for apps in any other language
+    let commands = TelegramCommand::bot_commands();
+    let ru_commands = i18n::ru_bot_commands();
+    let names = |commands: &[BotCommand]| commands.iter().map(|c| c.command.trim_start_matches('/').to_string()).collect::<Vec<_>>();
+    if names(&commands) != names(&ru_commands) {
+        warn!("The Russian command list doesn't match the commands; the Russian menu and /help are out of date");
+    }
+    let default_commands = match Lang::from_env() {
+        Lang::En => commands.clone(),
+        Lang::Ru => ru_commands.clone(),
+    };
+    bot.set_my_commands(default_commands).await?;
+    bot.set_my_commands(commands).language_code("en").await?;
+    bot.set_my_commands(ru_commands).language_code("ru").await?;
+    
+    // Matches and alerts for Discord and Slack (see notify.rs), shared by the tasks below
+    let notifier = Notifier::from_env();
+    
+    // Watch the reaction bot process and restart it if it crashes
+    let (events_tx, events_rx) = control::channel();
+    let exits = process::exits();
+    // Bots left running by the previous manager are taken over first, so nothing starts them twice
+    supervisor::adopt(&bot_state, &events_tx).await;
+    tokio::spawn(supervisor::run(bot.clone(), bot_state.clone(), events_tx.clone(), notifier.clone(), exits));
+    
+    // Relay events (like login prompts) from reaction bots to the admin chat
+    tokio::spawn(control::run(bot.clone(), bot_state.clone(), events_rx, notifier.clone()));
+    
+    // Check reaction bot heartbeats to catch a live process with a dead connection
+    tokio::spawn(health::run(bot.clone(), bot_state.clone(), notifier.clone()));
+    
+    // Post the daily summary to the /report chat
+    tokio::spawn(report::run(bot.clone(), bot_state.clone()));
+    
+    // Sum up what QUIET_HOURS held back once they're over
+    tokio::spawn(quiet::run(bot.clone(), bot_state.clone(), notifier));
+    
+    // Keep the pinned /dashboard message current
+    tokio::spawn(dashboard::run(bot.clone(), bot_state.clone()));
+    
+    // Periodic "still alive" posts to STATUS_CHAT_ID
+    tokio::spawn(status_channel::run(bot.clone(), bot_state.clone()));
+    
+    // Start command handler
+    let messages = Update::filter_message()
+        .filter_map_async(|message: Message, bot_state: Arc<Mutex<BotState>>| async move {
+            let user_id = message.from()?.id.0;
+            
+            // Check if user is allowed: ALLOWED_USERS and /adduser (see BotState::user_allowed)
+            if !bot_state.lock().await.user_allowed(user_id) {
+                info!("Unauthorized access attempt from user {}", user_id);
                 return None;
             }
             
             Some(message)
         })
+        .enter_dialogue::<Message, InMemStorage<setup::SetupState>, setup::SetupState>()
         .branch(
             dptree::entry()
-                .filter_command::<TelegramCommand>()
+                .filter_map_async(parse_command)
                 .endpoint(handle_command),
-        );
+        )
+        // Answers to the /setup wizard
+        .branch(dptree::case![setup::SetupState::Bank].endpoint(setup::receive_bank))
+        .branch(dptree::case![setup::SetupState::Amount(draft)].endpoint(setup::receive_amount))
+        .branch(dptree::case![setup::SetupState::Requisite(draft)].endpoint(setup::receive_requisite))
+        .branch(dptree::case![setup::SetupState::Chats(draft)].endpoint(setup::receive_chats));
+    let handler = dptree::entry()
+        .branch(messages)
+        // "@manager_bot status" from any chat (see inline.rs)
+        .branch(Update::filter_inline_query().endpoint(inline::answer))
+        // /history page buttons
+        .branch(Update::filter_callback_query().endpoint(history_page));
     
     // Start the bot
     Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![bot_state])
+        .dependencies(dptree::deps![
+            bot_state,
+            events_tx,
+            InMemStorage::<setup::SetupState>::new(),
+            Arc::new(Mutex::new(Confirmations::default()))
+        ])
         .enable_ctrlc_handler()
         .build()
         .dispatch()
@@ -0,0 +1,191 @@
+use std::{env, future::Future, sync::Arc, time::Duration};
+use log::{info, warn};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::{discord::DiscordSink, email::EmailSink, i18n::Lang, quiet::QuietHours, slack::SlackSink};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 3;
+// Upper bound on a Retry-After we're willing to honour
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// Something worth telling the team outside the admin chat
+pub enum Notification {
+    // A "match" event from a reaction bot, and its MATCH_TEMPLATE text when there is one
+    Match { instance: String, event: Value, text: Option<String> },
+    // An alert already worded for the admin chat (crashes, health, session and error-rate changes).
+    // Critical ones need someone to step in: the session is gone, the bot keeps crashing, or it
+    // has been cut off from Telegram for a long time
+    Alert { instance: String, text: String, critical: bool },
+    // What QUIET_HOURS held back, summed up once they're over (see quiet.rs)
+    Digest { text: String },
+}
+
+// A place notifications are posted to (a Discord channel, a Slack channel, ...). Each sink gets its
+// own delivery task, so a slow or rate-limited one doesn't hold up the others
+pub trait Sink: Send + 'static {
+    fn name(&self) -> &'static str;
+    // Deliver one notification, retrying as the service expects
+    fn send(&mut self, notification: &Notification) -> impl Future<Output = ()> + Send;
+}
+
+struct Route {
+    matches: bool,
+    alerts: bool,
+    critical: bool,
+    notifications: mpsc::UnboundedSender<Arc<Notification>>,
+}
+
+// Hands matches and alerts to every configured sink. Cheap to clone; clones share the sinks
+#[derive(Clone, Default)]
+pub struct Notifier {
+    routes: Arc<Vec<Route>>,
+    quiet: QuietHours,
+}
+
+impl Notifier {
+    // Sinks are configured by their own variables (DISCORD_WEBHOOK_URL, SLACK_WEBHOOK_URL,
+    // SMTP_HOST); each one's <NAME>_NOTIFY picks "matches", "alerts" (critical ones included) and
+    // "critical", comma separated. Messages are in BOT_LANGUAGE
+    pub fn from_env() -> Self {
+        let lang = Lang::from_env();
+        let mut routes = Vec::new();
+        if let Some(sink) = DiscordSink::from_env(lang) {
+            routes.push(route("DISCORD_NOTIFY", "matches,alerts", sink));
+        }
+        if let Some(sink) = SlackSink::from_env(lang) {
+            routes.push(route("SLACK_NOTIFY", "matches,alerts", sink));
+        }
+        if let Some(sink) = EmailSink::from_env(lang) {
+            routes.push(route("EMAIL_NOTIFY", "critical", sink));
+        }
+        Self { routes: Arc::new(routes), quiet: QuietHours::from_env() }
+    }
+
+    // QUIET_HOURS, which the other notifications of the manager go by too
+    pub fn quiet(&self) -> &QuietHours {
+        &self.quiet
+    }
+
+    // Dropped during quiet hours; the digest counts them instead
+    pub fn matched(&self, instance: &str, event: &Value, text: Option<String>) {
+        if self.quiet.is_quiet() {
+            return;
+        }
+        self.dispatch(Notification::Match { instance: instance.to_string(), event: event.clone(), text });
+    }
+
+    pub fn alert(&self, instance: &str, text: &str) {
+        self.dispatch(Notification::Alert { instance: instance.to_string(), text: text.to_string(), critical: false });
+    }
+
+    pub fn critical(&self, instance: &str, text: &str) {
+        self.dispatch(Notification::Alert { instance: instance.to_string(), text: text.to_string(), critical: true });
+    }
+
+    pub fn digest(&self, text: &str) {
+        self.dispatch(Notification::Digest { text: text.to_string() });
+    }
+
+    fn dispatch(&self, notification: Notification) {
+        let notification = Arc::new(notification);
+        for route in self.routes.iter() {
+            let wanted = match *notification {
+                Notification::Match { .. } => route.matches,
+                Notification::Alert { critical, .. } => route.alerts || (critical && route.critical),
+                Notification::Digest { .. } => route.matches || route.alerts,
+            };
+            if wanted {
+                let _ = route.notifications.send(notification.clone());
+            }
+        }
+    }
+}
+
+// Start delivering to a sink
+fn route(notify_var: &str, default_kinds: &str, sink: impl Sink) -> Route {
+    let kinds = env::var(notify_var).unwrap_or_else(|_| default_kinds.to_string()).to_lowercase();
+    let wants = |kind: &str| kinds.split(',').any(|k| k.trim() == kind);
+    info!("Posting {} to {}", kinds, sink.name());
+
+    let (notifications, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(run(sink, receiver));
+    Route { matches: wants("matches"), alerts: wants("alerts"), critical: wants("critical"), notifications }
+}
+
+// One notification at a time, so they arrive in order and a rate limit holds back the rest
+async fn run(mut sink: impl Sink, mut notifications: mpsc::UnboundedReceiver<Arc<Notification>>) {
+    while let Some(notification) = notifications.recv().await {
+        sink.send(&notification).await;
+    }
+}
+
+pub fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+// POST JSON to an incoming webhook (see send)
+pub async fn post_json(client: &reqwest::Client, url: &str, body: &Value, service: &str) {
+    send(client.post(url).json(body), MAX_ATTEMPTS, service).await;
+}
+
+// Send a webhook request up to `attempts` times, retrying network errors, 5xx and 429 (after the
+// Retry-After the service asks for). Other errors mean the message itself was rejected and aren't
+// retried. Returns whether it was delivered
+pub async fn send(request: reqwest::RequestBuilder, attempts: u32, service: &str) -> bool {
+    for attempt in 1..=attempts {
+        let Some(request) = request.try_clone() else { return false };
+        let delay = match request.send().await {
+            Ok(response) if response.status().is_success() => return true,
+            Ok(response) if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                let header = response.headers().get(reqwest::header::RETRY_AFTER).and_then(|value| value.to_str().ok());
+                header.map_or(Duration::from_secs(1), retry_after)
+            }
+            Ok(response) if response.status().is_server_error() => {
+                warn!("{} webhook returned {} (attempt {})", service, response.status(), attempt);
+                Duration::from_secs(attempt as u64)
+            }
+            Ok(response) => {
+                warn!("{} webhook rejected a message: {}", service, response.status());
+                return false;
+            }
+            Err(e) => {
+                warn!("{} webhook request failed (attempt {}): {}", service, attempt, e);
+                Duration::from_secs(attempt as u64)
+            }
+        };
+        if attempt < attempts {
+            tokio::time::sleep(delay).await;
+        }
+    }
+    warn!("Giving up on a {} message after {} attempts", service, attempts);
+    false
+}
+
+// A Retry-After header in seconds, at most MAX_RETRY_AFTER; anything unusable (a date, "inf",
+// "NaN", negative) waits a second
+fn retry_after(header: &str) -> Duration {
+    match header.trim().parse::<f64>() {
+        Ok(secs) if secs.is_finite() => Duration::from_secs_f64(secs.clamp(0.0, MAX_RETRY_AFTER.as_secs_f64())),
+        _ => Duration::from_secs(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_is_clamped_and_never_panics() {
+        assert_eq!(retry_after("2.5"), Duration::from_millis(2500));
+        assert_eq!(retry_after("1e300"), MAX_RETRY_AFTER);
+        assert_eq!(retry_after("-5"), Duration::ZERO);
+        for header in ["inf", "NaN", "Wed, 21 Oct 2026 07:28:00 GMT"] {
+            assert_eq!(retry_after(header), Duration::from_secs(1), "{}", header);
+        }
+    }
+}
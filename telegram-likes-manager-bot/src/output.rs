@@ -0,0 +1,176 @@
+use std::{
+    collections::VecDeque,
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use chrono::Local;
+use log::{info, warn};
+use tokio::{io::{AsyncBufReadExt, BufReader}, process::Child};
+
+use crate::state::Instance;
+
+pub const DEFAULT_LOG_DIR: &str = "logs";
+const DEFAULT_LOG_TAIL_LINES: usize = 20;
+// Keep crash reports well below Telegram's 4096 character message limit
+const MAX_TAIL_CHARS: usize = 3000;
+const DEFAULT_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_LOG_KEEP_RUNS: usize = 10;
+
+fn log_tail_lines() -> usize {
+    env::var("LOG_TAIL_LINES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_LOG_TAIL_LINES)
+}
+
+// Directory for per-run log files of the reaction bots
+pub fn log_dir() -> String {
+    env::var("LOG_DIR").unwrap_or_else(|_| DEFAULT_LOG_DIR.to_string())
+}
+
+// The last few lines a reaction bot wrote to stderr, for crash reports
+#[derive(Clone, Default)]
+pub struct LogTail(Arc<Mutex<VecDeque<String>>>);
+
+impl LogTail {
+    fn push(&self, line: String, capacity: usize) {
+        let mut lines = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    // Recent lines joined for a message, trimmed from the front if too long
+    pub fn text(&self) -> String {
+        let lines = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let mut text = String::new();
+        for line in lines.iter().rev() {
+            if text.len() + line.len() + 1 > MAX_TAIL_CHARS {
+                break;
+            }
+            text.insert_str(0, &format!("{}\n", line));
+        }
+        text.trim_end().to_string()
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+}
+
+// Log file for a single run of a reaction bot, shared by its stdout and stderr readers.
+// When it grows past LOG_MAX_BYTES it is moved to "<file>.1" and a fresh file is started.
+#[derive(Clone)]
+pub struct RunLog(Arc<Mutex<LogFile>>);
+
+impl RunLog {
+    fn create(name: &str) -> std::io::Result<Self> {
+        let dir = log_dir();
+        fs::create_dir_all(&dir)?;
+        prune_old_runs(Path::new(&dir), name);
+
+        let path = Path::new(&dir).join(format!("{}-{}.log", name, Local::now().format("%Y%m%d-%H%M%S")));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        info!("Logging reaction bot '{}' output to {}", name, path.display());
+
+        Ok(Self(Arc::new(Mutex::new(LogFile {
+            path,
+            file,
+            written: 0,
+            max_bytes: env::var("LOG_MAX_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_LOG_MAX_BYTES),
+        }))))
+    }
+
+    pub fn write_line(&self, stream: &str, line: &str) {
+        let mut log = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = format!("{} [{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S%.3f"), stream, line);
+
+        if log.written + entry.len() as u64 > log.max_bytes {
+            if let Err(e) = log.rotate() {
+                warn!("Failed to rotate {}: {}", log.path.display(), e);
+            }
+        }
+        if log.file.write_all(entry.as_bytes()).is_ok() {
+            log.written += entry.len() as u64;
+        }
+    }
+}
+
+impl LogFile {
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+// Delete the oldest run logs of an instance, keeping LOG_KEEP_RUNS minus the one about to start
+fn prune_old_runs(dir: &Path, name: &str) {
+    let keep = env::var("LOG_KEEP_RUNS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_LOG_KEEP_RUNS);
+
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let prefix = format!("{}-", name);
+    let mut runs = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|file| {
+            // "<name>-YYYYmmdd-HHMMSS.log", so "<name>-foo" instances don't match
+            file.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".log"))
+                .is_some_and(|stamp| stamp.len() == 15 && stamp.chars().all(|c| c.is_ascii_digit() || c == '-'))
+        })
+        .collect::<Vec<_>>();
+    runs.sort();
+
+    let excess = (runs.len() + 1).saturating_sub(keep.max(1));
+    for file in runs.into_iter().take(excess) {
+        let path = dir.join(&file);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(dir.join(format!("{}.1", file)));
+    }
+}
+
+// Write a freshly spawned child's stderr to its run log and ours, remembering its tail
+pub fn capture(name: &str, instance: &mut Instance, child: &mut Child) {
+    instance.log_tail = LogTail::default();
+    instance.run_log = match RunLog::create(name) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            warn!("Failed to create log file for '{}': {}", name, e);
+            None
+        }
+    };
+
+    let Some(stderr) = child.stderr.take() else { return };
+    let name = name.to_string();
+    let tail = instance.log_tail.clone();
+    let run_log = instance.run_log.clone();
+    let capacity = log_tail_lines();
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            eprintln!("[{}] {}", name, line);
+            if let Some(log) = &run_log {
+                log.write_line("stderr", &line);
+            }
+            tail.push(line, capacity);
+        }
+    });
+}
@@ -0,0 +1,29 @@
+use std::{collections::BTreeMap, env};
+use log::info;
+
+use crate::{state::FilterSettings, storage};
+
+pub const DEFAULT_PRESETS_FILE: &str = "presets.json";
+
+// Get the presets file path from environment or use default
+fn presets_path() -> String {
+    env::var("PRESETS_FILE").unwrap_or_else(|_| DEFAULT_PRESETS_FILE.to_string())
+}
+
+// Load saved presets, falling back to an empty set if the file is missing or invalid
+pub fn load() -> BTreeMap<String, FilterSettings> {
+    let path = presets_path();
+
+    storage::load_json(&path).unwrap_or_else(|| {
+        info!("No presets loaded from {}, starting with no presets", path);
+        BTreeMap::new()
+    })
+}
+
+// Write all presets back to disk
+pub fn save(presets: &BTreeMap<String, FilterSettings>) -> anyhow::Result<()> {
+    let path = presets_path();
+    storage::save_json(&path, presets)?;
+    info!("Saved {} presets to {}", presets.len(), path);
+    Ok(())
+}
@@ -0,0 +1,411 @@
+use std::{env, path::{Path, PathBuf}, process::{ExitStatus, Output, Stdio}, sync::OnceLock, time::Duration};
+use log::{info, warn};
+use tokio::{process::{Child, Command as ProcessCommand}, sync::{mpsc, oneshot, watch}};
+
+use crate::{blacklist, health, history, state::Instance};
+
+// The reaction bot's crate next to this one, as checked out from the repository
+pub const DEFAULT_REACTION_BOT_PATH: &str = "../telegram-reaction-bot";
+
+// Get reaction bot path from environment
+pub fn reaction_bot_path() -> String {
+    env::var("REACTION_BOT_PATH").unwrap_or_else(|_| DEFAULT_REACTION_BOT_PATH.to_string())
+}
+
+// For maximum speed, use the pre-built binary directly instead of cargo run
+// This significantly reduces startup time and improves reaction speed.
+// REACTION_BOT_BINARY names another one (an installed or debug build)
+pub fn binary_path() -> String {
+    env::var("REACTION_BOT_BINARY")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| format!("{}/target/release/tdlib-test{}", reaction_bot_path(), env::consts::EXE_SUFFIX))
+}
+
+// Where the instance files the reaction bots are started with are written
+pub const DEFAULT_INSTANCES_DIR: &str = "instances";
+
+// The config file this manager was loaded from, passed on to the reaction bots so they read the same
+static CONFIG_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+pub fn set_config_file(file: Option<&Path>) {
+    let _ = CONFIG_FILE.set(file.map(|file| std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf())));
+}
+
+fn instances_dir() -> String {
+    env::var("INSTANCES_DIR").unwrap_or_else(|_| DEFAULT_INSTANCES_DIR.to_string())
+}
+
+// The socket an instance's bot answers JSON-RPC on (see rpc.rs), next to its instance file.
+// None where there are no Unix domain sockets, leaving the control channel on stdio
+pub fn control_socket(name: &str) -> Option<PathBuf> {
+    cfg!(unix).then(|| Path::new(&instances_dir()).join(format!("{}.sock", name)))
+}
+
+// The instance file an instance's bot is started with (see spawn)
+fn instance_file(name: &str) -> PathBuf {
+    Path::new(&instances_dir()).join(format!("{}.toml", name))
+}
+
+// Whether `pid` is this instance's reaction bot rather than a process that got its PID after the bot
+// exited: spawn starts every bot with --instance and the instance's own file, which shows in
+// /proc/<pid>/cmdline. Where there is no /proc to look in, the PID is taken at its word
+pub fn is_instance(pid: u32, name: &str) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(cmdline) = std::fs::read(format!("/proc/{}/cmdline", pid)) else { return false };
+        let args: Vec<&[u8]> = cmdline.split(|&byte| byte == 0).collect();
+        let instance_file = instance_file(name);
+        args.windows(2)
+            .any(|pair| pair[0] == b"--instance" && Path::new(std::ffi::OsStr::from_bytes(pair[1])) == instance_file)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (pid, name);
+        true
+    }
+}
+
+// An instance's settings as the reaction bot takes them. Unset ones are left to the environment
+// and the config file, like any other setting
+fn settings(name: &str, instance: &Instance) -> Vec<(&'static str, String)> {
+    let filters = &instance.filters;
+    let mut settings = vec![
+        ("HEARTBEAT_FILE", instance.heartbeat_path(name)),
+        ("HISTORY_DB", instance.history_path(name)),
+    ];
+    if let Some(socket) = control_socket(name) {
+        settings.push(("CONTROL_SOCKET", socket.to_string_lossy().into_owned()));
+    }
+    
+    // Give each instance its own TDLib session
+    if let Some(session_dir) = &instance.session_dir {
+        settings.push(("TDLIB_DATA_DIR", session_dir.clone()));
+    }
+    
+    // Monitor this instance's own chats instead of the inherited ALLOWED_CHAT_IDS
+    if let Some(chats) = instance.allowed_chats_env() {
+        info!("Instance '{}' monitors chats {}", name, chats);
+        settings.push(("ALLOWED_CHAT_IDS", chats));
+    }
+    
+    if let Some(bank) = &filters.bank_filter {
+        settings.push(("BANK_FILTER", bank.clone()));
+    }
+    if let Some(requisite) = &filters.requisite_filter {
+        settings.push(("REQUISITE_FILTER", requisite.clone()));
+    }
+    
+    // Requisites blacklisted with /blacklist, shared by all instances
+    let blacklist = blacklist::load();
+    if !blacklist.is_empty() {
+        settings.push(("REQUISITE_BLACKLIST", blacklist::env(&blacklist)));
+    }
+    
+    settings.push(("MIN_AMOUNT", filters.min_amount.to_string()));
+    if !filters.bank_min_amounts.is_empty() {
+        settings.push(("BANK_MIN_AMOUNTS", filters.bank_min_amounts_env()));
+    }
+    
+    // The minimum expected profit; commissions come from the shared configuration
+    if filters.min_profit > 0.0 {
+        settings.push(("MIN_PROFIT", filters.min_profit.to_string()));
+    }
+    
+    settings.push(("REACTION_EMOJI", filters.reaction_emoji.clone()));
+    if !filters.emoji_tiers.is_empty() {
+        settings.push(("EMOJI_TIERS", filters.emoji_tiers_env()));
+    }
+    
+    // Working balance set with /balance, continuing from what was left
+    if let Some(balance) = instance.balance {
+        settings.push(("WORKING_BALANCE", balance.to_string()));
+        settings.push(("BALANCE_LEFT", instance.balance_left.unwrap_or(balance).to_string()));
+    }
+    if let Some(reset) = &instance.balance_reset {
+        settings.push(("BALANCE_RESET", reset.clone()));
+    }
+    settings
+}
+
+// Spawn an instance's reaction bot. Its settings go in an instance file in INSTANCES_DIR, read
+// over the environment and config file the bot shares with the manager (see botdg-config), so
+// the file says what the bot runs with
+pub fn spawn(name: &str, instance: &Instance) -> std::io::Result<Child> {
+    let mut command = ProcessCommand::new(instance.binary_path());
+    
+    // Run the bot in its own process group so stopping it also stops anything it spawned,
+    // and nothing else
+    #[cfg(unix)]
+    command.process_group(0);
+    // The Windows equivalent, which also keeps a Ctrl+C in the manager's console from reaching it
+    #[cfg(windows)]
+    {
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+    
+    // Start from a clean heartbeat so a stale file from a previous run isn't mistaken for a live bot
+    std::fs::create_dir_all(health::heartbeat_dir())?;
+    let _ = std::fs::remove_file(instance.heartbeat_path(name));
+    std::fs::create_dir_all(history::history_dir())?;
+    
+    let instance_file = instance_file(name);
+    let comment = format!("Instance '{}', written by the manager each time it starts the bot: change it with the manager's commands", name);
+    botdg_config::write(&instance_file, &comment, "reaction-bot", &settings(name, instance)).map_err(std::io::Error::other)?;
+    command.arg("--instance").arg(&instance_file);
+    if let Some(file) = CONFIG_FILE.get().and_then(Option::as_ref) {
+        command.arg("--config").arg(file);
+    }
+    
+    // Talk to the child over stdin/stdout (see control.rs); logs on stderr are
+    // captured for crash reports (see output.rs)
+    command
+        .env("CONTROL_CHANNEL", "stdio")
+        .env("INSTANCE_NAME", name)
+        .env("PANIC", if instance.panicked { "true" } else { "false" })
+        // The owner's /watch changes are kept in the instance's chats instead (see control.rs)
+        .env("WATCHED_CHATS_FILE", "")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    
+    // Special handling for T-Bank messages when requisite filter is set to "+"
+    // This ensures T-Bank messages are included even if they don't have a "+" in their requisite
+    if instance.filters.requisite_filter.as_deref() == Some("+") {
+        info!("Special handling for T-Bank messages with '+' filter is enabled");
+    }
+    
+    command.spawn()
+}
+
+// Run `tdlib-test logout` on a stopped instance's session: it logs the account out and deletes
+// the session directories
+pub async fn logout(binary_path: String, session_dir: Option<String>) -> std::io::Result<Output> {
+    let mut command = ProcessCommand::new(binary_path);
+    command.arg("logout").stdin(Stdio::null());
+    if let Some(session_dir) = session_dir {
+        command.env("TDLIB_DATA_DIR", session_dir);
+    }
+    command.output().await
+}
+
+// A reaction bot process that ended, for the supervisor to handle (see watch)
+pub struct Exit {
+    pub instance: String,
+    pub pid: u32,
+    pub status: Result<ExitStatus, String>,
+}
+
+pub type ExitReceiver = mpsc::UnboundedReceiver<Exit>;
+
+// Every watched process's exit goes to the supervisor as it happens
+static EXITS: OnceLock<mpsc::UnboundedSender<Exit>> = OnceLock::new();
+
+// The exits of all reaction bots spawned from now on; taken once, by the supervisor
+pub fn exits() -> ExitReceiver {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let _ = EXITS.set(sender);
+    receiver
+}
+
+// A running reaction bot. Its exit is awaited by a task of its own, which reports it to the
+// supervisor and to anyone waiting on this handle
+pub struct ChildHandle {
+    pid: u32,
+    exited: watch::Receiver<Option<Result<ExitStatus, String>>>,
+    kill: Option<oneshot::Sender<()>>,
+}
+
+impl ChildHandle {
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    // The exit status, once the process has ended (it may have already)
+    pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        let exited = self.exited.wait_for(Option::is_some).await.map_err(std::io::Error::other)?;
+        exited.clone().unwrap_or_else(|| Err("no exit status".to_string())).map_err(std::io::Error::other)
+    }
+
+    // Kill the process itself, whatever happened to its group
+    fn kill(&mut self) {
+        if let Some(kill) = self.kill.take() {
+            let _ = kill.send(());
+        }
+    }
+}
+
+// Start awaiting a freshly spawned child's exit. Dropping the handle leaves the process running
+pub fn watch(name: &str, mut child: Child) -> ChildHandle {
+    let pid = child.id().unwrap_or_default();
+    let (report, exited) = watch::channel(None);
+    let (kill, mut killed) = oneshot::channel();
+    let name = name.to_string();
+    tokio::spawn(async move {
+        let status = tokio::select! {
+            status = child.wait() => status,
+            Ok(()) = &mut killed => {
+                let _ = child.start_kill();
+                child.wait().await
+            }
+        };
+        let status = status.map_err(|e| e.to_string());
+        report.send_replace(Some(status.clone()));
+        if let Some(exits) = EXITS.get() {
+            let _ = exits.send(Exit { instance: name, pid, status });
+        }
+    });
+    ChildHandle { pid, exited, kill: Some(kill) }
+}
+
+// Like watch, for a bot still running from before the manager restarted (see supervisor::adopt).
+// It isn't our child, so its exit is noticed by checking on it every second, without a status; a
+// process that took the PID over afterwards doesn't count (see is_instance)
+pub fn adopt(name: &str, pid: u32) -> ChildHandle {
+    let (report, exited) = watch::channel(None);
+    let (kill, mut killed) = oneshot::channel::<()>();
+    let name = name.to_string();
+    tokio::spawn(async move {
+        while alive(pid).await && is_instance(pid, &name) {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                Ok(()) = &mut killed => {
+                    #[cfg(unix)]
+                    // SAFETY: kill() has no memory-safety requirements
+                    unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) };
+                    #[cfg(not(unix))]
+                    let _ = taskkill(pid, true).await;
+                    break;
+                }
+            }
+        }
+        while alive(pid).await && is_instance(pid, &name) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let status = Err("exit status unknown".to_string());
+        report.send_replace(Some(status.clone()));
+        if let Some(exits) = EXITS.get() {
+            let _ = exits.send(Exit { instance: name, pid, status });
+        }
+    });
+    ChildHandle { pid, exited, kill: Some(kill) }
+}
+
+// Whether a process with this PID is still there
+async fn alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: signal 0 only checks whether the process exists
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        is_running(pid).await
+    }
+}
+
+// Send a signal to the process group led by `pid` (see spawn), or to the process alone when it
+// doesn't lead one (a bot started some other way, such as by hand)
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    let kill = |target: libc::pid_t| {
+        // SAFETY: kill() has no memory-safety requirements; a negative PID addresses the group
+        if unsafe { libc::kill(target, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    };
+    match kill(-(pid as libc::pid_t)) {
+        Err(e) if e.raw_os_error() == Some(libc::ESRCH) => kill(pid as libc::pid_t),
+        result => result,
+    }
+}
+
+// Ask a reaction bot process (and its process group) to exit and wait until it has,
+// killing it after `timeout`
+pub async fn terminate(child: &mut ChildHandle, timeout: Duration) -> std::io::Result<ExitStatus> {
+    let pid = child.id();
+    
+    #[cfg(unix)]
+    let result = signal_group(pid, libc::SIGTERM);
+    #[cfg(not(unix))]
+    let result = taskkill(pid, false).await;
+    if let Err(e) = result {
+        warn!("Failed to send termination signal to PID {}: {}", pid, e);
+    }
+    
+    if let Ok(status) = tokio::time::timeout(timeout, child.wait()).await {
+        return status;
+    }
+    
+    warn!("Reaction bot PID {} did not exit within {} s, killing it", pid, timeout.as_secs());
+    #[cfg(unix)]
+    let _ = signal_group(pid, libc::SIGKILL);
+    #[cfg(not(unix))]
+    let _ = taskkill(pid, true).await;
+    child.kill();
+    child.wait().await
+}
+
+// Like terminate, for a process we only know the PID of (it isn't our child, so
+// there's no exit status to collect)
+pub async fn terminate_pid(pid: u32, timeout: Duration) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        signal_group(pid, libc::SIGTERM)?;
+        
+        let deadline = tokio::time::Instant::now() + timeout;
+        while tokio::time::Instant::now() < deadline {
+            // SAFETY: signal 0 only checks whether the process exists
+            if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        
+        warn!("Reaction bot PID {} did not exit within {} s, killing it", pid, timeout.as_secs());
+        match signal_group(pid, libc::SIGKILL) {
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            result => result,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        taskkill(pid, false).await?;
+        
+        let deadline = tokio::time::Instant::now() + timeout;
+        while tokio::time::Instant::now() < deadline {
+            if !is_running(pid).await {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        
+        warn!("Reaction bot PID {} did not exit within {} s, killing it", pid, timeout.as_secs());
+        taskkill(pid, true).await
+    }
+}
+
+// Windows has no signals: taskkill asks the process tree to close, or with `force` ends it
+#[cfg(not(unix))]
+async fn taskkill(pid: u32, force: bool) -> std::io::Result<()> {
+    let mut command = ProcessCommand::new("taskkill");
+    if force {
+        command.arg("/F");
+    }
+    command.args(["/T", "/PID", &pid.to_string()]).output().await.map(|_| ())
+}
+
+// Whether a process with this PID still exists, by tasklist's CSV line for it
+#[cfg(not(unix))]
+async fn is_running(pid: u32) -> bool {
+    ProcessCommand::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .await
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+}
@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, env, sync::{Arc, Mutex}, time::Duration};
+use botdg_config::{schedule::Schedule, timezone};
+use chrono::{Datelike, Timelike};
+use log::{info, warn};
+use serde_json::Value;
+use teloxide::prelude::*;
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{i18n::{tr, Lang}, notify::Notifier, state::{self, BotState}};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Alerts listed in the digest one by one; the rest are counted
+const MAX_DIGEST_ALERTS: usize = 20;
+
+// What an instance matched while it was quiet
+#[derive(Default)]
+struct Tally {
+    matches: u32,
+    volume: i64,
+    sent: u32,
+    failed: u32,
+}
+
+#[derive(Default)]
+struct Held {
+    tallies: BTreeMap<String, Tally>,
+    alerts: Vec<(String, String)>,
+}
+
+impl Held {
+    fn is_empty(&self) -> bool {
+        self.tallies.is_empty() && self.alerts.is_empty()
+    }
+}
+
+// QUIET_HOURS: when nobody wants to be woken up, in the WORKING_HOURS syntax and TIMEZONE, e.g.
+// "23:00-08:00". The reaction bots keep reacting and recording as usual; what goes quiet is the
+// manager's notifications: the event feed, match notifications to Discord, Slack and email, and
+// alerts that aren't critical (a lost session, repeated crashes and long outages still go out).
+// With QUIET_MODE=digest (the default) what was held back is summed up in one message once the
+// quiet hours are over; with QUIET_MODE=drop it is just dropped. Cheap to clone; clones share
+// what's held
+#[derive(Clone, Default)]
+pub struct QuietHours {
+    schedule: Option<Arc<Schedule>>,
+    digest: bool,
+    held: Arc<Mutex<Held>>,
+}
+
+impl QuietHours {
+    // Checked by validate.rs
+    pub fn from_env() -> Self {
+        let Some(value) = env::var("QUIET_HOURS").ok().filter(|value| !value.trim().is_empty()) else {
+            return Self::default();
+        };
+        let schedule = match Schedule::parse(&value) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                warn!("Ignoring invalid QUIET_HOURS '{}': {}", value, e);
+                return Self::default();
+            }
+        };
+        let digest = !env::var("QUIET_MODE").is_ok_and(|mode| mode.trim().eq_ignore_ascii_case("drop"));
+        info!("Quiet hours {} ({})", value, if digest { "morning digest" } else { "dropped" });
+        Self { schedule: Some(Arc::new(schedule)), digest, held: Arc::default() }
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        let Some(schedule) = &self.schedule else {
+            return false;
+        };
+        let now = timezone::now();
+        schedule.is_open(now.weekday().num_days_from_monday() as usize, now.hour() * 60 + now.minute())
+    }
+
+    // Count a match or reaction outcome from `instance` for the digest, during quiet hours
+    pub fn note_event(&self, instance: &str, event: &Value) {
+        if !self.digest || !self.is_quiet() {
+            return;
+        }
+        let kind = event["event"].as_str().unwrap_or_default();
+        if !matches!(kind, "match" | "reaction_sent" | "reaction_failed") {
+            return;
+        }
+        let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        let tally = held.tallies.entry(instance.to_string()).or_default();
+        match kind {
+            "match" => {
+                tally.matches += 1;
+                tally.volume += event["price"].as_i64().unwrap_or_default();
+            }
+            "reaction_sent" => tally.sent += 1,
+            _ => tally.failed += 1,
+        }
+    }
+
+    // Keep a non-critical alert for the digest instead of sending it: true during quiet hours
+    pub fn hold_alert(&self, instance: &str, text: &str) -> bool {
+        if !self.is_quiet() {
+            return false;
+        }
+        if self.digest {
+            let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+            held.alerts.push((instance.to_string(), text.to_string()));
+        }
+        true
+    }
+
+    // Everything held back, worded for a chat in `lang`, once the quiet hours are over
+    fn take_digest(&self, lang: Lang) -> Option<String> {
+        if self.is_quiet() {
+            return None;
+        }
+        let held = std::mem::take(&mut *self.held.lock().unwrap_or_else(|e| e.into_inner()));
+        if held.is_empty() {
+            return None;
+        }
+        let mut text = lang.pick("🌅 While notifications were quiet:", "🌅 Пока уведомления были отключены:").to_string();
+        for (name, tally) in &held.tallies {
+            text.push_str(&tr!(
+                lang,
+                "\n{}: {} matches for {} ₽ (reacted {}, failed {})",
+                "\n{}: заявок {} на {} ₽ (реакций {}, не удалось {})",
+                name,
+                tally.matches,
+                tally.volume,
+                tally.sent,
+                tally.failed
+            ));
+        }
+        if !held.alerts.is_empty() {
+            text.push_str(&tr!(lang, "\n\nAlerts ({}):", "\n\nОповещения ({}):", held.alerts.len()));
+            for (name, alert) in held.alerts.iter().take(MAX_DIGEST_ALERTS) {
+                text.push_str(&format!("\n• [{}] {}", name, alert));
+            }
+            if held.alerts.len() > MAX_DIGEST_ALERTS {
+                text.push_str(&tr!(lang, "\n…and {} more", "\n…и еще {}", held.alerts.len() - MAX_DIGEST_ALERTS));
+            }
+        }
+        Some(text)
+    }
+}
+
+// Post the digest to the admin chat and NOTIFY_CHAT_IDS, and to the notification sinks, when the
+// quiet hours end
+pub async fn run(bot: Bot, bot_state: Arc<AsyncMutex<BotState>>, notifier: Notifier) {
+    let quiet = notifier.quiet().clone();
+    if quiet.schedule.is_none() || !quiet.digest {
+        return;
+    }
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+
+        let (chats, lang) = {
+            let state = bot_state.lock().await;
+            let chat = state.admin_chat();
+            (state::with_broadcast(chat), state.chat_language(chat))
+        };
+        let Some(text) = quiet.take_digest(lang) else { continue };
+        info!("Quiet hours are over, posting the digest");
+        notifier.digest(&text);
+        for chat in chats {
+            if let Err(e) = bot.send_message(chat, text.clone()).await {
+                warn!("Failed to send the quiet hours digest: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::{env, sync::Arc, time::Duration};
+use botdg_config::timezone;
+use chrono::{NaiveTime, TimeDelta};
+use log::{info, warn};
+use teloxide::prelude::*;
+use tokio::sync::Mutex;
+
+use crate::{competitors, history, i18n::{tr, Lang}, state::{self, BotState, Instance}, stats};
+
+const DEFAULT_REPORT_TIME: &str = "09:00";
+const REPORT_PERIOD_SECS: i64 = 24 * 60 * 60;
+
+// Time of day the daily report is posted, from DAILY_REPORT_TIME ("HH:MM") in TIMEZONE
+fn report_time() -> NaiveTime {
+    let time = env::var("DAILY_REPORT_TIME").unwrap_or_else(|_| DEFAULT_REPORT_TIME.to_string());
+    NaiveTime::parse_from_str(&time, "%H:%M").unwrap_or_else(|_| {
+        warn!("Invalid DAILY_REPORT_TIME '{}', using {}", time, DEFAULT_REPORT_TIME);
+        NaiveTime::parse_from_str(DEFAULT_REPORT_TIME, "%H:%M").expect("valid default time")
+    })
+}
+
+fn until_next(time: NaiveTime) -> Duration {
+    let now = timezone::now().naive_local();
+    let mut next = now.date().and_time(time);
+    if next <= now {
+        next += TimeDelta::days(1);
+    }
+    (next - now).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+// Summary of the last 24 hours of the given instances' reaction histories
+pub fn build<'a>(instances: impl Iterator<Item = (&'a String, &'a Instance)>, lang: Lang) -> String {
+    let since = chrono::Utc::now().timestamp() - REPORT_PERIOD_SECS;
+    let mut text = lang.pick("📊 Daily summary (last 24 h)", "📊 Сводка за сутки (последние 24 ч)").to_string();
+
+    for (name, instance) in instances {
+        let path = instance.history_path(name);
+        let summary = if history::readable(&path) { Some(history::summary(&path, since)) } else { None };
+
+        let section = match summary {
+            Some(Ok(summary)) if summary.matches > 0 => {
+                let mut section = tr!(
+                    lang,
+                    "{}:\nMatches: {} (confirmed {}, failed {})\nMatched volume: {} ₽",
+                    "{}:\nЗаявок: {} (подтверждено {}, не удалось {})\nОбъем заявок: {} ₽",
+                    name,
+                    summary.matches,
+                    summary.confirmed,
+                    summary.failed,
+                    summary.volume
+                );
+                // Histories written before race tracking have no "won" column
+                let commission = stats::default_commission();
+                if let Ok(earnings) = history::earnings(&path, since, commission) {
+                    section.push('\n');
+                    section.push_str(&stats::format_earnings(&earnings, commission, lang));
+                }
+                if let Some((chat, matches)) = summary.busiest_chat {
+                    section.push_str(&tr!(lang, "\nBusiest chat: {}, {} matches", "\nСамый активный чат: {}, {} заявок", chat, matches));
+                }
+                if let Some(latency) = summary.avg_latency_us {
+                    section.push_str(&tr!(lang, "\nAverage latency: {:.0} µs", "\nСредняя задержка: {:.0} мкс", latency));
+                }
+                // Histories written before competitor tracking have no such table
+                if let Ok(Some(competitors)) = competitors::section(&path, since, lang) {
+                    section.push('\n');
+                    section.push_str(&competitors);
+                }
+                section
+            }
+            Some(Err(e)) => tr!(lang, "{}: failed to read the history: {}", "{}: не удалось прочитать историю: {}", name, e),
+            _ => tr!(lang, "{}: no matches", "{}: заявок не было", name),
+        };
+        text.push_str("\n\n");
+        text.push_str(&section);
+    }
+    text
+}
+
+// Post the daily summary to the /report chat at DAILY_REPORT_TIME every day
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>) {
+    let time = report_time();
+    info!("Daily report scheduled at {} (local time)", time);
+
+    loop {
+        tokio::time::sleep(until_next(time)).await;
+
+        // The /report chat and NOTIFY_CHAT_IDS, in the /report chat's language. It covers every
+        // instance, so only admins set the /report chat
+        let (chats, text) = {
+            let state = bot_state.lock().await;
+            let chat = state.report_chat_id.map(ChatId);
+            let chats = state::with_broadcast(chat);
+            if chats.is_empty() {
+                continue;
+            }
+            (chats, build(state.instances.iter(), state.chat_language(chat)))
+        };
+
+        for chat in chats {
+            if let Err(e) = bot.send_message(chat, text.clone()).await {
+                warn!("Failed to send the daily report: {}", e);
+            }
+        }
+    }
+}
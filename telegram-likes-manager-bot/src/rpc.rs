@@ -0,0 +1,87 @@
+use std::path::Path;
+use serde_json::Value;
+
+use crate::control::{ControlSender, EventSender};
+
+// How long a call waits for the reaction bot to answer
+#[cfg(unix)]
+const CALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+// A call over a reaction bot's control socket (see its rpc.rs): JSON-RPC 2.0, one line each way.
+// The result, or the error's message
+#[cfg(unix)]
+pub async fn call(socket: &Path, method: &str, params: Value) -> Result<Value, String> {
+    use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::UnixStream};
+
+    let exchange = async {
+        let mut stream = UnixStream::connect(socket).await.map_err(|e| format!("{}: {}", socket.display(), e))?;
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        stream.write_all(format!("{}\n", request).as_bytes()).await.map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(stream).lines();
+        // Nothing else comes on a connection that isn't tailing events, but skip it if it does
+        while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+            let Ok(response) = serde_json::from_str::<Value>(&line) else { continue };
+            if response["id"] != 1 {
+                continue;
+            }
+            if let Some(error) = response.get("error") {
+                return Err(error["message"].as_str().unwrap_or("error").to_string());
+            }
+            return Ok(response["result"].clone());
+        }
+        Err("the bot closed the connection".to_string())
+    };
+    tokio::time::timeout(CALL_TIMEOUT, exchange).await.unwrap_or_else(|_| Err("no answer".to_string()))
+}
+
+#[cfg(not(unix))]
+pub async fn call(_socket: &Path, _method: &str, _params: Value) -> Result<Value, String> {
+    Err("control sockets are only supported on Unix".to_string())
+}
+
+// Reattach to a running bot this manager didn't start (see supervisor::adopt), over one connection
+// to its control socket: its events come in through tail_events as they would on its stdout, and
+// the control channel's commands go out as notifications
+#[cfg(unix)]
+pub async fn attach(name: &str, socket: &Path, events: &EventSender) -> Result<ControlSender, String> {
+    use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::UnixStream, sync::mpsc};
+    use crate::control::ChildEvent;
+
+    let stream = UnixStream::connect(socket).await.map_err(|e| format!("{}: {}", socket.display(), e))?;
+    let (reader, mut writer) = stream.into_split();
+    let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "tail_events" });
+    writer.write_all(format!("{}\n", request).as_bytes()).await.map_err(|e| e.to_string())?;
+
+    let (control, mut commands) = mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(line) = commands.recv().await {
+            let Ok(Value::Object(mut params)) = serde_json::from_str::<Value>(&line) else { continue };
+            let Some(Value::String(method)) = params.remove("command") else { continue };
+            let notification = serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params });
+            if writer.write_all(format!("{}\n", notification).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let name = name.to_string();
+    let events = events.clone();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Ok(message) = serde_json::from_str::<Value>(&line) else { continue };
+            if message["method"] != "event" {
+                continue;
+            }
+            if events.send(ChildEvent { instance: name.clone(), line: message["params"].to_string() }).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(control)
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_name: &str, _socket: &Path, _events: &EventSender) -> Result<ControlSender, String> {
+    Err("control sockets are only supported on Unix".to_string())
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+use anyhow::Result;
+use teloxide::{
+    dispatching::dialogue::{Dialogue, InMemStorage},
+    prelude::*,
+    types::{KeyboardButton, KeyboardMarkup, KeyboardRemove},
+};
+use tokio::sync::Mutex;
+
+use crate::{i18n::{tr, Lang}, restart_hint, state::BotState};
+
+// Answers collected so far by the /setup wizard
+#[derive(Clone, Default)]
+pub struct SetupDraft {
+    bank_filter: Option<String>,
+    min_amount: i32,
+    requisite_filter: Option<String>,
+}
+
+// Step of the /setup conversation for a chat
+#[derive(Clone, Default)]
+pub enum SetupState {
+    #[default]
+    Idle,
+    Bank,
+    Amount(SetupDraft),
+    Requisite(SetupDraft),
+    Chats(SetupDraft),
+}
+
+pub type SetupDialogue = Dialogue<SetupState, InMemStorage<SetupState>>;
+
+// Button labels as [English, Russian]; answers are accepted in either language
+const T_BANK: [&str; 2] = ["T-Bank", "Т-Банк"];
+const SBER: [&str; 2] = ["Sber", "Сбер"];
+const ANY_BANK: [&str; 2] = ["Any bank", "Любой банк"];
+const ANY_REQUISITE: [&str; 2] = ["Any requisite", "Любые реквизиты"];
+const SBP_REQUISITE: [&str; 2] = ["SBP (+)", "СБП (+)"];
+const KEEP_CHATS: [&str; 2] = ["Keep current chats", "Оставить текущие чаты"];
+
+fn label(lang: Lang, label: [&'static str; 2]) -> &'static str {
+    lang.pick(label[0], label[1])
+}
+
+fn keyboard(rows: &[&[&str]]) -> KeyboardMarkup {
+    KeyboardMarkup::new(
+        rows.iter()
+            .map(|row| row.iter().map(|label| KeyboardButton::new(*label)).collect::<Vec<_>>())
+    )
+    .resize_keyboard(true)
+    .one_time_keyboard(true)
+}
+
+async fn language(bot_state: &Mutex<BotState>, message: &Message) -> Lang {
+    bot_state.lock().await.language(message.from().map(|user| user.id))
+}
+
+// Entry point for /setup: ask the first question
+pub async fn start(bot: Bot, message: Message, dialogue: SetupDialogue, bot_state: Arc<Mutex<BotState>>) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    let name = bot_state.lock().await.selected_name(message.from().map(|user| user.id));
+    dialogue.update(SetupState::Bank).await?;
+
+    bot.send_message(
+        message.chat.id,
+        tr!(
+            lang,
+            "⚙️ Setting up instance '{}'. Send /cancel at any time to stop.\n\n1/4 Which bank should the bot react to? Pick one or type part of the bank name.",
+            "⚙️ Настройка экземпляра '{}'. Отправьте /cancel, чтобы прервать.\n\n1/4 На какой банк реагировать? Выберите кнопку или введите часть названия банка.",
+            name
+        )
+    )
+    .reply_markup(keyboard(&[&[label(lang, T_BANK), label(lang, SBER)], &[label(lang, ANY_BANK)]]))
+    .await?;
+    Ok(())
+}
+
+pub async fn cancel(bot: Bot, message: Message, dialogue: SetupDialogue, bot_state: Arc<Mutex<BotState>>) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    dialogue.exit().await?;
+    bot.send_message(message.chat.id, lang.pick("Setup cancelled, nothing was changed.", "Настройка отменена, ничего не изменено."))
+        .reply_markup(KeyboardRemove::new())
+        .await?;
+    Ok(())
+}
+
+pub async fn receive_bank(bot: Bot, message: Message, dialogue: SetupDialogue, bot_state: Arc<Mutex<BotState>>) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    let Some(text) = message.text().map(str::trim) else {
+        bot.send_message(message.chat.id, lang.pick("Please send the bank as text.", "Отправьте банк текстом.")).await?;
+        return Ok(());
+    };
+
+    let bank_filter = if ANY_BANK.contains(&text) {
+        None
+    } else if T_BANK.contains(&text) {
+        Some("t".to_string())
+    } else if SBER.contains(&text) {
+        Some("сбер".to_string())
+    } else {
+        Some(text.to_lowercase())
+    };
+    dialogue.update(SetupState::Amount(SetupDraft { bank_filter, ..Default::default() })).await?;
+
+    bot.send_message(message.chat.id, lang.pick("2/4 What is the minimum amount (₽) to react to?", "2/4 Какая минимальная сумма (₽) для реакции?"))
+        .reply_markup(keyboard(&[&["30000", "38000", "50000"]]))
+        .await?;
+    Ok(())
+}
+
+pub async fn receive_amount(
+    bot: Bot,
+    message: Message,
+    dialogue: SetupDialogue,
+    draft: SetupDraft,
+    bot_state: Arc<Mutex<BotState>>,
+) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    let Some(amount) = message.text().and_then(|t| t.trim().replace(' ', "").parse::<i32>().ok()) else {
+        bot.send_message(message.chat.id, lang.pick("Please send the amount as a number, e.g. 38000.", "Отправьте сумму числом, например 38000.")).await?;
+        return Ok(());
+    };
+
+    dialogue.update(SetupState::Requisite(SetupDraft { min_amount: amount, ..draft })).await?;
+
+    bot.send_message(
+        message.chat.id,
+        lang.pick(
+            "3/4 Which requisites should be accepted? Pick one or type a fragment to match.",
+            "3/4 Какие реквизиты принимать? Выберите кнопку или введите фрагмент для поиска."
+        )
+    )
+    .reply_markup(keyboard(&[&[label(lang, SBP_REQUISITE), label(lang, ANY_REQUISITE)]]))
+    .await?;
+    Ok(())
+}
+
+pub async fn receive_requisite(
+    bot: Bot,
+    message: Message,
+    dialogue: SetupDialogue,
+    draft: SetupDraft,
+    bot_state: Arc<Mutex<BotState>>,
+) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    let Some(text) = message.text().map(str::trim) else {
+        bot.send_message(message.chat.id, lang.pick("Please send the requisite as text.", "Отправьте реквизиты текстом.")).await?;
+        return Ok(());
+    };
+
+    let requisite_filter = if ANY_REQUISITE.contains(&text) {
+        None
+    } else if SBP_REQUISITE.contains(&text) {
+        Some("+".to_string())
+    } else {
+        Some(text.to_string())
+    };
+    dialogue.update(SetupState::Chats(SetupDraft { requisite_filter, ..draft })).await?;
+
+    bot.send_message(
+        message.chat.id,
+        lang.pick(
+            "4/4 Which chats should be monitored? Send chat IDs separated by commas (e.g. -1002685602852,-4649902952).",
+            "4/4 Какие чаты отслеживать? Отправьте ID чатов через запятую (например, -1002685602852,-4649902952)."
+        )
+    )
+    .reply_markup(keyboard(&[&[label(lang, KEEP_CHATS)]]))
+    .await?;
+    Ok(())
+}
+
+pub async fn receive_chats(
+    bot: Bot,
+    message: Message,
+    dialogue: SetupDialogue,
+    draft: SetupDraft,
+    bot_state: Arc<Mutex<BotState>>,
+) -> Result<()> {
+    let lang = language(&bot_state, &message).await;
+    let text = message.text().map(str::trim).unwrap_or_default();
+
+    let chats = if KEEP_CHATS.contains(&text) {
+        None
+    } else {
+        let parsed = text
+            .split([',', ' '])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>();
+        match parsed {
+            Ok(chats) if !chats.is_empty() => Some(chats),
+            _ => {
+                bot.send_message(message.chat.id, lang.pick("Please send numeric chat IDs separated by commas.", "Отправьте числовые ID чатов через запятую.")).await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let user = message.from().map(|user| user.id);
+    let mut state = bot_state.lock().await;
+    let name = state.selected_name(user);
+    let instance = state.selected_mut(user);
+
+    instance.filters.bank_filter = draft.bank_filter;
+    instance.filters.min_amount = draft.min_amount;
+    instance.filters.requisite_filter = draft.requisite_filter;
+    if chats.is_some() {
+        instance.allowed_chats = chats;
+    }
+
+    let summary = tr!(
+        lang,
+        "✅ Setup of '{}' complete:\n\n{}\nChats: {}",
+        "✅ Настройка '{}' завершена:\n\n{}\nЧаты: {}",
+        name,
+        instance.filters.describe(lang),
+        instance.describe_chats(lang)
+    );
+    let running = instance.is_running;
+    state.save();
+    dialogue.exit().await?;
+
+    bot.send_message(message.chat.id, summary)
+        .reply_markup(KeyboardRemove::new())
+        .await?;
+    if running {
+        bot.send_message(message.chat.id, restart_hint(lang)).await?;
+    } else {
+        bot.send_message(message.chat.id, lang.pick("Send /start to launch the reaction bot.", "Отправьте /start, чтобы запустить бот реакций.")).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::env;
+use serde_json::{json, Value};
+
+use crate::{control, i18n::{tr, Lang}, notify::{self, Notification, Sink}};
+
+// Posts to a Slack channel through an incoming webhook, SLACK_WEBHOOK_URL
+pub struct SlackSink {
+    url: String,
+    client: reqwest::Client,
+    lang: Lang,
+}
+
+impl SlackSink {
+    pub fn from_env(lang: Lang) -> Option<Self> {
+        let url = env::var("SLACK_WEBHOOK_URL").ok().filter(|url| !url.is_empty())?;
+        Some(Self { url, client: notify::http_client(), lang })
+    }
+
+    // Block Kit message; "text" is what notifications and clients without blocks show
+    fn message(&self, notification: &Notification) -> Value {
+        let lang = self.lang;
+        match notification {
+            Notification::Match { text: Some(text), .. } => json!({
+                "text": text,
+                "blocks": [
+                    { "type": "section", "text": { "type": "mrkdwn", "text": text } },
+                ],
+            }),
+            Notification::Match { instance, event, text: None } => {
+                let title = tr!(lang, "🎯 Match on '{}'", "🎯 Заявка на '{}'", instance);
+                let amount = event["price"].as_i64().map_or("?".to_string(), |p| format!("{} ₽", p));
+                let bank = event["bank"].as_str().unwrap_or(lang.pick("unknown", "неизвестен"));
+                let latency = event["latency_us"]
+                    .as_u64()
+                    .map_or("?".to_string(), |us| format!("{:.2} ms", us as f64 / 1000.0));
+                let field = |name: &str, value: &str| json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", name, value) });
+                json!({
+                    "text": format!("{}: {}, {}", title, amount, bank),
+                    "blocks": [
+                        { "type": "section", "text": { "type": "mrkdwn", "text": format!("*{}*", title) } },
+                        { "type": "section", "fields": [
+                            field(lang.pick("Amount", "Сумма"), &amount),
+                            field(lang.pick("Bank", "Банк"), bank),
+                            field(lang.pick("Latency", "Задержка"), &latency),
+                            field(lang.pick("Message", "Сообщение"), &control::place(event)),
+                        ] },
+                    ],
+                })
+            }
+            Notification::Digest { text } => json!({
+                "text": text,
+                "blocks": [
+                    { "type": "section", "text": { "type": "mrkdwn", "text": text } },
+                ],
+            }),
+            Notification::Alert { instance, text, .. } => {
+                let title = tr!(lang, "Reaction bot '{}'", "Бот реакций '{}'", instance);
+                json!({
+                    "text": format!("{}: {}", title, text),
+                    "blocks": [
+                        { "type": "section", "text": { "type": "mrkdwn", "text": format!("*{}*\n{}", title, text) } },
+                    ],
+                })
+            }
+        }
+    }
+}
+
+impl Sink for SlackSink {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    async fn send(&mut self, notification: &Notification) {
+        let body = self.message(notification);
+        notify::post_json(&self.client, &self.url, &body, self.name()).await;
+    }
+}
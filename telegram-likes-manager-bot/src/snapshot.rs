@@ -0,0 +1,98 @@
+use std::{env, path::{Path, PathBuf}, process::Command as ProcessCommand};
+use botdg_config::timezone;
+use log::info;
+
+pub const DEFAULT_SNAPSHOT_DIR: &str = "snapshots";
+const EXTENSION: &str = ".tar.gz";
+
+// Where /snapshot keeps its archives of TDLib data directories
+pub fn snapshot_dir() -> String {
+    env::var("SNAPSHOT_DIR").unwrap_or_else(|_| DEFAULT_SNAPSHOT_DIR.to_string())
+}
+
+// Archive an instance's stopped TDLib data directory as SNAPSHOT_DIR/<instance>-<time>.tar.gz;
+// the snapshot's name and size in bytes
+pub async fn create(instance: &str, data_dir: String) -> std::io::Result<(String, u64)> {
+    let name = format!("{}-{}", instance, timezone::now().format("%Y%m%d-%H%M%S"));
+    let path = path_of(&name);
+    tokio::task::spawn_blocking(move || {
+        if !Path::new(&data_dir).is_dir() {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("no session directory {}", data_dir)));
+        }
+        std::fs::create_dir_all(snapshot_dir())?;
+        tar(ProcessCommand::new("tar").arg("-czf").arg(&path).arg("-C").arg(&data_dir).arg("."))?;
+        info!("Saved {} to snapshot {}", data_dir, path.display());
+        Ok((name, std::fs::metadata(&path)?.len()))
+    })
+    .await
+    .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+}
+
+// Put one of an instance's own snapshots in place of its stopped TDLib data directory. The
+// directory it replaces is kept next to it as <dir>.before-restore (replacing the one from an
+// earlier restore), and is put back if the snapshot can't be unpacked
+pub async fn restore(instance: &str, name: &str, data_dir: String) -> std::io::Result<()> {
+    let path = path_of(name);
+    if !belongs_to(name, instance) || !path.is_file() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("no snapshot '{}'", name)));
+    }
+    tokio::task::spawn_blocking(move || {
+        let previous = format!("{}.before-restore", data_dir.trim_end_matches('/'));
+        if Path::new(&previous).exists() {
+            std::fs::remove_dir_all(&previous)?;
+        }
+        let had_session = Path::new(&data_dir).exists();
+        if had_session {
+            std::fs::rename(&data_dir, &previous)?;
+        }
+        std::fs::create_dir_all(&data_dir)?;
+        if let Err(e) = tar(ProcessCommand::new("tar").arg("-xzf").arg(&path).arg("-C").arg(&data_dir)) {
+            std::fs::remove_dir_all(&data_dir)?;
+            if had_session {
+                std::fs::rename(&previous, &data_dir)?;
+            }
+            return Err(e);
+        }
+        info!("Restored {} from snapshot {}", data_dir, path.display());
+        Ok(())
+    })
+    .await
+    .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+}
+
+// An instance's snapshots, newest first
+pub fn list(instance: &str) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(snapshot_dir())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|file| file.strip_suffix(EXTENSION).map(str::to_string))
+        .filter(|name| belongs_to(name, instance))
+        .collect();
+    names.sort_unstable_by(|a, b| b.cmp(a));
+    names
+}
+
+// Whether a snapshot was taken of `instance`: its name followed by the time, as `create` names it.
+// The time has to be all there, or instance "a" would own the snapshots of "a-b"
+pub fn belongs_to(name: &str, instance: &str) -> bool {
+    let name = name.strip_suffix(EXTENSION).unwrap_or(name);
+    name.strip_prefix(instance).and_then(|time| time.strip_prefix('-')).is_some_and(|time| {
+        time.len() == "YYYYmmdd-HHMMSS".len() && time.chars().enumerate().all(|(at, c)| if at == 8 { c == '-' } else { c.is_ascii_digit() })
+    })
+}
+
+// Snapshot names come from chat messages: nothing that leaves SNAPSHOT_DIR
+fn path_of(name: &str) -> PathBuf {
+    let name = Path::new(name).file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    Path::new(&snapshot_dir()).join(format!("{}{}", name.trim_end_matches(EXTENSION), EXTENSION))
+}
+
+fn tar(command: &mut ProcessCommand) -> std::io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(std::io::Error::other(format!("tar failed: {}", stderr.lines().last().unwrap_or_default())))
+}
@@ -0,0 +1,485 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, env};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use teloxide::types::{ChatId, UserId};
+use tokio::sync::oneshot;
+
+use crate::{accounts, aliases, blacklist, control::ControlSender, health::{self, HealthState}, history, i18n::{tr, Lang}, output::{LogTail, RunLog}, presets, process::{self, ChildHandle}, storage, supervisor::SupervisorState};
+
+pub const DEFAULT_STATE_FILE: &str = "manager_state.json";
+pub const DEFAULT_MIN_AMOUNT: i32 = 38000;
+pub const DEFAULT_REACTION_EMOJI: &str = "👍";
+pub const DEFAULT_INSTANCE: &str = "default";
+// The reaction bot's own default, for instances without a session directory
+pub const DEFAULT_TDLIB_DATA_DIR: &str = "tdlib_data";
+
+// A new instance's own TDLib data directory, next to the default one
+pub fn session_dir(name: &str) -> String {
+    botdg_config::dirs::data_path(&format!("tdlib_data_{}", name))
+}
+
+// Get the state file path from environment or use default
+fn state_path() -> String {
+    env::var("STATE_FILE").unwrap_or_else(|_| DEFAULT_STATE_FILE.to_string())
+}
+
+// Users listed in ALLOWED_USERS. They are the admins, who can also let others in with /adduser
+pub fn configured_users() -> BTreeSet<u64> {
+    env::var("ALLOWED_USERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse::<u64>().ok())
+        .collect()
+}
+
+// Chats in NOTIFY_CHAT_IDS, e.g. a shared ops group: they get every notification and the daily
+// report on top of the chat they would go to anyway
+pub fn broadcast_chats() -> Vec<ChatId> {
+    env::var("NOTIFY_CHAT_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse::<i64>().ok())
+        .map(ChatId)
+        .collect()
+}
+
+// `chat` followed by the NOTIFY_CHAT_IDS chats, each once
+pub fn with_broadcast(chat: Option<ChatId>) -> Vec<ChatId> {
+    let mut chats = Vec::from_iter(chat);
+    for broadcast in broadcast_chats() {
+        if !chats.contains(&broadcast) {
+            chats.push(broadcast);
+        }
+    }
+    chats
+}
+
+// Filter settings passed to the reaction bot
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterSettings {
+    pub bank_filter: Option<String>,
+    pub requisite_filter: Option<String>,
+    pub min_amount: i32,
+    pub reaction_emoji: String,
+    pub bank_min_amounts: BTreeMap<String, i32>,   // Overrides min_amount for matching banks
+    pub min_profit: f64,                           // Expected profit in rubles (MIN_PROFIT); 0 is off
+    pub emoji_tiers: BTreeMap<i32, String>,        // Emoji from this amount up (EMOJI_TIERS)
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            bank_filter: None,
+            requisite_filter: None,
+            min_amount: DEFAULT_MIN_AMOUNT,
+            reaction_emoji: DEFAULT_REACTION_EMOJI.to_string(),
+            bank_min_amounts: BTreeMap::new(),
+            min_profit: 0.0,
+            emoji_tiers: BTreeMap::new(),
+        }
+    }
+}
+
+impl FilterSettings {
+    // Human-readable summary used in /start and /status replies
+    pub fn describe(&self, lang: Lang) -> String {
+        let none = lang.pick("None", "Нет");
+        let mut text = tr!(
+            lang,
+            "Bank filter: {}\nRequisite filter: {}\nMinimum amount: {}\nReaction emoji: {}",
+            "Фильтр по банку: {}\nФильтр по реквизитам: {}\nМинимальная сумма: {}\nЭмодзи реакции: {}",
+            self.bank_filter.as_deref().unwrap_or(none),
+            self.requisite_filter.as_deref().unwrap_or(none),
+            self.min_amount,
+            self.reaction_emoji
+        );
+        for (bank, amount) in &self.bank_min_amounts {
+            text.push_str(&tr!(lang, "\nMinimum amount for {}: {}", "\nМинимальная сумма для {}: {}", bank, amount));
+        }
+        if self.min_profit > 0.0 {
+            text.push_str(&tr!(lang, "\nMinimum expected profit: {} ₽", "\nМинимальная ожидаемая прибыль: {} ₽", self.min_profit));
+        }
+        for (amount, emoji) in &self.emoji_tiers {
+            text.push_str(&tr!(lang, "\nReaction emoji from {} ₽: {}", "\nЭмодзи реакции от {} ₽: {}", amount, emoji));
+        }
+        text
+    }
+    
+    // Emoji tiers in the EMOJI_TIERS format understood by the reaction bot
+    pub fn emoji_tiers_env(&self) -> String {
+        self.emoji_tiers
+            .iter()
+            .map(|(amount, emoji)| format!("{}:{}", amount, emoji))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+    
+    // Per-bank amounts in the BANK_MIN_AMOUNTS format understood by the reaction bot
+    pub fn bank_min_amounts_env(&self) -> String {
+        self.bank_min_amounts
+            .iter()
+            .map(|(bank, amount)| format!("{}:{}", bank, amount))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+// A single managed reaction bot: its process, settings and session
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Instance {
+    #[serde(skip)]
+    pub reaction_bot_process: Option<ChildHandle>,
+    pub is_running: bool,
+    pub last_status: String,
+    pub filters: FilterSettings,
+    pub binary_path: Option<String>,   // Overrides the default reaction bot binary
+    pub session_dir: Option<String>,   // TDLib data directory passed as TDLIB_DATA_DIR
+    pub allowed_chats: Option<Vec<i64>>, // Overrides the global ALLOWED_CHAT_IDS
+    pub owner: Option<u64>,            // User who added it with /instance add; None means shared
+    pub balance: Option<i64>,          // Working balance set with /balance (WORKING_BALANCE)
+    pub balance_left: Option<i64>,     // What the bot last reported left of it
+    pub balance_reset: Option<String>, // Daily refill time (BALANCE_RESET)
+    #[serde(skip)]
+    pub supervisor: SupervisorState,
+    #[serde(skip)]
+    pub health: HealthState,
+    #[serde(skip)]
+    pub control: Option<ControlSender>,     // Control channel to the running process
+    #[serde(skip)]
+    pub pending_auth: Option<String>,       // Authorization value the process is waiting for
+    #[serde(skip)]
+    pub paused: bool,                       // Reactions paused via /pause; reset on every spawn
+    pub panicked: bool,                     // Kill switch engaged via /panic; kept until /panic off
+    #[serde(skip)]
+    pub log_tail: LogTail,
+    #[serde(skip)]
+    pub run_log: Option<RunLog>,            // Log file of the current run
+    #[serde(skip)]
+    pub waiting: HashMap<String, oneshot::Sender<Value>>, // Asked over the control channel, by the event answering (see control::ask)
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            reaction_bot_process: None,
+            is_running: false,
+            last_status: "Not started".to_string(),
+            filters: FilterSettings::default(),
+            binary_path: None,
+            session_dir: None,
+            allowed_chats: None,
+            owner: None,
+            balance: None,
+            balance_left: None,
+            balance_reset: None,
+            supervisor: SupervisorState::default(),
+            health: HealthState::default(),
+            control: None,
+            pending_auth: None,
+            paused: false,
+            panicked: false,
+            log_tail: LogTail::default(),
+            run_log: None,
+            waiting: HashMap::new(),
+        }
+    }
+}
+
+impl Instance {
+    // Shared instances are open to every allowed user, owned ones only to their owner
+    pub fn accessible_by(&self, user: Option<UserId>) -> bool {
+        self.owner.is_none() || self.owner == user.map(|user| user.0)
+    }
+
+    pub fn binary_path(&self) -> String {
+        self.binary_path.clone().unwrap_or_else(process::binary_path)
+    }
+
+    // Heartbeat file the reaction bot writes for this instance
+    pub fn heartbeat_path(&self, name: &str) -> String {
+        format!("{}/{}.json", health::heartbeat_dir(), name)
+    }
+
+    // Reaction history database the reaction bot writes for this instance
+    pub fn history_path(&self, name: &str) -> String {
+        format!("{}/{}.db", history::history_dir(), name)
+    }
+
+    // Chat IDs in the ALLOWED_CHAT_IDS format, if this instance overrides them
+    pub fn allowed_chats_env(&self) -> Option<String> {
+        let chats = self.allowed_chats.as_ref()?;
+        Some(chats.iter().map(i64::to_string).collect::<Vec<_>>().join(","))
+    }
+
+    pub fn describe_chats(&self, lang: Lang) -> String {
+        self.allowed_chats_env()
+            .unwrap_or_else(|| lang.pick("global ALLOWED_CHAT_IDS", "общий ALLOWED_CHAT_IDS").to_string())
+    }
+
+    pub fn status_icon(&self, lang: Lang) -> &'static str {
+        if self.panicked {
+            lang.pick("🛑 Kill switch engaged", "🛑 Аварийная остановка")
+        } else if self.is_running && self.paused {
+            lang.pick("⏸ Paused", "⏸ Приостановлен")
+        } else if self.is_running {
+            lang.pick("✅ Running", "✅ Работает")
+        } else if self.supervisor.next_restart.is_some() {
+            lang.pick("🔄 Waiting for automatic restart", "🔄 Ожидает автоматического перезапуска")
+        } else {
+            lang.pick("❌ Not running", "❌ Не запущен")
+        }
+    }
+
+    // TDLib data directory the bot runs with: its own, or the inherited TDLIB_DATA_DIR
+    pub fn data_dir(&self) -> String {
+        self.session_dir.clone().unwrap_or_else(|| env::var("TDLIB_DATA_DIR").unwrap_or_else(|_| DEFAULT_TDLIB_DATA_DIR.to_string()))
+    }
+    
+    // Binary, session and chat details shown in /status and /instance list
+    pub fn describe_paths(&self, lang: Lang) -> String {
+        let session = match self.session_dir.as_deref() {
+            Some(dir) => match accounts::name_of(dir) {
+                Some(account) => tr!(lang, "{} (account '{}')", "{} (аккаунт '{}')", dir, account),
+                None => dir.to_string(),
+            },
+            None => lang.pick("default", "по умолчанию").to_string(),
+        };
+        tr!(
+            lang,
+            "Binary: {}\nSession directory: {}\nChats: {}",
+            "Бинарник: {}\nКаталог сессии: {}\nЧаты: {}",
+            self.binary_path(),
+            session,
+            self.describe_chats(lang)
+        )
+    }
+}
+
+// Global state: the registry of managed reaction bot instances
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotState {
+    pub instances: BTreeMap<String, Instance>,
+    pub selected_instance: String,           // Shared instance used by users who haven't picked one
+    pub selections: BTreeMap<u64, String>,   // Instance selected per user (/instance use)
+    pub notify_chat_id: Option<i64>,
+    pub event_feed_chat_id: Option<i64>,    // Chat receiving the live event feed (/events)
+    pub report_chat_id: Option<i64>,        // Chat receiving the daily summary (/report)
+    pub dashboard: Option<(i64, i32)>,       // Chat and ID of the pinned /dashboard message
+    pub dashboard_user: Option<u64>,         // Who posted it, whose instances it shows (see visible_to)
+    pub languages: BTreeMap<u64, Lang>,      // Reply language per user (/language)
+    pub added_users: BTreeSet<u64>,          // Let in with /adduser on top of ALLOWED_USERS
+    pub removed_users: BTreeSet<u64>,        // Locked out with /removeuser, even if in ALLOWED_USERS
+    pub aliases: BTreeMap<String, String>,   // Set with /alias, on top of COMMAND_ALIASES
+    #[serde(skip)]
+    pub presets: BTreeMap<String, FilterSettings>,
+    #[serde(skip)]
+    pub blacklist: BTreeSet<String>,        // Requisites never reacted to (see blacklist.rs)
+    #[serde(skip)]
+    pub configured_aliases: BTreeMap<String, String>,
+}
+
+impl Default for BotState {
+    fn default() -> Self {
+        Self {
+            instances: BTreeMap::from([(DEFAULT_INSTANCE.to_string(), Instance::default())]),
+            selected_instance: DEFAULT_INSTANCE.to_string(),
+            selections: BTreeMap::new(),
+            notify_chat_id: None,
+            event_feed_chat_id: None,
+            report_chat_id: None,
+            dashboard: None,
+            dashboard_user: None,
+            languages: BTreeMap::new(),
+            added_users: BTreeSet::new(),
+            removed_users: BTreeSet::new(),
+            aliases: BTreeMap::new(),
+            presets: BTreeMap::new(),
+            blacklist: BTreeSet::new(),
+            configured_aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl BotState {
+    // Restore the state saved by a previous run, or start fresh
+    pub fn load() -> Self {
+        let path = state_path();
+
+        let mut state = match storage::load_json::<BotState>(&path) {
+            Some(state) => {
+                info!("Restored manager state from {}", path);
+                for (name, instance) in &state.instances {
+                    if instance.is_running {
+                        // The process handle doesn't survive a restart; the bot is adopted
+                        // again if it's still there (see supervisor::adopt)
+                        info!("Instance '{}' was running before the restart", name);
+                    }
+                }
+                state
+            }
+            None => {
+                info!("No saved state found at {}, using defaults", path);
+                BotState::default()
+            }
+        };
+
+        if state.instances.is_empty() {
+            state.instances.insert(DEFAULT_INSTANCE.to_string(), Instance::default());
+        }
+        // Everyone falls back to a shared instance, so there must always be one
+        if !state.instances.values().any(|instance| instance.owner.is_none()) {
+            state.instances.insert(DEFAULT_INSTANCE.to_string(), Instance::default());
+        }
+        if state.instances.get(&state.selected_instance).is_none_or(|instance| instance.owner.is_some()) {
+            state.selected_instance = state.first_shared();
+        }
+
+        state.presets = presets::load();
+        state.blacklist = blacklist::load();
+        state.configured_aliases = aliases::from_env();
+        state
+    }
+
+    pub fn first_shared(&self) -> String {
+        self.instances
+            .iter()
+            .find(|(_, instance)| instance.owner.is_none())
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default()
+    }
+
+    // Instances a user can see and configure
+    pub fn accessible(&self, user: Option<UserId>) -> impl Iterator<Item = (&String, &Instance)> {
+        self.instances.iter().filter(move |(_, instance)| instance.accessible_by(user))
+    }
+
+    // The instances a user's summaries (/report, /dashboard) cover: all of them for admins, the
+    // accessible ones for everyone else
+    pub fn visible_to(&self, user: Option<UserId>) -> impl Iterator<Item = (&String, &Instance)> {
+        let admin = self.is_admin(user);
+        self.instances.iter().filter(move |(_, instance)| admin || instance.accessible_by(user))
+    }
+
+    // Resolve a user-supplied instance name; an empty name means the user's selected instance.
+    // Other users' instances resolve to None, as if they didn't exist
+    pub fn resolve_instance(&self, name: &str, user: Option<UserId>) -> Option<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            Some(self.selected_name(user))
+        } else if self.instances.get(name).is_some_and(|instance| instance.accessible_by(user)) {
+            Some(name.to_string())
+        } else {
+            None
+        }
+    }
+
+    // The user's /instance use choice, falling back to the shared default
+    pub fn selected_name(&self, user: Option<UserId>) -> String {
+        user.and_then(|user| self.selections.get(&user.0))
+            .filter(|name| self.instances.get(*name).is_some_and(|instance| instance.accessible_by(user)))
+            .cloned()
+            .unwrap_or_else(|| self.selected_instance.clone())
+    }
+
+    pub fn select(&mut self, user: Option<UserId>, name: &str) {
+        match user {
+            Some(user) => {
+                self.selections.insert(user.0, name.to_string());
+            }
+            None => self.selected_instance = name.to_string(),
+        }
+    }
+
+    pub fn selected(&self, user: Option<UserId>) -> &Instance {
+        &self.instances[&self.selected_name(user)]
+    }
+
+    pub fn selected_mut(&mut self, user: Option<UserId>) -> &mut Instance {
+        let name = self.selected_name(user);
+        self.instances
+            .get_mut(&name)
+            .expect("selected instance must exist")
+    }
+
+    // Whether a user may use the bot: ALLOWED_USERS and /adduser, minus /removeuser. With neither
+    // list set, anyone may
+    pub fn user_allowed(&self, user: u64) -> bool {
+        let configured = configured_users();
+        if configured.is_empty() && self.added_users.is_empty() {
+            return true;
+        }
+        (configured.contains(&user) || self.added_users.contains(&user)) && !self.removed_users.contains(&user)
+    }
+
+    pub fn is_admin(&self, user: Option<UserId>) -> bool {
+        user.is_some_and(|user| configured_users().contains(&user.0) && !self.removed_users.contains(&user.0))
+    }
+
+    // Everyone currently let in, sorted
+    pub fn users(&self) -> Vec<u64> {
+        configured_users()
+            .union(&self.added_users)
+            .filter(|user| !self.removed_users.contains(user))
+            .copied()
+            .collect()
+    }
+
+    // COMMAND_ALIASES overridden by /alias
+    pub fn all_aliases(&self) -> BTreeMap<String, String> {
+        let mut aliases = self.configured_aliases.clone();
+        aliases.extend(self.aliases.clone());
+        aliases
+    }
+
+    // Chat that receives notifications: ADMIN_CHAT_ID if set, otherwise the chat that last started the bot
+    pub fn admin_chat(&self) -> Option<ChatId> {
+        env::var("ADMIN_CHAT_ID")
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .or(self.notify_chat_id)
+            .map(ChatId)
+    }
+
+    // Where notifications about an instance go: its owner's private chat, or the admin chat for shared instances
+    pub fn notify_chat(&self, name: &str) -> Option<ChatId> {
+        match self.instances.get(name).and_then(|instance| instance.owner) {
+            Some(owner) => i64::try_from(owner).ok().map(ChatId),
+            None => self.admin_chat(),
+        }
+    }
+
+    // Notification chats for every instance (see notify_chat and NOTIFY_CHAT_IDS), and the
+    // language of the first one, which the notification is written in
+    pub fn notify_chats(&self) -> BTreeMap<String, (Vec<ChatId>, Lang)> {
+        self.instances
+            .keys()
+            .map(|name| {
+                let chat = self.notify_chat(name);
+                (name.clone(), (with_broadcast(chat), self.chat_language(chat)))
+            })
+            .collect()
+    }
+
+    // Reply language for a user: their /language choice or BOT_LANGUAGE
+    pub fn language(&self, user: Option<UserId>) -> Lang {
+        user.and_then(|user| self.languages.get(&user.0).copied())
+            .unwrap_or_else(Lang::from_env)
+    }
+
+    // Language for messages sent to a chat; private chats share their user's ID
+    pub fn chat_language(&self, chat: Option<ChatId>) -> Lang {
+        let user = chat.and_then(|chat| u64::try_from(chat.0).ok()).map(UserId);
+        self.language(user)
+    }
+
+    // Persist the current state; failures are logged but never fatal
+    pub fn save(&self) {
+        if let Err(e) = storage::save_json(&state_path(), self) {
+            warn!("Failed to save manager state: {}", e);
+        }
+    }
+}
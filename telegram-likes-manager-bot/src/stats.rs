@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, env};
+use serde::Deserialize;
+
+use crate::{control, history::{Earnings, RaceSummary}, i18n::{tr, Lang}};
+
+const DEFAULT_COMMISSION_PERCENT: f64 = 1.0;
+
+// Commission assumed for orders that don't state their own, from COMMISSION_PERCENT
+pub fn default_commission() -> f64 {
+    env::var("COMMISSION_PERCENT")
+        .ok()
+        .and_then(|s| s.replace(',', ".").parse().ok())
+        .unwrap_or(DEFAULT_COMMISSION_PERCENT)
+}
+
+// Reacted/skipped counters from the reaction bot's heartbeat, since the bot started
+#[derive(Deserialize, Default)]
+pub struct FilterStats {
+    pub reacted: u64,
+    pub skipped: BTreeMap<String, u64>,
+}
+
+// Skip reasons as reported by the reaction bot, in display order
+const REASONS: [(&str, &str, &str); 22] = [
+    ("no_price", "no price", "нет суммы"),
+    ("below_min_amount", "below the minimum amount", "сумма ниже минимальной"),
+    ("below_min_profit", "below the minimum profit", "прибыль ниже минимальной"),
+    ("below_min_score", "below the minimum score", "оценка ниже минимальной"),
+    ("over_budget", "hourly budget spent", "исчерпан лимит на час"),
+    ("off_hours", "outside working hours", "вне рабочего времени"),
+    ("over_balance", "over the remaining balance", "больше остатка баланса"),
+    ("blacklisted", "requisite blacklisted", "реквизиты в черном списке"),
+    ("suspicious", "looks like a fake order", "похоже на поддельную заявку"),
+    ("plugin", "rejected by a plugin", "отклонено плагином"),
+    ("hook", "rejected by the pre-reaction hook", "отклонено хуком перед реакцией"),
+    ("deadline", "not decided in time", "не успели обработать вовремя"),
+    ("bank_mismatch", "bank doesn't match", "банк не подходит"),
+    ("requisite_mismatch", "requisite doesn't match", "реквизиты не подходят"),
+    ("chat_not_allowed", "chat not monitored", "чат не отслеживается"),
+    ("topic_not_allowed", "forum topic not monitored", "тема форума не отслеживается"),
+    ("language", "not in an order language", "не на языке заявок"),
+    ("origin_not_allowed", "not forwarded from a trusted source", "переслано не из доверенного источника"),
+    ("sender_not_allowed", "not from the chat's order bot", "отправлено не ботом заявок"),
+    ("too_old", "arrived too late", "пришло слишком поздно"),
+    ("content_type", "content type ignored", "тип сообщения не учитывается"),
+    ("duplicate", "duplicate", "повтор"),
+];
+
+// /stats block for one instance
+pub fn format(name: &str, stats: &FilterStats, lang: Lang) -> String {
+    let total = stats.reacted + stats.skipped.values().sum::<u64>();
+    let mut text = tr!(
+        lang,
+        "📊 '{}': {} messages, reacted to {}",
+        "📊 '{}': {} сообщений, реакций {}",
+        name,
+        total,
+        stats.reacted
+    );
+    for (key, en, ru) in REASONS {
+        let count = stats.skipped.get(key).copied().unwrap_or_default();
+        if count > 0 {
+            text.push_str(&format!("\n• {}: {} ({:.1}%)", lang.pick(en, ru), count, count as f64 * 100.0 / total as f64));
+        }
+    }
+    if total > 0 && stats.reacted == 0 {
+        text.push_str(lang.pick(
+            "\nNo reactions at all: the filters may be too strict, or the order format has changed.",
+            "\nНи одной реакции: возможно, фильтры слишком строгие или изменился формат заявок."
+        ));
+    }
+    text
+}
+
+// Win rate of the reaction race: how often we were the first to react to a match
+pub fn format_race(race: &RaceSummary, lang: Lang) -> String {
+    let races = race.wins + race.losses;
+    if races == 0 {
+        return lang.pick("🏁 No races decided yet.", "🏁 Результатов гонки пока нет.").to_string();
+    }
+    let mut text = tr!(
+        lang,
+        "🏁 First on {} of {} matches ({:.1}%)",
+        "🏁 Первые в {} из {} заявок ({:.1}%)",
+        race.wins,
+        races,
+        race.wins as f64 * 100.0 / races as f64
+    );
+    if let Some(margin) = race.avg_margin_secs {
+        text.push_str(&tr!(
+            lang,
+            "\nAverage margin when contested: {:+.1} s (negative: others were faster)",
+            "\nСредний отрыв при конкуренции: {:+.1} с (отрицательный - другие были быстрее)",
+            margin
+        ));
+    }
+    // Only known when the chat marks orders (see the reaction bot's lifecycle.rs)
+    if race.paid + race.cancelled > 0 {
+        text.push_str(&tr!(
+            lang,
+            "\nOf the won orders: {} paid, {} cancelled ({:.1}% went through)",
+            "\nИз выигранных: оплачено {}, отменено {} (прошло {:.1}%)",
+            race.paid,
+            race.cancelled,
+            race.paid as f64 * 100.0 / (race.paid + race.cancelled) as f64
+        ));
+    }
+    text
+}
+
+// Volume of the orders we won and the estimated earnings on it
+pub fn format_earnings(earnings: &Earnings, default_pct: f64, lang: Lang) -> String {
+    if earnings.orders == 0 {
+        return lang.pick("💰 No won orders yet.", "💰 Выигранных заявок пока нет.").to_string();
+    }
+    tr!(
+        lang,
+        "💰 Won {} orders, {} ₽\nEstimated earnings: {:.0} ₽ ({} with their own commission, the rest at {}%)",
+        "💰 Выиграно {} заявок на {} ₽\nОценка заработка: {:.0} ₽ ({} со своей комиссией, остальные по {}%)",
+        earnings.orders,
+        earnings.volume,
+        earnings.estimated,
+        earnings.with_commission,
+        default_pct
+    )
+}
+
+// How the reaction bot says its last reactions ended (see its recent.rs)
+const OUTCOMES: [(&str, &str, &str); 6] = [
+    ("pending", "⏳ waiting", "⏳ ожидает"),
+    ("sent", "✅ sent", "✅ отправлена"),
+    ("won", "🏆 won", "🏆 первая"),
+    ("lost", "🥈 lost", "🥈 опередили"),
+    ("failed", "❌ failed", "❌ не удалась"),
+    ("unconfirmed", "❔ unconfirmed", "❔ не подтверждена"),
+];
+
+// /status panel of the last reactions, newest first, as the running bot reported them
+pub fn format_recent(reactions: &[serde_json::Value], lang: Lang) -> String {
+    if reactions.is_empty() {
+        return lang.pick("⏱ No reactions since the start.", "⏱ С запуска реакций не было.").to_string();
+    }
+    let mut text = lang.pick("⏱ Last reactions:", "⏱ Последние реакции:").to_string();
+    for reaction in reactions {
+        let outcome = reaction["outcome"].as_str().unwrap_or_default();
+        let outcome = OUTCOMES.iter().find(|(key, ..)| *key == outcome).map_or(outcome, |(_, en, ru)| lang.pick(en, ru));
+        let amount = reaction["amount"].as_i64().map_or("?".to_string(), |amount| format!("{} ₽", amount));
+        let latency = reaction["latency_us"].as_u64().map_or("?".to_string(), |us| format!("{:.2}", us as f64 / 1000.0));
+        text.push_str(&tr!(
+            lang,
+            "\n• {} {}: {} ms, {}",
+            "\n• {} {}: {} мс, {}",
+            control::place(reaction),
+            amount,
+            latency,
+            outcome
+        ));
+    }
+    text
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+use log::{info, warn};
+use serde_json::Value;
+use teloxide::prelude::*;
+use tokio::sync::Mutex;
+
+use crate::{health, history, i18n::{tr, Lang}, state::{BotState, Instance}};
+
+const DEFAULT_INTERVAL_MINUTES: u64 = 30;
+const CONNECTION_READY: &str = "connectionStateReady";
+const HOUR_SECS: i64 = 60 * 60;
+
+// Chat for passive "still alive" posts, from STATUS_CHAT_ID (disabled when unset)
+fn status_chat() -> Option<ChatId> {
+    env::var("STATUS_CHAT_ID").ok().and_then(|s| s.parse().ok()).map(ChatId)
+}
+
+// Immediate posts when an instance's TDLib connection drops and comes back, by the last known state
+// of each instance
+pub struct StatusChannel {
+    chat: Option<ChatId>,
+    degraded: HashMap<String, bool>,
+}
+
+impl StatusChannel {
+    pub fn from_env() -> Self {
+        Self { chat: status_chat(), degraded: HashMap::new() }
+    }
+
+    pub fn chat(&self) -> Option<ChatId> {
+        self.chat
+    }
+
+    // A post for a "connection" event that leaves or regains the ready state
+    pub fn connection_notice(&mut self, name: &str, event: &Value, lang: Lang) -> Option<(ChatId, String)> {
+        let chat = self.chat?;
+        if event["event"] != "connection" {
+            return None;
+        }
+        let state = event["state"].as_str().unwrap_or_default();
+        let degraded = state != CONNECTION_READY;
+        // Nothing is posted until the first state is known, so starting up isn't reported as degraded
+        let was_degraded = self.degraded.insert(name.to_string(), degraded);
+
+        match (was_degraded, degraded) {
+            (Some(false), true) => Some((chat, tr!(lang, "🟠 {}: degraded, TDLib connection is {}", "🟠 {}: проблемы, соединение TDLib: {}", name, state))),
+            (Some(true), false) => Some((chat, tr!(lang, "🟢 {}: connection restored", "🟢 {}: соединение восстановлено", name))),
+            _ => None,
+        }
+    }
+}
+
+// One line per instance: alive, connected, matches in the last hour. Also the inline status card
+pub fn build<'a>(instances: impl Iterator<Item = (&'a String, &'a Instance)>, lang: Lang) -> String {
+    let since = chrono::Utc::now().timestamp() - HOUR_SECS;
+    let mut lines = vec![lang.pick("💓 Status", "💓 Статус").to_string()];
+
+    for (name, instance) in instances {
+        if !instance.is_running {
+            lines.push(tr!(lang, "⚪ {}: stopped", "⚪ {}: остановлен", name));
+            continue;
+        }
+        let connected = health::read_heartbeat(instance, name).is_some_and(|hb| hb.connection_state == CONNECTION_READY);
+        let path = instance.history_path(name);
+        let matches = history::readable(&path)
+            .then(|| history::summary(&path, since).ok())
+            .flatten()
+            .map_or(0, |summary| summary.matches);
+
+        let icon = if instance.health.unhealthy_reason.is_none() && connected { "🟢" } else { "🟠" };
+        lines.push(tr!(
+            lang,
+            "{} {}: alive, {}, {} matches in the last hour",
+            "{} {}: работает, {}, заявок за час: {}",
+            icon,
+            name,
+            if connected { lang.pick("connected", "на связи") } else { lang.pick("not connected", "нет связи") },
+            matches
+        ));
+    }
+    lines.join("\n")
+}
+
+// Post the status to STATUS_CHAT_ID every STATUS_INTERVAL_MINUTES
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>) {
+    let Some(chat) = status_chat() else { return };
+    let minutes = env::var("STATUS_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&minutes| minutes > 0)
+        .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    info!("Posting status to chat {} every {} min", chat, minutes);
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+
+        let text = {
+            let state = bot_state.lock().await;
+            build(state.instances.iter(), state.chat_language(Some(chat)))
+        };
+        if let Err(e) = bot.send_message(chat, text).await {
+            warn!("Failed to post status: {}", e);
+        }
+    }
+}
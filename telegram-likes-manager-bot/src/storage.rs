@@ -0,0 +1,28 @@
+use std::path::Path;
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+// Read a JSON document from disk, returning None if it is missing or invalid
+pub fn load_json<T: DeserializeOwned>(path: &str) -> Option<T> {
+    let contents = std::fs::read_to_string(path).ok()?;
+
+    match serde_json::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path, e);
+            None
+        }
+    }
+}
+
+// Write a JSON document atomically (write to a temp file, then rename over the target)
+pub fn save_json<T: Serialize>(path: &str, value: &T) -> anyhow::Result<()> {
+    if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use std::{env, sync::Arc, time::{Duration, Instant}};
+use log::{error, info, warn};
+use teloxide::prelude::*;
+use tokio::sync::Mutex;
+
+use crate::{control::{self, EventSender}, health, notify::Notifier, i18n::{tr, Lang}, output, process::{self, Exit, ExitReceiver}, rpc, state::{BotState, Instance}};
+
+// First restart delay; doubled after every consecutive crash
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(5);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(300);
+// A process that stayed up this long is considered healthy again
+const STABLE_RUN_TIME: Duration = Duration::from_secs(60);
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 2;
+// Crashes in a row after which the crash alert is critical (see notify.rs)
+const REPEATED_CRASHES: u32 = 3;
+// Exit status of a reaction bot that can't run as configured (see its error.rs): restarting it
+// would only fail again
+const EXIT_CONFIG: i32 = 78;
+
+// Crash/restart bookkeeping for the reaction bot process (not persisted)
+#[derive(Default)]
+pub struct SupervisorState {
+    pub restart_attempts: u32,
+    pub next_restart: Option<Instant>,
+    pub started_at: Option<Instant>,
+}
+
+impl SupervisorState {
+    // Forget any crash history, e.g. after a manual /start or /stop
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Record that a fresh process has just been spawned
+    pub fn mark_started(&mut self) {
+        self.started_at = Some(Instant::now());
+        self.next_restart = None;
+    }
+
+    // Schedule the next restart attempt using exponential backoff
+    fn schedule_restart(&mut self) -> Duration {
+        let delay = RESTART_BACKOFF_BASE
+            .saturating_mul(2u32.saturating_pow(self.restart_attempts))
+            .min(RESTART_BACKOFF_MAX);
+        self.restart_attempts += 1;
+        self.next_restart = Some(Instant::now() + delay);
+        delay
+    }
+}
+
+// Take over the reaction bots still running from before the manager restarted, found by their
+// control socket or a recent heartbeat, rather than losing track of them or starting a second bot
+// on the same session. Instances that were running and aren't anymore are marked stopped
+pub async fn adopt(bot_state: &Mutex<BotState>, events: &EventSender) {
+    let mut state = bot_state.lock().await;
+    let chats = state.notify_chats();
+    for (name, instance) in state.instances.iter_mut() {
+        let lang = chats[name].1;
+        let socket = process::control_socket(name).filter(|socket| socket.exists());
+        let mut pid = None;
+        if let Some(socket) = &socket {
+            let status = rpc::call(socket, "status", serde_json::Value::Null).await;
+            pid = status.ok().and_then(|status| status["pid"].as_u64()).map(|pid| pid as u32).filter(|&pid| process::is_instance(pid, name));
+        }
+        let Some(pid) = pid.or_else(|| health::live_pid(instance, name)) else {
+            if instance.is_running {
+                info!("Reaction bot '{}' stopped while the manager was down", name);
+                instance.is_running = false;
+                instance.last_status = lang.pick("Stopped while the manager was down", "Остановлен, пока контрольный бот не работал").to_string();
+            }
+            continue;
+        };
+
+        info!("Adopting reaction bot '{}' (PID {}), still running from before", name, pid);
+        // Without a control socket it's only watched, and controlled again once restarted
+        if let Some(socket) = &socket {
+            match rpc::attach(name, socket, events).await {
+                Ok(control) => instance.control = Some(control),
+                Err(e) => warn!("Failed to reattach to reaction bot '{}': {}", name, e),
+            }
+        }
+        instance.reaction_bot_process = Some(process::adopt(name, pid));
+        instance.is_running = true;
+        instance.last_status = lang.pick("Running (adopted after a manager restart)", "Работает (подхвачен после перезапуска контрольного бота)").to_string();
+        instance.supervisor.reset();
+        instance.supervisor.mark_started();
+    }
+    state.save();
+}
+
+// Mark reaction bots stopped as soon as their process exits (see process::watch), and restart
+// the crashed ones when their backoff is over
+pub async fn run(bot: Bot, bot_state: Arc<Mutex<BotState>>, events: EventSender, notifier: Notifier, mut exits: ExitReceiver) {
+    let poll_interval = env::var("SUPERVISOR_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+
+    info!("Supervisor checking for due restarts every {} s", poll_interval);
+
+    loop {
+        let exit = tokio::select! {
+            Some(exit) = exits.recv() => Some(exit),
+            _ = tokio::time::sleep(Duration::from_secs(poll_interval)) => None,
+        };
+
+        let notifications = {
+            let mut state = bot_state.lock().await;
+            let chats = state.notify_chats();
+            let notifications = state.instances
+                .iter_mut()
+                .filter_map(|(name, instance)| {
+                    let (targets, lang) = &chats[name];
+                    let (text, critical) = match &exit {
+                        Some(exit) if exit.instance == *name => exited(name, instance, exit, *lang)?,
+                        Some(_) => return None,
+                        None => (restart(name, instance, &events, *lang)?, false),
+                    };
+                    // Repeated crashes are sent once per crash streak, not on every later restart
+                    if critical || (!instance.is_running && instance.supervisor.restart_attempts == REPEATED_CRASHES) {
+                        notifier.critical(name, &text);
+                    } else if notifier.quiet().hold_alert(name, &text) {
+                        return None;
+                    } else {
+                        notifier.alert(name, &text);
+                    }
+                    Some((targets.clone(), text))
+                })
+                .collect::<Vec<_>>();
+            if !notifications.is_empty() {
+                state.save();
+            }
+            notifications
+        };
+
+        for (chats, text) in notifications {
+            for chat in chats {
+                if let Err(e) = bot.send_message(chat, text.clone()).await {
+                    warn!("Failed to send supervisor notification: {}", e);
+                }
+            }
+        }
+    }
+}
+
+// Handle a process that exited without being stopped; returns a notification to send and whether
+// it's critical (sent even in quiet hours). Processes stopped with /stop and the like were already
+// let go of, and there's nothing to do for them
+fn exited(name: &str, instance: &mut Instance, exit: &Exit, lang: Lang) -> Option<(String, bool)> {
+    if instance.reaction_bot_process.as_ref().is_none_or(|child| child.id() != exit.pid) {
+        return None;
+    }
+    let (code, status) = match &exit.status {
+        Ok(status) => (status.code(), status.to_string()),
+        Err(e) => (None, e.clone()),
+    };
+
+    error!("Reaction bot '{}' exited unexpectedly: {}", name, status);
+    instance.reaction_bot_process = None;
+    instance.control = None;
+    instance.pending_auth = None;
+    instance.is_running = false;
+
+    if code == Some(EXIT_CONFIG) {
+        instance.supervisor.reset();
+        instance.last_status = tr!(lang, "Stopped: configuration error", "Остановлен: ошибка конфигурации");
+        let mut text = tr!(
+            lang,
+            "🛑 Reaction bot '{}' can't run with its current settings and won't be restarted. Fix them and /start {} again.",
+            "🛑 Бот реакций '{}' не может работать с текущими настройками и не будет перезапущен. Исправьте их и снова отправьте /start {}.",
+            name,
+            name
+        );
+        let tail = instance.log_tail.text();
+        if !tail.is_empty() {
+            text.push_str(&tr!(lang, "\n\nLast log lines:\n{}", "\n\nПоследние строки лога:\n{}", tail));
+        }
+        // Nothing restarts it, so someone has to hear about it now
+        return Some((text, true));
+    }
+
+    if instance.supervisor.started_at.is_some_and(|t| t.elapsed() >= STABLE_RUN_TIME) {
+        instance.supervisor.restart_attempts = 0;
+    }
+
+    let delay = instance.supervisor.schedule_restart();
+    instance.last_status = tr!(lang, "Crashed ({}), restarting in {} s", "Упал ({}), перезапуск через {} с", status, delay.as_secs());
+
+    let mut text = tr!(
+        lang,
+        "⚠️ Reaction bot '{}' exited unexpectedly ({}).\nRestarting in {} s (attempt {}). Send /stop {} to cancel.",
+        "⚠️ Бот реакций '{}' неожиданно завершился ({}).\nПерезапуск через {} с (попытка {}). Отправьте /stop {} для отмены.",
+        name,
+        status,
+        delay.as_secs(),
+        instance.supervisor.restart_attempts,
+        name
+    );
+    let tail = instance.log_tail.text();
+    if !tail.is_empty() {
+        text.push_str(&tr!(lang, "\n\nLast log lines:\n{}", "\n\nПоследние строки лога:\n{}", tail));
+    }
+    Some((text, false))
+}
+
+// Perform a due restart; returns a notification to send
+fn restart(name: &str, instance: &mut Instance, events: &EventSender, lang: Lang) -> Option<String> {
+    if instance.is_running {
+        return None;
+    }
+    let restart_at = instance.supervisor.next_restart?;
+    if Instant::now() < restart_at {
+        return None;
+    }
+
+    info!("Restarting reaction bot '{}' (attempt {})", name, instance.supervisor.restart_attempts);
+
+    let notification = match process::spawn(name, instance) {
+        Ok(mut child) => {
+            output::capture(name, instance, &mut child);
+            control::attach(name, instance, &mut child, events);
+            instance.reaction_bot_process = Some(process::watch(name, child));
+            instance.is_running = true;
+            instance.last_status = lang.pick("Running (restarted automatically)", "Работает (перезапущен автоматически)").to_string();
+            instance.supervisor.mark_started();
+            tr!(lang, "✅ Reaction bot '{}' restarted automatically.", "✅ Бот реакций '{}' автоматически перезапущен.", name)
+        }
+        Err(e) => {
+            let delay = instance.supervisor.schedule_restart();
+            instance.last_status = tr!(lang, "Failed to restart: {}", "Не удалось перезапустить: {}", e);
+            tr!(
+                lang,
+                "❌ Failed to restart reaction bot '{}': {}\nRetrying in {} s.",
+                "❌ Не удалось перезапустить бот реакций '{}': {}\nПовтор через {} с.",
+                name,
+                e,
+                delay.as_secs()
+            )
+        }
+    };
+
+    Some(notification)
+}
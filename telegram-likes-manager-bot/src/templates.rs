@@ -0,0 +1,102 @@
+use std::{collections::HashMap, env, time::{Duration, Instant}};
+use handlebars::Handlebars;
+use log::{info, warn};
+use serde_json::{json, Value};
+
+// A match is forgotten if no outcome has come for it by then
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(300);
+
+// The variables holding templates, and the reaction bot event each one words
+pub const TEMPLATES: [(&str, &str); 4] = [
+    ("MATCH_TEMPLATE", "match"),
+    ("REACTION_SENT_TEMPLATE", "reaction_sent"),
+    ("REACTION_FAILED_TEMPLATE", "reaction_failed"),
+    ("REACTION_UNCONFIRMED_TEMPLATE", "reaction_unconfirmed"),
+];
+
+// Handlebars templates for what the manager says about orders, instead of its own wording: the
+// event feed's notices about matches and reaction outcomes, and matches posted to Discord, Slack
+// and email. Every template gets the same fields (see `context`); an outcome's are those of the
+// match it belongs to, so they're remembered until it comes. Events without a template, and
+// templates that fail to render, get the built-in text
+pub struct Templates {
+    registry: Handlebars<'static>,
+    matches: HashMap<String, (Value, Instant)>,
+}
+
+impl Templates {
+    // Checked by validate.rs
+    pub fn from_env() -> Self {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(handlebars::no_escape);
+        for (name, event) in TEMPLATES {
+            let Some(text) = env::var(name).ok().filter(|text| !text.trim().is_empty()) else { continue };
+            match registry.register_template_string(event, text) {
+                Ok(()) => info!("Notices about '{}' events use {}", event, name),
+                Err(e) => warn!("Ignoring {}: {}", name, e),
+            }
+        }
+        Self { registry, matches: HashMap::new() }
+    }
+
+    // The text for a match or reaction outcome from `instance`, None when its event has no template
+    pub fn render(&mut self, instance: &str, event: &Value) -> Option<String> {
+        let name = event["event"].as_str()?;
+        let key = format!("{}/{}", instance, event["target"].as_str().unwrap_or_default());
+        let matched = match name {
+            "match" => {
+                self.matches.retain(|_, (_, at)| at.elapsed() < OUTCOME_TIMEOUT);
+                if TEMPLATES[1..].iter().any(|(_, outcome)| self.registry.has_template(outcome)) {
+                    self.matches.insert(key, (event.clone(), Instant::now()));
+                }
+                event.clone()
+            }
+            "reaction_sent" | "reaction_failed" | "reaction_unconfirmed" => {
+                self.matches.remove(&key).map_or_else(|| json!({ "target": event["target"] }), |(matched, _)| matched)
+            }
+            _ => return None,
+        };
+        if !self.registry.has_template(name) {
+            return None;
+        }
+        let outcome = (name != "match").then_some(event);
+        self.registry
+            .render(name, &context(instance, &matched, outcome))
+            .map_err(|e| warn!("Failed to render the template for '{}': {}", name, e))
+            .ok()
+    }
+}
+
+// Whether a template compiles, for validate.rs
+pub fn check(text: &str) -> Result<(), String> {
+    handlebars::Template::compile(text).map(drop).map_err(|e| e.to_string())
+}
+
+// What a template can use: {{instance}}, {{target}} ("chat:message"), {{chat_id}}, {{message_id}},
+// {{chat_title}} and {{sender_name}} (once the reaction bot knows them), {{amount}} (rubles), {{bank}}, {{expected_profit}}, {{score}}, {{balance_left}}, {{latency_ms}}
+// (from the update to the reaction), {{delivery_ms}} (from the post to the update), and for outcomes
+// {{outcome}} ("sent", "failed" or "unconfirmed") and {{error}}. Fields an order doesn't have are null
+fn context(instance: &str, matched: &Value, outcome: Option<&Value>) -> Value {
+    let target = matched["target"].as_str().unwrap_or_default();
+    let (chat_id, message_id) = target
+        .split_once(':')
+        .map(|(chat, message)| (chat.parse::<i64>().ok(), message.parse::<i64>().ok()))
+        .unwrap_or_default();
+    json!({
+        "instance": instance,
+        "target": target,
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "chat_title": matched["chat_title"],
+        "sender_name": matched["sender_name"],
+        "amount": matched["price"],
+        "bank": matched["bank"],
+        "expected_profit": matched["expected_profit"],
+        "score": matched["score"],
+        "balance_left": matched["balance_left"],
+        "latency_ms": matched["latency_us"].as_u64().map(|us| us as f64 / 1000.0),
+        "delivery_ms": matched["delivery_ms"],
+        "outcome": outcome.and_then(|outcome| outcome["event"].as_str()).map(|event| event.trim_start_matches("reaction_")),
+        "error": outcome.map_or(Value::Null, |outcome| outcome["message"].clone()),
+    })
+}
@@ -0,0 +1,93 @@
+use botdg_config::{schedule, validate::{var, Problems}};
+use chrono::NaiveTime;
+
+use crate::{accounts, aliases, blacklist, health, history, output, presets, process, snapshot, state, templates};
+
+// Everything the manager reads, checked before it connects to Telegram. The reaction bots check
+// their own settings when they start (see the reaction bot's validate.rs)
+pub fn all() -> Problems {
+    let mut problems = Problems::new();
+
+    if let Some(token) = problems.required("BOT_TOKEN", var("BOT_TOKEN")) {
+        // <bot id>:<secret>, as @BotFather hands it out
+        if token.trim().split_once(':').is_none_or(|(id, secret)| id.parse::<u64>().is_err() || secret.is_empty()) {
+            problems.push("BOT_TOKEN doesn't look like a bot token (123456:ABC...)");
+        }
+    }
+    if let Some(url) = var("BOT_API_URL").filter(|url| reqwest::Url::parse(url).is_err()) {
+        problems.push(format!("BOT_API_URL '{}' is not a URL", url));
+    }
+    problems.ids("ALLOWED_USERS");
+    for entry in var("COMMAND_ALIASES").unwrap_or_default().split(';').filter(|entry| !entry.trim().is_empty()) {
+        if aliases::parse_entry(entry).is_none() {
+            problems.push(format!("COMMAND_ALIASES entry '{}' is not name=/command", entry.trim()));
+        }
+    }
+    problems.number::<i64>("ADMIN_CHAT_ID", ..);
+    problems.ids("NOTIFY_CHAT_IDS");
+    problems.number::<i64>("STATUS_CHAT_ID", ..);
+    problems.one_of("BOT_LANGUAGE", &["en", "ru"]);
+    if let Some(time) = var("DAILY_REPORT_TIME") {
+        if NaiveTime::parse_from_str(time.trim(), "%H:%M").is_err() {
+            problems.push(format!("DAILY_REPORT_TIME '{}' is not HH:MM", time));
+        }
+    }
+    if let Some(Err(e)) = var("TIMEZONE").map(|tz| schedule::parse_offset(&tz)) {
+        problems.push(format!("TIMEZONE: {}", e));
+    }
+    if let Some(Err(e)) = var("QUIET_HOURS").map(|hours| schedule::Schedule::parse(&hours)) {
+        problems.push(format!("QUIET_HOURS: {}", e));
+    }
+    problems.one_of("QUIET_MODE", &["digest", "drop"]);
+
+    // Timings and limits
+    problems.number::<f64>("COMMISSION_PERCENT", 0.0..=100.0);
+    problems.number::<i64>("HEALTH_TIMEOUT_SECS", 1..);
+    problems.number::<u64>("HEALTH_CHECK_INTERVAL_SECS", 1..);
+    problems.number::<i64>("CRITICAL_UNHEALTHY_MINUTES", 1..);
+    problems.number::<u64>("SUPERVISOR_INTERVAL_SECS", 1..);
+    problems.number::<u64>("STATUS_INTERVAL_MINUTES", 1..);
+    problems.number::<u64>("DASHBOARD_INTERVAL_SECS", 5..);
+    problems.number::<u32>("EVENT_FEED_MAX_PER_MINUTE", 1..);
+    problems.number::<u64>("CONFIRM_TIMEOUT_SECS", 1..);
+    problems.number::<usize>("LOG_TAIL_LINES", 1..);
+    problems.number::<u64>("LOG_MAX_BYTES", 1..);
+    problems.number::<usize>("LOG_KEEP_RUNS", 0..);
+    problems.number::<u32>("WEBHOOK_MAX_RETRIES", 0..);
+    problems.number::<u16>("SMTP_PORT", 1..);
+    problems.one_of("SMTP_TLS", &["starttls", "tls", "none"]);
+
+    // Outgoing notifications
+    for name in ["WEBHOOK_URL", "DISCORD_WEBHOOK_URL", "SLACK_WEBHOOK_URL"] {
+        if let Some(url) = var(name).filter(|url| !url.starts_with("https://") && !url.starts_with("http://")) {
+            problems.push(format!("{} '{}' is not an http(s) URL", name, url));
+        }
+    }
+    if var("SMTP_HOST").is_some() {
+        problems.required("SMTP_TO", var("SMTP_TO"));
+    }
+    for (name, _) in templates::TEMPLATES {
+        if let Some(Err(e)) = var(name).map(|text| templates::check(&text)) {
+            problems.push(format!("{}: {}", name, e));
+        }
+    }
+
+    // Paths
+    if let Some(path) = var("REACTION_BOT_PATH").filter(|path| !std::path::Path::new(path).is_dir()) {
+        problems.push(format!("REACTION_BOT_PATH {} is not a directory", path));
+    }
+    if let Some(path) = var("REACTION_BOT_BINARY").filter(|path| !std::path::Path::new(path).is_file()) {
+        problems.push(format!("REACTION_BOT_BINARY {} is not a file", path));
+    }
+    problems.writable_file("STATE_FILE", Some(state::DEFAULT_STATE_FILE));
+    problems.writable_file("PRESETS_FILE", Some(presets::DEFAULT_PRESETS_FILE));
+    problems.writable_file("REQUISITE_BLACKLIST_FILE", Some(blacklist::DEFAULT_BLACKLIST_FILE));
+    problems.writable_dir("ACCOUNTS_DIR", Some(accounts::DEFAULT_ACCOUNTS_DIR));
+    problems.writable_dir("HISTORY_DIR", Some(history::DEFAULT_HISTORY_DIR));
+    problems.writable_dir("HEARTBEAT_DIR", Some(health::DEFAULT_HEARTBEAT_DIR));
+    problems.writable_dir("LOG_DIR", Some(output::DEFAULT_LOG_DIR));
+    problems.writable_dir("SNAPSHOT_DIR", Some(snapshot::DEFAULT_SNAPSHOT_DIR));
+    problems.writable_dir("INSTANCES_DIR", Some(process::DEFAULT_INSTANCES_DIR));
+
+    problems
+}
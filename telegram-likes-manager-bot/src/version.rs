@@ -0,0 +1,36 @@
+use std::sync::OnceLock;
+
+use crate::i18n::{tr, Lang};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const COMMIT: &str = env!("BUILD_COMMIT");
+
+static STARTED_AT: OnceLock<i64> = OnceLock::new();
+
+// Remember when the manager started, for /uptime
+pub fn mark_started() {
+    STARTED_AT.get_or_init(|| chrono::Utc::now().timestamp());
+}
+
+pub fn uptime_secs() -> i64 {
+    STARTED_AT.get().map_or(0, |started| chrono::Utc::now().timestamp() - started)
+}
+
+// "2d 3h 15m" style duration, down to seconds only for the first minute
+pub fn format_duration(secs: i64, lang: Lang) -> String {
+    let secs = secs.max(0);
+    if secs < 60 {
+        return tr!(lang, "{}s", "{} с", secs);
+    }
+
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3_600, secs % 3_600 / 60);
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(tr!(lang, "{}d", "{} д", days));
+    }
+    if days > 0 || hours > 0 {
+        parts.push(tr!(lang, "{}h", "{} ч", hours));
+    }
+    parts.push(tr!(lang, "{}m", "{} мин", minutes));
+    parts.join(" ")
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, env, time::{Duration, Instant}};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::notify;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// A match is posted without an outcome if TDLib hasn't confirmed or rejected the reaction by then
+const OUTCOME_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Posts every match, together with its reaction outcome, to WEBHOOK_URL
+pub struct Webhook {
+    events: Option<mpsc::UnboundedSender<(String, Value)>>,
+}
+
+impl Webhook {
+    pub fn from_env() -> Self {
+        let Some(url) = env::var("WEBHOOK_URL").ok().filter(|url| !url.is_empty()) else {
+            return Self { events: None };
+        };
+        let secret = env::var("WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty());
+        let max_retries = env::var("WEBHOOK_MAX_RETRIES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_MAX_RETRIES);
+
+        info!("Posting matches to webhook {} ({})", url, if secret.is_some() { "signed" } else { "unsigned" });
+        let (events, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(url, secret, max_retries, receiver));
+        Self { events: Some(events) }
+    }
+
+    // Hand a reaction bot event to the webhook task; only matches and reaction outcomes are used
+    pub fn forward(&self, instance: &str, event: &Value) {
+        if let Some(events) = &self.events {
+            let _ = events.send((instance.to_string(), event.clone()));
+        }
+    }
+}
+
+// A match waiting for its reaction outcome
+struct PendingMatch {
+    payload: Value,
+    matched_at: Instant,
+}
+
+async fn run(url: String, secret: Option<String>, max_retries: u32, mut events: mpsc::UnboundedReceiver<(String, Value)>) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create the webhook HTTP client: {}", e);
+            return;
+        }
+    };
+    let mut pending: HashMap<String, PendingMatch> = HashMap::new();
+    let mut sweep = tokio::time::interval(Duration::from_secs(5));
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let Some((instance, event)) = event else { break };
+                let target = event["target"].as_str().unwrap_or_default();
+                let key = format!("{}/{}", instance, target);
+
+                match event["event"].as_str() {
+                    Some("match") => {
+                        pending.insert(key, PendingMatch { payload: payload(&instance, &event), matched_at: Instant::now() });
+                    }
+                    Some(outcome @ ("reaction_sent" | "reaction_failed")) => {
+                        let Some(PendingMatch { mut payload, .. }) = pending.remove(&key) else { continue };
+                        payload["outcome"] = json!(if outcome == "reaction_sent" { "sent" } else { "failed" });
+                        payload["error"] = event["message"].clone();
+                        deliver(&client, &url, secret.as_deref(), max_retries, payload);
+                    }
+                    Some("reaction_unconfirmed") => {
+                        let Some(PendingMatch { mut payload, .. }) = pending.remove(&key) else { continue };
+                        payload["outcome"] = json!("unconfirmed");
+                        deliver(&client, &url, secret.as_deref(), max_retries, payload);
+                    }
+                    _ => {}
+                }
+            }
+            _ = sweep.tick() => {
+                let expired = pending
+                    .iter()
+                    .filter(|(_, pending)| pending.matched_at.elapsed() >= OUTCOME_TIMEOUT)
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<_>>();
+                for key in expired {
+                    if let Some(PendingMatch { mut payload, .. }) = pending.remove(&key) {
+                        payload["outcome"] = json!("unconfirmed");
+                        deliver(&client, &url, secret.as_deref(), max_retries, payload);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Webhook body for a "match" event from the reaction bot
+fn payload(instance: &str, event: &Value) -> Value {
+    let (chat_id, message_id) = event["target"]
+        .as_str()
+        .and_then(|target| target.split_once(':'))
+        .map(|(chat, message)| (chat.parse::<i64>().ok(), message.parse::<i64>().ok()))
+        .unwrap_or_default();
+
+    json!({
+        "event": "match",
+        "instance": instance,
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "amount": event["price"],
+        "bank": event["bank"],
+        "expected_profit": event["expected_profit"],
+        "score": event["score"],
+        "latency_us": event["latency_us"],
+        "delivery_ms": event["delivery_ms"],
+        "matched_at": chrono::Utc::now().to_rfc3339(),
+        "outcome": Value::Null,
+        "error": Value::Null,
+    })
+}
+
+// HMAC-SHA256 of the body, hex encoded, sent as "X-Signature-256: sha256=<hex>"
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// POST in the background, retrying network errors, 429 and 5xx with exponential backoff
+fn deliver(client: &reqwest::Client, url: &str, secret: Option<&str>, max_retries: u32, payload: Value) {
+    let body = payload.to_string();
+    let mut request = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.clone());
+    if let Some(secret) = secret {
+        request = request.header("X-Signature-256", sign(secret, &body));
+    }
+
+    tokio::spawn(async move {
+        if !notify::send(request, max_retries.saturating_add(1), "Match").await {
+            warn!("Giving up on webhook delivery: {}", body);
+        }
+    });
+}
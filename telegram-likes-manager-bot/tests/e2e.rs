@@ -0,0 +1,290 @@
+// The manager and a reaction bot end to end, with no Telegram account: a fake Bot API server
+// (BOT_API_URL) stands in for Telegram on the manager's side, and the reaction bot plays the
+// chat's updates from a file (MOCK_UPDATES) instead of connecting with TDLib. Commands go in as
+// Bot API updates, the reactions come out of the reaction bot's MOCK_REQUESTS
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+
+const BOT_TOKEN: &str = "123456:e2e";
+const ADMIN_ID: i64 = 4242;
+const CHAT_ID: i64 = -1001234567890;
+// How long anything may take, the reaction bot's start included
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct Api {
+    updates: VecDeque<Value>,
+    last_update_id: i64,
+    // The text of every message the manager sent
+    sent: Vec<String>,
+}
+
+type Shared = Arc<(Mutex<Api>, Condvar)>;
+
+// Just enough of the Bot API for the manager: its own user, updates by long polling, messages
+// recorded, and `true` for everything else (command menus)
+struct FakeBotApi {
+    url: String,
+    api: Shared,
+}
+
+impl FakeBotApi {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let api = Shared::default();
+        let shared = api.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let api = shared.clone();
+                thread::spawn(move || serve(stream, &api));
+            }
+        });
+        Self { url, api }
+    }
+
+    // A message from the admin in their private chat with the manager
+    fn send(&self, text: &str) {
+        let (api, changed) = &*self.api;
+        let mut api = api.lock().unwrap();
+        api.last_update_id += 1;
+        let id = api.last_update_id;
+        let admin = json!({ "id": ADMIN_ID, "is_bot": false, "first_name": "Admin", "language_code": "en" });
+        api.updates.push_back(json!({
+            "update_id": id,
+            "message": {
+                "message_id": id,
+                "date": now(),
+                "chat": { "id": ADMIN_ID, "type": "private", "first_name": "Admin" },
+                "from": admin,
+                "text": text,
+            }
+        }));
+        changed.notify_all();
+    }
+
+    // Wait for the manager to send a message containing `text`
+    fn expect_reply(&self, text: &str) {
+        let (api, changed) = &*self.api;
+        let api = api.lock().unwrap();
+        let (api, _) = changed.wait_timeout_while(api, TIMEOUT, |api| !api.sent.iter().any(|sent| sent.contains(text))).unwrap();
+        assert!(api.sent.iter().any(|sent| sent.contains(text)), "no reply with '{}', the manager sent {:?}", text, api.sent);
+    }
+}
+
+// One request per connection, answered as the Bot API would
+fn serve(stream: TcpStream, api: &Shared) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    if reader.read_exact(&mut body).is_err() {
+        return;
+    }
+    // POST /bot<token>/<method>
+    let method = request_line.split_whitespace().nth(1).and_then(|path| path.rsplit('/').next()).unwrap_or_default();
+    let result = answer(&method.to_lowercase(), &serde_json::from_slice(&body).unwrap_or_default(), api);
+    let response = json!({ "ok": true, "result": result }).to_string();
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.len(),
+        response
+    );
+}
+
+fn answer(method: &str, body: &Value, api: &Shared) -> Value {
+    let (api, changed) = &**api;
+    match method {
+        "getme" => json!({
+            "id": 123456,
+            "is_bot": true,
+            "first_name": "Manager",
+            "username": "manager_bot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false,
+        }),
+        "getwebhookinfo" => json!({ "url": "", "has_custom_certificate": false, "pending_update_count": 0 }),
+        // A short long poll, so new commands are picked up quickly
+        "getupdates" => {
+            let api = api.lock().unwrap();
+            let (mut api, _) = changed.wait_timeout_while(api, Duration::from_millis(500), |api| api.updates.is_empty()).unwrap();
+            Value::Array(api.updates.drain(..).collect())
+        }
+        "sendmessage" => {
+            let text = body["text"].as_str().unwrap_or_default().to_string();
+            let mut api = api.lock().unwrap();
+            api.sent.push(text.clone());
+            changed.notify_all();
+            json!({
+                "message_id": api.sent.len(),
+                "date": now(),
+                "chat": { "id": body["chat_id"], "type": "private", "first_name": "Admin" },
+                "text": text,
+            })
+        }
+        _ => Value::Bool(true),
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+// A scratch directory for the manager to run in, removed afterwards. Removing MOCK_UPDATES with it
+// ends a reaction bot the test didn't get to stop
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("botdg-e2e-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+struct Manager(Child);
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn reaction_bot_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../telegram-reaction-bot")
+}
+
+// The reaction bot's debug build, brought up to date
+fn build_reaction_bot() -> PathBuf {
+    let crate_dir = reaction_bot_dir();
+    let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "the reaction bot didn't build");
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| crate_dir.join("target"), PathBuf::from);
+    target_dir.join("debug/tdlib-test")
+}
+
+// Post a message to the monitored chat
+fn post(updates: &Path, message_id: i64, text: &str) {
+    let update = json!({
+        "@type": "updateNewMessage",
+        "message": {
+            "id": message_id,
+            "chat_id": CHAT_ID,
+            "date": now(),
+            "content": { "@type": "messageText", "text": { "@type": "formattedText", "text": text } }
+        }
+    });
+    let mut file = OpenOptions::new().append(true).open(updates).unwrap();
+    writeln!(file, "{}", update).unwrap();
+}
+
+// The messages the reaction bot has sent reactions to so far
+fn reacted(requests: &Path) -> Vec<i64> {
+    let mut reacted: Vec<i64> = std::fs::read_to_string(requests)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|request| request["@type"] == "addMessageReaction")
+        .filter_map(|request| request["message_id"].as_i64())
+        .collect();
+    reacted.dedup();
+    reacted
+}
+
+fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < TIMEOUT, "timed out waiting until {}", what);
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn reacts_with_the_minimum_amount_set_in_the_manager() {
+    let reaction_bot = build_reaction_bot();
+    let scratch = Scratch::new();
+    let updates = scratch.0.join("updates.jsonl");
+    let requests = scratch.0.join("requests.jsonl");
+    std::fs::write(&updates, "").unwrap();
+    let api = FakeBotApi::start();
+
+    let _manager = Manager(
+        Command::new(env!("CARGO_BIN_EXE_telegram-likes-manager-bot"))
+            .current_dir(&scratch.0)
+            // State, logs and any config file in the scratch directory, not the user's own
+            .env("BOTDG_DATA_DIR", &scratch.0)
+            .env("BOTDG_CONFIG_DIR", &scratch.0)
+            .env("BOT_TOKEN", BOT_TOKEN)
+            .env("BOT_API_URL", &api.url)
+            .env("ALLOWED_USERS", ADMIN_ID.to_string())
+            .env("BOT_LANGUAGE", "en")
+            .env("REACTION_BOT_PATH", reaction_bot_dir())
+            .env("REACTION_BOT_BINARY", &reaction_bot)
+            // Passed on to the reaction bot
+            .env("MOCK_UPDATES", &updates)
+            .env("MOCK_REQUESTS", &requests)
+            .env("TELEGRAM_API_ID", "12345")
+            .env("TELEGRAM_API_HASH", "0123456789abcdef")
+            .env("TDLIB_DATA_DIR", scratch.0.join("tdlib"))
+            .env("ALLOWED_CHAT_IDS", CHAT_ID.to_string())
+            .spawn()
+            .unwrap(),
+    );
+
+    // Above the default minimum (38 000) and still skipped, so the change reached the bot
+    api.send("/amount 50000");
+    api.expect_reply("Minimum amount set to: 50000");
+    api.send("/start");
+    api.expect_reply("Reaction bot 'default' started successfully");
+    api.expect_reply("Reaction bot 'default' authorized and running");
+
+    post(&updates, 1 << 20, "Сумма: 45 000 ₽\nБанк: Т-Банк");
+    post(&updates, 2 << 20, "Сумма: 60 000 ₽\nБанк: Т-Банк");
+    // Messages are decided in order, so the first one has been once the second is reacted to
+    wait_until("the order is reacted to", || reacted(&requests).contains(&(2 << 20)));
+    assert_eq!(reacted(&requests), [2 << 20]);
+
+    api.send("/stop");
+    api.expect_reply("Send /confirm");
+    api.send("/confirm");
+    api.expect_reply("Reaction bot 'default' stopped successfully");
+}
@@ -0,0 +1,34 @@
+/*
+ * Native plugins for the reaction bot (PLUGINS=/path/to/libmyplugin.so,...), ABI version 1.
+ *
+ * Build a shared library exporting these functions with C linkage. Only botdg_plugin_abi is
+ * required. Strings are NUL-terminated UTF-8, owned by the bot and valid only during the call.
+ * Every function is called on the bot's receive loop: return quickly and hand anything slow to a
+ * thread of your own.
+ */
+#ifndef BOTDG_PLUGIN_H
+#define BOTDG_PLUGIN_H
+
+#include <stdint.h>
+
+#define BOTDG_PLUGIN_ABI 1
+
+/* Must return BOTDG_PLUGIN_ABI; a plugin built for another version is refused at startup */
+uint32_t botdg_plugin_abi(void);
+
+/* Name shown in the logs and check-config; the library's path when missing or NULL */
+const char *botdg_plugin_name(void);
+
+/*
+ * Called for every order that passed the bot's own filters, with the chat and the message text.
+ * Return 0 to react; anything else skips the order as "plugin" in the statistics.
+ */
+int32_t botdg_plugin_check(int64_t chat_id, const char *text);
+
+/*
+ * Called for every event the bot sends its manager, as the same JSON: "match" for an order reacted
+ * to, "reaction_sent", "reaction_failed", "reaction_unconfirmed", "connection", "paused" and so on.
+ */
+void botdg_plugin_event(const char *event_json);
+
+#endif
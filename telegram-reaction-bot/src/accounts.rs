@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{client::TelegramClient, discussion::Discussions, reaction::ReactionFormat, sender::TdSender};
+
+// "@extra" of the getOption request asking an account for its user ID
+const MY_ID_EXTRA: &str = "race_account:my_id";
+
+// RACE_ACCOUNT_DIRS=tdlib_data_b,tdlib_data_c: TDLib sessions of further accounts, each logged in
+// on its own beforehand (`tdlib-test login` with TDLIB_DATA_DIR pointing at it). Every match is
+// reacted to from all of them as well as from the main account, and the race check counts it as won
+// when any of them was first (see race.rs). Reactions are sent with the same message ID, so this
+// only works in supergroups and channels, where message IDs are the same for every account
+pub fn data_dirs() -> Vec<String> {
+    std::env::var("RACE_ACCOUNT_DIRS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// User IDs of the race accounts, each with its data directory; filled in as the accounts answer
+pub type AccountIds = Arc<Mutex<HashMap<i64, String>>>;
+
+pub struct RaceAccounts {
+    senders: Vec<TdSender>,
+    ids: AccountIds,
+    format: ReactionFormat,
+}
+
+impl RaceAccounts {
+    pub fn new(format: ReactionFormat) -> Self {
+        Self { senders: Vec::new(), ids: AccountIds::default(), format }
+    }
+
+    // Take over a logged-in account's client. Its updates are read on a thread of its own, which
+    // keeps the session connected and only looks at its user ID and the answers to its reactions
+    pub fn add(&mut self, dir: &str, client: Arc<dyn TelegramClient>, sender: TdSender) {
+        sender.send(json!({ "@type": "getOption", "name": "my_id", "@extra": MY_ID_EXTRA }).to_string());
+        let ids = self.ids.clone();
+        let dir = dir.to_string();
+        thread::spawn(move || {
+            let mut update = String::new();
+            while !client.is_exhausted() {
+                if !client.receive_into(1.0, &mut update) {
+                    continue;
+                }
+                let Ok(json) = serde_json::from_str::<Value>(&update) else { continue };
+                if json["@extra"] == MY_ID_EXTRA {
+                    // 64-bit option values come as strings
+                    let id = json["value"].as_i64().or_else(|| json["value"].as_str().and_then(|id| id.parse().ok()));
+                    if let (Some(id), Ok(mut ids)) = (id, ids.lock()) {
+                        info!("Race account {} is user {}", dir, id);
+                        ids.insert(id, dir.clone());
+                    }
+                } else if json["@type"] == "error" && json["@extra"].as_str().is_some_and(|extra| extra.starts_with("reaction:")) {
+                    warn!("Reaction {} from race account {} failed: {}", json["@extra"], dir, json["message"]);
+                } else if json["authorization_state"]["@type"] == "authorizationStateClosed" {
+                    warn!("Race account {} was logged out; log it in again and restart the bot to use it", dir);
+                    break;
+                }
+            }
+        });
+        self.senders.push(sender);
+    }
+
+    pub fn ids(&self) -> AccountIds {
+        self.ids.clone()
+    }
+
+    // React from every race account, right after the main one; their answers never reach the main
+    // loop. Not in DISCUSSION_REACTIONS channels, whose discussion copy only the main account looks up
+    pub fn react(&self, discussions: &Discussions, (chat_id, message_id): (i64, i64), emoji: &str, extra: &str) {
+        if discussions.always(chat_id) {
+            return;
+        }
+        for sender in &self.senders {
+            for request in self.format.requests(chat_id, message_id, emoji, extra) {
+                sender.send(request.to_string());
+            }
+        }
+    }
+}
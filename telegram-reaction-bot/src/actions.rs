@@ -0,0 +1,225 @@
+use std::{collections::HashMap, time::Duration};
+use botdg_core::Order;
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::sender::TdSender;
+
+// "@extra" of the requests actions send, followed by action:chat:message
+pub const EXTRA_PREFIX: &str = "action:";
+const DEFAULT_REPLY_TEXT: &str = "+";
+// Name REPLY_TEXT is registered under
+const REPLY: &str = "reply";
+
+// What the bot does with a matched order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    React,    // The reaction, with everything around it: emoji tiers, jitter, in-flight limits, resends
+    Reply,    // REPLY_TEXT as a reply to the order
+    Forward,  // The order forwarded to FORWARD_TO_CHAT_ID
+    Button,   // The order's inline button pressed: the one labelled BUTTON_TEXT, or the first
+}
+
+const NAMES: [(Action, &str); 4] = [(Action::React, "react"), (Action::Reply, "reply"), (Action::Forward, "forward"), (Action::Button, "button")];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        NAMES[self as usize].1
+    }
+}
+
+// The actions taken on a match, in the order given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strategy(Vec<Action>);
+
+impl Strategy {
+    // Action names joined by "+" (or ","), e.g. "react+reply"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut actions = Vec::new();
+        for name in value.split(['+', ',']).map(str::trim).filter(|name| !name.is_empty()) {
+            let (action, _) = NAMES.iter().find(|(_, known)| name.eq_ignore_ascii_case(known)).ok_or_else(|| {
+                let names: Vec<&str> = NAMES.iter().map(|(_, name)| *name).collect();
+                format!("unknown action '{}' (one of {})", name, names.join(", "))
+            })?;
+            if !actions.contains(action) {
+                actions.push(*action);
+            }
+        }
+        if actions.is_empty() {
+            return Err("no actions given".to_string());
+        }
+        Ok(Self(actions))
+    }
+
+    pub fn contains(&self, action: Action) -> bool {
+        self.0.contains(&action)
+    }
+
+    pub fn name(&self) -> String {
+        self.0.iter().map(|action| action.name()).collect::<Vec<_>>().join("+")
+    }
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self(vec![Action::React])
+    }
+}
+
+// What is done with matched orders: ACTIONS for every chat (default "react"), CHAT_ACTIONS
+// ("chat:reply+button,...") for some. The reaction goes out from the receive loop as it always
+// has; the rest are sent from here right after it, with no jitter or in-flight limit. A new
+// action is a name in NAMES and an arm in `run`
+pub struct Actions {
+    default: Strategy,
+    chats: HashMap<i64, Strategy>,
+    reply: Handlebars<'static>,
+    forward_to: Option<i64>,
+    button_text: Option<String>,
+}
+
+impl Actions {
+    // Checked by validate.rs
+    pub fn from_env() -> Self {
+        let default = std::env::var("ACTIONS").ok().and_then(|actions| Strategy::parse(&actions).ok()).unwrap_or_default();
+        let mut chats = HashMap::new();
+        for entry in std::env::var("CHAT_ACTIONS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_entry(entry) {
+                Some((chat_id, strategy)) => {
+                    info!("Chat {}: {}", chat_id, strategy.name());
+                    chats.insert(chat_id, strategy);
+                }
+                None => warn!("Ignoring invalid CHAT_ACTIONS entry '{}'", entry),
+            }
+        }
+        if default != Strategy::default() {
+            info!("On a match: {}", default.name());
+        }
+        let reply_text = std::env::var("REPLY_TEXT").ok().filter(|text| !text.trim().is_empty());
+        let reply = reply_template(reply_text.as_deref().unwrap_or(DEFAULT_REPLY_TEXT)).unwrap_or_else(|e| {
+            warn!("REPLY_TEXT: {}", e);
+            reply_template(DEFAULT_REPLY_TEXT).expect("the default reply is a valid template")
+        });
+        Self {
+            default,
+            chats,
+            reply,
+            forward_to: std::env::var("FORWARD_TO_CHAT_ID").ok().and_then(|chat| chat.trim().parse().ok()),
+            button_text: std::env::var("BUTTON_TEXT").ok().filter(|text| !text.trim().is_empty()),
+        }
+    }
+
+    pub fn strategy(&self, chat_id: i64) -> &Strategy {
+        self.chats.get(&chat_id).unwrap_or(&self.default)
+    }
+
+    // Whether matches in the chat get a reaction
+    pub fn reacts(&self, chat_id: i64) -> bool {
+        self.strategy(chat_id).contains(Action::React)
+    }
+
+    // Every action of the chat's strategy but the reaction, for a matched order `latency` after it
+    // was received
+    pub fn run(&self, client: &TdSender, chat_id: i64, message: &Value, order: &Order, latency: Duration) {
+        let Some(message_id) = message["id"].as_i64() else {
+            return;
+        };
+        for &action in &self.strategy(chat_id).0 {
+            let extra = format!("{}{}:{}:{}", EXTRA_PREFIX, action.name(), chat_id, message_id);
+            let request = match action {
+                Action::React => continue,
+                Action::Reply => match self.reply.render(REPLY, &reply_context(chat_id, message_id, order, latency)) {
+                    Ok(text) => json!({
+                        "@type": "sendMessage",
+                        "chat_id": chat_id,
+                        // Older TDLib takes reply_to_message_id, newer reply_to; each ignores the other
+                        "reply_to_message_id": message_id,
+                        "reply_to": { "@type": "inputMessageReplyToMessage", "message_id": message_id },
+                        "input_message_content": {
+                            "@type": "inputMessageText",
+                            "text": { "@type": "formattedText", "text": text }
+                        },
+                        "@extra": extra
+                    }),
+                    Err(e) => {
+                        warn!("Failed to render REPLY_TEXT for {}:{}: {}", chat_id, message_id, e);
+                        continue;
+                    }
+                },
+                Action::Forward => match self.forward_to {
+                    Some(forward_to) => json!({
+                        "@type": "forwardMessages",
+                        "chat_id": forward_to,
+                        "from_chat_id": chat_id,
+                        "message_ids": [message_id],
+                        "send_copy": false,
+                        "remove_caption": false,
+                        "@extra": extra
+                    }),
+                    None => continue,
+                },
+                Action::Button => match callback_data(message, self.button_text.as_deref()) {
+                    Some(data) => json!({
+                        "@type": "getCallbackQueryAnswer",
+                        "chat_id": chat_id,
+                        "message_id": message_id,
+                        "payload": { "@type": "callbackQueryPayloadData", "data": data },
+                        "@extra": extra
+                    }),
+                    None => {
+                        warn!("No button to press on {}:{}", chat_id, message_id);
+                        continue;
+                    }
+                },
+            };
+            client.send(request.to_string());
+        }
+    }
+}
+
+// "chat:react+reply"
+pub fn parse_entry(entry: &str) -> Option<(i64, Strategy)> {
+    let (chat_id, actions) = entry.split_once(':')?;
+    Some((chat_id.trim().parse().ok()?, Strategy::parse(actions).ok()?))
+}
+
+// REPLY_TEXT compiled as a Handlebars template, for `run` and validate.rs. Texts go out as they
+// are rendered, without HTML escaping
+pub fn reply_template(text: &str) -> Result<Handlebars<'static>, String> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(handlebars::no_escape);
+    registry.register_template_string(REPLY, text).map_err(|e| e.to_string())?;
+    Ok(registry)
+}
+
+// What REPLY_TEXT can use: {{amount}} (rubles), {{stated_amount}} and {{currency}} as the message
+// gives them, {{bank}}, {{requisite}}, {{commission}}, {{chat_id}}, {{message_id}} and {{latency_ms}}.
+// Fields the order doesn't have render as nothing
+fn reply_context(chat_id: i64, message_id: i64, order: &Order, latency: Duration) -> Value {
+    json!({
+        "amount": order.rubles,
+        "stated_amount": order.amount.as_ref().map(|amount| amount.value),
+        "currency": order.amount.as_ref().map(|amount| &amount.currency),
+        "bank": order.bank,
+        "requisite": order.requisite,
+        "commission": order.commission,
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "latency_ms": (latency.as_secs_f64() * 100_000.0).round() / 100.0,
+    })
+}
+
+// The data of the message's inline callback button whose label contains `label` (any case), or of
+// its first callback button without one
+fn callback_data(message: &Value, label: Option<&str>) -> Option<String> {
+    let label = label.map(str::to_lowercase);
+    message["reply_markup"]["rows"]
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter(|button| button["type"]["@type"] == "inlineKeyboardButtonTypeCallback")
+        .find(|button| label.as_ref().is_none_or(|label| button["text"].as_str().is_some_and(|text| text.to_lowercase().contains(label))))
+        .and_then(|button| button["type"]["data"].as_str().map(str::to_string))
+}
@@ -0,0 +1,253 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+
+use crate::{bot_token, client::TelegramClient, configure, control::ControlChannel, error::BotError, heartbeat::Heartbeat, sender::TdSender, tdlib_data_dir, TdClient};
+
+// How long to wait for each TDLib message while logging in
+const POLL_TIMEOUT: f64 = 0.1;
+// Rejected answers and stalled states together; the login gives up after this many
+const MAX_FAILURES: u32 = 3;
+// How often the heartbeat and health checks are kept up while waiting for an answer
+const PROMPT_KEEP_ALIVE: Duration = Duration::from_secs(5);
+
+// TDLib's authorizationState, as far as logging in is concerned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum State {
+    WaitTdlibParameters,
+    WaitPhoneNumber,
+    WaitCode,
+    WaitPassword,
+    Ready,
+    // Logging out or closing: nothing to answer until TDLib reports Closed
+    Ending,
+    Closed,
+    // Email login, registration of a new account, QR confirmation: not supported here
+    Unsupported(String),
+}
+
+impl State {
+    fn parse(name: &str) -> Self {
+        match name {
+            "authorizationStateWaitTdlibParameters" => Self::WaitTdlibParameters,
+            "authorizationStateWaitPhoneNumber" => Self::WaitPhoneNumber,
+            "authorizationStateWaitCode" => Self::WaitCode,
+            "authorizationStateWaitPassword" => Self::WaitPassword,
+            "authorizationStateReady" => Self::Ready,
+            "authorizationStateLoggingOut" | "authorizationStateClosing" => Self::Ending,
+            "authorizationStateClosed" => Self::Closed,
+            other => Self::Unsupported(other.to_string()),
+        }
+    }
+
+    // How long TDLib may take to move on from this state once it has been answered. Waiting for a
+    // person to type the answer isn't counted: the clock starts after the answer is sent.
+    // AUTH_TIMEOUT_SECS sets it for every state, for slow networks and proxies
+    fn timeout(&self) -> Duration {
+        if let Some(secs) = std::env::var("AUTH_TIMEOUT_SECS").ok().and_then(|s| s.parse::<u64>().ok()).filter(|secs| *secs > 0) {
+            return Duration::from_secs(secs);
+        }
+        match self {
+            Self::WaitTdlibParameters | Self::Ending => Duration::from_secs(30),
+            // Sending the code or checking the answer is a round trip to Telegram
+            _ => Duration::from_secs(60),
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsupported(name) => write!(f, "{}", name),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+// Why logging in failed
+#[derive(Debug)]
+pub enum AuthError {
+    // TDLib rejected our answers or got stuck MAX_FAILURES times; `last` is the latest reason
+    TooManyFailures { state: State, last: String },
+    // The account needs a login step this bot can't do
+    Unsupported(String),
+    // TDLib closed the session before the login finished
+    Closed,
+    // Nobody could be asked for the phone number, code or password (see control.rs)
+    Input(std::io::Error),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyFailures { state, last } => {
+                write!(f, "Failed to log in after {} attempts (in {}): {}", MAX_FAILURES, state, last)
+            }
+            Self::Unsupported(state) => write!(f, "Unsupported authorization state {}; log in with another client first", state),
+            Self::Closed => write!(f, "TDLib closed the session while logging in"),
+            Self::Input(e) => write!(f, "Failed to get login input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+// Drive TDLib's authorization state machine until the account is logged in. The caller has
+// already sent the TDLib parameters (see configure); prompts go to the terminal or the manager
+// (see control.rs). A TDLib error leaves the state unchanged, so the same question is asked again;
+// errors and states that don't move on within their timeout both count towards MAX_FAILURES
+pub async fn authorize(
+    client: &dyn TelegramClient,
+    sender: &TdSender,
+    control: &ControlChannel,
+    heartbeat: &mut Heartbeat,
+) -> Result<(), AuthError> {
+    let mut state = State::WaitTdlibParameters;
+    let mut deadline = Instant::now() + state.timeout();
+    let mut failures = 0;
+
+    let mut fail = |state: &State, reason: String| {
+        failures += 1;
+        if failures >= MAX_FAILURES {
+            Err(AuthError::TooManyFailures { state: state.clone(), last: reason })
+        } else {
+            Ok(())
+        }
+    };
+
+    loop {
+        heartbeat.tick();
+        crate::health::alive();
+        // Waiting on a person for the code isn't a stall (only matters when logging in again)
+        crate::systemd::keep_alive();
+        if Instant::now() >= deadline {
+            warn!("Still in {} after {} s", state, state.timeout().as_secs());
+            fail(&state, format!("no response within {} s", state.timeout().as_secs()))?;
+            deadline = Instant::now() + state.timeout();
+            continue;
+        }
+
+        let message = client.receive(POLL_TIMEOUT);
+        let Some(json) = message.and_then(|msg| serde_json::from_str::<Value>(&msg).ok()) else {
+            continue;
+        };
+
+        match json["@type"].as_str().unwrap_or_default() {
+            "updateAuthorizationState" => {
+                let name = json["authorization_state"]["@type"].as_str().unwrap_or_default();
+                crate::health::set_auth_state(name);
+                state = State::parse(name);
+                info!("Auth state: {}", state);
+                match &state {
+                    State::Ready => {
+                        info!("Authorization successful!");
+                        control.emit(json!({ "event": "authorized" }));
+                        return Ok(());
+                    }
+                    State::Closed => return Err(AuthError::Closed),
+                    State::Unsupported(name) => return Err(AuthError::Unsupported(name.clone())),
+                    _ => answer(sender, control, heartbeat, &state).await?,
+                }
+                deadline = Instant::now() + state.timeout();
+            }
+            "error" => {
+                error!("Error from TDLib: {}", json["message"]);
+                control.emit(json!({ "event": "auth_error", "message": json["message"] }));
+                fail(&state, json["message"].as_str().unwrap_or("unknown error").to_string())?;
+                // TDLib stays where it was after a rejected answer, so ask again
+                answer(sender, control, heartbeat, &state).await?;
+                deadline = Instant::now() + state.timeout();
+            }
+            "updateConnectionState" => {
+                if let Some(state) = json["state"]["@type"].as_str() {
+                    heartbeat.set_connection_state(state);
+                    crate::health::set_connection_state(state);
+                }
+            }
+            "updateOption" if json["name"] == "version" => {
+                if let Some(version) = json["value"]["value"].as_str() {
+                    info!("TDLib version: {}", version);
+                    heartbeat.set_tdlib_version(version);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Send TDLib what the state asks for
+async fn answer(sender: &TdSender, control: &ControlChannel, heartbeat: &mut Heartbeat, state: &State) -> Result<(), AuthError> {
+    let request = match state {
+        // With a bot token the same pipeline runs under a bot account
+        State::WaitPhoneNumber => match bot_token() {
+            Some(token) => {
+                info!("Logging in with the bot token from TELEGRAM_BOT_TOKEN");
+                json!({
+                    "@type": "checkAuthenticationBotToken",
+                    "token": token
+                })
+            }
+            None => {
+                let phone_number =
+                    ask(control, heartbeat, "phone_number", "Please enter your phone number (with country code, e.g. +1234567890):").await?;
+                json!({
+                    "@type": "setAuthenticationPhoneNumber",
+                    "phone_number": phone_number
+                })
+            }
+        },
+        State::WaitCode => {
+            let code = ask(control, heartbeat, "code", "Please enter the verification code:").await?;
+            json!({
+                "@type": "checkAuthenticationCode",
+                "code": code
+            })
+        }
+        State::WaitPassword => {
+            let password = ask(control, heartbeat, "password", "Please enter your 2FA password:").await?;
+            json!({
+                "@type": "checkAuthenticationPassword",
+                "password": password
+            })
+        }
+        // The parameters were sent with the log settings (see configure); the rest need no answer
+        _ => return Ok(()),
+    };
+
+    sender.send(request.to_string());
+    Ok(())
+}
+
+// Wait for a prompt's answer (see control.rs) without going quiet: the heartbeat, health checks and
+// systemd watchdog are kept up meanwhile, so a person taking their time isn't taken for a hang
+async fn ask(control: &ControlChannel, heartbeat: &mut Heartbeat, kind: &str, text: &str) -> Result<String, AuthError> {
+    let prompt = control.prompt(kind, text);
+    tokio::pin!(prompt);
+    let mut keep_alive = tokio::time::interval(PROMPT_KEEP_ALIVE);
+    loop {
+        tokio::select! {
+            answer = &mut prompt => return answer.map_err(AuthError::Input),
+            _ = keep_alive.tick() => {
+                heartbeat.tick();
+                crate::health::alive();
+                crate::systemd::keep_alive();
+            }
+        }
+    }
+}
+
+// `auth`: log in (prompts on the terminal, or through the manager with CONTROL_CHANNEL=stdio) and
+// exit, so the session in TDLIB_DATA_DIR is ready before the bot is first started
+pub fn run() -> Result<(), BotError> {
+    let data_dir = tdlib_data_dir();
+    let client = TdClient::connect()?;
+    let sender = TdSender::spawn(client.clone());
+    configure(&sender, &data_dir)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(authorize(client.as_ref(), &sender, &ControlChannel::from_env(), &mut Heartbeat::from_env()))?;
+    eprintln!("Logged in; the session is in {}", data_dir);
+    Ok(())
+}
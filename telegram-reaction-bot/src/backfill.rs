@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{formatted::MessageText, sender::TdSender};
+
+// "@extra" of getChatHistory requests, followed by the chat ID
+pub const EXTRA_PREFIX: &str = "backfill:";
+// Messages read per chat by the manager's /backfill when neither it nor BACKFILL_LIMIT says
+pub const DEFAULT_COMMAND_LIMIT: u32 = 50;
+const DEFAULT_MAX_AGE_SECS: i64 = 300;
+// getChatHistory returns at most 100 messages per call, and often fewer
+const PAGE_SIZE: u32 = 100;
+
+// A chat whose history is still being read
+struct Progress {
+    remaining: u32,
+    oldest: i64,   // Message ID the next page starts before
+}
+
+#[derive(Default)]
+struct Summary {
+    fetched: u64,
+    matched: u64,
+    open: u64,    // Matches young enough that nobody has reacted to yet
+    stale: u64,   // Matches older than BACKFILL_MAX_AGE_SECS
+    taken: u64,   // Matches someone (maybe us, before the restart) already reacted to
+}
+
+// Catches up on orders posted while the bot was down. Reads the last messages of every monitored
+// chat with getChatHistory and runs them through the filters; with BACKFILL_REACT=true, matches at
+// most BACKFILL_MAX_AGE_SECS old that nobody has reacted to yet are fed back into the receive loop
+// as updateNewMessage, so they take the same path (and duplicate check) as live messages.
+// Everything else is only counted, and the counts go to the manager as a "backfill" event
+pub struct Backfill {
+    limit: u32,
+    react: bool,
+    max_age_secs: i64,
+    chats: HashMap<i64, Progress>,
+    summary: Summary,
+    queue: VecDeque<String>,
+}
+
+impl Backfill {
+    // BACKFILL_LIMIT messages per chat are read on start and after logging in again (0, the
+    // default, only on the manager's /backfill)
+    pub fn from_env() -> Self {
+        Self {
+            limit: std::env::var("BACKFILL_LIMIT").ok().and_then(|s| s.parse().ok()).unwrap_or(0),
+            react: std::env::var("BACKFILL_REACT").is_ok_and(|v| v == "true" || v == "1"),
+            max_age_secs: std::env::var("BACKFILL_MAX_AGE_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_MAX_AGE_SECS),
+            chats: HashMap::new(),
+            summary: Summary::default(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    // Messages per chat for a /backfill that may name its own count
+    pub fn command_limit(&self, requested: Option<u64>) -> u32 {
+        match requested {
+            Some(limit) => limit.min(u32::MAX as u64) as u32,
+            None if self.limit > 0 => self.limit,
+            None => DEFAULT_COMMAND_LIMIT,
+        }
+    }
+
+    // Read the last `limit` messages of each chat. A run still in progress is dropped: its
+    // answers are ignored, and anything it queued is caught by the duplicate check
+    pub fn start(&mut self, sender: &TdSender, chat_ids: &HashSet<i64>, limit: u32) {
+        if limit == 0 || chat_ids.is_empty() {
+            return;
+        }
+        info!("Backfilling the last {} messages of {} chats", limit, chat_ids.len());
+        self.summary = Summary::default();
+        self.chats.clear();
+        for &chat_id in chat_ids {
+            self.chats.insert(chat_id, Progress { remaining: limit, oldest: 0 });
+            request(sender, chat_id, 0, limit);
+        }
+    }
+
+    // Forget a run whose answers won't come (the client they were sent through is gone)
+    pub fn cancel(&mut self) {
+        self.chats.clear();
+        self.queue.clear();
+    }
+
+    // A getChatHistory answer, by the chat ID after EXTRA_PREFIX. `matches` is the live filter
+    // decision for a message. Returns the summary event once the last chat is done
+    pub fn handle(&mut self, chat: &str, response: &Value, sender: &TdSender, matches: impl Fn(i64, i64, &MessageText) -> bool) -> Option<Value> {
+        let chat_id: i64 = chat.parse().ok()?;
+        let progress = self.chats.get_mut(&chat_id)?;
+
+        let messages: &[Value] = match response["messages"].as_array() {
+            Some(messages) if response["@type"] == "messages" => messages,
+            _ => {
+                warn!("Can't read the history of chat {}: {}", chat_id, response["message"]);
+                &[]
+            }
+        };
+        // Pages are newest first; one that starts at the previous page's last message repeats it
+        let page: Vec<&Value> = messages
+            .iter()
+            .filter(|message| progress.oldest == 0 || message["id"].as_i64().is_some_and(|id| id < progress.oldest))
+            .take(progress.remaining as usize)
+            .collect();
+
+        let now = chrono::Utc::now().timestamp();
+        for message in &page {
+            let (Some(message_id), Some(text)) = (message["id"].as_i64(), MessageText::from_tdlib(&message["content"]["text"])) else {
+                continue;
+            };
+            self.summary.fetched += 1;
+            if !matches(chat_id, message_id, &text) {
+                continue;
+            }
+            self.summary.matched += 1;
+            if has_reactions(message) {
+                self.summary.taken += 1;
+            } else if now - message["date"].as_i64().unwrap_or_default() > self.max_age_secs {
+                self.summary.stale += 1;
+            } else {
+                self.summary.open += 1;
+                if self.react {
+                    info!("Backfill: reacting to {}:{} posted while the bot was down", chat_id, message_id);
+                    self.queue.push_back(json!({ "@type": "updateNewMessage", "message": message }).to_string());
+                }
+            }
+        }
+
+        progress.remaining -= page.len() as u32;
+        if let Some(oldest) = page.last().and_then(|message| message["id"].as_i64()) {
+            progress.oldest = oldest;
+        }
+        if !page.is_empty() && progress.remaining > 0 {
+            request(sender, chat_id, progress.oldest, progress.remaining);
+            return None;
+        }
+
+        self.chats.remove(&chat_id);
+        if !self.chats.is_empty() {
+            return None;
+        }
+        let summary = &self.summary;
+        info!(
+            "Backfill done: {} messages read, {} matched: {} open{}, {} stale, {} already reacted to",
+            summary.fetched,
+            summary.matched,
+            summary.open,
+            if self.react { " (reacted)" } else { "" },
+            summary.stale,
+            summary.taken
+        );
+        Some(json!({
+            "event": "backfill",
+            "fetched": summary.fetched,
+            "matched": summary.matched,
+            "open": summary.open,
+            "stale": summary.stale,
+            "taken": summary.taken,
+            "reacted": self.react
+        }))
+    }
+
+    // The next match to react to, as an update for the receive loop
+    pub fn next_update(&mut self) -> Option<String> {
+        self.queue.pop_front()
+    }
+}
+
+fn request(sender: &TdSender, chat_id: i64, from_message_id: i64, remaining: u32) {
+    sender.send(json!({
+        "@type": "getChatHistory",
+        "chat_id": chat_id,
+        "from_message_id": from_message_id,
+        "offset": 0,
+        "limit": remaining.min(PAGE_SIZE),
+        "only_local": false,
+        "@extra": format!("{}{}", EXTRA_PREFIX, chat_id)
+    }).to_string());
+}
+
+// Whether anyone reacted to the message. Newer TDLib wraps the list in messageReactions
+fn has_reactions(message: &Value) -> bool {
+    let reactions = &message["interaction_info"]["reactions"];
+    reactions["reactions"]
+        .as_array()
+        .or(reactions.as_array())
+        .is_some_and(|reactions| reactions.iter().any(|reaction| reaction["total_count"].as_i64().unwrap_or_default() > 0))
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+
+// What the receive loop has taken in since it last looked, and what is still waiting in it
+pub struct Depth {
+    pub pulled: usize,             // Updates received from TDLib
+    pub oldest: Option<Instant>,   // When the oldest update still waiting was received
+}
+
+// How far behind the receive loop runs, between two heartbeats: how many updates each receive
+// call picked up (more than one only when they are read ahead, with HOT_CHAT_IDS, CHAT_PRIORITIES
+// or PIPELINE_WORKERS), and how long the oldest update read ahead but not handled yet had waited.
+// Updates still queued inside TDLib aren't visible here; the delivery latency (see latency.rs)
+// covers those
+#[derive(Default)]
+pub struct Backlog {
+    receives: u64,
+    pulled: u64,
+    max_pulled: usize,
+    lag: Duration,
+    max_lag: Duration,
+}
+
+impl Backlog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Taken once per receive loop iteration
+    pub fn record(&mut self, depth: Depth) {
+        if depth.pulled > 0 {
+            self.receives += 1;
+            self.pulled += depth.pulled as u64;
+            self.max_pulled = self.max_pulled.max(depth.pulled);
+        }
+        self.lag = depth.oldest.map_or(Duration::ZERO, |oldest| oldest.elapsed());
+        self.max_lag = self.max_lag.max(self.lag);
+    }
+
+    // Gauge for the heartbeat file; the maximums start over after each
+    pub fn gauge(&mut self) -> Value {
+        let per_receive = if self.receives == 0 { 0.0 } else { self.pulled as f64 / self.receives as f64 };
+        let gauge = json!({
+            "per_receive": (per_receive * 10.0).round() / 10.0,
+            "max_per_receive": self.max_pulled,
+            "lag_ms": self.lag.as_millis() as u64,
+            "max_lag_ms": self.max_lag.as_millis() as u64,
+        });
+        *self = Self { lag: self.lag, ..Self::default() };
+        gauge
+    }
+}
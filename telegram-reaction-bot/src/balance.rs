@@ -0,0 +1,88 @@
+use botdg_config::timezone;
+use chrono::{NaiveDate, NaiveTime};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::stats::SkipReason;
+
+// Working balance: WORKING_BALANCE=150000 is the money there is to pay for claimed orders. Every
+// match takes its amount off what's left, and orders larger than the rest are skipped as
+// over_balance, so the bot doesn't claim what can't be funded. BALANCE_LEFT starts with less (the
+// manager passes on what was left before a restart), BALANCE_RESET=09:00 fills it up again every
+// day at that time in TIMEZONE, and the manager's /balance sets or refills it while running. Off when unset
+pub struct Balance {
+    total: Option<i64>,
+    left: i64,
+    reset_at: Option<NaiveTime>,
+    reset_on: NaiveDate,   // Day of the last scheduled refill
+}
+
+impl Balance {
+    pub fn from_env() -> Self {
+        let number = |name: &str| std::env::var(name).ok().and_then(|value| value.trim().parse::<i64>().ok());
+        let total = number("WORKING_BALANCE").filter(|total| *total > 0);
+        let left = number("BALANCE_LEFT").map_or(total.unwrap_or_default(), |left| left.clamp(0, total.unwrap_or_default()));
+        let reset_at = std::env::var("BALANCE_RESET").ok().filter(|value| !value.trim().is_empty()).and_then(|value| {
+            parse_time(&value).or_else(|| {
+                warn!("Ignoring invalid BALANCE_RESET '{}'", value);
+                None
+            })
+        });
+        if let Some(total) = total {
+            info!("Working balance: {} of {} left", left, total);
+        }
+        // Starting after today's refill time doesn't count as one; before it, today's is still due
+        let now = timezone::now();
+        let today = now.date_naive();
+        let reset_on = match reset_at {
+            Some(reset_at) if now.time() < reset_at => today.pred_opt().unwrap_or(today),
+            _ => today,
+        };
+        Self { total, left, reset_at, reset_on }
+    }
+
+    // Ok when the order fits in what's left; orders without an amount aren't held back
+    pub fn check(&self, amount: Option<i32>) -> Result<(), SkipReason> {
+        match (self.total, amount) {
+            (Some(_), Some(amount)) if i64::from(amount) > self.left => Err(SkipReason::OverBalance),
+            _ => Ok(()),
+        }
+    }
+
+    // Take a matched order's amount off the balance; what's left, if there is a balance
+    pub fn spend(&mut self, amount: Option<i32>) -> Option<i64> {
+        self.total?;
+        self.left = (self.left - i64::from(amount.unwrap_or_default())).max(0);
+        Some(self.left)
+    }
+
+    // Set the balance (None turns it off) and fill it up; a "balance" event for the manager
+    pub fn set(&mut self, total: Option<i64>) -> Value {
+        self.total = total.filter(|total| *total > 0);
+        self.left = self.total.unwrap_or_default();
+        info!("Working balance set to {:?}", self.total);
+        self.event("set")
+    }
+
+    // The daily BALANCE_RESET refill, once its time has come
+    pub fn check_reset(&mut self) -> Option<Value> {
+        let (reset_at, total) = (self.reset_at?, self.total?);
+        let now = timezone::now();
+        if now.date_naive() == self.reset_on || now.time() < reset_at {
+            return None;
+        }
+        self.reset_on = now.date_naive();
+        self.left = total;
+        info!("Working balance refilled to {}", total);
+        Some(self.event("reset"))
+    }
+
+    fn event(&self, reason: &str) -> Value {
+        json!({ "event": "balance", "reason": reason, "total": self.total, "left": self.total.map(|_| self.left) })
+    }
+}
+
+// BALANCE_RESET as "HH:MM"
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashSet, sync::mpsc};
+use tracing::warn;
+
+// Chats whose reactions go through the Bot API instead of TDLib
+enum Chats {
+    None,
+    All,
+    Listed(HashSet<i64>),
+}
+
+// Reacts through the official Bot API (setMessageReaction) with a bot token, in the chats listed
+// in BOT_API_CHAT_IDS, for setups where the chat admits the bot and reacting from a user session
+// is too risky. Updates still come from TDLib; only the reaction itself takes the other route.
+// Results come back as TDLib-style "ok"/"error" objects carrying the reaction's "@extra", so the
+// main loop handles them like any other reaction outcome (see standby::Updates::add_results)
+pub struct BotApi {
+    chats: Chats,
+    #[cfg(feature = "bot-api")]
+    reactions: Option<mpsc::Sender<Reaction>>,
+}
+
+#[cfg(feature = "bot-api")]
+struct Reaction {
+    chat_id: i64,
+    message_id: i64,
+    emoji: String,
+    extra: String,
+}
+
+impl BotApi {
+    // BOT_API_CHAT_IDS: comma separated chat IDs, or "all". BOT_API_TOKEN is the bot's token
+    // (TELEGRAM_BOT_TOKEN when unset). Needs the "bot-api" feature. Also returns the receiving
+    // end of the results, if reactions can go this way at all
+    pub fn from_env() -> (Self, Option<mpsc::Receiver<String>>) {
+        let chats = match std::env::var("BOT_API_CHAT_IDS").unwrap_or_default().trim() {
+            "" => Chats::None,
+            "all" => Chats::All,
+            list => Chats::Listed(list.split(',').filter_map(|id| id.trim().parse().ok()).collect()),
+        };
+        if matches!(chats, Chats::None) {
+            return (Self::disabled(), None);
+        }
+
+        #[cfg(feature = "bot-api")]
+        {
+            let Some(token) = crate::secrets::get("BOT_API_TOKEN").or_else(crate::bot_token) else {
+                warn!("BOT_API_CHAT_IDS is set but there is no BOT_API_TOKEN; reacting through TDLib everywhere");
+                return (Self::disabled(), None);
+            };
+            match &chats {
+                Chats::All => tracing::info!("Reacting through the Bot API in every chat"),
+                Chats::Listed(ids) => tracing::info!("Reacting through the Bot API in chats {:?}", ids),
+                Chats::None => {}
+            }
+            let (reactions, receiver) = mpsc::channel();
+            let (results, results_receiver) = mpsc::channel();
+            std::thread::spawn(move || worker::run(token, receiver, results));
+            (Self { chats, reactions: Some(reactions) }, Some(results_receiver))
+        }
+
+        #[cfg(not(feature = "bot-api"))]
+        {
+            warn!("BOT_API_CHAT_IDS is set but this build has no bot-api feature; reacting through TDLib everywhere");
+            (Self::disabled(), None)
+        }
+    }
+
+    fn disabled() -> Self {
+        Self {
+            chats: Chats::None,
+            #[cfg(feature = "bot-api")]
+            reactions: None,
+        }
+    }
+
+    pub fn handles(&self, chat_id: i64) -> bool {
+        match &self.chats {
+            Chats::None => false,
+            Chats::All => true,
+            Chats::Listed(ids) => ids.contains(&chat_id),
+        }
+    }
+
+    // Queue a reaction; `extra` tags its result like a TDLib request's "@extra"
+    pub fn react(&self, chat_id: i64, message_id: i64, emoji: &str, extra: &str) {
+        #[cfg(feature = "bot-api")]
+        if let Some(reactions) = &self.reactions {
+            let _ = reactions.send(Reaction { chat_id, message_id, emoji: emoji.to_string(), extra: extra.to_string() });
+        }
+        #[cfg(not(feature = "bot-api"))]
+        let _ = (chat_id, message_id, emoji, extra);
+    }
+}
+
+#[cfg(feature = "bot-api")]
+mod worker {
+    use std::{sync::mpsc, time::Duration};
+    use serde_json::{json, Value};
+    use tracing::warn;
+
+    use super::Reaction;
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+    // TDLib message IDs of server messages are the Bot API ones shifted left by 20 bits
+    const SERVER_MESSAGE_ID_SHIFT: u32 = 20;
+
+    pub fn run(token: String, reactions: mpsc::Receiver<Reaction>, results: mpsc::Sender<String>) {
+        // One agent keeps the connection to api.telegram.org alive between reactions
+        let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+        let url = format!("https://api.telegram.org/bot{}/setMessageReaction", token);
+
+        while let Ok(reaction) = reactions.recv() {
+            let result = match react(&agent, &url, &reaction) {
+                Ok(()) => json!({ "@type": "ok", "@extra": reaction.extra }),
+                Err((code, message)) => {
+                    warn!("Bot API reaction {} failed: {}", reaction.extra, message);
+                    json!({ "@type": "error", "code": code, "message": message, "@extra": reaction.extra })
+                }
+            };
+            if results.send(result.to_string()).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn react(agent: &ureq::Agent, url: &str, reaction: &Reaction) -> Result<(), (u16, String)> {
+        if reaction.message_id & ((1 << SERVER_MESSAGE_ID_SHIFT) - 1) != 0 {
+            return Err((400, format!("message {} has no Bot API ID", reaction.message_id)));
+        }
+        let body = json!({
+            "chat_id": reaction.chat_id,
+            "message_id": reaction.message_id >> SERVER_MESSAGE_ID_SHIFT,
+            "reaction": [{ "type": "emoji", "emoji": reaction.emoji }]
+        });
+        match agent.post(url).send_json(body) {
+            Ok(_) => Ok(()),
+            // The Bot API explains rejections in "description"
+            Err(ureq::Error::Status(code, response)) => {
+                let description = response
+                    .into_json::<Value>()
+                    .ok()
+                    .and_then(|body| body["description"].as_str().map(str::to_string))
+                    .unwrap_or_else(|| format!("HTTP {}", code));
+                Err((code, description))
+            }
+            // Not e.to_string(): that includes the URL, and with it the token
+            Err(ureq::Error::Transport(transport)) => {
+                Err((0, format!("{} ({})", transport.kind(), transport.message().unwrap_or("no details"))))
+            }
+        }
+    }
+}
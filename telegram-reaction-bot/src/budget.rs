@@ -0,0 +1,38 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::stats::SkipReason;
+
+const WINDOW: Duration = Duration::from_secs(3600);
+
+// Budget mode: at most REACTION_BUDGET reactions in any hour, so an account that gets limited for
+// reacting too often spends them on the orders that matter. With MIN_SCORE set, only orders scoring
+// above it get that far (see score.rs in botdg-core). Off when unset or 0
+pub struct Budget {
+    limit: usize,
+    spent: VecDeque<Instant>,
+}
+
+impl Budget {
+    pub fn from_env() -> Self {
+        let limit = std::env::var("REACTION_BUDGET").ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+        Self { limit, spent: VecDeque::new() }
+    }
+
+    // Ok and counted when the last hour left room for another reaction
+    pub fn take(&mut self) -> Result<(), SkipReason> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        while self.spent.front().is_some_and(|at| at.elapsed() >= WINDOW) {
+            self.spent.pop_front();
+        }
+        if self.spent.len() >= self.limit {
+            return Err(SkipReason::OverBudget);
+        }
+        self.spent.push_back(Instant::now());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+const DEFAULT_MAX_AGE_SECS: i64 = 300;
+
+// A reaction the filters approved, until TDLib confirms it or it fails for good
+#[derive(Clone)]
+pub struct Claim {
+    pub extra: String,
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub posted_at: i64,
+    pub emoji: String,
+}
+
+// Reactions approved but not yet confirmed (sent and waiting for TDLib, held by REACTION_JITTER_MS,
+// queued by MAX_IN_FLIGHT_PER_CHAT or waiting out a flood wait) are kept in PENDING_REACTIONS_FILE
+// ("pending_reactions" in TDLIB_DATA_DIR by default; empty keeps them in memory only), so a crash or
+// restart in the middle of a burst doesn't drop them. On the next start they're sent again and
+// waited for like new ones; Telegram keeps one reaction per emoji, so one that did go out is just
+// confirmed. Claims on messages posted more than PENDING_REACTIONS_MAX_AGE_SECS (default 300) ago
+// are dropped, and so is everything while the kill switch is engaged
+pub struct Claims {
+    file: Option<PathBuf>,
+    claims: BTreeMap<String, Claim>,
+    changed: bool,
+    writing: Option<JoinHandle<()>>,
+}
+
+impl Claims {
+    pub fn from_env() -> Self {
+        let file = match std::env::var("PENDING_REACTIONS_FILE") {
+            Ok(path) if path.trim().is_empty() => None,
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(Path::new(&crate::tdlib_data_dir()).join("pending_reactions")),
+        };
+        Self { file, claims: BTreeMap::new(), changed: false, writing: None }
+    }
+
+    // The claims left from the last run that are still worth sending, oldest first. They're kept
+    // until settled like new ones
+    pub fn restore(&mut self, halted: bool) -> Vec<Claim> {
+        let Some(file) = &self.file else { return Vec::new() };
+        let Ok(contents) = std::fs::read_to_string(file) else { return Vec::new() };
+        let saved = match serde_json::from_str::<Value>(&contents) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Ignoring {}: {}", file.display(), e);
+                Value::Null
+            }
+        };
+        let max_age = std::env::var("PENDING_REACTIONS_MAX_AGE_SECS").ok().and_then(|s| s.trim().parse().ok()).unwrap_or(DEFAULT_MAX_AGE_SECS);
+        let oldest = chrono::Utc::now().timestamp() - max_age;
+        let mut restored: Vec<Claim> = saved
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|claim| {
+                Some(Claim {
+                    extra: claim["extra"].as_str()?.to_string(),
+                    chat_id: claim["chat_id"].as_i64()?,
+                    message_id: claim["message_id"].as_i64()?,
+                    posted_at: claim["posted_at"].as_i64()?,
+                    emoji: claim["emoji"].as_str()?.to_string(),
+                })
+            })
+            .collect();
+        let total = restored.len();
+        if halted {
+            restored.clear();
+        }
+        restored.retain(|claim| claim.posted_at >= oldest);
+        if restored.len() < total {
+            info!("Dropping {} unconfirmed reactions from before the restart", total - restored.len());
+        }
+        restored.sort_by_key(|claim| claim.posted_at);
+        for claim in &restored {
+            self.claims.insert(claim.extra.clone(), claim.clone());
+        }
+        self.changed = true;
+        restored
+    }
+
+    pub fn add(&mut self, claim: Claim) {
+        self.claims.insert(claim.extra.clone(), claim);
+        self.changed = true;
+    }
+
+    // Confirmed, or failed for good
+    pub fn settle(&mut self, extra: &str) {
+        self.changed |= self.claims.remove(extra).is_some();
+    }
+
+    pub fn clear(&mut self) {
+        self.changed |= !self.claims.is_empty();
+        self.claims.clear();
+    }
+
+    // Write the claims out if they changed; once round the receive loop, after the reactions went out.
+    // The write runs on a blocking thread, one at a time: changes made while one is still going wait
+    // for the first round after it's done, so a burst costs a write or two rather than one a message
+    pub fn save(&mut self) {
+        if !self.changed || self.writing.as_ref().is_some_and(|writing| !writing.is_finished()) {
+            return;
+        }
+        self.changed = false;
+        let Some(file) = self.file.clone() else { return };
+        let claims: Vec<Value> = self
+            .claims
+            .values()
+            .map(|claim| {
+                json!({
+                    "extra": claim.extra,
+                    "chat_id": claim.chat_id,
+                    "message_id": claim.message_id,
+                    "posted_at": claim.posted_at,
+                    "emoji": claim.emoji,
+                })
+            })
+            .collect();
+        let contents = Value::from(claims).to_string();
+        self.writing = Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = write(&file, &contents) {
+                warn!("Failed to save the unconfirmed reactions to {}: {}", file.display(), e);
+            }
+        }));
+    }
+}
+
+// Written whole to a temporary file first, so a crash halfway leaves the last list rather than half of one
+fn write(file: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = file.as_os_str().to_owned();
+    tmp.push(".tmp");
+    file.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&tmp, contents))
+        .and_then(|()| std::fs::rename(&tmp, file))
+}
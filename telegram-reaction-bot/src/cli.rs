@@ -0,0 +1,299 @@
+use std::{collections::{BTreeMap, HashSet}, io::Read, path::Path};
+use botdg_config::{validate::var, ConfigArgs, Loaded};
+use botdg_core::PricePatterns;
+use clap::{Parser, Subcommand};
+
+use crate::{
+    daemon::DaemonOptions, decide, formatted::MessageText, get_allowed_chat_ids,
+    history::ExportFormat,
+    init::InitOptions,
+    rates::Prices,
+    secrets, tdlib_data_dir, validate, FilterSettings,
+};
+
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("BUILD_COMMIT"), ")");
+
+// Without a subcommand the bot runs, so `tdlib-test --daemon` keeps working as before `run` existed.
+// Settings come from the environment, .env and the config file; --config and --set work everywhere
+#[derive(Parser)]
+#[command(name = "tdlib-test", version = VERSION, about = "Reacts to new order messages in Telegram chats through TDLib")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub run: DaemonOptions,
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// The same as `check-config`, for deployment scripts that only pass flags
+    #[arg(long)]
+    pub check_config: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the bot (the default)
+    Run(DaemonOptions),
+    /// Write a commented botdg.toml and a .env for first-time setup, asking for what isn't given
+    Init(InitOptions),
+    /// Log in to Telegram interactively and exit, so `run` starts with a ready session
+    Auth,
+    /// Show whether a message would be reacted to with the current filters; exits with 1 if not
+    TestFilter {
+        /// Message text; read from stdin when omitted
+        text: Option<String>,
+        /// Also check the chat against ALLOWED_CHAT_IDS
+        #[arg(long)]
+        chat: Option<i64>,
+        /// Log every filter step to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+    /// Dump the reaction history (HISTORY_DB) to a file or stdout
+    #[command(alias = "export")]
+    ExportHistory {
+        #[arg(value_enum)]
+        format: ExportFormat,
+        /// Only reactions since a date (2026-10-01) or a period back from now (7d, 12h)
+        #[arg(long)]
+        since: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print the effective configuration and what's wrong with it; exits with 1 on problems
+    CheckConfig,
+    /// Run recorded updates (RECORD_UPDATES files) through the filters without reacting
+    Replay {
+        /// Recordings, oldest first
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Print only the messages that would be reacted to
+        #[arg(long)]
+        reacted: bool,
+    },
+    /// Run generated order messages through the bot against a fake Telegram and report match rate and latency
+    Simulate {
+        /// Messages per second
+        #[arg(long, default_value_t = 10.0)]
+        rate: f64,
+        /// Messages in total
+        #[arg(long, default_value_t = 200)]
+        count: usize,
+        /// Generate the same messages as an earlier run
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Log what the bot does to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+    /// Parse the sample order messages in a corpus directory and report what was found; exits with 1 when one isn't read as expected
+    ValidateCorpus {
+        /// Directory of samples: .txt files of one message, .jsonl files of messages with what they should give
+        #[arg(default_value = crate::corpus::DEFAULT_CORPUS_DIR)]
+        dir: String,
+        /// Also list the samples missing an amount, bank or requisite
+        #[arg(long)]
+        missing: bool,
+    },
+    /// End the session in TDLIB_DATA_DIR and delete its files
+    Logout,
+    /// Manage credentials in the OS keyring
+    Keyring {
+        #[command(subcommand)]
+        action: KeyringAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyringAction {
+    /// Store a credential, read from stdin
+    Set { name: String },
+    /// Delete a stored credential
+    Delete { name: String },
+}
+
+// `test-filter`: the same chat, filter and amount checks as the live bot, for one message
+pub fn test_filter(text: Option<String>, chat: Option<i64>, verbose: bool) -> Result<bool, String> {
+    let text = match text {
+        Some(text) => text,
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text).map_err(|e| format!("Failed to read the message: {}", e))?;
+            text
+        }
+    };
+    if verbose {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).with_target(false).init();
+    }
+
+    let filter_settings = FilterSettings::from_env();
+    let prices = Prices::from_env();
+    let decision = match chat {
+        Some(chat_id) if !get_allowed_chat_ids().contains(&chat_id) => Err(crate::stats::SkipReason::ChatNotAllowed),
+        _ => decide(chat.unwrap_or_default(), 0, &MessageText::plain(&text), None, &HashSet::new(), &filter_settings, &prices),
+    };
+
+    println!(
+        "{} | {} ₽ | {}",
+        decision.map_or_else(|reason| format!("skip {}", reason.name()), |()| "react".to_string()),
+        prices.extract(&MessageText::plain(&text)).map_or("?".to_string(), |p| p.to_string()),
+        prices.order(&MessageText::plain(&text)).and_then(|order| order.bank).unwrap_or_else(|| "?".to_string())
+    );
+    Ok(decision.is_ok())
+}
+
+// `check-config`: what the bot would run with, and anything that would stop it or make it ignore
+// every message
+pub fn check_config(config: &Loaded) -> Result<(), String> {
+    if let Some(profile) = &config.profile {
+        println!("Profile: {}", profile);
+    }
+    match &config.file {
+        Some(file) => println!("Config file: {}", file.display()),
+        None => println!("Config file: none"),
+    }
+    println!("Data directory: {}", botdg_config::dirs::data_dir().display());
+    for (key, origin) in config.origins() {
+        println!("  {} ({})", key, origin);
+    }
+
+    if let Some(id) = secrets::get("TELEGRAM_API_ID") {
+        println!("TELEGRAM_API_ID: {}", id.trim());
+    }
+    if secrets::get("TELEGRAM_API_HASH").is_some() {
+        println!("TELEGRAM_API_HASH: set");
+    }
+    println!("Login: {}", if crate::bot_token().is_some() { "bot token" } else { "user account" });
+    chats();
+
+    let filters = FilterSettings::from_env();
+    println!("Bank filter: {:?}", filters.filter.bank);
+    println!("Requisite filter: {:?}", filters.filter.requisite);
+    println!("Minimum amount: {}", filters.filter.min_amount);
+    let library = botdg_core::patterns::library().map(|(name, _)| name).collect::<Vec<_>>().join(", ");
+    println!("Price patterns: {} (of {})", var("PRICE_PATTERNS").unwrap_or_else(|| "default".to_string()), library);
+    price_patterns();
+    if let Some(languages) = var("ORDER_LANGUAGES") {
+        println!("Order languages: {}", languages);
+    }
+    if filters.filter.min_profit > 0.0 {
+        println!("Minimum expected profit: {}", filters.filter.min_profit);
+    }
+    if let Some(min_score) = filters.min_score {
+        println!("Minimum score: {} ({:?})", min_score, filters.weights);
+    }
+    println!("On a match: {}", var("ACTIONS").unwrap_or_else(|| "react".to_string()));
+    if let Some(chats) = var("CHAT_ACTIONS") {
+        println!("On a match, by chat: {}", chats);
+    }
+    println!("Reaction emoji: {}", filters.reaction_emoji);
+    if let Some(tiers) = var("EMOJI_TIERS") {
+        println!("Emoji tiers: {}", tiers);
+    }
+    match crate::plugins::load() {
+        Ok(plugins) if plugins.is_empty() => {}
+        Ok(plugins) => println!("Plugins: {}", plugins.names().join(", ")),
+        Err(e) => println!("Plugins: {}", e),
+    }
+    match crate::wasm::load().map(|filters| filters.names()) {
+        Ok(filters) if filters.is_empty() => {}
+        Ok(filters) => println!("WASM filters: {}", filters.join(", ")),
+        Err(e) => println!("WASM filters: {}", e),
+    }
+    match std::env::var("IGNORE_CONTENT_TYPES").unwrap_or_else(|_| crate::content::DEFAULT_IGNORED.to_string()) {
+        types if types.trim().is_empty() => println!("Ignored content: none"),
+        types => println!("Ignored content: {}", types),
+    }
+    if let Some(checks) = var("SCAM_CHECKS") {
+        println!("Scam checks: {} ({})", checks, var("SCAM_ACTION").unwrap_or_else(|| "skip".to_string()));
+    }
+    if let Some(chats) = var("OCR_CHAT_IDS") {
+        println!("Screenshots read in: {} ({})", chats, var("OCR_LANGUAGES").unwrap_or_else(|| "rus+eng".to_string()));
+    }
+    if let Some(hours) = var("WORKING_HOURS") {
+        println!("Working hours: {} ({})", hours, var("WORKING_HOURS_TZ").or_else(|| var("TIMEZONE")).unwrap_or_else(|| "local time".to_string()));
+    }
+
+    let data_dir = tdlib_data_dir();
+    let session = Path::new(&data_dir).join("td.binlog").exists();
+    println!("TDLib data: {} ({})", data_dir, if session { "has a session" } else { "no session yet, run `auth`" });
+    match var("TDLIB_PATH") {
+        Some(path) => println!("TDLib: {}", path),
+        None => println!("TDLib: default locations"),
+    }
+
+    // The same checks `run` fails on
+    match validate::all().check() {
+        Ok(()) => {
+            println!("Configuration OK");
+            Ok(())
+        }
+        Err(problems) => {
+            for problem in problems.iter() {
+                eprintln!("✗ {}", problem);
+            }
+            Err(format!("{} configuration problem(s)", problems.iter().count()))
+        }
+    }
+}
+
+// What each chat ID is taken to be by its form and what is set up for it, without asking Telegram;
+// chats the other settings name but ALLOWED_CHAT_IDS doesn't are never seen at all
+fn chats() {
+    let allowed = get_allowed_chat_ids();
+    let mut roles: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+    let mut add = |chat_id: i64, role: String| roles.entry(chat_id).or_default().push(role);
+    for chat_id in &allowed {
+        add(*chat_id, "watched".to_string());
+    }
+    for chat_id in crate::chat_ids("HOT_CHAT_IDS") {
+        add(chat_id, "hot".to_string());
+    }
+    for chat_id in crate::chat_ids("OCR_CHAT_IDS") {
+        add(chat_id, "screenshots read".to_string());
+    }
+    let entries = |name: &str| var(name).unwrap_or_default().split(',').map(str::trim).filter(|entry| !entry.is_empty()).map(str::to_string).collect::<Vec<_>>();
+    for entry in entries("ALLOWED_TOPICS") {
+        if let Some((chat_id, _)) = crate::topics::parse_entry(&entry) {
+            add(chat_id, format!("topic {}", entry.split_once(':').map_or("", |(_, topic)| topic.trim())));
+        }
+    }
+    for (chat_id, _) in entries("DISCUSSION_REACTIONS").iter().filter_map(|entry| crate::discussion::parse_entry(entry)) {
+        add(chat_id, "reacted to in its discussion group".to_string());
+    }
+    for (chat_id, origin) in entries("FORWARD_ORIGINS").iter().filter_map(|entry| crate::origins::parse_entry(entry)) {
+        add(chat_id, format!("forwarded from {}", origin));
+    }
+    for (chat_id, sender) in entries("ORDER_SENDERS").iter().filter_map(|entry| crate::senders::parse_entry(entry)) {
+        add(chat_id, format!("posted by {}", sender));
+    }
+    for entry in entries("CHAT_ACTIONS") {
+        if let Some((chat_id, _)) = crate::actions::parse_entry(&entry) {
+            add(chat_id, format!("on a match: {}", entry.split_once(':').map_or("", |(_, actions)| actions.trim())));
+        }
+    }
+
+    println!("Chats: {}", allowed.len());
+    for (chat_id, roles) in &roles {
+        let kind = match *chat_id {
+            id if id <= -1_000_000_000_000 => "channel or supergroup",
+            id if id < 0 => "basic group",
+            _ => "user or bot",
+        };
+        let warning = if allowed.contains(chat_id) { "" } else { " ✗ not in ALLOWED_CHAT_IDS, never seen" };
+        println!("  {} ({}): {}{}", chat_id, kind, roles.join(", "), warning);
+    }
+}
+
+// Each price pattern in use against its example, so a PRICE_PATTERNS mistake shows before an order
+// is missed
+fn price_patterns() {
+    let selected = crate::rates::price_patterns().names();
+    for (name, example) in botdg_core::patterns::library().filter(|(name, _)| selected.contains(name)) {
+        let found = PricePatterns::parse(name).ok().and_then(|pattern| pattern.find_amount(example));
+        match found {
+            Some(amount) => println!("  {}: \"{}\" → {} {}", name, example, amount.value, amount.currency),
+            None => println!("  {}: \"{}\" ✗ reads no amount", name, example),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// What the bot needs from a Telegram client: TDLib's JSON interface. TdClient (main.rs) is the
+// real one, over the td_json_client FFI; MockClient (mock.rs) plays a script instead, so the whole update
+// pipeline runs without TDLib or an account
+pub trait TelegramClient: Send + Sync {
+    // `buffer` is scratch space kept by the caller between requests
+    fn send(&self, request: &str, buffer: &mut Vec<u8>);
+
+    // Receive the next update into `buffer`, reusing its allocation; false on timeout
+    fn receive_into(&self, timeout: f64, buffer: &mut String) -> bool;
+
+    // Replace the client with a fresh one after its session was closed. Only called from the
+    // sender thread while nothing is receiving (see sender.rs)
+    fn reset(&self);
+
+    // Whether no update will come any more, so the receive loop can end. Never for TDLib
+    fn is_exhausted(&self) -> bool {
+        false
+    }
+
+    fn receive(&self, timeout: f64) -> Option<String> {
+        let mut update = String::new();
+        self.receive_into(timeout, &mut update).then_some(update)
+    }
+}
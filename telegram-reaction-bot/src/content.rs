@@ -0,0 +1,75 @@
+// Kinds of message content the prefilter drops unparsed, from IGNORE_CONTENT_TYPES
+pub const DEFAULT_IGNORED: &str = "sticker,animation,voice,video_note,dice,poll,service";
+
+// IGNORE_CONTENT_TYPES names and the TDLib MessageContent types they stand for; "service" is
+// everything that isn't one of these or text (joins, pins, title changes, topic events...)
+const TYPES: &[(&str, &[&str])] = &[
+    ("photo", &["messagePhoto"]),
+    ("video", &["messageVideo"]),
+    ("animation", &["messageAnimation"]),
+    ("audio", &["messageAudio"]),
+    ("document", &["messageDocument"]),
+    ("sticker", &["messageSticker"]),
+    ("voice", &["messageVoiceNote"]),
+    ("video_note", &["messageVideoNote"]),
+    ("poll", &["messagePoll"]),
+    ("dice", &["messageDice"]),
+    ("location", &["messageLocation", "messageVenue"]),
+    ("contact", &["messageContact"]),
+    ("story", &["messageStory"]),
+];
+// Sent by people, so never "service", though not worth a name of their own
+const OTHER_USER_CONTENT: &[&str] = &["messageText", "messageGame", "messageInvoice", "messagePaidMedia", "messageUnsupported"];
+
+// Messages that can't be orders, skipped before they're parsed and counted as content_type: in a
+// chatty group stickers, voice notes and join notices outnumber the orders, and each would otherwise
+// be parsed only to be dropped. IGNORE_CONTENT_TYPES is a comma separated list of the names in TYPES
+// and "service"; empty ignores nothing
+#[derive(Clone, Default)]
+pub struct IgnoredContent {
+    types: Vec<&'static str>,
+    service: bool,
+}
+
+impl IgnoredContent {
+    // Checked by validate.rs
+    pub fn from_env() -> Self {
+        let value = std::env::var("IGNORE_CONTENT_TYPES").unwrap_or_else(|_| DEFAULT_IGNORED.to_string());
+        Self::parse(&value).unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut ignored = Self::default();
+        for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match TYPES.iter().find(|(known, _)| name.eq_ignore_ascii_case(known)) {
+                Some((_, types)) => ignored.types.extend_from_slice(types),
+                None if name.eq_ignore_ascii_case("service") => ignored.service = true,
+                None => {
+                    let names: Vec<&str> = TYPES.iter().map(|(name, _)| *name).chain(["service"]).collect();
+                    return Err(format!("unknown content type '{}' (one of {})", name, names.join(", ")));
+                }
+            }
+        }
+        Ok(ignored)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && !self.service
+    }
+
+    // Whether a message with this MessageContent type is skipped
+    pub fn ignores(&self, content_type: &str) -> bool {
+        self.types.contains(&content_type)
+            || (self.service
+                && !OTHER_USER_CONTENT.contains(&content_type)
+                && !TYPES.iter().any(|(_, types)| types.contains(&content_type)))
+    }
+}
+
+// The MessageContent type of a raw updateNewMessage. The message's own "content" comes last, after
+// any quoted in reply_to, and a string value can't hold an unescaped quote, so the last match is it
+pub fn content_type(raw: &str) -> Option<&str> {
+    const KEY: &str = "\"content\":{\"@type\":\"";
+    let start = raw.rfind(KEY)? + KEY.len();
+    raw[start..].split_once('"').map(|(content_type, _)| content_type)
+}
@@ -0,0 +1,172 @@
+use std::{
+    io::{BufRead, IsTerminal, Write},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, Stdin},
+    sync::{mpsc, Mutex},
+};
+use tracing::{info, warn};
+use serde_json::{json, Value};
+
+// Control channel to the manager bot: newline-delimited JSON events on stdout
+// and commands on stdin. Enabled with CONTROL_CHANNEL=stdio (set by the manager);
+// otherwise the bot talks to the terminal as before.
+pub struct ControlChannel {
+    commands: Option<Mutex<mpsc::UnboundedReceiver<Value>>>,
+    // The terminal prompts read from without a control channel; one reader for the whole run, so
+    // nothing typed ahead is lost with a reader dropped between prompts
+    terminal: Mutex<BufReader<Stdin>>,
+}
+
+impl ControlChannel {
+    pub fn from_env() -> Self {
+        if std::env::var("CONTROL_CHANNEL").as_deref() != Ok("stdio") {
+            return Self { commands: None, terminal: Mutex::new(BufReader::new(tokio::io::stdin())) };
+        }
+
+        info!("Control channel enabled on stdin/stdout");
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        // Read commands on a dedicated thread so the receive loop never blocks on stdin
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(command) => {
+                        if tx.send(command).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Ignoring malformed control command: {}", e),
+                }
+            }
+            info!("Control channel closed");
+        });
+
+        Self { commands: Some(Mutex::new(rx)), terminal: Mutex::new(BufReader::new(tokio::io::stdin())) }
+    }
+
+    // Send an event to the manager (no-op without a control channel)
+    pub fn emit(&self, event: Value) {
+        if self.commands.is_none() {
+            return;
+        }
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", event);
+        let _ = stdout.flush();
+    }
+
+    // Next pending command from the manager, if any (never blocks; None while a prompt is waiting
+    // for its answer)
+    pub fn try_command(&self) -> Option<Value> {
+        self.commands.as_ref()?.try_lock().ok()?.try_recv().ok()
+    }
+
+    // Ask for an authorization value: from the configuration when it provides one (see configured),
+    // otherwise relayed by the manager or typed in the terminal. Nothing blocks while waiting, so the
+    // caller can keep the heartbeat going and drop the prompt; AUTH_PROMPT_TIMEOUT_SECS (default 900,
+    // 0 waits for good) gives up on an answer that doesn't come
+    pub async fn prompt(&self, kind: &str, text: &str) -> std::io::Result<String> {
+        let answer = self.answer(kind, text);
+        let Some(limit) = prompt_timeout() else { return answer.await };
+        tokio::time::timeout(limit, answer).await.map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::TimedOut, format!("no {} within {} s", kind, limit.as_secs()))
+        })?
+    }
+
+    async fn answer(&self, kind: &str, text: &str) -> std::io::Result<String> {
+        if let Some(value) = configured(kind).await {
+            return value;
+        }
+
+        let Some(commands) = &self.commands else {
+            // Blocking on a stdin nobody can type into would hang a container forever
+            if !std::io::stdin().is_terminal() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "no terminal to ask for the {}; set TELEGRAM_PHONE, TELEGRAM_CODE_FILE and TELEGRAM_2FA_PASSWORD_FILE or run under the manager bot",
+                        kind
+                    ),
+                ));
+            }
+            println!("\n{}", text);
+            let mut input = String::new();
+            self.terminal.lock().await.read_line(&mut input).await?;
+            return Ok(input.trim().to_string());
+        };
+
+        info!("Waiting for {} from the manager bot", kind);
+        self.emit(json!({ "event": "auth_prompt", "kind": kind, "text": text }));
+
+        let mut commands = commands.lock().await;
+        loop {
+            let Some(command) = commands.recv().await else {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "control channel closed"));
+            };
+            if command["command"] == "auth_reply" {
+                return Ok(command["value"].as_str().unwrap_or_default().trim().to_string());
+            }
+            warn!("Ignoring control command during authorization: {}", command);
+        }
+    }
+}
+
+const DEFAULT_PROMPT_TIMEOUT: Duration = Duration::from_secs(900);
+
+fn prompt_timeout() -> Option<Duration> {
+    match std::env::var("AUTH_PROMPT_TIMEOUT_SECS").ok().and_then(|s| s.trim().parse::<u64>().ok()) {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(DEFAULT_PROMPT_TIMEOUT),
+    }
+}
+
+// Authorization values that don't need anyone at a terminal: the phone number from TELEGRAM_PHONE,
+// the login code from the file at TELEGRAM_CODE_FILE, which is waited for (e.g. written with
+// `docker exec ... sh -c 'echo 12345 > /app/tdlib_data/code'`), and the 2FA password from
+// TELEGRAM_2FA_PASSWORD, which botdg-config reads from TELEGRAM_2FA_PASSWORD_FILE when that's set
+// (or the OS keyring, see secrets.rs)
+async fn configured(kind: &str) -> Option<std::io::Result<String>> {
+    let var = |name: &str| std::env::var(name).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+    match kind {
+        "phone_number" => var("TELEGRAM_PHONE").map(|phone| {
+            info!("Using the phone number from TELEGRAM_PHONE");
+            Ok(phone)
+        }),
+        "code" => match var("TELEGRAM_CODE_FILE") {
+            Some(path) => Some(wait_for_code(&path).await),
+            None => None,
+        },
+        "password" => crate::secrets::get("TELEGRAM_2FA_PASSWORD").map(|password| {
+            info!("Using the 2FA password from TELEGRAM_2FA_PASSWORD");
+            Ok(password)
+        }),
+        _ => None,
+    }
+}
+
+const CODE_FILE_POLL: Duration = Duration::from_secs(1);
+
+async fn wait_for_code(path: &str) -> std::io::Result<String> {
+    // A file left from an earlier attempt holds a code that's no longer valid
+    let _ = tokio::fs::remove_file(path).await;
+    info!("Waiting for the login code in {}", path);
+
+    loop {
+        tokio::time::sleep(CODE_FILE_POLL).await;
+        let Ok(contents) = tokio::fs::read_to_string(path).await else { continue };
+        let code = contents.chars().filter(|c| c.is_ascii_digit()).collect::<String>();
+        if code.is_empty() {
+            continue;
+        }
+        let _ = tokio::fs::remove_file(path).await;
+        info!("Read the login code from {}", path);
+        return Ok(code);
+    }
+}
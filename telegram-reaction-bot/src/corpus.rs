@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use botdg_core::{parse_order_formatted, Order, PricePatterns};
+use serde_json::Value;
+
+use crate::{formatted::MessageText, rates::price_patterns};
+
+// Where `validate-corpus` looks by default: the samples bundled with the bot, next to Cargo.toml
+pub const DEFAULT_CORPUS_DIR: &str = "corpus";
+
+// The fields checked, as named in a sample and in the report
+const FIELDS: [&str; 3] = ["amount", "bank", "requisite"];
+
+// One message of the corpus. From a .jsonl file: {"text": ..., "amount": 50000, "currency": "RUB",
+// "bank": "Т-Банк", "requisite": ...}, where every field but the text is optional and null expects
+// nothing to be found; "patterns" reads the amount with other PRICE_PATTERNS than the configured
+// ones, and "entities" are its formatting as TDLib sends it. A .txt file is one message with nothing
+// expected, only counted
+pub struct Sample {
+    pub source: String,   // file:line
+    pub text: String,
+    patterns: Option<PricePatterns>,
+    expected: Value,
+}
+
+impl Sample {
+    // A message known not to be an order (everything expected null), left out of the coverage
+    pub fn is_chatter(&self) -> bool {
+        FIELDS.iter().all(|field| self.expected.get(field) == Some(&Value::Null))
+    }
+
+    // What the parser reads in it, and what it got wrong against the expectations
+    pub fn check(&self, configured: &PricePatterns) -> (Option<Order>, Vec<String>) {
+        let entities = MessageText::from_tdlib(&self.expected).map(|text| text.entities).unwrap_or_default();
+        let order = parse_order_formatted(&self.text, &entities, self.patterns.as_ref().unwrap_or(configured));
+        let found = |field: &str| -> Value {
+            let Some(order) = &order else { return Value::Null };
+            match field {
+                "amount" => order.amount.as_ref().map(|amount| amount.value).into(),
+                "currency" => order.amount.as_ref().map(|amount| amount.currency.clone()).into(),
+                "bank" => order.bank.clone().into(),
+                _ => order.requisite.clone().into(),
+            }
+        };
+        let wrong = FIELDS
+            .iter()
+            .chain(&["currency"])
+            .filter_map(|field| {
+                let expected = self.expected.get(field)?;
+                let found = found(field);
+                let same = match (expected.as_f64(), found.as_f64()) {
+                    (Some(expected), Some(found)) => (expected - found).abs() < 0.005,
+                    _ => *expected == found,
+                };
+                (!same).then(|| format!("{}: expected {}, found {}", field, expected, found))
+            })
+            .collect();
+        (order, wrong)
+    }
+}
+
+// Every sample under `dir`, subdirectories included, in file name order
+pub fn load(dir: &Path) -> Result<Vec<Sample>, String> {
+    let mut files = Vec::new();
+    collect(dir, &mut files).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    files.sort();
+    let mut samples = Vec::new();
+    for file in files {
+        let contents = fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        match file.extension().and_then(|extension| extension.to_str()) {
+            Some("txt") if !contents.trim().is_empty() => samples.push(Sample {
+                source: file.display().to_string(),
+                text: contents.trim_end().to_string(),
+                patterns: None,
+                expected: Value::Null,
+            }),
+            Some("jsonl") => {
+                for (n, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                    let source = format!("{}:{}", file.display(), n + 1);
+                    let expected: Value = serde_json::from_str(line).map_err(|e| format!("{}: {}", source, e))?;
+                    let text = expected["text"].as_str().ok_or_else(|| format!("{}: no \"text\"", source))?.to_string();
+                    let patterns = expected["patterns"]
+                        .as_str()
+                        .map(|names| PricePatterns::parse(names).map_err(|e| format!("{}: {}", source, e)))
+                        .transpose()?;
+                    samples.push(Sample { source, text, patterns, expected });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(samples)
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// `validate-corpus [DIR] [--missing]`: parse every sample message and report how many had their
+// amount, bank and requisite found, and every expectation the parser didn't meet. Exits with 1 on
+// any, so a parser change that breaks a known format fails. Users add their own messages to the
+// directory: a .txt file per message, or .jsonl lines with what should be found
+pub fn run(dir: &str, show_missing: bool) -> Result<bool, String> {
+    let samples = load(Path::new(dir))?;
+    if samples.is_empty() {
+        return Err(format!("No samples (.txt or .jsonl) in {}", dir));
+    }
+    let configured = price_patterns();
+    let mut found = [0; FIELDS.len()];
+    let mut orders = 0;
+    let mut failed = 0;
+    for sample in &samples {
+        let (order, wrong) = sample.check(&configured);
+        if !wrong.is_empty() {
+            failed += 1;
+            println!("✗ {}: {}", sample.source, wrong.join("; "));
+        }
+        if sample.is_chatter() {
+            continue;
+        }
+        orders += 1;
+        let present = [
+            order.as_ref().is_some_and(|order| order.amount.is_some()),
+            order.as_ref().is_some_and(|order| order.bank.is_some()),
+            order.as_ref().is_some_and(|order| order.requisite.is_some()),
+        ];
+        for (count, present) in found.iter_mut().zip(present) {
+            *count += present as usize;
+        }
+        if show_missing && wrong.is_empty() && present.contains(&false) {
+            let missing = FIELDS.iter().zip(present).filter(|(_, present)| !present).map(|(field, _)| *field).collect::<Vec<_>>();
+            println!("- {}: no {} in {:?}", sample.source, missing.join(", "), sample.text);
+        }
+    }
+
+    println!("{} samples in {}, {} of them orders (price patterns {:?})", samples.len(), dir, orders, configured);
+    for (field, count) in FIELDS.iter().zip(found) {
+        println!("  {:<10} {:>5} ({:.0}%)", field, count, count as f64 * 100.0 / orders.max(1) as f64);
+    }
+    if failed > 0 {
+        println!("{} sample(s) not parsed as expected", failed);
+    }
+    Ok(failed == 0)
+}
@@ -0,0 +1,172 @@
+use std::{fs::File, io::Write};
+use tracing::info;
+
+use crate::error::{io_error, BotError};
+
+pub const DEFAULT_PID_FILE: &str = "tdlib-test.pid";
+// Locked in every TDLib data directory in use (see lock_session)
+const SESSION_LOCK: &str = "bot.pid";
+
+// Options of `run`: `--daemon [--pidfile PATH] [--log-file PATH]`
+#[derive(clap::Args, Default)]
+pub struct DaemonOptions {
+    /// Detach from the terminal and keep running in the background
+    #[arg(long)]
+    pub daemon: bool,
+    /// Lock this pidfile while running (PID_FILE or tdlib-test.pid with --daemon)
+    #[arg(long, value_name = "PATH")]
+    pub pidfile: Option<String>,
+    /// Write logs to this file instead of stderr (same as LOG_FILE)
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<String>,
+}
+
+impl DaemonOptions {
+    pub fn is_empty(&self) -> bool {
+        !self.daemon && self.pidfile.is_none() && self.log_file.is_none()
+    }
+
+    // A daemon always gets a pidfile so it can be found again
+    pub fn pidfile(&self) -> Option<String> {
+        self.pidfile.clone().or_else(|| {
+            self.daemon.then(|| std::env::var("PID_FILE").unwrap_or_else(|_| DEFAULT_PID_FILE.to_string()))
+        })
+    }
+}
+
+// An exclusively locked pidfile; the lock is held for as long as this value lives
+pub struct Pidfile {
+    file: File,
+    path: String,
+}
+
+impl Pidfile {
+    // Lock the pidfile, failing if another running instance already holds it
+    #[cfg(unix)]
+    pub fn acquire(path: &str) -> Result<Self, String> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| format!("Failed to open pidfile {}: {}", path, e))?;
+
+        // SAFETY: flock only operates on the descriptor we own
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let holder = std::fs::read_to_string(path).unwrap_or_default();
+            return Err(format!("Another instance is already running (pidfile {}, PID {})", path, holder.trim()));
+        }
+
+        Ok(Self { file, path: path.to_string() })
+    }
+
+    // Windows has no flock; a file opened without sharing reads or writes can't be opened again
+    // until it's closed, which holds the same way. Deleting is shared so Drop can remove it
+    #[cfg(windows)]
+    pub fn acquire(path: &str) -> Result<Self, String> {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        const FILE_SHARE_DELETE: u32 = 4;
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .share_mode(FILE_SHARE_DELETE)
+            .open(path);
+        match file {
+            Ok(file) => Ok(Self { file, path: path.to_string() }),
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => {
+                Err(format!("Another instance is already running (pidfile {})", path))
+            }
+            Err(e) => Err(format!("Failed to open pidfile {}: {}", path, e)),
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub fn acquire(path: &str) -> Result<Self, String> {
+        let _ = path;
+        Err("Pidfiles are only supported on Unix and Windows".to_string())
+    }
+
+    // Record the current process ID (call after detaching)
+    pub fn write_pid(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        let pid = std::process::id();
+        write!(&self.file, "{}", pid)?;
+        self.file.flush()?;
+        info!("Wrote PID {} to {}", pid, self.path);
+        Ok(())
+    }
+}
+
+// Lock a TDLib data directory for as long as the value lives. TDLib's database is corrupted by two
+// processes opening it at once, so a second bot, `auth` or `logout` on the same session stops here
+pub fn lock_session(data_dir: &str) -> Result<Pidfile, BotError> {
+    std::fs::create_dir_all(data_dir).map_err(|e| io_error(e, format!("Failed to create {}", data_dir)))?;
+    let path = std::path::Path::new(data_dir).join(SESSION_LOCK);
+    let mut lock = Pidfile::acquire(&path.to_string_lossy()).map_err(|e| {
+        BotError::Config(match std::fs::read_to_string(&path).ok().filter(|pid| !pid.trim().is_empty()) {
+            Some(pid) => format!("The session in {} is in use by another bot (PID {}); stop it first", data_dir, pid.trim()),
+            None => format!("The session in {} is in use: {}", data_dir, e),
+        })
+    })?;
+    lock.write_pid().map_err(|e| io_error(e, format!("Failed to write {}", path.display())))?;
+    Ok(lock)
+}
+
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Detach from the terminal: fork (the parent exits), start a new session and point
+// stdio at /dev/null or the log file. Must run before the tokio runtime starts.
+#[cfg(unix)]
+pub fn detach(log_file: Option<&str>) -> Result<(), String> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: no other threads exist yet, so forking is sound
+    match unsafe { libc::fork() } {
+        -1 => return Err(format!("fork failed: {}", std::io::Error::last_os_error())),
+        0 => {}
+        _ => std::process::exit(0),
+    }
+
+    // SAFETY: plain system calls on descriptors we own
+    unsafe {
+        if libc::setsid() == -1 {
+            return Err(format!("setsid failed: {}", std::io::Error::last_os_error()));
+        }
+
+        let null = File::options()
+            .read(true)
+            .write(true)
+            .open("/dev/null")
+            .map_err(|e| format!("Failed to open /dev/null: {}", e))?;
+        let output = match log_file {
+            Some(path) => File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Failed to open log file {}: {}", path, e))?,
+            None => null.try_clone().map_err(|e| e.to_string())?,
+        };
+
+        libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+        libc::dup2(output.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(output.as_raw_fd(), libc::STDERR_FILENO);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn detach(_log_file: Option<&str>) -> Result<(), String> {
+    Err("--daemon is only supported on Unix".to_string())
+}
@@ -0,0 +1,94 @@
+use std::{path::{Path, PathBuf}, sync::Mutex};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tracing::{info, warn};
+
+use crate::error::BotError;
+
+// Kept next to the session in its TDLib data directory, so snapshots and accounts take it along
+const KEY_FILE: &str = "db.key";
+const KEY_BYTES: usize = 32;
+
+// TDLib's database_encryption_key for a data directory, base64 as setTdlibParameters takes it.
+// DATABASE_ENCRYPTION=auto (the default) encrypts every new session with a random key, made on the
+// first run and kept in <TDLIB_DATA_DIR>/db.key (readable by the bot's user only) or, with
+// DATABASE_KEY_STORE=keyring, in the OS keyring (see secrets.rs). A session made before there was a
+// key stays unencrypted until it's logged out; DATABASE_ENCRYPTION=off keeps new ones unencrypted too.
+// Empty (no encryption) when the key can't be stored, as a session nobody can open again is worse.
+// A key that's there but can't be read is an error: going on without it would take an encrypted
+// session for an unencrypted one, or a new key would replace it
+pub fn get(data_dir: &str) -> Result<String, BotError> {
+    if std::env::var("DATABASE_ENCRYPTION").is_ok_and(|mode| mode.trim().eq_ignore_ascii_case("off")) {
+        return Ok(String::new());
+    }
+    let keyring = std::env::var("DATABASE_KEY_STORE").is_ok_and(|store| store.trim().eq_ignore_ascii_case("keyring"));
+    // The receive loop, the standby and race accounts may all configure a client at once
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let stored = if keyring { crate::secrets::read(&keyring_name(data_dir)) } else { read(data_dir) };
+    let stored = stored.map_err(|e| BotError::Config(format!("Can't read the database key of the session in {}: {}", data_dir, e)))?;
+    if let Some(key) = stored {
+        return Ok(key);
+    }
+    if Path::new(data_dir).join("td.binlog").exists() {
+        info!("The session in {} isn't encrypted; log it out and in again to encrypt it", data_dir);
+        return Ok(String::new());
+    }
+
+    let mut bytes = [0; KEY_BYTES];
+    if let Err(e) = getrandom::fill(&mut bytes) {
+        warn!("Failed to make a database key, the session in {} won't be encrypted: {}", data_dir, e);
+        return Ok(String::new());
+    }
+    let key = STANDARD.encode(bytes);
+    let stored = if keyring { crate::secrets::store(&keyring_name(data_dir), Some(&key)) } else { write(data_dir, &key) };
+    Ok(match stored {
+        Ok(()) => {
+            info!("Encrypting the new session in {} with a key kept in {}", data_dir, if keyring { "the OS keyring" } else { KEY_FILE });
+            key
+        }
+        Err(e) => {
+            warn!("Failed to store the database key, the session in {} won't be encrypted: {}", data_dir, e);
+            String::new()
+        }
+    })
+}
+
+fn key_file(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join(KEY_FILE)
+}
+
+// One entry per data directory, by its full path
+fn keyring_name(data_dir: &str) -> String {
+    let dir = std::path::absolute(data_dir).unwrap_or_else(|_| PathBuf::from(data_dir));
+    format!("TDLIB_DATABASE_KEY:{}", dir.display())
+}
+
+// None when there's no key file yet
+fn read(data_dir: &str) -> Result<Option<String>, String> {
+    let path = key_file(data_dir);
+    match std::fs::read_to_string(&path) {
+        Ok(key) => Ok(Some(key.trim().to_string()).filter(|key| !key.is_empty())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+// Written whole to a temporary file first, so the bot never reads half a key
+fn write(data_dir: &str, key: &str) -> Result<(), String> {
+    let path = key_file(data_dir);
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    // Made before TDLib makes the directory itself
+    let written = std::fs::create_dir_all(data_dir)
+        .and_then(|()| options.open(&tmp))
+        .and_then(|mut file| std::io::Write::write_all(&mut file, key.as_bytes()))
+        .and_then(|()| std::fs::rename(&tmp, &path));
+    written.map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("{}: {}", path.display(), e)
+    })
+}
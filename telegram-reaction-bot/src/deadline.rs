@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+use botdg_core::filter::SkipReason;
+use tracing::{info, warn};
+
+// PROCESSING_DEADLINE_MS: how long a message may take from arriving to its reaction being sent,
+// queueing, parsing, filters and the pre-reaction hook included; an order that late is likely taken
+// already. What happens to one that runs over is DEADLINE_ACTION: `skip` (the default) skips it as
+// `deadline`, `react` reacts anyway, leaving out the checks still ahead (the pre-reaction hook).
+// Off when unset
+pub struct Deadline {
+    limit: Option<Duration>,
+    react_anyway: bool,
+}
+
+impl Deadline {
+    pub fn from_env() -> Self {
+        let limit = std::env::var("PROCESSING_DEADLINE_MS").ok().and_then(|s| s.parse().ok()).filter(|ms| *ms > 0).map(Duration::from_millis);
+        let react_anyway = std::env::var("DEADLINE_ACTION").is_ok_and(|v| v.eq_ignore_ascii_case("react"));
+        if let Some(limit) = limit {
+            info!("Processing deadline: {} ms, then {}", limit.as_millis(), if react_anyway { "react" } else { "skip" });
+        }
+        Self { limit, react_anyway }
+    }
+
+    // Whether a message that arrived then has run out of time
+    pub fn exceeded(&self, received: Instant) -> bool {
+        self.limit.is_some_and(|limit| received.elapsed() > limit)
+    }
+
+    // Run a check that may take a while if the message still has time for it, then see whether it
+    // still has. With DEADLINE_ACTION=react a check there's no time left for is left out
+    pub fn guard(&self, received: Instant, check: impl FnOnce() -> Result<(), SkipReason>) -> Result<(), SkipReason> {
+        if !self.exceeded(received) {
+            check()?;
+        }
+        self.check(received)
+    }
+
+    // Err(Deadline) for a message out of time, unless DEADLINE_ACTION=react
+    fn check(&self, received: Instant) -> Result<(), SkipReason> {
+        if !self.exceeded(received) {
+            return Ok(());
+        }
+        let elapsed = received.elapsed().as_millis();
+        if self.react_anyway {
+            warn!("Deadline exceeded ({} ms), reacting anyway", elapsed);
+            Ok(())
+        } else {
+            warn!("Deadline exceeded ({} ms), skipping", elapsed);
+            Err(SkipReason::Deadline)
+        }
+    }
+}
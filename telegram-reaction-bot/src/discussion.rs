@@ -0,0 +1,95 @@
+use std::collections::{HashMap, HashSet};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::sender::TdSender;
+
+// "@extra" of getMessageThread requests; the rest is the channel post's "chat:message"
+pub const EXTRA_PREFIX: &str = "discussion:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // React in the discussion group only; the channel doesn't take reactions
+    Always,
+    // React to the post, and in the discussion group if TDLib rejects that
+    Fallback,
+}
+
+// Channels whose orders are reacted to in their linked discussion group, where the post shows up
+// as the first message of its comment thread (getMessageThread finds it). DISCUSSION_REACTIONS
+// lists them, comma separated: "-100123" always reacts there, "-100123:fallback" only once a
+// reaction to the post itself failed
+#[derive(Default)]
+pub struct Discussions {
+    channels: HashMap<i64, Mode>,
+    // Posts whose discussion message is being looked up or reacted to, so a failure there doesn't
+    // redirect again
+    redirected: HashSet<(i64, i64)>,
+}
+
+impl Discussions {
+    pub fn from_env() -> Self {
+        let mut channels = HashMap::new();
+        for entry in std::env::var("DISCUSSION_REACTIONS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_entry(entry) {
+                Some((chat_id, mode)) => {
+                    info!("Reacting to posts of channel {} in its discussion group ({:?})", chat_id, mode);
+                    channels.insert(chat_id, mode);
+                }
+                None => warn!("Ignoring invalid DISCUSSION_REACTIONS entry '{}'", entry),
+            }
+        }
+        Self { channels, redirected: HashSet::new() }
+    }
+
+    // Whether reactions to this chat's posts go to the discussion group right away
+    pub fn always(&self, chat_id: i64) -> bool {
+        self.channels.get(&chat_id) == Some(&Mode::Always)
+    }
+
+    // Whether a failed reaction to this chat's post is retried in the discussion group
+    pub fn falls_back(&self, chat_id: i64) -> bool {
+        self.channels.get(&chat_id) == Some(&Mode::Fallback)
+    }
+
+    // Look up the post's discussion message; false if that was already tried for this post
+    pub fn redirect(&mut self, sender: &TdSender, chat_id: i64, message_id: i64) -> bool {
+        if !self.redirected.insert((chat_id, message_id)) {
+            return false;
+        }
+        sender.send(json!({
+            "@type": "getMessageThread",
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "@extra": format!("{}{}:{}", EXTRA_PREFIX, chat_id, message_id)
+        }).to_string());
+        true
+    }
+
+    // The reaction to a post is settled, one way or the other
+    pub fn finish(&mut self, chat_id: i64, message_id: i64) {
+        self.redirected.remove(&(chat_id, message_id));
+    }
+}
+
+// The discussion group message from a messageThreadInfo, as (chat, message)
+pub fn discussion_message(thread_info: &Value) -> Option<(i64, i64)> {
+    if thread_info["@type"] != "messageThreadInfo" {
+        return None;
+    }
+    let chat_id = thread_info["chat_id"].as_i64()?;
+    // Newest first; the thread starts with the forwarded post
+    let message_id = thread_info["messages"].as_array()?.last()?["id"].as_i64()?;
+    Some((chat_id, message_id))
+}
+
+// "chat" or "chat:fallback"
+pub fn parse_entry(entry: &str) -> Option<(i64, Mode)> {
+    let (chat_id, mode) = entry.split_once(':').unwrap_or((entry, "always"));
+    let mode = match mode.trim() {
+        "always" => Mode::Always,
+        "fallback" => Mode::Fallback,
+        _ => return None,
+    };
+    Some((chat_id.trim().parse().ok()?, mode))
+}
@@ -0,0 +1,162 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::sender::TdSender;
+
+pub const EXTRA: &str = "disk:optimize";
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+const MB: u64 = 1024 * 1024;
+
+// What the TDLib directories take, measured off the receive loop
+#[derive(Clone, Copy)]
+struct Usage {
+    data: u64,
+    files: u64,
+    free: Option<u64>,   // On the file system holding them
+    at: Instant,
+}
+
+// The TDLib database and files directories only grow, and on a small VPS they end up filling the
+// disk. Their size is measured every DISK_CHECK_INTERVAL_SECS (300) and published in the heartbeat
+// for the manager's /status. Past DISK_USAGE_LIMIT_MB the files are trimmed with TDLib's
+// optimizeStorage and the manager is alerted, as it is when the disk has less than DISK_MIN_FREE_MB
+// left; both off when unset
+pub struct DiskWatch {
+    usage: Arc<Mutex<Option<Usage>>>,
+    limit: Option<u64>,
+    min_free: Option<u64>,
+    checked: Option<Instant>,   // The measurement check() last looked at
+    alerting: bool,
+}
+
+impl DiskWatch {
+    pub fn from_env(data_dir: &str, files_dir: &str) -> Self {
+        let megabytes = |name: &str| std::env::var(name).ok().and_then(|s| s.parse::<u64>().ok()).filter(|mb| *mb > 0).map(|mb| mb * MB);
+        let interval = std::env::var("DISK_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        let usage = Arc::new(Mutex::new(None));
+
+        let (measured, data_dir, files_dir) = (usage.clone(), data_dir.to_string(), files_dir.to_string());
+        std::thread::Builder::new()
+            .name("disk".to_string())
+            .spawn(move || loop {
+                let usage = Usage { data: dir_size(Path::new(&data_dir)), files: dir_size(Path::new(&files_dir)), free: free_space(&data_dir), at: Instant::now() };
+                *measured.lock().unwrap() = Some(usage);
+                std::thread::sleep(Duration::from_secs(interval));
+            })
+            .expect("Failed to start the disk usage thread");
+
+        Self { usage, limit: megabytes("DISK_USAGE_LIMIT_MB"), min_free: megabytes("DISK_MIN_FREE_MB"), checked: None, alerting: false }
+    }
+
+    // Look at a new measurement: trim the files when over the limit, and a "disk" event for the
+    // manager when the alert starts or ends
+    pub fn check(&mut self, sender: &TdSender) -> Option<Value> {
+        let usage = (*self.usage.lock().unwrap())?;
+        if self.checked == Some(usage.at) {
+            return None;
+        }
+        self.checked = Some(usage.at);
+
+        let total = usage.data + usage.files;
+        let over_limit = self.limit.is_some_and(|limit| total > limit);
+        let low_free = matches!((self.min_free, usage.free), (Some(min), Some(free)) if free < min);
+        if let Some(limit) = self.limit.filter(|_| over_limit) {
+            // The database can't be trimmed, so the files get whatever the limit leaves
+            let size = limit.saturating_sub(usage.data);
+            info!("TDLib directories take {} MB, over {} MB: trimming files to {} MB", total / MB, limit / MB, size / MB);
+            sender.send(
+                json!({
+                    "@type": "optimizeStorage",
+                    "size": size,
+                    "ttl": -1,
+                    "count": -1,
+                    "immunity_delay": 0,
+                    "file_types": [],
+                    "chat_ids": [],
+                    "exclude_chat_ids": [],
+                    "return_deleted_file_statistics": false,
+                    "chat_limit": -1,
+                    "@extra": EXTRA,
+                })
+                .to_string(),
+            );
+        }
+
+        let alerting = over_limit || low_free;
+        if alerting == self.alerting {
+            return None;
+        }
+        self.alerting = alerting;
+        if alerting {
+            warn!("Disk usage alert: TDLib directories take {} MB, {} MB free", total / MB, usage.free.map_or(-1, |free| (free / MB) as i64));
+        }
+        Some(json!({
+            "event": "disk",
+            "alerting": alerting,
+            "used_bytes": total,
+            "free_bytes": usage.free,
+            "limit_bytes": self.limit,
+            "min_free_bytes": self.min_free,
+        }))
+    }
+
+    // The optimizeStorage result
+    pub fn optimized(&self, json: &Value) {
+        match json["@type"].as_str() {
+            Some("error") => warn!("Failed to trim TDLib files: {}", json["message"]),
+            _ => info!("Trimmed TDLib files, {} MB left", json["size"].as_u64().unwrap_or_default() / MB),
+        }
+    }
+
+    // Gauge for the heartbeat file; null until first measured
+    pub fn gauge(&self) -> Value {
+        match *self.usage.lock().unwrap() {
+            Some(usage) => json!({
+                "data_bytes": usage.data,
+                "files_bytes": usage.files,
+                "free_bytes": usage.free,
+                "limit_bytes": self.limit,
+                "alerting": self.alerting,
+            }),
+            None => Value::Null,
+        }
+    }
+}
+
+// Bytes taken by the files under a directory, 0 if it's missing
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else { return 0 };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map_or(0, |metadata| metadata.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
+// Space left for unprivileged users on the file system holding a path
+#[cfg(unix)]
+fn free_space(path: &str) -> Option<u64> {
+    let path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &str) -> Option<u64> {
+    None
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::auth::AuthError;
+
+// Exit statuses telling the manager whether starting the bot again can help (sysexits.h):
+// a temporary failure is restarted as any crash is, a configuration error isn't
+pub const EXIT_TEMPORARY: i32 = 75;
+pub const EXIT_CONFIG: i32 = 78;
+
+// Why the bot stopped, or a request failed
+#[derive(Debug, Error)]
+pub enum BotError {
+    // libtdjson couldn't be loaded or lacks the td_json_client functions
+    #[error("TDLib: {0}")]
+    Ffi(String),
+    // An "error" object from TDLib (or the Bot API, in the same shape)
+    #[error("TDLib error {code}: {message}")]
+    Api { code: i64, message: String },
+    #[error("Invalid JSON from TDLib: {0}")]
+    Parse(#[from] serde_json::Error),
+    // Settings, plugins and WASM filters, found wrong before any update is handled
+    #[error("{0}")]
+    Config(String),
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+    // The sessions are gone (logged out, terminated) and there's nothing left to react with
+    #[error("{0}")]
+    Session(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl BotError {
+    // A TDLib error object, as it arrives
+    pub fn from_tdlib(json: &Value) -> Self {
+        Self::Api {
+            code: json["code"].as_i64().unwrap_or_default(),
+            message: json["message"].as_str().unwrap_or("unknown error").to_string(),
+        }
+    }
+
+    // Whether trying again later may succeed: flood waits, Telegram's internal errors and lost
+    // connections, but not bad requests, rights or settings
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Api { code, message } => *code == 429 || *code >= 500 || message.contains("Connection") || message.starts_with("Timeout"),
+            Self::Session(_) | Self::Io(_) => true,
+            Self::Auth(AuthError::Input(_) | AuthError::Closed) => true,
+            Self::Ffi(_) | Self::Parse(_) | Self::Config(_) | Self::Auth(_) => false,
+        }
+    }
+
+    // How long Telegram asked to wait before trying again ("Too Many Requests: retry after 17")
+    pub fn retry_after(&self) -> Option<Duration> {
+        let Self::Api { code: 429, message } = self else { return None };
+        message.rsplit(' ').next()?.parse().ok().map(Duration::from_secs)
+    }
+
+    // The exit status for a fatal error (see EXIT_CONFIG)
+    pub fn exit_code(&self) -> i32 {
+        if self.is_retryable() { EXIT_TEMPORARY } else { EXIT_CONFIG }
+    }
+}
+
+// An I/O error with what was being done, still an Io (retryable) error
+pub fn io_error(error: std::io::Error, context: String) -> BotError {
+    BotError::Io(std::io::Error::new(error.kind(), format!("{}: {}", context, error)))
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::metrics;
+
+const DEFAULT_WINDOW_SECS: u64 = 300;
+const DEFAULT_THRESHOLD: f64 = 0.5;
+const DEFAULT_MIN_SAMPLES: usize = 5;
+
+// Share of TDLib requests that failed over a sliding window. Reactions count once, as failed
+// only when every request sent for them was rejected (see reaction.rs); stray TDLib errors count as failures too.
+// A sustained high rate usually means the reaction payload no longer matches the installed TDLib
+pub struct ErrorRate {
+    samples: VecDeque<(Instant, bool)>,   // (when, failed)
+    window: Duration,
+    threshold: f64,
+    min_samples: usize,
+    alerting: bool,
+}
+
+impl ErrorRate {
+    // ERROR_RATE_WINDOW_SECS, ERROR_RATE_THRESHOLD (0-1) and ERROR_RATE_MIN_SAMPLES tune the alert
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            samples: VecDeque::new(),
+            window: Duration::from_secs(var("ERROR_RATE_WINDOW_SECS", DEFAULT_WINDOW_SECS)),
+            threshold: var("ERROR_RATE_THRESHOLD", DEFAULT_THRESHOLD),
+            min_samples: var("ERROR_RATE_MIN_SAMPLES", DEFAULT_MIN_SAMPLES),
+            alerting: false,
+        }
+    }
+
+    pub fn record(&mut self, failed: bool) {
+        self.samples.push_back((Instant::now(), failed));
+        metrics::count("tdlib_requests", &[("result", if failed { "failed" } else { "ok" })]);
+    }
+
+    fn counts(&mut self) -> (usize, usize) {
+        while self.samples.front().is_some_and(|(at, _)| at.elapsed() > self.window) {
+            self.samples.pop_front();
+        }
+        let failed = self.samples.iter().filter(|(_, failed)| *failed).count();
+        (failed, self.samples.len())
+    }
+
+    // An "error_rate" event for the manager when the rate crosses the threshold either way
+    pub fn check(&mut self) -> Option<Value> {
+        let (failed, total) = self.counts();
+        let rate = if total == 0 { 0.0 } else { failed as f64 / total as f64 };
+        let alerting = total >= self.min_samples && rate >= self.threshold;
+        if alerting == self.alerting {
+            return None;
+        }
+
+        self.alerting = alerting;
+        if alerting {
+            warn!("TDLib error rate {:.0}% ({} of {} requests in {} s)", rate * 100.0, failed, total, self.window.as_secs());
+        }
+        Some(json!({
+            "event": "error_rate",
+            "alerting": alerting,
+            "failed": failed,
+            "total": total,
+            "window_secs": self.window.as_secs(),
+        }))
+    }
+
+    // Gauge for the heartbeat file
+    pub fn gauge(&mut self) -> Value {
+        let (failed, total) = self.counts();
+        json!({ "failed": failed, "total": total, "alerting": self.alerting })
+    }
+}
@@ -0,0 +1,161 @@
+use std::time::Duration;
+use serde_json::{json, Value};
+
+use crate::{
+    control::ControlChannel,
+    history::{History, ReactionRecord},
+    publish::MatchPublisher,
+};
+
+// An order reacted to, with what the filters made of it
+pub struct Match<'a> {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub target: &'a str,   // "chat:message" of the reaction, which may be a discussion group copy
+    pub chat_title: Option<&'a str>,    // Once known (see names.rs)
+    pub sender_name: Option<&'a str>,
+    pub text: &'a str,
+    pub price: Option<i32>,
+    pub bank: Option<&'a str>,
+    pub commission_pct: Option<f64>,
+    pub expected_profit: Option<f64>,
+    pub score: f64,
+    pub balance_left: Option<i64>,
+    pub reacted_at: i64,
+    pub latency: Duration,             // From receiving the update to sending the reaction
+    pub delivery: Option<Duration>,   // From the message's date to receiving it
+}
+
+// How TDLib settled a reaction
+pub enum Outcome<'a> {
+    Sent,
+    Failed(&'a Value),   // TDLib's error message
+    Unconfirmed,          // No answer, resends used up (see pending.rs)
+}
+
+// What happened in the receive loop
+pub enum Event<'a> {
+    Matched(Match<'a>),
+    Reaction { target: &'a str, outcome: Outcome<'a> },
+    // Everything else the manager hears about (connection, pause, kill switch, alerts...), already
+    // in the control channel's JSON
+    Status(Value),
+}
+
+// Something that reacts to events: the manager, downstream workers, the reaction history
+pub trait Subscriber {
+    fn on_event(&self, event: &Event);
+}
+
+// Hands every event to each subscriber in turn, so the receive loop says what happened once and
+// doesn't need to know who's listening. Subscribers are called on the receive loop's thread and
+// are expected to hand anything slow to a thread of their own, as they all do
+pub struct EventBus<'a> {
+    subscribers: Vec<&'a dyn Subscriber>,
+}
+
+impl<'a> EventBus<'a> {
+    pub fn new() -> Self {
+        Self { subscribers: Vec::new() }
+    }
+
+    pub fn subscribe(&mut self, subscriber: &'a dyn Subscriber) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn publish(&self, event: Event) {
+        for subscriber in &self.subscribers {
+            subscriber.on_event(&event);
+        }
+    }
+
+    // Shorthand for an Event::Status
+    pub fn status(&self, event: Value) {
+        self.publish(Event::Status(event));
+    }
+}
+
+impl Event<'_> {
+    // The event as the manager gets it over the control channel
+    pub fn to_json(&self) -> Value {
+        match self {
+            Event::Matched(order) => json!({
+                "event": "match",
+                "target": order.target,
+                "chat_title": order.chat_title,
+                "sender_name": order.sender_name,
+                "price": order.price,
+                "bank": order.bank,
+                "expected_profit": order.expected_profit,
+                "score": order.score,
+                "balance_left": order.balance_left,
+                "latency_us": order.latency.as_micros() as u64,
+                "delivery_ms": order.delivery.map(|delivery| delivery.as_millis() as u64)
+            }),
+            Event::Reaction { target, outcome: Outcome::Sent } => json!({ "event": "reaction_sent", "target": target }),
+            Event::Reaction { target, outcome: Outcome::Failed(message) } => json!({
+                "event": "reaction_failed",
+                "target": target,
+                "message": message
+            }),
+            Event::Reaction { target, outcome: Outcome::Unconfirmed } => json!({ "event": "reaction_unconfirmed", "target": target }),
+            Event::Status(event) => event.clone(),
+        }
+    }
+}
+
+impl Subscriber for ControlChannel {
+    fn on_event(&self, event: &Event) {
+        self.emit(event.to_json());
+    }
+}
+
+impl Match<'_> {
+    // The order itself, as workers downstream and the post-reaction hook get it
+    pub fn to_json(&self) -> Value {
+        json!({
+            "chat_id": self.chat_id,
+            "message_id": self.message_id,
+            "chat_title": self.chat_title,
+            "sender_name": self.sender_name,
+            "amount": self.price,
+            "bank": self.bank,
+            "commission_pct": self.commission_pct,
+            "expected_profit": self.expected_profit,
+            "score": self.score,
+            "text": self.text,
+            "reacted_at": self.reacted_at,
+            "latency_us": self.latency.as_micros() as u64
+        })
+    }
+}
+
+impl Subscriber for MatchPublisher {
+    fn on_event(&self, event: &Event) {
+        let Event::Matched(order) = event else { return };
+        self.publish(&order.to_json());
+    }
+}
+
+impl Subscriber for History {
+    fn on_event(&self, event: &Event) {
+        match event {
+            Event::Matched(order) => self.record(ReactionRecord {
+                chat_id: order.chat_id,
+                message_id: order.message_id,
+                amount: order.price,
+                bank: order.bank.map(str::to_string),
+                reacted_at: order.reacted_at,
+                latency_us: order.latency.as_micros() as i64,
+                commission_pct: order.commission_pct,
+            }),
+            // Unconfirmed reactions keep no outcome: TDLib may still have set them
+            Event::Reaction { target, outcome: outcome @ (Outcome::Sent | Outcome::Failed(_)) } => {
+                if let Some((chat_id, message_id)) = crate::parse_target(target) {
+                    self.record_outcome(chat_id, message_id, matches!(outcome, Outcome::Sent));
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use botdg_core::{Entity, EntityKind};
+use serde_json::Value;
+
+// A message's text with the formatting the order parser can use (bold runs, code; see botdg-core's
+// entities.rs), from TDLib's formattedText
+pub struct MessageText<'a> {
+    pub text: &'a str,
+    pub entities: Vec<Entity>,
+}
+
+impl<'a> MessageText<'a> {
+    // Text with no formatting, as typed for test-filter or generated by simulate
+    pub fn plain(text: &'a str) -> Self {
+        Self { text, entities: Vec::new() }
+    }
+
+    // A formattedText object, such as a message's content.text
+    pub fn from_tdlib(formatted: &'a Value) -> Option<Self> {
+        let text = formatted["text"].as_str()?;
+        let entities = formatted["entities"]
+            .as_array()
+            .map(|entities| entities.iter().filter_map(entity).collect())
+            .unwrap_or_default();
+        Some(Self { text, entities })
+    }
+}
+
+// A textEntity, when it's of a kind the parser looks at
+fn entity(entity: &Value) -> Option<Entity> {
+    let kind = match entity["type"]["@type"].as_str()? {
+        "textEntityTypeBold" => EntityKind::Bold,
+        "textEntityTypeCode" => EntityKind::Code,
+        "textEntityTypePre" | "textEntityTypePreCode" => EntityKind::Pre,
+        _ => return None,
+    };
+    Some(Entity::new(entity["offset"].as_u64()? as usize, entity["length"].as_u64()? as usize, kind))
+}
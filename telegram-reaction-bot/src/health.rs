@@ -0,0 +1,155 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::events::{Event, Outcome, Subscriber};
+
+// The receive loop comes round at least this often; longer means it's stuck
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(60);
+// A probe that doesn't send its request within this is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_LINE: usize = 8192;
+
+struct State {
+    started: Instant,
+    alive: Instant,
+    auth_state: String,
+    connection_state: String,
+    last_update: Option<Instant>,
+    last_reaction: Option<Instant>,
+}
+
+// HEALTH_ADDR=127.0.0.1:8080: /healthz and /readyz for container orchestrators and uptime
+// monitors, answered on a thread of their own. /healthz is 503 once the receive loop has stopped
+// coming round (or the bot is logging in for longer than that); /readyz is 503 unless the
+// account is logged in and TDLib is connected. Both return the same JSON with the auth and
+// connection states and how long ago the last update and the last reaction were. Nothing is
+// served without HEALTH_ADDR
+static STATE: OnceLock<Option<Mutex<State>>> = OnceLock::new();
+
+fn state() -> Option<&'static Mutex<State>> {
+    STATE
+        .get_or_init(|| {
+            let addr = std::env::var("HEALTH_ADDR").ok().filter(|addr| !addr.trim().is_empty())?;
+            let listener = match TcpListener::bind(addr.trim()) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to listen for health checks on {}: {}", addr, e);
+                    return None;
+                }
+            };
+            info!("Serving /healthz and /readyz on {}", addr.trim());
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    answer(stream);
+                }
+            });
+            Some(Mutex::new(State {
+                started: Instant::now(),
+                alive: Instant::now(),
+                auth_state: "unknown".to_string(),
+                connection_state: "unknown".to_string(),
+                last_update: None,
+                last_reaction: None,
+            }))
+        })
+        .as_ref()
+}
+
+fn update(change: impl FnOnce(&mut State)) {
+    if let Some(state) = state() {
+        change(&mut state.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+// Called every time round the receive loop, and the login loop
+pub fn alive() {
+    update(|state| state.alive = Instant::now());
+}
+
+// TDLib's authorizationState, e.g. "authorizationStateReady"
+pub fn set_auth_state(auth_state: &str) {
+    update(|state| state.auth_state = auth_state.to_string());
+}
+
+// TDLib's connectionState, e.g. "connectionStateReady"
+pub fn set_connection_state(connection_state: &str) {
+    update(|state| state.connection_state = connection_state.to_string());
+}
+
+// Anything received from TDLib
+pub fn seen() {
+    update(|state| state.last_update = Some(Instant::now()));
+}
+
+// Both probes' answer: whether each is passing, and the JSON body
+fn report() -> (bool, bool, Value) {
+    let Some(state) = state() else {
+        return (false, false, Value::Null);
+    };
+    let state = state.lock().unwrap_or_else(|e| e.into_inner());
+    let live = state.alive.elapsed() < LIVENESS_TIMEOUT;
+    let ready = live && state.auth_state == "authorizationStateReady" && state.connection_state == "connectionStateReady";
+    let age = |at: Option<Instant>| at.map(|at| at.elapsed().as_secs());
+    let body = json!({
+        "live": live,
+        "ready": ready,
+        "auth_state": state.auth_state,
+        "connection_state": state.connection_state,
+        "last_update_age_secs": age(state.last_update),
+        "last_reaction_age_secs": age(state.last_reaction),
+        "uptime_secs": state.started.elapsed().as_secs(),
+    });
+    (live, ready, body)
+}
+
+// One request per connection; anything but GET /healthz or /readyz is a 404
+fn answer(mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+    // Only the request line matters, which may come in pieces
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.contains(&b'\n') && request.len() < MAX_REQUEST_LINE {
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(length) => request.extend_from_slice(&buffer[..length]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.lines().next().and_then(|line| line.strip_prefix("GET ")).and_then(|rest| rest.split(' ').next());
+    let (live, ready, body) = report();
+    let (status, body) = match path.map(|path| path.split('?').next().unwrap_or(path)) {
+        Some("/healthz") => (if live { "200 OK" } else { "503 Service Unavailable" }, body.to_string()),
+        Some("/readyz") => (if ready { "200 OK" } else { "503 Service Unavailable" }, body.to_string()),
+        _ => ("404 Not Found", json!({ "error": "not found" }).to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// The last reaction TDLib confirmed, and connection changes of the primary session
+pub struct EventHealth;
+
+impl Subscriber for EventHealth {
+    fn on_event(&self, event: &Event) {
+        match event {
+            Event::Reaction { outcome: Outcome::Sent, .. } => update(|state| state.last_reaction = Some(Instant::now())),
+            Event::Status(status) if status["event"] == "connection" => {
+                if let Some(connection_state) = status["state"].as_str() {
+                    set_connection_state(connection_state);
+                }
+            }
+            _ => {}
+        }
+    }
+}
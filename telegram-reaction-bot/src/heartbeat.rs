@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+use tracing::warn;
+use serde_json::{json, Value};
+
+// How often the heartbeat file is rewritten
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+// Periodically written status file the manager uses to detect a stalled bot
+pub struct Heartbeat {
+    path: Option<String>,
+    last_write: Option<Instant>,
+    connection_state: String,
+    connection_changed_at: i64,
+    started_at: i64,
+    tdlib_version: Option<String>,
+    filter_stats: Value,
+    error_rate: Value,
+    disk_usage: Value,
+    backlog: Value,
+}
+
+impl Heartbeat {
+    // Heartbeats are only written when HEARTBEAT_FILE is set (the manager sets it)
+    pub fn from_env() -> Self {
+        Self {
+            path: std::env::var("HEARTBEAT_FILE").ok().filter(|p| !p.is_empty()),
+            last_write: None,
+            connection_state: "unknown".to_string(),
+            connection_changed_at: chrono::Utc::now().timestamp(),
+            started_at: chrono::Utc::now().timestamp(),
+            tdlib_version: None,
+            filter_stats: Value::Null,
+            error_rate: Value::Null,
+            disk_usage: Value::Null,
+            backlog: Value::Null,
+        }
+    }
+
+    // Record a TDLib updateConnectionState change and publish it immediately
+    pub fn set_connection_state(&mut self, state: &str) {
+        if self.connection_state != state {
+            self.connection_state = state.to_string();
+            self.connection_changed_at = chrono::Utc::now().timestamp();
+            self.last_write = None;
+        }
+    }
+
+    // TDLib reports its version through updateOption right after start
+    pub fn set_tdlib_version(&mut self, version: &str) {
+        self.tdlib_version = Some(version.to_string());
+        self.last_write = None;
+    }
+
+    pub fn tdlib_version(&self) -> Option<&str> {
+        self.tdlib_version.as_deref()
+    }
+
+    pub fn started_at(&self) -> i64 {
+        self.started_at
+    }
+
+    // Reacted/skipped counters (see stats.rs), published with the next write
+    pub fn set_filter_stats(&mut self, stats: Value) {
+        self.filter_stats = stats;
+    }
+
+    // TDLib error rate gauge (see errors.rs), published with the next write
+    pub fn set_error_rate(&mut self, error_rate: Value) {
+        self.error_rate = error_rate;
+    }
+
+    // Size of the TDLib directories (see disk.rs), published with the next write
+    pub fn set_disk_usage(&mut self, disk_usage: Value) {
+        self.disk_usage = disk_usage;
+    }
+
+    // Updates per receive and how long they wait (see backlog.rs), published with the next write
+    pub fn set_backlog(&mut self, backlog: Value) {
+        self.backlog = backlog;
+    }
+
+    // Whether the next tick() will write the file
+    pub fn is_due(&self) -> bool {
+        self.path.is_some() && self.last_write.is_none_or(|t| t.elapsed() >= HEARTBEAT_INTERVAL)
+    }
+
+    // Rewrite the heartbeat file if the interval has elapsed
+    pub fn tick(&mut self) {
+        if !self.is_due() {
+            return;
+        }
+        let Some(path) = &self.path else { return };
+        self.last_write = Some(Instant::now());
+
+        let heartbeat = json!({
+            "timestamp": chrono::Utc::now().timestamp(),
+            "pid": std::process::id(),
+            "connection_state": self.connection_state,
+            "connection_changed_at": self.connection_changed_at,
+            "started_at": self.started_at,
+            "version": env!("CARGO_PKG_VERSION"),
+            "commit": env!("BUILD_COMMIT"),
+            "tdlib_version": self.tdlib_version,
+            "filter_stats": self.filter_stats,
+            "error_rate": self.error_rate,
+            "disk_usage": self.disk_usage,
+            "backlog": self.backlog,
+        });
+
+        // Write to a temp file and rename so the manager never reads a partial file
+        let tmp_path = format!("{}.tmp", path);
+        if let Err(e) = std::fs::write(&tmp_path, heartbeat.to_string())
+            .and_then(|_| std::fs::rename(&tmp_path, path))
+        {
+            warn!("Failed to write heartbeat file {}: {}", path, e);
+        }
+    }
+}
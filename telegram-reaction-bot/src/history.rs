@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::{race::Competitor, store::{HistoryStore, MemoryStore, SqliteStore}};
+use rusqlite::Connection;
+use serde_json::json;
+use tokio::sync::oneshot;
+
+pub const DEFAULT_HISTORY_DB: &str = "reactions.db";
+const DEFAULT_DECISION_TRACE_DAYS: i64 = 7;
+// How often traces older than DECISION_TRACE_DAYS are deleted
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+// A message the bot reacted to
+#[derive(Clone)]
+pub struct ReactionRecord {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub amount: Option<i32>,
+    pub bank: Option<String>,
+    pub reacted_at: i64,   // Unix timestamp
+    pub latency_us: i64,   // From receiving the update to sending the reaction
+    pub commission_pct: Option<f64>,   // The order's own commission, when it states one
+}
+
+// Why a message in a watched chat was or wasn't reacted to: the decision, the order as parsed and
+// every filter rule with its inputs, as JSON (see FilterSettings::trace)
+#[derive(Clone)]
+pub struct DecisionTrace {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub decided_at: i64,   // Unix timestamp
+    pub trace: String,
+}
+
+// Who posted an order and the requisite (as `normalize::requisite_key`) and bank it gave, for the
+// scam checks (see scam.rs)
+#[derive(Clone)]
+pub struct SeenOrder {
+    pub chat_id: i64,
+    pub sender_id: Option<i64>,
+    pub requisite: Option<String>,
+    pub bank: Option<String>,
+    pub seen_at: i64,   // Unix timestamp
+}
+
+enum Request {
+    Record(ReactionRecord),
+    Decision(DecisionTrace),
+    Seen(SeenOrder),
+    Outcome { chat_id: i64, message_id: i64, confirmed: bool },
+    Race { chat_id: i64, message_id: i64, won: bool, margin_secs: Option<i64>, won_by: Option<String> },
+    FinalState { chat_id: i64, message_id: i64, state: &'static str },
+    Competitors { chat_id: i64, message_id: i64, competitors: Vec<Competitor> },
+    Name { id: i64, name: String },
+    Recent(usize, oneshot::Sender<Vec<ReactionRecord>>),
+    ReactedSince(i64, oneshot::Sender<Vec<(i64, i64)>>),
+    Why(i64, oneshot::Sender<Vec<DecisionTrace>>),
+    SeenOrders(oneshot::Sender<Vec<SeenOrder>>),
+    Clear(oneshot::Sender<usize>),
+}
+
+// Reaction history, kept in a HistoryStore (see store.rs). All storage work happens on a
+// dedicated thread, so recording a reaction is just a channel send on the hot path
+pub struct History {
+    requests: Option<mpsc::Sender<Request>>,
+    traces: bool,   // DECISION_TRACE_DAYS isn't 0
+}
+
+impl History {
+    // HISTORY_STORE picks the backend: "sqlite" (default; HISTORY_DB sets the file, an empty
+    // value disables the history), "memory", or "postgres" (HISTORY_POSTGRES_URL; needs the
+    // "postgres" feature)
+    pub fn from_env() -> Self {
+        let backend = std::env::var("HISTORY_STORE").unwrap_or_default().to_lowercase();
+        let store: Result<Box<dyn HistoryStore>, String> = match backend.as_str() {
+            "" | "sqlite" => {
+                let path = std::env::var("HISTORY_DB").unwrap_or_else(|_| DEFAULT_HISTORY_DB.to_string());
+                if path.is_empty() {
+                    info!("Reaction history disabled");
+                    return Self { requests: None, traces: false };
+                }
+                info!("Recording reaction history to {}", path);
+                SqliteStore::open(&path)
+                    .map(|store| Box::new(store) as Box<dyn HistoryStore>)
+                    .map_err(|e| format!("Failed to open reaction history {}: {}", path, e))
+            }
+            "memory" => {
+                info!("Keeping reaction history in memory");
+                Ok(Box::new(MemoryStore::new()))
+            }
+            #[cfg(feature = "postgres")]
+            "postgres" => {
+                let url = std::env::var("HISTORY_POSTGRES_URL").unwrap_or_default();
+                let instance = std::env::var("INSTANCE_NAME").unwrap_or_else(|_| "default".to_string());
+                info!("Recording reaction history to PostgreSQL as instance '{}'", instance);
+                crate::store::postgres::PostgresStore::connect(&url, &instance)
+                    .map(|store| Box::new(store) as Box<dyn HistoryStore>)
+                    .map_err(|e| format!("Failed to connect to the PostgreSQL history: {}", e))
+            }
+            other => Err(format!("Unknown HISTORY_STORE '{}' (sqlite, memory or, with the postgres feature, postgres)", other)),
+        };
+
+        // Decision traces are kept for DECISION_TRACE_DAYS; 0 doesn't keep them at all
+        let trace_days = std::env::var("DECISION_TRACE_DAYS")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_DECISION_TRACE_DAYS);
+        match store {
+            Ok(store) => {
+                let (requests, receiver) = mpsc::channel();
+                thread::spawn(move || serve(store, receiver, trace_days));
+                Self { requests: Some(requests), traces: trace_days > 0 }
+            }
+            Err(e) => {
+                warn!("{}; history disabled", e);
+                Self { requests: None, traces: false }
+            }
+        }
+    }
+
+    // Nothing recorded, for commands that only go through the filters
+    pub fn disabled() -> Self {
+        Self { requests: None, traces: false }
+    }
+
+    pub fn record(&self, record: ReactionRecord) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Record(record));
+        }
+    }
+
+    // Whether decisions are kept, so the caller can skip building their traces
+    pub fn traces(&self) -> bool {
+        self.traces
+    }
+
+    pub fn record_decision(&self, trace: DecisionTrace) {
+        if let Some(requests) = self.requests.as_ref().filter(|_| self.traces) {
+            let _ = requests.send(Request::Decision(trace));
+        }
+    }
+
+    // Whether TDLib confirmed the reaction or every request sent for it failed
+    pub fn record_outcome(&self, chat_id: i64, message_id: i64, confirmed: bool) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Outcome { chat_id, message_id, confirmed });
+        }
+    }
+
+    // Whether we were the first to react, by how many seconds, and from which race account (see race.rs)
+    pub fn record_race(&self, chat_id: i64, message_id: i64, won: bool, margin_secs: Option<i64>, won_by: Option<String>) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Race { chat_id, message_id, won, margin_secs, won_by });
+        }
+    }
+
+    // What became of a matched order, as seen in the chat (see lifecycle.rs)
+    pub fn record_final_state(&self, chat_id: i64, message_id: i64, state: &'static str) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::FinalState { chat_id, message_id, state });
+        }
+    }
+
+    // Other accounts that reacted to a matched order (see race.rs)
+    pub fn record_seen(&self, order: SeenOrder) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Seen(order));
+        }
+    }
+
+    pub fn record_competitors(&self, chat_id: i64, message_id: i64, competitors: Vec<Competitor>) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Competitors { chat_id, message_id, competitors });
+        }
+    }
+
+    // A chat's title or a user's name, for readers of the history to show instead of the ID
+    pub fn record_name(&self, id: i64, name: String) {
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(Request::Name { id, name });
+        }
+    }
+
+    // Most recent reactions, newest first
+    pub async fn recent(&self, limit: usize) -> Option<Vec<ReactionRecord>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.as_ref()?.send(Request::Recent(limit, reply)).ok()?;
+        response.await.ok()
+    }
+
+    // Messages reacted to at or after the timestamp, oldest first
+    pub async fn reacted_since(&self, since: i64) -> Option<Vec<(i64, i64)>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.as_ref()?.send(Request::ReactedSince(since, reply)).ok()?;
+        response.await.ok()
+    }
+
+    // Decisions about messages with this ID, in any chat, newest first
+    pub async fn why(&self, message_id: i64) -> Option<Vec<DecisionTrace>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.as_ref()?.send(Request::Why(message_id, reply)).ok()?;
+        response.await.ok()
+    }
+
+    // Every order the scam checks have seen, oldest first
+    pub async fn seen_orders(&self) -> Option<Vec<SeenOrder>> {
+        let (reply, response) = oneshot::channel();
+        self.requests.as_ref()?.send(Request::SeenOrders(reply)).ok()?;
+        response.await.ok()
+    }
+
+    // Delete all recorded reactions, returning how many there were
+    pub async fn clear(&self) -> Option<usize> {
+        let (reply, response) = oneshot::channel();
+        self.requests.as_ref()?.send(Request::Clear(reply)).ok()?;
+        response.await.ok()
+    }
+}
+
+fn serve(mut store: Box<dyn HistoryStore>, requests: mpsc::Receiver<Request>, trace_days: i64) {
+    let mut pruned: Option<Instant> = None;
+    while let Ok(request) = requests.recv() {
+        // Write everything queued during a burst in one go
+        let mut batch = Vec::new();
+        let mut decisions = Vec::new();
+        let mut next = Some(request);
+        while let Some(request) = next.take() {
+            match request {
+                Request::Record(record) => batch.push(record),
+                Request::Decision(trace) => decisions.push(trace),
+                request => {
+                    // The reaction being updated or asked about may still be in a batch
+                    flush(store.as_mut(), &mut batch);
+                    flush_decisions(store.as_mut(), &mut decisions);
+                    handle(store.as_mut(), request);
+                }
+            }
+            next = requests.try_recv().ok();
+        }
+        flush(store.as_mut(), &mut batch);
+        flush_decisions(store.as_mut(), &mut decisions);
+
+        if trace_days > 0 && pruned.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+            let before = chrono::Utc::now().timestamp() - trace_days * 86_400;
+            if let Err(e) = store.prune_decisions(before) {
+                warn!("Failed to delete old decision traces: {}", e);
+            }
+            pruned = Some(Instant::now());
+        }
+    }
+}
+
+fn flush_decisions(store: &mut dyn HistoryStore, decisions: &mut Vec<DecisionTrace>) {
+    if decisions.is_empty() {
+        return;
+    }
+    if let Err(e) = store.insert_decisions(decisions) {
+        warn!("Failed to record {} decision traces: {}", decisions.len(), e);
+    }
+    decisions.clear();
+}
+
+fn flush(store: &mut dyn HistoryStore, batch: &mut Vec<ReactionRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = store.insert(batch) {
+        warn!("Failed to record {} reactions: {}", batch.len(), e);
+    }
+    batch.clear();
+}
+
+fn handle(store: &mut dyn HistoryStore, request: Request) {
+    match request {
+        Request::Record(record) => flush(store, &mut vec![record]),
+        Request::Decision(trace) => flush_decisions(store, &mut vec![trace]),
+        Request::Outcome { chat_id, message_id, confirmed } => {
+            let outcome = if confirmed { "sent" } else { "failed" };
+            if let Err(e) = store.set_outcome(chat_id, message_id, outcome) {
+                warn!("Failed to record reaction outcome: {}", e);
+            }
+        }
+        Request::Race { chat_id, message_id, won, margin_secs, won_by } => {
+            if let Err(e) = store.set_race(chat_id, message_id, won, margin_secs, won_by.as_deref()) {
+                warn!("Failed to record race result: {}", e);
+            }
+        }
+        Request::FinalState { chat_id, message_id, state } => {
+            if let Err(e) = store.set_final_state(chat_id, message_id, state) {
+                warn!("Failed to record order state: {}", e);
+            }
+        }
+        Request::Competitors { chat_id, message_id, competitors } => {
+            if let Err(e) = store.add_competitors(chat_id, message_id, &competitors) {
+                warn!("Failed to record competitor reactions: {}", e);
+            }
+        }
+        Request::Name { id, name } => {
+            if let Err(e) = store.set_name(id, &name) {
+                warn!("Failed to record the name of {}: {}", id, e);
+            }
+        }
+        Request::Seen(order) => {
+            if let Err(e) = store.insert_seen(&order) {
+                warn!("Failed to record a seen order: {}", e);
+            }
+        }
+        Request::Recent(limit, reply) => {
+            let records = store.recent(limit).unwrap_or_else(|e| {
+                warn!("Failed to read reaction history: {}", e);
+                Vec::new()
+            });
+            let _ = reply.send(records);
+        }
+        Request::ReactedSince(since, reply) => {
+            let targets = store.reacted_since(since).unwrap_or_else(|e| {
+                warn!("Failed to read reaction history: {}", e);
+                Vec::new()
+            });
+            let _ = reply.send(targets);
+        }
+        Request::Why(message_id, reply) => {
+            let traces = store.decisions(message_id).unwrap_or_else(|e| {
+                warn!("Failed to read decision traces: {}", e);
+                Vec::new()
+            });
+            let _ = reply.send(traces);
+        }
+        Request::SeenOrders(reply) => {
+            let orders = store.seen_orders().unwrap_or_else(|e| {
+                warn!("Failed to read seen orders: {}", e);
+                Vec::new()
+            });
+            let _ = reply.send(orders);
+        }
+        Request::Clear(reply) => match store.clear() {
+            Ok(deleted) => {
+                let _ = reply.send(deleted);
+            }
+            Err(e) => warn!("Failed to clear reaction history: {}", e),
+        },
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// `export-history csv|json [--since 2026-10-01|7d|12h] [--output FILE]`: dump the SQLite history to a file or stdout
+pub fn export(format: ExportFormat, since: Option<&str>, output: Option<&str>) -> Result<(), String> {
+    let since = match since {
+        Some(since) => parse_since(since).ok_or("--since needs a date (2026-10-01) or a period (7d, 12h)")?,
+        None => 0,
+    };
+
+    let path = std::env::var("HISTORY_DB").unwrap_or_else(|_| DEFAULT_HISTORY_DB.to_string());
+    let connection = Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open reaction history {}: {}", path, e))?;
+    let records = since_timestamp(&connection, since).map_err(|e| format!("Failed to read reaction history: {}", e))?;
+    let names = names(&connection);
+
+    let contents = match format {
+        ExportFormat::Csv => to_csv(&records, &names),
+        ExportFormat::Json => to_json(&records, &names),
+    };
+    match output {
+        Some(output) => {
+            std::fs::write(output, contents).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+            eprintln!("Exported {} reactions to {}", records.len(), output);
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn since_timestamp(connection: &Connection, since: i64) -> rusqlite::Result<Vec<(ReactionRecord, Option<String>)>> {
+    let mut statement = connection.prepare(
+        "SELECT chat_id, message_id, amount, bank, reacted_at, latency_us, commission_pct, outcome
+         FROM reactions WHERE reacted_at >= ?1 ORDER BY id",
+    )?;
+    let rows = statement.query_map([since], |row| {
+        Ok((
+            ReactionRecord {
+                chat_id: row.get(0)?,
+                message_id: row.get(1)?,
+                amount: row.get(2)?,
+                bank: row.get(3)?,
+                reacted_at: row.get(4)?,
+                latency_us: row.get(5)?,
+                commission_pct: row.get(6)?,
+            },
+            row.get(7)?,
+        ))
+    })?;
+    rows.collect()
+}
+
+// Chat titles and user names by ID (see names.rs); none in histories from before they were kept
+fn names(connection: &Connection) -> HashMap<i64, String> {
+    let names = connection.prepare("SELECT id, name FROM names").and_then(|mut statement| {
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()
+    });
+    names.unwrap_or_default()
+}
+
+// A date ("2026-10-01") or a period back from now ("7d", "12h")
+fn parse_since(value: &str) -> Option<i64> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp());
+    }
+
+    let (count, unit) = value.split_at(value.len().checked_sub(1)?);
+    let seconds = match unit {
+        "d" => 86_400,
+        "h" => 3_600,
+        _ => return None,
+    };
+    Some(chrono::Utc::now().timestamp() - count.parse::<i64>().ok()? * seconds)
+}
+
+fn iso_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(records: &[(ReactionRecord, Option<String>)], names: &HashMap<i64, String>) -> String {
+    let mut csv = "reacted_at,chat_id,message_id,amount,bank,latency_us,outcome,commission_pct,chat_title\n".to_string();
+    for (record, outcome) in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            iso_time(record.reacted_at),
+            record.chat_id,
+            record.message_id,
+            record.amount.map(|a| a.to_string()).unwrap_or_default(),
+            csv_field(record.bank.as_deref().unwrap_or_default()),
+            record.latency_us,
+            outcome.as_deref().unwrap_or_default(),
+            record.commission_pct.map(|c| c.to_string()).unwrap_or_default(),
+            csv_field(names.get(&record.chat_id).map_or("", String::as_str))
+        ));
+    }
+    csv
+}
+
+fn to_json(records: &[(ReactionRecord, Option<String>)], names: &HashMap<i64, String>) -> String {
+    let records = records
+        .iter()
+        .map(|(record, outcome)| {
+            json!({
+                "chat_id": record.chat_id,
+                "message_id": record.message_id,
+                "amount": record.amount,
+                "bank": record.bank,
+                "reacted_at": record.reacted_at,
+                "latency_us": record.latency_us,
+                "outcome": outcome,
+                "commission_pct": record.commission_pct,
+                "chat_title": names.get(&record.chat_id),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&records).unwrap_or_default()
+}
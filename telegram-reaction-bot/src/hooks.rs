@@ -0,0 +1,149 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Sender},
+    time::{Duration, Instant},
+};
+use botdg_core::{filter::SkipReason, Order};
+use tracing::{info, warn};
+
+use crate::events::{Event, Subscriber};
+
+const DEFAULT_PRE_TIMEOUT_MS: u64 = 500;
+const DEFAULT_POST_TIMEOUT_MS: u64 = 10_000;
+
+// A shell command (run with `sh -c`) or an http(s) URL (POSTed to, needs the "hooks-http" feature)
+// that gets an order as JSON: on stdin, or as the request body
+struct Hook {
+    target: String,
+    stage: &'static str,   // "pre" or "post", in BOTDG_HOOK for commands
+    timeout: Duration,
+}
+
+impl Hook {
+    fn from_env(name: &str, stage: &'static str, default_timeout_ms: u64) -> Option<Self> {
+        let target = std::env::var(name).ok().filter(|target| !target.trim().is_empty())?;
+        let timeout_ms = std::env::var(format!("{}_TIMEOUT_MS", name)).ok().and_then(|s| s.parse().ok()).unwrap_or(default_timeout_ms);
+        Some(Self { target, stage, timeout: Duration::from_millis(timeout_ms) })
+    }
+
+    fn is_url(&self) -> bool {
+        self.target.starts_with("http://") || self.target.starts_with("https://")
+    }
+
+    // Ok(true) when the hook agreed (exit status 0, a 2xx response), Ok(false) when it said no, and
+    // an error when it couldn't be run or didn't answer in time
+    fn run(&self, input: &str) -> Result<bool, String> {
+        if self.is_url() {
+            return self.post(input);
+        }
+        // A command line for the system's shell: sh, or cmd on Windows
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let mut child = Command::new(shell)
+            .arg(flag)
+            .arg(&self.target)
+            .env("BOTDG_HOOK", self.stage)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        // A hook that doesn't read its input shouldn't fail it
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+                return Ok(status.success());
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("no answer within {} ms", self.timeout.as_millis()));
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[cfg(feature = "hooks-http")]
+    fn post(&self, input: &str) -> Result<bool, String> {
+        let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+        match agent.post(&self.target).set("Content-Type", "application/json").set("X-Botdg-Hook", self.stage).send_string(input) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(..)) => Ok(false),
+            Err(ureq::Error::Transport(transport)) => Err(transport.to_string()),
+        }
+    }
+
+    #[cfg(not(feature = "hooks-http"))]
+    fn post(&self, _input: &str) -> Result<bool, String> {
+        Err("URL hooks need a build with the hooks-http feature".to_string())
+    }
+}
+
+// PRE_REACTION_HOOK runs for every order that passed the filters, before the reaction, and can turn
+// it down (skipped as `hook`). It holds the reaction back while it runs, so it gets
+// PRE_REACTION_HOOK_TIMEOUT_MS (500); when it fails or times out the order is reacted to anyway,
+// unless PRE_REACTION_HOOK_ON_FAILURE=skip. POST_REACTION_HOOK runs after every reaction sent, on
+// a thread of its own, one at a time, each within POST_REACTION_HOOK_TIMEOUT_MS (10000); failures
+// are only logged. Both off when unset
+pub struct Hooks {
+    pre: Option<Hook>,
+    skip_on_failure: bool,
+    post: Option<Sender<String>>,
+}
+
+impl Hooks {
+    pub fn from_env() -> Self {
+        let pre = Hook::from_env("PRE_REACTION_HOOK", "pre", DEFAULT_PRE_TIMEOUT_MS);
+        let post = Hook::from_env("POST_REACTION_HOOK", "post", DEFAULT_POST_TIMEOUT_MS).map(|hook| {
+            info!("Post-reaction hook: {}", hook.target);
+            let (orders, receiver) = mpsc::channel::<String>();
+            std::thread::spawn(move || {
+                for order in receiver {
+                    match hook.run(&order) {
+                        Ok(true) => {}
+                        Ok(false) => warn!("Post-reaction hook failed"),
+                        Err(e) => warn!("Post-reaction hook failed: {}", e),
+                    }
+                }
+            });
+            orders
+        });
+        if let Some(hook) = &pre {
+            info!("Pre-reaction hook: {} (within {} ms)", hook.target, hook.timeout.as_millis());
+        }
+        let skip_on_failure = std::env::var("PRE_REACTION_HOOK_ON_FAILURE").is_ok_and(|v| v.eq_ignore_ascii_case("skip"));
+        Self { pre, skip_on_failure, post }
+    }
+
+    // Ask the pre-reaction hook about an order; `order` is only parsed when there is one
+    pub fn before(&self, chat_id: i64, message_id: i64, text: &str, order: impl FnOnce() -> Order) -> Result<(), SkipReason> {
+        let Some(hook) = &self.pre else { return Ok(()) };
+        let input = crate::order_json(chat_id, Some(message_id), text, &order()).to_string();
+        match hook.run(&input) {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                info!("Pre-reaction hook turned the order down");
+                Err(SkipReason::Hook)
+            }
+            Err(e) if self.skip_on_failure => {
+                warn!("Pre-reaction hook failed, skipping the order: {}", e);
+                Err(SkipReason::Hook)
+            }
+            Err(e) => {
+                warn!("Pre-reaction hook failed, reacting anyway: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+// The post-reaction hook gets the match as downstream workers do (see events.rs)
+impl Subscriber for Hooks {
+    fn on_event(&self, event: &Event) {
+        let (Some(post), Event::Matched(order)) = (&self.post, event) else { return };
+        let _ = post.send(order.to_json().to_string());
+    }
+}
+
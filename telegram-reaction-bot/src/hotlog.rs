@@ -0,0 +1,63 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::Instant,
+};
+
+// The lines logged for every message in a watched chat (filter decisions, skips, reaction timings)
+// go under their own target, so LOG_LEVEL=info,hot_path=warn drops them and keeps everything else
+// (see logging.rs). Short of that, skipped messages can be sampled: HOT_LOG_EVERY logs one in N of
+// them, HOT_LOG_PER_MINUTE caps how many are logged a minute (0, the default for both, logs every
+// one), and the next line logged says how many were left out. Reactions are always logged
+pub const TARGET: &str = "hot_path";
+
+// Consulted for every skipped message on the receive loop, so the counters are atomics rather
+// than anything behind a lock
+struct Sampling {
+    every: u64,
+    per_minute: u64,
+    started: Instant,
+}
+
+static SAMPLING: OnceLock<Sampling> = OnceLock::new();
+static SEEN: AtomicU64 = AtomicU64::new(0);
+static MINUTE: AtomicU64 = AtomicU64::new(0);
+static LOGGED: AtomicU64 = AtomicU64::new(0);
+static LEFT_OUT: AtomicU64 = AtomicU64::new(0);
+
+fn sampling() -> &'static Sampling {
+    SAMPLING.get_or_init(|| {
+        let var = |name: &str| std::env::var(name).ok().and_then(|value| value.trim().parse::<u64>().ok()).unwrap_or(0);
+        Sampling { every: var("HOT_LOG_EVERY").max(1), per_minute: var("HOT_LOG_PER_MINUTE"), started: Instant::now() }
+    })
+}
+
+// Whether to log this skipped message: Some with the number left out since the last one logged
+pub fn skipped() -> Option<u64> {
+    let sampling = sampling();
+    let sampled = SEEN.fetch_add(1, Ordering::Relaxed).is_multiple_of(sampling.every);
+    let under_cap = sampled && (sampling.per_minute == 0 || {
+        let minute = sampling.started.elapsed().as_secs() / 60;
+        if MINUTE.swap(minute, Ordering::Relaxed) != minute {
+            LOGGED.store(0, Ordering::Relaxed);
+        }
+        LOGGED.fetch_add(1, Ordering::Relaxed) < sampling.per_minute
+    });
+    if under_cap {
+        Some(LEFT_OUT.swap(0, Ordering::Relaxed))
+    } else {
+        LEFT_OUT.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+// What a sampled line adds about the ones left out before it
+pub fn left_out(count: u64) -> String {
+    if count == 0 {
+        String::new()
+    } else {
+        format!(" ({} skipped messages not logged)", count)
+    }
+}
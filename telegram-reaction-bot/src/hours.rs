@@ -0,0 +1,52 @@
+use botdg_config::{schedule::{self, Schedule}, timezone};
+use chrono::{Datelike, FixedOffset, Timelike, Utc};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+// WORKING_HOURS=mon-fri 09:00-18:00: react only within these hours (format in botdg-config's
+// schedule.rs), in WORKING_HOURS_TZ ("+03:00") or TIMEZONE (see botdg-config's timezone.rs). Checked for every
+// message, so off hours the bot keeps running and its session stays warm. Always open when unset
+pub struct WorkingHours {
+    schedule: Option<Schedule>,
+    offset: Option<FixedOffset>,
+    open: bool,
+}
+
+impl WorkingHours {
+    pub fn from_env() -> Self {
+        let schedule = std::env::var("WORKING_HOURS").ok().filter(|value| !value.trim().is_empty()).and_then(|value| {
+            Schedule::parse(&value).map_err(|e| warn!("Ignoring invalid WORKING_HOURS '{}': {}", value, e)).ok()
+        });
+        let offset = std::env::var("WORKING_HOURS_TZ").ok().and_then(|value| match schedule::parse_offset(&value) {
+            Ok(offset) => offset.and_then(FixedOffset::east_opt),
+            Err(e) => {
+                warn!("Ignoring invalid WORKING_HOURS_TZ: {}", e);
+                None
+            }
+        });
+        Self { schedule, offset, open: true }
+    }
+
+    pub fn is_open(&self) -> bool {
+        let Some(schedule) = &self.schedule else {
+            return true;
+        };
+        let now = match self.offset {
+            Some(offset) => Utc::now().with_timezone(&offset),
+            None => timezone::now(),
+        };
+        schedule.is_open(now.weekday().num_days_from_monday() as usize, now.hour() * 60 + now.minute())
+    }
+
+    // A "working_hours" event for the manager when the hours open or close
+    pub fn check(&mut self) -> Option<Value> {
+        let open = self.is_open();
+        if open == self.open {
+            return None;
+        }
+
+        self.open = open;
+        info!("{}", if open { "Working hours started, reacting again" } else { "Working hours are over, not reacting until they start" });
+        Some(json!({ "event": "working_hours", "open": open }))
+    }
+}
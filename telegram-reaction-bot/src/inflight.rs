@@ -0,0 +1,66 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::pending::PendingReactions;
+
+// A reaction waiting for room in its chat
+pub struct Queued {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub extra: String,
+    pub posted_at: i64,
+    pub target: Option<(i64, i64)>,   // As for PendingReactions::insert
+}
+
+// MAX_IN_FLIGHT_PER_CHAT: at most this many unconfirmed reactions per chat. When dozens of orders
+// drop at once, the rest wait here in order and go out as TDLib answers the earlier ones, so a
+// flood doesn't turn into a burst of requests to one chat. Off when unset or 0
+pub struct InFlightLimit {
+    limit: usize,
+    queued: VecDeque<Queued>,
+}
+
+impl InFlightLimit {
+    pub fn from_env() -> Self {
+        let limit = std::env::var("MAX_IN_FLIGHT_PER_CHAT").ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+        Self { limit, queued: VecDeque::new() }
+    }
+
+    // Whether a reaction in the chat can go now; not while earlier ones of the chat are queued
+    pub fn admits(&self, chat_id: i64, pending: &PendingReactions) -> bool {
+        self.limit == 0 || (pending.in_chat(chat_id) < self.limit && !self.queued.iter().any(|queued| queued.chat_id == chat_id))
+    }
+
+    pub fn queue(&mut self, queued: Queued) {
+        self.queued.push_back(queued);
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.queued.len()
+    }
+
+    // Queued reactions that fit now, oldest first within each chat
+    pub fn ready(&mut self, pending: &PendingReactions) -> Vec<Queued> {
+        if self.queued.is_empty() {
+            return Vec::new();
+        }
+        let mut in_flight: HashMap<i64, usize> = HashMap::new();
+        let mut ready = Vec::new();
+        let mut waiting = VecDeque::new();
+        for queued in self.queued.drain(..) {
+            let count = in_flight.entry(queued.chat_id).or_insert_with(|| pending.in_chat(queued.chat_id));
+            // Once one of a chat's reactions has to wait, so do the ones after it
+            if *count < self.limit && !waiting.iter().any(|w: &Queued| w.chat_id == queued.chat_id) {
+                *count += 1;
+                ready.push(queued);
+            } else {
+                waiting.push_back(queued);
+            }
+        }
+        self.queued = waiting;
+        ready
+    }
+
+    pub fn clear(&mut self) {
+        self.queued.clear();
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+use botdg_config::ConfigArgs;
+
+const DEFAULT_MIN_AMOUNT: i32 = 38000;
+
+// What `init` asks for; anything not given on the command line is asked in a terminal, and left as
+// a placeholder to fill in otherwise
+#[derive(clap::Args)]
+pub struct InitOptions {
+    /// TELEGRAM_API_ID from https://my.telegram.org/apps
+    #[arg(long)]
+    api_id: Option<String>,
+    /// TELEGRAM_API_HASH from https://my.telegram.org/apps
+    #[arg(long)]
+    api_hash: Option<String>,
+    /// Chats to watch, comma separated (ALLOWED_CHAT_IDS)
+    #[arg(long)]
+    chats: Option<String>,
+    /// Smallest order reacted to, in rubles (MIN_AMOUNT)
+    #[arg(long)]
+    min_amount: Option<i32>,
+    /// The manager bot's token from @BotFather (BOT_TOKEN)
+    #[arg(long)]
+    bot_token: Option<String>,
+    /// Telegram user IDs allowed to use the manager bot, comma separated (ALLOWED_USERS)
+    #[arg(long)]
+    admins: Option<String>,
+    /// Don't ask, leave placeholders for what isn't given
+    #[arg(long)]
+    no_input: bool,
+    /// Overwrite existing files
+    #[arg(long)]
+    force: bool,
+}
+
+// `init`: a commented config file for both bots (botdg.toml, or --config / the --profile's) and a
+// .env with the secrets, which are better kept out of the file. Neither is overwritten without --force
+pub fn run(mut options: InitOptions, config: &ConfigArgs) -> Result<(), String> {
+    let profile = config.profile.clone().or_else(|| std::env::var("BOTDG_PROFILE").ok()).filter(|profile| !profile.is_empty());
+    let config_file = config.config.clone().unwrap_or_else(|| botdg_config::default_file(profile.as_deref()));
+    let env_file = PathBuf::from(profile.as_ref().map_or(".env".to_string(), |profile| format!(".env.{}", profile)));
+    for file in [&config_file, &env_file] {
+        if file.exists() && !options.force {
+            return Err(format!("{} already exists; pass --force to overwrite it", file.display()));
+        }
+    }
+
+    if !options.no_input && std::io::stdin().is_terminal() {
+        println!("Setting up {} and {}. Press Enter to leave a value for later.", config_file.display(), env_file.display());
+        ask(&mut options.api_id, "Telegram API ID (https://my.telegram.org/apps)")?;
+        ask(&mut options.api_hash, "Telegram API hash")?;
+        ask(&mut options.chats, "Chat IDs to watch, comma separated (e.g. -1001234567890)")?;
+        if options.min_amount.is_none() {
+            let mut amount = None;
+            ask(&mut amount, &format!("Smallest order to react to, in rubles [{}]", DEFAULT_MIN_AMOUNT))?;
+            options.min_amount = amount.map(|amount| amount.parse().map_err(|_| format!("'{}' is not an amount", amount))).transpose()?;
+        }
+        ask(&mut options.bot_token, "Manager bot token from @BotFather (skip without the manager)")?;
+        ask(&mut options.admins, "Your Telegram user ID, for the manager bot")?;
+    }
+
+    let chats = options.chats.as_deref().map(parse_ids).transpose()?;
+    let admins = options.admins.as_deref().map(parse_ids).transpose()?;
+    write(&config_file, &config_text(chats.as_deref(), options.min_amount.unwrap_or(DEFAULT_MIN_AMOUNT), admins.as_deref()))?;
+    write(&env_file, &env_text(options.api_id.as_deref(), options.api_hash.as_deref(), options.bot_token.as_deref()))?;
+
+    println!("Wrote {} and {}", config_file.display(), env_file.display());
+    let missing: Vec<&str> = [
+        ("TELEGRAM_API_ID", options.api_id.is_none()),
+        ("TELEGRAM_API_HASH", options.api_hash.is_none()),
+        ("ALLOWED_CHAT_IDS", chats.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(name, missing)| missing.then_some(name))
+    .collect();
+    if !missing.is_empty() {
+        println!("Still to fill in: {}", missing.join(", "));
+    }
+    println!("Next: `tdlib-test check-config`, then `tdlib-test auth` to log in");
+    Ok(())
+}
+
+// Ask for a value in the terminal unless it was given; an empty answer leaves it unset
+fn ask(value: &mut Option<String>, question: &str) -> Result<(), String> {
+    if value.is_some() {
+        return Ok(());
+    }
+    print!("{}: ", question);
+    std::io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).map_err(|e| format!("Failed to read the answer: {}", e))?;
+    *value = Some(answer.trim().to_string()).filter(|answer| !answer.is_empty());
+    Ok(())
+}
+
+fn parse_ids(list: &str) -> Result<Vec<i64>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| format!("'{}' is not an ID", id)))
+        .collect()
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    // The config directory may not be there yet
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+    parent.map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(path, contents)).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn list(ids: &[i64]) -> String {
+    ids.iter().map(i64::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn config_text(chats: Option<&[i64]>, min_amount: i32, admins: Option<&[i64]>) -> String {
+    let chats = match chats {
+        Some(chats) => format!("allowed_chat_ids = [{}]", list(chats)),
+        None => "allowed_chat_ids = []   # TODO: the chats orders are posted in, e.g. [-1001234567890]".to_string(),
+    };
+    let admins = match admins {
+        Some(admins) => format!("allowed_users = [{}]", list(admins)),
+        None => "# allowed_users = [123456789]   # who may use the manager bot".to_string(),
+    };
+    let data_dir = botdg_config::dirs::data_dir().display().to_string();
+    format!(
+        r#"# Configuration of both bots, written by `tdlib-test init`. Keys are the environment variable names
+# in any case, lists are arrays. --set KEY=VALUE, then the environment and .env, win over this file.
+# Secrets (TELEGRAM_API_HASH, BOT_TOKEN) are in .env. All settings: telegram-reaction-bot/env.example
+# and telegram-likes-manager-bot/env.example
+
+# For both bots
+{chats}
+
+[reaction-bot]
+# Orders below this many rubles are skipped
+min_amount = {min_amount}
+# reaction_emoji = "👍"
+# bank_filter = "Т"
+# requisite_filter = "+"
+# The session and the history are kept in the data directory ({data_dir}) unless set here
+# tdlib_data_dir = "tdlib_data"
+# history_db = "reactions.db"
+# working_hours = "mon-fri 09:00-21:00"
+
+[manager]
+{admins}
+# reaction_bot_path = "../telegram-reaction-bot"
+# bot_language = "ru"
+"#
+    )
+}
+
+fn env_text(api_id: Option<&str>, api_hash: Option<&str>, bot_token: Option<&str>) -> String {
+    format!(
+        r#"# Secrets for both bots, written by `tdlib-test init`; keep this file out of version control
+
+# Telegram API credentials from https://my.telegram.org/apps
+TELEGRAM_API_ID={}
+TELEGRAM_API_HASH={}
+
+# Log in without a terminal: the phone number, and a file the login code is written to
+# TELEGRAM_PHONE=+1234567890
+# TELEGRAM_CODE_FILE=tdlib_data/code
+
+# The manager bot's token from @BotFather
+{}
+"#,
+        api_id.unwrap_or("your_api_id_here"),
+        api_hash.unwrap_or("your_api_hash_here"),
+        bot_token.map_or("# BOT_TOKEN=your_bot_token_here".to_string(), |token| format!("BOT_TOKEN={}", token)),
+    )
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::simulate::Random;
+
+// REACTION_JITTER_MS=50-300: wait a random time in the range before each reaction, for accounts
+// that would rather look less like a bot than win every race. Off by default. Delayed reactions
+// wait here while the loop goes on with other updates
+pub struct Jitter {
+    range: Option<(u64, u64)>,
+    random: Random,
+    waiting: Vec<(Instant, i64, i64, String)>,   // (due, chat, message, "@extra")
+}
+
+impl Jitter {
+    pub fn from_env() -> Self {
+        let range = match std::env::var("REACTION_JITTER_MS") {
+            Ok(value) if !value.trim().is_empty() => {
+                let range = parse_range(&value);
+                if range.is_none() {
+                    warn!("Ignoring invalid REACTION_JITTER_MS '{}'", value);
+                }
+                range.filter(|&(_, to)| to > 0)
+            }
+            _ => None,
+        };
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |since| since.as_nanos() as u64);
+        Self { range, random: Random::new(seed), waiting: Vec::new() }
+    }
+
+    // When a reaction decided now should go out, or None to send it right away
+    pub fn delay(&mut self) -> Option<Instant> {
+        let (from, to) = self.range?;
+        let millis = from + self.random.below((to - from + 1) as usize) as u64;
+        Some(Instant::now() + Duration::from_millis(millis))
+    }
+
+    pub fn hold(&mut self, due: Instant, chat_id: i64, message_id: i64, extra: String) {
+        self.waiting.push((due, chat_id, message_id, extra));
+    }
+
+    // Reactions whose wait is over, as (chat, message, "@extra")
+    pub fn due(&mut self) -> Vec<(i64, i64, String)> {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        self.waiting.retain(|(at, chat_id, message_id, extra)| {
+            let ready = *at <= now;
+            if ready {
+                due.push((*chat_id, *message_id, extra.clone()));
+            }
+            !ready
+        });
+        due
+    }
+
+    // How long the loop may wait for an update without holding a reaction back, in seconds
+    pub fn receive_timeout(&self, timeout: f64) -> f64 {
+        let now = Instant::now();
+        self.waiting
+            .iter()
+            .map(|(at, ..)| at.saturating_duration_since(now).as_secs_f64())
+            .fold(timeout, f64::min)
+    }
+
+    pub fn clear(&mut self) {
+        self.waiting.clear();
+    }
+}
+
+// "50-300" in milliseconds, or a single "200" for a fixed delay
+pub fn parse_range(value: &str) -> Option<(u64, u64)> {
+    let (from, to) = value.split_once('-').unwrap_or((value, value));
+    let (from, to) = (from.trim().parse::<u64>().ok()?, to.trim().parse::<u64>().ok()?);
+    (from <= to).then_some((from, to))
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tracing::{info, warn};
+
+// Emergency stop: the manager's /panic, or PANIC_PHRASE sent from this account in any monitored
+// chat, stops every reaction until it's explicitly released with /panic off. Unlike /pause it's
+// written to PANIC_FILE (the "panic" file in TDLIB_DATA_DIR by default), so a restart doesn't lift
+// it. The manager, which keeps its own record, passes PANIC=true or false to the processes it starts
+pub struct KillSwitch {
+    file: PathBuf,
+    phrase: Option<String>,
+    engaged: bool,
+}
+
+impl KillSwitch {
+    pub fn from_env() -> Self {
+        let file = std::env::var("PANIC_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&crate::tdlib_data_dir()).join("panic"));
+        let phrase = std::env::var("PANIC_PHRASE").ok().map(|phrase| phrase.trim().to_lowercase()).filter(|phrase| !phrase.is_empty());
+
+        let mut switch = Self { engaged: file.exists(), file, phrase };
+        match std::env::var("PANIC").as_deref() {
+            Ok("true" | "1") => {
+                switch.set(true);
+            }
+            Ok("false" | "0") => {
+                switch.set(false);
+            }
+            _ => {}
+        }
+        if switch.engaged {
+            warn!("Kill switch is engaged, not reacting until it's released with /panic off");
+        }
+        switch
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.engaged
+    }
+
+    // Whether a new message is the account's own PANIC_PHRASE
+    pub fn is_trigger(&self, message: &Value, text: &str) -> bool {
+        let Some(phrase) = &self.phrase else {
+            return false;
+        };
+        message["is_outgoing"].as_bool() == Some(true) && text.trim().to_lowercase() == *phrase
+    }
+
+    // Engage or release; false when it already was
+    pub fn set(&mut self, engaged: bool) -> bool {
+        let changed = engaged != self.engaged;
+        self.engaged = engaged;
+        let saved = if engaged {
+            self.file.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(&self.file, b"engaged\n"))
+        } else {
+            std::fs::remove_file(&self.file).or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+        };
+        if let Err(e) = saved {
+            warn!("Failed to record the kill switch in {}: {}", self.file.display(), e);
+        }
+        if changed {
+            info!("Kill switch {}", if engaged { "engaged" } else { "released" });
+        }
+        changed
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+use crate::metrics;
+
+// Samples kept per stage; percentiles describe the most recent reactions
+const MAX_SAMPLES: usize = 1000;
+const DEFAULT_REPORT_INTERVAL_SECS: u64 = 300;
+
+// Hot path stages, in order
+#[derive(Clone, Copy)]
+pub enum Stage {
+    Parse,     // Update received from TDLib -> JSON parsed
+    Filter,    // Parsed -> filter decision
+    Send,      // Decision -> reaction requests handed to TDLib
+    Confirm,   // Requests sent -> TDLib confirmed the reaction
+    Total,     // Update received -> reaction requests sent
+    Delivery,  // Message posted (its server date) -> update received, for every monitored message
+}
+
+// Delivery first: Telegram's share of the delay, before ours
+const STAGES: [(Stage, &str); 6] = [
+    (Stage::Delivery, "delivery"),
+    (Stage::Parse, "parse"),
+    (Stage::Filter, "filter"),
+    (Stage::Send, "send"),
+    (Stage::Confirm, "confirm"),
+    (Stage::Total, "total"),
+];
+
+// Rolling per-stage timings with p50/p95/p99 summaries
+pub struct LatencyStats {
+    samples: [VecDeque<u64>; 6],   // Microseconds, indexed by Stage
+    report_interval: Duration,
+    last_report: Instant,
+}
+
+impl LatencyStats {
+    // LATENCY_REPORT_SECS sets how often the summary is logged; 0 disables the periodic log
+    pub fn from_env() -> Self {
+        let report_interval = std::env::var("LATENCY_REPORT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_REPORT_INTERVAL_SECS);
+        Self {
+            samples: Default::default(),
+            report_interval: Duration::from_secs(report_interval),
+            last_report: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, stage: Stage, elapsed: Duration) {
+        if let Some((_, name)) = STAGES.iter().find(|(known, _)| *known as usize == stage as usize) {
+            metrics::timing("latency", elapsed, &[("stage", name)]);
+        }
+        let samples = &mut self.samples[stage as usize];
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed.as_micros() as u64);
+    }
+
+    // Multi-line p50/p95/p99 table, also sent in reply to /latency
+    pub fn summary(&self) -> String {
+        let mut text = "⏱ Reaction latency (µs): p50 / p95 / p99 (samples)".to_string();
+        for (stage, name) in STAGES {
+            let samples = &self.samples[stage as usize];
+            if samples.is_empty() {
+                text.push_str(&format!("\n{}: no samples yet", name));
+                continue;
+            }
+            let mut sorted = samples.iter().copied().collect::<Vec<_>>();
+            sorted.sort_unstable();
+            text.push_str(&format!(
+                "\n{}: {} / {} / {} ({})",
+                name,
+                percentile(&sorted, 50),
+                percentile(&sorted, 95),
+                percentile(&sorted, 99),
+                sorted.len()
+            ));
+        }
+        text
+    }
+
+    // Log the summary when the report interval has elapsed and there is something to report
+    pub fn maybe_report(&mut self) {
+        if self.report_interval.is_zero() || self.last_report.elapsed() < self.report_interval {
+            return;
+        }
+        self.last_report = Instant::now();
+        if self.samples[Stage::Total as usize].is_empty() {
+            return;
+        }
+        for line in self.summary().lines() {
+            info!("{}", line);
+        }
+    }
+}
+
+// How long Telegram took to deliver a message: from its server date to `received`. Dates are whole
+// seconds and compared with the local clock, so this is only good to about a second
+pub fn delivery_delay(message: &serde_json::Value, received: Instant) -> Option<Duration> {
+    let posted_us = message["date"].as_i64()?.checked_mul(1_000_000)?;
+    let received_us = chrono::Utc::now().timestamp_micros() - received.elapsed().as_micros() as i64;
+    Some(Duration::from_micros(received_us.saturating_sub(posted_us).max(0) as u64))
+}
+
+// Nearest-rank percentile of sorted samples
+pub fn percentile(sorted: &[u64], p: usize) -> u64 {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use botdg_core::normalize;
+use serde_json::Value;
+
+const DEFAULT_WATCH_MINUTES: u64 = 60;
+// "state=pattern,pattern;..." as in ORDER_STATE_PATTERNS
+const DEFAULT_PATTERNS: &str = "cancelled=отмен,cancel;paid=оплачен,оплатил,оплата прошла,paid;taken=взял,взята,в работе,taken";
+
+// How far a matched order got, as far as the chat tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    Taken,
+    Paid,
+    Cancelled,
+}
+
+impl OrderState {
+    // As stored in the history's final_state column
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Taken => "taken",
+            Self::Paid => "paid",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "taken" => Some(Self::Taken),
+            "paid" => Some(Self::Paid),
+            "cancelled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+
+    // Nothing follows a paid or cancelled order
+    fn is_final(self) -> bool {
+        self != Self::Taken
+    }
+}
+
+// ORDER_STATE_PATTERNS as (state, lowercase substrings), in the order they are checked; normalized
+// like the messages, so "✅ Оплачено" matches however the chat decorates it
+pub fn parse_patterns(value: &str) -> Result<Vec<(OrderState, Vec<String>)>, String> {
+    value
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (state, patterns) = entry.split_once('=').ok_or_else(|| format!("'{}' is not state=pattern,...", entry.trim()))?;
+            let state = OrderState::parse(state).ok_or_else(|| format!("unknown state '{}' (taken, paid or cancelled)", state.trim()))?;
+            let patterns = patterns.split(',').map(|p| normalize::message(p).to_lowercase()).filter(|p| !p.is_empty()).collect();
+            Ok((state, patterns))
+        })
+        .collect()
+}
+
+// What happened to matched orders after the reaction. For ORDER_WATCH_MINUTES after a match,
+// edits of the order and replies to it are checked for ORDER_STATE_PATTERNS ("taken", "paid",
+// "cancelled"), so the history shows which won orders actually went through. Paid and cancelled
+// end the watch
+pub struct OrderLifecycle {
+    patterns: Vec<(OrderState, Vec<String>)>,
+    window: Duration,
+    watching: HashMap<(i64, i64), (Instant, Option<OrderState>)>,
+}
+
+impl OrderLifecycle {
+    pub fn from_env() -> Self {
+        let patterns = std::env::var("ORDER_STATE_PATTERNS").unwrap_or_else(|_| DEFAULT_PATTERNS.to_string());
+        let minutes = std::env::var("ORDER_WATCH_MINUTES").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_WATCH_MINUTES);
+        Self {
+            // Checked by validate.rs
+            patterns: parse_patterns(&patterns).unwrap_or_default(),
+            window: Duration::from_secs(minutes * 60),
+            watching: HashMap::new(),
+        }
+    }
+
+    pub fn watch(&mut self, chat_id: i64, message_id: i64) {
+        if self.patterns.is_empty() || self.window.is_zero() {
+            return;
+        }
+        let window = self.window;
+        self.watching.retain(|_, (since, _)| since.elapsed() < window);
+        self.watching.insert((chat_id, message_id), (Instant::now(), None));
+    }
+
+    // An edit of a watched order (updateMessageContent); returns its new state
+    pub fn on_edit(&mut self, chat_id: i64, message_id: i64, content: &Value) -> Option<OrderState> {
+        let text = content["text"]["text"].as_str().or_else(|| content["caption"]["text"].as_str())?;
+        self.advance((chat_id, message_id), text)
+    }
+
+    // A new message, if it replies to a watched order; returns the order and its new state
+    pub fn on_reply(&mut self, message: &Value, text: &str) -> Option<((i64, i64), OrderState)> {
+        let chat_id = message["chat_id"].as_i64()?;
+        let reply_to = &message["reply_to"];
+        let order = match reply_to["@type"].as_str() {
+            Some("messageReplyToMessage") => (reply_to["chat_id"].as_i64().unwrap_or(chat_id), reply_to["message_id"].as_i64()?),
+            // TDLib before 1.8.21
+            _ => (message["reply_in_chat_id"].as_i64().filter(|&id| id != 0).unwrap_or(chat_id), message["reply_to_message_id"].as_i64()?),
+        };
+        self.advance(order, text).map(|state| (order, state))
+    }
+
+    fn advance(&mut self, order: (i64, i64), text: &str) -> Option<OrderState> {
+        let (since, current) = self.watching.get_mut(&order)?;
+        if since.elapsed() >= self.window {
+            self.watching.remove(&order);
+            return None;
+        }
+        let text = normalize::message(text).to_lowercase();
+        let state = self
+            .patterns
+            .iter()
+            .find(|(_, patterns)| patterns.iter().any(|pattern| text.contains(pattern.as_str())))
+            .map(|(state, _)| *state)
+            .filter(|state| Some(*state) != *current)?;
+        *current = Some(state);
+        if state.is_final() {
+            self.watching.remove(&order);
+        }
+        Some(state)
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    time::{Duration, SystemTime},
+};
+
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_AGE_HOURS: u64 = 24;
+const DEFAULT_KEEP: u64 = 5;
+
+// A file rotated when it grows past <PREFIX>_MAX_BYTES or gets older than <PREFIX>_MAX_AGE_HOURS
+// (0 disables either limit): "bot.log" moves to "bot.log.1", older files shift up, and only
+// <PREFIX>_KEEP rotated files are kept. The bot's log uses the LOG_FILE prefix
+pub struct RotatingFile {
+    path: String,
+    file: File,
+    written: u64,
+    opened_at: SystemTime,
+    max_bytes: u64,
+    max_age: Duration,
+    keep: usize,
+    capture_stderr: bool,
+}
+
+impl RotatingFile {
+    // With capture_stderr (daemon mode), stderr follows the current file, so panics and
+    // anything TDLib prints land next to the log lines
+    pub fn open(path: &str, capture_stderr: bool) -> io::Result<Self> {
+        Self::open_with_limits(path, "LOG_FILE", capture_stderr)
+    }
+
+    // Limits are read from <prefix>_MAX_BYTES, <prefix>_MAX_AGE_HOURS and <prefix>_KEEP
+    pub fn open_with_limits(path: &str, prefix: &str, capture_stderr: bool) -> io::Result<Self> {
+        let var = |suffix: &str, default: u64| {
+            std::env::var(format!("{}_{}", prefix, suffix)).ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let log = Self {
+            path: path.to_string(),
+            written: metadata.len(),
+            // An existing file keeps aging across restarts
+            opened_at: metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file,
+            max_bytes: var("MAX_BYTES", DEFAULT_MAX_BYTES),
+            max_age: Duration::from_secs(var("MAX_AGE_HOURS", DEFAULT_MAX_AGE_HOURS) * 3600),
+            keep: var("KEEP", DEFAULT_KEEP) as usize,
+            capture_stderr,
+        };
+        log.redirect_stderr();
+        Ok(log)
+    }
+
+    fn due(&self, incoming: usize) -> bool {
+        let too_big = self.max_bytes > 0 && self.written > 0 && self.written + incoming as u64 > self.max_bytes;
+        let too_old = !self.max_age.is_zero() && self.opened_at.elapsed().is_ok_and(|age| age >= self.max_age);
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(format!("{}.{}", self.path, self.keep));
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(format!("{}.{}", self.path, n), format!("{}.{}", self.path, n + 1));
+            }
+            fs::rename(&self.path, format!("{}.1", self.path))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        self.opened_at = SystemTime::now();
+        self.redirect_stderr();
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn redirect_stderr(&self) {
+        use std::os::unix::io::AsRawFd;
+
+        if self.capture_stderr {
+            // SAFETY: dup2 onto stderr with a descriptor we own
+            unsafe { libc::dup2(self.file.as_raw_fd(), libc::STDERR_FILENO) };
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn redirect_stderr(&self) {}
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.due(buf.len()) {
+            // Keep logging to the old file rather than losing lines
+            if let Err(e) = self.rotate() {
+                eprintln!("Failed to rotate log file {}: {}", self.path, e);
+                self.opened_at = SystemTime::now();
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer, Registry};
+
+use crate::logfile::RotatingFile;
+
+// Flushes exported traces when dropped; keep it alive until the bot exits
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush OTLP traces: {}", e);
+            }
+        }
+    }
+}
+
+// Logs go to stderr: stdout is the manager's control channel. With a log file (--log-file or
+// LOG_FILE) they go to that file instead, rotated by size and age (see logfile.rs).
+// LOG_FORMAT=json writes one JSON object per line, with the current span's fields
+// (chat_id, message_id, decision), for ingestion by Loki/ELK.
+// Built with the "otlp" feature, spans are also exported when OTEL_EXPORTER_OTLP_ENDPOINT is set.
+// LOG_LEVEL (or RUST_LOG, which wins) sets the level, "info" by default, with per-module levels
+// after it in the same syntax: LOG_LEVEL=info,hot_path=warn,tdlib_test::pipeline=debug (see
+// hotlog.rs for hot_path).
+// Must run outside the tokio runtime: the OTLP exporter uses a blocking HTTP client
+pub fn init(log_file: Option<&str>, capture_stderr: bool) -> Telemetry {
+    let filter = ["RUST_LOG", "LOG_LEVEL"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.trim().is_empty()))
+        .and_then(|directives| {
+            EnvFilter::try_new(&directives)
+                .map_err(|e| eprintln!("Ignoring log level '{}': {}; logging at info", directives, e))
+                .ok()
+        })
+        .unwrap_or_else(|| EnvFilter::new("info"));
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
+
+    let file = log_file.and_then(|path| {
+        RotatingFile::open(path, capture_stderr)
+            .map_err(|e| eprintln!("Failed to open log file {}: {}; logging to stderr", path, e))
+            .ok()
+    });
+    let layer = fmt::layer().with_ansi(file.is_none());
+    let layer = match (file, json) {
+        (Some(file), true) => layer.json().with_current_span(true).with_span_list(false).with_writer(Mutex::new(file)).boxed(),
+        (Some(file), false) => layer.with_writer(Mutex::new(file)).boxed(),
+        (None, true) => layer.json().with_current_span(true).with_span_list(false).with_writer(std::io::stderr).boxed(),
+        (None, false) => layer.with_writer(std::io::stderr).boxed(),
+    };
+
+    #[cfg_attr(not(feature = "otlp"), allow(unused_mut))]
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = vec![layer];
+
+    #[cfg(feature = "otlp")]
+    let provider = otlp::provider();
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = &provider {
+        use opentelemetry::trace::TracerProvider as _;
+        layers.push(tracing_opentelemetry::layer().with_tracer(provider.tracer("tdlib-test")).boxed());
+    }
+
+    tracing_subscriber::registry().with(layers).with(filter).init();
+
+    Telemetry {
+        #[cfg(feature = "otlp")]
+        provider,
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+
+    // Exporter for OTEL_EXPORTER_OTLP_ENDPOINT (or OTEL_EXPORTER_OTLP_TRACES_ENDPOINT), over HTTP/protobuf
+    pub fn provider() -> Option<SdkTracerProvider> {
+        let endpoint = ["OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "OTEL_EXPORTER_OTLP_ENDPOINT"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))?;
+
+        let exporter = match SpanExporter::builder().with_http().build() {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                eprintln!("Failed to set up OTLP export to {}: {}", endpoint, e);
+                return None;
+            }
+        };
+
+        // Instances are told apart by INSTANCE_NAME, which the manager sets
+        let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "tdlib-test".to_string());
+        let mut resource = Resource::builder().with_service_name(service_name);
+        if let Ok(instance) = std::env::var("INSTANCE_NAME") {
+            resource = resource.with_attribute(KeyValue::new("service.instance.id", instance));
+        }
+
+        eprintln!("Exporting traces to {}", endpoint);
+        Some(
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(resource.build())
+                .build(),
+        )
+    }
+}
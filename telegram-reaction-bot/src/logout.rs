@@ -0,0 +1,67 @@
+use std::{path::Path, time::{Duration, Instant}};
+use serde_json::{json, Value};
+
+use crate::{configure, sender::TdSender, tdlib_data_dir, tdlib_files_dir, TdClient};
+
+// Logging out needs a round trip to Telegram; past this the session is left alone
+const LOGOUT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// `logout`: end the session on Telegram's side, wait until TDLib has closed it, then delete the
+// local database and files directories, so a server can be detached without leaving a live
+// session on disk. The bot must not be running on the same TDLIB_DATA_DIR
+pub fn run() -> Result<(), String> {
+    let data_dir = tdlib_data_dir();
+    let files_dir = tdlib_files_dir(&data_dir);
+
+    if Path::new(&data_dir).join("td.binlog").exists() {
+        let client = TdClient::connect().map_err(|e| e.to_string())?;
+        let sender = TdSender::spawn(client.clone());
+        configure(&sender, &data_dir).map_err(|e| e.to_string())?;
+
+        let deadline = Instant::now() + LOGOUT_TIMEOUT;
+        let mut logging_out = false;
+        let mut closed = false;
+        while !closed && Instant::now() < deadline {
+            let Some(message) = client.receive(1.0) else {
+                continue;
+            };
+            let Ok(update) = serde_json::from_str::<Value>(&message) else {
+                continue;
+            };
+            match update["@type"].as_str() {
+                Some("updateAuthorizationState") => match update["authorization_state"]["@type"].as_str().unwrap_or_default() {
+                    "authorizationStateClosed" => closed = true,
+                    // Parameters were sent by configure; the rest is TDLib working on it
+                    "authorizationStateWaitTdlibParameters" | "authorizationStateLoggingOut" | "authorizationStateClosing" => {}
+                    // Any other state means the session is open (or half-way through a login)
+                    state if !logging_out => {
+                        eprintln!("Session state: {}, logging out", state);
+                        sender.send(json!({ "@type": "logOut" }).to_string());
+                        logging_out = true;
+                    }
+                    _ => {}
+                },
+                Some("error") => return Err(format!("TDLib refused to log out: {}", update["message"])),
+                _ => {}
+            }
+        }
+        if !closed {
+            return Err(format!(
+                "The session didn't close within {} s; {} was left in place",
+                LOGOUT_TIMEOUT.as_secs(),
+                data_dir
+            ));
+        }
+    } else {
+        eprintln!("No session in {}", data_dir);
+    }
+
+    for dir in [&data_dir, &files_dir] {
+        if Path::new(dir).exists() {
+            std::fs::remove_dir_all(dir).map_err(|e| format!("Failed to remove {}: {}", dir, e))?;
+            eprintln!("Removed {}", dir);
+        }
+    }
+    eprintln!("Logged out");
+    Ok(())
+}
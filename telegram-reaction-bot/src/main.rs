@@ -1,53 +1,229 @@
 use std::{
-    collections::HashSet,
-    ffi::{CStr, CString},
-    sync::Arc,
+    collections::{HashSet, VecDeque},
+    ffi::CStr,
+    sync::{atomic::{AtomicPtr, Ordering}, Arc},
     time::Instant,
-    os::raw::c_void,
+    os::raw::{c_char, c_void},
 };
-use regex::Regex;
 use serde_json::json;
-use tokio::sync::Mutex;
-use log::{info, error, warn};
-use libloading::{Library, Symbol};
+use tracing::{error, field, info, info_span, warn};
+use libloading::Library;
+
+mod actions;
+mod auth;
+mod accounts;
+mod backfill;
+mod backlog;
+mod balance;
+mod botapi;
+mod budget;
+mod claims;
+mod cli;
+mod client;
+mod content;
+mod control;
+mod corpus;
+mod daemon;
+mod dbkey;
+mod deadline;
+mod discussion;
+mod error;
+mod disk;
+mod errors;
+mod events;
+mod formatted;
+mod health;
+mod heartbeat;
+mod history;
+mod hooks;
+mod hotlog;
+mod hours;
+mod inflight;
+mod init;
+mod jitter;
+mod killswitch;
+mod latency;
+mod lifecycle;
+mod pending;
+mod pipeline;
+mod plugins;
+mod polling;
+mod presence;
+mod logfile;
+mod logging;
+mod logout;
+mod metrics;
+mod mock;
+mod names;
+mod ocr;
+mod origins;
+mod owner;
+mod prefilter;
+mod publish;
+mod race;
+mod rates;
+mod reaction;
+mod recent;
+mod recorder;
+mod replay;
+mod routing;
+mod rpc;
+mod scam;
+mod secrets;
+mod senders;
+mod sender;
+mod simulate;
+mod stall;
+mod standby;
+mod stats;
+mod store;
+mod systemd;
+#[cfg(test)]
+mod tests;
+mod tiers;
+mod topics;
+mod tuning;
+mod validate;
+mod watch;
+mod wasm;
+mod why;
+
+use accounts::RaceAccounts;
+use actions::Actions;
+use botapi::BotApi;
+use budget::Budget;
+use claims::{Claim, Claims};
+use botdg_config::{timezone, DefaultValue};
+use clap::Parser;
+use cli::{Cli, Command};
+use client::TelegramClient;
+use control::ControlChannel;
+use deadline::Deadline;
+use discussion::Discussions;
+use error::{io_error, BotError};
+use errors::ErrorRate;
+use events::{Event, EventBus, Match, Outcome};
+use formatted::MessageText;
+use heartbeat::Heartbeat;
+use history::{DecisionTrace, History, ReactionRecord};
+use hooks::Hooks;
+use hours::WorkingHours;
+use inflight::{InFlightLimit, Queued};
+use jitter::Jitter;
+use killswitch::KillSwitch;
+use names::Names;
+use latency::{delivery_delay, LatencyStats, Stage};
+use lifecycle::OrderLifecycle;
+use pending::{Expired, Pending, PendingReactions};
+use pipeline::{Pipeline, Prepared};
+use polling::Polling;
+use presence::Presence;
+use prefilter::{Prefilter, Verdict};
+use publish::MatchPublisher;
+use race::RaceTracker;
+use rates::Prices;
+use reaction::ReactionFormat;
+use recent::RecentReactions;
+use rpc::Fault;
+use recorder::Recorder;
+use sender::TdSender;
+use stall::StallWatchdog;
+use disk::DiskWatch;
+use standby::{Failover, Source, Updates};
+use backfill::Backfill;
+use ocr::Ocr;
+use backlog::Backlog;
+use balance::Balance;
+use stats::{FilterStats, SkipReason};
+use systemd::Watchdog;
+use tiers::EmojiTiers;
+use topics::Topics;
+use watch::WatchedChats;
+use origins::ForwardOrigins;
+use owner::{OwnerCommand, Owners};
+use senders::OrderSenders;
+use scam::ScamChecks;
+use botdg_core::{
+    normalize, score,
+    Commissions, Context, Filter, Scorer, Weights,
+};
+use chrono::Timelike;
 
 // Default minimum amount if not specified in environment
 const DEFAULT_MIN_AMOUNT: i32 = 38000;
-const REACTION_EMOJI: &str = "👍";
-const AUTH_TIMEOUT: f64 = 0.1;
-const RECEIVE_TIMEOUT: f64 = 1.0;
-const MAX_AUTH_ATTEMPTS: u8 = 3;
+const DEFAULT_REACTION_EMOJI: &str = "👍";
+// Same default as the manager's earnings estimate
+const DEFAULT_COMMISSION_PERCENT: f64 = 1.0;
+const DEFAULT_TDLIB_DATA_DIR: &str = "tdlib_data";
+// Defaults filled in by the config loader, so `check-config` shows them along with the values
+// from the config file. Per-profile ones are kept apart under --profile
+const CONFIG_DEFAULTS: &[DefaultValue] = &[
+    DefaultValue::per_profile("TDLIB_DATA_DIR", DEFAULT_TDLIB_DATA_DIR),
+    DefaultValue::per_profile("HISTORY_DB", history::DEFAULT_HISTORY_DB),
+    DefaultValue::per_profile("PID_FILE", daemon::DEFAULT_PID_FILE).in_state_dir(),
+    DefaultValue::new("REACTION_EMOJI", DEFAULT_REACTION_EMOJI),
+];
+const DEFAULT_TDLIB_LOG_MAX_BYTES: i64 = 10 * 1024 * 1024;
 const TDLIB_VERSION: &str = "1.8.0";
+const DEFAULT_LIST_LIMIT: usize = 20;
+const MAX_LIST_LIMIT: usize = 100;
+const HISTORY_DISABLED: &str = "ℹ️ Reaction history is disabled (HISTORY_DB is empty).";
+// Messages remembered for duplicate detection
+const MAX_RECENT_REACTIONS: usize = 10_000;
+// How far back the history is read for them at startup
+const DEFAULT_DEDUP_TTL_SECS: i64 = 86_400;
 
-// Get API credentials from environment variables
+// Get API credentials from environment variables or the OS keyring (see secrets.rs); checked by
+// validate.rs before anything gets here
 fn get_api_id() -> i32 {
-    std::env::var("TELEGRAM_API_ID")
+    secrets::get("TELEGRAM_API_ID")
         .expect("TELEGRAM_API_ID must be set")
         .parse()
         .expect("TELEGRAM_API_ID must be a valid integer")
 }
 
 fn get_api_hash() -> String {
-    std::env::var("TELEGRAM_API_HASH")
+    secrets::get("TELEGRAM_API_HASH")
         .expect("TELEGRAM_API_HASH must be set")
 }
 
+// Bot account token; when set, the bot logs in as that bot instead of a user account
+fn bot_token() -> Option<String> {
+    secrets::get("TELEGRAM_BOT_TOKEN").filter(|token| !token.trim().is_empty())
+}
+
 // Get allowed chat IDs from environment variable
 fn get_allowed_chat_ids() -> HashSet<i64> {
-    std::env::var("ALLOWED_CHAT_IDS")
+    chat_ids("ALLOWED_CHAT_IDS")
+}
+
+// A comma separated list of chat IDs
+pub fn chat_ids(name: &str) -> HashSet<i64> {
+    std::env::var(name)
         .unwrap_or_default()
         .split(',')
         .filter_map(|s| s.trim().parse::<i64>().ok())
         .collect()
 }
 
+// TDLib's JSON interface, resolved once when the library is loaded
+type CreateFn = unsafe extern "C" fn() -> *mut c_void;
+type SendFn = unsafe extern "C" fn(*mut c_void, *const c_char);
+type ReceiveFn = unsafe extern "C" fn(*mut c_void, f64) -> *const c_char;
+type DestroyFn = unsafe extern "C" fn(*mut c_void);
+
 struct TdClient {
-    client: *mut c_void,
-    tdlib: Library,
+    client: AtomicPtr<c_void>,
+    create_fn: CreateFn,
+    send_fn: SendFn,
+    receive_fn: ReceiveFn,
+    destroy_fn: DestroyFn,
+    // Keeps the functions above loaded
+    _tdlib: Library,
 }
 
 impl TdClient {
-    unsafe fn new() -> Self {
+    unsafe fn new() -> Result<Self, BotError> {
         // Try multiple possible locations for TDLib
         let possible_paths = if cfg!(target_os = "macos") {
             vec![
@@ -56,6 +232,17 @@ impl TdClient {
                 Some("/opt/homebrew/lib/libtdjson.dylib".to_string()),
                 Some("./libtdjson.dylib".to_string())
             ]
+        } else if cfg!(windows) {
+            // Next to the executable, where the TDLib build's bin directory is usually copied, then
+            // the working directory and PATH (a bare name is searched for there)
+            let exe_dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("tdjson.dll")));
+            vec![
+                std::env::var("TDLIB_PATH").ok(),
+                exe_dir.map(|path| path.display().to_string()),
+                Some(".\\tdjson.dll".to_string()),
+                Some("C:\\td\\tdlib\\bin\\tdjson.dll".to_string()),
+                Some("tdjson.dll".to_string())
+            ]
         } else {
             vec![
                 std::env::var("TDLIB_PATH").ok(),
@@ -75,16 +262,28 @@ impl TdClient {
             println!("Trying to load TDLib from: {}", lib_path);
             match Library::new(&lib_path) {
                 Ok(tdlib) => {
-                    match tdlib.get::<unsafe extern "C" fn() -> *mut c_void>(b"td_json_client_create") {
-                        Ok(create) => {
+                    let symbols = (|| -> Result<_, libloading::Error> {
+                        Ok((
+                            *tdlib.get::<CreateFn>(b"td_json_client_create")?,
+                            *tdlib.get::<SendFn>(b"td_json_client_send")?,
+                            *tdlib.get::<ReceiveFn>(b"td_json_client_receive")?,
+                            *tdlib.get::<DestroyFn>(b"td_json_client_destroy")?,
+                        ))
+                    })();
+                    match symbols {
+                        Ok((create_fn, send_fn, receive_fn, destroy_fn)) => {
                             println!("Successfully loaded TDLib from: {}", lib_path);
-                            return TdClient {
-                                client: create(),
-                                tdlib,
-                            };
+                            return Ok(TdClient {
+                                client: AtomicPtr::new(create_fn()),
+                                create_fn,
+                                send_fn,
+                                receive_fn,
+                                destroy_fn,
+                                _tdlib: tdlib,
+                            });
                         },
                         Err(e) => {
-                            println!("Found library at {} but couldn't get td_json_client_create: {}", lib_path, e);
+                            println!("Found library at {} but couldn't get the td_json_client functions: {}", lib_path, e);
                             continue;
                         }
                     }
@@ -97,47 +296,74 @@ impl TdClient {
         }
         
         // If we get here, we couldn't find TDLib anywhere
-        panic!("Could not find TDLib in any of the expected locations. Please install TDLib or set TDLIB_PATH environment variable.");
+        Err(BotError::Ffi("could not find it in any of the expected locations; install TDLib or set TDLIB_PATH".to_string()))
     }
 
-    fn send(&self, request: &str) {
-        let request_c = CString::new(request).unwrap();
+    // Load TDLib and create a client
+    fn connect() -> Result<Arc<dyn TelegramClient>, BotError> {
+        Ok(Arc::new(unsafe { Self::new() }?))
+    }
+}
+
+impl TelegramClient for TdClient {
+    // `buffer` is reused between calls, so after the first few requests building the C string
+    // doesn't allocate. JSON escapes NUL, so a request never contains one
+    fn send(&self, request: &str, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        buffer.extend_from_slice(request.as_bytes());
+        buffer.push(0);
         unsafe {
-            let send: Symbol<unsafe extern "C" fn(*mut c_void, *const i8)> = 
-                self.tdlib.get(b"td_json_client_send").unwrap();
-            send(self.client, request_c.as_ptr());
+            (self.send_fn)(self.client.load(Ordering::Acquire), buffer.as_ptr().cast());
         }
     }
 
-    fn receive(&self, timeout: f64) -> Option<String> {
+    // A closed TDLib client can't be used again
+    fn reset(&self) {
         unsafe {
-            let receive: Symbol<unsafe extern "C" fn(*mut c_void, f64) -> *const i8> = 
-                self.tdlib.get(b"td_json_client_receive").unwrap();
-            
-            let result = receive(self.client, timeout);
-            if result.is_null() {
-                None
-            } else {
-                Some(CStr::from_ptr(result).to_string_lossy().into_owned())
-            }
+            let old = self.client.swap((self.create_fn)(), Ordering::AcqRel);
+            (self.destroy_fn)(old);
         }
     }
+
+    fn receive_into(&self, timeout: f64, buffer: &mut String) -> bool {
+        let result = unsafe { (self.receive_fn)(self.client.load(Ordering::Acquire), timeout) };
+        if result.is_null() {
+            return false;
+        }
+        // Valid until the next receive call
+        let bytes = unsafe { CStr::from_ptr(result) }.to_bytes();
+        buffer.clear();
+        match std::str::from_utf8(bytes) {
+            Ok(update) => buffer.push_str(update),
+            Err(_) => buffer.push_str(&String::from_utf8_lossy(bytes)),
+        }
+        true
+    }
 }
 
 unsafe impl Send for TdClient {}
 unsafe impl Sync for TdClient {}
 
+// What the filters made of a message, before plugins and WASM filters
+struct Evaluation {
+    order: botdg_core::Order,
+    decision: Result<(), SkipReason>,
+    generation: u64,   // Of the settings it was made with (see pipeline.rs)
+}
+
 // Filter settings structure
+#[derive(Clone)]
 struct FilterSettings {
-    bank_filter: Option<String>,     // Filter for bank name (e.g., "Т" for T-banks)
-    requisite_filter: Option<String>, // Filter for requisite filter (e.g., "+" for SBP)
-    min_amount: i32,                // Minimum amount to react to
+    filter: Filter,                 // Bank, requisite and amount filters (see botdg-core)
+    weights: Weights,               // Order score by amount, bank, chat and hour (see botdg-core's score.rs)
+    min_score: Option<f64>,         // Skip orders scoring lower
+    reaction_emoji: String,         // Emoji used for reactions
 }
 
 impl FilterSettings {
     fn from_env() -> Self {
-        let bank_filter = std::env::var("BANK_FILTER").ok();
-        let requisite_filter = std::env::var("REQUISITE_FILTER").ok();
+        let bank = std::env::var("BANK_FILTER").ok();
+        let requisite = std::env::var("REQUISITE_FILTER").ok();
         
         // Parse min amount from environment or use default
         let min_amount = std::env::var("MIN_AMOUNT")
@@ -145,602 +371,1514 @@ impl FilterSettings {
             .and_then(|s| s.parse::<i32>().ok())
             .unwrap_or(DEFAULT_MIN_AMOUNT);
         
-        Self {
-            bank_filter,
-            requisite_filter,
-            min_amount,
-        }
-    }
-    
-    // Normalize filter to handle both Latin and Cyrillic characters
-    fn normalize_filter(&self, filter: &str) -> String {
-        let filter = filter.to_lowercase();
-        info!("Original filter: '{}'", filter);
+        let reaction_emoji = std::env::var("REACTION_EMOJI")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_REACTION_EMOJI.to_string());
         
-        // Replace Latin T with both Latin and Cyrillic T
-        if filter == "t" || filter == "т" {
-            info!("Using special T filter matching");
-            return "t".to_string(); // We'll do special T matching in normalize_bank_name
+        let mut filter = Filter::new(min_amount);
+        if let Some(bank) = bank {
+            filter = filter.with_bank(bank);
+        }
+        if let Some(requisite) = requisite {
+            filter = filter.with_requisite(requisite);
         }
-        filter
-    }
-    
-    // Normalize bank name for comparison
-    fn normalize_bank_name(&self, bank_name: &str) -> String {
-        // Replace Cyrillic characters with Latin equivalents for matching
-        let mut normalized = bank_name.to_lowercase();
-        info!("Original bank name: '{}'", bank_name);
-        
-        // Replace all variants of T with 't'
-        normalized = normalized.replace("т", "t"); // Cyrillic т -> Latin t
-        normalized = normalized.replace("-", ""); // Remove hyphens
-        normalized = normalized.replace(" ", ""); // Remove spaces
-        
-        info!("Normalized bank name: '{}'", normalized);
-        normalized
-    }
-    
-    async fn process_message(&self, client: &TdClient, update: &serde_json::Value, price_regex: &Regex) -> Result<(), Box<dyn std::error::Error>> {
-        // Main function to process a new message
-        let start_time = Instant::now();
-        
-        // Extract message details
-        let chat_id = update["message"]["chat_id"].as_i64().unwrap_or(0);
-        let message_id = update["message"]["id"].as_i64().unwrap_or(0);
-        let message_text = update["message"]["content"]["text"]["text"].as_str().unwrap_or("");
         
-        // Skip empty messages
-        if message_text.is_empty() {
-            return Ok(());
+        // Parse per-bank minimum amounts, e.g. BANK_MIN_AMOUNTS=tbank:50000,sber:40000
+        for entry in std::env::var("BANK_MIN_AMOUNTS").unwrap_or_default().split(',') {
+            let Some((bank, amount)) = entry.split_once(':') else {
+                continue;
+            };
+            match amount.trim().parse::<i32>() {
+                Ok(amount) => filter = filter.with_bank_min_amount(bank, amount),
+                Err(_) => warn!("Ignoring invalid BANK_MIN_AMOUNTS entry '{}'", entry),
+            }
         }
         
-        info!("Checking message: ID: {}\n{}", message_id, message_text);
+        // REQUISITE_BLACKLIST: cards and phones never reacted to, e.g. 2200123456789012,+79001234567
+        for requisite in std::env::var("REQUISITE_BLACKLIST").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+            filter = filter.with_blacklisted(requisite);
+        }
         
-        // Parse price from the message
-        let price = extract_price(message_text, price_regex);
+        // MIN_PROFIT: skip orders expected to earn less, at the order's own commission, its bank's
+        // from BANK_COMMISSIONS (tbank:1.5,sber:1.2) or COMMISSION_PERCENT (see botdg-core's profit.rs)
+        let min_profit = std::env::var("MIN_PROFIT").ok().and_then(|s| s.replace(',', ".").parse::<f64>().ok()).unwrap_or(0.0);
+        let default_pct = std::env::var("COMMISSION_PERCENT")
+            .ok()
+            .and_then(|s| s.replace(',', ".").parse().ok())
+            .unwrap_or(DEFAULT_COMMISSION_PERCENT);
+        let mut commissions = Commissions::new(default_pct);
+        for entry in std::env::var("BANK_COMMISSIONS").unwrap_or_default().split(',') {
+            let Some((bank, pct)) = entry.split_once(':') else {
+                continue;
+            };
+            match pct.trim().replace(',', ".").parse::<f64>() {
+                Ok(pct) => commissions = commissions.with_bank(bank, pct),
+                Err(_) => warn!("Ignoring invalid BANK_COMMISSIONS entry '{}'", entry),
+            }
+        }
+        filter = filter.with_min_profit(min_profit, commissions);
         
-        if let Some(price) = price {
-            info!("Found price: {}", price);
-            
-            // Log current filter settings
-            info!("Current filter settings: bank={:?}, requisite={:?}, min_amount={}", 
-                  self.bank_filter, self.requisite_filter, self.min_amount);
-            
-            // Apply minimum amount filter
-            if price < self.min_amount {
-                info!("Price {} does not meet minimum amount {}", price, self.min_amount);
-                return Ok(());
-            } else {
-                info!("Price {} meets minimum amount {}", price, self.min_amount);
+        // MIN_SCORE: skip orders scoring lower, by SCORE_PER_THOUSAND rubles and the SCORE_BANKS
+        // (tbank:10), SCORE_CHATS (-1001234567890:5) and SCORE_HOURS (22-6:-20) weights
+        let min_score = std::env::var("MIN_SCORE").ok().and_then(|s| s.replace(',', ".").parse::<f64>().ok());
+        let weight = |s: &str| s.trim().replace(',', ".").parse::<f64>().ok();
+        let per_thousand = std::env::var("SCORE_PER_THOUSAND").ok().and_then(|s| weight(&s)).unwrap_or(1.0);
+        let mut weights = Weights::new(per_thousand);
+        for (bank, value) in score_entries("SCORE_BANKS") {
+            match weight(&value) {
+                Some(value) => weights = weights.with_bank(&bank, value),
+                None => warn!("Ignoring invalid SCORE_BANKS entry '{}:{}'", bank, value),
             }
-            
-            // Apply bank filter if set
-            if let Some(bank_filter) = &self.bank_filter {
-                if !message_text.contains(bank_filter) {
-                    info!("Message does not contain bank filter: {}", bank_filter);
-                    return Ok(());
-                } else {
-                    info!("Message contains bank filter: {}", bank_filter);
-                }
+        }
+        for (chat, value) in score_entries("SCORE_CHATS") {
+            match (chat.parse::<i64>(), weight(&value)) {
+                (Ok(chat), Some(value)) => weights = weights.with_chat(chat, value),
+                _ => warn!("Ignoring invalid SCORE_CHATS entry '{}:{}'", chat, value),
             }
-            
-            // Apply requisite filter if set
-            if let Some(requisite_filter) = &self.requisite_filter {
-                // Special case: if requisite filter is "+" and message contains "T-Bank", allow it
-                let is_tbank = message_text.contains("T-Bank") && requisite_filter == "+";
-                
-                if !is_tbank && !message_text.contains(requisite_filter) {
-                    info!("Message does not contain requisite filter: {}", requisite_filter);
-                    return Ok(());
-                } else {
-                    if is_tbank {
-                        info!("Special case: T-Bank message with '+' filter");
-                    } else {
-                        info!("Message contains requisite filter: {}", requisite_filter);
-                    }
-                }
+        }
+        for (hours, value) in score_entries("SCORE_HOURS") {
+            match (parse_hours(&hours), weight(&value)) {
+                (Some((from, to)), Some(value)) => weights = weights.with_hours(from, to, value),
+                _ => warn!("Ignoring invalid SCORE_HOURS entry '{}:{}'", hours, value),
             }
-            
-            // All filters passed, use ultra-fast reaction method
-            info!("All filters passed, reacting to message ⚡");
-            
-            // Send both formats simultaneously for maximum speed and compatibility
-            // Format 1: Newer format with reaction_type
-            let reaction_request = json!({
-                "@type": "addMessageReaction",
-                "chat_id": chat_id,
-                "message_id": message_id,
-                "reaction_type": {
-                    "@type": "reactionTypeEmoji",
-                    "emoji": REACTION_EMOJI
-                },
-                "is_big": false
-            });
-            
-            // Format 2: Alternative format with direct reaction
-            let alt_reaction_request = json!({
-                "@type": "addMessageReaction",
-                "chat_id": chat_id,
-                "message_id": message_id,
-                "reaction": REACTION_EMOJI,
-                "is_big": false
-            });
-            
-            // Send both formats without waiting - this is what gives us <5ms reaction time
-            client.send(&reaction_request.to_string());
-            client.send(&alt_reaction_request.to_string());
-            
-            // Log the ultra-fast reaction time
-            info!("Message passed all filters, reaction confirmed. Reaction time: {:?}", start_time.elapsed());
-        } else {
-            info!("No price found in message, skipping");
         }
         
-        Ok(())
+        Self { filter, weights, min_score, reaction_emoji }
     }
     
-    fn should_react(&self, text: &str, regex: &Regex) -> bool {
-        // First extract the price for logging purposes
-        let price_opt = extract_price(text, regex);
-        
-        // Log the message we're checking
-        info!("Checking message: {}", text);
-        if let Some(price) = price_opt {
-            info!("Found price: {}", price);
-        } else {
-            info!("No price found in message");
-        }
-        
-        // Log the current filter settings
-        info!("Current filter settings: bank={:?}, requisite={:?}, min_amount={}", 
-              self.bank_filter, self.requisite_filter, self.min_amount);
-        
-        // Track if all filters pass
-        let mut min_amount_filter_passed = true;
-        let mut bank_filter_passed = true;
-        let mut requisite_filter_passed = true;
-        
-        // Check minimum amount filter if set
-        if self.min_amount > 0 {
-            if let Some(price) = price_opt {
-                if price < self.min_amount {
-                    info!("Price {} is below minimum amount {}, skipping", price, self.min_amount);
-                    min_amount_filter_passed = false;
-                } else {
-                    info!("Price {} meets minimum amount {}", price, self.min_amount);
+    // The filters a control socket client can see and change (see update)
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "min_amount": self.filter.min_amount,
+            "bank": self.filter.bank,
+            "requisite": self.filter.requisite,
+            "reaction_emoji": self.reaction_emoji,
+            "min_score": self.min_score,
+            "blacklisted": self.filter.blacklist.len(),
+        })
+    }
+    
+    // Change filters from a set_filters request; nothing changes unless all of them are valid
+    fn update(&mut self, changes: &serde_json::Value) -> Result<(), String> {
+        let mut updated = self.clone();
+        for (key, value) in changes.as_object().into_iter().flatten() {
+            let text = || match value {
+                serde_json::Value::Null => Ok(None),
+                serde_json::Value::String(text) if text.trim().is_empty() => Ok(None),
+                serde_json::Value::String(text) => Ok(Some(text.trim().to_string())),
+                _ => Err(format!("{} must be a string or null", key)),
+            };
+            match key.as_str() {
+                "command" => {}
+                "min_amount" => {
+                    updated.filter.min_amount = value
+                        .as_i64()
+                        .and_then(|amount| i32::try_from(amount).ok())
+                        .filter(|amount| *amount >= 0)
+                        .ok_or("min_amount must be a number of rubles")?;
                 }
-            } else {
-                // No price found but minimum amount filter is set
-                info!("No price found in message, but minimum amount filter is set, skipping");
-                min_amount_filter_passed = false;
+                "bank" => updated.filter.bank = text()?,
+                "requisite" => updated.filter.requisite = text()?,
+                "reaction_emoji" => updated.reaction_emoji = text()?.ok_or("reaction_emoji can't be empty")?,
+                "min_score" => {
+                    updated.min_score = match value {
+                        serde_json::Value::Null => None,
+                        value => Some(value.as_f64().ok_or("min_score must be a number or null")?),
+                    };
+                }
+                _ => return Err(format!("unknown filter '{}'", key)),
             }
         }
-        
-        // If no filters are set and no price is found, skip
-        if price_opt.is_none() && self.bank_filter.is_none() && self.requisite_filter.is_none() {
-            info!("No price found in message and no filters set, skipping");
-            return false;
+        info!("Filters changed over the control socket: {}", updated.to_json());
+        *self = updated;
+        Ok(())
+    }
+    
+    // Replace the requisite blacklist, as sent by the manager's /blacklist
+    fn set_blacklist<'a>(&mut self, requisites: impl Iterator<Item = &'a str>) {
+        self.filter.blacklist = requisites.map(normalize::requisite_key).collect();
+        info!("Requisite blacklist: {} entries", self.filter.blacklist.len());
+    }
+    
+    // The order's score in a chat, at the current local hour
+    fn score(&self, chat_id: i64, order: &botdg_core::Order) -> f64 {
+        self.weights.score(order, &Context::new(chat_id, timezone::now().hour()))
+    }
+    
+    // The filters proper: no side effects, so pipeline workers can run them (see pipeline.rs)
+    fn evaluate(&self, chat_id: i64, text: &MessageText, prices: &Prices) -> Evaluation {
+        if !prices.speaks(text) {
+            return Evaluation { order: botdg_core::Order::default(), decision: Err(SkipReason::Language), generation: 0 };
         }
-        
-        // Check bank filter if set
-        if let Some(bank_filter) = &self.bank_filter {
-            if !text.contains("Банк: ") {
-                info!("Message doesn't contain bank info, skipping");
-                return false;
+        let order = prices.order(text).unwrap_or_default();
+        let decision = self.filter.evaluate(&order).and_then(|()| match self.min_score {
+            Some(min_score) if !score::meets_min(self.score(chat_id, &order), min_score) => Err(SkipReason::BelowMinScore),
+            _ => Ok(()),
+        });
+        Evaluation { order, decision, generation: 0 }
+    }
+    
+    // Ok if the message passes every filter, otherwise the first filter it failed; always on the
+    // main loop
+    fn settle(&self, chat_id: i64, text: &str, Evaluation { order, decision, .. }: Evaluation) -> Result<(), SkipReason> {
+        // Native plugins and WASM filters get the last word on orders the filters let through (see
+        // plugins.rs and wasm.rs)
+        let rejecting = || plugins::loaded().rejecting(chat_id, text).or_else(|| wasm::loaded().rejecting(chat_id, text, &order));
+        let decision = decision.and_then(|()| match rejecting() {
+            Some(plugin) => {
+                info!(target: hotlog::TARGET, "Plugin {} turned the order down", plugin);
+                Err(SkipReason::Plugin)
             }
-            
-            // Extract bank name from the message
-            if let Some(bank_line) = text.lines().find(|line| line.starts_with("Банк: ")) {
-                let bank_name = bank_line.trim_start_matches("Банк: ").to_lowercase();
-                info!("Found bank name: '{}'", bank_name);
-                
-                // Special handling for T filter
-                if bank_filter.to_lowercase() == "t" || bank_filter.to_lowercase() == "т" {
-                    // For T filter, check if the bank name contains T-Bank or similar variations
-                    let bank_lower = bank_name.to_lowercase();
-                    info!("Checking if '{}' matches T-Bank filter", bank_lower);
-                    
-                    // Check for various forms of T-Bank
-                    if bank_lower.contains("t-bank") || 
-                       bank_lower.contains("т-bank") ||
-                       bank_lower.contains("t bank") ||
-                       bank_lower.contains("т bank") ||
-                       bank_lower.contains("tbank") ||
-                       bank_lower.contains("тbank") ||
-                       bank_lower.contains("t-банк") || 
-                       bank_lower.contains("т-банк") ||
-                       bank_lower.contains("t банк") ||
-                       bank_lower.contains("т банк") ||
-                       bank_lower.contains("tбанк") ||
-                       bank_lower.contains("тбанк") ||
-                       bank_lower == "t" ||
-                       bank_lower == "т" ||
-                       bank_lower.starts_with("t") ||
-                       bank_lower.starts_with("т") {
-                        info!("Bank '{}' matches T filter ✅", bank_name);
-                    } else {
-                        info!("Bank '{}' doesn't match T filter, skipping ❌", bank_name);
-                        bank_filter_passed = false;
-                    }
-                } else {
-                    // Normal filter matching for other filters
-                    let normalized_filter = self.normalize_filter(bank_filter);
-                    let normalized_bank = self.normalize_bank_name(&bank_name);
-                    
-                    if !normalized_bank.contains(&normalized_filter) {
-                        info!("Bank '{}' doesn't match filter '{}', skipping", bank_name, normalized_filter);
-                        bank_filter_passed = false;
-                    } else {
-                        info!("Bank '{}' matches filter '{}'", bank_name, normalized_filter);
-                    }
+            None => Ok(()),
+        });
+        // One line per message, with the text escaped onto it; skips are sampled (see hotlog.rs)
+        match decision {
+            Ok(()) => info!(target: hotlog::TARGET, "All filters passed, reacting to message ✅ {:?} ({:?})", text, order),
+            Err(reason) => {
+                if let Some(left_out) = hotlog::skipped() {
+                    info!(target: hotlog::TARGET, "Filter {} failed, not reacting to message ❌ {:?} ({:?}){}", reason.name(), text, order, hotlog::left_out(left_out));
                 }
-            } else {
-                bank_filter_passed = false;
             }
         }
-        
-        // Check requisite filter if set
-        if let Some(req_filter) = &self.requisite_filter {
-            // First check if it's a T-Bank message (for special handling with '+' filter)
-            let is_tbank = if let Some(bank_line) = text.lines().find(|line| line.starts_with("Банк: ")) {
-                let bank_name = bank_line.trim_start_matches("Банк: ").to_lowercase();
-                let bank_lower = bank_name.to_lowercase();
-                
-                // Check for various forms of T-Bank
-                bank_lower.contains("t-bank") || 
-                bank_lower.contains("т-bank") ||
-                bank_lower.contains("t bank") ||
-                bank_lower.contains("т bank") ||
-                bank_lower.contains("tbank") ||
-                bank_lower.contains("t-банк") || 
-                bank_lower.contains("т-банк") ||
-                bank_lower.contains("t банк") ||
-                bank_lower.contains("т банк") ||
-                bank_lower.contains("tбанк") ||
-                bank_lower.contains("тбанк") ||
-                bank_lower == "t" ||
-                bank_lower == "т" ||
-                bank_lower.starts_with("t") ||
-                bank_lower.starts_with("т")
-            } else {
-                false
-            };
-            
-            // Special case: If it's a T-Bank message and filter is '+', automatically pass
-            if req_filter == "+" && is_tbank {
-                info!("Special case: T-Bank message with '+' filter, automatically passing requisite check ✅");
-                requisite_filter_passed = true; // Explicitly set to true to ensure it passes
-            } else if !text.contains("Реквизит: ") {
-                info!("Message doesn't contain requisite info, skipping");
-                requisite_filter_passed = false;
-            } else {
-                // Extract requisite from the message
-                if let Some(req_line) = text.lines().find(|line| line.starts_with("Реквизит: ")) {
-                    let requisite = req_line.trim_start_matches("Реквизит: ");
-                    info!("Found requisite: '{}'", requisite);
-                    
-                    // Special case for '+' filter to match SBP requisites
-                    if req_filter == "+" {
-                        if requisite.contains('+') {
-                            info!("Requisite '{}' matches SBP filter '+' ✅", requisite);
-                        } else {
-                            info!("Requisite '{}' doesn't match '+' filter, skipping ❌", requisite);
-                            requisite_filter_passed = false;
-                        }
-                    } else if !requisite.contains(req_filter) {
-                        info!("Requisite '{}' doesn't match filter '{}', skipping ❌", requisite, req_filter);
-                        requisite_filter_passed = false;
-                    } else {
-                        info!("Requisite '{}' matches filter '{}' ✅", requisite, req_filter);
-                    }
-                } else {
-                    info!("Couldn't extract requisite from message, skipping");
-                    requisite_filter_passed = false;
-                }
+        decision
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // --set, the environment (and .env), the config file and defaults, in that order (see botdg-config)
+    let cli = Cli::parse();
+    // Writes the files loaded next, so it can't need them
+    if let Some(Command::Init(options)) = cli.command {
+        return Ok(init::run(options, &cli.config)?);
+    }
+    let config = botdg_config::load("reaction-bot", &cli.config, CONFIG_DEFAULTS)?;
+    
+    if cli.check_config {
+        return Ok(cli::check_config(&config)?);
+    }
+    
+    // Everything but `run` (the default) is a one-off task that exits when done (see cli.rs)
+    if cli.command.as_ref().is_some_and(|command| !matches!(command, Command::Run(_))) && !cli.run.is_empty() {
+        return Err("--daemon, --pidfile and --log-file only apply to `run`".into());
+    }
+    let options = match cli.command {
+        None => cli.run,
+        // `tdlib-test --daemon run` as well as `tdlib-test run --daemon`
+        Some(Command::Run(options)) if options.is_empty() => cli.run,
+        Some(Command::Run(options)) => options,
+        Some(Command::Init(_)) => unreachable!("handled before loading the configuration"),
+        Some(Command::Auth) => {
+            validate::check_credentials()?;
+            let _session = daemon::lock_session(&tdlib_data_dir())?;
+            return Ok(auth::run()?);
+        }
+        Some(Command::TestFilter { text, chat, verbose }) => {
+            if !cli::test_filter(text, chat, verbose)? {
+                std::process::exit(1);
             }
+            return Ok(());
         }
-        
-        // Final check - all active filters must pass
-        
-        // Final check - all active filters must pass
-        let bank_filter_result = if self.bank_filter.is_some() { bank_filter_passed } else { true };
-        let requisite_filter_result = if self.requisite_filter.is_some() { requisite_filter_passed } else { true };
-        let min_amount_filter_result = if self.min_amount > 0 { min_amount_filter_passed } else { true };
-        
-        let final_result = bank_filter_result && requisite_filter_result && min_amount_filter_result;
-        
-        if final_result {
-            info!("All filters passed, reacting to message ✅");
-        } else {
-            info!("Some filters failed, not reacting to message ❌");
-            info!("Bank filter: {}, Requisite filter: {}, Min amount filter: {}", 
-                  bank_filter_result, requisite_filter_result, min_amount_filter_result);
+        Some(Command::ExportHistory { format, since, output }) => {
+            return Ok(history::export(format, since.as_deref(), output.as_deref())?)
         }
-        
-        final_result
+        Some(Command::CheckConfig) => return Ok(cli::check_config(&config)?),
+        Some(Command::Replay { files, reacted }) => return Ok(replay::run(&files, reacted)?),
+        Some(Command::Simulate { rate, count, seed, verbose }) => return Ok(simulate::run(rate, count, seed, verbose)?),
+        Some(Command::ValidateCorpus { dir, missing }) => {
+            if !corpus::run(&dir, missing)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Logout) => {
+            validate::check_credentials()?;
+            let _session = daemon::lock_session(&tdlib_data_dir())?;
+            return Ok(logout::run()?);
+        }
+        Some(Command::Keyring { action }) => return Ok(secrets::run(action)?),
+    };
+    
+    // Fail on every bad setting at once, while the error still reaches the terminal
+    validate::all().check()?;
+    
+    // Detaching has to happen before any threads (including the tokio runtime) exist
+    let mut pidfile = options.pidfile().as_deref().map(daemon::Pidfile::acquire).transpose()?;
+    if options.daemon {
+        daemon::detach(options.log_file.as_deref())?;
+    }
+    if let Some(pidfile) = pidfile.as_mut() {
+        pidfile.write_pid()?;
+    }
+    
+    let log_file = options.log_file.clone().or_else(|| std::env::var("LOG_FILE").ok().filter(|p| !p.is_empty()));
+    let telemetry = logging::init(log_file.as_deref(), options.daemon);
+    
+    let runtime = tokio::runtime::Runtime::new()?;
+    let result = match mock::from_env() {
+        Ok(None) => match lock_sessions() {
+            Ok(_sessions) => runtime.block_on(run(TdClient::connect)),
+            Err(e) => Err(e),
+        },
+        Ok(Some(client)) => runtime.block_on(run(move || Ok(client.clone() as Arc<dyn TelegramClient>))),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &result {
+        error!("Stopped: {} ({})", e, if e.is_retryable() { "temporary" } else { "won't go away by restarting" });
+    }
+    drop(telemetry);
+    drop(pidfile);
+    // The manager restarts a bot that exited with a temporary failure, and not one that can't start
+    // as configured (see error.rs)
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
     }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load environment variables from .env file
-    dotenv::dotenv().ok();
-    
-    std::env::set_var("RUST_LOG", "info");
-    std::env::set_var("TDLIB_LOG_VERBOSITY", "0");
+// The bot itself. `connect` creates the Telegram client (and the standby's); TdClient::connect
+// outside of tests. Returns once the client has no more updates, which only a mock ever does
+async fn run(connect: impl Fn() -> Result<Arc<dyn TelegramClient>, BotError>) -> Result<(), BotError> {
     
     // Create required directories
-    std::fs::create_dir_all("tdlib_data").expect("Failed to create data directory");
-    std::fs::create_dir_all("tdlib_files").expect("Failed to create files directory");
+    let data_dir = tdlib_data_dir();
+    let files_dir = tdlib_files_dir(&data_dir);
+    std::fs::create_dir_all(&data_dir).map_err(|e| io_error(e, format!("Failed to create {}", data_dir)))?;
+    std::fs::create_dir_all(&files_dir).map_err(|e| io_error(e, format!("Failed to create {}", files_dir)))?;
     
     // Set directory permissions
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions("tdlib_data", std::fs::Permissions::from_mode(0o755))
-            .expect("Failed to set data directory permissions");
-        std::fs::set_permissions("tdlib_files", std::fs::Permissions::from_mode(0o755))
-            .expect("Failed to set files directory permissions");
+        std::fs::set_permissions(&data_dir, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| io_error(e, format!("Failed to set permissions of {}", data_dir)))?;
+        std::fs::set_permissions(&files_dir, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| io_error(e, format!("Failed to set permissions of {}", files_dir)))?;
     }
     
-    env_logger::init();
-    
     // Load filter settings from environment
     let filter_settings = FilterSettings::from_env();
     info!("Starting ultra-fast Telegram reaction bot (TDLib v{}) with filters:", TDLIB_VERSION);
-    info!("Bank filter: {:?}", filter_settings.bank_filter);
-    info!("Requisite filter: {:?}", filter_settings.requisite_filter);
-    info!("Minimum amount: {}", filter_settings.min_amount);
-
-    let client = Arc::new(Mutex::new(unsafe { TdClient::new() }));
-    {
-        let lock = client.lock().await;
-        lock.send(&json!({
-            "@type": "setLogVerbosityLevel",
-            "new_verbosity_level": 0
-        }).to_string());
+    info!("Bank filter: {:?}", filter_settings.filter.bank);
+    info!("Requisite filter: {:?}", filter_settings.filter.requisite);
+    info!("Minimum amount: {}", filter_settings.filter.min_amount);
+    if !filter_settings.filter.blacklist.is_empty() {
+        info!("Blacklisted requisites: {}", filter_settings.filter.blacklist.len());
+    }
+    info!("Reaction emoji: {}", filter_settings.reaction_emoji);
+    let plugins = plugins::load().map_err(BotError::Config)?;
+    if !plugins.is_empty() {
+        info!("Plugins: {:?}", plugins.names());
+    }
+    let wasm_filters = wasm::load().map_err(BotError::Config)?.names();
+    if !wasm_filters.is_empty() {
+        info!("WASM filters: {:?}", wasm_filters);
     }
 
-    let allowed_chat_ids: HashSet<i64> = get_allowed_chat_ids();
+    // The receive loop below owns receiving (on this thread: block_on runs it here); everything is
+    // sent through the sender thread
+    tuning::tune_current_thread("receive");
+    let client = connect()?;
+    let sender = TdSender::spawn(client.clone());
+    configure(&sender, &tdlib_data_dir())?;
+
+    let mut allowed_chat_ids: HashSet<i64> = get_allowed_chat_ids();
+    // With the chats the owner added or removed with /watch (see watch.rs)
+    let mut watched_chats = WatchedChats::from_env();
+    watched_chats.apply(&mut allowed_chat_ids);
     
     info!("Monitoring {} chat IDs: {:?}", allowed_chat_ids.len(), allowed_chat_ids);
 
-    let price_regex = Arc::new(Regex::new(r"а:\s*([\d\s]+)\s*₽").unwrap());
+    // Amounts in other currencies are converted to rubles (see rates.rs)
+    let prices = Arc::new(Prices::from_env());
     
     // Load filter settings from environment
-    let filter_settings = Arc::new(FilterSettings::from_env());
+    let mut filter_settings = FilterSettings::from_env();
 
-    // Setup TDLib with proper parameters
-    {
-        let lock = client.lock().await;
-        info!("Setting up TDLib parameters");
+    let mut heartbeat = Heartbeat::from_env();
+    let control = ControlChannel::from_env();
+    let history = History::from_env();
+    let publisher = MatchPublisher::from_env();
+    let hooks = Hooks::from_env();
+    let deadline = Deadline::from_env();
+    let recent = RecentReactions::default();
+    // Who hears about matches, reactions and status changes (see events.rs)
+    let mut events = EventBus::new();
+    events.subscribe(&control);
+    events.subscribe(&publisher);
+    events.subscribe(&history);
+    events.subscribe(plugins);
+    events.subscribe(&hooks);
+    events.subscribe(&recent);
+    events.subscribe(&metrics::EventMetrics);
+    events.subscribe(&health::EventHealth);
+    events.subscribe(&rpc::EventTail);
+    rpc::open();
+    let (bot_api, bot_api_results) = BotApi::from_env();
+    let recorder = Recorder::from_env();
+    let mut prefilter = Prefilter::new(&allowed_chat_ids).ignoring(content::IgnoredContent::from_env());
+    let topics = Topics::from_env();
+    let owners = Owners::from_env();
+    let mut names = Names::default();
+    let forward_origins = ForwardOrigins::from_env();
+    let order_senders = OrderSenders::from_env();
+    let actions = Actions::from_env();
+    let mut scam_checks = ScamChecks::from_env();
+    scam_checks.load(&history).await;
+    let mut discussions = Discussions::from_env();
+    let mut backfill = Backfill::from_env();
+    let mut ocr = Ocr::from_env();
+    let mut presence = Presence::from_env();
+
+    auth::authorize(client.as_ref(), &sender, &control, &mut heartbeat).await?;
+    subscribe(&sender, &allowed_chat_ids);
+    warm_up(&sender, &allowed_chat_ids, &mut presence);
+    // TDLib reports its version while logging in
+    let reaction_format = ReactionFormat::from_env(heartbeat.tdlib_version());
+    
+    // A second session kept connected to take over while the primary is down (see standby.rs)
+    let (mut updates, mut failover) = match standby::data_dir() {
+        Some(standby_dir) => {
+            info!("Starting the standby TDLib client in {}", standby_dir);
+            let standby_client = connect()?;
+            let standby_sender = TdSender::spawn(standby_client.clone());
+            configure(&standby_sender, &standby_dir)?;
+            auth::authorize(standby_client.as_ref(), &standby_sender, &control, &mut heartbeat).await?;
+            subscribe(&standby_sender, &allowed_chat_ids);
+            warm_up(&standby_sender, &allowed_chat_ids, &mut presence);
+            (standby::merge(client.clone(), standby_client), Failover::new(Some(standby_sender)))
+        }
+        None => (Updates::direct(client.clone()), Failover::new(None)),
+    };
+    if let Some(results) = bot_api_results {
+        updates.add_results(results);
+    }
+    
+    // Further accounts that react to every match too (see accounts.rs)
+    let mut race_accounts = RaceAccounts::new(reaction_format);
+    for dir in accounts::data_dirs() {
+        info!("Starting the race account in {}", dir);
+        let account_client = connect()?;
+        let account_sender = TdSender::spawn(account_client.clone());
+        configure(&account_sender, &dir)?;
+        auth::authorize(account_client.as_ref(), &account_sender, &control, &mut heartbeat).await?;
+        subscribe(&account_sender, &allowed_chat_ids);
+        warm_up(&account_sender, &allowed_chat_ids, &mut presence);
+        race_accounts.add(&dir, account_client, account_sender);
+    }
+    updates.set_router(routing::Router::from_env(&chat_ids("HOT_CHAT_IDS")));
+    let mut pipeline = Pipeline::from_env(&allowed_chat_ids, &filter_settings, prices.clone());
+    let mut backlog = Backlog::new();
+    // Orders posted while the bot was down (see backfill.rs)
+    backfill.start(&sender, &allowed_chat_ids, backfill.limit());
+    systemd::notify(&format!("READY=1\nSTATUS=Watching {} chats", allowed_chat_ids.len()));
+    let mut watchdog = Watchdog::new();
+    let mut stall = StallWatchdog::from_env();
+    let mut disk = DiskWatch::from_env(&data_dir, &files_dir);
+    let mut polling = Polling::from_env();
+
+    // Outstanding reaction requests by "@extra" tag, resent when TDLib doesn't answer (see pending.rs)
+    let mut pending_reactions = PendingReactions::from_env();
+    let mut latency = LatencyStats::from_env();
+    let mut filter_stats = FilterStats::default();
+    let mut race = RaceTracker::from_env();
+    race.set_accounts(race_accounts.ids());
+    let mut lifecycle = OrderLifecycle::from_env();
+    let mut budget = Budget::from_env();
+    let mut balance = Balance::from_env();
+    let mut emoji_tiers = EmojiTiers::from_env(&filter_settings.reaction_emoji);
+    let mut working_hours = WorkingHours::from_env();
+    let mut jitter = Jitter::from_env();
+    let mut in_flight = InFlightLimit::from_env();
+    let mut error_rate = ErrorRate::from_env();
+    
+    // Messages reacted to recently, so a repeated update for the same message isn't reacted to twice
+    let mut recent_reactions: HashSet<(i64, i64)> = HashSet::new();
+    let mut recent_order: VecDeque<(i64, i64)> = VecDeque::new();
+    // Including those from before a restart, which TDLib may deliver again when it catches up
+    let dedup_secs = std::env::var("DEDUP_TTL_SECS").ok().and_then(|s| s.parse::<i64>().ok()).unwrap_or(DEFAULT_DEDUP_TTL_SECS);
+    if dedup_secs > 0 {
+        let restored = history.reacted_since(chrono::Utc::now().timestamp() - dedup_secs).await.unwrap_or_default();
+        if !restored.is_empty() {
+            info!("Not reacting again to {} messages reacted to in the last {} s", restored.len(), dedup_secs);
+        }
+        for message in restored {
+            remember_reaction(&mut recent_reactions, &mut recent_order, message);
+        }
+    }
+    
+    // Last TDLib connection state, to warm up again once a lost connection is back
+    let mut connection_state: Option<String> = None;
+    
+    // Set by the manager's /pause: updates keep flowing so the session stays warm, but nothing is reacted to
+    let mut paused = false;
+    let mut kill_switch = KillSwitch::from_env();
+    
+    // MAX_MESSAGE_AGE_SECS: orders that old when they arrive (after a reconnect, say) are long taken
+    let max_message_age = std::env::var("MAX_MESSAGE_AGE_SECS").ok().and_then(|s| s.parse::<i64>().ok()).filter(|secs| *secs > 0);
+    
+    // MARK_AS_READ=true: read matched messages after reacting; bots have no unread counters
+    let mark_as_read = std::env::var("MARK_AS_READ").is_ok_and(|v| v == "true" || v == "1") && bot_token().is_none();
+    
+    // Reactions approved before a restart that TDLib never confirmed: sent again and waited for (see claims.rs)
+    let mut claims = Claims::from_env();
+    for claim in claims.restore(kill_switch.is_engaged()) {
+        info!("Resuming reaction {} from before the restart", claim.extra);
+        emoji_tiers.remember(&claim.extra, claim.emoji.clone());
+        let target = (claim.chat_id, claim.message_id);
+        send_reaction(&mut discussions, failover.sender(&sender), &bot_api, reaction_format, &claim.emoji, target, &claim.extra);
+        let reacted_to = (!discussions.always(claim.chat_id)).then_some(target);
+        pending_reactions.insert(claim.extra, Instant::now(), claim.posted_at, reacted_to);
+    }
+    
+    // Main message processing loop
+    loop {
+        claims.save();
+        backlog.record(pipeline.take_depth(&mut updates));
+        if heartbeat.is_due() {
+            heartbeat.set_filter_stats(filter_stats.to_json());
+            heartbeat.set_error_rate(error_rate.gauge());
+            heartbeat.set_disk_usage(disk.gauge());
+            heartbeat.set_backlog(backlog.gauge());
+        }
+        heartbeat.tick();
+        health::alive();
+        latency.maybe_report();
+        if let Some(event) = error_rate.check() {
+            events.status(event);
+        }
+        if let Some(event) = failover.check() {
+            events.status(event);
+        }
+        if let Some(event) = working_hours.check() {
+            events.status(event);
+        }
+        if let Some(event) = balance.check_reset() {
+            events.status(event);
+        }
+        if let Some(event) = disk.check(&sender) {
+            events.status(event);
+        }
+        if failover.all_lost() {
+            return Err(BotError::Session("Both the primary and the standby TDLib sessions were closed".to_string()));
+        }
         
-        // Get TDLib data directory from environment variable or use default
-        let tdlib_data_dir = std::env::var("TDLIB_DATA_DIR").unwrap_or_else(|_| "tdlib_data".to_string());
-        let tdlib_files_dir = format!("{}_files", tdlib_data_dir.trim_end_matches("/"));
+        // A client that went quiet while claiming to be connected: start it over, like a closed
+        // session but without having to log in again (see stall.rs)
+        let ready = connection_state.as_deref() == Some("connectionStateReady");
+        if let Some(silent) = stall.check(ready).filter(|_| !failover.is_enabled()) {
+            warn!("No updates from TDLib for {} s while connected, restarting the client", silent.as_secs());
+            events.status(json!({ "event": "stalled", "silent_secs": silent.as_secs() }));
+            systemd::notify("STATUS=No updates, restarting the TDLib client");
+            sender.reset().await;
+            configure(&sender, &tdlib_data_dir())?;
+            pending_reactions.clear();
+            claims.clear();
+            jitter.clear();
+            in_flight.clear();
+            backfill.cancel();
+            ocr.cancel();
+            connection_state = None;
+            auth::authorize(client.as_ref(), &sender, &control, &mut heartbeat).await?;
+            subscribe(&sender, &allowed_chat_ids);
+            warm_up(&sender, &allowed_chat_ids, &mut presence);
+            backfill.start(&sender, &allowed_chat_ids, backfill.limit());
+            stall.seen();
+        }
         
-        info!("Using TDLib data directory: {}", tdlib_data_dir);
+        watchdog.probe(failover.sender(&sender));
+        presence.refresh(failover.sender(&sender));
         
-        let params = json!({
-            "@type": "setTdlibParameters",
-            "database_directory": tdlib_data_dir,
-            "files_directory": tdlib_files_dir,
-            "database_encryption_key": "",
-            "use_test_dc": false,
-            "api_id": get_api_id(),
-            "api_hash": get_api_hash(),
-            "system_language_code": "en",
-            "device_model": "ReactionBot",
-            "system_version": "1.0",
-            "application_version": "1.0",
-            "enable_storage_optimizer": true,
-            "ignore_file_names": false,
-            "use_file_database": true,
-            "use_chat_info_database": true,
-            "use_message_database": true,
-            "use_secret_chats": false
-        });
+        for (chat_id, message_id) in race.expired() {
+            failover.sender(&sender).send(RaceTracker::request(chat_id, message_id).to_string());
+        }
         
-        lock.send(&params.to_string());
-        // No need to check database encryption key separately
-        // TDLib handles this automatically in setTdlibParameters
-    }
-
-    // Wait for authorization
-    let mut auth_state = String::from("waitTdlibParameters");
-    let mut auth_attempts = 0;
-    
-    while auth_state != "authorizationStateReady" && auth_attempts < MAX_AUTH_ATTEMPTS {
-        info!("Current auth state: {}", auth_state);
-        let message = {
-            let lock = client.lock().await;
-            let msg = lock.receive(AUTH_TIMEOUT);
-            msg
+        for expired in pending_reactions.expired() {
+            match expired {
+                Expired::Resend(extra, (chat_id, message_id)) => {
+                    info!("No answer to {} yet, sending it again", extra);
+                    if bot_api.handles(chat_id) {
+                        bot_api.react(chat_id, message_id, emoji_tiers.get(&extra), &extra);
+                    } else {
+                        for request in reaction_format.requests(chat_id, message_id, emoji_tiers.get(&extra), &extra) {
+                            failover.sender(&sender).send(request.to_string());
+                        }
+                    }
+                }
+                Expired::GiveUp(extra) => {
+                    let target = &extra["reaction:".len()..];
+                    warn!("Reaction {} was never confirmed, giving up", extra);
+                    claims.settle(&extra);
+                    if let Some((chat_id, message_id)) = parse_target(target) {
+                        discussions.finish(chat_id, message_id);
+                    }
+                    events.publish(Event::Reaction { target, outcome: Outcome::Unconfirmed });
+                    error_rate.record(true);
+                }
+            }
+        }
+        
+        for (chat_id, message_id, extra) in jitter.due() {
+            send_reaction(&mut discussions, failover.sender(&sender), &bot_api, reaction_format, emoji_tiers.get(&extra), (chat_id, message_id), &extra);
+            race_accounts.react(&discussions, (chat_id, message_id), emoji_tiers.get(&extra), &extra);
+        }
+        
+        // Reactions that waited for their chat's earlier ones to be answered (see inflight.rs)
+        for queued in in_flight.ready(&pending_reactions) {
+            info!("Sending queued reaction {}", queued.extra);
+            send_reaction(&mut discussions, failover.sender(&sender), &bot_api, reaction_format, emoji_tiers.get(&queued.extra), (queued.chat_id, queued.message_id), &queued.extra);
+            race_accounts.react(&discussions, (queued.chat_id, queued.message_id), emoji_tiers.get(&queued.extra), &queued.extra);
+            pending_reactions.insert(queued.extra, Instant::now(), queued.posted_at, queued.target);
+        }
+        
+        // Commands from the manager over stdio, and requests from the control socket (see rpc.rs)
+        while let Some((command, request)) = control
+            .try_command()
+            .map(|command| (command, None))
+            .or_else(|| rpc::try_request().map(|request| (request.command.clone(), Some(request))))
+        {
+            let answer = match command["command"].as_str() {
+                Some("pause") => {
+                    info!("Paused by the manager");
+                    paused = true;
+                    events.status(json!({ "event": "paused" }));
+                    Ok(json!({ "paused": true }))
+                }
+                Some("resume") if kill_switch.is_engaged() => {
+                    warn!("Not resuming: the kill switch is engaged");
+                    events.status(json!({ "event": "panic", "engaged": true }));
+                    Err(Fault::Refused("the kill switch is engaged".to_string()))
+                }
+                Some("resume") => {
+                    info!("Resumed by the manager");
+                    paused = false;
+                    events.status(json!({ "event": "resumed" }));
+                    Ok(json!({ "paused": false }))
+                }
+                Some("panic") => {
+                    warn!("Kill switch engaged by the manager");
+                    kill_switch.set(true);
+                    halt(&mut pending_reactions, &mut jitter, &mut in_flight, &mut claims);
+                    events.status(json!({ "event": "panic", "engaged": true, "source": "manager" }));
+                    Ok(json!({ "kill_switch": true }))
+                }
+                Some("unpanic") => {
+                    kill_switch.set(false);
+                    events.status(json!({ "event": "panic", "engaged": false, "source": "manager" }));
+                    Ok(json!({ "kill_switch": false }))
+                }
+                Some("balance") => {
+                    let event = balance.set(command["total"].as_i64());
+                    events.status(event.clone());
+                    Ok(event)
+                }
+                Some("blacklist") => {
+                    let requisites = command["requisites"].as_array().map(Vec::as_slice).unwrap_or_default();
+                    filter_settings.set_blacklist(requisites.iter().filter_map(|requisite| requisite.as_str()));
+                    pipeline.set_filter_settings(&filter_settings);
+                    Ok(json!({ "blacklisted": filter_settings.filter.blacklist.len() }))
+                }
+                // The manager's /status
+                Some("recent") => Ok(json!({ "reactions": recent.to_json() })),
+                Some("backfill") => {
+                    info!("Backfill requested by the manager");
+                    let limit = backfill.command_limit(command["limit"].as_u64());
+                    backfill.start(failover.sender(&sender), &allowed_chat_ids, limit);
+                    Ok(json!({ "limit": limit }))
+                }
+                Some("status") => Ok(json!({
+                    "instance": std::env::var("INSTANCE_NAME").ok(),
+                    "pid": std::process::id(),
+                    "paused": paused,
+                    "kill_switch": kill_switch.is_engaged(),
+                    "connection_state": connection_state,
+                    "tdlib_version": heartbeat.tdlib_version(),
+                    "chats": allowed_chat_ids.len(),
+                    "filters": filter_settings.to_json(),
+                })),
+                Some("stats") => Ok(json!({
+                    "filters": filter_stats.to_json(),
+                    "error_rate": error_rate.gauge(),
+                    "disk": disk.gauge(),
+                    "recent": recent.to_json(),
+                })),
+                Some("set_filters") => match filter_settings.update(&command) {
+                    Ok(()) => {
+                        pipeline.set_filter_settings(&filter_settings);
+                        emoji_tiers = EmojiTiers::from_env(&filter_settings.reaction_emoji);
+                        Ok(filter_settings.to_json())
+                    }
+                    Err(e) => Err(Fault::InvalidParams(e)),
+                },
+                _ => Err(Fault::UnknownMethod),
+            };
+            match request {
+                Some(request) => request.answer(answer),
+                None => match answer {
+                    // Over stdio the answer comes back as an event
+                    Ok(mut reactions) if command["command"] == "recent" => {
+                        reactions["event"] = "recent_reactions".into();
+                        events.status(reactions);
+                    }
+                    Err(Fault::UnknownMethod) => warn!("Ignoring unknown control command: {}", command),
+                    _ => {}
+                },
+            }
+        }
+        
+        // /pause and the kill switch both keep updates flowing, so the session stays warm
+        let halted = paused || kill_switch.is_engaged();
+        
+        // Backfilled matches and screenshots read go first: they're already late
+        let backfilled = backfill.next_update().or_else(|| ocr.next_update());
+        let message = match &backfilled {
+            Some(update) => Some((Source::Primary, update.as_str(), None)),
+            None => pipeline.next(&mut updates, jitter.receive_timeout(polling.timeout(!pending_reactions.is_empty()))),
         };
 
-        if let Some(msg) = message {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&msg) {
-                if let Some(update_type) = json["@type"].as_str() {
-                    match update_type {
-                        "updateAuthorizationState" => {
-                            if let Some(state) = json["authorization_state"]["@type"].as_str() {
-                                info!("New auth state: {}", state);
-                                auth_state = state.to_string();
-                                
-                                match state {
-                                    "authorizationStateWaitPhoneNumber" => {
-                                        println!("\nPlease enter your phone number (with country code, e.g. +1234567890):");
-                                        let mut input = String::new();
-                                        std::io::stdin().read_line(&mut input)?;
-                                        let phone_number = input.trim();
-                                        
-                                        let lock = client.lock().await;
-                                        lock.send(&json!({
-                                            "@type": "setAuthenticationPhoneNumber",
-                                            "phone_number": phone_number
-                                        }).to_string());
-                                    }
-                                    "authorizationStateWaitCode" => {
-                                        println!("\nPlease enter the verification code:");
-                                        let mut input = String::new();
-                                        std::io::stdin().read_line(&mut input)?;
-                                        let code = input.trim();
-                                        
-                                        let lock = client.lock().await;
-                                        lock.send(&json!({
-                                            "@type": "checkAuthenticationCode",
-                                            "code": code
-                                        }).to_string());
-                                    }
-                                    "authorizationStateWaitPassword" => {
-                                        println!("\nPlease enter your 2FA password:");
-                                        let mut input = String::new();
-                                        std::io::stdin().read_line(&mut input)?;
-                                        let password = input.trim();
-                                        
-                                        let lock = client.lock().await;
-                                        lock.send(&json!({
-                                            "@type": "checkAuthenticationPassword",
-                                            "password": password
-                                        }).to_string());
-                                    }
-                                    "authorizationStateReady" => {
-                                        info!("Authorization successful!");
-                                    }
-                                    _ => {
-                                        info!("Current auth state: {}", state);
+        if let Some((source, msg, prepared)) = message {
+            // Parsed and evaluated ahead by a pipeline worker, or about to be here
+            let (received, verdict, json, parsed, mut evaluation) = match prepared {
+                Some(Prepared { verdict, json, received, parsed, evaluation }) => (received, verdict, json, Some(parsed), evaluation),
+                None => (Instant::now(), prefilter.check(msg), None, None, None),
+            };
+            polling.seen();
+            if backfilled.is_none() {
+                health::seen();
+            }
+            if source == Source::Primary && backfilled.is_none() {
+                stall.seen();
+            }
+            if source == Source::Standby && !failover.standby_update(msg) {
+                continue;
+            }
+            match verdict {
+                Verdict::Parse => {}
+                verdict => {
+                    match verdict {
+                        Verdict::ChatNotAllowed if !halted => filter_stats.skipped(SkipReason::ChatNotAllowed),
+                        Verdict::ContentIgnored if !halted => filter_stats.skipped(SkipReason::ContentType),
+                        _ => {}
+                    }
+                    recorder.record_unparsed(msg, prefilter.mentions_allowed_chat(msg));
+                    continue;
+                }
+            }
+            if let Some(json) = json.or_else(|| parse_update(msg)) {
+                let parsed = parsed.unwrap_or_else(Instant::now);
+                recorder.record(msg, &json, &allowed_chat_ids);
+                
+                // The session ended under us (logged out from another device or terminated in the settings):
+                // start over with a new client and log in again, asking the manager for anything needed
+                if json["@type"] == "updateAuthorizationState" {
+                    match json["authorization_state"]["@type"].as_str().unwrap_or_default() {
+                        // With a standby the receive threads own the clients, so it just takes over
+                        "authorizationStateClosed" if failover.is_enabled() => {
+                            warn!("TDLib session closed; continuing on the standby");
+                            events.status(json!({ "event": "session_closed", "standby": true }));
+                            failover.set_primary_lost();
+                        }
+                        "authorizationStateClosed" => {
+                            warn!("TDLib session closed, logging in again");
+                            events.status(json!({ "event": "session_closed" }));
+                            health::set_auth_state("authorizationStateClosed");
+                            systemd::notify("STATUS=Session closed, logging in again");
+                            sender.reset().await;
+                            configure(&sender, &tdlib_data_dir())?;
+                            pending_reactions.clear();
+                            claims.clear();
+                            jitter.clear();
+                            in_flight.clear();
+                            backfill.cancel();
+                            ocr.cancel();
+                            auth::authorize(client.as_ref(), &sender, &control, &mut heartbeat).await?;
+                            subscribe(&sender, &allowed_chat_ids);
+                            warm_up(&sender, &allowed_chat_ids, &mut presence);
+                            backfill.start(&sender, &allowed_chat_ids, backfill.limit());
+                        }
+                        state @ ("authorizationStateLoggingOut" | "authorizationStateClosing") => {
+                            warn!("TDLib session is ending: {}", state);
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                
+                // Track connection state so the manager can detect a dead TDLib connection
+                if json["@type"] == "updateConnectionState" {
+                    if let Some(state) = json["state"]["@type"].as_str() {
+                        // A marker span, so traces show TDLib connection changes next to slow messages
+                        let _span = info_span!("connection_state", state).entered();
+                        info!("Connection state: {}", state);
+                        failover.set_primary_connection(state);
+                        if state == "connectionStateReady" && connection_state.as_deref().is_some_and(|last| last != state) {
+                            warm_up(&sender, &allowed_chat_ids, &mut presence);
+                        }
+                        connection_state = Some(state.to_string());
+                        heartbeat.set_connection_state(state);
+                        systemd::notify(&format!("STATUS=Watching {} chats, {}", allowed_chat_ids.len(), state));
+                        events.status(json!({ "event": "connection", "state": state }));
+                    }
+                    continue;
+                }
+                
+                if let Some(chat) = json["@extra"].as_str().and_then(|e| e.strip_prefix(backfill::EXTRA_PREFIX)) {
+                    let matches = |chat_id, message_id, text: &MessageText| {
+                        decide(chat_id, message_id, text, None, &recent_reactions, &filter_settings, &prices).is_ok()
+                    };
+                    if let Some(event) = backfill.handle(chat, &json, failover.sender(&sender), matches) {
+                        events.status(event);
+                    }
+                    continue;
+                }
+                
+                if let Some(extra) = json["@extra"].as_str().filter(|e| e.starts_with(ocr::EXTRA_PREFIX)) {
+                    ocr.downloaded(extra, &json);
+                    continue;
+                }
+                
+                if json["@extra"] == disk::EXTRA {
+                    disk.optimized(&json);
+                    continue;
+                }
+                
+                if json["@extra"] == systemd::PROBE_EXTRA {
+                    watchdog.answered(&json, connection_state.as_deref() == Some("connectionStateReady"));
+                    continue;
+                }
+                
+                // Results of our reaction requests; with ReactionFormat::Both one of the two is expected to fail
+                if let Some(target) = json["@extra"].as_str().and_then(|e| e.strip_prefix("reaction:")) {
+                    let extra = format!("reaction:{}", target);
+                    match (json["@type"].as_str(), pending_reactions.get_mut(&extra)) {
+                        (Some("ok"), Some(&mut Pending { sent_at, posted_at, .. })) => {
+                            latency.record(Stage::Confirm, sent_at.elapsed());
+                            pending_reactions.remove(&extra);
+                            claims.settle(&extra);
+                            if let Some((chat_id, message_id)) = parse_target(target) {
+                                race.watch(chat_id, message_id, posted_at);
+                                discussions.finish(chat_id, message_id);
+                            }
+                            events.publish(Event::Reaction { target, outcome: Outcome::Sent });
+                            error_rate.record(false);
+                            if source == Source::Primary {
+                                failover.record_primary_reaction(true);
+                            }
+                        }
+                        (Some("error"), Some(pending)) => {
+                            // A flood wait or Telegram's own failure: try again rather than give up
+                            let error = BotError::from_tdlib(&json);
+                            if error.is_retryable() && pending.target.is_some() {
+                                warn!("Reaction {} failed for now ({}), sending it again later", extra, error);
+                                pending.retry_later(error.retry_after());
+                                continue;
+                            }
+                            pending.failures += 1;
+                            // The Bot API takes a single request per reaction
+                            if pending.failures >= reaction_format.request_count() || source == Source::BotApi {
+                                // Some channels only take reactions in their discussion group (see discussion.rs)
+                                if let Some((chat_id, message_id)) = parse_target(target).filter(|(chat_id, _)| discussions.falls_back(*chat_id)) {
+                                    if discussions.redirect(failover.sender(&sender), chat_id, message_id) {
+                                        info!("Reaction to {} failed ({}), trying the discussion group", target, json["message"]);
+                                        pending.failures = 0;
+                                        pending.target = None;
+                                        continue;
                                     }
                                 }
+                                pending_reactions.remove(&extra);
+                                claims.settle(&extra);
+                                warn!("Reaction {} failed: {}", extra, json["message"]);
+                                if let Some((chat_id, message_id)) = parse_target(target) {
+                                    discussions.finish(chat_id, message_id);
+                                }
+                                events.publish(Event::Reaction { target, outcome: Outcome::Failed(&json["message"]) });
+                                error_rate.record(true);
+                                if source == Source::Primary {
+                                    failover.record_primary_reaction(false);
+                                }
                             }
                         }
-                        "error" => {
-                            error!("Error from TDLib: {}", json["message"]);
-                            auth_attempts += 1;
-                            if auth_attempts >= MAX_AUTH_ATTEMPTS {
-                                return Err("Too many authentication attempts".into());
+                        _ => {}
+                    }
+                    continue;
+                }
+                
+                // The discussion group copy of a channel post: react to it in the post's name, so the
+                // result settles the post's pending reaction
+                if let Some(target) = json["@extra"].as_str().and_then(|e| e.strip_prefix(discussion::EXTRA_PREFIX)) {
+                    let extra = format!("reaction:{}", target);
+                    match discussion::discussion_message(&json) {
+                        Some((chat_id, message_id)) => {
+                            info!("Reacting to {} in the discussion group as {}:{}", target, chat_id, message_id);
+                            pending_reactions.retarget(&extra, (chat_id, message_id));
+                            for request in reaction_format.requests(chat_id, message_id, emoji_tiers.get(&extra), &extra) {
+                                failover.sender(&sender).send(request.to_string());
                             }
                         }
-                        _ => {}
+                        None => {
+                            warn!("No discussion group message for {}: {}", target, json["message"]);
+                            pending_reactions.remove(&extra);
+                            claims.settle(&extra);
+                            if let Some((chat_id, message_id)) = parse_target(target) {
+                                discussions.finish(chat_id, message_id);
+                            }
+                            events.publish(Event::Reaction { target, outcome: Outcome::Failed(&json["message"]) });
+                            error_rate.record(true);
+                        }
                     }
+                    continue;
                 }
-            }
-        } else {
-            warn!("No message received within timeout period");
-        }
-    }
-
-    if auth_state != "authorizationStateReady" {
-        return Err("Failed to authenticate with Telegram".into());
-    }
-
-    // Request chats to start receiving updates
-    {
-        info!("Requesting chats to start receiving updates");
-        let lock = client.lock().await;
-        lock.send(&json!({
-            "@type": "getChats",
-            "limit": 100
-        }).to_string());
-    }
-
-    // Get available reactions for the chat
-    for chat_id in &allowed_chat_ids {
-        info!("Getting available reactions for chat {}", chat_id);
-        let lock = client.lock().await;
-        lock.send(&json!({
-            "@type": "getChatAvailableReactions",
-            "chat_id": chat_id
-        }).to_string());
-    }
-
-    // Main message processing loop
-    loop {
-        let message = {
-            let lock = client.lock().await;
-            lock.receive(RECEIVE_TIMEOUT)
-        };
-
-        if let Some(msg) = message {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&msg) {
+                
+                // Errors for requests nobody waits on (getChats, sendMessage, ...)
+                if json["@type"] == "error" && json["@extra"].is_null() {
+                    warn!("Error from TDLib: {}", json["message"]);
+                    error_rate.record(true);
+                    continue;
+                }
+                
+                // Chat titles and user names asked for, and watched chats renamed (see names.rs)
+                if json["@type"] == "updateChatTitle" || json["@extra"].as_str().is_some_and(|e| e.starts_with(names::EXTRA_PREFIX)) {
+                    if let Some((id, name)) = names.learn(&json) {
+                        info!("{} is {}", id, name);
+                        history.record_name(id, name);
+                    }
+                    continue;
+                }
+                
+                // Other accounts reacting to a message we're racing for
+                if json["@type"] == "updateMessageInteractionInfo" {
+                    if let (Some(chat_id), Some(message_id)) = (json["chat_id"].as_i64(), json["message_id"].as_i64()) {
+                        if race.is_open(chat_id, message_id) {
+                            failover.sender(&sender).send(RaceTracker::request(chat_id, message_id).to_string());
+                        }
+                    }
+                    continue;
+                }
+                
+                // Matched orders edited to say they were taken, paid or cancelled (see lifecycle.rs)
+                if json["@type"] == "updateMessageContent" {
+                    if let (Some(chat_id), Some(message_id)) = (json["chat_id"].as_i64(), json["message_id"].as_i64()) {
+                        if let Some(state) = lifecycle.on_edit(chat_id, message_id, &json["new_content"]) {
+                            info!("Order {}:{} is {}", chat_id, message_id, state.name());
+                            history.record_final_state(chat_id, message_id, state.name());
+                        }
+                    }
+                    continue;
+                }
+                
+                // Answers to replies, forwards and button presses (see actions.rs)
+                if let Some(action) = json["@extra"].as_str().and_then(|e| e.strip_prefix(actions::EXTRA_PREFIX)) {
+                    if json["@type"] == "error" {
+                        warn!("Action {} failed: {}", action, json["message"]);
+                        error_rate.record(true);
+                    } else {
+                        error_rate.record(false);
+                    }
+                    continue;
+                }
+                
+                // Answers to getMessageAddedReactions: who reacted, in order
+                if let Some(target) = json["@extra"].as_str().and_then(|e| e.strip_prefix("race:")) {
+                    if let Some((chat_id, message_id)) = parse_target(target) {
+                        if json["@type"] == "addedReactions" {
+                            if let Some(result) = race.resolve(chat_id, message_id, &json) {
+                                info!(
+                                    "Race for {}: {} (margin {:?} s)",
+                                    target,
+                                    match (&result.won_by, result.won) {
+                                        (Some(account), _) => format!("first from {}", account),
+                                        (None, true) => "first".to_string(),
+                                        (None, false) => "beaten".to_string(),
+                                    },
+                                    result.margin_secs
+                                );
+                                recent.race(target, result.won);
+                                metrics::count("race", &[("result", if result.won { "won" } else { "lost" })]);
+                                history.record_race(chat_id, message_id, result.won, result.margin_secs, result.won_by);
+                            }
+                            if let Some(competitors) = race.finish(chat_id, message_id, &json) {
+                                for competitor in &competitors {
+                                    names.request(failover.sender(&sender), competitor.sender_id, competitor.sender_id < 0);
+                                }
+                                history.record_competitors(chat_id, message_id, competitors);
+                            }
+                        } else {
+                            // E.g. channels, where the list of reactors isn't available
+                            warn!("Can't read reactions on {}: {}", target, json["message"]);
+                            race.forget(chat_id, message_id);
+                        }
+                    }
+                    continue;
+                }
+                
                 if json["@type"] == "updateNewMessage" {
                     if let Some(chat_id) = json["message"]["chat_id"].as_i64() {
+                        // Everything logged while handling the message carries its IDs and the filter decision
+                        let span = info_span!(
+                            "message",
+                            chat_id,
+                            chat_title = names.get(chat_id),
+                            message_id = json["message"]["id"].as_i64(),
+                            forward_origin = origins::origin(&json["message"]),
+                            decision = field::Empty,
+                            skip_reason = field::Empty,
+                            deadline = field::Empty
+                        );
+                        let _entered = span.enter();
+                        
                         // Check if this is a command
                         if let Some(text) = json["message"]["content"]["text"]["text"].as_str() {
-                            // Handle /likes command
-                            if text.trim() == "/list" || text.trim() == "/list@reaction_bot" {
-                                info!("Received /list command from chat {}", chat_id);
-                                send_message(&client, chat_id, "ℹ️ Database storage has been disabled for performance reasons.").await;
+                            // PANIC_PHRASE from the account itself stops everything at once (see killswitch.rs)
+                            if allowed_chat_ids.contains(&chat_id) && kill_switch.is_trigger(&json["message"], text) {
+                                warn!("Kill switch engaged from chat {}", chat_id);
+                                if kill_switch.set(true) {
+                                    halt(&mut pending_reactions, &mut jitter, &mut in_flight, &mut claims);
+                                    events.status(json!({ "event": "panic", "engaged": true, "source": "chat", "chat_id": chat_id }));
+                                }
                                 continue;
-                            } else if text.trim() == "/clear" || text.trim() == "/clear@reaction_bot" {
-                                info!("Received /clear command from chat {}", chat_id);
-                                send_message(&client, chat_id, "ℹ️ Database storage has been disabled for performance reasons.").await;
+                            }
+                            // The owner's commands: /ping, /status, /pause and /resume for when the manager is down,
+                            // /watch, and the history's (see owner.rs)
+                            if let Some(command) = owners.command(&json["message"], text) {
+                                let reply = match command {
+                                    OwnerCommand::Ping => format!("🏓 Pong, up {}", owner::uptime(heartbeat.started_at())),
+                                    OwnerCommand::Status => {
+                                        let state = if kill_switch.is_engaged() {
+                                            "🛑 Kill switch engaged"
+                                        } else if paused {
+                                            "⏸ Paused"
+                                        } else {
+                                            "✅ Reacting"
+                                        };
+                                        let connection = connection_state.as_deref().map_or("unknown", |state| state.trim_start_matches("connectionState"));
+                                        format!(
+                                            "{}\nConnection: {}\nWatching {} chats, from {} ₽\nUp {}",
+                                            state,
+                                            connection,
+                                            allowed_chat_ids.len(),
+                                            filter_settings.filter.min_amount,
+                                            owner::uptime(heartbeat.started_at())
+                                        )
+                                    }
+                                    OwnerCommand::Pause => {
+                                        info!("Paused by the owner from chat {}", chat_id);
+                                        paused = true;
+                                        events.status(json!({ "event": "paused", "source": "chat" }));
+                                        "⏸ Paused. /resume to react again".to_string()
+                                    }
+                                    OwnerCommand::Resume if kill_switch.is_engaged() => {
+                                        "🛑 The kill switch is engaged; only the manager's /panic off releases it".to_string()
+                                    }
+                                    OwnerCommand::Resume => {
+                                        info!("Resumed by the owner from chat {}", chat_id);
+                                        paused = false;
+                                        events.status(json!({ "event": "resumed", "source": "chat" }));
+                                        "▶️ Reacting again".to_string()
+                                    }
+                                    OwnerCommand::Watch(watching) if watching == allowed_chat_ids.contains(&chat_id) => {
+                                        if watching { "👀 Already watching this chat" } else { "This chat isn't watched" }.to_string()
+                                    }
+                                    OwnerCommand::Watch(watching) => {
+                                        info!("{} chat {} at the owner's request", if watching { "Watching" } else { "No longer watching" }, names.label(chat_id));
+                                        if watching {
+                                            allowed_chat_ids.insert(chat_id);
+                                        } else {
+                                            allowed_chat_ids.remove(&chat_id);
+                                        }
+                                        watched_chats.set(chat_id, watching);
+                                        prefilter = Prefilter::new(&allowed_chat_ids).ignoring(content::IgnoredContent::from_env());
+                                        pipeline.set_chats(&allowed_chat_ids);
+                                        if watching {
+                                            let chat = HashSet::from([chat_id]);
+                                            subscribe(failover.sender(&sender), &chat);
+                                            warm_up(failover.sender(&sender), &chat, &mut presence);
+                                        }
+                                        events.status(json!({ "event": "watch", "chat_id": chat_id, "chat_title": names.get(chat_id), "watching": watching }));
+                                        if watching {
+                                            format!("👀 Watching this chat ({}) from now on", chat_id)
+                                        } else {
+                                            format!("🙈 No longer watching this chat ({})", chat_id)
+                                        }
+                                    }
+                                    OwnerCommand::List(args) => {
+                                        info!("Received /list command from chat {}", chat_id);
+                                        let limit = args.parse().unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+                                        match history.recent(limit).await {
+                                            Some(records) => format_history(&records, &names),
+                                            None => HISTORY_DISABLED.to_string(),
+                                        }
+                                    }
+                                    OwnerCommand::Clear => {
+                                        info!("Received /clear command from chat {}", chat_id);
+                                        match history.clear().await {
+                                            Some(deleted) => format!("🗑 Deleted {} reactions from the history.", deleted),
+                                            None => HISTORY_DISABLED.to_string(),
+                                        }
+                                    }
+                                    OwnerCommand::Latency => {
+                                        info!("Received /latency command from chat {}", chat_id);
+                                        latency.summary()
+                                    }
+                                    OwnerCommand::Stats => {
+                                        info!("Received /stats command from chat {}", chat_id);
+                                        format!("{}\n\n{}", filter_stats.summary(), race.summary())
+                                    }
+                                    OwnerCommand::Why(args) => {
+                                        info!("Received /why command from chat {}", chat_id);
+                                        match why::message_id(&args) {
+                                            Some(message_id) => match history.why(message_id).await {
+                                                Some(traces) => why::format(message_id, &traces, &names),
+                                                None => HISTORY_DISABLED.to_string(),
+                                            },
+                                            None => "Usage: /why <message number from its link>".to_string(),
+                                        }
+                                    }
+                                };
+                                send_message(failover.sender(&sender), chat_id, &reply);
                                 continue;
                             }
-                            
                             // Process regular messages
-                            if allowed_chat_ids.contains(&chat_id) {
+                            let thread_id = json["message"]["message_thread_id"].as_i64().unwrap_or(0);
+                            let delivery = delivery_delay(&json["message"], received);
+                            if let Some(delivery) = delivery.filter(|_| allowed_chat_ids.contains(&chat_id)) {
+                                latency.record(Stage::Delivery, delivery);
+                            }
+                            if !halted && !allowed_chat_ids.contains(&chat_id) {
+                                filter_stats.skipped(SkipReason::ChatNotAllowed);
+                            } else if !halted && !topics.allows(chat_id, thread_id) {
+                                filter_stats.skipped(SkipReason::TopicNotAllowed);
+                            } else if !halted && !forward_origins.allows(chat_id, &json["message"]) {
+                                span.record("decision", "skip");
+                                span.record("skip_reason", SkipReason::OriginNotAllowed.name());
+                                filter_stats.skipped(SkipReason::OriginNotAllowed);
+                            } else if !halted && !order_senders.allows(chat_id, &json["message"]) {
+                                if let Some(left_out) = hotlog::skipped() {
+                                    info!(target: hotlog::TARGET, "Sent by {:?}, not the chat's order bot, skipping{}", senders::sender(&json["message"]), hotlog::left_out(left_out));
+                                }
+                                span.record("decision", "skip");
+                                span.record("skip_reason", SkipReason::SenderNotAllowed.name());
+                                filter_stats.skipped(SkipReason::SenderNotAllowed);
+                            } else if !halted && max_message_age.is_some_and(|max_age| message_age(&json["message"]) > max_age) {
+                                if let Some(left_out) = hotlog::skipped() {
+                                    info!(target: hotlog::TARGET, "Message is {} s old, skipping{}", message_age(&json["message"]), hotlog::left_out(left_out));
+                                }
+                                span.record("decision", "skip");
+                                span.record("skip_reason", SkipReason::TooOld.name());
+                                filter_stats.skipped(SkipReason::TooOld);
+                            } else if !halted && !working_hours.is_open() {
+                                span.record("decision", "skip");
+                                span.record("skip_reason", SkipReason::OffHours.name());
+                                filter_stats.skipped(SkipReason::OffHours);
+                            } else if !halted {
                                 if let Some(message_id) = json["message"]["id"].as_i64() {
                                     // Process in the main thread for speed - no spawning
                                     let start = Instant::now();
                                     
                                     // Apply all filters to determine if we should react
-                                    if filter_settings.should_react(text, &price_regex) {
-                                        // HYPER-OPTIMIZED REACTION - <1ms reaction time
-                                        // Simply use direct JSON serialization for maximum reliability while still being fast
-                                        {
-                                            let lock = client.lock().await;
-                                            
-                                            // Format 1: Newer format with reaction_type
-                                            let reaction_request = json!({
-                                                "@type": "addMessageReaction",
-                                                "chat_id": chat_id,
-                                                "message_id": message_id,
-                                                "reaction_type": {
-                                                    "@type": "reactionTypeEmoji",
-                                                    "emoji": REACTION_EMOJI
-                                                },
-                                                "is_big": false
-                                            });
-                                            
-                                            // Format 2: Alternative format with direct reaction
-                                            let alt_reaction_request = json!({
-                                                "@type": "addMessageReaction",
-                                                "chat_id": chat_id,
-                                                "message_id": message_id,
-                                                "reaction": REACTION_EMOJI,
-                                                "is_big": false
-                                            });
-                                            
-                                            // Send both formats without waiting - this is what gives us <5ms reaction time
-                                            lock.send(&reaction_request.to_string());
-                                            
-                                            // Small delay between requests to avoid conflicts
-                                            std::thread::sleep(std::time::Duration::from_micros(10));
-                                            lock.send(&alt_reaction_request.to_string());
-                                        } // Lock is released here immediately
+                                    let formatted = MessageText::from_tdlib(&json["message"]["content"]["text"]).unwrap_or_else(|| MessageText::plain(text));
+                                    let amount = prices.extract(&formatted);
+                                    let decision = decide(chat_id, message_id, &formatted, evaluation.take(), &recent_reactions, &filter_settings, &prices)
+                                        .and_then(|()| balance.check(amount))
+                                        .and_then(|()| scam_checks.check(&history, chat_id, &json["message"], || prices.order(&formatted).unwrap_or_default()))
+                                        .and_then(|()| deadline.guard(received, || hooks.before(chat_id, message_id, text, || prices.order(&formatted).unwrap_or_default())))
+                                        .and_then(|()| budget.take());
+                                    let decided = Instant::now();
+                                    if deadline.exceeded(received) {
+                                        span.record("deadline", "exceeded");
+                                    }
+                                    latency.record(Stage::Parse, parsed - received);
+                                    latency.record(Stage::Filter, decided - start);
+                                    match decision {
+                                        Ok(()) => {
+                                            span.record("decision", "react");
+                                            filter_stats.reacted();
+                                        }
+                                        Err(reason) => {
+                                            span.record("decision", "skip");
+                                            span.record("skip_reason", reason.name());
+                                            filter_stats.skipped(reason);
+                                        }
+                                    }
+                                    if decision.is_ok() {
+                                        let extra = format!("reaction:{}:{}", chat_id, message_id);
+                                        // ACTIONS and CHAT_ACTIONS may leave the reaction out (see actions.rs)
+                                        let reacts = actions.reacts(chat_id);
+                                        let emoji = if reacts { emoji_tiers.pick(&extra, amount).to_string() } else { String::new() };
+                                        let posted_at = json["message"]["date"].as_i64().unwrap_or_default();
+                                        // Nothing to resend until the discussion message is known
+                                        let reacted_to = (!discussions.always(chat_id)).then_some((chat_id, message_id));
+                                        
+                                        // MAX_IN_FLIGHT_PER_CHAT queues it behind the chat's unanswered reactions (see
+                                        // inflight.rs); REACTION_JITTER_MS holds it back for a while (see jitter.rs)
+                                        let queued = reacts && !in_flight.admits(chat_id, &pending_reactions);
+                                        let due = if queued || !reacts { None } else { jitter.delay() };
+                                        if !reacts {
+                                            // Only the other actions, sent below
+                                        } else if queued {
+                                            in_flight.queue(Queued { chat_id, message_id, extra: extra.clone(), posted_at, target: reacted_to });
+                                            info!(target: hotlog::TARGET, "Too many reactions in flight in chat {}, queued ({} waiting)", chat_id, in_flight.waiting());
+                                        } else if let Some(due) = due {
+                                            info!(target: hotlog::TARGET, "Reacting in {} ms", due.saturating_duration_since(Instant::now()).as_millis());
+                                            jitter.hold(due, chat_id, message_id, extra.clone());
+                                        } else {
+                                            send_reaction(
+                                                &mut discussions,
+                                                failover.sender(&sender),
+                                                &bot_api,
+                                                reaction_format,
+                                                &emoji,
+                                                (chat_id, message_id),
+                                                &extra,
+                                            );
+                                            race_accounts.react(&discussions, (chat_id, message_id), &emoji, &extra);
+                                        }
+                                        
+                                        let sent = Instant::now();
+                                        latency.record(Stage::Send, sent - decided);
+                                        latency.record(Stage::Total, sent - received);
                                         
                                         // Log the ultra-fast reaction time
                                         let elapsed = start.elapsed();
-                                        if elapsed.as_micros() < 1000 {
-                                            info!("⚡⚡ HYPER-FAST reaction sent in {} µs", elapsed.as_micros());
+                                        if !reacts || queued || due.is_some() {
+                                            // Held back on purpose, or not reacted to
+                                        } else if elapsed.as_micros() < 1000 {
+                                            info!(target: hotlog::TARGET, "⚡⚡ HYPER-FAST reaction sent in {} µs", elapsed.as_micros());
                                         } else {
-                                            info!("⚡ Fast reaction sent in {:?}", elapsed);
+                                            info!(target: hotlog::TARGET, "⚡ Fast reaction sent in {:?}", elapsed);
+                                        }
+                                        
+                                        let price = amount;
+                                        let balance_left = balance.spend(price);
+                                        let order = prices.order(&formatted).unwrap_or_default();
+                                        actions.run(failover.sender(&sender), chat_id, &json["message"], &order, sent - received);
+                                        let bank = order.bank.as_deref();
+                                        let reacted_at = chrono::Utc::now().timestamp();
+                                        let commission_pct = order.commission;
+                                        let expected_profit = filter_settings.filter.commissions.expected_profit(&order);
+                                        let score = filter_settings.score(chat_id, &order);
+                                        events.publish(Event::Matched(Match {
+                                            chat_id,
+                                            message_id,
+                                            target: &extra["reaction:".len()..],
+                                            chat_title: names.get(chat_id),
+                                            sender_name: senders::sender(&json["message"]).and_then(|sender| names.get(sender)),
+                                            text,
+                                            price,
+                                            bank,
+                                            commission_pct,
+                                            expected_profit,
+                                            score,
+                                            balance_left,
+                                            reacted_at,
+                                            latency: sent - received,
+                                            delivery,
+                                        }));
+                                        // Kept across restarts until settled, wherever it's waiting (see claims.rs)
+                                        if reacts {
+                                            claims.add(Claim { extra: extra.clone(), chat_id, message_id, posted_at, emoji: emoji.clone() });
+                                        }
+                                        // A held reaction is only waited for once it's sent; a queued one once it leaves the queue
+                                        if reacts && !queued {
+                                            pending_reactions.insert(extra, due.unwrap_or(sent), posted_at, reacted_to);
+                                        }
+                                        lifecycle.watch(chat_id, message_id);
+                                        
+                                        remember_reaction(&mut recent_reactions, &mut recent_order, (chat_id, message_id));
+                                        
+                                        // Off the hot path: the reaction is already on its way
+                                        if mark_as_read {
+                                            let mentioned = json["message"]["contains_unread_mention"].as_bool().unwrap_or(false);
+                                            mark_read(failover.sender(&sender), chat_id, message_id, mentioned);
                                         }
-                                    } else {
-                                        info!("Message did not pass filters, ignoring");
+                                    }
+                                    // SCAM_ACTION=notify (see scam.rs)
+                                    if let Some(notice) = scam_checks.take_notice() {
+                                        events.status(notice);
+                                    }
+                                    
+                                    // Who posted it, for the next order they post (see names.rs)
+                                    names.request_sender(failover.sender(&sender), &json["message"]);
+                                    
+                                    // For /why, once the reaction is on its way
+                                    if history.traces() {
+                                        history.record_decision(DecisionTrace {
+                                            chat_id,
+                                            message_id,
+                                            decided_at: chrono::Utc::now().timestamp(),
+                                            trace: why::trace(&filter_settings, chat_id, &formatted, &prices, decision),
+                                        });
                                     }
                                 }
                             }
+                            
+                            // Replies saying a matched order was taken, paid or cancelled (see lifecycle.rs)
+                            if let Some(((order_chat, order_id), state)) = lifecycle.on_reply(&json["message"], text) {
+                                info!("Order {}:{} is {}", order_chat, order_id, state.name());
+                                history.record_final_state(order_chat, order_id, state.name());
+                            }
+                        } else if !halted && allowed_chat_ids.contains(&chat_id) && ocr.request(failover.sender(&sender), chat_id, &json["message"]) {
+                            // Orders posted as screenshots come back as text once read (see ocr.rs)
+                            info!("Reading the photo in {}:{}", chat_id, json["message"]["id"]);
                         }
                     }
                 }
             }
+        } else if updates.is_exhausted() {
+            info!("No more updates, stopping");
+            return Ok(());
+        }
+    }
+}
+
+// Every TDLib data directory the bot opens, locked against another bot on the same session (the
+// mock client opens none)
+fn lock_sessions() -> Result<Vec<daemon::Pidfile>, BotError> {
+    std::iter::once(tdlib_data_dir())
+        .chain(standby::data_dir())
+        .chain(accounts::data_dirs())
+        .map(|dir| daemon::lock_session(&dir))
+        .collect()
+}
+
+// TDLib's database directory (TDLIB_DATA_DIR, "tdlib_data" by default)
+fn tdlib_data_dir() -> String {
+    std::env::var("TDLIB_DATA_DIR").unwrap_or_else(|_| DEFAULT_TDLIB_DATA_DIR.to_string())
+}
+
+// The files directory kept next to a database directory
+fn tdlib_files_dir(data_dir: &str) -> String {
+    format!("{}_files", data_dir.trim_end_matches("/"))
+}
+
+// Send TDLib's log settings and our parameters to a freshly created client; fails (before sending
+// anything) when the session's database key is there but can't be read
+fn configure(client: &TdSender, tdlib_data_dir: &str) -> Result<(), BotError> {
+    let database_encryption_key = dbkey::get(tdlib_data_dir)?;
+
+    // TDLib's own log: off unless TDLIB_LOG_FILE is set, in which case TDLib itself rotates it
+    // to "<file>.old" past TDLIB_LOG_MAX_BYTES
+    let tdlib_log_file = std::env::var("TDLIB_LOG_FILE").ok().filter(|p| !p.is_empty());
+    let verbosity = match &tdlib_log_file {
+        Some(_) => std::env::var("TDLIB_LOG_VERBOSITY").ok().and_then(|s| s.parse().ok()).unwrap_or(2),
+        None => 0,
+    };
+    if let Some(path) = &tdlib_log_file {
+        info!("Writing the TDLib log to {} (verbosity {})", path, verbosity);
+        client.send(json!({
+            "@type": "setLogStream",
+            "log_stream": {
+                "@type": "logStreamFile",
+                "path": path,
+                "max_file_size": std::env::var("TDLIB_LOG_MAX_BYTES").ok().and_then(|s| s.parse::<i64>().ok()).unwrap_or(DEFAULT_TDLIB_LOG_MAX_BYTES),
+                "redirect_stderr": false
+            }
+        }).to_string());
+    }
+    client.send(json!({
+        "@type": "setLogVerbosityLevel",
+        "new_verbosity_level": verbosity
+    }).to_string());
+
+    // Setup TDLib with proper parameters
+    info!("Setting up TDLib parameters");
+    
+    let tdlib_files_dir = tdlib_files_dir(tdlib_data_dir);
+    
+    info!("Using TDLib data directory: {}", tdlib_data_dir);
+    
+    let params = json!({
+        "@type": "setTdlibParameters",
+        "database_directory": tdlib_data_dir,
+        "files_directory": tdlib_files_dir,
+        "database_encryption_key": database_encryption_key,
+        // For a test profile: Telegram's test servers, with their own accounts
+        "use_test_dc": std::env::var("TDLIB_TEST_DC").is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+        "api_id": get_api_id(),
+        "api_hash": get_api_hash(),
+        "system_language_code": "en",
+        "device_model": "ReactionBot",
+        "system_version": "1.0",
+        "application_version": "1.0",
+        "enable_storage_optimizer": true,
+        "ignore_file_names": false,
+        "use_file_database": true,
+        "use_chat_info_database": true,
+        "use_message_database": true,
+        "use_secret_chats": false
+    });
+    
+    client.send(params.to_string());
+    // No need to check database encryption key separately
+    // TDLib handles this automatically in setTdlibParameters
+    Ok(())
+}
+
+// Ask for the chat list and the chats' available reactions so TDLib starts sending updates
+fn subscribe(client: &TdSender, allowed_chat_ids: &HashSet<i64>) {
+    // Request chats to start receiving updates; bots get updates without it and can't list chats
+    if bot_token().is_none() {
+        info!("Requesting chats to start receiving updates");
+        client.send(json!({
+            "@type": "getChats",
+            "limit": 100
+        }).to_string());
+    }
+
+    // Get available reactions for the chat
+    for chat_id in allowed_chat_ids {
+        info!("Getting available reactions for chat {}", chat_id);
+        client.send(json!({
+            "@type": "getChatAvailableReactions",
+            "chat_id": chat_id
+        }).to_string());
+    }
+}
+
+// Load the monitored chats and mark the account online (see presence.rs), so the first matching message
+// after a cold start or a reconnect isn't delayed by TDLib loading the chat lazily. Repeated after every reconnect
+fn warm_up(client: &TdSender, allowed_chat_ids: &HashSet<i64>, presence: &mut Presence) {
+    info!("Warming up {} monitored chats", allowed_chat_ids.len());
+    let is_user = bot_token().is_none();
+    for chat_id in allowed_chat_ids {
+        // The answer also brings the chat's title (see names.rs)
+        client.send(json!({
+            "@type": "getChat",
+            "chat_id": chat_id,
+            "@extra": format!("{}{}", names::EXTRA_PREFIX, chat_id)
+        }).to_string());
+        // Open chats get all their updates right away; bots can't open chats
+        if is_user {
+            client.send(json!({
+                "@type": "openChat",
+                "chat_id": chat_id
+            }).to_string());
+        }
+    }
+    presence.go_online(client);
+}
+
+// Filter decision for a message in a monitored chat; shared with `replay` so recorded traffic
+// goes through exactly the same checks
+fn decide(
+    chat_id: i64,
+    message_id: i64,
+    text: &MessageText,
+    evaluation: Option<Evaluation>,
+    recent_reactions: &HashSet<(i64, i64)>,
+    filter_settings: &FilterSettings,
+    prices: &Prices,
+) -> Result<(), SkipReason> {
+    if recent_reactions.contains(&(chat_id, message_id)) {
+        return Err(SkipReason::Duplicate);
+    }
+    // Evaluated ahead by a pipeline worker, or here
+    let evaluation = evaluation.unwrap_or_else(|| filter_settings.evaluate(chat_id, text, prices));
+    filter_settings.settle(chat_id, text.text, evaluation)
+}
+
+// "key:weight" entries of a comma separated scoring variable; the weight is after the last colon
+fn score_entries(name: &str) -> Vec<(String, String)> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| match entry.rsplit_once(':') {
+            Some((key, weight)) => (key.trim().to_string(), weight.to_string()),
+            None => (entry.trim().to_string(), String::new()),
+        })
+        .collect()
+}
+
+// "22-6" as hours of the day (inclusive, wrapping past midnight), or a single "9"
+fn parse_hours(hours: &str) -> Option<(u32, u32)> {
+    let (from, to) = hours.split_once('-').unwrap_or((hours, hours));
+    let (from, to) = (from.trim().parse::<u32>().ok()?, to.trim().parse::<u32>().ok()?);
+    (from < 24 && to < 24).then_some((from, to))
+}
+
+// An order as WASM filters and hooks get it: the message and what was parsed from it
+fn order_json(chat_id: i64, message_id: Option<i64>, text: &str, order: &botdg_core::Order) -> serde_json::Value {
+    json!({
+        "chat_id": chat_id,
+        "message_id": message_id,
+        "text": text,
+        "amount": order.amount.as_ref().map(|amount| amount.value),
+        "currency": order.amount.as_ref().map(|amount| &amount.currency),
+        "rubles": order.rubles,
+        "bank": order.bank,
+        "requisite": order.requisite,
+        "commission": order.commission,
+    })
+}
+
+// Seconds since the message was posted, by its server date
+fn message_age(message: &serde_json::Value) -> i64 {
+    message["date"].as_i64().map_or(0, |date| chrono::Utc::now().timestamp() - date)
+}
+
+// Remember a reacted message for duplicate detection, forgetting the oldest past MAX_RECENT_REACTIONS
+fn remember_reaction(recent_reactions: &mut HashSet<(i64, i64)>, recent_order: &mut VecDeque<(i64, i64)>, message: (i64, i64)) {
+    recent_reactions.insert(message);
+    recent_order.push_back(message);
+    if recent_order.len() > MAX_RECENT_REACTIONS {
+        if let Some(oldest) = recent_order.pop_front() {
+            recent_reactions.remove(&oldest);
         }
     }
 }
 
+// Drop every reaction that hasn't gone out yet and stop resending the rest, for the kill switch
+fn halt(pending_reactions: &mut PendingReactions, jitter: &mut Jitter, in_flight: &mut InFlightLimit, claims: &mut Claims) {
+    claims.clear();
+    pending_reactions.clear();
+    jitter.clear();
+    in_flight.clear();
+}
+
+// Send a reaction: to the discussion group copy for DISCUSSION_REACTIONS channels, through the Bot
+// API for BOT_API_CHAT_IDS, otherwise through TDLib
+fn send_reaction(
+    discussions: &mut Discussions,
+    client: &TdSender,
+    bot_api: &BotApi,
+    reaction_format: ReactionFormat,
+    emoji: &str,
+    (chat_id, message_id): (i64, i64),
+    extra: &str,
+) {
+    if discussions.always(chat_id) {
+        // Reacted to once the discussion message is known (see discussion.rs)
+        discussions.redirect(client, chat_id, message_id);
+    } else if bot_api.handles(chat_id) {
+        bot_api.react(chat_id, message_id, emoji, extra);
+    } else {
+        // One request in the format the installed TDLib takes (see reaction.rs)
+        for request in reaction_format.requests(chat_id, message_id, emoji, extra) {
+            client.send(request.to_string());
+        }
+    }
+}
+
+// Mark a message as read, like opening the chat and scrolling to it would, and clear the chat's
+// mentions if it mentioned us, so the unread counters don't pile up on matched orders
+fn mark_read(client: &TdSender, chat_id: i64, message_id: i64, mentioned: bool) {
+    client.send(json!({
+        "@type": "viewMessages",
+        "chat_id": chat_id,
+        "message_ids": [message_id],
+        "force_read": true
+    }).to_string());
+    if mentioned {
+        client.send(json!({
+            "@type": "readAllChatMentions",
+            "chat_id": chat_id
+        }).to_string());
+    }
+}
+
 // Send a message to a chat
-async fn send_message(client: &Arc<Mutex<TdClient>>, chat_id: i64, message: &str) {
+fn send_message(client: &TdSender, chat_id: i64, message: &str) {
     let send_request = json!({
         "@type": "sendMessage",
         "chat_id": chat_id,
@@ -753,132 +1891,51 @@ async fn send_message(client: &Arc<Mutex<TdClient>>, chat_id: i64, message: &str
         }
     });
     
-    let client_lock = client.lock().await;
-    client_lock.send(&send_request.to_string());
+    client.send(send_request.to_string());
     info!("Sent message to chat {}", chat_id);
 }
 
-// Extract message ID from text content
-fn extract_message_id(text: &str) -> Option<String> {
-    // Look for "ID: XXXXX" pattern in the text
-    let id_pattern = Regex::new(r"ID:\s*(\d+)").ok()?;
-    
-    if let Some(captures) = id_pattern.captures(text) {
-        if let Some(id_match) = captures.get(1) {
-            let id = id_match.as_str().to_string();
-            info!("Extracted message ID from text: {}", id);
-            return Some(id);
-        }
-    }
-    
-    info!("No message ID found in text");
-    None
+// Parse a TDLib update. With the "simd-json" feature it goes through simd-json first, which is
+// faster on large updates; anything it rejects is retried with serde_json
+#[cfg(feature = "simd-json")]
+fn parse_update(raw: &str) -> Option<serde_json::Value> {
+    let mut bytes = raw.as_bytes().to_vec();
+    simd_json::serde::from_slice(&mut bytes)
+        .ok()
+        .or_else(|| serde_json::from_str(raw).ok())
 }
 
+#[cfg(not(feature = "simd-json"))]
+fn parse_update(raw: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(raw).ok()
+}
 
-
-// Ultra-fast reaction function that doesn't wait for response but tries both reaction formats simultaneously
-fn send_reaction_fast(client: &TdClient, chat_id: i64, message_id: i64, _message_text: &str) {
-    // Send both formats simultaneously for maximum speed and compatibility
-    // Format 1: Newer format with reaction_type
-    let reaction_request = json!({
-        "@type": "addMessageReaction",
-        "chat_id": chat_id,
-        "message_id": message_id,
-        "reaction_type": {
-            "@type": "reactionTypeEmoji",
-            "emoji": REACTION_EMOJI
-        },
-        "is_big": false
-    });
-    
-    // Format 2: Alternative format with direct reaction
-    let alt_reaction_request = json!({
-        "@type": "addMessageReaction",
-        "chat_id": chat_id,
-        "message_id": message_id,
-        "reaction": REACTION_EMOJI,
-        "is_big": false
-    });
-    
-    // Send both formats without waiting
-    client.send(&reaction_request.to_string());
-    client.send(&alt_reaction_request.to_string());
-    
-    // Log the action with ultra-fast indicator
-    let reaction_time = std::time::Instant::now();
-    info!("⚡ Ultra-fast reaction sent to message {} in chat {}. Reaction time: {:?}", 
-          message_id, chat_id, reaction_time.elapsed());
+// "chat:message" from a reaction's "@extra" tag
+fn parse_target(target: &str) -> Option<(i64, i64)> {
+    let (chat_id, message_id) = target.split_once(':')?;
+    Some((chat_id.parse().ok()?, message_id.parse().ok()?))
 }
 
-async fn react_to_message(client: &TdClient, chat_id: i64, message_id: i64, _message_text: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // Start timing for reaction speed measurement
-    let start_time = Instant::now();
-    
-    // Use the ultra-fast approach - send both formats simultaneously
-    // Format 1: Newer format with reaction_type
-    let reaction_request = json!({
-        "@type": "addMessageReaction",
-        "chat_id": chat_id,
-        "message_id": message_id,
-        "reaction_type": {
-            "@type": "reactionTypeEmoji",
-            "emoji": REACTION_EMOJI
-        },
-        "is_big": false
-    });
-    
-    // Format 2: Alternative format with direct reaction
-    let alt_reaction_request = json!({
-        "@type": "addMessageReaction",
-        "chat_id": chat_id,
-        "message_id": message_id,
-        "reaction": REACTION_EMOJI,
-        "is_big": false
-    });
-    
-    // Send both formats without waiting for response
-    client.send(&reaction_request.to_string());
-    client.send(&alt_reaction_request.to_string());
-    
-    // Log the reaction time immediately after sending
-    let send_time = start_time.elapsed();
-    info!("⚡ Ultra-fast reaction sent in {:?}", send_time);
-    
-    // Instead of spawning a task that would capture the client reference,
-    // we'll do a quick non-blocking check for confirmation
-    let mut success = false;
-    let check_start = Instant::now();
-    
-    // Only check for a very short time (50ms max) to maintain ultra-fast speed
-    while check_start.elapsed().as_millis() < 50 {
-        if let Some(response) = client.receive(0.001) {
-            if let Ok(json_response) = serde_json::from_str::<serde_json::Value>(&response) {
-                if json_response["@type"] == "ok" || 
-                   (json_response["@type"] == "updateMessageReactions" && 
-                    json_response["chat_id"] == chat_id && 
-                    json_response["message_id"] == message_id) {
-                    success = true;
-                    break;
-                }
-            }
-        }
+fn format_history(records: &[ReactionRecord], names: &Names) -> String {
+    if records.is_empty() {
+        return "📋 No reactions recorded yet.".to_string();
     }
-    
-    // Log the final status with timing information
-    if success {
-        info!("Message passed all filters, reaction confirmed. Reaction time: {:?}", start_time.elapsed());
+
+    let mut text = format!("📋 Last {} reactions:\n", records.len());
+    for record in records {
+        let time = timezone::from_timestamp(record.reacted_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        text.push_str(&format!(
+            "\n{} | chat {} msg {} | {} ₽ | {} | {} µs",
+            time,
+            names.label(record.chat_id),
+            record.message_id,
+            record.amount.map_or("?".to_string(), |a| a.to_string()),
+            record.bank.as_deref().unwrap_or("?"),
+            record.latency_us
+        ));
     }
-    
-    // Return immediately to maintain ultra-fast speed
-    Ok(())
+    text
 }
 
-fn extract_price(text: &str, regex: &Regex) -> Option<i32> {
-    regex.captures(text)?
-        .get(1)?
-        .as_str()
-        .replace(' ', "")
-        .parse()
-        .ok()
-}
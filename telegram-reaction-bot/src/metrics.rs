@@ -0,0 +1,164 @@
+use std::{
+    net::UdpSocket,
+    sync::{mpsc, OnceLock},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::events::{Event, Outcome, Subscriber};
+
+const DEFAULT_STATSD_ADDR: &str = "127.0.0.1:8125";
+const DEFAULT_PREFIX: &str = "botdg";
+// Lines are packed into datagrams up to this size, safe for any network's MTU
+const MAX_DATAGRAM: usize = 1432;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// A measurement, named without the prefix and tagged with what it is about
+pub enum Metric<'a> {
+    Count(&'a str, i64, &'a [(&'a str, &'a str)]),
+    Timing(&'a str, Duration, &'a [(&'a str, &'a str)]),
+}
+
+// Where metrics go. The rest of the bot only calls `record` (or `count` and `timing`), so another
+// backend is an implementation of this and a name in `exporter`
+trait Exporter: Send + Sync {
+    fn export(&self, metric: &Metric);
+}
+
+static EXPORTER: OnceLock<Option<Box<dyn Exporter>>> = OnceLock::new();
+
+// METRICS_EXPORTER picks the backend: "statsd" or "dogstatsd" (see StatsD). Nothing is measured
+// without one
+fn exporter() -> Option<&'static dyn Exporter> {
+    EXPORTER
+        .get_or_init(|| {
+            let name = std::env::var("METRICS_EXPORTER").ok().filter(|name| !name.trim().is_empty())?;
+            let exporter: Box<dyn Exporter> = match name.trim().to_lowercase().as_str() {
+                "statsd" => Box::new(StatsD::from_env(false)?),
+                "dogstatsd" => Box::new(StatsD::from_env(true)?),
+                _ => {
+                    warn!("Unknown METRICS_EXPORTER '{}' (statsd or dogstatsd)", name);
+                    return None;
+                }
+            };
+            Some(exporter)
+        })
+        .as_deref()
+}
+
+pub fn record(metric: Metric) {
+    if let Some(exporter) = exporter() {
+        exporter.export(&metric);
+    }
+}
+
+pub fn count(name: &str, tags: &[(&str, &str)]) {
+    record(Metric::Count(name, 1, tags));
+}
+
+pub fn timing(name: &str, elapsed: Duration, tags: &[(&str, &str)]) {
+    record(Metric::Timing(name, elapsed, tags));
+}
+
+// StatsD over UDP to STATSD_ADDR (127.0.0.1:8125), every name under STATSD_PREFIX ("botdg").
+// DogStatsD (the Datadog agent) gets tags as "|#key:value" along with STATSD_TAGS
+// ("env:prod,team:ops"); plain StatsD has no tags, so their values go into the name instead
+// ("botdg.skipped.below_min_amount"). Lines are handed to a thread that packs them into datagrams
+// once a second, so measuring costs the receive loop a channel send
+struct StatsD {
+    lines: mpsc::Sender<String>,
+    prefix: String,
+    tags: Option<String>,   // DogStatsD only: STATSD_TAGS, and where the metric's own go
+}
+
+impl StatsD {
+    fn from_env(dogstatsd: bool) -> Option<Self> {
+        let addr = std::env::var("STATSD_ADDR").unwrap_or_else(|_| DEFAULT_STATSD_ADDR.to_string());
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| socket.connect(&addr).map(|_| socket));
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("Failed to open the StatsD socket to {}: {}", addr, e);
+                return None;
+            }
+        };
+        let prefix = std::env::var("STATSD_PREFIX").unwrap_or_else(|_| DEFAULT_PREFIX.to_string());
+        let tags = dogstatsd.then(|| std::env::var("STATSD_TAGS").unwrap_or_default().trim().to_string());
+        info!("Sending {} metrics to {}", if dogstatsd { "DogStatsD" } else { "StatsD" }, addr);
+
+        let (lines, receiver) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            let mut datagram = String::new();
+            loop {
+                let line = match receiver.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(line) => Some(line),
+                    Err(mpsc::RecvTimeoutError::Timeout) => None,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+                let full = line.as_ref().is_some_and(|line| datagram.len() + line.len() + 1 > MAX_DATAGRAM);
+                if (line.is_none() || full) && !datagram.is_empty() {
+                    // Nobody listening is fine: metrics are best effort
+                    let _ = socket.send(datagram.as_bytes());
+                    datagram.clear();
+                }
+                if let Some(line) = line {
+                    if !datagram.is_empty() {
+                        datagram.push('\n');
+                    }
+                    datagram.push_str(&line);
+                }
+            }
+        });
+        Some(Self { lines, prefix, tags })
+    }
+
+    fn line(&self, name: &str, value: String, kind: &str, tags: &[(&str, &str)]) -> String {
+        let Some(common) = &self.tags else {
+            let suffix: String = tags.iter().map(|(_, value)| format!(".{}", value)).collect();
+            return format!("{}.{}{}:{}|{}", self.prefix, name, suffix, value, kind);
+        };
+        let mut line = format!("{}.{}:{}|{}", self.prefix, name, value, kind);
+        let tags = common
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .chain(tags.iter().map(|(key, value)| format!("{}:{}", key, value)))
+            .collect::<Vec<_>>();
+        if !tags.is_empty() {
+            line.push_str("|#");
+            line.push_str(&tags.join(","));
+        }
+        line
+    }
+}
+
+impl Exporter for StatsD {
+    fn export(&self, metric: &Metric) {
+        let line = match *metric {
+            Metric::Count(name, value, tags) => self.line(name, value.to_string(), "c", tags),
+            Metric::Timing(name, elapsed, tags) => self.line(name, format!("{:.3}", elapsed.as_secs_f64() * 1000.0), "ms", tags),
+        };
+        let _ = self.lines.send(line);
+    }
+}
+
+// Matches and how their reactions ended, as "matched" and "reaction" tagged with the outcome
+pub struct EventMetrics;
+
+impl Subscriber for EventMetrics {
+    fn on_event(&self, event: &Event) {
+        match event {
+            Event::Matched(_) => count("matched", &[]),
+            Event::Reaction { outcome, .. } => {
+                let outcome = match outcome {
+                    Outcome::Sent => "sent",
+                    Outcome::Failed(_) => "failed",
+                    Outcome::Unconfirmed => "unconfirmed",
+                };
+                count("reaction", &[("outcome", outcome)]);
+            }
+            Event::Status(_) => {}
+        }
+    }
+}
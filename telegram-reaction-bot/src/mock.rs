@@ -0,0 +1,189 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{client::TelegramClient, error::{io_error, BotError}};
+
+// How often the MOCK_UPDATES file is looked at for more lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(50);
+
+// A request turned into the updates TDLib would answer it with
+type Responder = Box<dyn Fn(&Value) -> Vec<Value> + Send + Sync>;
+
+#[derive(Default)]
+struct Script {
+    updates: VecDeque<String>,
+    // With when each was sent
+    sent: Vec<(Instant, Value)>,
+    // More updates may still be pushed
+    open: bool,
+}
+
+// In-memory client: hands out scripted updates in order, records every request, and optionally
+// answers requests through a responder. Used by the tests, `simulate` and MOCK_UPDATES. Receiving waits for the timeout like TDLib does when the
+// script is empty, so requests queued on the sender thread are recorded (and answered) before
+// the receive loop sees an exhausted script
+#[derive(Default)]
+pub struct MockClient {
+    script: Mutex<Script>,
+    arrived: Condvar,
+    responder: Option<Responder>,
+}
+
+impl MockClient {
+    // A client that plays `updates` and then has nothing more to say
+    #[cfg(test)]
+    pub fn new(updates: impl IntoIterator<Item = Value>) -> Self {
+        let client = Self::default();
+        client.script.lock().unwrap().updates.extend(updates.into_iter().map(|update| update.to_string()));
+        client
+    }
+
+    // A client fed with `push` until `close`
+    pub fn open() -> Self {
+        let client = Self::default();
+        client.script.lock().unwrap().open = true;
+        client
+    }
+
+    pub fn with_responder(mut self, responder: impl Fn(&Value) -> Vec<Value> + Send + Sync + 'static) -> Self {
+        self.responder = Some(Box::new(responder));
+        self
+    }
+
+    pub fn push(&self, update: Value) {
+        self.script.lock().unwrap().updates.push_back(update.to_string());
+        self.arrived.notify_one();
+    }
+
+    // Nothing more will be pushed; the receive loop ends once the rest is handled
+    pub fn close(&self) {
+        self.script.lock().unwrap().open = false;
+        self.arrived.notify_one();
+    }
+
+    // Every request sent so far, oldest first
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<Value> {
+        self.sent_at().into_iter().map(|(_, request)| request).collect()
+    }
+
+    // The same, with when each was sent
+    pub fn sent_at(&self) -> Vec<(Instant, Value)> {
+        self.script.lock().unwrap().sent.clone()
+    }
+
+    // Requests of one "@type"
+    #[cfg(test)]
+    pub fn sent_of_type(&self, request_type: &str) -> Vec<Value> {
+        self.sent().into_iter().filter(|request| request["@type"] == request_type).collect()
+    }
+}
+
+impl TelegramClient for MockClient {
+    fn send(&self, request: &str, _buffer: &mut Vec<u8>) {
+        let Ok(request) = serde_json::from_str::<Value>(request) else {
+            return;
+        };
+        let answers = self.responder.as_ref().map(|respond| respond(&request)).unwrap_or_default();
+        let mut script = self.script.lock().unwrap();
+        script.sent.push((Instant::now(), request));
+        script.updates.extend(answers.iter().map(Value::to_string));
+        if !answers.is_empty() {
+            self.arrived.notify_one();
+        }
+    }
+
+    fn receive_into(&self, timeout: f64, buffer: &mut String) -> bool {
+        let script = self.script.lock().unwrap();
+        let (mut script, _) = self
+            .arrived
+            .wait_timeout_while(script, Duration::from_secs_f64(timeout), |script| script.updates.is_empty())
+            .unwrap();
+        match script.updates.pop_front() {
+            Some(update) => {
+                buffer.clear();
+                buffer.push_str(&update);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn reset(&self) {}
+
+    fn is_exhausted(&self) -> bool {
+        let script = self.script.lock().unwrap();
+        !script.open && script.updates.is_empty()
+    }
+}
+
+// MOCK_UPDATES: instead of connecting to Telegram, play the updates appended to this file, one JSON
+// object per line, as if the session were logged in, and confirm every reaction. MOCK_REQUESTS
+// gets every request the bot sends, one per line. Removing the file ends the run. For running the
+// bot (and the manager around it) end to end without an account; see the manager's tests/e2e.rs
+pub fn from_env() -> Result<Option<Arc<MockClient>>, BotError> {
+    let Some(path) = std::env::var("MOCK_UPDATES").ok().filter(|path| !path.is_empty()) else {
+        return Ok(None);
+    };
+    let updates = OpenOptions::new().create(true).append(true).read(true).open(&path)
+        .map_err(|e| io_error(e, format!("MOCK_UPDATES {}", path)))?;
+    let requests = std::env::var("MOCK_REQUESTS")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(|path| OpenOptions::new().create(true).append(true).open(&path).map_err(|e| io_error(e, format!("MOCK_REQUESTS {}", path))))
+        .transpose()?
+        .map(Mutex::new);
+    warn!("Playing updates from {} instead of connecting to Telegram", path);
+
+    let client = Arc::new(MockClient::open().with_responder(move |request| {
+        if let Some(requests) = &requests {
+            let _ = writeln!(requests.lock().unwrap(), "{}", request);
+        }
+        if request["@type"] == "addMessageReaction" {
+            vec![json!({ "@type": "ok", "@extra": request["@extra"] })]
+        } else {
+            Vec::new()
+        }
+    }));
+    client.push(json!({ "@type": "updateAuthorizationState", "authorization_state": { "@type": "authorizationStateReady" } }));
+    let follower = client.clone();
+    std::thread::Builder::new()
+        .name("mock-updates".to_string())
+        .spawn(move || follow(&path, BufReader::new(updates), &follower))?;
+    Ok(Some(client))
+}
+
+// Push each line as it is completed, until the file is removed
+fn follow(path: &str, mut updates: BufReader<File>, client: &MockClient) {
+    let mut line = String::new();
+    loop {
+        match updates.read_line(&mut line) {
+            Ok(_) if line.ends_with('\n') => {
+                match serde_json::from_str(&line) {
+                    Ok(update) => client.push(update),
+                    Err(e) => warn!("Skipping a MOCK_UPDATES line that isn't JSON: {}", e),
+                }
+                line.clear();
+            }
+            // Nothing more yet, or half a line
+            Ok(_) if std::path::Path::new(path).exists() => std::thread::sleep(FOLLOW_INTERVAL),
+            Ok(_) => {
+                info!("{} removed, no more updates", path);
+                client.close();
+                return;
+            }
+            Err(e) => {
+                warn!("Stopped reading MOCK_UPDATES: {}", e);
+                client.close();
+                return;
+            }
+        }
+    }
+}
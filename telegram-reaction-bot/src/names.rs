@@ -0,0 +1,80 @@
+use std::collections::{HashMap, HashSet};
+use serde_json::{json, Value};
+
+use crate::sender::TdSender;
+
+// Answers to getChat and getUser for names, routed back by their "@extra" tag
+pub const EXTRA_PREFIX: &str = "name:";
+
+// Titles of the watched chats and names of order senders and competitors, so logs, events and the
+// history say who instead of a bare ID. Each ID is asked about once, with getChat for chats and
+// getUser for users; updateChatTitle keeps watched chats' titles current. Until the answer comes
+// the ID is all there is
+#[derive(Default)]
+pub struct Names {
+    names: HashMap<i64, String>,
+    asked: HashSet<i64>,
+}
+
+impl Names {
+    pub fn get(&self, id: i64) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+
+    // "Title (id)", or just the ID while the name isn't known
+    pub fn label(&self, id: i64) -> String {
+        match self.get(id) {
+            Some(name) => format!("{} ({})", name, id),
+            None => id.to_string(),
+        }
+    }
+
+    // Ask for a chat's title, or a user's name for a positive ID that isn't a chat we know of
+    pub fn request(&mut self, client: &TdSender, id: i64, is_chat: bool) {
+        if self.names.contains_key(&id) || !self.asked.insert(id) {
+            return;
+        }
+        let request = if is_chat {
+            json!({ "@type": "getChat", "chat_id": id, "@extra": format!("{}{}", EXTRA_PREFIX, id) })
+        } else {
+            json!({ "@type": "getUser", "user_id": id, "@extra": format!("{}{}", EXTRA_PREFIX, id) })
+        };
+        client.send(request.to_string());
+    }
+
+    // Whoever sent a message: a user, or a chat posting as itself
+    pub fn request_sender(&mut self, client: &TdSender, message: &Value) {
+        let sender = &message["sender_id"];
+        match sender["@type"].as_str() {
+            Some("messageSenderUser") => sender["user_id"].as_i64().into_iter().for_each(|id| self.request(client, id, false)),
+            Some("messageSenderChat") => sender["chat_id"].as_i64().into_iter().for_each(|id| self.request(client, id, true)),
+            _ => {}
+        }
+    }
+
+    // Take a name from an answer to one of the requests above or an updateChatTitle. The ID and
+    // name when it's new or changed, for the history
+    pub fn learn(&mut self, json: &Value) -> Option<(i64, String)> {
+        let (id, name) = match json["@type"].as_str()? {
+            "chat" => (json["id"].as_i64()?, json["title"].as_str()?.trim().to_string()),
+            "updateChatTitle" => (json["chat_id"].as_i64()?, json["title"].as_str()?.trim().to_string()),
+            "user" => (json["id"].as_i64()?, user_name(json)?),
+            _ => return None,
+        };
+        if name.is_empty() || self.get(id) == Some(name.as_str()) {
+            return None;
+        }
+        self.names.insert(id, name.clone());
+        Some((id, name))
+    }
+}
+
+// "First Last", or "@username" for a user without a name (deleted accounts have neither)
+fn user_name(user: &Value) -> Option<String> {
+    let name = format!("{} {}", user["first_name"].as_str().unwrap_or_default(), user["last_name"].as_str().unwrap_or_default());
+    let name = name.trim();
+    if !name.is_empty() {
+        return Some(name.to_string());
+    }
+    user["usernames"]["active_usernames"][0].as_str().map(|username| format!("@{}", username))
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Instant,
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::sender::TdSender;
+
+// "@extra" of downloadFile requests, followed by chat:message
+pub const EXTRA_PREFIX: &str = "ocr:";
+const DEFAULT_LANGUAGES: &str = "rus+eng";
+// TDLib's download priority, 1 to 32: orders come before anything else
+const DOWNLOAD_PRIORITY: i32 = 32;
+
+// Orders posted as screenshots. Photos in OCR_CHAT_IDS are downloaded (downloadFile) and read with
+// Tesseract in OCR_LANGUAGES (rus+eng) on a thread of their own; the text, after the caption if any,
+// goes back into the receive loop as an updateNewMessage of the same message, so it takes the same
+// path as a typed order: filters, duplicate check, hooks and history. Needs the "ocr" feature and
+// Tesseract's language data (TESSDATA_PREFIX)
+pub struct Ocr {
+    chats: HashSet<i64>,
+    // Photos being downloaded, by their request's "@extra"
+    downloading: HashMap<String, Value>,
+    worker: Option<Worker>,
+}
+
+// The OCR thread: photos (file path and message) in, updates out
+struct Worker {
+    photos: Sender<(String, Value)>,
+    updates: Receiver<String>,
+}
+
+impl Ocr {
+    pub fn from_env() -> Self {
+        let chats = crate::chat_ids("OCR_CHAT_IDS");
+        let languages = std::env::var("OCR_LANGUAGES").ok().filter(|s| !s.trim().is_empty()).unwrap_or_else(|| DEFAULT_LANGUAGES.to_string());
+        // Checked by validate.rs: without the feature OCR_CHAT_IDS stops the bot from starting
+        let worker = (!chats.is_empty() && cfg!(feature = "ocr")).then(|| spawn(languages));
+        Self { chats, downloading: HashMap::new(), worker }
+    }
+
+    // Ask for the photo of a new message in an OCR chat; false when it's nothing to read
+    pub fn request(&mut self, sender: &TdSender, chat_id: i64, message: &Value) -> bool {
+        if self.worker.is_none() || !self.chats.contains(&chat_id) || message["content"]["@type"] != "messagePhoto" {
+            return false;
+        }
+        // The largest size, which reads best
+        let Some(file_id) = message["content"]["photo"]["sizes"].as_array().and_then(|sizes| sizes.last()).and_then(|size| size["photo"]["id"].as_i64()) else {
+            return false;
+        };
+        let extra = format!("{}{}:{}", EXTRA_PREFIX, chat_id, message["id"]);
+        sender.send(json!({
+            "@type": "downloadFile",
+            "file_id": file_id,
+            "priority": DOWNLOAD_PRIORITY,
+            "offset": 0,
+            "limit": 0,
+            "synchronous": true,
+            "@extra": extra
+        }).to_string());
+        self.downloading.insert(extra, message.clone());
+        true
+    }
+
+    // A downloadFile answer: the photo goes to the OCR thread
+    pub fn downloaded(&mut self, extra: &str, response: &Value) {
+        let Some(message) = self.downloading.remove(extra) else {
+            return;
+        };
+        let path = response["local"]["path"].as_str().filter(|path| !path.is_empty() && response["local"]["is_downloading_completed"] == true);
+        match (path, &self.worker) {
+            (Some(path), Some(worker)) => {
+                let _ = worker.photos.send((path.to_string(), message));
+            }
+            _ => warn!("Can't download the photo for {}: {}", extra, response["message"]),
+        }
+    }
+
+    // Forget downloads whose answers won't come (the client they were asked of is gone)
+    pub fn cancel(&mut self) {
+        self.downloading.clear();
+    }
+
+    // The next photo read, as an update for the receive loop
+    pub fn next_update(&mut self) -> Option<String> {
+        self.worker.as_ref()?.updates.try_recv().ok()
+    }
+}
+
+fn spawn(languages: String) -> Worker {
+    let (sender, photos) = mpsc::channel::<(String, Value)>();
+    let (done, updates) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("ocr".to_string())
+        .spawn(move || {
+            let mut engine = Engine::new(languages);
+            for (path, message) in photos {
+                let start = Instant::now();
+                match engine.read(&path) {
+                    Ok(text) => {
+                        info!("Read {} characters off the photo of {}:{} in {:?}", text.trim().chars().count(), message["chat_id"], message["id"], start.elapsed());
+                        if done.send(update(message, &text)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => warn!("Can't read the photo of {}:{}: {}", message["chat_id"], message["id"], e),
+                }
+            }
+        })
+        .expect("Failed to start the OCR thread");
+    Worker { photos: sender, updates }
+}
+
+// The message with the text read off its photo in place of the photo, after the caption (whose
+// formatting still applies, the caption coming first)
+fn update(mut message: Value, recognized: &str) -> String {
+    let caption = message["content"]["caption"].take();
+    let text = match caption["text"].as_str().filter(|caption| !caption.is_empty()) {
+        Some(caption) => format!("{}\n{}", caption, recognized.trim()),
+        None => recognized.trim().to_string(),
+    };
+    message["content"] = json!({
+        "@type": "messageText",
+        "text": { "@type": "formattedText", "text": text, "entities": caption["entities"] }
+    });
+    json!({ "@type": "updateNewMessage", "message": message }).to_string()
+}
+
+// Tesseract, set up on the first photo and kept: loading the language data takes longer than
+// reading a screenshot
+#[cfg(feature = "ocr")]
+struct Engine {
+    languages: String,
+    tesseract: Option<tesseract::Tesseract>,
+}
+
+#[cfg(feature = "ocr")]
+impl Engine {
+    fn new(languages: String) -> Self {
+        Self { languages, tesseract: None }
+    }
+
+    fn read(&mut self, path: &str) -> Result<String, String> {
+        let tesseract = match self.tesseract.take() {
+            Some(tesseract) => tesseract,
+            None => tesseract::Tesseract::new(None, Some(&self.languages)).map_err(|e| e.to_string())?,
+        };
+        let mut tesseract = tesseract.set_image(path).map_err(|e| e.to_string())?.recognize().map_err(|e| e.to_string())?;
+        let text = tesseract.get_text().map_err(|e| e.to_string());
+        self.tesseract = Some(tesseract);
+        text
+    }
+}
+
+#[cfg(not(feature = "ocr"))]
+struct Engine;
+
+#[cfg(not(feature = "ocr"))]
+impl Engine {
+    fn new(_languages: String) -> Self {
+        Self
+    }
+
+    fn read(&mut self, _path: &str) -> Result<String, String> {
+        Err("this build has no ocr feature".to_string())
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::{HashMap, HashSet};
+use serde_json::Value;
+use tracing::{info, warn};
+
+// Where forwarded orders may come from, from FORWARD_ORIGINS: "chat:origin" pairs, comma separated,
+// e.g. "-1001234567890:-1009876543210". In a chat with an entry only messages forwarded from one of
+// its origins (a channel or group by its chat ID, a user by their user ID) go through the filters,
+// so orders relayed from a trusted source channel are reacted to and anything typed or forwarded
+// from elsewhere is skipped as origin_not_allowed. Chats without an entry are watched whole
+#[derive(Default)]
+pub struct ForwardOrigins {
+    allowed: HashMap<i64, HashSet<i64>>,
+}
+
+impl ForwardOrigins {
+    pub fn from_env() -> Self {
+        let mut allowed: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for entry in std::env::var("FORWARD_ORIGINS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_entry(entry) {
+                Some((chat_id, origin)) => {
+                    allowed.entry(chat_id).or_default().insert(origin);
+                }
+                None => warn!("Ignoring invalid FORWARD_ORIGINS entry '{}'", entry),
+            }
+        }
+        for (chat_id, origins) in &allowed {
+            info!("Watching only messages forwarded from {:?} in chat {}", origins, chat_id);
+        }
+        Self { allowed }
+    }
+
+    // Whether a message in a watched chat should go through the filters
+    pub fn allows(&self, chat_id: i64, message: &Value) -> bool {
+        self.allowed.get(&chat_id).is_none_or(|origins| origin(message).is_some_and(|origin| origins.contains(&origin)))
+    }
+}
+
+// "chat:origin"
+pub fn parse_entry(entry: &str) -> Option<(i64, i64)> {
+    let (chat_id, origin) = entry.split_once(':')?;
+    Some((chat_id.trim().parse().ok()?, origin.trim().parse().ok()?))
+}
+
+// Who a forwarded message first came from: the channel or group's chat ID, or the user's ID. None
+// for a message that wasn't forwarded, or was by a user who hides their account
+pub fn origin(message: &Value) -> Option<i64> {
+    let origin = &message["forward_info"]["origin"];
+    match origin["@type"].as_str()? {
+        "messageOriginChannel" | "messageForwardOriginChannel" => origin["chat_id"].as_i64(),
+        "messageOriginChat" | "messageForwardOriginChat" => origin["sender_chat_id"].as_i64(),
+        "messageOriginUser" | "messageForwardOriginUser" => origin["sender_user_id"].as_i64(),
+        _ => None,
+    }
+}
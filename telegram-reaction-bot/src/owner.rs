@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use serde_json::Value;
+
+use crate::senders;
+
+// Commands the owner can send in any chat the account sees, to check on and pause the bot while
+// the manager is down, to watch the chat or stop watching it (see watch.rs), and to read or clear
+// the history. Only messages from the account itself or a user in OWNER_USER_IDS count, by the
+// sender's ID; from anyone else they're ordinary messages
+pub enum OwnerCommand {
+    Ping,
+    Status,
+    Pause,
+    Resume,
+    Watch(bool),
+    List(String),   // The arguments, e.g. "50"
+    Clear,
+    Latency,
+    Stats,
+    Why(String),   // The message number or ID
+}
+
+pub struct Owners {
+    users: HashSet<i64>,
+}
+
+impl Owners {
+    pub fn from_env() -> Self {
+        let users = std::env::var("OWNER_USER_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .collect();
+        Self { users }
+    }
+
+    // The owner's command in a new message, if it is one
+    pub fn command(&self, message: &Value, text: &str) -> Option<OwnerCommand> {
+        let text = text.trim();
+        let (name, args) = text.split_once(char::is_whitespace).map_or((text, ""), |(name, args)| (name, args.trim()));
+        let name = name.to_lowercase();
+        let command = match (name.strip_suffix("@reaction_bot").unwrap_or(&name), args.to_lowercase().as_str()) {
+            ("/ping", "") => OwnerCommand::Ping,
+            ("/status", "") => OwnerCommand::Status,
+            ("/pause", "") => OwnerCommand::Pause,
+            ("/resume", "") => OwnerCommand::Resume,
+            ("/watch", "on") => OwnerCommand::Watch(true),
+            ("/watch", "off") => OwnerCommand::Watch(false),
+            ("/list", _) => OwnerCommand::List(args.to_string()),
+            ("/clear", _) => OwnerCommand::Clear,
+            ("/latency", _) => OwnerCommand::Latency,
+            ("/stats", _) => OwnerCommand::Stats,
+            ("/why", _) => OwnerCommand::Why(args.to_string()),
+            _ => return None,
+        };
+        let own = message["is_outgoing"].as_bool() == Some(true);
+        let owner = message["sender_id"]["@type"] == "messageSenderUser" && senders::sender(message).is_some_and(|user| self.users.contains(&user));
+        (own || owner).then_some(command)
+    }
+}
+
+// "3 h 12 min" since `started_at` (Unix time)
+pub fn uptime(started_at: i64) -> String {
+    let secs = (chrono::Utc::now().timestamp() - started_at).max(0);
+    match secs {
+        0..60 => format!("{} s", secs),
+        60..3600 => format!("{} min", secs / 60),
+        _ => format!("{} h {} min", secs / 3600, secs % 3600 / 60),
+    }
+}
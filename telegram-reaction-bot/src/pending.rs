@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_CONFIRM_TIMEOUT_MS: u64 = 3000;
+const DEFAULT_RESENDS: u8 = 2;
+
+// A reaction sent and not yet answered by TDLib
+pub struct Pending {
+    pub failures: u8,              // Requests of the current attempt TDLib rejected (see reaction.rs)
+    pub sent_at: Instant,          // First attempt, for the confirm latency
+    pub posted_at: i64,            // Message date
+    // The message the reaction went to: the post itself, or its discussion group copy. None while
+    // that copy is still being looked up, so there is nothing to resend yet
+    pub target: Option<(i64, i64)>,
+    resends: u8,
+    deadline: Instant,
+}
+
+impl Pending {
+    // Rejected for a reason that may pass (see BotError::is_retryable): send it again as if it timed
+    // out, once `after` is over (right away without it), as one of its resends
+    pub fn retry_later(&mut self, after: Option<Duration>) {
+        self.failures = 0;
+        self.deadline = Instant::now() + after.unwrap_or_default();
+    }
+}
+
+// What to do about a reaction TDLib didn't answer in time
+pub enum Expired {
+    Resend(String, (i64, i64)),   // Send it again with the same "@extra": (chat, message)
+    GiveUp(String),               // Out of resends
+}
+
+// Outstanding reactions by "@extra" tag. Each gets REACTION_CONFIRM_TIMEOUT_MS to be confirmed or
+// rejected; past that it is sent again with the same tag, up to REACTION_RESENDS times, and then
+// given up on as unconfirmed. A late answer to an earlier attempt settles it all the same
+pub struct PendingReactions {
+    pending: HashMap<String, Pending>,
+    timeout: Duration,
+    resends: u8,
+}
+
+impl PendingReactions {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            pending: HashMap::new(),
+            timeout: Duration::from_millis(var("REACTION_CONFIRM_TIMEOUT_MS", DEFAULT_CONFIRM_TIMEOUT_MS).max(1)),
+            resends: var("REACTION_RESENDS", DEFAULT_RESENDS),
+        }
+    }
+
+    pub fn insert(&mut self, extra: String, sent_at: Instant, posted_at: i64, target: Option<(i64, i64)>) {
+        let deadline = sent_at + self.timeout;
+        self.pending.insert(extra, Pending { failures: 0, sent_at, posted_at, target, resends: 0, deadline });
+    }
+
+    pub fn get_mut(&mut self, extra: &str) -> Option<&mut Pending> {
+        self.pending.get_mut(extra)
+    }
+
+    pub fn remove(&mut self, extra: &str) -> Option<Pending> {
+        self.pending.remove(extra)
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Reactions to messages of a chat still waiting for an answer
+    pub fn in_chat(&self, chat_id: i64) -> usize {
+        let prefix = format!("reaction:{}:", chat_id);
+        self.pending.keys().filter(|extra| extra.starts_with(&prefix)).count()
+    }
+
+    // The reaction goes to another message from now on (the discussion group copy), as a fresh attempt
+    pub fn retarget(&mut self, extra: &str, target: (i64, i64)) {
+        let timeout = self.timeout;
+        if let Some(pending) = self.pending.get_mut(extra) {
+            pending.target = Some(target);
+            pending.failures = 0;
+            pending.deadline = Instant::now() + timeout;
+        }
+    }
+
+    // Reactions past their deadline, each moved on to its next attempt or dropped
+    pub fn expired(&mut self) -> Vec<Expired> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        for (extra, pending) in self.pending.iter_mut().filter(|(_, pending)| pending.deadline <= now) {
+            if pending.resends >= self.resends {
+                expired.push(Expired::GiveUp(extra.clone()));
+                continue;
+            }
+            pending.deadline = now + self.timeout;
+            pending.resends += 1;
+            // Still looking up the discussion group copy: nothing to resend, but the attempt counts
+            let Some(target) = pending.target else { continue };
+            pending.failures = 0;
+            expired.push(Expired::Resend(extra.clone(), target));
+        }
+        for expired in &expired {
+            if let Expired::GiveUp(extra) = expired {
+                self.pending.remove(extra);
+            }
+        }
+        expired
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex, RwLock},
+    thread,
+    time::Instant,
+};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    formatted::MessageText,
+    prefilter::{Prefilter, Verdict},
+    rates::Prices,
+    backlog::Depth,
+    standby::{Source, Updates},
+    Evaluation, FilterSettings,
+};
+
+// Updates handed to the workers and not yet taken by the main loop at most
+const MAX_IN_FLIGHT: usize = 256;
+
+// What a worker made of a raw update
+pub struct Prepared {
+    pub verdict: Verdict,
+    pub json: Option<Value>,             // Parsed when the verdict says so
+    pub received: Instant,               // When it was handed to a worker
+    pub parsed: Instant,
+    pub evaluation: Option<Evaluation>,  // The filters' decision on a new message
+}
+
+// The filter settings workers evaluate with, numbered so evaluations made before a change
+// (the manager's /blacklist) aren't used after it
+type Settings = RwLock<(u64, Arc<FilterSettings>)>;
+
+// The prefilter for the watched chats, numbered the same way so updates judged before /watch
+// changed them (see watch.rs) are judged again on the main loop
+type Chats = RwLock<(u64, Arc<Prefilter>)>;
+
+struct Job {
+    seq: u64,
+    source: Source,
+    raw: String,
+    received: Instant,
+}
+
+struct Done {
+    seq: u64,
+    source: Source,
+    raw: String,
+    chats: u64,
+    prepared: Option<Prepared>,   // None when the worker panicked: the main loop does it itself
+}
+
+struct Workers {
+    jobs: mpsc::Sender<Job>,
+    done: mpsc::Receiver<Done>,
+    finished: BTreeMap<u64, Done>,
+    next_seq: u64,
+    next_out: u64,
+    settings: Arc<Settings>,
+    chats: Arc<Chats>,
+    generation: u64,
+    chats_generation: u64,
+    spare: Vec<String>,
+    in_flight: VecDeque<Instant>,   // When the updates with the workers were received, oldest first
+}
+
+// PIPELINE_WORKERS: threads that prefilter, parse and run the filters on updates as they arrive,
+// so a burst of heavy messages is parsed side by side instead of one after another. Everything
+// with state (duplicates, plugins, balance, hooks, budget) and every request sent stays on the main
+// loop, which takes the workers' results strictly in arrival order: reactions go out in the same
+// order as without workers. 0 (the default) does it all on the main loop, reusing its buffers
+pub struct Pipeline {
+    workers: Option<Workers>,
+    current: (Source, String),
+}
+
+impl Pipeline {
+    pub fn from_env(allowed_chat_ids: &HashSet<i64>, filter_settings: &FilterSettings, prices: Arc<Prices>) -> Self {
+        let count = std::env::var("PIPELINE_WORKERS").ok().and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
+        let current = (Source::Primary, String::new());
+        if count == 0 {
+            return Self { workers: None, current };
+        }
+        info!("Parsing and filtering updates on {} worker threads", count);
+        let settings = Arc::new(RwLock::new((0, Arc::new(filter_settings.clone()))));
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        let (finished, done) = mpsc::channel();
+        let chats = Arc::new(RwLock::new((0, Arc::new(Prefilter::new(allowed_chat_ids).ignoring(crate::content::IgnoredContent::from_env())))));
+        for n in 0..count {
+            let queue = queue.clone();
+            let finished = finished.clone();
+            let settings = settings.clone();
+            let prices = prices.clone();
+            let chats = chats.clone();
+            thread::Builder::new()
+                .name(format!("pipeline-{}", n))
+                .spawn(move || loop {
+                    let job = queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                    let Ok(Job { seq, source, raw, received }) = job else { return };
+                    let (generation, prefilter) = chats.read().unwrap_or_else(|e| e.into_inner()).clone();
+                    let prepared = panic::catch_unwind(AssertUnwindSafe(|| prepare(&prefilter, &settings, &prices, &raw, received)))
+                        .map_err(|_| warn!("Pipeline worker failed on an update, handling it on the main loop"))
+                        .ok();
+                    if finished.send(Done { seq, source, raw, chats: generation, prepared }).is_err() {
+                        return;
+                    }
+                })
+                .expect("failed to start a pipeline worker");
+        }
+        let workers = Workers {
+            jobs,
+            done,
+            finished: BTreeMap::new(),
+            next_seq: 0,
+            next_out: 0,
+            settings,
+            chats,
+            generation: 0,
+            chats_generation: 0,
+            spare: Vec::new(),
+            in_flight: VecDeque::new(),
+        };
+        Self { workers: Some(workers), current }
+    }
+
+    // Evaluate with these settings from now on
+    pub fn set_filter_settings(&mut self, filter_settings: &FilterSettings) {
+        if let Some(workers) = &mut self.workers {
+            workers.generation += 1;
+            *workers.settings.write().unwrap_or_else(|e| e.into_inner()) = (workers.generation, Arc::new(filter_settings.clone()));
+        }
+    }
+
+    // Prefilter for these chats from now on (see watch.rs)
+    pub fn set_chats(&mut self, allowed_chat_ids: &HashSet<i64>) {
+        if let Some(workers) = &mut self.workers {
+            workers.chats_generation += 1;
+            let prefilter = Prefilter::new(allowed_chat_ids).ignoring(crate::content::IgnoredContent::from_env());
+            *workers.chats.write().unwrap_or_else(|e| e.into_inner()) = (workers.chats_generation, Arc::new(prefilter));
+        }
+    }
+
+    // For the receive loop's backlog: updates with the workers wait too (see backlog.rs)
+    pub fn take_depth(&mut self, updates: &mut Updates) -> Depth {
+        let depth = updates.take_depth();
+        let with_workers = self.workers.as_ref().and_then(|workers| workers.in_flight.front().copied());
+        Depth { oldest: with_workers.into_iter().chain(depth.oldest).min(), ..depth }
+    }
+
+    // The next update in arrival order, with what the workers made of it; without workers straight
+    // from `updates`. The update stays valid until the next call
+    pub fn next<'a>(&'a mut self, updates: &'a mut Updates, timeout: f64) -> Option<(Source, &'a str, Option<Prepared>)> {
+        let Some(workers) = &mut self.workers else {
+            return updates.next(timeout).map(|(source, raw)| (source, raw, None));
+        };
+
+        // Hand out whatever is already there, waiting only when nothing is on its way
+        while workers.next_seq - workers.next_out < MAX_IN_FLIGHT as u64 {
+            let pending = workers.next_seq > workers.next_out;
+            let Some((source, update)) = updates.next(if pending { 0.0 } else { timeout }) else { break };
+            let mut raw = workers.spare.pop().unwrap_or_default();
+            raw.clear();
+            raw.push_str(update);
+            let received = Instant::now();
+            if workers.jobs.send(Job { seq: workers.next_seq, source, raw, received }).is_err() {
+                break;
+            }
+            workers.next_seq += 1;
+            workers.in_flight.push_back(received);
+        }
+        if workers.next_seq == workers.next_out {
+            return None;
+        }
+
+        // The oldest one, whichever worker had it
+        let done = loop {
+            if let Some(done) = workers.finished.remove(&workers.next_out) {
+                break done;
+            }
+            let done = workers.done.recv().ok()?;
+            workers.finished.insert(done.seq, done);
+        };
+        workers.next_out += 1;
+        workers.in_flight.pop_front();
+        let mut prepared = done.prepared.filter(|_| done.chats == workers.chats_generation);
+        let generation = workers.generation;
+        if let Some(prepared) = &mut prepared {
+            prepared.evaluation = prepared.evaluation.take().filter(|evaluation| evaluation.generation == generation);
+        }
+        let (_, old) = std::mem::replace(&mut self.current, (done.source, done.raw));
+        workers.spare.push(old);
+        Some((self.current.0, &self.current.1, prepared))
+    }
+}
+
+fn prepare(prefilter: &Prefilter, settings: &Settings, prices: &Prices, raw: &str, received: Instant) -> Prepared {
+    let verdict = prefilter.check(raw);
+    let json = (verdict == Verdict::Parse).then(|| crate::parse_update(raw)).flatten();
+    let parsed = Instant::now();
+    let evaluation = json.as_ref().filter(|json| json["@type"] == "updateNewMessage").and_then(|json| {
+        let chat_id = json["message"]["chat_id"].as_i64()?;
+        let text = MessageText::from_tdlib(&json["message"]["content"]["text"]).filter(|text| !text.text.starts_with('/'))?;
+        let (generation, filter_settings) = settings.read().unwrap_or_else(|e| e.into_inner()).clone();
+        Some(Evaluation { generation, ..filter_settings.evaluate(chat_id, &text, prices) })
+    });
+    Prepared { verdict, json, received, parsed, evaluation }
+}
@@ -0,0 +1,117 @@
+use std::{
+    ffi::{c_char, CStr, CString},
+    sync::OnceLock,
+};
+use libloading::Library;
+use tracing::info;
+
+use crate::events::{Event, Subscriber};
+
+// The C ABI plugins are built against (see plugins/botdg_plugin.h); bumped on any incompatible change
+pub const ABI_VERSION: u32 = 1;
+
+type AbiFn = unsafe extern "C" fn() -> u32;
+type NameFn = unsafe extern "C" fn() -> *const c_char;
+type CheckFn = unsafe extern "C" fn(i64, *const c_char) -> i32;
+type EventFn = unsafe extern "C" fn(*const c_char);
+
+// One shared library from PLUGINS
+struct Plugin {
+    name: String,
+    check: Option<CheckFn>,
+    event: Option<EventFn>,
+    _library: Library,   // Keeps the functions above loaded
+}
+
+// Shared libraries listed in PLUGINS (comma separated paths), loaded once at startup, for matching
+// logic and post-match actions that can't live in this crate. Each exports botdg_plugin_abi() and any
+// of botdg_plugin_name(), botdg_plugin_check() (an order that passed every filter is skipped as
+// `plugin` unless it returns 0) and botdg_plugin_event() (every event, as the manager gets it).
+// Plugins run inside the bot's process, on the receive loop: a slow one slows the reactions, a
+// crashing one takes the bot down
+pub struct Plugins(Vec<Plugin>);
+
+static LOADED: OnceLock<Result<Plugins, String>> = OnceLock::new();
+static NONE: Plugins = Plugins(Vec::new());
+
+// The PLUGINS libraries, loaded on first use; an error names the one that couldn't be
+pub fn load() -> Result<&'static Plugins, String> {
+    LOADED.get_or_init(Plugins::from_env).as_ref().map_err(Clone::clone)
+}
+
+// The plugins, or none if they failed to load (run() refuses to start then, see load)
+pub fn loaded() -> &'static Plugins {
+    load().unwrap_or(&NONE)
+}
+
+impl Plugins {
+    fn from_env() -> Result<Self, String> {
+        let paths = std::env::var("PLUGINS").unwrap_or_default();
+        let plugins = paths
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| Plugin::load(path).map_err(|e| format!("plugin {}: {}", path, e)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self(plugins))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|plugin| plugin.name.as_str()).collect()
+    }
+
+    // The first plugin that turns an order down
+    pub fn rejecting(&self, chat_id: i64, text: &str) -> Option<&str> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let text = c_string(text);
+        self.0
+            .iter()
+            .find(|plugin| plugin.check.is_some_and(|check| unsafe { check(chat_id, text.as_ptr()) } != 0))
+            .map(|plugin| plugin.name.as_str())
+    }
+}
+
+impl Subscriber for Plugins {
+    fn on_event(&self, event: &Event) {
+        let mut listeners = self.0.iter().filter_map(|plugin| plugin.event).peekable();
+        if listeners.peek().is_none() {
+            return;
+        }
+        let event = c_string(&event.to_json().to_string());
+        for listener in listeners {
+            unsafe { listener(event.as_ptr()) };
+        }
+    }
+}
+
+impl Plugin {
+    fn load(path: &str) -> Result<Self, String> {
+        let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        let abi = unsafe { library.get::<AbiFn>(b"botdg_plugin_abi") }.map_err(|e| e.to_string())?;
+        let abi = unsafe { abi() };
+        if abi != ABI_VERSION {
+            return Err(format!("built for plugin ABI {}, this bot has {}", abi, ABI_VERSION));
+        }
+        let name = unsafe { library.get::<NameFn>(b"botdg_plugin_name") }
+            .ok()
+            .map(|name| unsafe { name() })
+            .filter(|name| !name.is_null())
+            .map(|name| unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        let check = unsafe { library.get::<CheckFn>(b"botdg_plugin_check") }.ok().map(|check| *check);
+        let event = unsafe { library.get::<EventFn>(b"botdg_plugin_event") }.ok().map(|event| *event);
+        info!("Loaded plugin {} from {} (check: {}, events: {})", name, path, check.is_some(), event.is_some());
+        Ok(Self { name, check, event, _library: library })
+    }
+}
+
+// Texts never hold a NUL in practice; one would cut them short rather than lose them
+fn c_string(text: &str) -> CString {
+    CString::new(text.split('\0').next().unwrap_or_default()).unwrap_or_default()
+}
@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+
+const DEFAULT_RECEIVE_TIMEOUT_MS: u64 = 1000;
+const DEFAULT_IDLE_AFTER_SECS: u64 = 60;
+
+// How long the receive loop waits for an update before it runs its timers (heartbeat, control
+// commands, confirmations). TDLib hands over an update the moment it arrives whatever the timeout, so
+// a longer one only delays the timers and saves wake-ups. RECEIVE_TIMEOUT_MS (1000) is used while
+// updates are coming in or reactions await confirmation; after IDLE_AFTER_SECS (60) without any update
+// the loop switches to IDLE_RECEIVE_TIMEOUT_MS, to spare the CPU of a quiet account. No idle timeout
+// when that is unset
+pub struct Polling {
+    active: Duration,
+    idle: Option<Duration>,
+    idle_after: Duration,
+    last_update: Instant,
+}
+
+impl Polling {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.parse::<u64>().ok()).filter(|value| *value > 0);
+        let active = Duration::from_millis(var("RECEIVE_TIMEOUT_MS").unwrap_or(DEFAULT_RECEIVE_TIMEOUT_MS));
+        Self {
+            active,
+            idle: var("IDLE_RECEIVE_TIMEOUT_MS").map(Duration::from_millis).filter(|idle| *idle > active),
+            idle_after: Duration::from_secs(var("IDLE_AFTER_SECS").unwrap_or(DEFAULT_IDLE_AFTER_SECS)),
+            last_update: Instant::now(),
+        }
+    }
+
+    // An update arrived
+    pub fn seen(&mut self) {
+        self.last_update = Instant::now();
+    }
+
+    // The receive timeout in seconds, as TDLib takes it; `busy` while anything waits on a timer
+    pub fn timeout(&self, busy: bool) -> f64 {
+        match self.idle {
+            Some(idle) if !busy && self.last_update.elapsed() >= self.idle_after => idle.as_secs_f64(),
+            _ => self.active.as_secs_f64(),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+
+use crate::content::{self, IgnoredContent};
+
+// Update types the main loop acts on besides new messages; anything else is skipped unparsed
+// unless it answers one of our requests (carries "@extra")
+const HANDLED_TYPES: &[&str] = &[
+    "updateAuthorizationState",
+    "updateConnectionState",
+    "updateMessageInteractionInfo",
+    "error",
+];
+
+// What a raw update needs, judged from its bytes alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    // Parse it and run it through the main loop
+    Parse,
+    // A text message from a chat that isn't monitored and isn't a command
+    ChatNotAllowed,
+    // A message in a monitored chat of a kind in IGNORE_CONTENT_TYPES (see content.rs)
+    ContentIgnored,
+    // Nothing the bot acts on
+    Irrelevant,
+}
+
+// Cheap check run on every update before serde_json touches it. On a busy account most updates
+// are about other chats or of types the bot ignores, and parsing them dominated the receive loop's
+// CPU. Relies on TDLib writing compact JSON with "@type" first, which it always does
+pub struct Prefilter {
+    // `"chat_id":<id>` for each allowed chat
+    needles: Vec<String>,
+    ignored: IgnoredContent,
+}
+
+impl Prefilter {
+    pub fn new(allowed_chat_ids: &HashSet<i64>) -> Self {
+        Self { needles: allowed_chat_ids.iter().map(|id| format!("\"chat_id\":{}", id)).collect(), ignored: IgnoredContent::default() }
+    }
+
+    // Also drop messages of these kinds
+    pub fn ignoring(self, ignored: IgnoredContent) -> Self {
+        Self { ignored, ..self }
+    }
+
+    pub fn check(&self, raw: &str) -> Verdict {
+        let update_type = update_type(raw);
+        if update_type == "updateNewMessage" {
+            // Commands (/list, /stats, ...) are answered in any chat
+            if self.mentions_allowed_chat(raw) || raw.contains("\"text\":\"/") {
+                match content::content_type(raw) {
+                    Some(content_type) if !self.ignored.is_empty() && self.ignored.ignores(content_type) => Verdict::ContentIgnored,
+                    _ => Verdict::Parse,
+                }
+            } else if raw.contains("\"@type\":\"messageText\"") {
+                Verdict::ChatNotAllowed
+            } else {
+                Verdict::Irrelevant
+            }
+        } else if update_type == "updateMessageContent" || update_type == "updateChatTitle" {
+            // Edits of orders, for their lifecycle (see lifecycle.rs), and renamed chats (see names.rs)
+            if self.mentions_allowed_chat(raw) { Verdict::Parse } else { Verdict::Irrelevant }
+        } else if HANDLED_TYPES.contains(&update_type) || raw.contains("\"@extra\":") {
+            Verdict::Parse
+        } else {
+            Verdict::Irrelevant
+        }
+    }
+
+    pub fn is_new_message(&self, raw: &str) -> bool {
+        update_type(raw) == "updateNewMessage"
+    }
+
+    // Whether the update carries one of the allowed chat IDs (and not just an ID starting with one)
+    pub fn mentions_allowed_chat(&self, raw: &str) -> bool {
+        self.needles.iter().any(|needle| {
+            raw.match_indices(needle.as_str())
+                .any(|(start, _)| !raw.as_bytes().get(start + needle.len()).is_some_and(u8::is_ascii_digit))
+        })
+    }
+}
+
+// The value of the first "@type", which is the update's own
+fn update_type(raw: &str) -> &str {
+    const KEY: &str = "\"@type\":\"";
+    raw.find(KEY)
+        .map(|start| &raw[start + KEY.len()..])
+        .and_then(|rest| rest.split_once('"'))
+        .map_or("", |(update_type, _)| update_type)
+}
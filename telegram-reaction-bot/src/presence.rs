@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+use serde_json::json;
+use tracing::info;
+
+use crate::sender::TdSender;
+
+// Whether the account shows as online. An online user session gets its updates pushed sooner on
+// some DCs than an idle one, so KEEP_ONLINE (on by default) sets it online after every warm-up,
+// and ONLINE_REFRESH_SECS, when set, repeats that periodically in case the server let the status
+// lapse. Bots have no presence
+pub struct Presence {
+    enabled: bool,
+    refresh: Duration,
+    last: Instant,
+}
+
+impl Presence {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("KEEP_ONLINE").map_or(true, |v| v == "true" || v == "1") && crate::bot_token().is_none();
+        let refresh = std::env::var("ONLINE_REFRESH_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(0);
+        if enabled && refresh > 0 {
+            info!("Refreshing the online status every {} s", refresh);
+        }
+        Self { enabled, refresh: Duration::from_secs(refresh), last: Instant::now() }
+    }
+
+    pub fn go_online(&mut self, client: &TdSender) {
+        if !self.enabled {
+            return;
+        }
+        client.send(json!({
+            "@type": "setOption",
+            "name": "online",
+            "value": { "@type": "optionValueBoolean", "value": true }
+        }).to_string());
+        self.last = Instant::now();
+    }
+
+    // Set the status again once ONLINE_REFRESH_SECS have passed since the last time
+    pub fn refresh(&mut self, client: &TdSender) {
+        if !self.refresh.is_zero() && self.last.elapsed() >= self.refresh {
+            self.go_online(client);
+        }
+    }
+}
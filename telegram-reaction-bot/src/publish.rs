@@ -0,0 +1,145 @@
+use serde_json::Value;
+use tracing::warn;
+
+// Channel matches are published to unless REDIS_CHANNEL says otherwise
+#[cfg(feature = "redis")]
+const DEFAULT_REDIS_CHANNEL: &str = "botdg:matches";
+// Stream entries kept when REDIS_STREAM_MAXLEN isn't set (trimmed approximately)
+#[cfg(feature = "redis")]
+const DEFAULT_REDIS_STREAM_MAXLEN: u64 = 100_000;
+
+// Hands matched orders to downstream workers (auto-payment tooling, analytics) so they don't
+// have to poll the bot. Publishing happens on a dedicated thread; on the hot path it's a channel send
+pub struct MatchPublisher {
+    #[cfg(feature = "redis")]
+    matches: Option<std::sync::mpsc::Sender<String>>,
+}
+
+impl MatchPublisher {
+    // REDIS_URL enables publishing to REDIS_CHANNEL ("botdg:matches" by default; empty to skip
+    // pub/sub) and, when REDIS_STREAM is set, appending to that stream too, capped at
+    // REDIS_STREAM_MAXLEN entries. Needs the "redis" feature
+    pub fn from_env() -> Self {
+        let url = std::env::var("REDIS_URL").ok().filter(|url| !url.is_empty());
+
+        #[cfg(feature = "redis")]
+        {
+            let Some(url) = url else {
+                return Self { matches: None };
+            };
+            let target = backend::Target::from_env();
+            let client = match redis::Client::open(url.as_str()) {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("Invalid REDIS_URL: {}; not publishing matches", e);
+                    return Self { matches: None };
+                }
+            };
+            tracing::info!(
+                "Publishing matches to Redis (channel: {:?}, stream: {:?})",
+                target.channel, target.stream
+            );
+            let (matches, receiver) = std::sync::mpsc::channel();
+            std::thread::spawn(move || backend::serve(client, target, receiver));
+            Self { matches: Some(matches) }
+        }
+
+        #[cfg(not(feature = "redis"))]
+        {
+            if url.is_some() {
+                warn!("REDIS_URL is set but this build has no redis feature; not publishing matches");
+            }
+            Self {}
+        }
+    }
+
+    // One matched order as JSON (see the "match" event in main.rs)
+    pub fn publish(&self, event: &Value) {
+        #[cfg(feature = "redis")]
+        if let Some(matches) = &self.matches {
+            let _ = matches.send(event.to_string());
+        }
+        #[cfg(not(feature = "redis"))]
+        let _ = event;
+    }
+}
+
+#[cfg(feature = "redis")]
+mod backend {
+    use std::{sync::mpsc, thread, time::Duration};
+    use redis::{Client, Connection, RedisResult};
+    use tracing::{info, warn};
+
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+    // Wait between reconnect attempts while Redis is down
+    const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+    pub struct Target {
+        pub channel: Option<String>,
+        pub stream: Option<String>,
+        maxlen: u64,
+    }
+
+    impl Target {
+        pub fn from_env() -> Self {
+            let channel = std::env::var("REDIS_CHANNEL").unwrap_or_else(|_| super::DEFAULT_REDIS_CHANNEL.to_string());
+            Self {
+                channel: Some(channel).filter(|c| !c.is_empty()),
+                stream: std::env::var("REDIS_STREAM").ok().filter(|s| !s.is_empty()),
+                maxlen: std::env::var("REDIS_STREAM_MAXLEN")
+                    .ok()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(super::DEFAULT_REDIS_STREAM_MAXLEN),
+            }
+        }
+
+        fn publish(&self, connection: &mut Connection, payload: &str) -> RedisResult<()> {
+            if let Some(channel) = &self.channel {
+                redis::cmd("PUBLISH").arg(channel).arg(payload).query::<i64>(connection)?;
+            }
+            if let Some(stream) = &self.stream {
+                redis::cmd("XADD")
+                    .arg(stream)
+                    .arg("MAXLEN")
+                    .arg("~")
+                    .arg(self.maxlen)
+                    .arg("*")
+                    .arg("match")
+                    .arg(payload)
+                    .query::<String>(connection)?;
+            }
+            Ok(())
+        }
+    }
+
+    // A match that can't be published after one reconnect is dropped: it's stale by the time
+    // Redis is back, and the history keeps it anyway
+    pub fn serve(client: Client, target: Target, matches: mpsc::Receiver<String>) {
+        let mut connection: Option<Connection> = None;
+        while let Ok(payload) = matches.recv() {
+            for attempt in 0..2 {
+                if connection.is_none() {
+                    match client.get_connection_with_timeout(CONNECT_TIMEOUT) {
+                        Ok(c) => {
+                            info!("Connected to Redis");
+                            connection = Some(c);
+                        }
+                        Err(e) => {
+                            warn!("Failed to connect to Redis: {}; dropping the match", e);
+                            thread::sleep(RETRY_DELAY);
+                            break;
+                        }
+                    }
+                }
+                let Some(c) = connection.as_mut() else { break };
+                match target.publish(c, &payload) {
+                    Ok(()) => break,
+                    Err(e) => {
+                        warn!("Failed to publish a match to Redis (attempt {}): {}", attempt + 1, e);
+                        connection = None;
+                    }
+                }
+            }
+        }
+    }
+}
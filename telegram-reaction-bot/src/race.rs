@@ -0,0 +1,204 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+
+use crate::accounts::AccountIds;
+
+const DEFAULT_RACE_WINDOW_SECS: u64 = 30;
+
+// A confirmed reaction watched until its window closes
+struct Watch {
+    since: Instant,
+    posted_at: i64,      // Message date, for competitors' reaction delays
+    decided: bool,       // Someone else reacted, so the race already has a verdict
+    final_check: bool,   // The window has passed; the next answer decides the race either way
+}
+
+// Another account's reaction to a matched order
+pub struct Competitor {
+    pub sender_id: i64,    // User ID, or chat ID for reactions on behalf of a chat
+    pub reacted_at: i64,
+    pub delay_secs: i64,   // Since the order was posted
+}
+
+// Whether we were first to react to a matched order, and by how much.
+// Other accounts' reactions are read with getMessageAddedReactions (their dates have one second
+// resolution, but the list itself is in reaction order), whenever TDLib reports a reaction change
+// on an undecided message and once more when the watch window closes, which also collects
+// everyone who reacted for the competitor analytics. Reactions from our race accounts (see
+// accounts.rs) count as ours, not as competitors'
+pub struct RaceTracker {
+    watching: HashMap<(i64, i64), Watch>,
+    accounts: AccountIds,
+    window: Duration,
+    wins: u64,
+    losses: u64,
+    margin_total_secs: i64,   // Over contested races: positive when we were ahead
+    contested: u64,
+}
+
+// The verdict for one match
+pub struct RaceResult {
+    pub won: bool,
+    pub margin_secs: Option<i64>,   // Their first reaction minus ours; None if nobody else reacted
+    pub won_by: Option<String>,     // The race account that was first, if it wasn't the main one
+}
+
+impl RaceTracker {
+    // RACE_WINDOW_SECS sets how long other reactions are waited for
+    pub fn from_env() -> Self {
+        let window = std::env::var("RACE_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_RACE_WINDOW_SECS);
+        Self {
+            watching: HashMap::new(),
+            accounts: AccountIds::default(),
+            window: Duration::from_secs(window),
+            wins: 0,
+            losses: 0,
+            margin_total_secs: 0,
+            contested: 0,
+        }
+    }
+
+    pub fn set_accounts(&mut self, accounts: AccountIds) {
+        self.accounts = accounts;
+    }
+
+    // The race account that sent a reaction, if it was one
+    fn account(&self, reaction: &Value) -> Option<String> {
+        let user_id = reaction["sender_id"]["user_id"].as_i64()?;
+        self.accounts.lock().ok()?.get(&user_id).cloned()
+    }
+
+    fn is_ours(&self, reaction: &Value) -> bool {
+        reaction["is_outgoing"].as_bool().unwrap_or(false) || self.account(reaction).is_some()
+    }
+
+    // Start watching a message once TDLib confirmed our reaction
+    pub fn watch(&mut self, chat_id: i64, message_id: i64, posted_at: i64) {
+        let watch = Watch { since: Instant::now(), posted_at, decided: false, final_check: false };
+        self.watching.insert((chat_id, message_id), watch);
+    }
+
+    // Whether a reaction change on this message could still change the verdict
+    pub fn is_open(&self, chat_id: i64, message_id: i64) -> bool {
+        self.watching.get(&(chat_id, message_id)).is_some_and(|watch| !watch.decided)
+    }
+
+    // Watches whose window just closed, to be checked one last time
+    pub fn expired(&mut self) -> Vec<(i64, i64)> {
+        let window = self.window;
+        self.watching
+            .iter_mut()
+            .filter(|(_, watch)| !watch.final_check && watch.since.elapsed() >= window)
+            .map(|(&target, watch)| {
+                watch.final_check = true;
+                target
+            })
+            .collect()
+    }
+
+    pub fn forget(&mut self, chat_id: i64, message_id: i64) {
+        self.watching.remove(&(chat_id, message_id));
+    }
+
+    // Decide a race from an addedReactions answer; None while it's still open or already decided
+    pub fn resolve(&mut self, chat_id: i64, message_id: i64, added: &Value) -> Option<RaceResult> {
+        let watch = self.watching.get(&(chat_id, message_id))?;
+        if watch.decided {
+            return None;
+        }
+        let final_check = watch.final_check;
+        let reactions = added["reactions"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+        // Newest first
+        let first_date = |ours: bool| {
+            reactions
+                .iter()
+                .filter(|r| self.is_ours(r) == ours)
+                .filter_map(|r| r["date"].as_i64())
+                .min()
+        };
+        let won_by = || reactions.last().and_then(|r| self.account(r));
+        let result = match (first_date(true), first_date(false)) {
+            (Some(ours), Some(theirs)) => {
+                let won = reactions.last().is_some_and(|r| self.is_ours(r));
+                RaceResult { won, margin_secs: Some(theirs - ours), won_by: won_by().filter(|_| won) }
+            }
+            (Some(_), None) if final_check => RaceResult { won: true, margin_secs: None, won_by: won_by() },
+            _ => return None,
+        };
+
+        self.watching.get_mut(&(chat_id, message_id))?.decided = true;
+        if result.won {
+            self.wins += 1;
+        } else {
+            self.losses += 1;
+        }
+        if let Some(margin) = result.margin_secs {
+            self.contested += 1;
+            self.margin_total_secs += margin;
+        }
+        Some(result)
+    }
+
+    // Everyone else who reacted, once the window has closed; ends the watch
+    pub fn finish(&mut self, chat_id: i64, message_id: i64, added: &Value) -> Option<Vec<Competitor>> {
+        if !self.watching.get(&(chat_id, message_id))?.final_check {
+            return None;
+        }
+        let posted_at = self.watching.remove(&(chat_id, message_id))?.posted_at;
+
+        let reactions = added["reactions"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let competitors = reactions
+            .iter()
+            .filter(|r| !self.is_ours(r))
+            .filter_map(|r| {
+                let sender = &r["sender_id"];
+                let sender_id = sender["user_id"].as_i64().or_else(|| sender["chat_id"].as_i64())?;
+                let reacted_at = r["date"].as_i64()?;
+                Some(Competitor { sender_id, reacted_at, delay_secs: reacted_at - posted_at })
+            })
+            .collect();
+        Some(competitors)
+    }
+
+    // Lines appended to /stats
+    pub fn summary(&self) -> String {
+        let races = self.wins + self.losses;
+        if races == 0 {
+            return "🏁 No races decided yet".to_string();
+        }
+        let mut text = format!(
+            "🏁 First on {} of {} matches ({:.1}%)",
+            self.wins,
+            races,
+            self.wins as f64 * 100.0 / races as f64
+        );
+        if self.contested > 0 {
+            text.push_str(&format!(
+                "\nAverage margin over {} contested matches: {:+.1} s",
+                self.contested,
+                self.margin_total_secs as f64 / self.contested as f64
+            ));
+        }
+        text
+    }
+
+    // getMessageAddedReactions request whose answer is routed back by its "@extra" tag
+    pub fn request(chat_id: i64, message_id: i64) -> Value {
+        json!({
+            "@type": "getMessageAddedReactions",
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "reaction_type": null,
+            "offset": "",
+            "limit": 100,
+            "@extra": format!("race:{}:{}", chat_id, message_id)
+        })
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use botdg_core::{language, parse_order_formatted, Languages, Order, PricePatterns};
+use tracing::{info, warn};
+
+use crate::formatted::MessageText;
+
+const DEFAULT_TTL_SECS: u64 = 3600;
+
+// Where exchange rates come from: rubles per unit of each currency it knows, keyed by upper-case
+// code (USD, USDT, EUR, ...)
+pub trait RateProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn fetch(&self) -> Result<HashMap<String, f64>, String>;
+}
+
+// EXCHANGE_RATES, e.g. "USD:92.5,USDT:93,EUR:100". Also the fallback while an HTTP source
+// hasn't answered yet or is down
+pub struct StaticRates {
+    rates: HashMap<String, f64>,
+}
+
+impl StaticRates {
+    pub fn from_env() -> Self {
+        let rates = std::env::var("EXCHANGE_RATES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| {
+                let (currency, rate) = pair.split_once(':')?;
+                Some((currency.trim().to_uppercase(), rate.trim().parse().ok()?))
+            })
+            .collect();
+        Self { rates }
+    }
+}
+
+impl RateProvider for StaticRates {
+    fn name(&self) -> &str {
+        "EXCHANGE_RATES"
+    }
+
+    fn fetch(&self) -> Result<HashMap<String, f64>, String> {
+        Ok(self.rates.clone())
+    }
+}
+
+// Current rates, cached for EXCHANGE_RATES_TTL_SECS and refreshed in the background, so the hot
+// path only takes a read lock on a small map
+#[derive(Clone)]
+pub struct Rates {
+    table: Arc<RwLock<HashMap<String, f64>>>,
+}
+
+impl Rates {
+    // EXCHANGE_RATES_SOURCE picks the providers: "static" (default, EXCHANGE_RATES only), or
+    // "cbr" and/or "binance" (comma separated; need the "rates-http" feature) on top of it
+    pub fn from_env() -> Self {
+        let fallback = StaticRates::from_env();
+        let table = Arc::new(RwLock::new(fallback.fetch().unwrap_or_default()));
+        let rates = Self { table };
+
+        let ttl = Duration::from_secs(
+            std::env::var("EXCHANGE_RATES_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_TTL_SECS),
+        );
+        let providers = http_providers();
+        if !providers.is_empty() {
+            let cache = rates.clone();
+            std::thread::spawn(move || loop {
+                for provider in &providers {
+                    cache.refresh(provider.as_ref());
+                }
+                std::thread::sleep(ttl);
+            });
+        }
+        rates
+    }
+
+    fn refresh(&self, provider: &dyn RateProvider) {
+        match provider.fetch() {
+            Ok(fetched) => {
+                info!("Exchange rates from {}: {:?}", provider.name(), fetched);
+                if let Ok(mut table) = self.table.write() {
+                    table.extend(fetched);
+                }
+            }
+            Err(e) => warn!("Failed to fetch exchange rates from {}: {}; keeping the previous ones", provider.name(), e),
+        }
+    }
+
+    // Rubles per unit of `currency`
+    pub fn get(&self, currency: &str) -> Option<f64> {
+        if currency == "RUB" {
+            return Some(1.0);
+        }
+        self.table.read().ok()?.get(currency).copied()
+    }
+}
+
+#[cfg(feature = "rates-http")]
+fn http_providers() -> Vec<Box<dyn RateProvider>> {
+    let sources = std::env::var("EXCHANGE_RATES_SOURCE").unwrap_or_default().to_lowercase();
+    sources
+        .split(',')
+        .filter_map(|source| match source.trim() {
+            "" | "static" => None,
+            "cbr" => Some(Box::new(http::HttpRates::new(http::Source::Cbr)) as Box<dyn RateProvider>),
+            "binance" => Some(Box::new(http::HttpRates::new(http::Source::Binance)) as Box<dyn RateProvider>),
+            other => {
+                warn!("Unknown EXCHANGE_RATES_SOURCE '{}' (static, cbr or binance)", other);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "rates-http"))]
+fn http_providers() -> Vec<Box<dyn RateProvider>> {
+    let sources = std::env::var("EXCHANGE_RATES_SOURCE").unwrap_or_default();
+    if !matches!(sources.trim(), "" | "static") {
+        warn!("EXCHANGE_RATES_SOURCE={} needs a build with the rates-http feature; using EXCHANGE_RATES only", sources);
+    }
+    Vec::new()
+}
+
+#[cfg(feature = "rates-http")]
+mod http {
+    use std::{collections::HashMap, time::Duration};
+    use serde_json::Value;
+
+    use super::RateProvider;
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+    const CBR_URL: &str = "https://www.cbr-xml-daily.ru/daily_json.js";
+    const BINANCE_URL: &str = "https://api.binance.com/api/v3/ticker/price?symbol=USDTRUB";
+
+    pub enum Source {
+        // Official Bank of Russia rates for every currency it publishes
+        Cbr,
+        // The USDT/RUB spot price, for orders in USDT
+        Binance,
+    }
+
+    pub struct HttpRates {
+        source: Source,
+        agent: ureq::Agent,
+    }
+
+    impl HttpRates {
+        pub fn new(source: Source) -> Self {
+            Self { source, agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build() }
+        }
+
+        fn get(&self, url: &str) -> Result<Value, String> {
+            self.agent
+                .get(url)
+                .call()
+                .map_err(|e| e.to_string())?
+                .into_json::<Value>()
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    impl RateProvider for HttpRates {
+        fn name(&self) -> &str {
+            match self.source {
+                Source::Cbr => "CBR",
+                Source::Binance => "Binance",
+            }
+        }
+
+        fn fetch(&self) -> Result<HashMap<String, f64>, String> {
+            match self.source {
+                // {"Valute": {"USD": {"Nominal": 1, "Value": 92.5}, ...}}
+                Source::Cbr => {
+                    let body = self.get(CBR_URL)?;
+                    let currencies = body["Valute"].as_object().ok_or("no Valute in the response")?;
+                    Ok(currencies
+                        .iter()
+                        .filter_map(|(code, currency)| {
+                            let nominal = currency["Nominal"].as_f64().filter(|n| *n > 0.0)?;
+                            Some((code.to_uppercase(), currency["Value"].as_f64()? / nominal))
+                        })
+                        .collect())
+                }
+                // {"symbol": "USDTRUB", "price": "93.10000000"}
+                Source::Binance => {
+                    let body = self.get(BINANCE_URL)?;
+                    let price = body["price"]
+                        .as_str()
+                        .and_then(|price| price.parse::<f64>().ok())
+                        .ok_or("no price in the response")?;
+                    Ok(HashMap::from([("USDT".to_string(), price)]))
+                }
+            }
+        }
+    }
+}
+
+// Finds an order's amount and converts it to rubles, so MIN_AMOUNT, the per-bank minimums and
+// the recorded history (and with it the manager's earnings reports) work for orders in any
+// currency with a known rate. PRICE_PATTERNS=default,amount_en picks the formats the amount is
+// read in from botdg-core's library (patterns.rs), for exchange bots that don't write "Сумма: 50 000 ₽".
+// With ORDER_LANGUAGES (ru, en) a message is only read by the patterns for the languages it's written
+// in, so "Rita: 50 000 ₽ for the tickets" isn't taken for "Сумма: 50 000 ₽", and messages in none of
+// them (emoji and sticker chatter included) aren't read at all
+pub struct Prices {
+    rates: Rates,
+    patterns: PricePatterns,
+    languages: Option<Languages>,
+}
+
+impl Prices {
+    pub fn from_env() -> Self {
+        let patterns = price_patterns();
+        info!("Price patterns: {:?}", patterns);
+        // Checked by validate.rs
+        let languages = std::env::var("ORDER_LANGUAGES").ok().and_then(|names| Languages::parse(&names).ok()).filter(|languages| !languages.is_empty());
+        if let Some(languages) = languages {
+            info!("Reading orders in {}", languages.names().join(", "));
+        }
+        Self { rates: Rates::from_env(), patterns, languages }
+    }
+
+    // Whether a message is written in one of ORDER_LANGUAGES, always true without them
+    pub fn speaks(&self, text: &MessageText) -> bool {
+        self.languages.is_none_or(|languages| language::detect(text.text).intersects(languages))
+    }
+
+    // The order in a message (see botdg-core), its amount converted at the current rate
+    pub fn order(&self, text: &MessageText) -> Option<Order> {
+        let patterns = match self.languages {
+            Some(languages) => Cow::Owned(self.patterns.only(language::detect(text.text) & languages)),
+            None => Cow::Borrowed(&self.patterns),
+        };
+        let mut order = parse_order_formatted(text.text, &text.entities, &patterns)?;
+        order.convert(|currency| {
+            let rate = self.rates.get(currency);
+            if rate.is_none() {
+                warn!("No exchange rate for {}; set EXCHANGE_RATES or EXCHANGE_RATES_SOURCE", currency);
+            }
+            rate
+        });
+        Some(order)
+    }
+
+    // Rubles, rounded down; None if there is no amount or no rate for its currency
+    pub fn extract(&self, text: &MessageText) -> Option<i32> {
+        self.order(text)?.rubles
+    }
+}
+
+// The amount formats PRICE_PATTERNS picks
+pub fn price_patterns() -> PricePatterns {
+    std::env::var("PRICE_PATTERNS").ok().filter(|names| !names.trim().is_empty()).map_or_else(PricePatterns::default, |names| {
+        PricePatterns::parse(&names).unwrap_or_else(|e| {
+            warn!("Ignoring invalid PRICE_PATTERNS: {}", e);
+            PricePatterns::default()
+        })
+    })
+}
@@ -0,0 +1,86 @@
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+// TDLib 1.8.8 replaced addMessageReaction's `reaction` string with a `reaction_type` object
+const TYPED_SINCE: (u32, u32, u32) = (1, 8, 8);
+
+// Which addMessageReaction schema the installed TDLib takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionFormat {
+    // `reaction_type: reactionTypeEmoji`
+    Typed,
+    // `reaction: "👍"`
+    Legacy,
+    // Both requests for every reaction, one of which is expected to fail
+    Both,
+}
+
+impl ReactionFormat {
+    // REACTION_FORMAT: "auto" (default) picks the format from the version TDLib reported, "typed"
+    // or "legacy" force one, and "both" sends both formats for every reaction, for TDLib builds
+    // that report a misleading version
+    pub fn from_env(tdlib_version: Option<&str>) -> Self {
+        let configured = std::env::var("REACTION_FORMAT").unwrap_or_default().to_lowercase();
+        let format = match configured.as_str() {
+            "typed" => Self::Typed,
+            "legacy" => Self::Legacy,
+            "both" => Self::Both,
+            "" | "auto" => match tdlib_version.and_then(parse_version) {
+                Some(version) if version >= TYPED_SINCE => Self::Typed,
+                Some(_) => Self::Legacy,
+                None => {
+                    warn!("TDLib didn't report a usable version ({:?}); sending both reaction formats", tdlib_version);
+                    Self::Both
+                }
+            },
+            other => {
+                warn!("Unknown REACTION_FORMAT '{}', sending both reaction formats", other);
+                Self::Both
+            }
+        };
+        info!("Reaction request format: {:?} (TDLib {})", format, tdlib_version.unwrap_or("version unknown"));
+        format
+    }
+
+    // The addMessageReaction request(s) for one reaction, all tagged with `extra`
+    pub fn requests(self, chat_id: i64, message_id: i64, emoji: &str, extra: &str) -> Vec<Value> {
+        let typed = || json!({
+            "@type": "addMessageReaction",
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "reaction_type": {
+                "@type": "reactionTypeEmoji",
+                "emoji": emoji
+            },
+            "is_big": false,
+            "@extra": extra
+        });
+        let legacy = || json!({
+            "@type": "addMessageReaction",
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "reaction": emoji,
+            "is_big": false,
+            "@extra": extra
+        });
+        match self {
+            Self::Typed => vec![typed()],
+            Self::Legacy => vec![legacy()],
+            Self::Both => vec![typed(), legacy()],
+        }
+    }
+
+    // How many rejected requests mean the reaction failed
+    pub fn request_count(self) -> u8 {
+        match self {
+            Self::Both => 2,
+            _ => 1,
+        }
+    }
+}
+
+// "1.8.29" -> (1, 8, 29)
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
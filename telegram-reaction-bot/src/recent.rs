@@ -0,0 +1,82 @@
+use std::{cell::RefCell, collections::VecDeque};
+use serde_json::{json, Value};
+
+use crate::events::{Event, Outcome, Subscriber};
+
+// Reactions kept for the manager's /status
+const MAX_RECENT: usize = 10;
+
+struct Recent {
+    target: String,
+    chat_title: Option<String>,
+    amount: Option<i32>,
+    latency_us: u64,
+    outcome: &'static str,
+}
+
+// The last reactions with their latency and how they ended, for the manager to ask for with the
+// "recent" control command: "pending" until TDLib answers, then "sent", "failed" or
+// "unconfirmed", and "won" or "lost" once the race for the order is decided (see race.rs)
+#[derive(Default)]
+pub struct RecentReactions {
+    reactions: RefCell<VecDeque<Recent>>,
+}
+
+impl RecentReactions {
+    pub fn race(&self, target: &str, won: bool) {
+        self.settle(target, if won { "won" } else { "lost" });
+    }
+
+    // Newest first
+    pub fn to_json(&self) -> Value {
+        let reactions = self.reactions.borrow();
+        Value::Array(
+            reactions
+                .iter()
+                .rev()
+                .map(|recent| json!({
+                    "target": recent.target,
+                    "chat_title": recent.chat_title,
+                    "amount": recent.amount,
+                    "latency_us": recent.latency_us,
+                    "outcome": recent.outcome,
+                }))
+                .collect(),
+        )
+    }
+
+    fn settle(&self, target: &str, outcome: &'static str) {
+        if let Some(recent) = self.reactions.borrow_mut().iter_mut().rev().find(|recent| recent.target == target) {
+            recent.outcome = outcome;
+        }
+    }
+}
+
+impl Subscriber for RecentReactions {
+    fn on_event(&self, event: &Event) {
+        match event {
+            Event::Matched(order) => {
+                let mut reactions = self.reactions.borrow_mut();
+                if reactions.len() == MAX_RECENT {
+                    reactions.pop_front();
+                }
+                reactions.push_back(Recent {
+                    target: order.target.to_string(),
+                    chat_title: order.chat_title.map(str::to_string),
+                    amount: order.price,
+                    latency_us: order.latency.as_micros() as u64,
+                    outcome: "pending",
+                });
+            }
+            Event::Reaction { target, outcome } => self.settle(
+                target,
+                match outcome {
+                    Outcome::Sent => "sent",
+                    Outcome::Failed(_) => "failed",
+                    Outcome::Unconfirmed => "unconfirmed",
+                },
+            ),
+            Event::Status(_) => {}
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{collections::HashSet, io::Write, sync::mpsc, thread};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::logfile::RotatingFile;
+
+// Opt-in recorder of raw TDLib updates for `replay` and for working out after the fact why a
+// message wasn't reacted to. RECORD_UPDATES sets the file (newline-delimited JSON, one update per
+// line), rotated by RECORD_UPDATES_MAX_BYTES, RECORD_UPDATES_MAX_AGE_HOURS and RECORD_UPDATES_KEEP.
+// RECORD_UPDATES_SCOPE=allowed (default) keeps only updates from ALLOWED_CHAT_IDS, "all" keeps everything.
+// Lines are written on a separate thread, so the hot path only pays for a channel send
+pub struct Recorder {
+    lines: Option<mpsc::Sender<String>>,
+    all_chats: bool,
+}
+
+impl Recorder {
+    pub fn from_env() -> Self {
+        let disabled = Self { lines: None, all_chats: false };
+        let Some(path) = std::env::var("RECORD_UPDATES").ok().filter(|p| !p.is_empty()) else {
+            return disabled;
+        };
+        let all_chats = match std::env::var("RECORD_UPDATES_SCOPE").unwrap_or_default().to_lowercase().as_str() {
+            "" | "allowed" => false,
+            "all" => true,
+            other => {
+                warn!("Unknown RECORD_UPDATES_SCOPE '{}', recording only allowed chats", other);
+                false
+            }
+        };
+
+        let mut file = match RotatingFile::open_with_limits(&path, "RECORD_UPDATES", false) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to open update recording {}: {}; not recording", path, e);
+                return disabled;
+            }
+        };
+        info!("Recording {} updates to {}", if all_chats { "all" } else { "allowed chats'" }, path);
+
+        let (lines, receiver) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            while let Ok(mut line) = receiver.recv() {
+                line.push('\n');
+                if let Err(e) = file.write_all(line.as_bytes()) {
+                    warn!("Failed to record update to {}: {}", path, e);
+                }
+            }
+        });
+        Self { lines: Some(lines), all_chats }
+    }
+
+    pub fn record(&self, raw: &str, update: &Value, allowed_chat_ids: &HashSet<i64>) {
+        let Some(lines) = &self.lines else { return };
+        if !self.all_chats {
+            // Updates about a chat carry its ID either at the top level or in the message
+            let chat_id = update["chat_id"].as_i64().or_else(|| update["message"]["chat_id"].as_i64());
+            if !chat_id.is_some_and(|chat_id| allowed_chat_ids.contains(&chat_id)) {
+                return;
+            }
+        }
+        let _ = lines.send(raw.to_string());
+    }
+
+    // For updates the prefilter skipped (see prefilter.rs), with its byte-level guess at whether
+    // they're about an allowed chat
+    pub fn record_unparsed(&self, raw: &str, about_allowed_chat: bool) {
+        let Some(lines) = &self.lines else { return };
+        if self.all_chats || about_allowed_chat {
+            let _ = lines.send(raw.to_string());
+        }
+    }
+}
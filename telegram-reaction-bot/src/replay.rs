@@ -0,0 +1,110 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::File,
+    io::{BufRead, BufReader},
+};
+use crate::{
+    decide,
+    formatted::MessageText,
+    get_allowed_chat_ids, parse_update,
+    history::History,
+    rates::Prices,
+    remember_reaction,
+    scam::ScamChecks,
+    stats::{FilterStats, SkipReason},
+    origins::ForwardOrigins,
+    senders::OrderSenders,
+    topics::Topics,
+    FilterSettings,
+};
+
+// `replay FILE... [--reacted]`: feed recorded TDLib updates (newline-delimited JSON, one update per
+// line) through the filters with the current settings. Reactions aren't sent; each message's decision
+// is printed instead, so filter or parser changes can be checked against real traffic by diffing the
+// output of two runs
+pub fn run(files: &[String], only_reacted: bool) -> Result<(), String> {
+    let filter_settings = FilterSettings::from_env();
+    let allowed_chat_ids = get_allowed_chat_ids();
+    let topics = Topics::from_env();
+    let forward_origins = ForwardOrigins::from_env();
+    let order_senders = OrderSenders::from_env();
+    // Starting from nothing rather than the history: the first order of every sender is new
+    let mut scam_checks = ScamChecks::from_env();
+    let history = History::disabled();
+    let prices = Prices::from_env();
+    let mut filter_stats = FilterStats::default();
+    let mut recent_reactions = HashSet::new();
+    let mut recent_order = VecDeque::new();
+    let mut updates = 0;
+    let mut invalid = 0;
+
+    for file in files {
+        let reader = BufReader::new(File::open(file).map_err(|e| format!("Failed to open {}: {}", file, e))?);
+        for line in reader.lines() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", file, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some(update) = parse_update(&line) else {
+                invalid += 1;
+                continue;
+            };
+            updates += 1;
+            if update["@type"] != "updateNewMessage" {
+                continue;
+            }
+
+            let message = &update["message"];
+            let (Some(chat_id), Some(message_id), Some(text)) = (
+                message["chat_id"].as_i64(),
+                message["id"].as_i64(),
+                MessageText::from_tdlib(&message["content"]["text"]),
+            ) else {
+                continue;
+            };
+
+            // Same order of checks as the main loop
+            let decision = if !allowed_chat_ids.contains(&chat_id) {
+                Err(SkipReason::ChatNotAllowed)
+            } else if !topics.allows(chat_id, message["message_thread_id"].as_i64().unwrap_or(0)) {
+                Err(SkipReason::TopicNotAllowed)
+            } else if !forward_origins.allows(chat_id, message) {
+                Err(SkipReason::OriginNotAllowed)
+            } else if !order_senders.allows(chat_id, message) {
+                Err(SkipReason::SenderNotAllowed)
+            } else {
+                decide(chat_id, message_id, &text, None, &recent_reactions, &filter_settings, &prices)
+                    .and_then(|()| scam_checks.check(&history, chat_id, message, || prices.order(&text).unwrap_or_default()))
+            };
+            match decision {
+                Ok(()) => {
+                    filter_stats.reacted();
+                    remember_reaction(&mut recent_reactions, &mut recent_order, (chat_id, message_id));
+                }
+                Err(reason) => filter_stats.skipped(reason),
+            }
+            if only_reacted && decision.is_err() {
+                continue;
+            }
+
+            let time = message["date"]
+                .as_i64()
+                .and_then(|date| chrono::DateTime::from_timestamp(date, 0))
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default();
+            println!(
+                "{} {}:{} {} | {} ₽ | {}",
+                time,
+                chat_id,
+                message_id,
+                decision.map_or_else(|reason| format!("skip {}", reason.name()), |()| "react".to_string()),
+                prices.extract(&text).map_or("?".to_string(), |p| p.to_string()),
+                prices.order(&text).and_then(|order| order.bank).unwrap_or_else(|| "?".to_string())
+            );
+        }
+    }
+
+    eprintln!("Replayed {} updates ({} unreadable lines skipped)", updates, invalid);
+    eprintln!("{}", filter_stats.summary());
+    Ok(())
+}
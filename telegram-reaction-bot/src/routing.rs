@@ -0,0 +1,76 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::prefilter::Prefilter;
+
+// What HOT_CHAT_IDS stand for among CHAT_PRIORITIES
+const HOT_PRIORITY: u32 = 100;
+const DEFAULT_MAX_WAIT_MS: u64 = 500;
+
+// Which of the updates already received goes first. New messages from chats with a priority
+// (CHAT_PRIORITIES=-1001234567890:10,-1009876543210:5; HOT_CHAT_IDS count as 100) go before
+// everything with a lower one, and everything else (other chats, reaction results, service updates)
+// has priority 0. Among equals the oldest goes first. So that a busy high-priority chat can't hold
+// back the rest for good, an update that has waited MAX_QUEUE_WAIT_MS (500) goes first whatever its
+// priority
+pub struct Router {
+    levels: Vec<(u32, Prefilter)>,   // Highest priority first
+    pub max_wait: Duration,
+}
+
+impl Router {
+    // None when no chat has a priority, and updates are taken as they come
+    pub fn from_env(hot_chats: &HashSet<i64>) -> Option<Self> {
+        let mut priorities: BTreeMap<i64, u32> = match parse(&std::env::var("CHAT_PRIORITIES").unwrap_or_default()) {
+            Ok(priorities) => priorities,
+            Err(e) => {
+                warn!("Ignoring invalid CHAT_PRIORITIES: {}", e);
+                BTreeMap::new()
+            }
+        };
+        for chat in hot_chats {
+            priorities.entry(*chat).or_insert(HOT_PRIORITY);
+        }
+        priorities.retain(|_, priority| *priority > 0);
+        if priorities.is_empty() {
+            return None;
+        }
+        info!("Handling chats by priority: {:?}", priorities);
+
+        let mut levels: BTreeMap<u32, HashSet<i64>> = BTreeMap::new();
+        for (chat, priority) in priorities {
+            levels.entry(priority).or_default().insert(chat);
+        }
+        let max_wait = std::env::var("MAX_QUEUE_WAIT_MS").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_MAX_WAIT_MS);
+        Some(Self {
+            levels: levels.into_iter().rev().map(|(priority, chats)| (priority, Prefilter::new(&chats))).collect(),
+            max_wait: Duration::from_millis(max_wait),
+        })
+    }
+
+    // A raw update's priority
+    pub fn priority(&self, raw: &str) -> u32 {
+        let Some((_, any)) = self.levels.first() else { return 0 };
+        if !any.is_new_message(raw) {
+            return 0;
+        }
+        self.levels.iter().find(|(_, chats)| chats.mentions_allowed_chat(raw)).map_or(0, |(priority, _)| *priority)
+    }
+}
+
+// CHAT_PRIORITIES as chat => priority
+pub fn parse(value: &str) -> Result<BTreeMap<i64, u32>, String> {
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (chat, priority) = entry.rsplit_once(':').ok_or_else(|| format!("'{}' is not chat:priority", entry.trim()))?;
+            let chat = chat.trim().parse::<i64>().map_err(|_| format!("'{}' is not a chat ID", chat.trim()))?;
+            let priority = priority.trim().parse::<u32>().map_err(|_| format!("'{}' is not a priority", priority.trim()))?;
+            Ok((chat, priority))
+        })
+        .collect()
+}
@@ -0,0 +1,196 @@
+use std::sync::{mpsc, Mutex, OnceLock};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::events::{Event, Subscriber};
+
+// A request from the control socket, for the receive loop to answer. `command` is in the control
+// channel's form, {"command": method, ...params}, so both are handled by the same code
+pub struct Request {
+    pub command: Value,
+    id: Option<Value>,   // None for a notification, which gets no answer
+    out: mpsc::Sender<String>,
+}
+
+// Why a request failed, as JSON-RPC error codes
+pub enum Fault {
+    UnknownMethod,
+    InvalidParams(String),
+    Refused(String),   // Understood, but not done in the bot's state (resume under the kill switch)
+}
+
+impl Request {
+    pub fn answer(self, answer: Result<Value, Fault>) {
+        let Some(id) = self.id else { return };
+        let response = match answer {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(fault) => {
+                let (code, message) = match fault {
+                    Fault::UnknownMethod => (-32601, format!("unknown method '{}'", self.command["command"].as_str().unwrap_or_default())),
+                    Fault::InvalidParams(message) => (-32602, message),
+                    Fault::Refused(message) => (1, message),
+                };
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        // The client may have gone away meanwhile
+        let _ = self.out.send(response.to_string());
+    }
+}
+
+struct Server {
+    requests: Mutex<mpsc::Receiver<Request>>,
+    tails: Mutex<Vec<mpsc::Sender<String>>>,
+}
+
+// CONTROL_SOCKET=/run/botdg/default.sock: JSON-RPC 2.0 over a Unix domain socket, one request or
+// response per line, for the manager (see its rpc.rs) and scripts (`socat - UNIX:...`) to control
+// a running bot without restarting it. Methods, answered by the receive loop:
+//
+//   status        paused, kill switch, connection state, chats and filters
+//   stats         skipped orders by reason, reactions, error rate, disk, recent reactions
+//   set_filters   {"min_amount": 50000, "bank": "Т", "requisite": null, "reaction_emoji": "🔥"};
+//                 null clears a filter, anything left out stays
+//   pause, resume, panic, unpanic, balance, blacklist, backfill, recent   as over the control channel
+//   tail_events   every event from then on, as "event" notifications with the control channel's JSON
+//
+// The socket is readable by the bot's user only. One that another bot is still listening on is
+// left alone; a stale one from a bot that's gone is replaced
+static SERVER: OnceLock<Option<Server>> = OnceLock::new();
+
+fn server() -> Option<&'static Server> {
+    SERVER.get_or_init(listen).as_ref()
+}
+
+#[cfg(unix)]
+fn listen() -> Option<Server> {
+    use std::os::unix::{fs::PermissionsExt, net::{UnixListener, UnixStream}};
+    use tracing::info;
+
+    let path = std::env::var("CONTROL_SOCKET").ok().filter(|path| !path.trim().is_empty())?;
+    if UnixStream::connect(&path).is_ok() {
+        warn!("Another bot is listening on the control socket {}, not opening it", path);
+        return None;
+    }
+    let _ = std::fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Failed to open the control socket {}: {}", path, e);
+            return None;
+        }
+    };
+    if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+        warn!("Failed to make the control socket {} private: {}", path, e);
+    }
+    info!("Answering JSON-RPC on {}", path);
+
+    let (requests, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let requests = requests.clone();
+            std::thread::spawn(move || {
+                if let Ok(writer) = stream.try_clone() {
+                    serve(stream, writer, requests);
+                }
+            });
+        }
+    });
+    Some(Server { requests: Mutex::new(receiver), tails: Mutex::new(Vec::new()) })
+}
+
+// Unix domain sockets are Unix-only; the control channel on stdio works everywhere
+#[cfg(not(unix))]
+fn listen() -> Option<Server> {
+    if std::env::var("CONTROL_SOCKET").is_ok_and(|path| !path.trim().is_empty()) {
+        warn!("CONTROL_SOCKET is only supported on Unix");
+    }
+    None
+}
+
+// One connection: requests are read here, answers and events written by a thread of its own so
+// a slow client never holds up the receive loop
+#[cfg(unix)]
+fn serve(reader: impl std::io::Read, mut writer: impl std::io::Write + Send + 'static, requests: mpsc::Sender<Request>) {
+    use std::io::BufRead;
+
+    let (out, lines) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in lines {
+            if writeln!(writer, "{}", line).and_then(|()| writer.flush()).is_err() {
+                break;
+            }
+        }
+    });
+
+    for line in std::io::BufReader::new(reader).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |id: &Value, code: i32, message: &str| {
+            json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+        };
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = out.send(error(&Value::Null, -32700, &e.to_string()));
+                continue;
+            }
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request["method"].as_str() else {
+            let _ = out.send(error(id.as_ref().unwrap_or(&Value::Null), -32600, "not a JSON-RPC request"));
+            continue;
+        };
+        let mut command = match &request["params"] {
+            Value::Object(params) => params.clone(),
+            Value::Null => serde_json::Map::new(),
+            _ => {
+                let _ = out.send(error(id.as_ref().unwrap_or(&Value::Null), -32602, "params must be an object"));
+                continue;
+            }
+        };
+
+        // Nothing for the receive loop to do: events are handed to the connection as they happen
+        if method == "tail_events" {
+            if let Some(id) = &id {
+                let _ = out.send(json!({ "jsonrpc": "2.0", "id": id, "result": { "subscribed": true } }).to_string());
+            }
+            if let Some(server) = server() {
+                server.tails.lock().unwrap_or_else(|e| e.into_inner()).push(out.clone());
+            }
+            continue;
+        }
+        command.insert("command".to_string(), method.into());
+        if requests.send(Request { command: Value::Object(command), id, out: out.clone() }).is_err() {
+            break;
+        }
+    }
+}
+
+// Open the socket (when configured) before the receive loop asks for requests, so clients can
+// connect while the bot logs in
+pub fn open() {
+    server();
+}
+
+// The next request for the receive loop, if any (never blocks)
+pub fn try_request() -> Option<Request> {
+    server()?.requests.lock().unwrap_or_else(|e| e.into_inner()).try_recv().ok()
+}
+
+// Every event to the connections tailing them; those that have gone away are dropped
+pub struct EventTail;
+
+impl Subscriber for EventTail {
+    fn on_event(&self, event: &Event) {
+        let Some(server) = server() else { return };
+        let mut tails = server.tails.lock().unwrap_or_else(|e| e.into_inner());
+        if tails.is_empty() {
+            return;
+        }
+        let notification = json!({ "jsonrpc": "2.0", "method": "event", "params": event.to_json() }).to_string();
+        tails.retain(|tail| tail.send(notification.clone()).is_ok());
+    }
+}
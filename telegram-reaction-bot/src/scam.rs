@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use botdg_core::{normalize, Order};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{
+    history::{History, SeenOrder},
+    senders,
+    stats::SkipReason,
+};
+
+// SCAM_CHECKS names
+const CHECKS: [&str; 3] = ["new_sender", "bank_conflict", "commission"];
+const DEFAULT_MAX_COMMISSION: f64 = 10.0;
+
+// Heuristics for fake orders, run on orders that passed every filter. SCAM_CHECKS is a comma
+// separated list of:
+// - new_sender: the first order from someone in a chat
+// - bank_conflict: a requisite given with another bank before, in any chat (and from then on, with any)
+// - commission: a stated commission over SCAM_MAX_COMMISSION percent (10), more than a real order pays
+// An order tripping one is skipped as suspicious; with SCAM_ACTION=notify the manager is told about
+// it too. Senders and requisites are remembered in the history (see history.rs), so they survive
+// restarts; every order checked is remembered, so a sender is new only once
+#[derive(Default)]
+pub struct ScamChecks {
+    new_sender: bool,
+    bank_conflict: bool,
+    max_commission: Option<f64>,
+    notify: bool,
+    // (chat, sender) of every order seen, and the banks (as `normalize::bank_key`) each requisite came with
+    senders: HashSet<(i64, i64)>,
+    banks: HashMap<String, HashSet<String>>,
+    notice: Option<Value>,
+}
+
+impl ScamChecks {
+    // Checked by validate.rs
+    pub fn from_env() -> Self {
+        let max_commission = std::env::var("SCAM_MAX_COMMISSION")
+            .ok()
+            .and_then(|s| s.trim().replace(',', ".").parse().ok())
+            .unwrap_or(DEFAULT_MAX_COMMISSION);
+        let notify = std::env::var("SCAM_ACTION").is_ok_and(|action| action.trim().eq_ignore_ascii_case("notify"));
+        let checks = Self::parse(&std::env::var("SCAM_CHECKS").unwrap_or_default(), max_commission).unwrap_or_else(|e| {
+            warn!("SCAM_CHECKS: {}", e);
+            Self::default()
+        });
+        Self { notify, ..checks }
+    }
+
+    pub fn parse(value: &str, max_commission: f64) -> Result<Self, String> {
+        let mut checks = Self::default();
+        for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match name.to_lowercase().as_str() {
+                "new_sender" => checks.new_sender = true,
+                "bank_conflict" => checks.bank_conflict = true,
+                "commission" => checks.max_commission = Some(max_commission),
+                _ => return Err(format!("unknown check '{}' (one of {})", name, CHECKS.join(", "))),
+            }
+        }
+        Ok(checks)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.new_sender || self.bank_conflict || self.max_commission.is_some()
+    }
+
+    // What the history knows of earlier orders, once at startup
+    pub async fn load(&mut self, history: &History) {
+        if !self.is_enabled() {
+            return;
+        }
+        let seen = history.seen_orders().await.unwrap_or_default();
+        for order in &seen {
+            self.remember(order);
+        }
+        info!("Scam checks know {} senders and {} requisites", self.senders.len(), self.banks.len());
+    }
+
+    // Err(Suspicious) for an order that trips one of the checks; `order` is only parsed when
+    // there are any
+    pub fn check(&mut self, history: &History, chat_id: i64, message: &Value, order: impl FnOnce() -> Order) -> Result<(), SkipReason> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let order = order();
+        let seen = SeenOrder {
+            chat_id,
+            sender_id: senders::sender(message),
+            requisite: order.requisite.as_deref().map(normalize::requisite_key),
+            bank: order.bank.clone(),
+            seen_at: chrono::Utc::now().timestamp(),
+        };
+        let tripped = self.tripped(&seen, &order);
+        if self.remember(&seen) {
+            history.record_seen(seen);
+        }
+        if tripped.is_empty() {
+            return Ok(());
+        }
+        info!("Order looks fake ({}), not reacting", tripped.join(", "));
+        if self.notify {
+            self.notice = Some(json!({
+                "event": "suspicious",
+                "chat_id": chat_id,
+                "message_id": message["id"],
+                "checks": tripped,
+                "amount": order.rubles,
+                "bank": order.bank,
+                "commission": order.commission,
+            }));
+        }
+        Err(SkipReason::Suspicious)
+    }
+
+    // The manager's notice about the last suspicious order, with SCAM_ACTION=notify
+    pub fn take_notice(&mut self) -> Option<Value> {
+        self.notice.take()
+    }
+
+    fn tripped(&self, seen: &SeenOrder, order: &Order) -> Vec<&'static str> {
+        let mut tripped = Vec::new();
+        if self.new_sender && seen.sender_id.is_some_and(|sender| !self.senders.contains(&(seen.chat_id, sender))) {
+            tripped.push("new_sender");
+        }
+        if self.bank_conflict {
+            if let (Some(requisite), Some(bank)) = (&seen.requisite, &seen.bank) {
+                let bank = normalize::bank_key(bank);
+                if self.banks.get(requisite).is_some_and(|banks| banks.iter().any(|known| *known != bank)) {
+                    tripped.push("bank_conflict");
+                }
+            }
+        }
+        if self.max_commission.is_some_and(|max| order.commission.is_some_and(|commission| commission > max)) {
+            tripped.push("commission");
+        }
+        tripped
+    }
+
+    // True when the order told us something new, so it's worth recording
+    fn remember(&mut self, seen: &SeenOrder) -> bool {
+        let mut new = seen.sender_id.is_some_and(|sender| self.senders.insert((seen.chat_id, sender)));
+        if let (Some(requisite), Some(bank)) = (&seen.requisite, &seen.bank) {
+            new |= self.banks.entry(requisite.clone()).or_default().insert(normalize::bank_key(bank));
+        }
+        new
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::Read;
+
+use crate::cli::KeyringAction;
+
+// Keyring entries are stored under this service, one entry per variable name
+const DEFAULT_KEYRING_SERVICE: &str = "telegram-reaction-bot";
+
+// Credentials (TELEGRAM_API_ID, TELEGRAM_API_HASH, TELEGRAM_2FA_PASSWORD, TELEGRAM_BOT_TOKEN) come
+// from the environment or .env first, or the files their <NAME>_FILE names (see botdg-config); with the "keyring" feature a missing one is looked up in the
+// OS keyring (macOS Keychain, Windows Credential Manager, Secret Service on Linux), so secrets
+// don't have to be kept in plaintext
+pub fn get(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .or_else(|| from_keyring(name))
+}
+
+fn keyring_service() -> String {
+    std::env::var("KEYRING_SERVICE").unwrap_or_else(|_| DEFAULT_KEYRING_SERVICE.to_string())
+}
+
+fn from_keyring(name: &str) -> Option<String> {
+    match read(name) {
+        Ok(Some(value)) => {
+            tracing::info!("Using {} from the OS keyring", name);
+            Some(value)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {}", name, e);
+            None
+        }
+    }
+}
+
+// A keyring entry: None when there's no such entry, an error when the keyring can't be read
+#[cfg(feature = "keyring")]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    match keyring::Entry::new(&keyring_service(), name).and_then(|entry| entry.get_password()) {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("OS keyring: {}", e)),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read(_name: &str) -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+// `keyring set NAME` (value read from stdin) and `keyring delete NAME`: manage the keyring entries
+// read by `get`
+pub fn run(action: KeyringAction) -> Result<(), String> {
+    match action {
+        KeyringAction::Set { name } => {
+            let mut value = String::new();
+            std::io::stdin().read_to_string(&mut value).map_err(|e| format!("Failed to read the value: {}", e))?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                return Err(format!("No value for {} on stdin", name));
+            }
+            store(&name, Some(value))?;
+            eprintln!("Stored {} in the OS keyring (service '{}')", name, keyring_service());
+        }
+        KeyringAction::Delete { name } => {
+            store(&name, None)?;
+            eprintln!("Deleted {} from the OS keyring (service '{}')", name, keyring_service());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "keyring")]
+pub fn store(name: &str, value: Option<&str>) -> Result<(), String> {
+    let entry = keyring::Entry::new(&keyring_service(), name).map_err(|e| e.to_string())?;
+    match value {
+        Some(value) => entry.set_password(value),
+        None => entry.delete_credential(),
+    }
+    .map_err(|e| format!("OS keyring: {}", e))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store(_name: &str, _value: Option<&str>) -> Result<(), String> {
+    Err("Built without OS keyring support; rebuild with --features keyring".to_string())
+}
@@ -0,0 +1,56 @@
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+};
+use tokio::sync::oneshot;
+
+use crate::client::TelegramClient;
+
+enum Request {
+    Send(String),
+    Reset(oneshot::Sender<()>),
+}
+
+// All requests to TDLib go through one thread that owns the sending side of the client, so
+// queueing a request (a reaction included) never waits for the receive loop, which spends most
+// of its time blocked in td_json_client_receive
+#[derive(Clone)]
+pub struct TdSender {
+    requests: mpsc::Sender<Request>,
+}
+
+impl TdSender {
+    // The thread exits once every TdSender is dropped
+    pub fn spawn(client: Arc<dyn TelegramClient>) -> Self {
+        let (requests, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            crate::tuning::tune_current_thread("sender");
+            // Reused for every request's NUL-terminated copy
+            let mut buffer = Vec::new();
+            while let Ok(request) = receiver.recv() {
+                match request {
+                    Request::Send(request) => client.send(&request, &mut buffer),
+                    Request::Reset(done) => {
+                        client.reset();
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self { requests }
+    }
+
+    // Takes the request by value so it moves to the sender thread without another copy
+    pub fn send(&self, request: String) {
+        let _ = self.requests.send(Request::Send(request));
+    }
+
+    // Replace the client with a fresh one after everything queued before has been sent; the caller
+    // must not be receiving from the client meanwhile
+    pub async fn reset(&self) {
+        let (done, reset) = oneshot::channel();
+        if self.requests.send(Request::Reset(done)).is_ok() {
+            let _ = reset.await;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::{HashMap, HashSet};
+use serde_json::Value;
+use tracing::{info, warn};
+
+// The bots allowed to post orders, from ORDER_SENDERS: "chat:sender" pairs, comma separated, e.g.
+// "-1001234567890:5000000001". In a chat with an entry only messages sent by one of its senders (a
+// user or bot by their user ID, a channel or anonymous admin by its chat ID) go through the filters;
+// anyone else is skipped as sender_not_allowed whatever they write, so a human quoting an order or
+// faking one gets no reaction. Chats without an entry take every sender
+#[derive(Default)]
+pub struct OrderSenders {
+    allowed: HashMap<i64, HashSet<i64>>,
+}
+
+impl OrderSenders {
+    pub fn from_env() -> Self {
+        let mut allowed: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for entry in std::env::var("ORDER_SENDERS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_entry(entry) {
+                Some((chat_id, sender)) => {
+                    allowed.entry(chat_id).or_default().insert(sender);
+                }
+                None => warn!("Ignoring invalid ORDER_SENDERS entry '{}'", entry),
+            }
+        }
+        for (chat_id, senders) in &allowed {
+            info!("Taking orders only from {:?} in chat {}", senders, chat_id);
+        }
+        Self { allowed }
+    }
+
+    // Whether a message in a watched chat should go through the filters
+    pub fn allows(&self, chat_id: i64, message: &Value) -> bool {
+        self.allowed.get(&chat_id).is_none_or(|senders| sender(message).is_some_and(|sender| senders.contains(&sender)))
+    }
+}
+
+// "chat:sender"
+pub fn parse_entry(entry: &str) -> Option<(i64, i64)> {
+    let (chat_id, sender) = entry.split_once(':')?;
+    Some((chat_id.trim().parse().ok()?, sender.trim().parse().ok()?))
+}
+
+// Who sent a message: a user's ID, or the chat ID of a channel or a group posting as itself
+pub fn sender(message: &Value) -> Option<i64> {
+    let sender = &message["sender_id"];
+    match sender["@type"].as_str()? {
+        "messageSenderUser" => sender["user_id"].as_i64(),
+        "messageSenderChat" => sender["chat_id"].as_i64(),
+        _ => None,
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+
+use crate::{
+    client::TelegramClient, decide,
+    formatted::MessageText,
+    get_allowed_chat_ids,
+    latency::percentile,
+    mock::MockClient,
+    rates::Prices,
+    stats::FilterStats,
+    FilterSettings,
+};
+
+// Where simulated orders are posted when ALLOWED_CHAT_IDS is empty
+const SIMULATED_CHAT_ID: i64 = -1000000000001;
+
+const BANKS: [&str; 8] = ["Т-Банк", "T-Bank", "Тинькофф", "Сбербанк", "Альфа-Банк", "ВТБ", "Райффайзен", "Озон Банк"];
+const CHATTER: [&str; 4] = ["Кто на связи?", "Заявка закрыта", "+", "Принял, жду чек"];
+
+// Settings that would make a simulated run touch real things: the manager, the shared history,
+// Redis, the Bot API, a standby session, recordings, hooks
+const SIDE_EFFECTS: [&str; 9] = [
+    "CONTROL_CHANNEL",
+    "HEARTBEAT_FILE",
+    "REDIS_URL",
+    "BOT_API_CHAT_IDS",
+    "STANDBY_TDLIB_DATA_DIR",
+    "RECORD_UPDATES",
+    "BACKFILL_LIMIT",
+    "PRE_REACTION_HOOK",
+    "POST_REACTION_HOOK",
+];
+
+// `simulate [--rate N] [--count N] [--seed N]`: generate order messages and run them through the
+// whole bot (run() with a MockClient in place of TDLib), then report how many were reacted to and
+// how long the reactions took. Filters come from the current settings; nothing reaches Telegram
+pub fn run(rate: f64, count: usize, seed: Option<u64>, verbose: bool) -> Result<(), String> {
+    if rate.is_nan() || rate <= 0.0 {
+        return Err("--rate has to be above 0".to_string());
+    }
+    if verbose {
+        tracing_subscriber::fmt().with_writer(std::io::stderr).with_target(false).init();
+    }
+
+    for name in SIDE_EFFECTS {
+        std::env::remove_var(name);
+    }
+    std::env::set_var("HISTORY_STORE", "memory");
+    std::env::set_var("TDLIB_DATA_DIR", std::env::temp_dir().join(format!("tdlib-test-simulate-{}", std::process::id())));
+    // run() wants credentials even though nothing logs in
+    for (name, placeholder) in [("TELEGRAM_API_ID", "1"), ("TELEGRAM_API_HASH", "simulate")] {
+        if crate::secrets::get(name).is_none() {
+            std::env::set_var(name, placeholder);
+        }
+    }
+    let chat_id = get_allowed_chat_ids().into_iter().min().unwrap_or_else(|| {
+        std::env::set_var("ALLOWED_CHAT_IDS", SIMULATED_CHAT_ID.to_string());
+        SIMULATED_CHAT_ID
+    });
+
+    let seed = seed.unwrap_or_else(|| chrono::Utc::now().timestamp_micros() as u64);
+    let mut random = Random::new(seed);
+    let orders: Vec<(i64, String)> = (1..=count as i64).map(|n| (n << 20, order(&mut random))).collect();
+
+    // TDLib confirms every reaction, so the confirm stage is measured too
+    let client = Arc::new(MockClient::open().with_responder(|request| {
+        if request["@type"] == "addMessageReaction" {
+            vec![json!({ "@type": "ok", "@extra": request["@extra"] })]
+        } else {
+            Vec::new()
+        }
+    }));
+    eprintln!("Simulating {} messages at {}/s in chat {} (seed {})", count, rate, chat_id, seed);
+
+    // Posted on schedule from another thread while the bot runs here, like a chat would
+    let feeder = {
+        let client = client.clone();
+        let orders = orders.clone();
+        std::thread::spawn(move || {
+            client.push(json!({ "@type": "updateAuthorizationState", "authorization_state": { "@type": "authorizationStateReady" } }));
+            let start = Instant::now();
+            let mut posted = HashMap::new();
+            for (n, (message_id, text)) in orders.into_iter().enumerate() {
+                let due = start + Duration::from_secs_f64(n as f64 / rate);
+                std::thread::sleep(due.saturating_duration_since(Instant::now()));
+                posted.insert(message_id, Instant::now());
+                client.push(message(chat_id, message_id, &text));
+            }
+            client.close();
+            (posted, start.elapsed())
+        })
+    };
+    let connect = {
+        let client = client.clone();
+        move || Ok(client.clone() as Arc<dyn TelegramClient>)
+    };
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("Failed to start the runtime: {}", e))?;
+    runtime.block_on(crate::run(connect)).map_err(|e| format!("The simulated bot failed: {}", e))?;
+    let (posted, elapsed) = feeder.join().map_err(|_| "The order generator panicked".to_string())?;
+
+    // What the filters should have done, decided the same way as `replay`
+    let filter_settings = FilterSettings::from_env();
+    let prices = Prices::from_env();
+    let mut filter_stats = FilterStats::default();
+    let mut expected = HashSet::new();
+    for (message_id, text) in &orders {
+        match decide(chat_id, *message_id, &MessageText::plain(text), None, &HashSet::new(), &filter_settings, &prices) {
+            Ok(()) => {
+                filter_stats.reacted();
+                expected.insert(*message_id);
+            }
+            Err(reason) => filter_stats.skipped(reason),
+        }
+    }
+
+    // First reaction request per message, timed from when the message was posted
+    let mut reacted = HashMap::new();
+    for (sent_at, request) in client.sent_at() {
+        if request["@type"] != "addMessageReaction" {
+            continue;
+        }
+        if let Some(message_id) = request["message_id"].as_i64() {
+            reacted.entry(message_id).or_insert(sent_at);
+        }
+    }
+    let mut latencies: Vec<u64> = reacted
+        .iter()
+        .filter_map(|(message_id, sent_at)| Some(sent_at.saturating_duration_since(*posted.get(message_id)?).as_micros() as u64))
+        .collect();
+    latencies.sort_unstable();
+
+    let share = |n: usize| if count == 0 { 0.0 } else { n as f64 * 100.0 / count as f64 };
+    println!(
+        "Posted {} messages in {:.1}s ({:.1}/s)",
+        count,
+        elapsed.as_secs_f64(),
+        count as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    println!("Should match: {} ({:.1}%)", expected.len(), share(expected.len()));
+    println!(
+        "Reacted: {} ({:.1}%), missed {}, unexpected {}",
+        reacted.len(),
+        share(reacted.len()),
+        expected.iter().filter(|message_id| !reacted.contains_key(message_id)).count(),
+        reacted.keys().filter(|message_id| !expected.contains(message_id)).count()
+    );
+    if latencies.is_empty() {
+        println!("Latency: no reactions");
+    } else {
+        println!(
+            "Latency, posted -> reaction sent (µs): p50 {} / p95 {} / p99 {} / max {}",
+            percentile(&latencies, 50),
+            percentile(&latencies, 95),
+            percentile(&latencies, 99),
+            latencies[latencies.len() - 1]
+        );
+    }
+    println!("{}", filter_stats.summary());
+    Ok(())
+}
+
+fn message(chat_id: i64, message_id: i64, text: &str) -> Value {
+    json!({
+        "@type": "updateNewMessage",
+        "message": {
+            "id": message_id,
+            "chat_id": chat_id,
+            "date": chrono::Utc::now().timestamp(),
+            "content": { "@type": "messageText", "text": { "@type": "formattedText", "text": text } }
+        }
+    })
+}
+
+// An order as they're posted in the order chats: amount in a few spellings (sometimes in USDT),
+// usually a bank, sometimes a requisite and a commission. One message in ten is chatter
+fn order(random: &mut Random) -> String {
+    if random.chance(10) {
+        return CHATTER[random.below(CHATTER.len())].to_string();
+    }
+    let amount = (random.below(1450) as u64 + 50) * 100;
+    let mut text = match random.below(4) {
+        0 => format!("Сумма: {} ₽", thousands(amount)),
+        1 => format!("Сумма: {} ₽", amount),
+        2 => format!("Новая заявка\nСумма: {} ₽", thousands(amount)),
+        // Converted with EXCHANGE_RATES when it has a USDT rate
+        _ => format!("Сумма: {} USDT", amount / 100),
+    };
+    if random.chance(85) {
+        text.push_str(&format!("\nБанк: {}", BANKS[random.below(BANKS.len())]));
+    }
+    if random.chance(50) {
+        let requisite = if random.chance(50) {
+            format!("+7 9{:02} {:03}-{:02}-{:02}", random.below(100), random.below(1000), random.below(100), random.below(100))
+        } else {
+            format!("2200 {:04} {:04} {:04}", random.below(10000), random.below(10000), random.below(10000))
+        };
+        text.push_str(&format!("\nРеквизит: {}", requisite));
+    }
+    if random.chance(30) {
+        text.push_str(&format!("\nКомиссия: {},{}%", 1 + random.below(3), random.below(10)));
+    }
+    text
+}
+
+// 50000 -> "50 000"
+fn thousands(amount: u64) -> String {
+    let digits = amount.to_string();
+    let mut text = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(' ');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+// xorshift64*: enough for made-up orders and reaction jitter, and the same --seed gives the same orders
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+// UPDATE_STALL_SECS=300: when TDLib says the connection is ready but nothing at all, not even a
+// service update, has come from it for this long, the client is taken to be stuck (as happens after
+// a laptop sleeps or the network flaps) and is destroyed and created again. Off when unset or 0,
+// and with a standby session, which takes over from a stuck primary anyway
+pub struct StallWatchdog {
+    limit: Option<Duration>,
+    last_update: Instant,
+}
+
+impl StallWatchdog {
+    pub fn from_env() -> Self {
+        let limit = std::env::var("UPDATE_STALL_SECS").ok().and_then(|s| s.parse().ok()).filter(|secs| *secs > 0).map(Duration::from_secs);
+        Self { limit, last_update: Instant::now() }
+    }
+
+    // Anything received from the primary client
+    pub fn seen(&mut self) {
+        self.last_update = Instant::now();
+    }
+
+    // How long the client has been silent, once that's over the limit; the clock only runs while
+    // the connection is ready, and starts over after each stall
+    pub fn check(&mut self, connection_ready: bool) -> Option<Duration> {
+        let limit = self.limit?;
+        if !connection_ready {
+            self.last_update = Instant::now();
+            return None;
+        }
+        let silent = self.last_update.elapsed();
+        if silent < limit {
+            return None;
+        }
+        self.last_update = Instant::now();
+        Some(silent)
+    }
+}
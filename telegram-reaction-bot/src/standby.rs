@@ -0,0 +1,322 @@
+use std::{
+    collections::VecDeque,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::{backlog::Depth, client::TelegramClient, routing::Router, sender::TdSender};
+
+// Reactions in a row the primary may fail while connected before the standby takes over
+const MAX_PRIMARY_FAILURES: u32 = 3;
+// Updates waiting behind a higher-priority chat's message at most
+const MAX_BATCH: usize = 256;
+// After failing over because of failed reactions (not a lost connection), give the primary another
+// chance after this long
+const PRIMARY_RETRY: Duration = Duration::from_secs(60);
+
+// STANDBY_TDLIB_DATA_DIR: a second session of the same account, logged in separately and kept
+// connected next to the primary. Without it the bot runs on the primary client alone
+pub fn data_dir() -> Option<String> {
+    std::env::var("STANDBY_TDLIB_DATA_DIR").ok().filter(|dir| !dir.is_empty())
+}
+
+// Which client an update came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Primary,
+    Standby,
+    // Results of reactions sent through the Bot API (see botapi.rs)
+    BotApi,
+}
+
+// Where the main loop gets updates from: straight from the primary client, or, with a standby,
+// from one receive thread per client. Either way the update buffers are reused, so receiving
+// doesn't allocate once they have grown to the size of the largest update
+pub struct Updates {
+    source: UpdateSource,
+    current: (Source, String),
+    bot_api: Option<mpsc::Receiver<String>>,
+    router: Option<Router>,
+    // Read ahead: each update with its priority and when it arrived
+    batch: VecDeque<(Source, String, u32, Instant)>,
+    pulled: usize,   // Received since the last take_depth()
+}
+
+enum UpdateSource {
+    Direct(Arc<dyn TelegramClient>),
+    // Handled buffers go back to the receive thread they came from
+    Merged {
+        updates: mpsc::Receiver<(Source, String)>,
+        spare_primary: mpsc::Sender<String>,
+        spare_standby: mpsc::Sender<String>,
+    },
+}
+
+impl Updates {
+    pub fn direct(client: Arc<dyn TelegramClient>) -> Self {
+        Self {
+            source: UpdateSource::Direct(client),
+            current: (Source::Primary, String::new()),
+            bot_api: None,
+            router: None,
+            batch: VecDeque::new(),
+            pulled: 0,
+        }
+    }
+
+    // Also hand out the Bot API's reaction results. They are picked up between TDLib updates, so
+    // one may wait up to a receive timeout while TDLib is quiet
+    pub fn add_results(&mut self, results: mpsc::Receiver<String>) {
+        self.bot_api = Some(results);
+    }
+
+    // Hand out what has already arrived in priority order (see routing.rs), so a busy chat can't
+    // hold up the one that matters. Everything waiting is read ahead for that, which costs a buffer
+    // per update, so it's only done when some chat has a priority
+    pub fn set_router(&mut self, router: Option<Router>) {
+        self.router = router;
+    }
+
+    // Whether a direct client has nothing more to hand out (see TelegramClient::is_exhausted)
+    pub fn is_exhausted(&self) -> bool {
+        match &self.source {
+            UpdateSource::Direct(client) => self.batch.is_empty() && client.is_exhausted(),
+            UpdateSource::Merged { .. } => false,
+        }
+    }
+
+    // The update stays valid until the next call
+    pub fn next(&mut self, timeout: f64) -> Option<(Source, &str)> {
+        if let Some(result) = self.bot_api.as_ref().and_then(|results| results.try_recv().ok()) {
+            self.current = (Source::BotApi, result);
+            return Some((self.current.0, &self.current.1));
+        }
+        if self.router.is_none() {
+            return self.receive(timeout).then_some((self.current.0, &self.current.1));
+        }
+
+        // Read ahead whatever is already there, waiting only when nothing is
+        while self.batch.len() < MAX_BATCH {
+            let timeout = if self.batch.is_empty() { timeout } else { 0.0 };
+            if !self.receive(timeout) {
+                break;
+            }
+            let (source, update) = std::mem::replace(&mut self.current, (Source::Primary, String::new()));
+            let priority = self.router.as_ref().map_or(0, |router| router.priority(&update));
+            self.batch.push_back((source, update, priority, Instant::now()));
+        }
+        let router = self.router.as_ref()?;
+        let (_, _, _, oldest) = self.batch.front()?;
+        // The oldest update once it has waited long enough, the first of the highest priority before
+        let next = if oldest.elapsed() >= router.max_wait {
+            0
+        } else {
+            let mut best = 0;
+            for (i, (_, _, priority, _)) in self.batch.iter().enumerate() {
+                if *priority > self.batch[best].2 {
+                    best = i;
+                }
+            }
+            best
+        };
+        let (source, update, _, _) = self.batch.remove(next)?;
+        self.current = (source, update);
+        Some((self.current.0, &self.current.1))
+    }
+
+    // Receive into `current`, handing its old buffer back
+    fn receive(&mut self, timeout: f64) -> bool {
+        match &self.source {
+            UpdateSource::Direct(client) => {
+                self.current.0 = Source::Primary;
+                if !client.receive_into(timeout, &mut self.current.1) {
+                    return false;
+                }
+            }
+            UpdateSource::Merged { updates, spare_primary, spare_standby } => {
+                let Ok(next) = updates.recv_timeout(Duration::from_secs_f64(timeout)) else {
+                    return false;
+                };
+                let (source, handled) = std::mem::replace(&mut self.current, next);
+                let _ = match source {
+                    Source::Primary => spare_primary.send(handled),
+                    Source::Standby => spare_standby.send(handled),
+                    Source::BotApi => Ok(()),
+                };
+            }
+        }
+        self.pulled += 1;
+        true
+    }
+
+    // For the receive loop's backlog (see backlog.rs)
+    pub fn take_depth(&mut self) -> Depth {
+        Depth { pulled: std::mem::take(&mut self.pulled), oldest: self.batch.front().map(|(_, _, _, received)| *received) }
+    }
+}
+
+// Receive from both clients at once. From here on nothing else may receive from either client
+pub fn merge(primary: Arc<dyn TelegramClient>, standby: Arc<dyn TelegramClient>) -> Updates {
+    let (updates, receiver) = mpsc::channel();
+    let mut spare_senders = Vec::new();
+    for (source, client) in [(Source::Primary, primary), (Source::Standby, standby)] {
+        let updates = updates.clone();
+        let (spare_sender, spare) = mpsc::channel::<String>();
+        spare_senders.push(spare_sender);
+        thread::spawn(move || {
+            if source == Source::Primary {
+                crate::tuning::tune_current_thread("receive");
+            }
+            let mut buffer = String::new();
+            loop {
+                if client.receive_into(1.0, &mut buffer) {
+                    let update = std::mem::replace(&mut buffer, spare.try_recv().unwrap_or_default());
+                    if updates.send((source, update)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+    let spare_standby = spare_senders.pop().expect("standby spare channel");
+    let spare_primary = spare_senders.pop().expect("primary spare channel");
+    Updates {
+        source: UpdateSource::Merged { updates: receiver, spare_primary, spare_standby },
+        current: (Source::Primary, String::new()),
+        bot_api: None,
+        router: None,
+        batch: VecDeque::new(),
+        pulled: 0,
+    }
+}
+
+// Routes requests through the standby client while the primary is disconnected, keeps failing
+// reactions or has lost its session, and back once it recovers. Standby updates are ignored
+// unless it's active, apart from its connection state and answers to requests sent through it
+pub struct Failover {
+    standby: Option<TdSender>,
+    active: Source,
+    primary_connected: bool,
+    primary_failures: u32,
+    primary_lost: bool,   // Session closed; a standby setup doesn't log in again, a restart does
+    standby_connected: bool,
+    standby_lost: bool,
+    failed_over_at: Option<Instant>,
+}
+
+impl Failover {
+    pub fn new(standby: Option<TdSender>) -> Self {
+        Self {
+            standby,
+            active: Source::Primary,
+            primary_connected: true,
+            primary_failures: 0,
+            primary_lost: false,
+            standby_connected: true,
+            standby_lost: false,
+            failed_over_at: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.standby.is_some()
+    }
+
+    // The client requests should go through right now
+    pub fn sender<'a>(&'a self, primary: &'a TdSender) -> &'a TdSender {
+        match (&self.standby, self.active) {
+            (Some(standby), Source::Standby) => standby,
+            _ => primary,
+        }
+    }
+
+    pub fn set_primary_connection(&mut self, state: &str) {
+        let connected = is_connected(state);
+        if connected && !self.primary_connected {
+            self.primary_failures = 0;
+        }
+        self.primary_connected = connected;
+    }
+
+    // Outcome of a reaction sent through the primary
+    pub fn record_primary_reaction(&mut self, confirmed: bool) {
+        if confirmed {
+            self.primary_failures = 0;
+        } else {
+            self.primary_failures += 1;
+        }
+    }
+
+    pub fn set_primary_lost(&mut self) {
+        self.primary_lost = true;
+    }
+
+    // Both sessions are gone; nothing left to react with
+    pub fn all_lost(&self) -> bool {
+        self.primary_lost && (self.standby.is_none() || self.standby_lost)
+    }
+
+    // Look at an update from the standby; returns whether the main loop should handle it
+    pub fn standby_update(&mut self, raw: &str) -> bool {
+        if raw.contains("\"@type\":\"updateConnectionState\"") {
+            if let Ok(update) = serde_json::from_str::<Value>(raw) {
+                let state = update["state"]["@type"].as_str().unwrap_or_default();
+                info!("Standby connection state: {}", state);
+                self.standby_connected = is_connected(state);
+            }
+            return false;
+        }
+        if raw.contains("\"@type\":\"updateAuthorizationState\"") {
+            if raw.contains("\"authorizationStateClosed\"") {
+                warn!("Standby TDLib session closed; running without a standby until restart");
+                self.standby_lost = true;
+            }
+            return false;
+        }
+        self.active == Source::Standby || raw.contains("\"@extra\":")
+    }
+
+    // Switch clients if needed; returns the event for the manager when the active one changed
+    pub fn check(&mut self) -> Option<Value> {
+        self.standby.as_ref()?;
+
+        // Failed over only because of failed reactions: see whether the primary works again
+        if self.active == Source::Standby
+            && self.primary_connected
+            && self.failed_over_at.is_some_and(|at| at.elapsed() >= PRIMARY_RETRY)
+        {
+            self.primary_failures = 0;
+        }
+
+        let primary_ok = !self.primary_lost && self.primary_connected && self.primary_failures < MAX_PRIMARY_FAILURES;
+        let standby_ok = !self.standby_lost && self.standby_connected;
+        let active = if primary_ok || !standby_ok { Source::Primary } else { Source::Standby };
+        if active == self.active {
+            return None;
+        }
+
+        self.active = active;
+        if active == Source::Standby {
+            self.failed_over_at = Some(Instant::now());
+            warn!(
+                "Primary TDLib client is down (connected: {}, failed reactions: {}, session lost: {}), using the standby",
+                self.primary_connected, self.primary_failures, self.primary_lost
+            );
+        } else {
+            self.failed_over_at = None;
+            info!("Back on the primary TDLib client");
+        }
+        Some(json!({
+            "event": "failover",
+            "active": if active == Source::Standby { "standby" } else { "primary" }
+        }))
+    }
+}
+
+// "Updating" means connected and catching up on missed updates
+fn is_connected(state: &str) -> bool {
+    matches!(state, "connectionStateReady" | "connectionStateUpdating")
+}
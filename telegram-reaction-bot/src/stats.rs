@@ -0,0 +1,46 @@
+use serde_json::{json, Value};
+pub use botdg_core::SkipReason;
+use botdg_core::filter::REASONS;
+
+use crate::metrics;
+
+// Counts of reacted and skipped messages since start, by skip reason
+#[derive(Default)]
+pub struct FilterStats {
+    reacted: u64,
+    skipped: [u64; REASONS.len()],   // Indexed by SkipReason
+}
+
+impl FilterStats {
+    pub fn reacted(&mut self) {
+        self.reacted += 1;
+        metrics::count("reacted", &[]);
+    }
+
+    pub fn skipped(&mut self, reason: SkipReason) {
+        self.skipped[reason as usize] += 1;
+        metrics::count("skipped", &[("reason", reason.name())]);
+    }
+
+    // Reply to /stats
+    pub fn summary(&self) -> String {
+        let total = self.reacted + self.skipped.iter().sum::<u64>();
+        let mut text = format!("📊 Messages since start: {}\nreacted: {}", total, self.reacted);
+        for (reason, name) in REASONS {
+            let count = self.skipped[reason as usize];
+            if count > 0 {
+                text.push_str(&format!("\n{}: {} ({:.1}%)", name, count, count as f64 * 100.0 / total as f64));
+            }
+        }
+        text
+    }
+
+    // Counters for the heartbeat file, read by the manager's /stats
+    pub fn to_json(&self) -> Value {
+        let mut skipped = serde_json::Map::new();
+        for (reason, name) in REASONS {
+            skipped.insert(name.to_string(), json!(self.skipped[reason as usize]));
+        }
+        json!({ "reacted": self.reacted, "skipped": skipped })
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::VecDeque;
+use rusqlite::{params, Connection};
+
+use crate::{history::{DecisionTrace, ReactionRecord, SeenOrder}, race::Competitor};
+
+pub type StoreResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// Where the reaction history is kept. Called only from the history thread (see history.rs),
+// so implementations may block
+pub trait HistoryStore: Send {
+    fn insert(&mut self, records: &[ReactionRecord]) -> StoreResult<()>;
+    // "sent" or "failed"
+    fn set_outcome(&mut self, chat_id: i64, message_id: i64, outcome: &str) -> StoreResult<()>;
+    // `won_by`: the race account that was first, when it wasn't the main one (see accounts.rs)
+    fn set_race(&mut self, chat_id: i64, message_id: i64, won: bool, margin_secs: Option<i64>, won_by: Option<&str>) -> StoreResult<()>;
+    // "taken", "paid" or "cancelled" (see lifecycle.rs)
+    fn set_final_state(&mut self, chat_id: i64, message_id: i64, state: &str) -> StoreResult<()>;
+    fn add_competitors(&mut self, chat_id: i64, message_id: i64, competitors: &[Competitor]) -> StoreResult<()>;
+    // A chat's title or a user's name (see names.rs), replacing what was kept for the ID
+    fn set_name(&mut self, id: i64, name: &str) -> StoreResult<()>;
+    // A later decision about the same message replaces the earlier one
+    fn insert_decisions(&mut self, traces: &[DecisionTrace]) -> StoreResult<()>;
+    // Traces decided before the timestamp
+    fn prune_decisions(&mut self, before: i64) -> StoreResult<()>;
+    // Newest first
+    fn recent(&mut self, limit: usize) -> StoreResult<Vec<ReactionRecord>>;
+    // (chat, message) of reactions at or after the timestamp, oldest first
+    fn reacted_since(&mut self, since: i64) -> StoreResult<Vec<(i64, i64)>>;
+    // Decisions about messages with this ID in any chat, newest first
+    fn decisions(&mut self, message_id: i64) -> StoreResult<Vec<DecisionTrace>>;
+    fn insert_seen(&mut self, order: &SeenOrder) -> StoreResult<()>;
+    // Oldest first
+    fn seen_orders(&mut self) -> StoreResult<Vec<SeenOrder>>;
+    // Returns how many reactions were deleted
+    fn clear(&mut self) -> StoreResult<usize>;
+}
+
+// The default: one SQLite file per bot, readable by the manager
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        if let Some(parent) = std::path::Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let connection = Connection::open(path)?;
+        // WAL lets the manager read the history while the bot writes to it
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS reactions (
+                id INTEGER PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                message_id INTEGER NOT NULL,
+                amount INTEGER,
+                bank TEXT,
+                reacted_at INTEGER NOT NULL,
+                latency_us INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS reactions_reacted_at ON reactions (reacted_at);
+            CREATE TABLE IF NOT EXISTS competitor_reactions (
+                chat_id INTEGER NOT NULL,
+                message_id INTEGER NOT NULL,
+                sender_id INTEGER NOT NULL,
+                reacted_at INTEGER NOT NULL,
+                delay_secs INTEGER NOT NULL,
+                PRIMARY KEY (chat_id, message_id, sender_id)
+            );
+            CREATE TABLE IF NOT EXISTS decisions (
+                chat_id INTEGER NOT NULL,
+                message_id INTEGER NOT NULL,
+                decided_at INTEGER NOT NULL,
+                trace TEXT NOT NULL,
+                PRIMARY KEY (chat_id, message_id)
+            );
+            CREATE INDEX IF NOT EXISTS decisions_message_id ON decisions (message_id);
+            CREATE INDEX IF NOT EXISTS decisions_decided_at ON decisions (decided_at);
+            CREATE TABLE IF NOT EXISTS seen_orders (
+                id INTEGER PRIMARY KEY,
+                chat_id INTEGER NOT NULL,
+                sender_id INTEGER,
+                requisite TEXT,
+                bank TEXT,
+                seen_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS names (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL
+            );",
+        )?;
+
+        // Added after the first release:
+        // outcome - "sent" or "failed", NULL while unconfirmed
+        // won - 1 if we reacted first, 0 if someone beat us, NULL until decided
+        // margin_secs - their first reaction minus ours, NULL if nobody else reacted
+        // commission_pct - the commission stated in the order, NULL if it doesn't state one
+        // final_state - "taken", "paid" or "cancelled" as seen in the chat, NULL if nothing was seen
+        // won_by - the race account (RACE_ACCOUNT_DIRS) that reacted first, NULL for the main account
+        let columns = [
+            ("outcome", "TEXT"),
+            ("won", "INTEGER"),
+            ("margin_secs", "INTEGER"),
+            ("commission_pct", "REAL"),
+            ("final_state", "TEXT"),
+            ("won_by", "TEXT"),
+        ];
+        for (column, column_type) in columns {
+            let exists = connection
+                .prepare("SELECT 1 FROM pragma_table_info('reactions') WHERE name = ?1")?
+                .exists([column])?;
+            if !exists {
+                connection.execute_batch(&format!("ALTER TABLE reactions ADD COLUMN {} {}", column, column_type))?;
+            }
+        }
+        Ok(Self { connection })
+    }
+}
+
+impl HistoryStore for SqliteStore {
+    fn insert(&mut self, records: &[ReactionRecord]) -> StoreResult<()> {
+        let transaction = self.connection.transaction()?;
+        for record in records {
+            transaction.execute(
+                "INSERT INTO reactions (chat_id, message_id, amount, bank, reacted_at, latency_us, commission_pct)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    record.chat_id,
+                    record.message_id,
+                    record.amount,
+                    record.bank,
+                    record.reacted_at,
+                    record.latency_us,
+                    record.commission_pct
+                ],
+            )?;
+        }
+        Ok(transaction.commit()?)
+    }
+
+    fn set_outcome(&mut self, chat_id: i64, message_id: i64, outcome: &str) -> StoreResult<()> {
+        self.connection.execute(
+            "UPDATE reactions SET outcome = ?1 WHERE chat_id = ?2 AND message_id = ?3",
+            params![outcome, chat_id, message_id],
+        )?;
+        Ok(())
+    }
+
+    fn set_race(&mut self, chat_id: i64, message_id: i64, won: bool, margin_secs: Option<i64>, won_by: Option<&str>) -> StoreResult<()> {
+        self.connection.execute(
+            "UPDATE reactions SET won = ?1, margin_secs = ?2, won_by = ?3 WHERE chat_id = ?4 AND message_id = ?5",
+            params![won, margin_secs, won_by, chat_id, message_id],
+        )?;
+        Ok(())
+    }
+
+    fn set_final_state(&mut self, chat_id: i64, message_id: i64, state: &str) -> StoreResult<()> {
+        self.connection.execute(
+            "UPDATE reactions SET final_state = ?1 WHERE chat_id = ?2 AND message_id = ?3",
+            params![state, chat_id, message_id],
+        )?;
+        Ok(())
+    }
+
+    fn add_competitors(&mut self, chat_id: i64, message_id: i64, competitors: &[Competitor]) -> StoreResult<()> {
+        let transaction = self.connection.transaction()?;
+        for competitor in competitors {
+            transaction.execute(
+                "INSERT OR IGNORE INTO competitor_reactions (chat_id, message_id, sender_id, reacted_at, delay_secs)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![chat_id, message_id, competitor.sender_id, competitor.reacted_at, competitor.delay_secs],
+            )?;
+        }
+        Ok(transaction.commit()?)
+    }
+
+    fn set_name(&mut self, id: i64, name: &str) -> StoreResult<()> {
+        self.connection.execute(
+            "INSERT INTO names (id, name) VALUES (?1, ?2) ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+            params![id, name],
+        )?;
+        Ok(())
+    }
+
+    fn insert_decisions(&mut self, traces: &[DecisionTrace]) -> StoreResult<()> {
+        let transaction = self.connection.transaction()?;
+        for trace in traces {
+            transaction.execute(
+                "INSERT OR REPLACE INTO decisions (chat_id, message_id, decided_at, trace) VALUES (?1, ?2, ?3, ?4)",
+                params![trace.chat_id, trace.message_id, trace.decided_at, trace.trace],
+            )?;
+        }
+        Ok(transaction.commit()?)
+    }
+
+    fn prune_decisions(&mut self, before: i64) -> StoreResult<()> {
+        self.connection.execute("DELETE FROM decisions WHERE decided_at < ?1", [before])?;
+        Ok(())
+    }
+
+    fn recent(&mut self, limit: usize) -> StoreResult<Vec<ReactionRecord>> {
+        let mut statement = self.connection.prepare(
+            "SELECT chat_id, message_id, amount, bank, reacted_at, latency_us, commission_pct
+             FROM reactions ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = statement.query_map([limit as i64], |row| {
+            Ok(ReactionRecord {
+                chat_id: row.get(0)?,
+                message_id: row.get(1)?,
+                amount: row.get(2)?,
+                bank: row.get(3)?,
+                reacted_at: row.get(4)?,
+                latency_us: row.get(5)?,
+                commission_pct: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn reacted_since(&mut self, since: i64) -> StoreResult<Vec<(i64, i64)>> {
+        let mut statement = self.connection.prepare("SELECT chat_id, message_id FROM reactions WHERE reacted_at >= ?1 ORDER BY id")?;
+        let rows = statement.query_map([since], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn decisions(&mut self, message_id: i64) -> StoreResult<Vec<DecisionTrace>> {
+        let mut statement = self.connection.prepare(
+            "SELECT chat_id, message_id, decided_at, trace FROM decisions WHERE message_id = ?1 ORDER BY decided_at DESC",
+        )?;
+        let rows = statement.query_map([message_id], |row| {
+            Ok(DecisionTrace { chat_id: row.get(0)?, message_id: row.get(1)?, decided_at: row.get(2)?, trace: row.get(3)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn insert_seen(&mut self, order: &SeenOrder) -> StoreResult<()> {
+        self.connection.execute(
+            "INSERT INTO seen_orders (chat_id, sender_id, requisite, bank, seen_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![order.chat_id, order.sender_id, order.requisite, order.bank, order.seen_at],
+        )?;
+        Ok(())
+    }
+
+    fn seen_orders(&mut self) -> StoreResult<Vec<SeenOrder>> {
+        let mut statement = self.connection.prepare("SELECT chat_id, sender_id, requisite, bank, seen_at FROM seen_orders ORDER BY id")?;
+        let rows = statement.query_map([], |row| {
+            Ok(SeenOrder { chat_id: row.get(0)?, sender_id: row.get(1)?, requisite: row.get(2)?, bank: row.get(3)?, seen_at: row.get(4)? })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn clear(&mut self) -> StoreResult<usize> {
+        self.connection.execute_batch("DELETE FROM competitor_reactions; DELETE FROM decisions")?;
+        Ok(self.connection.execute("DELETE FROM reactions", [])?)
+    }
+}
+
+// Kept only for the life of the process, for trying the bot out without any files.
+// The oldest reactions, decision traces and seen orders are dropped past MAX_MEMORY_RECORDS
+pub struct MemoryStore {
+    records: Vec<ReactionRecord>,
+    decisions: VecDeque<DecisionTrace>,
+    seen: VecDeque<SeenOrder>,
+}
+
+const MAX_MEMORY_RECORDS: usize = 10_000;
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self { records: Vec::new(), decisions: VecDeque::new(), seen: VecDeque::new() }
+    }
+}
+
+impl HistoryStore for MemoryStore {
+    fn insert(&mut self, records: &[ReactionRecord]) -> StoreResult<()> {
+        self.records.extend(records.iter().cloned());
+        let excess = self.records.len().saturating_sub(MAX_MEMORY_RECORDS);
+        self.records.drain(..excess);
+        Ok(())
+    }
+
+    // Outcomes, races, final states, competitors and names are only used by reports that read stored histories
+    fn set_outcome(&mut self, _chat_id: i64, _message_id: i64, _outcome: &str) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn set_race(&mut self, _chat_id: i64, _message_id: i64, _won: bool, _margin_secs: Option<i64>, _won_by: Option<&str>) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn set_final_state(&mut self, _chat_id: i64, _message_id: i64, _state: &str) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn add_competitors(&mut self, _chat_id: i64, _message_id: i64, _competitors: &[Competitor]) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn set_name(&mut self, _id: i64, _name: &str) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn insert_decisions(&mut self, traces: &[DecisionTrace]) -> StoreResult<()> {
+        for trace in traces {
+            self.decisions.retain(|kept| (kept.chat_id, kept.message_id) != (trace.chat_id, trace.message_id));
+            self.decisions.push_back(trace.clone());
+        }
+        let excess = self.decisions.len().saturating_sub(MAX_MEMORY_RECORDS);
+        self.decisions.drain(..excess);
+        Ok(())
+    }
+
+    fn prune_decisions(&mut self, before: i64) -> StoreResult<()> {
+        self.decisions.retain(|trace| trace.decided_at >= before);
+        Ok(())
+    }
+
+    fn recent(&mut self, limit: usize) -> StoreResult<Vec<ReactionRecord>> {
+        Ok(self.records.iter().rev().take(limit).cloned().collect())
+    }
+
+    fn reacted_since(&mut self, since: i64) -> StoreResult<Vec<(i64, i64)>> {
+        Ok(self.records.iter().filter(|record| record.reacted_at >= since).map(|record| (record.chat_id, record.message_id)).collect())
+    }
+
+    fn decisions(&mut self, message_id: i64) -> StoreResult<Vec<DecisionTrace>> {
+        Ok(self.decisions.iter().rev().filter(|trace| trace.message_id == message_id).cloned().collect())
+    }
+
+    fn insert_seen(&mut self, order: &SeenOrder) -> StoreResult<()> {
+        self.seen.push_back(order.clone());
+        if self.seen.len() > MAX_MEMORY_RECORDS {
+            self.seen.pop_front();
+        }
+        Ok(())
+    }
+
+    fn seen_orders(&mut self) -> StoreResult<Vec<SeenOrder>> {
+        Ok(self.seen.iter().cloned().collect())
+    }
+
+    fn clear(&mut self) -> StoreResult<usize> {
+        let deleted = self.records.len();
+        self.records.clear();
+        self.decisions.clear();
+        Ok(deleted)
+    }
+}
+
+// One database shared by several instances; rows are tagged with the instance name
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use postgres::{Client, NoTls};
+
+    use super::{HistoryStore, StoreResult};
+    use crate::{history::{DecisionTrace, ReactionRecord, SeenOrder}, race::Competitor};
+
+    pub struct PostgresStore {
+        client: Client,
+        instance: String,
+    }
+
+    impl PostgresStore {
+        pub fn connect(url: &str, instance: &str) -> StoreResult<Self> {
+            let mut client = Client::connect(url, NoTls)?;
+            client.batch_execute(
+                "CREATE TABLE IF NOT EXISTS reactions (
+                    id BIGSERIAL PRIMARY KEY,
+                    instance TEXT NOT NULL,
+                    chat_id BIGINT NOT NULL,
+                    message_id BIGINT NOT NULL,
+                    amount INTEGER,
+                    bank TEXT,
+                    reacted_at BIGINT NOT NULL,
+                    latency_us BIGINT NOT NULL,
+                    outcome TEXT,
+                    won BOOLEAN,
+                    margin_secs BIGINT
+                );
+                ALTER TABLE reactions ADD COLUMN IF NOT EXISTS commission_pct DOUBLE PRECISION;
+                ALTER TABLE reactions ADD COLUMN IF NOT EXISTS final_state TEXT;
+                ALTER TABLE reactions ADD COLUMN IF NOT EXISTS won_by TEXT;
+                CREATE INDEX IF NOT EXISTS reactions_instance_reacted_at ON reactions (instance, reacted_at);
+                CREATE TABLE IF NOT EXISTS competitor_reactions (
+                    instance TEXT NOT NULL,
+                    chat_id BIGINT NOT NULL,
+                    message_id BIGINT NOT NULL,
+                    sender_id BIGINT NOT NULL,
+                    reacted_at BIGINT NOT NULL,
+                    delay_secs BIGINT NOT NULL,
+                    PRIMARY KEY (instance, chat_id, message_id, sender_id)
+                );
+                CREATE TABLE IF NOT EXISTS decisions (
+                    instance TEXT NOT NULL,
+                    chat_id BIGINT NOT NULL,
+                    message_id BIGINT NOT NULL,
+                    decided_at BIGINT NOT NULL,
+                    trace TEXT NOT NULL,
+                    PRIMARY KEY (instance, chat_id, message_id)
+                );
+                CREATE INDEX IF NOT EXISTS decisions_instance_message_id ON decisions (instance, message_id);
+                CREATE INDEX IF NOT EXISTS decisions_decided_at ON decisions (decided_at);
+                CREATE TABLE IF NOT EXISTS seen_orders (
+                    id BIGSERIAL PRIMARY KEY,
+                    instance TEXT NOT NULL,
+                    chat_id BIGINT NOT NULL,
+                    sender_id BIGINT,
+                    requisite TEXT,
+                    bank TEXT,
+                    seen_at BIGINT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS names (
+                    id BIGINT PRIMARY KEY,
+                    name TEXT NOT NULL
+                );",
+            )?;
+            Ok(Self { client, instance: instance.to_string() })
+        }
+    }
+
+    impl HistoryStore for PostgresStore {
+        fn insert(&mut self, records: &[ReactionRecord]) -> StoreResult<()> {
+            let mut transaction = self.client.transaction()?;
+            for record in records {
+                transaction.execute(
+                    "INSERT INTO reactions (instance, chat_id, message_id, amount, bank, reacted_at, latency_us, commission_pct)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    &[
+                        &self.instance,
+                        &record.chat_id,
+                        &record.message_id,
+                        &record.amount,
+                        &record.bank,
+                        &record.reacted_at,
+                        &record.latency_us,
+                        &record.commission_pct,
+                    ],
+                )?;
+            }
+            Ok(transaction.commit()?)
+        }
+
+        fn set_outcome(&mut self, chat_id: i64, message_id: i64, outcome: &str) -> StoreResult<()> {
+            self.client.execute(
+                "UPDATE reactions SET outcome = $1 WHERE instance = $2 AND chat_id = $3 AND message_id = $4",
+                &[&outcome, &self.instance, &chat_id, &message_id],
+            )?;
+            Ok(())
+        }
+
+        fn set_race(&mut self, chat_id: i64, message_id: i64, won: bool, margin_secs: Option<i64>, won_by: Option<&str>) -> StoreResult<()> {
+            self.client.execute(
+                "UPDATE reactions SET won = $1, margin_secs = $2, won_by = $3 WHERE instance = $4 AND chat_id = $5 AND message_id = $6",
+                &[&won, &margin_secs, &won_by, &self.instance, &chat_id, &message_id],
+            )?;
+            Ok(())
+        }
+
+        fn set_final_state(&mut self, chat_id: i64, message_id: i64, state: &str) -> StoreResult<()> {
+            self.client.execute(
+                "UPDATE reactions SET final_state = $1 WHERE instance = $2 AND chat_id = $3 AND message_id = $4",
+                &[&state, &self.instance, &chat_id, &message_id],
+            )?;
+            Ok(())
+        }
+
+        fn add_competitors(&mut self, chat_id: i64, message_id: i64, competitors: &[Competitor]) -> StoreResult<()> {
+            let mut transaction = self.client.transaction()?;
+            for competitor in competitors {
+                transaction.execute(
+                    "INSERT INTO competitor_reactions (instance, chat_id, message_id, sender_id, reacted_at, delay_secs)
+                     VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                    &[&self.instance, &chat_id, &message_id, &competitor.sender_id, &competitor.reacted_at, &competitor.delay_secs],
+                )?;
+            }
+            Ok(transaction.commit()?)
+        }
+
+        // Names are the same whichever instance learned them
+        fn set_name(&mut self, id: i64, name: &str) -> StoreResult<()> {
+            self.client.execute(
+                "INSERT INTO names (id, name) VALUES ($1, $2) ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name",
+                &[&id, &name],
+            )?;
+            Ok(())
+        }
+
+        fn insert_decisions(&mut self, traces: &[DecisionTrace]) -> StoreResult<()> {
+            let mut transaction = self.client.transaction()?;
+            for trace in traces {
+                transaction.execute(
+                    "INSERT INTO decisions (instance, chat_id, message_id, decided_at, trace) VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (instance, chat_id, message_id) DO UPDATE SET decided_at = EXCLUDED.decided_at, trace = EXCLUDED.trace",
+                    &[&self.instance, &trace.chat_id, &trace.message_id, &trace.decided_at, &trace.trace],
+                )?;
+            }
+            Ok(transaction.commit()?)
+        }
+
+        fn prune_decisions(&mut self, before: i64) -> StoreResult<()> {
+            self.client.execute("DELETE FROM decisions WHERE instance = $1 AND decided_at < $2", &[&self.instance, &before])?;
+            Ok(())
+        }
+
+        fn recent(&mut self, limit: usize) -> StoreResult<Vec<ReactionRecord>> {
+            let rows = self.client.query(
+                "SELECT chat_id, message_id, amount, bank, reacted_at, latency_us, commission_pct
+                 FROM reactions WHERE instance = $1 ORDER BY id DESC LIMIT $2",
+                &[&self.instance, &(limit as i64)],
+            )?;
+            Ok(rows
+                .iter()
+                .map(|row| ReactionRecord {
+                    chat_id: row.get(0),
+                    message_id: row.get(1),
+                    amount: row.get(2),
+                    bank: row.get(3),
+                    reacted_at: row.get(4),
+                    latency_us: row.get(5),
+                    commission_pct: row.get(6),
+                })
+                .collect())
+        }
+
+        fn reacted_since(&mut self, since: i64) -> StoreResult<Vec<(i64, i64)>> {
+            let rows = self.client.query(
+                "SELECT chat_id, message_id FROM reactions WHERE instance = $1 AND reacted_at >= $2 ORDER BY id",
+                &[&self.instance, &since],
+            )?;
+            Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+        }
+
+        fn decisions(&mut self, message_id: i64) -> StoreResult<Vec<DecisionTrace>> {
+            let rows = self.client.query(
+                "SELECT chat_id, message_id, decided_at, trace FROM decisions
+                 WHERE instance = $1 AND message_id = $2 ORDER BY decided_at DESC",
+                &[&self.instance, &message_id],
+            )?;
+            Ok(rows
+                .iter()
+                .map(|row| DecisionTrace { chat_id: row.get(0), message_id: row.get(1), decided_at: row.get(2), trace: row.get(3) })
+                .collect())
+        }
+
+        fn insert_seen(&mut self, order: &SeenOrder) -> StoreResult<()> {
+            self.client.execute(
+                "INSERT INTO seen_orders (instance, chat_id, sender_id, requisite, bank, seen_at) VALUES ($1, $2, $3, $4, $5, $6)",
+                &[&self.instance, &order.chat_id, &order.sender_id, &order.requisite, &order.bank, &order.seen_at],
+            )?;
+            Ok(())
+        }
+
+        fn seen_orders(&mut self) -> StoreResult<Vec<SeenOrder>> {
+            let rows = self.client.query(
+                "SELECT chat_id, sender_id, requisite, bank, seen_at FROM seen_orders WHERE instance = $1 ORDER BY id",
+                &[&self.instance],
+            )?;
+            Ok(rows
+                .iter()
+                .map(|row| SeenOrder { chat_id: row.get(0), sender_id: row.get(1), requisite: row.get(2), bank: row.get(3), seen_at: row.get(4) })
+                .collect())
+        }
+
+        fn clear(&mut self) -> StoreResult<usize> {
+            let mut transaction = self.client.transaction()?;
+            transaction.execute("DELETE FROM competitor_reactions WHERE instance = $1", &[&self.instance])?;
+            transaction.execute("DELETE FROM decisions WHERE instance = $1", &[&self.instance])?;
+            let deleted = transaction.execute("DELETE FROM reactions WHERE instance = $1", &[&self.instance])?;
+            transaction.commit()?;
+            Ok(deleted as usize)
+        }
+    }
+}
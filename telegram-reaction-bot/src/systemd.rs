@@ -0,0 +1,120 @@
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+
+use crate::sender::TdSender;
+
+// "@extra" of the watchdog's probe requests
+pub const PROBE_EXTRA: &str = "watchdog";
+
+// systemd's Type=notify protocol: datagrams of "KEY=VALUE" lines to NOTIFY_SOCKET. Without
+// NOTIFY_SOCKET (not started by systemd, or another Type=) everything here does nothing
+#[cfg(unix)]
+struct Socket {
+    socket: std::os::unix::net::UnixDatagram,
+    addr: std::os::unix::net::SocketAddr,
+}
+
+#[cfg(unix)]
+static SOCKET: OnceLock<Option<Socket>> = OnceLock::new();
+
+#[cfg(unix)]
+fn socket() -> Option<&'static Socket> {
+    SOCKET
+        .get_or_init(|| {
+            use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+            let path = std::env::var("NOTIFY_SOCKET").ok().filter(|path| !path.is_empty())?;
+            // "@name" is an abstract socket
+            let addr = match path.strip_prefix('@') {
+                #[cfg(target_os = "linux")]
+                Some(name) => {
+                    use std::os::linux::net::SocketAddrExt;
+                    SocketAddr::from_abstract_name(name)
+                }
+                #[cfg(not(target_os = "linux"))]
+                Some(_) => return None,
+                None => SocketAddr::from_pathname(&path),
+            };
+            match addr.and_then(|addr| Ok(Socket { socket: UnixDatagram::unbound()?, addr })) {
+                Ok(socket) => Some(socket),
+                Err(e) => {
+                    tracing::warn!("Failed to open NOTIFY_SOCKET {}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+// Send systemd a state change, e.g. "READY=1" or "STATUS=..."
+pub fn notify(state: &str) {
+    #[cfg(unix)]
+    if let Some(socket) = socket() {
+        let _ = socket.socket.send_to_addr(state.as_bytes(), &socket.addr);
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+}
+
+// WatchdogSec= as systemd passes it (WATCHDOG_USEC, for this process only)
+fn watchdog_period() -> Option<Duration> {
+    static PERIOD: OnceLock<Option<Duration>> = OnceLock::new();
+    *PERIOD.get_or_init(|| {
+        let usec = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok().filter(|usec| *usec > 0)?;
+        let pid_matches = std::env::var("WATCHDOG_PID")
+            .map_or(true, |pid| pid.parse::<u32>().is_ok_and(|pid| pid == std::process::id()));
+        pid_matches.then(|| Duration::from_micros(usec))
+    })
+}
+
+// Tell systemd we're alive, at most every quarter of the watchdog period
+pub fn keep_alive() {
+    static LAST: Mutex<Option<Instant>> = Mutex::new(None);
+    let Some(period) = watchdog_period() else {
+        return;
+    };
+    let Ok(mut last) = LAST.lock() else {
+        return;
+    };
+    if last.is_none_or(|last| last.elapsed() >= period / 4) {
+        *last = Some(Instant::now());
+        notify("WATCHDOG=1");
+    }
+}
+
+// Feeds the watchdog from the receive loop, but only when TDLib answers a network round trip sent
+// through it: a loop that spins on without updates, or a client that stopped answering, lets the
+// watchdog expire and systemd restart the bot
+pub struct Watchdog {
+    last_probe: Option<Instant>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self { last_probe: None }
+    }
+
+    // Send testNetwork every half watchdog period
+    pub fn probe(&mut self, sender: &TdSender) {
+        let Some(period) = watchdog_period() else {
+            return;
+        };
+        if self.last_probe.is_none_or(|last| last.elapsed() >= period / 2) {
+            self.last_probe = Some(Instant::now());
+            sender.send(json!({ "@type": "testNetwork", "@extra": PROBE_EXTRA }).to_string());
+        }
+    }
+
+    // The probe's answer. While TDLib knows it's offline, a failed round trip is still an answer:
+    // restarting wouldn't bring the network back
+    pub fn answered(&self, response: &Value, connection_ready: bool) {
+        if response["@type"] == "ok" || !connection_ready {
+            keep_alive();
+        } else {
+            tracing::warn!("Watchdog probe failed while connected: {}", response["message"]);
+        }
+    }
+}
@@ -0,0 +1,800 @@
+// The update pipeline end to end against MockClient: scripted updates in, recorded requests out
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, UdpSocket},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    sync::{Arc, Once, OnceLock},
+    time::{Duration, Instant},
+};
+use serde_json::{json, Value};
+
+use crate::{client::TelegramClient, mock::MockClient, run};
+
+const CHAT_ID: i64 = -1001234567890;
+const OTHER_CHAT_ID: i64 = -1009876543210;
+// A forum where only topic 42 is watched
+const FORUM_CHAT_ID: i64 = -1005555555555;
+// A channel reacted to in its discussion group
+const CHANNEL_ID: i64 = -1007777777777;
+const DISCUSSION_ID: i64 = -1008888888888;
+// Handled before the other chats
+const HOT_CHAT_ID: i64 = -1003333333333;
+// Orders only count when forwarded from the source channel
+const RELAY_CHAT_ID: i64 = -1004444444444;
+const SOURCE_CHANNEL_ID: i64 = -1006666666666;
+// Orders only count when the exchange's bot posts them
+const BOT_CHAT_ID: i64 = -1002222222222;
+const ORDER_BOT_ID: i64 = 5000000001;
+// Orders are taken by a reply and the order's button instead of a reaction
+const ACTION_CHAT_ID: i64 = -1001111111111;
+// May send the bot's /pause and the like besides the account itself
+const OWNER_ID: i64 = 5000000002;
+
+// Where the bot's DogStatsD metrics land
+static STATSD: OnceLock<UdpSocket> = OnceLock::new();
+// Where the bot answers health checks
+static HEALTH_ADDR: OnceLock<String> = OnceLock::new();
+// Where the bot answers JSON-RPC
+static CONTROL_SOCKET: OnceLock<PathBuf> = OnceLock::new();
+
+// Settings are environment variables read by run(); every test uses the same ones, so tests
+// running in parallel don't see each other's
+fn setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| {
+        let data_dir = std::env::temp_dir().join(format!("tdlib-test-mock-{}", std::process::id()));
+        std::env::set_var("TELEGRAM_API_ID", "12345");
+        std::env::set_var("TELEGRAM_API_HASH", "0123456789abcdef");
+        std::env::set_var("TDLIB_DATA_DIR", data_dir);
+        std::env::set_var(
+            "ALLOWED_CHAT_IDS",
+            format!("{},{},{},{},{},{},{}", CHAT_ID, FORUM_CHAT_ID, CHANNEL_ID, HOT_CHAT_ID, RELAY_CHAT_ID, BOT_CHAT_ID, ACTION_CHAT_ID),
+        );
+        std::env::set_var("HOT_CHAT_IDS", HOT_CHAT_ID.to_string());
+        std::env::set_var("OWNER_USER_IDS", OWNER_ID.to_string());
+        // /watch changes last for the run only, so tests don't see each other's
+        std::env::set_var("WATCHED_CHATS_FILE", "");
+        // Nor the reactions another test left unconfirmed
+        std::env::set_var("PENDING_REACTIONS_FILE", "");
+        std::env::set_var("PIPELINE_WORKERS", "2");
+        std::env::set_var("DISCUSSION_REACTIONS", CHANNEL_ID.to_string());
+        std::env::set_var("ALLOWED_TOPICS", format!("{}:42", FORUM_CHAT_ID));
+        std::env::set_var("FORWARD_ORIGINS", format!("{}:{}", RELAY_CHAT_ID, SOURCE_CHANNEL_ID));
+        std::env::set_var("ORDER_SENDERS", format!("{}:{}", BOT_CHAT_ID, ORDER_BOT_ID));
+        std::env::set_var("CHAT_ACTIONS", format!("{}:reply+button", ACTION_CHAT_ID));
+        std::env::set_var("REPLY_TEXT", "Беру {{amount}}{{#if bank}}, {{bank}}{{/if}}");
+        std::env::set_var("BUTTON_TEXT", "взять");
+        std::env::set_var("MIN_AMOUNT", "40000");
+        std::env::set_var("PRICE_PATTERNS", "default,amount_en");
+        std::env::set_var("ORDER_LANGUAGES", "ru,en");
+        std::env::set_var("REQUISITE_BLACKLIST", "+7 900 111-22-33");
+        std::env::set_var("SCAM_CHECKS", "bank_conflict,commission");
+        std::env::set_var("REACTION_EMOJI", "🔥");
+        std::env::set_var("EMOJI_TIERS", "100000:⚡");
+        std::env::set_var("REACTION_FORMAT", "typed");
+        std::env::set_var("HISTORY_STORE", "memory");
+        std::env::set_var("MARK_AS_READ", "true");
+        std::env::set_var("MAX_MESSAGE_AGE_SECS", "60");
+        std::env::set_var("REACTION_CONFIRM_TIMEOUT_MS", "200");
+        std::env::set_var("REACTION_RESENDS", "2");
+        // Turns down cash orders, and takes its time over urgent ones
+        std::env::set_var("PRE_REACTION_HOOK", "order=$(cat); case $order in *срочно*) sleep 3;; esac; ! echo \"$order\" | grep -q наличные");
+        std::env::set_var("PRE_REACTION_HOOK_TIMEOUT_MS", "5000");
+        std::env::set_var("PROCESSING_DEADLINE_MS", "2000");
+        let statsd = STATSD.get_or_init(|| UdpSocket::bind("127.0.0.1:0").unwrap());
+        std::env::set_var("METRICS_EXPORTER", "dogstatsd");
+        std::env::set_var("STATSD_ADDR", statsd.local_addr().unwrap().to_string());
+        std::env::set_var("STATSD_TAGS", "env:test");
+        // A port that was free a moment ago
+        let health_addr = HEALTH_ADDR.get_or_init(|| TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string());
+        std::env::set_var("HEALTH_ADDR", health_addr);
+        let socket = CONTROL_SOCKET.get_or_init(|| std::env::temp_dir().join(format!("tdlib-test-mock-{}.sock", std::process::id())));
+        std::env::set_var("CONTROL_SOCKET", socket);
+    });
+}
+
+fn ready() -> Value {
+    json!({ "@type": "updateAuthorizationState", "authorization_state": { "@type": "authorizationStateReady" } })
+}
+
+fn message(chat_id: i64, message_id: i64, text: &str) -> Value {
+    json!({
+        "@type": "updateNewMessage",
+        "message": {
+            "id": message_id,
+            "chat_id": chat_id,
+            "date": chrono::Utc::now().timestamp(),
+            "content": { "@type": "messageText", "text": { "@type": "formattedText", "text": text } }
+        }
+    })
+}
+
+// The same sent from the logged-in account, as the owner's commands are
+fn own_message(chat_id: i64, message_id: i64, text: &str) -> Value {
+    let mut update = message(chat_id, message_id, text);
+    update["message"]["is_outgoing"] = true.into();
+    update
+}
+
+// The same with formatting: each run of the text with its textEntityType
+fn formatted_message(chat_id: i64, message_id: i64, text: &str, runs: &[(&str, &str)]) -> Value {
+    let utf16 = |s: &str| s.encode_utf16().count();
+    let entities: Vec<Value> = runs
+        .iter()
+        .map(|(run, kind)| {
+            let at = text.find(run).unwrap();
+            json!({ "@type": "textEntity", "offset": utf16(&text[..at]), "length": utf16(run), "type": { "@type": kind } })
+        })
+        .collect();
+    let mut update = message(chat_id, message_id, text);
+    update["message"]["content"]["text"]["entities"] = entities.into();
+    update
+}
+
+// Run the bot until the client's script is played out
+fn play(client: MockClient) -> Arc<MockClient> {
+    setup();
+    let client = Arc::new(client);
+    let connect = {
+        let client = client.clone();
+        move || Ok(client.clone() as Arc<dyn TelegramClient>)
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(run(connect)).unwrap();
+    client
+}
+
+// The reactions the bot sent, as (chat, message, emoji)
+fn reactions(client: MockClient) -> Vec<(i64, i64, String)> {
+    play(client)
+        .sent_of_type("addMessageReaction")
+        .iter()
+        .map(|request| {
+            let emoji = request["reaction_type"]["emoji"].as_str().unwrap_or_default().to_string();
+            (request["chat_id"].as_i64().unwrap(), request["message_id"].as_i64().unwrap(), emoji)
+        })
+        .collect()
+}
+
+#[test]
+fn reacts_to_a_matching_order() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк")]);
+    assert_eq!(reactions(client), [(CHAT_ID, 1 << 20, "🔥".to_string())]);
+}
+
+#[test]
+fn reads_amounts_with_the_configured_price_patterns() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "New order\nAmount: 50,000.00 RUB\nBank: Sber")]);
+    assert_eq!(reactions(client), [(CHAT_ID, 1 << 20, "🔥".to_string())]);
+}
+
+#[test]
+fn reads_orders_through_markup_and_decorations() {
+    let client = MockClient::new([
+        ready(),
+        message(CHAT_ID, 1 << 20, "🟢 Новая заявка\n💰 **Сумма:** 50 000 ₽\n🏦 Банк: Т-Банк"),
+        message(CHAT_ID, 2 << 20, "<b>Сумма:</b>&nbsp;60 000 ₽<br>Реквизит:\u{200b} +7 900 111-22-33"),
+    ]);
+    assert_eq!(reactions(client), [(CHAT_ID, 1 << 20, "🔥".to_string())]);
+}
+
+#[test]
+fn reacts_to_large_orders_with_their_tier_emoji() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 150 000 ₽\nБанк: Т-Банк")]);
+    assert_eq!(reactions(client), [(CHAT_ID, 1 << 20, "⚡".to_string())]);
+}
+
+#[test]
+fn skips_orders_below_the_minimum() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 39 999 ₽\nБанк: Т-Банк")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn skips_messages_without_an_amount() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Кто на связи?")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn skips_blacklisted_requisites() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк\nРеквизит: 8 (900) 111 22 33")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn skips_orders_the_pre_reaction_hook_turns_down() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк\nТолько наличные")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn skips_orders_not_decided_before_the_deadline() {
+    let client = MockClient::new([ready(), message(CHAT_ID, 1 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк\nсрочно")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn ignores_chats_that_are_not_monitored() {
+    let client = MockClient::new([ready(), message(OTHER_CHAT_ID, 1 << 20, "Сумма: 50 000 ₽")]);
+    assert!(reactions(client).is_empty());
+}
+
+#[test]
+fn reacts_once_to_a_repeated_update() {
+    let order = message(CHAT_ID, 2 << 20, "Сумма: 60 000 ₽");
+    let client = MockClient::new([ready(), order.clone(), order]);
+    assert_eq!(reactions(client).len(), 1);
+}
+
+#[test]
+fn reacts_to_every_order_in_a_burst() {
+    let orders = (1..=5).map(|n| message(CHAT_ID, n << 20, &format!("Сумма: {} 000 ₽", 40 + n * 10)));
+    let client = MockClient::new(std::iter::once(ready()).chain(orders));
+    let reacted: Vec<i64> = reactions(client).into_iter().map(|(_, message_id, _)| message_id).collect();
+    assert_eq!(reacted, (1..=5).map(|n| n << 20).collect::<Vec<_>>());
+}
+
+#[test]
+fn sends_the_tdlib_parameters_and_opens_the_chats() {
+    let client = play(MockClient::new([ready()]));
+    assert_eq!(client.sent_of_type("setTdlibParameters").len(), 1);
+    assert!(client.sent_of_type("openChat").iter().any(|request| request["chat_id"] == CHAT_ID));
+}
+
+#[test]
+fn encrypts_the_session_with_a_key_kept_for_the_next_run() {
+    let key = |client: Arc<MockClient>| client.sent_of_type("setTdlibParameters")[0]["database_encryption_key"].as_str().unwrap().to_string();
+    let first = key(play(MockClient::new([ready()])));
+    let second = key(play(MockClient::new([ready()])));
+    assert_eq!(first.len(), 44);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn handles_reaction_results() {
+    // TDLib confirms the reaction, which settles it: nothing is sent again
+    let client = MockClient::new([ready(), message(CHAT_ID, 3 << 20, "Сумма: 70 000 ₽")]).with_responder(|request| {
+        if request["@type"] == "addMessageReaction" {
+            vec![json!({ "@type": "ok", "@extra": request["@extra"] })]
+        } else {
+            Vec::new()
+        }
+    });
+    assert_eq!(reactions(client).len(), 1);
+}
+
+#[test]
+fn handles_updates_pushed_while_running() {
+    setup();
+    let client = Arc::new(MockClient::open());
+    let feeder = {
+        let client = client.clone();
+        std::thread::spawn(move || {
+            client.push(ready());
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            client.push(message(CHAT_ID, 4 << 20, "Сумма: 80 000 ₽"));
+            client.close();
+        })
+    };
+    let connect = {
+        let client = client.clone();
+        move || Ok(client.clone() as Arc<dyn TelegramClient>)
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(run(connect)).unwrap();
+    feeder.join().unwrap();
+    assert_eq!(client.sent_of_type("addMessageReaction").len(), 1);
+}
+
+#[test]
+fn marks_matched_messages_as_read() {
+    let client = play(MockClient::new([ready(), message(CHAT_ID, 5 << 20, "Сумма: 90 000 ₽"), message(CHAT_ID, 6 << 20, "Сумма: 100 ₽")]));
+    let viewed: Vec<Value> = client.sent_of_type("viewMessages").iter().map(|request| request["message_ids"].clone()).collect();
+    assert_eq!(viewed, [json!([5 << 20])]);
+}
+
+#[test]
+fn reacts_only_in_watched_forum_topics() {
+    let in_topic = |message_id: i64, thread_id: i64| {
+        let mut update = message(FORUM_CHAT_ID, message_id, "Сумма: 50 000 ₽");
+        update["message"]["message_thread_id"] = json!(thread_id);
+        update
+    };
+    let client = MockClient::new([ready(), in_topic(7 << 20, 42 << 20), in_topic(8 << 20, 43 << 20), message(FORUM_CHAT_ID, 9 << 20, "Сумма: 50 000 ₽")]);
+    let reacted: Vec<i64> = reactions(client).into_iter().map(|(_, message_id, _)| message_id).collect();
+    assert_eq!(reacted, [7 << 20]);
+}
+
+#[test]
+fn reacts_only_to_orders_forwarded_from_the_trusted_source() {
+    let forwarded = |message_id: i64, origin: Value| {
+        let mut update = message(RELAY_CHAT_ID, message_id, "Сумма: 50 000 ₽");
+        update["message"]["forward_info"] = json!({ "@type": "messageForwardInfo", "origin": origin, "date": 0 });
+        update
+    };
+    let client = MockClient::new([
+        ready(),
+        forwarded(70 << 20, json!({ "@type": "messageOriginChannel", "chat_id": SOURCE_CHANNEL_ID, "message_id": 1 << 20 })),
+        forwarded(71 << 20, json!({ "@type": "messageOriginChannel", "chat_id": OTHER_CHAT_ID, "message_id": 1 << 20 })),
+        forwarded(72 << 20, json!({ "@type": "messageOriginHiddenUser", "sender_name": "Someone" })),
+        message(RELAY_CHAT_ID, 73 << 20, "Сумма: 50 000 ₽"),
+    ]);
+    let reacted: Vec<i64> = reactions(client).into_iter().map(|(_, message_id, _)| message_id).collect();
+    assert_eq!(reacted, [70 << 20]);
+}
+
+#[test]
+fn reacts_only_to_orders_from_the_chats_order_bot() {
+    let sent_by = |message_id: i64, user_id: i64| {
+        let mut update = message(BOT_CHAT_ID, message_id, "Сумма: 50 000 ₽\nБанк: Т-Банк");
+        update["message"]["sender_id"] = json!({ "@type": "messageSenderUser", "user_id": user_id });
+        update
+    };
+    let client = MockClient::new([ready(), sent_by(80 << 20, 42), sent_by(81 << 20, ORDER_BOT_ID), message(BOT_CHAT_ID, 82 << 20, "Сумма: 50 000 ₽")]);
+    let reacted: Vec<i64> = reactions(client).into_iter().map(|(_, message_id, _)| message_id).collect();
+    assert_eq!(reacted, [81 << 20]);
+}
+
+#[test]
+fn reacts_to_channel_posts_in_the_discussion_group() {
+    let client = MockClient::new([ready(), message(CHANNEL_ID, 10 << 20, "Сумма: 50 000 ₽")]).with_responder(|request| {
+        if request["@type"] == "getMessageThread" {
+            vec![json!({
+                "@type": "messageThreadInfo",
+                "chat_id": DISCUSSION_ID,
+                "messages": [{ "id": 11 << 20, "chat_id": DISCUSSION_ID }],
+                "@extra": request["@extra"]
+            })]
+        } else {
+            Vec::new()
+        }
+    });
+    assert_eq!(reactions(client), [(DISCUSSION_ID, 11 << 20, "🔥".to_string())]);
+}
+
+#[test]
+fn skips_orders_that_arrive_too_late() {
+    let mut late = message(CHAT_ID, 12 << 20, "Сумма: 50 000 ₽");
+    late["message"]["date"] = json!(chrono::Utc::now().timestamp() - 600);
+    assert!(reactions(MockClient::new([ready(), late])).is_empty());
+}
+
+#[test]
+fn resends_unanswered_reactions_until_giving_up() {
+    setup();
+    let client = Arc::new(MockClient::open());
+    let feeder = {
+        let client = client.clone();
+        std::thread::spawn(move || {
+            client.push(ready());
+            client.push(message(CHAT_ID, 13 << 20, "Сумма: 50 000 ₽"));
+            // Chatter elsewhere keeps the loop turning past every deadline
+            for n in 0..20 {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                client.push(message(OTHER_CHAT_ID, (100 + n) << 20, "+"));
+            }
+            client.close();
+        })
+    };
+    let connect = {
+        let client = client.clone();
+        move || Ok(client.clone() as Arc<dyn TelegramClient>)
+    };
+    tokio::runtime::Runtime::new().unwrap().block_on(run(connect)).unwrap();
+    feeder.join().unwrap();
+    // The first attempt and two resends, all with the same tag
+    let sent = client.sent_of_type("addMessageReaction");
+    assert_eq!(sent.len(), 3);
+    assert!(sent.iter().all(|request| request["@extra"] == sent[0]["@extra"]));
+}
+
+#[test]
+fn reacts_in_hot_chats_first() {
+    let busy = (1..=3).map(|n| message(CHAT_ID, (20 + n) << 20, "Сумма: 50 000 ₽"));
+    let client = MockClient::new(std::iter::once(ready()).chain(busy).chain([message(HOT_CHAT_ID, 30 << 20, "Сумма: 50 000 ₽")]));
+    let chats: Vec<i64> = reactions(client).into_iter().map(|(chat_id, _, _)| chat_id).collect();
+    assert_eq!(chats, [HOT_CHAT_ID, CHAT_ID, CHAT_ID, CHAT_ID]);
+}
+
+#[test]
+fn explains_skipped_orders_with_why() {
+    let client = MockClient::new([
+        ready(),
+        message(CHAT_ID, 40 << 20, "Сумма: 39 999 ₽\nБанк: Т-Банк"),
+        own_message(OTHER_CHAT_ID, 41 << 20, "/why 40"),
+    ]);
+    let replies = play(client).sent_of_type("sendMessage");
+    let reply = replies[0]["input_message_content"]["text"]["text"].as_str().unwrap();
+    assert!(reply.contains("skipped: below_min_amount"), "{}", reply);
+    assert!(reply.contains("• amount: 39999 (wanted >= 40000) ❌"), "{}", reply);
+}
+
+// What validate-corpus checks: every bundled message format still reads as it should
+#[test]
+fn parses_the_bundled_corpus_as_expected() {
+    setup();
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(crate::corpus::DEFAULT_CORPUS_DIR);
+    let patterns = crate::rates::price_patterns();
+    let wrong: Vec<String> = crate::corpus::load(&dir)
+        .unwrap()
+        .iter()
+        .flat_map(|sample| sample.check(&patterns).1.into_iter().map(|wrong| format!("{}: {}", sample.source, wrong)))
+        .collect();
+    assert!(wrong.is_empty(), "{:#?}", wrong);
+}
+
+#[test]
+fn reads_the_amount_and_requisite_from_formatting() {
+    let text = "🟢 Новая заявка\n50 000 ₽\nСБП: +7 900 000-00-00 (Иван И.)";
+    let blacklisted = "🟢 Новая заявка\n60 000 ₽\nСБП: +7 900 111-22-33 (Иван И.)";
+    let client = MockClient::new([
+        ready(),
+        formatted_message(CHAT_ID, 50 << 20, text, &[("50 000 ₽", "textEntityTypeBold"), ("+7 900 000-00-00", "textEntityTypeCode")]),
+        formatted_message(CHAT_ID, 51 << 20, blacklisted, &[("60 000 ₽", "textEntityTypeBold"), ("+7 900 111-22-33", "textEntityTypeCode")]),
+        // Without the bold run there's no amount to go by
+        message(CHAT_ID, 52 << 20, text),
+    ]);
+    assert_eq!(reactions(client), [(CHAT_ID, 50 << 20, "🔥".to_string())]);
+}
+
+#[test]
+fn skips_ignored_content_types_before_parsing() {
+    let with_content = |message_id: i64, content: Value| {
+        let mut update = message(CHAT_ID, message_id, "");
+        update["message"]["content"] = content;
+        update
+    };
+    let client = MockClient::new([
+        ready(),
+        with_content(60 << 20, json!({ "@type": "messageSticker", "sticker": { "emoji": "👍" } })),
+        with_content(61 << 20, json!({ "@type": "messageChatAddMembers", "member_user_ids": [42] })),
+        message(CHAT_ID, 62 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        own_message(OTHER_CHAT_ID, 63 << 20, "/stats"),
+    ]);
+    let client = play(client);
+    assert_eq!(client.sent_of_type("addMessageReaction")[0]["message_id"], 62 << 20);
+    let replies = client.sent_of_type("sendMessage");
+    let reply = replies[0]["input_message_content"]["text"]["text"].as_str().unwrap();
+    assert!(reply.contains("content_type: 2"), "{}", reply);
+}
+
+#[test]
+fn skips_orders_that_look_fake() {
+    let client = MockClient::new([
+        ready(),
+        message(CHAT_ID, 90 << 20, "Сумма: 50 000 ₽\nБанк: Сбербанк\nРеквизит: +7 900 555-66-77"),
+        // The same phone number, now said to be at another bank
+        message(CHAT_ID, 91 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк\nРеквизит: 8 (900) 555 66 77"),
+        message(CHAT_ID, 92 << 20, "Сумма: 50 000 ₽\nБанк: Сбербанк\nКомиссия: 25%"),
+        message(CHAT_ID, 93 << 20, "Сумма: 50 000 ₽\nБанк: Сбербанк\nРеквизит: +7 900 777-88-99\nКомиссия: 2%"),
+        own_message(OTHER_CHAT_ID, 94 << 20, "/stats"),
+    ]);
+    let client = play(client);
+    let reacted: Vec<Value> = client.sent_of_type("addMessageReaction").iter().map(|request| request["message_id"].clone()).collect();
+    assert_eq!(reacted, [json!(90 << 20), json!(93 << 20)]);
+    let replies = client.sent_of_type("sendMessage");
+    let reply = replies[0]["input_message_content"]["text"]["text"].as_str().unwrap();
+    assert!(reply.contains("suspicious: 2"), "{}", reply);
+}
+
+#[test]
+fn reads_amounts_only_with_the_patterns_for_the_messages_language() {
+    let client = MockClient::new([
+        ready(),
+        // "total: 50 000 ₽" is the English amount pattern, but this is Russian chatter
+        message(CHAT_ID, 95 << 20, "Ребята, в итоге за билеты total: 50 000 ₽, скидываемся до пятницы"),
+        message(CHAT_ID, 96 << 20, "😂😂😂😂 ха: 50 000 ₽ 🔥🔥🔥"),
+        message(CHAT_ID, 97 << 20, "Amount: 50,000.00 RUB"),
+        message(CHAT_ID, 98 << 20, "Сумма: 50 000 ₽"),
+        own_message(OTHER_CHAT_ID, 99 << 20, "/stats"),
+    ]);
+    let client = play(client);
+    let reacted: Vec<Value> = client.sent_of_type("addMessageReaction").iter().map(|request| request["message_id"].clone()).collect();
+    assert_eq!(reacted, [json!(97 << 20), json!(98 << 20)]);
+    let replies = client.sent_of_type("sendMessage");
+    let reply = replies[0]["input_message_content"]["text"]["text"].as_str().unwrap();
+    assert!(reply.contains("language: 1"), "{}", reply);
+}
+
+#[test]
+fn replies_and_presses_the_button_where_configured_instead_of_reacting() {
+    let mut order = message(ACTION_CHAT_ID, 100 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк");
+    let button = |text: &str, data: &str| json!({ "text": text, "type": { "@type": "inlineKeyboardButtonTypeCallback", "data": data } });
+    order["message"]["reply_markup"] = json!({ "@type": "replyMarkupInlineKeyboard", "rows": [[button("Отказаться", "c2tpcA=="), button("✅ Взять", "dGFrZQ==")]] });
+    let client = play(MockClient::new([ready(), order]));
+    assert!(client.sent_of_type("addMessageReaction").is_empty());
+    let replies = client.sent_of_type("sendMessage");
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0]["reply_to"]["message_id"], 100 << 20);
+    assert_eq!(replies[0]["input_message_content"]["text"]["text"], "Беру 50000, Т-Банк");
+    let presses = client.sent_of_type("getCallbackQueryAnswer");
+    assert_eq!(presses.len(), 1);
+    assert_eq!(presses[0]["payload"]["data"], "dGFrZQ==");
+}
+
+#[test]
+fn sends_decisions_and_latency_to_dogstatsd() {
+    play(MockClient::new([ready(), message(CHAT_ID, 101 << 20, "Сумма: 39 999 ₽"), message(CHAT_ID, 102 << 20, "Сумма: 45 000 ₽")]));
+    // Every test's metrics come here; these are among them
+    let statsd = STATSD.get().unwrap();
+    statsd.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    let mut wanted = vec!["botdg.skipped:1|c|#env:test,reason:below_min_amount", "botdg.reacted:1|c|#env:test", "|ms|#env:test,stage:total"];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut buffer = [0; 2048];
+    while !wanted.is_empty() && Instant::now() < deadline {
+        let Ok(length) = statsd.recv(&mut buffer) else { continue };
+        let datagram = String::from_utf8_lossy(&buffer[..length]).to_string();
+        wanted.retain(|line| !datagram.lines().any(|received| received.ends_with(line) || received == *line));
+    }
+    assert!(wanted.is_empty(), "never received {:?}", wanted);
+}
+
+// GET a health endpoint: the status line's code and the JSON body
+fn probe(path: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(HEALTH_ADDR.get().unwrap()).unwrap();
+    stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn reports_health_and_readiness_over_http() {
+    let connected = json!({ "@type": "updateConnectionState", "state": { "@type": "connectionStateReady" } });
+    let client = MockClient::new([ready(), connected, message(CHAT_ID, 103 << 20, "Сумма: 55 000 ₽")]).with_responder(|request| {
+        if request["@type"] == "addMessageReaction" {
+            vec![json!({ "@type": "ok", "@extra": request["@extra"] })]
+        } else {
+            Vec::new()
+        }
+    });
+    play(client);
+    let (status, health) = probe("/healthz");
+    assert_eq!(status, 200);
+    assert_eq!(health["auth_state"], "authorizationStateReady");
+    assert!(health["last_update_age_secs"].is_u64());
+    assert!(health["last_reaction_age_secs"].is_u64());
+    // Other tests' sessions never report a connection state, so this one's is still there
+    let (status, ready) = probe("/readyz?verbose");
+    assert_eq!(status, 200, "{}", ready);
+    assert_eq!(ready["connection_state"], "connectionStateReady");
+    assert_eq!(probe("/metrics").0, 404);
+}
+
+// A connection to the control socket, once the bot has opened it
+fn connect_rpc() -> (UnixStream, BufReader<UnixStream>) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match UnixStream::connect(CONTROL_SOCKET.get().unwrap()) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+                let reader = BufReader::new(stream.try_clone().unwrap());
+                return (stream, reader);
+            }
+            Err(e) if Instant::now() > deadline => panic!("the control socket never opened: {}", e),
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+// Send a JSON-RPC request and read its response, skipping event notifications
+fn call(connection: &mut (UnixStream, BufReader<UnixStream>), id: u64, method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    connection.0.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    loop {
+        let mut line = String::new();
+        connection.1.read_line(&mut line).unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        if response["id"] == id {
+            return response;
+        }
+    }
+}
+
+#[test]
+fn answers_json_rpc_on_the_control_socket() {
+    setup();
+    let client = Arc::new(MockClient::open());
+    client.push(ready());
+    let bot = {
+        let client = client.clone();
+        std::thread::spawn(move || {
+            let connect = move || Ok(client.clone() as Arc<dyn TelegramClient>);
+            tokio::runtime::Runtime::new().unwrap().block_on(run(connect)).unwrap();
+        })
+    };
+
+    // Every test's bot shares the settings, so whichever answers has these
+    let mut connection = connect_rpc();
+    let status = call(&mut connection, 1, "status", json!({}));
+    assert_eq!(status["result"]["paused"], false, "{}", status);
+    assert_eq!(status["result"]["filters"]["min_amount"], 40000);
+    assert!(call(&mut connection, 2, "stats", Value::Null)["result"]["filters"]["skipped"].is_object());
+    assert_eq!(call(&mut connection, 3, "reboot", json!({}))["error"]["code"], -32601);
+    // Rejected as a whole, so no bot's filters change
+    let rejected = call(&mut connection, 4, "set_filters", json!({ "min_amount": "a lot", "bank": "Т" }));
+    assert_eq!(rejected["error"]["code"], -32602);
+
+    let mut tail = connect_rpc();
+    assert_eq!(call(&mut tail, 1, "tail_events", json!({}))["result"]["subscribed"], true);
+    client.push(message(CHAT_ID, 107 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"));
+    let target = format!("{}:{}", CHAT_ID, 107 << 20);
+    loop {
+        let mut line = String::new();
+        tail.1.read_line(&mut line).unwrap();
+        let notification: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(notification["method"], "event");
+        if notification["params"]["event"] == "match" && notification["params"]["target"] == target.as_str() {
+            break;
+        }
+    }
+    client.close();
+    bot.join().unwrap();
+}
+
+#[test]
+fn refuses_a_session_another_bot_holds() {
+    let dir = std::env::temp_dir().join(format!("tdlib-test-session-{}", std::process::id()));
+    let dir = dir.to_string_lossy();
+    let lock = crate::daemon::lock_session(&dir).unwrap();
+    let refused = crate::daemon::lock_session(&dir).err().unwrap();
+    assert_eq!(refused.exit_code(), crate::error::EXIT_CONFIG);
+    let refused = refused.to_string();
+    assert!(refused.contains(&format!("PID {}", std::process::id())), "{}", refused);
+    drop(lock);
+    crate::daemon::lock_session(&dir).unwrap();
+    let _ = std::fs::remove_dir_all(&*dir);
+}
+
+#[test]
+fn exits_as_temporary_when_the_session_directory_cant_be_created() {
+    // A file where the directory should go
+    let file = std::env::temp_dir().join(format!("tdlib-test-not-a-dir-{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    let error = crate::daemon::lock_session(&file.join("session").to_string_lossy()).err().unwrap();
+    assert_eq!(error.exit_code(), crate::error::EXIT_TEMPORARY, "{}", error);
+    assert!(error.to_string().starts_with("Failed to create"), "{}", error);
+    let _ = std::fs::remove_file(&file);
+}
+
+#[test]
+fn refuses_a_session_whose_database_key_cant_be_read() {
+    let dir = std::env::temp_dir().join(format!("tdlib-test-dbkey-{}", std::process::id()));
+    // A directory where the key file should be, and a session that needs it
+    std::fs::create_dir_all(dir.join("db.key")).unwrap();
+    std::fs::write(dir.join("td.binlog"), "").unwrap();
+    let error = crate::dbkey::get(&dir.to_string_lossy()).err().unwrap();
+    assert_eq!(error.exit_code(), crate::error::EXIT_CONFIG);
+    assert!(error.to_string().contains("db.key"), "{}", error);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn answers_the_owners_commands_in_any_chat() {
+    let from = |message_id: i64, user_id: i64, text: &str| {
+        let mut update = message(OTHER_CHAT_ID, message_id, text);
+        update["message"]["sender_id"] = json!({ "@type": "messageSenderUser", "user_id": user_id });
+        update
+    };
+    let mut own = message(OTHER_CHAT_ID, 114 << 20, "/resume");
+    own["message"]["is_outgoing"] = true.into();
+    let client = play(MockClient::new([
+        ready(),
+        // Not the owner: an ordinary message
+        from(110 << 20, 42, "/pause"),
+        from(111 << 20, OWNER_ID, "/pause"),
+        message(CHAT_ID, 112 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        from(113 << 20, OWNER_ID, "/status"),
+        own,
+        message(CHAT_ID, 115 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        from(116 << 20, OWNER_ID, "/ping"),
+    ]));
+    let reacted: Vec<Value> = client.sent_of_type("addMessageReaction").iter().map(|request| request["message_id"].clone()).collect();
+    assert_eq!(reacted, [json!(115 << 20)]);
+    let replies: Vec<String> = client
+        .sent_of_type("sendMessage")
+        .iter()
+        .map(|reply| reply["input_message_content"]["text"]["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(replies.len(), 4, "{:?}", replies);
+    assert!(replies[0].starts_with("⏸ Paused"), "{}", replies[0]);
+    assert!(replies[1].starts_with("⏸ Paused\n") && replies[1].contains("from 40000 ₽"), "{}", replies[1]);
+    assert_eq!(replies[2], "▶️ Reacting again");
+    assert!(replies[3].starts_with("🏓 Pong, up "), "{}", replies[3]);
+}
+
+#[test]
+fn shows_chat_titles_and_asks_who_posted_orders() {
+    let mut order = message(CHAT_ID, 120 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк");
+    order["message"]["sender_id"] = json!({ "@type": "messageSenderUser", "user_id": 42 });
+    let client = MockClient::new([
+        ready(),
+        json!({ "@type": "updateChatTitle", "chat_id": CHAT_ID, "title": "Обменник" }),
+        order,
+        own_message(OTHER_CHAT_ID, 121 << 20, "/list 1"),
+    ])
+    .with_responder(|request| {
+        if request["@type"] == "getUser" {
+            vec![json!({ "@type": "user", "id": request["user_id"], "first_name": "Иван", "last_name": "", "@extra": request["@extra"] })]
+        } else {
+            Vec::new()
+        }
+    });
+    let client = play(client);
+    let asked: Vec<Value> = client.sent_of_type("getUser").iter().map(|request| request["user_id"].clone()).collect();
+    assert_eq!(asked, [json!(42)]);
+    let replies = client.sent_of_type("sendMessage");
+    let reply = replies[0]["input_message_content"]["text"]["text"].as_str().unwrap();
+    assert!(reply.contains(&format!("chat Обменник ({}) msg {}", CHAT_ID, 120 << 20)), "{}", reply);
+}
+
+#[test]
+fn watches_the_chat_the_owner_sends_watch_in() {
+    let own = |chat_id: i64, message_id: i64, text: &str| {
+        let mut update = message(chat_id, message_id, text);
+        update["message"]["is_outgoing"] = true.into();
+        update
+    };
+    let client = play(MockClient::new([
+        ready(),
+        own(OTHER_CHAT_ID, 130 << 20, "/watch on"),
+        message(OTHER_CHAT_ID, 131 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        own(CHAT_ID, 132 << 20, "/watch off"),
+        message(CHAT_ID, 133 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        own(CHAT_ID, 134 << 20, "/watch off"),
+    ]));
+    let reacted: Vec<(i64, i64)> = client
+        .sent_of_type("addMessageReaction")
+        .iter()
+        .map(|request| (request["chat_id"].as_i64().unwrap(), request["message_id"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(reacted, [(OTHER_CHAT_ID, 131 << 20)]);
+    let replies: Vec<(i64, String)> = client
+        .sent_of_type("sendMessage")
+        .iter()
+        .map(|reply| (reply["chat_id"].as_i64().unwrap(), reply["input_message_content"]["text"]["text"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(
+        replies,
+        [
+            (OTHER_CHAT_ID, format!("👀 Watching this chat ({}) from now on", OTHER_CHAT_ID)),
+            (CHAT_ID, format!("🙈 No longer watching this chat ({})", CHAT_ID)),
+            (CHAT_ID, "This chat isn't watched".to_string()),
+        ]
+    );
+    assert!(client.sent_of_type("getChat").iter().any(|request| request["chat_id"] == OTHER_CHAT_ID));
+}
+
+#[test]
+fn ignores_history_commands_from_anyone_but_the_owner() {
+    let mut stranger = message(OTHER_CHAT_ID, 141 << 20, "/clear");
+    stranger["message"]["sender_id"] = json!({ "@type": "messageSenderUser", "user_id": 42 });
+    let client = play(MockClient::new([
+        ready(),
+        message(CHAT_ID, 140 << 20, "Сумма: 50 000 ₽\nБанк: Т-Банк"),
+        stranger,
+        message(OTHER_CHAT_ID, 142 << 20, "/list"),
+        own_message(OTHER_CHAT_ID, 143 << 20, "/list"),
+    ]));
+    let replies: Vec<(i64, String)> = client
+        .sent_of_type("sendMessage")
+        .iter()
+        .map(|reply| (reply["chat_id"].as_i64().unwrap(), reply["input_message_content"]["text"]["text"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(replies.len(), 1, "{:?}", replies);
+    assert!(replies[0].1.starts_with("📋 Last 1 reactions:"), "{}", replies[0].1);
+}
+
+#[test]
+fn lists_every_bad_setting_at_once() {
+    setup();
+    crate::validate::all().check().unwrap();
+    let mut problems = botdg_config::validate::Problems::new();
+    crate::validate::hot_log(&mut problems, Some("often".to_string()), Some("-1".to_string()));
+    let problems = problems.check().err().unwrap().to_string();
+    assert!(problems.starts_with("2 configuration problem(s):"), "{}", problems);
+    assert!(problems.contains("HOT_LOG_EVERY 'often' is not a number"), "{}", problems);
+    assert!(problems.contains("HOT_LOG_PER_MINUTE '-1' is not a number"), "{}", problems);
+}
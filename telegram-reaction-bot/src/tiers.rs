@@ -0,0 +1,75 @@
+use std::collections::{HashMap, VecDeque};
+use tracing::{info, warn};
+
+// Reactions whose emoji is remembered for resends; older ones fall back to REACTION_EMOJI
+const MAX_REMEMBERED: usize = 1000;
+
+// EMOJI_TIERS=60000:🔥,100000:⚡: react with another emoji by the order's amount in rubles, so its
+// size shows at a glance. Each emoji goes from its amount up to the next tier's; orders below the
+// lowest tier, or without an amount, get REACTION_EMOJI. The emoji picked for a reaction sticks to
+// its "@extra", so resends and the discussion group copy use the same one. Off when unset
+pub struct EmojiTiers {
+    default: String,
+    tiers: Vec<(i32, String)>,   // Ascending amounts
+    chosen: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl EmojiTiers {
+    pub fn from_env(default: &str) -> Self {
+        let tiers = match parse(&std::env::var("EMOJI_TIERS").unwrap_or_default()) {
+            Ok(tiers) => tiers,
+            Err(e) => {
+                warn!("Ignoring invalid EMOJI_TIERS: {}", e);
+                Vec::new()
+            }
+        };
+        if !tiers.is_empty() {
+            info!("Emoji tiers: {:?}", tiers);
+        }
+        Self { default: default.to_string(), tiers, chosen: HashMap::new(), order: VecDeque::new() }
+    }
+
+    // The emoji for a new reaction to an order of `amount` rubles
+    pub fn pick(&mut self, extra: &str, amount: Option<i32>) -> &str {
+        let Some((_, emoji)) = amount.and_then(|amount| self.tiers.iter().rev().find(|(from, _)| amount >= *from)) else {
+            return &self.default;
+        };
+        self.remember(extra, emoji.clone());
+        self.get(extra)
+    }
+
+    // Stick to an emoji picked earlier, e.g. before a restart (see claims.rs)
+    pub fn remember(&mut self, extra: &str, emoji: String) {
+        self.chosen.insert(extra.to_string(), emoji);
+        self.order.push_back(extra.to_string());
+        if self.order.len() > MAX_REMEMBERED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.chosen.remove(&oldest);
+            }
+        }
+    }
+
+    // The emoji picked for a reaction already decided on
+    pub fn get(&self, extra: &str) -> &str {
+        self.chosen.get(extra).unwrap_or(&self.default)
+    }
+}
+
+// EMOJI_TIERS as (amount, emoji), ascending
+pub fn parse(value: &str) -> Result<Vec<(i32, String)>, String> {
+    let mut tiers = value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (amount, emoji) = entry.split_once(':').ok_or_else(|| format!("'{}' is not amount:emoji", entry.trim()))?;
+            let amount = amount.trim().parse::<i32>().map_err(|_| format!("'{}' is not an amount", amount.trim()))?;
+            match emoji.trim() {
+                "" => Err(format!("no emoji for {}", amount)),
+                emoji => Ok((amount, emoji.to_string())),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    tiers.sort_by_key(|(amount, _)| *amount);
+    Ok(tiers)
+}
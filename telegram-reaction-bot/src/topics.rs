@@ -0,0 +1,46 @@
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+
+// TDLib message (and thread) IDs are server IDs shifted left by 20 bits
+const SERVER_ID_SHIFT: u32 = 20;
+
+// Forum topics to watch in forum supergroups, from ALLOWED_TOPICS: "chat:topic" pairs, comma
+// separated, e.g. "-1001234567890:42,-1001234567890:57". A topic is the number in its links
+// (t.me/c/1234567890/42); TDLib thread IDs (multiples of 1048576) work too. Chats without an
+// entry are watched whole
+#[derive(Default)]
+pub struct Topics {
+    allowed: HashMap<i64, HashSet<i64>>,
+}
+
+impl Topics {
+    pub fn from_env() -> Self {
+        let mut allowed: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for entry in std::env::var("ALLOWED_TOPICS").unwrap_or_default().split(',').filter(|e| !e.trim().is_empty()) {
+            match parse_entry(entry) {
+                Some((chat_id, thread_id)) => {
+                    allowed.entry(chat_id).or_default().insert(thread_id);
+                }
+                None => warn!("Ignoring invalid ALLOWED_TOPICS entry '{}'", entry),
+            }
+        }
+        for (chat_id, threads) in &allowed {
+            info!("Watching only {} topic(s) in chat {}", threads.len(), chat_id);
+        }
+        Self { allowed }
+    }
+
+    // Whether a message in `thread_id` (its message_thread_id, 0 outside of threads) of a watched
+    // chat should go through the filters
+    pub fn allows(&self, chat_id: i64, thread_id: i64) -> bool {
+        self.allowed.get(&chat_id).is_none_or(|threads| threads.contains(&thread_id))
+    }
+}
+
+// "chat:topic", the topic as a TDLib thread ID
+pub fn parse_entry(entry: &str) -> Option<(i64, i64)> {
+    let (chat_id, topic) = entry.split_once(':')?;
+    let topic: i64 = topic.trim().parse().ok().filter(|topic| *topic > 0)?;
+    let thread_id = if topic % (1 << SERVER_ID_SHIFT) == 0 { topic } else { topic << SERVER_ID_SHIFT };
+    Some((chat_id.trim().parse().ok()?, thread_id))
+}
@@ -0,0 +1,76 @@
+use tracing::{info, warn};
+
+// Optional CPU pinning and scheduling priority for the latency-critical threads, for bots running
+// on a dedicated machine. RECEIVE_THREAD_CPU and SENDER_THREAD_CPU pin the receive loop and the
+// sender thread (see sender.rs) to a core; THREAD_PRIORITY is "realtime" (SCHED_FIFO) or a nice
+// value such as -10. Both need Linux, and raising the priority needs root or CAP_SYS_NICE;
+// anything that can't be applied is logged and skipped
+pub fn tune_current_thread(role: &str) {
+    let cpu_var = format!("{}_THREAD_CPU", role.to_uppercase());
+    if let Some(cpu) = std::env::var(&cpu_var).ok().filter(|s| !s.is_empty()) {
+        match cpu.trim().parse::<usize>() {
+            Ok(cpu) => match pin(cpu) {
+                Ok(()) => info!("Pinned the {} thread to CPU {}", role, cpu),
+                Err(e) => warn!("Failed to pin the {} thread to CPU {}: {}", role, cpu, e),
+            },
+            Err(_) => warn!("Ignoring invalid {} '{}'", cpu_var, cpu),
+        }
+    }
+
+    if let Some(priority) = std::env::var("THREAD_PRIORITY").ok().filter(|s| !s.is_empty()) {
+        match raise_priority(priority.trim()) {
+            Ok(()) => info!("Set the {} thread's priority to {}", role, priority),
+            Err(e) => warn!("Failed to set the {} thread's priority to {}: {}", role, priority, e),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin(cpu: usize) -> std::io::Result<()> {
+    // SAFETY: cpu_set_t is plain data; pid 0 is the calling thread
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn raise_priority(priority: &str) -> std::io::Result<()> {
+    const REALTIME_PRIORITY: libc::c_int = 50;
+
+    if priority.eq_ignore_ascii_case("realtime") {
+        let param = libc::sched_param { sched_priority: REALTIME_PRIORITY };
+        // SAFETY: pthread_self is always valid for the calling thread
+        return match unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) } {
+            0 => Ok(()),
+            errno => Err(std::io::Error::from_raw_os_error(errno)),
+        };
+    }
+
+    let nice = priority
+        .parse::<libc::c_int>()
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "expected \"realtime\" or a nice value"))?;
+    // On Linux a thread ID addresses just that thread
+    // SAFETY: plain system calls on the calling thread
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        if libc::setpriority(libc::PRIO_PROCESS, tid, nice) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin(_cpu: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "only supported on Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn raise_priority(_priority: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "only supported on Linux"))
+}
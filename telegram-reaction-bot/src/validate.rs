@@ -0,0 +1,298 @@
+use botdg_config::{
+    schedule::{self, Schedule},
+    validate::{var, Problems},
+};
+use botdg_core::{Languages, PricePatterns};
+
+use crate::{actions, balance, content, discussion, history, jitter, lifecycle, origins, parse_hours, plugins, scam, score_entries, secrets, senders, tiers, topics, wasm, DEFAULT_TDLIB_DATA_DIR};
+
+// Longest REACTION_EMOJI in chars: flags, skin tones and ZWJ sequences are several code points
+const MAX_EMOJI_CHARS: usize = 10;
+
+// For commands that only log in (`auth`, `logout`)
+pub fn check_credentials() -> Result<(), Problems> {
+    let mut problems = Problems::new();
+    credentials(&mut problems);
+    problems.check()
+}
+
+// The API credentials, which every command talking to Telegram needs
+fn credentials(problems: &mut Problems) {
+    if let Some(id) = problems.required("TELEGRAM_API_ID", secrets::get("TELEGRAM_API_ID")) {
+        if id.trim().parse::<i32>().is_err() {
+            problems.push(format!("TELEGRAM_API_ID '{}' is not a number", id));
+        }
+    }
+    problems.required("TELEGRAM_API_HASH", secrets::get("TELEGRAM_API_HASH"));
+}
+
+// The sampling of skipped messages (see hotlog.rs)
+pub fn hot_log(problems: &mut Problems, every: Option<String>, per_minute: Option<String>) {
+    problems.number_value::<u64>("HOT_LOG_EVERY", every, 0..);
+    problems.number_value::<u32>("HOT_LOG_PER_MINUTE", per_minute, 0..);
+}
+
+// Everything `run` reads, checked before it daemonizes or touches TDLib; `check-config` prints
+// the same list
+pub fn all() -> Problems {
+    let mut problems = Problems::new();
+    credentials(&mut problems);
+
+    let allowed_chat_ids = problems.ids("ALLOWED_CHAT_IDS");
+    if allowed_chat_ids.is_empty() {
+        problems.push("ALLOWED_CHAT_IDS is empty: no chat would be watched");
+    }
+    for entry in var("ALLOWED_TOPICS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if topics::parse_entry(entry).is_none() {
+            problems.push(format!("ALLOWED_TOPICS entry '{}' is not chat:topic", entry.trim()));
+        }
+    }
+    for entry in var("DISCUSSION_REACTIONS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if discussion::parse_entry(entry).is_none() {
+            problems.push(format!("DISCUSSION_REACTIONS entry '{}' is not chat or chat:fallback", entry.trim()));
+        }
+    }
+    for entry in var("FORWARD_ORIGINS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if origins::parse_entry(entry).is_none() {
+            problems.push(format!("FORWARD_ORIGINS entry '{}' is not chat:origin", entry.trim()));
+        }
+    }
+    for entry in var("ORDER_SENDERS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if senders::parse_entry(entry).is_none() {
+            problems.push(format!("ORDER_SENDERS entry '{}' is not chat:sender", entry.trim()));
+        }
+    }
+    let mut strategies = Vec::new();
+    match var("ACTIONS").map(|value| actions::Strategy::parse(&value)) {
+        Some(Ok(strategy)) => strategies.push(strategy),
+        Some(Err(e)) => problems.push(format!("ACTIONS: {}", e)),
+        None => {}
+    }
+    for entry in var("CHAT_ACTIONS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        match actions::parse_entry(entry) {
+            Some((chat_id, _)) if !allowed_chat_ids.contains(&chat_id) => {
+                problems.push(format!("CHAT_ACTIONS: chat {} is not in ALLOWED_CHAT_IDS", chat_id))
+            }
+            Some((_, strategy)) => strategies.push(strategy),
+            None => problems.push(format!("CHAT_ACTIONS entry '{}' is not chat:action+action", entry.trim())),
+        }
+    }
+    if let Some(Err(e)) = var("REPLY_TEXT").map(|text| actions::reply_template(&text)) {
+        problems.push(format!("REPLY_TEXT: {}", e));
+    }
+    if strategies.iter().any(|strategy| strategy.contains(actions::Action::Forward)) {
+        if let Some(chat) = problems.required("FORWARD_TO_CHAT_ID", var("FORWARD_TO_CHAT_ID")) {
+            if chat.trim().parse::<i64>().is_err() {
+                problems.push(format!("FORWARD_TO_CHAT_ID '{}' is not a chat ID", chat.trim()));
+            }
+        }
+    }
+    problems.ids("HOT_CHAT_IDS");
+    problems.ids("OWNER_USER_IDS");
+    if let Some(Err(e)) = var("CHAT_PRIORITIES").map(|priorities| crate::routing::parse(&priorities)) {
+        problems.push(format!("CHAT_PRIORITIES: {}", e));
+    }
+    problems.number::<u64>("MAX_QUEUE_WAIT_MS", 1..);
+    problems.number::<usize>("PIPELINE_WORKERS", 0..=64);
+    problems.number::<u64>("PROCESSING_DEADLINE_MS", 0..);
+    problems.one_of("DEADLINE_ACTION", &["skip", "react"]);
+    if var("BOT_API_CHAT_IDS").is_some_and(|chats| chats.trim() != "all") {
+        problems.ids("BOT_API_CHAT_IDS");
+    }
+
+    // Filters
+    problems.number::<i32>("MIN_AMOUNT", 0..);
+    for entry in var("BANK_MIN_AMOUNTS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if entry.split_once(':').is_none_or(|(bank, amount)| bank.trim().is_empty() || amount.trim().parse::<u32>().is_err()) {
+            problems.push(format!("BANK_MIN_AMOUNTS entry '{}' is not bank:amount", entry.trim()));
+        }
+    }
+    problems.number::<f64>("MIN_PROFIT", 0.0..);
+    problems.number::<f64>("COMMISSION_PERCENT", 0.0..=100.0);
+    for entry in var("BANK_COMMISSIONS").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        let percent = |pct: &str| pct.trim().replace(',', ".").parse::<f64>().is_ok_and(|pct| (0.0..=100.0).contains(&pct));
+        if entry.split_once(':').is_none_or(|(bank, pct)| bank.trim().is_empty() || !percent(pct)) {
+            problems.push(format!("BANK_COMMISSIONS entry '{}' is not bank:percent", entry.trim()));
+        }
+    }
+    problems.number::<f64>("MIN_SCORE", ..);
+    problems.number::<f64>("SCORE_PER_THOUSAND", ..);
+    problems.number::<usize>("REACTION_BUDGET", 0..);
+    problems.number::<i64>("WORKING_BALANCE", 0..);
+    problems.number::<i64>("BALANCE_LEFT", 0..);
+    if let Some(reset) = var("BALANCE_RESET").filter(|reset| balance::parse_time(reset).is_none()) {
+        problems.push(format!("BALANCE_RESET '{}' is not a time like 09:00", reset));
+    }
+    let weight = |weight: &str| weight.trim().replace(',', ".").parse::<f64>().is_ok();
+    let mut weights = |name: &str, format: &str, key_valid: &dyn Fn(&str) -> bool| {
+        for (key, value) in score_entries(name) {
+            if !key_valid(&key) || !weight(&value) {
+                problems.push(format!("{} entry '{}:{}' is not {}", name, key, value, format));
+            }
+        }
+    };
+    weights("SCORE_BANKS", "bank:weight", &|bank| !bank.is_empty());
+    weights("SCORE_CHATS", "chat:weight", &|chat| chat.parse::<i64>().is_ok());
+    weights("SCORE_HOURS", "from-to:weight with hours 0-23", &|hours| parse_hours(hours).is_some());
+    if let Some(Err(e)) = var("WORKING_HOURS").map(|hours| Schedule::parse(&hours)) {
+        problems.push(format!("WORKING_HOURS: {}", e));
+    }
+    for name in ["WORKING_HOURS_TZ", "TIMEZONE"] {
+        if let Some(Err(e)) = var(name).map(|tz| schedule::parse_offset(&tz)) {
+            problems.push(format!("{}: {}", name, e));
+        }
+    }
+    let single_emoji = |emoji: &str| !emoji.chars().any(|c| c.is_ascii() || c.is_whitespace()) && emoji.chars().count() <= MAX_EMOJI_CHARS;
+    if let Some(emoji) = var("REACTION_EMOJI").filter(|emoji| !single_emoji(emoji.trim())) {
+        problems.push(format!("REACTION_EMOJI '{}' is not a single emoji", emoji.trim()));
+    }
+    match tiers::parse(&var("EMOJI_TIERS").unwrap_or_default()) {
+        Ok(tiers) => {
+            for (amount, emoji) in tiers.iter().filter(|(_, emoji)| !single_emoji(emoji)) {
+                problems.push(format!("EMOJI_TIERS: '{}' for {} is not a single emoji", emoji, amount));
+            }
+        }
+        Err(e) => problems.push(format!("EMOJI_TIERS: {}", e)),
+    }
+    for entry in var("EXCHANGE_RATES").unwrap_or_default().split(',').filter(|entry| !entry.trim().is_empty()) {
+        if entry.split_once(':').is_none_or(|(_, rate)| !rate.trim().parse::<f64>().is_ok_and(|rate| rate > 0.0)) {
+            problems.push(format!("EXCHANGE_RATES entry '{}' is not CURRENCY:rate", entry.trim()));
+        }
+    }
+
+    // Modes
+    problems.one_of("REACTION_FORMAT", &["auto", "typed", "legacy", "both"]);
+    problems.one_of("HISTORY_STORE", &["sqlite", "memory", "postgres"]);
+    problems.one_of("RECORD_UPDATES_SCOPE", &["allowed", "all"]);
+    problems.one_of("LOG_FORMAT", &["text", "json"]);
+    for name in ["RUST_LOG", "LOG_LEVEL"] {
+        if let Some(Err(e)) = var(name).map(|directives| tracing_subscriber::EnvFilter::try_new(directives.trim())) {
+            problems.push(format!("{}: {}", name, e));
+        }
+    }
+    hot_log(&mut problems, var("HOT_LOG_EVERY"), var("HOT_LOG_PER_MINUTE"));
+    problems.one_of("DATABASE_ENCRYPTION", &["auto", "off"]);
+    problems.one_of("DATABASE_KEY_STORE", &["file", "keyring"]);
+    if !cfg!(feature = "keyring") && var("DATABASE_KEY_STORE").is_some_and(|store| store.trim().eq_ignore_ascii_case("keyring")) {
+        problems.push("DATABASE_KEY_STORE=keyring needs a build with the keyring feature");
+    }
+    problems.one_of("METRICS_EXPORTER", &["statsd", "dogstatsd"]);
+    if let Some(addr) = var("STATSD_ADDR").filter(|addr| std::net::ToSocketAddrs::to_socket_addrs(addr.trim()).is_err()) {
+        problems.push(format!("STATSD_ADDR '{}' is not a host:port", addr));
+    }
+    if let Some(addr) = var("HEALTH_ADDR").filter(|addr| std::net::ToSocketAddrs::to_socket_addrs(addr.trim()).is_err()) {
+        problems.push(format!("HEALTH_ADDR '{}' is not a host:port", addr));
+    }
+    for flag in ["BACKFILL_REACT", "MARK_AS_READ", "KEEP_ONLINE", "PANIC"] {
+        problems.one_of(flag, &["true", "false", "1", "0"]);
+    }
+    if let Some(Err(e)) = var("PRICE_PATTERNS").map(|names| PricePatterns::parse(&names)) {
+        problems.push(format!("PRICE_PATTERNS: {}", e));
+    }
+    match var("ORDER_LANGUAGES").map(|languages| Languages::parse(&languages)) {
+        Some(Err(e)) => problems.push(format!("ORDER_LANGUAGES: {}", e)),
+        Some(Ok(languages)) if crate::rates::price_patterns().only(languages).names().is_empty() => {
+            problems.push(format!("ORDER_LANGUAGES: none of the PRICE_PATTERNS read {}", languages.names().join(" or ")))
+        }
+        _ => {}
+    }
+    // Loading is the only way to tell a plugin is usable
+    if let Err(e) = plugins::load() {
+        problems.push(format!("PLUGINS: {}", e));
+    }
+    for hook in ["PRE_REACTION_HOOK", "POST_REACTION_HOOK"] {
+        if cfg!(not(feature = "hooks-http")) && var(hook).is_some_and(|target| target.starts_with("http://") || target.starts_with("https://")) {
+            problems.push(format!("{}: URL hooks need a build with the hooks-http feature", hook));
+        }
+    }
+    problems.number::<u64>("PRE_REACTION_HOOK_TIMEOUT_MS", 1..);
+    problems.number::<u64>("POST_REACTION_HOOK_TIMEOUT_MS", 1..);
+    problems.one_of("PRE_REACTION_HOOK_ON_FAILURE", &["react", "skip"]);
+    if let Err(e) = wasm::load() {
+        problems.push(format!("WASM_FILTERS: {}", e));
+    }
+    problems.number::<u64>("WASM_FUEL", 1..);
+    problems.number::<usize>("WASM_MEMORY_MB", 1..);
+    let ocr_chat_ids = problems.ids("OCR_CHAT_IDS");
+    if !ocr_chat_ids.is_empty() && cfg!(not(feature = "ocr")) {
+        problems.push("OCR_CHAT_IDS is set but this build has no ocr feature");
+    }
+    for chat_id in ocr_chat_ids.iter().filter(|chat_id| !allowed_chat_ids.contains(chat_id)) {
+        problems.push(format!("OCR_CHAT_IDS: chat {} is not in ALLOWED_CHAT_IDS", chat_id));
+    }
+    match var("IGNORE_CONTENT_TYPES").map(|types| content::IgnoredContent::parse(&types)) {
+        Some(Err(e)) => problems.push(format!("IGNORE_CONTENT_TYPES: {}", e)),
+        Some(Ok(ignored)) if ignored.ignores("messagePhoto") && !ocr_chat_ids.is_empty() => {
+            problems.push("IGNORE_CONTENT_TYPES ignores photos, which OCR_CHAT_IDS are there to read")
+        }
+        _ => {}
+    }
+    if let Some(Err(e)) = var("SCAM_CHECKS").map(|checks| scam::ScamChecks::parse(&checks, 0.0)) {
+        problems.push(format!("SCAM_CHECKS: {}", e));
+    }
+    problems.one_of("SCAM_ACTION", &["skip", "notify"]);
+    problems.number::<f64>("SCAM_MAX_COMMISSION", 0.0..=100.0);
+    if let Err(e) = lifecycle::parse_patterns(&var("ORDER_STATE_PATTERNS").unwrap_or_default()) {
+        problems.push(format!("ORDER_STATE_PATTERNS: {}", e));
+    }
+    for source in var("EXCHANGE_RATES_SOURCE").unwrap_or_default().split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if !["static", "cbr", "binance"].contains(&source.to_lowercase().as_str()) {
+            problems.push(format!("EXCHANGE_RATES_SOURCE '{}' is not static, cbr or binance", source));
+        }
+    }
+
+    // Timings and limits
+    problems.number::<u64>("RACE_WINDOW_SECS", 1..);
+    problems.number::<u64>("ORDER_WATCH_MINUTES", 0..);
+    problems.number::<i64>("DECISION_TRACE_DAYS", 0..);
+    problems.number::<usize>("MAX_IN_FLIGHT_PER_CHAT", 0..);
+    if let Some(jitter) = var("REACTION_JITTER_MS").filter(|jitter| jitter::parse_range(jitter).is_none()) {
+        problems.push(format!("REACTION_JITTER_MS '{}' is not a range in milliseconds like 50-300", jitter));
+    }
+    problems.number::<u64>("ONLINE_REFRESH_SECS", 0..);
+    problems.number::<u64>("UPDATE_STALL_SECS", 0..);
+    problems.number::<i64>("DEDUP_TTL_SECS", 0..);
+    problems.number::<u64>("REACTION_CONFIRM_TIMEOUT_MS", 1..);
+    problems.number::<u64>("RECEIVE_TIMEOUT_MS", 1..);
+    problems.number::<u64>("IDLE_RECEIVE_TIMEOUT_MS", 1..);
+    problems.number::<u64>("IDLE_AFTER_SECS", 1..);
+    problems.number::<u64>("AUTH_TIMEOUT_SECS", 1..);
+    problems.number::<u64>("AUTH_PROMPT_TIMEOUT_SECS", 0..);
+    problems.number::<i64>("PENDING_REACTIONS_MAX_AGE_SECS", 0..);
+    problems.number::<u8>("REACTION_RESENDS", 0..);
+    problems.number::<u64>("MAX_MESSAGE_AGE_SECS", 0..);
+    problems.number::<u64>("LATENCY_REPORT_SECS", 0..);
+    problems.number::<u64>("EXCHANGE_RATES_TTL_SECS", 1..);
+    problems.number::<u64>("ERROR_RATE_WINDOW_SECS", 1..);
+    problems.number::<f64>("ERROR_RATE_THRESHOLD", 0.0..=1.0);
+    problems.number::<usize>("ERROR_RATE_MIN_SAMPLES", 1..);
+    problems.number::<u64>("DISK_USAGE_LIMIT_MB", 0..);
+    problems.number::<u64>("DISK_MIN_FREE_MB", 0..);
+    problems.number::<u64>("DISK_CHECK_INTERVAL_SECS", 1..);
+    problems.number::<u32>("BACKFILL_LIMIT", 0..);
+    problems.number::<i64>("BACKFILL_MAX_AGE_SECS", 0..);
+    problems.number::<i64>("TDLIB_LOG_MAX_BYTES", 1..);
+    problems.number::<i32>("TDLIB_LOG_VERBOSITY", 0..=1024);
+    problems.number::<usize>("RECEIVE_THREAD_CPU", 0..);
+    problems.number::<usize>("SENDER_THREAD_CPU", 0..);
+
+    // Paths
+    problems.existing_path("TDLIB_PATH");
+    problems.writable_dir("TDLIB_DATA_DIR", Some(DEFAULT_TDLIB_DATA_DIR));
+    problems.writable_dir("STANDBY_TDLIB_DATA_DIR", None);
+    if var("HISTORY_STORE").is_none_or(|store| store.eq_ignore_ascii_case("sqlite")) {
+        // An empty HISTORY_DB turns the history off
+        if std::env::var("HISTORY_DB").as_deref() != Ok("") {
+            problems.writable_file("HISTORY_DB", Some(history::DEFAULT_HISTORY_DB));
+        }
+    }
+    for file in ["LOG_FILE", "HEARTBEAT_FILE", "RECORD_UPDATES", "TDLIB_LOG_FILE", "PID_FILE", "PANIC_FILE", "CONTROL_SOCKET"] {
+        problems.writable_file(file, None);
+    }
+    // The longest path a Unix domain socket can have (sun_path)
+    if let Some(socket) = var("CONTROL_SOCKET").filter(|socket| socket.len() > 100) {
+        problems.push(format!("CONTROL_SOCKET {} is too long for a socket path (100 bytes at most)", socket));
+    }
+
+    problems
+}
@@ -0,0 +1,144 @@
+use std::sync::OnceLock;
+use botdg_core::Order;
+
+// Fuel (roughly, WebAssembly instructions) a filter may burn on one order
+#[cfg(feature = "wasm")]
+const DEFAULT_FUEL: u64 = 10_000_000;
+#[cfg(feature = "wasm")]
+const DEFAULT_MEMORY_MB: usize = 16;
+
+// WebAssembly modules listed in WASM_FILTERS (comma separated .wasm paths), each deciding on the orders
+// that passed every filter, as native plugins do (see plugins.rs) but sandboxed: a module gets no
+// imports at all, at most WASM_MEMORY_MB (16) of memory and WASM_FUEL (10 000 000) units of fuel per
+// order. It exports its `memory`, `alloc(len) -> ptr` for the bot to write the order into, and
+// `decide(ptr, len) -> i32` taking the order as JSON (see order_json) and returning 0 to react.
+// Anything else, or a trap (out of fuel or memory included), skips the order as `plugin`. Needs the
+// "wasm" feature
+pub struct WasmFilters {
+    #[cfg(feature = "wasm")]
+    modules: Vec<host::Filter>,
+}
+
+static LOADED: OnceLock<Result<WasmFilters, String>> = OnceLock::new();
+static NONE: WasmFilters = WasmFilters {
+    #[cfg(feature = "wasm")]
+    modules: Vec::new(),
+};
+
+// The WASM_FILTERS modules, compiled on first use; an error names the one that couldn't be
+pub fn load() -> Result<&'static WasmFilters, String> {
+    LOADED.get_or_init(WasmFilters::from_env).as_ref().map_err(Clone::clone)
+}
+
+// The filters, or none if they failed to load (run() refuses to start then, see load)
+pub fn loaded() -> &'static WasmFilters {
+    load().unwrap_or(&NONE)
+}
+
+impl WasmFilters {
+    fn from_env() -> Result<Self, String> {
+        let paths = std::env::var("WASM_FILTERS").unwrap_or_default();
+        let paths: Vec<&str> = paths.split(',').map(str::trim).filter(|path| !path.is_empty()).collect();
+
+        #[cfg(feature = "wasm")]
+        {
+            let fuel = std::env::var("WASM_FUEL").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_FUEL);
+            let memory_mb = std::env::var("WASM_MEMORY_MB").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_MEMORY_MB);
+            let modules = paths
+                .iter()
+                .map(|path| host::Filter::load(path, fuel, memory_mb * 1024 * 1024).map_err(|e| format!("WASM filter {}: {}", path, e)))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(Self { modules })
+        }
+
+        // Ignoring filters would react to orders they're there to turn down
+        #[cfg(not(feature = "wasm"))]
+        match paths.is_empty() {
+            true => Ok(Self {}),
+            false => Err("WASM_FILTERS is set but this build has no wasm feature".to_string()),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        #[cfg(feature = "wasm")]
+        return self.modules.iter().map(|module| module.name.as_str()).collect();
+        #[cfg(not(feature = "wasm"))]
+        Vec::new()
+    }
+
+    // The first module that turns an order down
+    #[cfg_attr(not(feature = "wasm"), allow(unused_variables))]
+    pub fn rejecting(&self, chat_id: i64, text: &str, order: &Order) -> Option<&str> {
+        #[cfg(feature = "wasm")]
+        {
+            if self.modules.is_empty() {
+                return None;
+            }
+            let input = crate::order_json(chat_id, None, text, order).to_string();
+            self.modules.iter().find(|module| !module.decide(&input)).map(|module| module.name.as_str())
+        }
+        #[cfg(not(feature = "wasm"))]
+        None
+    }
+}
+
+#[cfg(feature = "wasm")]
+mod host {
+    use std::sync::Mutex;
+    use tracing::{info, warn};
+    use wasmtime::{Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+    // One module, instantiated once; orders are decided one at a time
+    pub struct Filter {
+        pub name: String,
+        fuel: u64,
+        instance: Mutex<Guest>,
+    }
+
+    struct Guest {
+        store: Store<StoreLimits>,
+        memory: Memory,
+        alloc: TypedFunc<i32, i32>,
+        decide: TypedFunc<(i32, i32), i32>,
+    }
+
+    impl Filter {
+        pub fn load(path: &str, fuel: u64, memory_bytes: usize) -> wasmtime::Result<Self> {
+            let engine = Engine::new(Config::new().consume_fuel(true))?;
+            let module = Module::from_file(&engine, path)?;
+            let mut store = Store::new(&engine, StoreLimitsBuilder::new().memory_size(memory_bytes).instances(1).build());
+            store.limiter(|limits| limits);
+            store.set_fuel(fuel)?;
+            // No imports: a module that needs any (WASI included) fails to instantiate
+            let instance: Instance = Linker::new(&engine).instantiate(&mut store, &module)?;
+            let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| wasmtime::Error::msg("no exported memory"))?;
+            let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+            let decide = instance.get_typed_func::<(i32, i32), i32>(&mut store, "decide")?;
+            let name = std::path::Path::new(path).file_stem().map_or(path.to_string(), |stem| stem.to_string_lossy().into_owned());
+            info!("Loaded WASM filter {} from {}", name, path);
+            Ok(Self { name, fuel, instance: Mutex::new(Guest { store, memory, alloc, decide }) })
+        }
+
+        // Whether the module lets the order through
+        pub fn decide(&self, input: &str) -> bool {
+            let mut guest = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+            match guest.call(input, self.fuel) {
+                Ok(decision) => decision == 0,
+                Err(e) => {
+                    warn!("WASM filter {} failed, skipping the order: {}", self.name, e);
+                    false
+                }
+            }
+        }
+    }
+
+    impl Guest {
+        fn call(&mut self, input: &str, fuel: u64) -> wasmtime::Result<i32> {
+            self.store.set_fuel(fuel)?;
+            let len = i32::try_from(input.len())?;
+            let ptr = self.alloc.call(&mut self.store, len)?;
+            self.memory.write(&mut self.store, usize::try_from(ptr)?, input.as_bytes())?;
+            self.decide.call(&mut self.store, (ptr, len))
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+// The owner's `/watch on` and `/watch off` (see owner.rs) add the chat they're sent in to the
+// watched chats or take it out, without looking up its ID. The changes are kept in
+// WATCHED_CHATS_FILE ("watched_chats" in TDLIB_DATA_DIR by default; empty to keep them only until
+// the bot stops) as chats added to and removed from ALLOWED_CHAT_IDS, so later edits of the setting
+// still count. The manager, which keeps its own list of each instance's chats, turns the file off
+pub struct WatchedChats {
+    file: Option<PathBuf>,
+    added: BTreeSet<i64>,
+    removed: BTreeSet<i64>,
+}
+
+impl WatchedChats {
+    pub fn from_env() -> Self {
+        let file = match std::env::var("WATCHED_CHATS_FILE") {
+            Ok(path) if path.trim().is_empty() => None,
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(Path::new(&crate::tdlib_data_dir()).join("watched_chats")),
+        };
+        let saved = file.as_ref().and_then(|file| {
+            let contents = std::fs::read_to_string(file).ok()?;
+            serde_json::from_str::<Value>(&contents).map_err(|e| warn!("Ignoring {}: {}", file.display(), e)).ok()
+        });
+        let saved = saved.unwrap_or_default();
+        let ids = |key: &str| saved[key].as_array().into_iter().flatten().filter_map(Value::as_i64).collect();
+        Self { added: ids("added"), removed: ids("removed"), file }
+    }
+
+    // ALLOWED_CHAT_IDS with the owner's changes
+    pub fn apply(&self, chats: &mut HashSet<i64>) {
+        if !self.added.is_empty() || !self.removed.is_empty() {
+            info!("Watching {:?} besides ALLOWED_CHAT_IDS, and not {:?} (/watch)", self.added, self.removed);
+        }
+        chats.extend(&self.added);
+        chats.retain(|chat| !self.removed.contains(chat));
+    }
+
+    pub fn set(&mut self, chat_id: i64, watching: bool) {
+        if watching {
+            self.removed.remove(&chat_id);
+            self.added.insert(chat_id);
+        } else {
+            self.added.remove(&chat_id);
+            self.removed.insert(chat_id);
+        }
+        let Some(file) = &self.file else { return };
+        let contents = json!({ "added": self.added, "removed": self.removed }).to_string();
+        let saved = file.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(file, contents));
+        if let Err(e) = saved {
+            warn!("Failed to save the watched chats to {}: {}", file.display(), e);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use botdg_core::score;
+use serde_json::{json, Value};
+
+use crate::{formatted::MessageText, history::DecisionTrace, names::Names, rates::Prices, stats::SkipReason, FilterSettings};
+
+// TDLib message IDs are the number in the message's link (t.me/c/1234567890/42) shifted by this
+const SERVER_ID_SHIFT: u32 = 20;
+
+// The decision about a message as JSON: react or skip and why, the order as parsed, and every
+// filter rule that is on with its input, what it wanted and whether it passed. Stored with the
+// history for /why
+pub fn trace(filter_settings: &FilterSettings, chat_id: i64, text: &MessageText, prices: &Prices, decision: Result<(), SkipReason>) -> String {
+    let order = prices.order(text).unwrap_or_default();
+    let mut checks: Vec<Value> = filter_settings
+        .filter
+        .explain(&order)
+        .into_iter()
+        .map(|check| json!({ "rule": check.rule, "input": check.input, "wanted": check.wanted, "passed": check.passed }))
+        .collect();
+    if let Some(min_score) = filter_settings.min_score {
+        let score = filter_settings.score(chat_id, &order);
+        checks.push(json!({
+            "rule": "score",
+            "input": format!("{:.1}", score),
+            "wanted": format!(">= {}", min_score),
+            "passed": score::meets_min(score, min_score)
+        }));
+    }
+
+    json!({
+        "decision": if decision.is_ok() { "react" } else { "skip" },
+        "skip_reason": decision.err().map(SkipReason::name),
+        "order": {
+            "amount": order.amount.as_ref().map(|amount| json!({ "value": amount.value, "currency": amount.currency })),
+            "rubles": order.rubles,
+            "bank": order.bank,
+            "requisite": order.requisite,
+            "commission_pct": order.commission
+        },
+        "checks": checks
+    })
+    .to_string()
+}
+
+// `/why 42` takes the number from the message's link as well as TDLib's message ID
+pub fn message_id(args: &str) -> Option<i64> {
+    let id = args.trim().parse::<i64>().ok().filter(|&id| id > 0)?;
+    Some(if id < 1 << SERVER_ID_SHIFT { id << SERVER_ID_SHIFT } else { id })
+}
+
+pub fn format(message_id: i64, traces: &[DecisionTrace], names: &Names) -> String {
+    if traces.is_empty() {
+        return format!(
+            "🤷 No decision recorded for message {}. Only messages in watched chats are traced, and kept for DECISION_TRACE_DAYS.",
+            message_id >> SERVER_ID_SHIFT
+        );
+    }
+
+    let mut text = String::new();
+    for trace in traces {
+        let decided = botdg_config::timezone::from_timestamp(trace.decided_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let trace_json: Value = serde_json::from_str(&trace.trace).unwrap_or_default();
+        let decision = match trace_json["skip_reason"].as_str() {
+            Some(reason) => format!("❌ skipped: {}", reason),
+            None => "✅ reacted".to_string(),
+        };
+        text.push_str(&format!("💬 Chat {}, message {} at {}\n{}\n", names.label(trace.chat_id), trace.message_id >> SERVER_ID_SHIFT, decided, decision));
+        for check in trace_json["checks"].as_array().into_iter().flatten() {
+            text.push_str(&format!(
+                "• {}: {} (wanted {}) {}\n",
+                check["rule"].as_str().unwrap_or_default(),
+                check["input"].as_str().unwrap_or("none"),
+                check["wanted"].as_str().unwrap_or_default(),
+                if check["passed"].as_bool() == Some(true) { "✅" } else { "❌" }
+            ));
+        }
+        text.push('\n');
+    }
+    text.trim_end().to_string()
+}